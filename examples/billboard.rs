@@ -130,10 +130,9 @@ fn setup(
                 texture_slot: texture_slot,
                 sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
             })
-            .render(OrientModifier {
-                mode: OrientMode::FaceCameraPosition,
-                rotation: Some(rotation_attr),
-            })
+            .render(
+                OrientModifier::new(OrientMode::FaceCameraPosition).with_rotation(rotation_attr),
+            )
             .render(SizeOverLifetimeModifier {
                 gradient: Gradient::constant([0.2; 2].into()),
                 screen_space_size: false,
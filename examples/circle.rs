@@ -123,7 +123,7 @@ fn setup(
                 texture_slot: texture_slot,
                 sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
             })
-            .render(FlipbookModifier { sprite_grid_size })
+            .render(FlipbookModifier::new(sprite_grid_size))
             .render(ColorOverLifetimeModifier { gradient })
             .render(SizeOverLifetimeModifier {
                 gradient: Gradient::constant([0.5; 2].into()),
@@ -0,0 +1,108 @@
+//! Test that rendering an effect thumbnail produces an image of the requested
+//! size.
+
+use std::time::Duration;
+
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping, log::LogPlugin, prelude::*, time::TimeUpdateStrategy,
+    window::ExitCondition, winit::WinitPlugin,
+};
+use bevy_hanabi::prelude::*;
+
+const THUMBNAIL_SIZE: UVec2 = UVec2::new(64, 32);
+
+#[derive(Default, Resource)]
+struct Thumbnails(Vec<Image>);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::default();
+    app.insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            1. / 60.,
+        )))
+        .add_plugins(
+            DefaultPlugins
+                .set(LogPlugin {
+                    level: bevy::log::Level::INFO,
+                    filter: "bevy_hanabi=debug".to_string(),
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                // Render offscreen only; don't require a display server
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins((HanabiPlugin, EffectThumbnailPlugin))
+        .init_resource::<Thumbnails>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, collect);
+
+    app.finish();
+    app.cleanup();
+    for _ in 0..60 {
+        app.update();
+        if !app.world().resource::<Thumbnails>().0.is_empty() {
+            break;
+        }
+    }
+
+    let thumbnails = &app.world().resource::<Thumbnails>().0;
+    assert_eq!(thumbnails.len(), 1, "Thumbnail was not captured.");
+    let image = &thumbnails[0];
+    assert_eq!(image.size(), THUMBNAIL_SIZE);
+    assert_eq!(
+        image.data.len(),
+        (THUMBNAIL_SIZE.x * THUMBNAIL_SIZE.y * 4) as usize
+    );
+    // The image is cleared to white, so can't be all zeros
+    assert!(image.data.iter().any(|&b| b != 0));
+    info!("SUCCESS!");
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    let thumbnail =
+        EffectThumbnail::new(THUMBNAIL_SIZE, Duration::from_secs_f32(0.25), &mut images);
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                target: thumbnail.render_target(),
+                ..default()
+            },
+            tonemapping: Tonemapping::None,
+            transform: Transform::from_xyz(0., 0., 5.).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        thumbnail,
+    ));
+
+    let writer = ExprWriter::new();
+    let init_pos = SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, writer.lit(1.).expr());
+    let effect = EffectAsset::new(32, Spawner::rate(30.0.into()), writer.finish())
+        .init(init_pos)
+        .init(init_lifetime)
+        .render(SetColorModifier {
+            color: Vec4::new(1., 0., 0., 1.).into(),
+        });
+    commands.spawn(ParticleEffectBundle::new(effects.add(effect)));
+}
+
+fn collect(
+    mut commands: Commands,
+    mut events: EventReader<EffectThumbnailReady>,
+    mut thumbnails: ResMut<Thumbnails>,
+) {
+    for event in events.read() {
+        thumbnails.0.push(event.image.clone());
+        commands.entity(event.entity).despawn();
+    }
+}
@@ -2,7 +2,9 @@ use std::ops::Deref;
 
 use bevy::{
     asset::Asset,
+    math::Vec3,
     reflect::Reflect,
+    render::primitives::Aabb,
     utils::{default, HashSet},
 };
 
@@ -14,10 +16,13 @@ use thiserror::Error;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    modifier::{Modifier, RenderModifier},
+    modifier::{
+        InitModifier, Modifier, RenderContext, RenderModifier, ShaderWriter, UpdateModifier,
+    },
     spawn::{Cloner, Initializer},
-    Attribute, CpuValue, ExprHandle, GroupedModifier, ModifierContext, Module, ParticleGroupSet,
-    ParticleLayout, Property, PropertyLayout, SimulationSpace, Spawner, TextureLayout,
+    Attribute, CompiledShaders, CpuValue, EffectShaderSource, ExprError, ExprHandle, GraphStats,
+    GroupedModifier, ModifierContext, Module, ParticleGroupSet, ParticleLayout, Property,
+    PropertyLayout, ShaderGenerateError, SimulationSpace, Spawner, TextureLayout, Value,
 };
 
 /// Type of motion integration applied to the particles of a system.
@@ -45,6 +50,55 @@ pub enum MotionIntegration {
     PostUpdate,
 }
 
+/// Action taken on particles leaving the [`PositionBounds`] of an effect.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum PositionBoundsMode {
+    /// Clamp the [`Attribute::POSITION`] of the particle to the bounds. This is
+    /// the default.
+    ///
+    /// [`Attribute::POSITION`]: crate::Attribute::POSITION
+    #[default]
+    Clamp,
+    /// Kill the particle.
+    Kill,
+}
+
+/// Axis-aligned bounds applied to the position of all particles of an effect.
+///
+/// See [`EffectAsset::with_position_bounds()`] for details.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct PositionBounds {
+    /// Minimum corner of the bounds, in simulation space.
+    pub min: Vec3,
+    /// Maximum corner of the bounds, in simulation space.
+    pub max: Vec3,
+    /// Action taken on particles outside the bounds.
+    pub mode: PositionBoundsMode,
+}
+
+impl PositionBounds {
+    /// Create new position bounds from an AABB and a mode.
+    pub fn new(aabb: Aabb, mode: PositionBoundsMode) -> Self {
+        Self {
+            min: aabb.min().into(),
+            max: aabb.max().into(),
+            mode,
+        }
+    }
+
+    /// Set the action taken on particles outside the bounds.
+    pub fn with_mode(mut self, mode: PositionBoundsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl From<Aabb> for PositionBounds {
+    fn from(aabb: Aabb) -> Self {
+        Self::new(aabb, PositionBoundsMode::Clamp)
+    }
+}
+
 /// Simulation condition for an effect.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum SimulationCondition {
@@ -94,6 +148,77 @@ pub enum SimulationCondition {
     Always,
 }
 
+/// Pseudo-random number generator (PRNG) algorithm of an effect.
+///
+/// All random values used by the shaders of an effect, like the ones produced
+/// by [`ExprWriter::rand()`], are derived from a hash function applied
+/// repeatedly to a per-particle seed. This selects that hash function, which
+/// allows trading some statistical quality for speed, or the other way around.
+/// None of those algorithms is cryptographically secure.
+///
+/// [`ExprWriter::rand()`]: crate::graph::ExprWriter::rand
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum RngAlgorithm {
+    /// PCG hash.
+    ///
+    /// A hash derived from the PCG family of generators, which has a good
+    /// statistical quality for a reasonable cost. This is the default.
+    #[default]
+    Pcg,
+
+    /// Xorshift hash.
+    ///
+    /// A 32-bit xorshift generator. This is the fastest option, but has a
+    /// lower statistical quality than [`RngAlgorithm::Pcg`].
+    Xorshift,
+
+    /// Wang hash.
+    ///
+    /// Thomas Wang's 32-bit integer hash. It's slightly cheaper than
+    /// [`RngAlgorithm::Pcg`], with a lower statistical quality.
+    Wang,
+}
+
+impl RngAlgorithm {
+    /// Get the name of the shader define selecting this algorithm, if any.
+    ///
+    /// The default [`RngAlgorithm::Pcg`] algorithm doesn't need any define.
+    pub(crate) fn shader_define(&self) -> Option<&'static str> {
+        match *self {
+            RngAlgorithm::Pcg => None,
+            RngAlgorithm::Xorshift => Some("RNG_XORSHIFT"),
+            RngAlgorithm::Wang => Some("RNG_WANG"),
+        }
+    }
+}
+
+/// Render layer of an effect.
+///
+/// An effect renders its particles once per render layer, each layer with its
+/// own set of render modifiers. All layers draw the same simulated particles,
+/// so this allows rendering the particles several times with different
+/// materials, for example a sharp core and a wide additive glow, without
+/// simulating them twice. Effects currently support up to two render layers.
+///
+/// See [`EffectAsset::render_layer()`] for details.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum EffectRenderLayer {
+    /// Primary render layer.
+    ///
+    /// All effects render this layer, which contains the render modifiers added
+    /// with [`EffectAsset::render()`] and the other related methods. This is the
+    /// default.
+    #[default]
+    Primary,
+
+    /// Secondary render layer.
+    ///
+    /// The secondary layer is only rendered if it contains at least one render
+    /// modifier.
+    Secondary,
+}
+
 /// Alpha mode for rendering an effect.
 ///
 /// The alpha mode determines how the alpha value of a particle is used to
@@ -199,6 +324,25 @@ pub enum AlphaMode {
     ///
     /// [`AlphaMask3d`]: bevy::core_pipeline::core_3d::AlphaMask3d
     Mask(ExprHandle),
+
+    /// Render the effect with alpha-to-coverage.
+    ///
+    /// With this mode, the alpha value of each particle fragment is converted
+    /// into a sample coverage mask by the GPU, instead of being blended. This
+    /// produces smooth anti-aliased edges for cutout particles like foliage or
+    /// hair, without the sorting issues of alpha blending, and writes to the
+    /// depth buffer.
+    ///
+    /// Alpha-to-coverage requires a multisampled render target. When the view
+    /// is not multisampled ([`Msaa::Off`]), this mode falls back to
+    /// [`AlphaMode::Blend`].
+    ///
+    /// For 3D views, effects with this mode are rendered during the
+    /// [`Transparent3d`] render phase.
+    ///
+    /// [`Msaa::Off`]: bevy::render::view::Msaa::Off
+    /// [`Transparent3d`]: bevy::core_pipeline::core_3d::Transparent3d
+    AlphaToCoverage,
 }
 
 /// Asset describing a visual effect.
@@ -241,6 +385,14 @@ pub struct EffectAsset {
     pub z_layer_2d: f32,
     /// Particle simulation space.
     pub simulation_space: SimulationSpace,
+    /// Fraction of the emitter motion followed by the particles of an effect
+    /// simulated in global space.
+    ///
+    /// See [`with_simulation_space_blend()`] for details.
+    ///
+    /// [`with_simulation_space_blend()`]: crate::EffectAsset::with_simulation_space_blend
+    #[serde(default)]
+    pub simulation_space_blend: f32,
     /// Condition under which the effect is simulated.
     pub simulation_condition: SimulationCondition,
     /// Init modifier defining the effect.
@@ -255,17 +407,103 @@ pub struct EffectAsset {
     #[reflect(ignore)]
     // TODO - Can't manage to implement FromReflect for BoxedModifier in a nice way yet
     render_modifiers: Vec<GroupedModifier>,
+    /// Render modifiers of the secondary render layer of the effect.
+    #[reflect(ignore)]
+    // TODO - Can't manage to implement FromReflect for BoxedModifier in a nice way yet
+    #[serde(default)]
+    secondary_render_modifiers: Vec<GroupedModifier>,
     /// Type of motion integration applied to the particles of a system.
     pub motion_integration: MotionIntegration,
     /// Expression module for this effect.
     module: Module,
     /// Alpha mode.
     pub alpha_mode: AlphaMode,
+    /// Premultiply the color of the particles by their alpha value.
+    ///
+    /// See [`with_premultiply_alpha()`] for details.
+    ///
+    /// [`with_premultiply_alpha()`]: crate::EffectAsset::with_premultiply_alpha
+    #[serde(default)]
+    pub premultiply_alpha: bool,
     /// Which group is to render as ribbons.
     ///
     /// There can be only one such group, because there's only one set of
     /// next/previous pointers.
     pub ribbon_group: Option<usize>,
+    /// Optional random offset added to the position of spawned particles.
+    ///
+    /// See [`with_position_jitter()`] for details.
+    ///
+    /// Expression type: `Vec3`
+    ///
+    /// [`with_position_jitter()`]: crate::EffectAsset::with_position_jitter
+    pub position_jitter: Option<ExprHandle>,
+    /// Optional grid spacing the position of spawned particles is snapped to.
+    ///
+    /// See [`with_snap_to_grid()`] for details.
+    ///
+    /// [`with_snap_to_grid()`]: crate::EffectAsset::with_snap_to_grid
+    pub snap_to_grid: Option<Vec3>,
+    /// Optional initial age of the spawned particles.
+    ///
+    /// See [`with_initial_age()`] for details.
+    ///
+    /// [`with_initial_age()`]: crate::EffectAsset::with_initial_age
+    pub initial_age: Option<ExprHandle>,
+    /// Attributes packed first in the particle layout.
+    ///
+    /// See [`with_packing_hint()`] for details.
+    ///
+    /// [`with_packing_hint()`]: crate::EffectAsset::with_packing_hint
+    #[serde(default)]
+    pub packing_hint: Vec<Attribute>,
+    /// Optional bounds applied to the position of all particles.
+    ///
+    /// See [`with_position_bounds()`] for details.
+    ///
+    /// [`with_position_bounds()`]: crate::EffectAsset::with_position_bounds
+    pub position_bounds: Option<PositionBounds>,
+    /// Optional upper bound of the delta time used to update the effect.
+    ///
+    /// See [`with_max_delta_time()`] for details.
+    ///
+    /// [`with_max_delta_time()`]: crate::EffectAsset::with_max_delta_time
+    pub max_delta_time: Option<f32>,
+    /// Skip the simulation and rendering of effect instances which are empty.
+    ///
+    /// See [`with_skip_when_empty()`] for details.
+    ///
+    /// [`with_skip_when_empty()`]: crate::EffectAsset::with_skip_when_empty
+    #[serde(default)]
+    pub skip_when_empty: bool,
+    /// Simulate the effect without rendering it.
+    ///
+    /// See [`with_simulation_only()`] for details.
+    ///
+    /// [`with_simulation_only()`]: crate::EffectAsset::with_simulation_only
+    #[serde(default)]
+    pub simulation_only: bool,
+    /// Double-buffer the particles of the effect.
+    ///
+    /// See [`with_double_buffering()`] for details.
+    ///
+    /// [`with_double_buffering()`]: crate::EffectAsset::with_double_buffering
+    #[serde(default)]
+    pub double_buffered: bool,
+    /// Pseudo-random number generator algorithm of the effect.
+    ///
+    /// See [`with_rng_algorithm()`] for details.
+    ///
+    /// [`with_rng_algorithm()`]: crate::EffectAsset::with_rng_algorithm
+    #[serde(default)]
+    pub rng_algorithm: RngAlgorithm,
+    /// Named constants injected into the generated shaders.
+    ///
+    /// See [`with_shader_define()`] for details.
+    ///
+    /// [`with_shader_define()`]: crate::EffectAsset::with_shader_define
+    #[serde(default)]
+    shader_defines: Vec<(String, Value)>,
 }
 
 impl EffectAsset {
@@ -398,12 +636,338 @@ impl EffectAsset {
         self
     }
 
+    /// Set the fraction of the emitter motion followed by the particles.
+    ///
+    /// This blends between the two simulation spaces for an effect simulated in
+    /// [`SimulationSpace::Global`]. Each frame, the update pass moves the
+    /// particles along with the emitter by the given fraction of the motion of
+    /// the emitter since the previous frame, including its rotation and scale.
+    /// With a blend of `0.` (the default) the particles don't follow the
+    /// emitter at all, as for a global space simulation, while with a blend of
+    /// `1.` they follow it entirely, as for a local space simulation.
+    /// Intermediate values make the particles partially follow the emitter, and
+    /// trail behind it.
+    ///
+    /// The motion is linearly interpolated per particle, so a blend other than
+    /// `0.` or `1.` doesn't conserve the distance to the emitter under rotation.
+    /// The velocity of the particles, if any, is blended the same way. Particles
+    /// are moved during the frame they're spawned too. The blend is ignored for
+    /// effects simulated in [`SimulationSpace::Local`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `blend` is not in \[0:1\].
+    pub fn with_simulation_space_blend(mut self, blend: f32) -> Self {
+        assert!(
+            (0. ..=1.).contains(&blend),
+            "Invalid simulation space blend {}, must be in [0:1].",
+            blend
+        );
+        self.simulation_space_blend = blend;
+        self
+    }
+
     /// Set the alpha mode.
     pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
         self.alpha_mode = alpha_mode;
         self
     }
 
+    /// Premultiply the color of the particles by their alpha value.
+    ///
+    /// When enabled, the RGB channels of the color of each particle fragment
+    /// are multiplied by its alpha value after all render modifiers, including
+    /// any texture sampling, and the effect is blended like with
+    /// [`AlphaMode::Premultiply`]. This avoids the dark halos appearing around
+    /// textured particles blended with straight (non-premultiplied) alpha,
+    /// without having to premultiply the textures themselves.
+    ///
+    /// This only applies to the [`AlphaMode::Blend`] alpha mode, and is
+    /// ignored for all other modes. This is disabled by default.
+    pub fn with_premultiply_alpha(mut self, premultiply_alpha: bool) -> Self {
+        self.premultiply_alpha = premultiply_alpha;
+        self
+    }
+
+    /// Set an offset added to the position of each spawned particle.
+    ///
+    /// The offset expression is evaluated once per spawned particle, after all
+    /// init modifiers were applied, and added to the particle position. This
+    /// allows offsetting the emission position independently of the shape
+    /// modifier used to initialize the position, for example to simulate a
+    /// shaky nozzle with a small random offset.
+    ///
+    /// The offset only applies to particles spawned by a [`Spawner`]; cloned
+    /// particles are left untouched.
+    ///
+    /// Expression type: `Vec3`
+    pub fn with_position_jitter(mut self, position_jitter: ExprHandle) -> Self {
+        self.position_jitter = Some(position_jitter);
+        self
+    }
+
+    /// Snap the position of each spawned particle to a grid.
+    ///
+    /// The position is rounded to the nearest multiple of the grid spacing,
+    /// independently on each axis, after all init modifiers and any
+    /// [`position_jitter`] were applied. This is useful for voxel or pixel-art
+    /// effects. A zero spacing on an axis disables the snapping along that
+    /// axis, and the sign of the spacing is ignored.
+    ///
+    /// Like the position jitter, the snapping only applies to particles spawned
+    /// by a [`Spawner`]; cloned particles are left untouched.
+    ///
+    /// [`position_jitter`]: crate::EffectAsset::position_jitter
+    pub fn with_snap_to_grid(mut self, grid: Vec3) -> Self {
+        self.snap_to_grid = Some(grid);
+        self
+    }
+
+    /// Set the initial age of each spawned particle.
+    ///
+    /// By default particles spawn with a zero age, unless an init modifier
+    /// assigns [`Attribute::AGE`]. The initial age expression is evaluated once
+    /// per spawned particle, after all init modifiers were applied, and
+    /// overwrites the age of the particle, so that particles are already
+    /// partway through their lifetime when they first appear. This is useful
+    /// to stagger the lifetimes of particles spawned in a burst, or to prewarm
+    /// an effect. The expression can be random, to give each particle a
+    /// different initial age.
+    ///
+    /// The initial age is clamped to be positive and, if the particles have an
+    /// [`Attribute::LIFETIME`], to not exceed their lifetime. A particle
+    /// spawned with an age equal to its lifetime dies on its first update.
+    ///
+    /// The initial age only applies to particles spawned by a [`Spawner`];
+    /// the age of cloned particles is managed automatically. Setting an
+    /// initial age adds [`Attribute::AGE`] to the particle layout.
+    ///
+    /// Expression type: `f32`
+    pub fn with_initial_age(mut self, initial_age: ExprHandle) -> Self {
+        self.initial_age = Some(initial_age);
+        self
+    }
+
+    /// Hint which particle attributes are accessed together.
+    ///
+    /// The hinted attributes are stored first in the GPU particle struct,
+    /// adjacent to each other in the given order, instead of the default
+    /// order minimizing the padding between attributes. This can improve the
+    /// GPU cache usage for attributes often accessed together, like the
+    /// position and velocity of each particle during the update pass, at the
+    /// expense of some padding. Attributes not used by the effect are ignored.
+    ///
+    /// See [`ParticleLayoutBuilder::pack_first()`] for details.
+    ///
+    /// [`ParticleLayoutBuilder::pack_first()`]: crate::ParticleLayoutBuilder::pack_first
+    pub fn with_packing_hint(mut self, attributes: &[Attribute]) -> Self {
+        self.packing_hint = attributes.to_vec();
+        self
+    }
+
+    /// Set some bounds restricting the position of all particles.
+    ///
+    /// This is a safeguard against runaway particles, for example due to an
+    /// unstable force or a buggy custom expression, whose position would
+    /// otherwise grow to very large values and cause floating-point precision
+    /// artifacts. The bounds are applied each simulation update, after all
+    /// update modifiers and motion integration. By default particles outside
+    /// the bounds are clamped back inside; use
+    /// [`PositionBounds::with_mode()`] to kill them instead.
+    ///
+    /// The bounds are expressed in simulation space, and have no effect on
+    /// effects without [`Attribute::POSITION`]. This is unrelated to any
+    /// modifier confining particles inside a volume, which are part of the
+    /// simulation itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::{prelude::*, render::primitives::Aabb};
+    /// # let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), Module::default());
+    /// let bounds = Aabb::from_min_max(Vec3::splat(-100.), Vec3::splat(100.));
+    /// let asset = asset
+    ///     .with_position_bounds(PositionBounds::from(bounds).with_mode(PositionBoundsMode::Kill));
+    /// ```
+    ///
+    /// [`Attribute::POSITION`]: crate::Attribute::POSITION
+    pub fn with_position_bounds(mut self, position_bounds: impl Into<PositionBounds>) -> Self {
+        self.position_bounds = Some(position_bounds.into());
+        self
+    }
+
+    /// Clamp the delta time used to update the effect.
+    ///
+    /// After a frame hitch, the delta time since the last update can be very
+    /// large, which makes the particles jump by a large distance in a single
+    /// update, and can make forces and other integrated quantities unstable.
+    /// With a maximum delta time, the update pass of this effect, including
+    /// the particle aging, uses `min(delta_time, max_delta_time)` instead of
+    /// the actual delta time, so the effect simply slows down during hitches.
+    ///
+    /// This only affects the `delta_time` of the simulation parameters, as
+    /// read by [`BuiltInOperator::DeltaTime`]. The simulation time, as well as
+    /// the virtual and real delta times, are left untouched. This is not a
+    /// fixed timestep; the delta time still varies from frame to frame below
+    /// the maximum.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_delta_time` is not strictly positive.
+    ///
+    /// [`BuiltInOperator::DeltaTime`]: crate::graph::BuiltInOperator::DeltaTime
+    pub fn with_max_delta_time(mut self, max_delta_time: f32) -> Self {
+        assert!(
+            max_delta_time > 0.,
+            "Invalid max delta time {}, must be strictly positive.",
+            max_delta_time
+        );
+        self.max_delta_time = Some(max_delta_time);
+        self
+    }
+
+    /// Skip the simulation and rendering of effect instances which are empty.
+    ///
+    /// An effect instance whose spawners finished spawning still runs its
+    /// compute passes and draw calls each frame, even once all its particles
+    /// died. When enabled, an effect instance with no particle alive in any of
+    /// its groups, and none of whose initializers spawns this frame, is skipped
+    /// entirely, without any compute dispatch nor draw call. The instance wakes
+    /// up as soon as one of its initializers spawns again, for example after
+    /// [`EffectInitializers::reset()`] or [`EffectInitializers::set_active()`],
+    /// or because a burst spawner reached its next period.
+    ///
+    /// The number of particles alive is read back from GPU each frame, and
+    /// reaches the CPU a few frames later. An instance is only considered empty
+    /// once a readback simulated after its last spawn reports no particle
+    /// alive, so particles are never frozen while still alive. See
+    /// [`EffectInitializers::is_idle()`] for details.
+    ///
+    /// [`EffectInitializers::reset()`]: crate::EffectInitializers::reset
+    /// [`EffectInitializers::set_active()`]: crate::EffectInitializers::set_active
+    /// [`EffectInitializers::is_idle()`]: crate::EffectInitializers::is_idle
+    pub fn with_skip_when_empty(mut self, skip_when_empty: bool) -> Self {
+        self.skip_when_empty = skip_when_empty;
+        self
+    }
+
+    /// Simulate the effect on GPU without rendering it.
+    ///
+    /// A simulation-only effect runs its init and update passes as usual, but
+    /// never emits any draw call, and any render modifier is ignored. This is
+    /// useful to use the particle system as a GPU data producer, for example
+    /// to drive other compute passes. The particle buffer of such effects is
+    /// exposed in the render world via the [`SimulationOnlyBuffers`] resource.
+    ///
+    /// Because a simulation-only effect is never visible, you generally want
+    /// to combine this with [`SimulationCondition::Always`], otherwise the
+    /// effect is culled and its simulation paused.
+    ///
+    /// [`SimulationOnlyBuffers`]: crate::SimulationOnlyBuffers
+    pub fn with_simulation_only(mut self, simulation_only: bool) -> Self {
+        self.simulation_only = simulation_only;
+        self
+    }
+
+    /// Double-buffer the particles of the effect on GPU.
+    ///
+    /// By default, the update pass reads and writes each particle in place in a
+    /// single particle buffer. With double-buffering, two particle buffers are
+    /// allocated and swapped each simulated frame: the update pass reads the
+    /// particles from the buffer it wrote during the previous frame, and
+    /// writes them into the other buffer, which the render pass then reads.
+    /// This guarantees that the update pass always reads a fully consistent
+    /// state from the end of the previous frame, so that for example a
+    /// [`Expr::PrevAttribute`] is truly the value from the last frame, even
+    /// when reading the attributes of other particles.
+    ///
+    /// This doubles the GPU memory used by the particles of the effect, that is
+    /// the capacity of the effect multiplied by the size of the
+    /// [`ParticleLayout`]. This has no effect on the other GPU buffers.
+    ///
+    /// Double-buffering is not supported with ribbons and trails, which link
+    /// particles together with the [`Attribute::PREV`] and
+    /// [`Attribute::NEXT`] attributes. The shaders of such effects fail to
+    /// generate, and the effect is not simulated nor rendered. This is
+    /// disabled by default.
+    ///
+    /// [`Expr::PrevAttribute`]: crate::graph::Expr::PrevAttribute
+    /// [`Attribute::PREV`]: crate::Attribute::PREV
+    /// [`Attribute::NEXT`]: crate::Attribute::NEXT
+    pub fn with_double_buffering(mut self, double_buffered: bool) -> Self {
+        self.double_buffered = double_buffered;
+        self
+    }
+
+    /// Set the pseudo-random number generator algorithm of the effect.
+    ///
+    /// This selects the hash function used by all the shaders of the effect to
+    /// generate random values. Changing the algorithm changes the shader code,
+    /// so the effect pipelines are re-specialized the next time the effect is
+    /// compiled. Defaults to [`RngAlgorithm::Pcg`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// // Favor speed over quality of the random values.
+    /// let asset = EffectAsset::default().with_rng_algorithm(RngAlgorithm::Xorshift);
+    /// assert_eq!(asset.rng_algorithm, RngAlgorithm::Xorshift);
+    /// ```
+    pub fn with_rng_algorithm(mut self, rng_algorithm: RngAlgorithm) -> Self {
+        self.rng_algorithm = rng_algorithm;
+        self
+    }
+
+    /// Define a named constant in the generated shaders.
+    ///
+    /// The constant is emitted as a WGSL `const` declaration at module scope
+    /// of the init, update, and render shaders, and can be referenced by name
+    /// from any custom WGSL code injected into those shaders, like a
+    /// preprocessor define would:
+    ///
+    /// ```wgsl
+    /// const {name}: {type} = {value};
+    /// ```
+    ///
+    /// If a constant with the same name already exists, its value is replaced.
+    /// Because the constant is part of the shader source code, changing its
+    /// value produces a different shader, and the effect pipelines are
+    /// re-specialized the next time the effect is compiled.
+    ///
+    /// The name must be a valid WGSL identifier, otherwise the effect fails to
+    /// compile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let asset = EffectAsset::default()
+    ///     .with_shader_define("USE_FANCY_FADE", true)
+    ///     .with_shader_define("FADE_POWER", 2.5_f32);
+    /// assert_eq!(asset.shader_defines().len(), 2);
+    /// ```
+    pub fn with_shader_define(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        let name = name.into();
+        let value = value.into();
+        if let Some(define) = self.shader_defines.iter_mut().find(|(n, _)| *n == name) {
+            define.1 = value;
+        } else {
+            self.shader_defines.push((name, value));
+        }
+        self
+    }
+
+    /// Get the named constants injected into the generated shaders.
+    ///
+    /// See [`with_shader_define()`] for details.
+    ///
+    /// [`with_shader_define()`]: crate::EffectAsset::with_shader_define
+    pub fn shader_defines(&self) -> &[(String, Value)] {
+        &self.shader_defines
+    }
+
     /// Adds a new particle group that clones particles at an interval to
     /// produce a trail.
     ///
@@ -491,7 +1055,31 @@ impl EffectAsset {
     /// Add an initialization modifier to the effect.
     ///
     /// Initialization modifiers apply to all particles that are spawned or
-    /// cloned.
+    /// cloned. Only modifiers implementing [`InitModifier`] are accepted, so
+    /// adding a modifier not supporting the init context is a compile error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut module = Module::default();
+    /// let vel = module.lit(Vec3::Y);
+    /// let asset = EffectAsset::new(32, Spawner::once(1.0.into(), true), module)
+    ///     .init(SetAttributeModifier::new(Attribute::VELOCITY, vel));
+    /// ```
+    ///
+    /// Render modifiers can't be used to initialize particles, since they
+    /// don't implement [`InitModifier`]:
+    ///
+    /// ```compile_fail,E0277
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec4;
+    /// let asset = EffectAsset::new(32, Spawner::once(1.0.into(), true), Module::default())
+    ///     .init(SetColorModifier {
+    ///         color: Vec4::ONE.into(),
+    ///     });
+    /// ```
     ///
     /// # Panics
     ///
@@ -501,7 +1089,7 @@ impl EffectAsset {
     #[inline]
     pub fn init<M>(mut self, modifier: M) -> Self
     where
-        M: Modifier + Send + Sync,
+        M: InitModifier + Send + Sync,
     {
         assert!(modifier.context().contains(ModifierContext::Init));
         self.init_modifiers.push(GroupedModifier {
@@ -523,7 +1111,7 @@ impl EffectAsset {
     /// [`ModifierContext::Init`]).
     pub fn init_groups<M>(mut self, modifier: M, groups: ParticleGroupSet) -> Self
     where
-        M: Modifier + Send + Sync,
+        M: InitModifier + Send + Sync,
     {
         self.init_modifiers.push(GroupedModifier {
             modifier: Box::new(modifier),
@@ -534,6 +1122,17 @@ impl EffectAsset {
 
     /// Add an update modifier to the effect.
     ///
+    /// Only modifiers implementing [`UpdateModifier`] are accepted, so adding
+    /// a modifier not supporting the update context is a compile error.
+    ///
+    /// ```compile_fail,E0277
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// // SetPositionPointCloudModifier only supports the init context
+    /// let asset = EffectAsset::new(32, Spawner::once(1.0.into(), true), Module::default())
+    ///     .update(SetPositionPointCloudModifier::new(vec![Vec3::ZERO]));
+    /// ```
+    ///
     /// # Panics
     ///
     /// Panics if the modifier doesn't support the update context (that is,
@@ -542,7 +1141,7 @@ impl EffectAsset {
     #[inline]
     pub fn update<M>(mut self, modifier: M) -> Self
     where
-        M: Modifier + Send + Sync,
+        M: UpdateModifier + Send + Sync,
     {
         assert!(modifier.context().contains(ModifierContext::Update));
         self.update_modifiers.push(GroupedModifier {
@@ -559,7 +1158,7 @@ impl EffectAsset {
     #[inline]
     pub fn update_groups<M>(mut self, modifier: M, groups: ParticleGroupSet) -> Self
     where
-        M: Modifier + Send + Sync,
+        M: UpdateModifier + Send + Sync,
     {
         self.update_modifiers.push(GroupedModifier {
             modifier: Box::new(modifier),
@@ -642,6 +1241,79 @@ impl EffectAsset {
         self
     }
 
+    /// Add a render modifier to a render layer of the effect.
+    ///
+    /// Modifiers added with [`render()`] and the other related methods all
+    /// belong to the [`EffectRenderLayer::Primary`] layer. Adding at least one
+    /// modifier to the [`EffectRenderLayer::Secondary`] layer makes the effect
+    /// render its particles a second time, after the primary layer, with the
+    /// render modifiers of that secondary layer only. The particles are
+    /// simulated only once, and both layers draw the same particles from the
+    /// same GPU buffer.
+    ///
+    /// Both layers share the [`AlphaMode`] and the textures of the effect, but
+    /// each layer applies its own render modifiers, so can for example use a
+    /// different texture slot, color, or size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::prelude::*;
+    /// # let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), Module::default());
+    /// // Render a small white core, and a larger orange glow around it
+    /// let asset = asset
+    ///     .render(SetSizeModifier {
+    ///         size: Vec2::splat(0.1).into(),
+    ///     })
+    ///     .render_layer(
+    ///         EffectRenderLayer::Secondary,
+    ///         SetSizeModifier {
+    ///             size: Vec2::splat(0.5).into(),
+    ///         },
+    ///     )
+    ///     .render_layer(
+    ///         EffectRenderLayer::Secondary,
+    ///         SetColorModifier {
+    ///             color: Vec4::new(1., 0.5, 0., 0.3).into(),
+    ///         },
+    ///     );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modifier doesn't support the render context (that is,
+    /// `modifier.context()` returns a flag which doesn't include
+    /// [`ModifierContext::Render`]).
+    ///
+    /// [`render()`]: crate::EffectAsset::render
+    pub fn render_layer<M>(mut self, layer: EffectRenderLayer, modifier: M) -> Self
+    where
+        M: RenderModifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Render));
+        let grouped_modifier = GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::all(),
+        };
+        match layer {
+            EffectRenderLayer::Primary => self.render_modifiers.push(grouped_modifier),
+            EffectRenderLayer::Secondary => self.secondary_render_modifiers.push(grouped_modifier),
+        }
+        self
+    }
+
+    /// Check if the effect renders a secondary render layer.
+    ///
+    /// This is `true` if at least one render modifier was added to the
+    /// [`EffectRenderLayer::Secondary`] layer. See [`render_layer()`] for
+    /// details.
+    ///
+    /// [`render_layer()`]: crate::EffectAsset::render_layer
+    pub fn has_secondary_render_layer(&self) -> bool {
+        !self.secondary_render_modifiers.is_empty()
+    }
+
     /// Add a render modifier to specific groups of this effect.
     ///
     /// # Panics
@@ -712,6 +1384,7 @@ impl EffectAsset {
             .chain(
                 self.render_modifiers
                     .iter()
+                    .chain(self.secondary_render_modifiers.iter())
                     .map(|grouped_modifier| &*grouped_modifier.modifier),
             )
     }
@@ -795,15 +1468,19 @@ impl EffectAsset {
     /// Get a list of all the render modifiers of this effect.
     ///
     /// This is a filtered list of all modifiers, retaining only modifiers
-    /// executing in the [`ModifierContext::Render`] context.
+    /// executing in the [`ModifierContext::Render`] context. This includes the
+    /// modifiers of all render layers.
     ///
     /// [`ModifierContext::Render`]: crate::ModifierContext::Render
     pub fn render_modifiers(&self) -> impl Iterator<Item = &dyn RenderModifier> {
-        self.render_modifiers.iter().filter_map(|m| m.as_render())
+        self.render_modifiers
+            .iter()
+            .chain(self.secondary_render_modifiers.iter())
+            .filter_map(|m| m.as_render())
     }
 
-    /// Get a list of all the render modifiers of this effect that affect a
-    /// specific group.
+    /// Get a list of all the render modifiers of the primary render layer of
+    /// this effect that affect a specific group.
     ///
     /// This is a filtered list of all modifiers, retaining only modifiers
     /// executing in the [`ModifierContext::Render`] context and that affect the
@@ -814,7 +1491,27 @@ impl EffectAsset {
         &self,
         group_index: u32,
     ) -> impl Iterator<Item = &dyn RenderModifier> {
-        self.render_modifiers.iter().filter_map(move |m| {
+        self.render_modifiers_for_layer(group_index, EffectRenderLayer::Primary)
+    }
+
+    /// Get a list of all the render modifiers of a render layer of this effect
+    /// that affect a specific group.
+    ///
+    /// This is a filtered list of all modifiers, retaining only modifiers
+    /// executing in the [`ModifierContext::Render`] context and that affect the
+    /// given group.
+    ///
+    /// [`ModifierContext::Render`]: crate::ModifierContext::Render
+    pub fn render_modifiers_for_layer(
+        &self,
+        group_index: u32,
+        layer: EffectRenderLayer,
+    ) -> impl Iterator<Item = &dyn RenderModifier> {
+        let modifiers = match layer {
+            EffectRenderLayer::Primary => &self.render_modifiers,
+            EffectRenderLayer::Secondary => &self.secondary_render_modifiers,
+        };
+        modifiers.iter().filter_map(move |m| {
             if m.groups.contains(group_index) {
                 m.modifier.as_render()
             } else {
@@ -837,6 +1534,11 @@ impl EffectAsset {
             }
         }
 
+        // The initial age overwrites the age of spawned particles
+        if self.initial_age.is_some() {
+            set.insert(Attribute::AGE);
+        }
+
         // If we're using ribbons, we need a linked list.
         if self.ribbon_group.is_some() {
             set.insert(Attribute::PREV);
@@ -844,7 +1546,7 @@ impl EffectAsset {
         }
 
         // Build the layout
-        let mut layout = ParticleLayout::new();
+        let mut layout = ParticleLayout::new().pack_first(&self.packing_hint);
         for attr in set {
             layout = layout.append(attr);
         }
@@ -865,6 +1567,94 @@ impl EffectAsset {
         self.module.texture_layout()
     }
 
+    /// Estimate the per-particle cost of the effect.
+    ///
+    /// This returns a [`GraphStats`] summarizing the operations, texture
+    /// samples, and transcendental function calls of the shader code generated
+    /// by the init, update, and render modifiers of all groups of the effect.
+    /// This includes the expressions used by those modifiers, but not the
+    /// unused expressions of the [`Module`], as well as the code the modifiers
+    /// write directly, like the sampling of a shape or a texture. The estimate
+    /// is not cycle-accurate, and is meant as a relative indicator to compare
+    /// effect variants during performance tuning.
+    ///
+    /// Modifiers failing to generate their code are ignored.
+    pub fn estimate_cost(&self) -> GraphStats {
+        let particle_layout = self.particle_layout();
+        let property_layout = self.property_layout();
+        let mut module = self.module.clone();
+        let mut stats = GraphStats::default();
+        for group_index in 0..self.init.len() as u32 {
+            for (context, modifiers) in [
+                (
+                    ModifierContext::Init,
+                    self.init_modifiers_for_group(group_index)
+                        .collect::<Vec<_>>(),
+                ),
+                (
+                    ModifierContext::Update,
+                    self.update_modifiers_for_group(group_index)
+                        .collect::<Vec<_>>(),
+                ),
+            ] {
+                let mut writer = ShaderWriter::new(context, &property_layout, &particle_layout);
+                for m in modifiers {
+                    let _ = m.apply(&mut module, &mut writer);
+                }
+                stats += GraphStats::from_wgsl(&writer.main_code);
+                stats += GraphStats::from_wgsl(&writer.extra_code);
+            }
+
+            if self.simulation_only {
+                continue;
+            }
+            for layer in [EffectRenderLayer::Primary, EffectRenderLayer::Secondary] {
+                let texture_layout = module.texture_layout();
+                let mut context =
+                    RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+                for m in self.render_modifiers_for_layer(group_index, layer) {
+                    let _ = m.apply_render(&mut module, &mut context);
+                }
+                stats += GraphStats::from_wgsl(&context.vertex_code);
+                stats += GraphStats::from_wgsl(&context.fragment_code);
+                stats += GraphStats::from_wgsl(&context.render_extra);
+                // Account for the texture sampling helper, which is only emitted when
+                // generating the full render shader.
+                if context.needs_uv {
+                    stats.texture_samples += 1;
+                    if context.flipbook_blend {
+                        stats.texture_samples += 1;
+                        stats.alu_ops += 2;
+                    }
+                }
+            }
+        }
+        stats
+    }
+
+    /// Generate the WGSL source code of the shaders of the effect.
+    ///
+    /// This applies all modifiers and returns the init, update, and render
+    /// shader source code for each particle group, exactly as the crate
+    /// generates them before specializing and compiling them on the GPU. This
+    /// doesn't require any render world or GPU device, and is intended for
+    /// inspecting and debugging the generated code, or feeding it to external
+    /// shader tools.
+    ///
+    /// The returned code is not specialized; conditional directives like
+    /// `#ifdef` and `#import` statements are still present. The code assumes
+    /// the effect is not affected by any [`WindZone`] nor [`EffectGravity`].
+    ///
+    /// [`WindZone`]: crate::WindZone
+    /// [`EffectGravity`]: crate::EffectGravity
+    pub fn generated_wgsl(&self) -> Result<CompiledShaders, ExprError> {
+        let source = EffectShaderSource::generate(self, false, false).map_err(|err| match err {
+            ShaderGenerateError::Expr(err) => err,
+            ShaderGenerateError::Validate(msg) => ExprError::GraphEvalError(msg),
+        })?;
+        Ok(source.into())
+    }
+
     /// Computes the group evaluation order, which ensures that cloners run
     /// before spawners.
     ///
@@ -1053,6 +1843,103 @@ mod tests {
         // assert_eq!(effect.render_layout, render_layout);
     }
 
+    #[test]
+    fn estimate_cost() {
+        let writer = ExprWriter::new();
+        let pos = writer.lit(Vec3::ZERO).expr();
+        let module = writer.finish();
+        let baseline = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, pos));
+        let baseline_stats = baseline.estimate_cost();
+        assert_eq!(baseline_stats, GraphStats::default());
+
+        // Add some procedural noise to the position
+        let writer = ExprWriter::new();
+        let freq = writer.lit(3.);
+        let noise = (writer.rand(VectorType::VEC3F) * freq).sin() + writer.lit(Vec3::ZERO);
+        let pos = noise.expr();
+        let module = writer.finish();
+        let noisy = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, pos));
+        let noisy_stats = noisy.estimate_cost();
+        assert_eq!(noisy_stats.alu_ops, 3);
+        assert_eq!(noisy_stats.transcendentals, 1);
+        assert_eq!(noisy_stats.texture_samples, 0);
+        assert!(noisy_stats.cost() > baseline_stats.cost());
+
+        // Expressions not used by any modifier don't cost anything
+        let writer = ExprWriter::new();
+        let pos = writer.lit(Vec3::ZERO).expr();
+        let _unused = (writer.rand(VectorType::VEC3F) * writer.lit(3.)).sin();
+        let module = writer.finish();
+        assert!(module.stats().cost() > 0);
+        let dead = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, pos));
+        assert_eq!(dead.estimate_cost(), baseline_stats);
+
+        // The code written by the modifiers themselves is accounted for
+        let writer = ExprWriter::new();
+        let center = writer.lit(Vec3::ZERO).expr();
+        let radius = writer.lit(1.).expr();
+        let module = writer.finish();
+        let sphere = EffectAsset::new(256, Spawner::rate(32.0.into()), module).init(
+            SetPositionSphereModifier {
+                center,
+                radius,
+                dimension: ShapeDimension::Volume,
+            },
+        );
+        let sphere_stats = sphere.estimate_cost();
+        assert!(sphere_stats.transcendentals > 0);
+        assert!(sphere_stats.alu_ops > 0);
+
+        // Texture sampling in the render shader
+        let writer = ExprWriter::new();
+        let pos = writer.lit(Vec3::ZERO).expr();
+        let texture_slot = writer.lit(0u32).expr();
+        let mut module = writer.finish();
+        module.add_texture("color");
+        let textured = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, pos))
+            .render(ParticleTextureModifier::new(texture_slot));
+        let textured_stats = textured.estimate_cost();
+        assert_eq!(textured_stats.texture_samples, 1);
+        assert!(textured_stats.cost() > baseline_stats.cost());
+    }
+
+    #[test]
+    fn generated_wgsl() {
+        let writer = ExprWriter::new();
+        let pos = writer.lit(Vec3::ZERO).expr();
+        let vel = writer.lit(Vec3::Y).expr();
+        let accel = writer.lit(Vec3::NEG_Y).expr();
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, pos))
+            .init(SetAttributeModifier::new(Attribute::VELOCITY, vel))
+            .update(AccelModifier::new(accel));
+
+        let shaders = asset.generated_wgsl().unwrap();
+        assert_eq!(shaders.init.len(), 1);
+        assert_eq!(shaders.update.len(), 1);
+        assert_eq!(shaders.render.len(), 1);
+        let position = format!("particle.{}", Attribute::POSITION.name());
+        let velocity = format!("particle.{}", Attribute::VELOCITY.name());
+        for code in [&shaders.init[0], &shaders.update[0]] {
+            assert!(!code.is_empty());
+            assert!(code.contains(&position));
+            assert!(code.contains(&velocity));
+        }
+        assert!(!shaders.render[0].is_empty());
+
+        // Invalid effects report an error instead
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), Module::default());
+        assert!(matches!(
+            asset.generated_wgsl(),
+            Err(ExprError::GraphEvalError(_))
+        ));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_serde_ron() {
@@ -1098,6 +1985,7 @@ mod tests {
     ],
     z_layer_2d: 0.0,
     simulation_space: Global,
+    simulation_space_blend: 0.0,
     simulation_condition: WhenVisible,
     init_modifiers: [
         (
@@ -1112,6 +2000,7 @@ mod tests {
     ],
     update_modifiers: [],
     render_modifiers: [],
+    secondary_render_modifiers: [],
     motion_integration: PostUpdate,
     module: (
         expressions: [
@@ -1139,7 +2028,19 @@ mod tests {
         ),
     ),
     alpha_mode: Blend,
+    premultiply_alpha: false,
     ribbon_group: None,
+    position_jitter: None,
+    snap_to_grid: None,
+    initial_age: None,
+    packing_hint: [],
+    position_bounds: None,
+    max_delta_time: None,
+    skip_when_empty: false,
+    simulation_only: false,
+    double_buffered: false,
+    rng_algorithm: Pcg,
+    shader_defines: [],
 )"#
         );
         let effect_serde: EffectAsset = ron::from_str(&s).unwrap();
@@ -1155,6 +2056,17 @@ mod tests {
         assert_eq!(effect.motion_integration, effect_serde.motion_integration);
         assert_eq!(effect.module, effect_serde.module);
         assert_eq!(effect.alpha_mode, effect_serde.alpha_mode);
+        assert_eq!(effect.premultiply_alpha, effect_serde.premultiply_alpha);
+        assert_eq!(effect.position_jitter, effect_serde.position_jitter);
+        assert_eq!(effect.snap_to_grid, effect_serde.snap_to_grid);
+        assert_eq!(effect.initial_age, effect_serde.initial_age);
+        assert_eq!(effect.packing_hint, effect_serde.packing_hint);
+        assert_eq!(effect.position_bounds, effect_serde.position_bounds);
+        assert_eq!(effect.max_delta_time, effect_serde.max_delta_time);
+        assert_eq!(effect.simulation_only, effect_serde.simulation_only);
+        assert_eq!(effect.double_buffered, effect_serde.double_buffered);
+        assert_eq!(effect.rng_algorithm, effect_serde.rng_algorithm);
+        assert_eq!(effect.shader_defines, effect_serde.shader_defines);
         assert_eq!(
             effect.init_modifiers().count(),
             effect_serde.init_modifiers().count()
@@ -1168,4 +2080,81 @@ mod tests {
             effect_serde.render_modifiers().count()
         );
     }
+
+    #[test]
+    fn test_serde_ron_backward_compat() {
+        // Serialized effect predating the more recent optional fields, which
+        // must all fall back to their default value.
+        let s = r#"(
+    name: "Effect",
+    capacities: [
+        4096,
+    ],
+    init: [
+        Spawner((
+            num_particles: Single(30.0),
+            spawn_time: Single(1.0),
+            period: Single(1.0),
+            starts_active: true,
+            starts_immediately: true,
+        )),
+    ],
+    z_layer_2d: 0.0,
+    simulation_space: Global,
+    simulation_condition: WhenVisible,
+    init_modifiers: [
+        (
+            modifier: {
+                "SetAttributeModifier": (
+                    attribute: "position",
+                    value: 1,
+                ),
+            },
+            groups: (4294967295),
+        ),
+    ],
+    update_modifiers: [],
+    render_modifiers: [],
+    motion_integration: PostUpdate,
+    module: (
+        expressions: [
+            Literal(Vector(Vec3((1.2, -3.45, 87.54485)))),
+            Literal(Vector(BVec2((false, true)))),
+            Binary(
+                op: Add,
+                left: 2,
+                right: 1,
+            ),
+            Property(1),
+            Unary(
+                op: Abs,
+                expr: 4,
+            ),
+        ],
+        properties: [
+            (
+                name: "my_prop",
+                default_value: Vector(Vec3((1.2, -2.3, 55.32))),
+            ),
+        ],
+        texture_layout: (
+            layout: [],
+        ),
+    ),
+    alpha_mode: Blend,
+    ribbon_group: None,
+)"#;
+        let effect: EffectAsset = ron::from_str(s).unwrap();
+        assert_eq!(effect.name, "Effect");
+        assert_eq!(effect.capacities, vec![4096]);
+        assert_eq!(effect.init_modifiers().count(), 1);
+        assert_eq!(effect.simulation_space_blend, 0.);
+        assert!(!effect.has_secondary_render_layer());
+        assert!(!effect.premultiply_alpha);
+        assert!(effect.packing_hint.is_empty());
+        assert!(!effect.simulation_only);
+        assert!(!effect.double_buffered);
+        assert_eq!(effect.rng_algorithm, RngAlgorithm::default());
+        assert!(effect.shader_defines().is_empty());
+    }
 }
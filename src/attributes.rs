@@ -90,6 +90,7 @@
 //! | [`Attribute::AXIS_Y`] | Y axis of the particle frame. |
 //! | [`Attribute::AXIS_Z`] | Z axis of the particle frame. |
 //! | [`Attribute::SPRITE_INDEX`] | Index of the current sprite for flipbook animation. |
+//! | [`Attribute::SPRITE_BLEND`] | Blend factor toward the next sprite for flipbook animation. |
 //!
 //! # Custom attributes
 //!
@@ -514,6 +515,11 @@ impl AttributeInner {
         Value::Scalar(ScalarValue::Float(1.)),
     );
 
+    pub const BIRTH_TIME: &'static AttributeInner = &AttributeInner::new(
+        Cow::Borrowed("birth_time"),
+        Value::Scalar(ScalarValue::Float(0.)),
+    );
+
     pub const COLOR: &'static AttributeInner = &AttributeInner::new(
         Cow::Borrowed("color"),
         Value::Scalar(ScalarValue::Uint(0xFFFFFFFFu32)),
@@ -567,6 +573,11 @@ impl AttributeInner {
         Value::Scalar(ScalarValue::Int(0)),
     );
 
+    pub const SPRITE_BLEND: &'static AttributeInner = &AttributeInner::new(
+        Cow::Borrowed("sprite_blend"),
+        Value::Scalar(ScalarValue::Float(0.)),
+    );
+
     pub const F32_0: &'static AttributeInner = &AttributeInner::new(
         Cow::Borrowed("f32_0"),
         Value::Scalar(ScalarValue::Float(0.)),
@@ -893,6 +904,28 @@ impl Attribute {
     /// [`ScalarType::Float`]
     pub const LIFETIME: Attribute = Attribute(AttributeInner::LIFETIME);
 
+    /// The absolute simulation time at which the particle was spawned.
+    ///
+    /// Unlike [`Attribute::AGE`] which is relative to the particle itself, this
+    /// attribute stores the value of the effect simulation clock
+    /// ([`BuiltInOperator::Time`]) when the particle was spawned. This allows
+    /// keying an effect to a global clock, for example to synchronize it with
+    /// external events. Use [`SetAttributeModifier::birth_time()`] to write
+    /// this attribute at spawn time, then read it back during update with
+    /// _e.g._ `writer.attr(Attribute::BIRTH_TIME)`.
+    ///
+    /// # Name
+    ///
+    /// `birth_time`
+    ///
+    /// # Type
+    ///
+    /// [`ScalarType::Float`]
+    ///
+    /// [`BuiltInOperator::Time`]: crate::graph::BuiltInOperator::Time
+    /// [`SetAttributeModifier::birth_time()`]: crate::modifier::attr::SetAttributeModifier::birth_time
+    pub const BIRTH_TIME: Attribute = Attribute(AttributeInner::BIRTH_TIME);
+
     /// The particle's base color.
     ///
     /// This attribute stores a per-particle color, which can be used for
@@ -1075,6 +1108,25 @@ impl Attribute {
     /// [`FlipbookModifier`]: crate::modifier::output::FlipbookModifier
     pub const SPRITE_INDEX: Attribute = Attribute(AttributeInner::SPRITE_INDEX);
 
+    /// The blend factor toward the next sprite in a flipbook animation.
+    ///
+    /// This attribute stores the fractional position between the sprite at
+    /// [`Attribute::SPRITE_INDEX`] and the next one, in `[0:1]`. This is used
+    /// by the [`FlipbookModifier`] to cross-fade between consecutive sprites
+    /// when [`FlipbookModifier::blend_frames`] is enabled.
+    ///
+    /// # Name
+    ///
+    /// `sprite_blend`
+    ///
+    /// # Type
+    ///
+    /// [`ScalarType::Float`]
+    ///
+    /// [`FlipbookModifier`]: crate::modifier::output::FlipbookModifier
+    /// [`FlipbookModifier::blend_frames`]: crate::modifier::output::FlipbookModifier::blend_frames
+    pub const SPRITE_BLEND: Attribute = Attribute(AttributeInner::SPRITE_BLEND);
+
     /// A generic scalar float attribute.
     ///
     /// This attribute can be used for anything. It has no specific meaning. You
@@ -1149,11 +1201,12 @@ impl Attribute {
     declare_custom_attr_pub!(F32X4_3, "f32x4_3", 4, VEC4F);
 
     /// Collection of all the existing particle attributes.
-    const ALL: [Attribute; 31] = [
+    const ALL: [Attribute; 33] = [
         Attribute::POSITION,
         Attribute::VELOCITY,
         Attribute::AGE,
         Attribute::LIFETIME,
+        Attribute::BIRTH_TIME,
         Attribute::COLOR,
         Attribute::HDR_COLOR,
         Attribute::ALPHA,
@@ -1165,6 +1218,7 @@ impl Attribute {
         Attribute::AXIS_Y,
         Attribute::AXIS_Z,
         Attribute::SPRITE_INDEX,
+        Attribute::SPRITE_BLEND,
         Attribute::F32_0,
         Attribute::F32_1,
         Attribute::F32_2,
@@ -1281,6 +1335,10 @@ impl std::fmt::Debug for AttributeLayout {
 #[derive(Debug, Default, Clone)]
 pub struct ParticleLayoutBuilder {
     layout: Vec<AttributeLayout>,
+    /// Attributes to pack first, in order. See [`pack_first()`].
+    ///
+    /// [`pack_first()`]: ParticleLayoutBuilder::pack_first
+    packing_hint: Vec<Attribute>,
 }
 
 impl ParticleLayoutBuilder {
@@ -1301,6 +1359,44 @@ impl ParticleLayoutBuilder {
         self
     }
 
+    /// Hint that some attributes are accessed together, and should be packed
+    /// first in the layout.
+    ///
+    /// By default, [`build()`] orders the attributes to minimize the padding
+    /// between them. This instead places the hinted attributes first, adjacent
+    /// to each other in the given order, so that the GPU can fetch attributes
+    /// often accessed together, like the position and velocity of a particle,
+    /// from a same cache line. The remaining attributes follow, ordered as
+    /// usual. This may introduce some padding, which increases the size of the
+    /// layout.
+    ///
+    /// Only attributes which are also [`append()`]ed are packed; any other
+    /// hinted attribute is ignored. Calling this method multiple times appends
+    /// to the list of hinted attributes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let layout = ParticleLayout::new()
+    ///     .append(Attribute::AGE)
+    ///     .append(Attribute::POSITION)
+    ///     .append(Attribute::VELOCITY)
+    ///     .pack_first(&[Attribute::VELOCITY, Attribute::POSITION])
+    ///     .build();
+    /// assert_eq!(
+    ///     layout.generate_code(),
+    ///     "    velocity: vec3<f32>,\n    position: vec3<f32>,\n    age: f32,\n"
+    /// );
+    /// ```
+    ///
+    /// [`build()`]: ParticleLayoutBuilder::build
+    /// [`append()`]: ParticleLayoutBuilder::append
+    pub fn pack_first(mut self, attributes: &[Attribute]) -> Self {
+        self.packing_hint.extend_from_slice(attributes);
+        self
+    }
+
     /// Finalize the builder pattern and build the layout from the existing
     /// attributes.
     ///
@@ -1315,8 +1411,19 @@ impl ParticleLayoutBuilder {
         self.layout.sort_unstable_by_key(|la| la.attribute.name());
         self.layout.dedup_by_key(|la| la.attribute.name());
 
-        // Sort by size
-        self.layout.sort_unstable_by_key(|la| la.attribute.size());
+        // Pull out the hinted attributes, which are packed first in hint order
+        let packed_first: Vec<AttributeLayout> = self
+            .packing_hint
+            .iter()
+            .filter_map(|attr| {
+                let index = self.layout.iter().position(|la| la.attribute == *attr)?;
+                Some(self.layout.remove(index))
+            })
+            .collect();
+
+        // Sort by size. Use a stable sort to keep the attributes of a same size
+        // sorted by name, so the layout is deterministic.
+        self.layout.sort_by_key(|la| la.attribute.size());
 
         let mut layout = vec![];
         let mut offset = 0;
@@ -1426,6 +1533,23 @@ impl ParticleLayoutBuilder {
             }
         }
 
+        // Prepend the hinted attributes, and re-assign all offsets with the
+        // alignment rules of WGSL, which inserts some padding before any
+        // misaligned field of the particle struct.
+        if !packed_first.is_empty() {
+            let mut offset = 0;
+            layout = packed_first
+                .into_iter()
+                .chain(layout)
+                .map(|mut attr| {
+                    offset = next_multiple_of(offset, attr.attribute.align());
+                    attr.offset = offset as u32;
+                    offset += attr.attribute.size();
+                    attr
+                })
+                .collect();
+        }
+
         ParticleLayout { layout }
     }
 }
@@ -1434,6 +1558,7 @@ impl From<&ParticleLayout> for ParticleLayoutBuilder {
     fn from(layout: &ParticleLayout) -> Self {
         Self {
             layout: layout.layout.clone(),
+            packing_hint: vec![],
         }
     }
 }
@@ -1713,6 +1838,45 @@ mod tests {
         }
     }
 
+    // Ensure the offsets of a layout packed with a hint match the offsets of the
+    // fields of the WGSL particle struct generated from that layout, by querying
+    // naga as a reference.
+    #[test]
+    fn layout_packing_hint_offsets() {
+        let layout = ParticleLayout::new()
+            .append(Attribute::AGE)
+            .append(Attribute::LIFETIME)
+            .append(Attribute::POSITION)
+            .append(Attribute::VELOCITY)
+            .append(Attribute::SIZE2)
+            .append(Attribute::HDR_COLOR)
+            .pack_first(&[Attribute::AGE, Attribute::POSITION, Attribute::VELOCITY])
+            .build();
+        let attrs: Vec<_> = layout.attributes().iter().map(|a| a.attribute).collect();
+        assert_eq!(
+            &attrs[..3],
+            &[Attribute::AGE, Attribute::POSITION, Attribute::VELOCITY]
+        );
+        assert_eq!(attrs.len(), 6);
+
+        let src = format!("struct Particle {{\n{}}}\n", layout.generate_code());
+        let m = Frontend::new().parse(&src).unwrap();
+        let (_, ty) = m
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("Particle"))
+            .unwrap();
+        let naga::TypeInner::Struct { members, span } = &ty.inner else {
+            panic!("Particle is not a struct");
+        };
+        assert_eq!(members.len(), layout.attributes().len());
+        for (member, attr) in members.iter().zip(layout.attributes()) {
+            assert_eq!(member.name.as_deref(), Some(attr.attribute.name()));
+            assert_eq!(member.offset, attr.offset, "{:?}", attr);
+        }
+        assert_eq!(*span as u64, layout.min_binding_size().get());
+    }
+
     #[test]
     fn value_type_is_numeric() {
         assert!(!ScalarType::Bool.is_numeric());
@@ -2122,6 +2286,24 @@ mod tests {
             }
         }
 
+        // [1, 2, 3, 4] with hint [1, 3] -> [1 3 4 2]
+        {
+            let layout = ParticleLayout::new()
+                .append(F1)
+                .append(F2)
+                .append(F3)
+                .append(F4)
+                .pack_first(&[F1, F3, F1B])
+                .build();
+            assert_eq!(layout.layout.len(), 4);
+            for (i, (off, a)) in [(0, F1), (16, F3), (32, F4), (48, F2)].iter().enumerate() {
+                let attr_i = layout.layout[i];
+                assert_eq!(attr_i.offset, *off);
+                assert_eq!(attr_i.attribute, *a);
+            }
+            assert_eq!(layout.size(), 56);
+        }
+
         // [1, 4, 3, 2, 2, 3] -> [4 3 1 2 2 3]
         {
             let mut layout = ParticleLayout::new();
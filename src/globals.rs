@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+/// Global values shared by all effects.
+///
+/// The globals are a global resource uploaded each frame to the GPU alongside
+/// the other simulation parameters, so that environment-driven effects can
+/// read them without any per-effect property. Any change to this resource
+/// therefore affects all effects on the next frame, without recompiling their
+/// shaders.
+///
+/// The set of globals is fixed:
+///
+/// | Field            | Built-in operator                 | Type  |
+/// |------------------|-----------------------------------|-------|
+/// | [`time_of_day`]  | [`BuiltInOperator::TimeOfDay`]    | `f32` |
+/// | [`weather`]      | [`BuiltInOperator::Weather`]      | `f32` |
+///
+/// The meaning of each value is defined by the application; Hanabi only
+/// uploads them. Read them from any expression of any effect with
+/// [`ExprWriter::time_of_day()`] and [`ExprWriter::weather()`].
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// # use bevy::prelude::*;
+/// fn day_night_cycle(mut globals: ResMut<EffectGlobals>, time: Res<Time>) {
+///     // One full day every 10 minutes
+///     globals.time_of_day = (time.elapsed_seconds() / 600.).fract();
+/// }
+/// ```
+///
+/// [`time_of_day`]: EffectGlobals::time_of_day
+/// [`weather`]: EffectGlobals::weather
+/// [`BuiltInOperator::TimeOfDay`]: crate::BuiltInOperator::TimeOfDay
+/// [`BuiltInOperator::Weather`]: crate::BuiltInOperator::Weather
+/// [`ExprWriter::time_of_day()`]: crate::ExprWriter::time_of_day
+/// [`ExprWriter::weather()`]: crate::ExprWriter::weather
+#[derive(Debug, Default, Clone, Copy, PartialEq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct EffectGlobals {
+    /// Time of day, conventionally as a fraction of the day in \[0:1\[, with
+    /// `0.` at midnight and `0.5` at noon.
+    pub time_of_day: f32,
+    /// Weather intensity, conventionally in \[0:1\], from `0.` for clear
+    /// weather to `1.` for the worst storm.
+    pub weather: f32,
+}
+
+impl EffectGlobals {
+    /// Create new globals with the given values.
+    pub fn new(time_of_day: f32, weather: f32) -> Self {
+        Self {
+            time_of_day,
+            weather,
+        }
+    }
+}
@@ -102,18 +102,137 @@
 //! [`Modifier`]: crate::Modifier
 //! [`EffectAsset`]: crate::EffectAsset
 
-use std::{cell::RefCell, num::NonZeroU32, rc::Rc};
+use std::{
+    cell::RefCell,
+    hash::{Hash, Hasher},
+    num::NonZeroU32,
+    rc::Rc,
+};
 
-use bevy::{prelude::default, reflect::Reflect};
+use bevy::{prelude::default, reflect::Reflect, utils::HashMap};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::Value;
+use super::{ScalarValue, ScalarValueMut, Value, VectorValue};
 use crate::{
     Attribute, ModifierContext, ParticleLayout, Property, PropertyLayout, ScalarType,
     TextureLayout, TextureSlot, ToWgslString, ValueType, VectorType,
 };
 
+/// Check if all the components of a numeric value are equal to a number.
+///
+/// Boolean and matrix values never compare equal.
+fn value_is(value: &Value, number: f32) -> bool {
+    let scalar_is = |scalar: ScalarValue| match scalar {
+        ScalarValue::Float(f) => f == number,
+        ScalarValue::Int(i) => i as f32 == number,
+        ScalarValue::Uint(u) => u as f32 == number,
+        ScalarValue::Bool(_) => false,
+    };
+    match value {
+        Value::Scalar(scalar) => scalar_is(*scalar),
+        Value::Vector(vector) => {
+            (0..vector.vector_type().count()).all(|index| scalar_is(vector.value(index)))
+        }
+        Value::Matrix(_) => false,
+    }
+}
+
+/// Check if all the components of a value are finite.
+fn value_is_finite(value: &Value) -> bool {
+    let scalar_is_finite = |scalar: ScalarValue| match scalar {
+        ScalarValue::Float(f) => f.is_finite(),
+        _ => true,
+    };
+    match value {
+        Value::Scalar(scalar) => scalar_is_finite(*scalar),
+        Value::Vector(vector) => {
+            (0..vector.vector_type().count()).all(|index| scalar_is_finite(vector.value(index)))
+        }
+        Value::Matrix(_) => false,
+    }
+}
+
+/// Compute on the CPU a binary arithmetic operation between two scalars.
+///
+/// Returns `None` if the operation can't be folded, either because the operands
+/// are not of the same numeric type, or because the result is not
+/// representable as a literal (integer overflow or division by zero, or a
+/// non-finite float).
+fn fold_scalar(op: BinaryOperator, left: ScalarValue, right: ScalarValue) -> Option<ScalarValue> {
+    match (left, right) {
+        (ScalarValue::Float(l), ScalarValue::Float(r)) => {
+            let value = match op {
+                BinaryOperator::Add => l + r,
+                BinaryOperator::Sub => l - r,
+                BinaryOperator::Mul => l * r,
+                BinaryOperator::Div => l / r,
+                BinaryOperator::Min => l.min(r),
+                BinaryOperator::Max => l.max(r),
+                _ => return None,
+            };
+            value.is_finite().then_some(ScalarValue::Float(value))
+        }
+        (ScalarValue::Int(l), ScalarValue::Int(r)) => match op {
+            BinaryOperator::Add => l.checked_add(r),
+            BinaryOperator::Sub => l.checked_sub(r),
+            BinaryOperator::Mul => l.checked_mul(r),
+            BinaryOperator::Div => l.checked_div(r),
+            BinaryOperator::Min => Some(l.min(r)),
+            BinaryOperator::Max => Some(l.max(r)),
+            _ => None,
+        }
+        .map(ScalarValue::Int),
+        (ScalarValue::Uint(l), ScalarValue::Uint(r)) => match op {
+            BinaryOperator::Add => l.checked_add(r),
+            BinaryOperator::Sub => l.checked_sub(r),
+            BinaryOperator::Mul => l.checked_mul(r),
+            BinaryOperator::Div => l.checked_div(r),
+            BinaryOperator::Min => Some(l.min(r)),
+            BinaryOperator::Max => Some(l.max(r)),
+            _ => None,
+        }
+        .map(ScalarValue::Uint),
+        _ => None,
+    }
+}
+
+/// Compute on the CPU a binary arithmetic operation between two values.
+///
+/// Vectors are operated component-wise. A scalar operand is broadcast to all
+/// the components of a vector operand, as in WGSL, for the `+`, `-`, `*`, and
+/// `/` operators only. Returns `None` if the operation can't be folded.
+fn fold_values(op: BinaryOperator, left: &Value, right: &Value) -> Option<Value> {
+    let broadcasts = matches!(
+        op,
+        BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul | BinaryOperator::Div
+    );
+    let (left, right) = match (left, right) {
+        (Value::Scalar(l), Value::Scalar(r)) => {
+            return fold_scalar(op, *l, *r).map(Value::Scalar);
+        }
+        (Value::Vector(l), Value::Vector(r)) if l.vector_type() == r.vector_type() => (*l, *r),
+        (Value::Scalar(l), Value::Vector(r)) if broadcasts => {
+            (VectorValue::splat(l, r.vector_type().count() as u8), *r)
+        }
+        (Value::Vector(l), Value::Scalar(r)) if broadcasts => {
+            (*l, VectorValue::splat(r, l.vector_type().count() as u8))
+        }
+        _ => return None,
+    };
+    let mut result = left;
+    for index in 0..left.vector_type().count() {
+        let value = fold_scalar(op, left.value(index), right.value(index))?;
+        match (result.value_mut(index), value) {
+            (ScalarValueMut::Float(dst), ScalarValue::Float(v)) => *dst = v,
+            (ScalarValueMut::Int(dst), ScalarValue::Int(v)) => *dst = v,
+            (ScalarValueMut::Uint(dst), ScalarValue::Uint(v)) => *dst = v,
+            _ => return None,
+        }
+    }
+    Some(Value::Vector(result))
+}
+
 /// A one-based ID into a collection of a [`Module`].
 type Id = NonZeroU32;
 
@@ -251,9 +370,20 @@ impl TextureHandle {
 /// deleted. Modules are not designed to be used as editing structures, but as
 /// storage and serialization ones.
 ///
+/// The [`lit()`], [`attr()`], and [`builtin()`] helpers reuse any identical
+/// expression already stored in the module, so that for example reading the
+/// same attribute from several graph nodes only emits a single read into the
+/// shader code. This only applies to side-effect-free expressions; random
+/// built-ins like [`BuiltInOperator::Rand`] always produce a new expression.
+/// Literals are compared by bit pattern, so `0.` and `-0.` are distinct. A
+/// reused handle is shared by all the callers which obtained it, so modifying
+/// its expression with [`get_mut()`] affects all of them.
+///
 /// [`EffectAsset`]: crate::EffectAsset
+/// [`get_mut()`]: Module::get_mut
 /// [`lit()`]: Module::lit
 /// [`attr()`]: Module::attr
+/// [`builtin()`]: Module::builtin
 #[derive(Debug, Default, Clone, PartialEq, Hash, Reflect, Serialize, Deserialize)]
 pub struct Module {
     /// Expressions defined in the module.
@@ -262,6 +392,76 @@ pub struct Module {
     properties: Vec<Property>,
     /// Texture layout.
     texture_layout: TextureLayout,
+    /// Index of the reusable expressions, to find them without scanning all
+    /// expressions.
+    #[reflect(ignore)]
+    #[serde(skip)]
+    reuse_cache: ReuseCache,
+}
+
+/// Key identifying a side-effect-free expression which [`Module`] reuses
+/// instead of appending a duplicate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ReuseKey {
+    /// Literal value, by bit pattern, so that for example `0.` and `-0.` are
+    /// distinct.
+    Literal(ValueType, Vec<u32>),
+    /// Read of a particle attribute.
+    Attribute(Attribute),
+    /// Side-effect-free built-in.
+    BuiltIn(BuiltInOperator),
+}
+
+impl ReuseKey {
+    /// Get the key of an expression, if it can be reused.
+    fn new(expr: &Expr) -> Option<Self> {
+        match expr {
+            Expr::Literal(lit) => {
+                let bits = match &lit.value {
+                    Value::Scalar(scalar) => vec![match *scalar {
+                        ScalarValue::Bool(b) => b as u32,
+                        ScalarValue::Float(f) => f.to_bits(),
+                        ScalarValue::Int(i) => i as u32,
+                        ScalarValue::Uint(u) => u,
+                    }],
+                    Value::Vector(vector) => {
+                        vector.storage[..vector.vector_type().count()].to_vec()
+                    }
+                    Value::Matrix(matrix) => matrix.storage.iter().map(|f| f.to_bits()).collect(),
+                };
+                Some(Self::Literal(lit.value.value_type(), bits))
+            }
+            Expr::Attribute(attr) => Some(Self::Attribute(attr.attr)),
+            Expr::BuiltIn(builtin) if !builtin.has_side_effect() => {
+                Some(Self::BuiltIn(builtin.operator))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Index of the reusable expressions of a [`Module`], by [`ReuseKey`].
+///
+/// This only speeds up lookups; it's rebuilt on demand from the expressions,
+/// so is neither serialized nor taken into account when comparing or hashing
+/// modules.
+#[derive(Debug, Default, Clone)]
+struct ReuseCache {
+    /// Index into the expressions of the module of the first expression with
+    /// each key.
+    indices: HashMap<ReuseKey, usize>,
+    /// Number of expressions of the module already indexed.
+    indexed_count: usize,
+}
+
+impl PartialEq for ReuseCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Hash for ReuseCache {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
 }
 
 macro_rules! impl_module_unary {
@@ -301,6 +501,7 @@ impl Module {
             expressions: expr,
             properties: vec![],
             texture_layout: default(),
+            reuse_cache: default(),
         }
     }
 
@@ -327,6 +528,36 @@ impl Module {
         }
     }
 
+    /// Add a new array property to the module.
+    ///
+    /// The property holds `len` elements, all initialized with
+    /// `default_value`. Elements are read with [`prop_index()`]. See
+    /// [`Property::new_array()`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a property with the same name already exists, or if `len` is
+    /// zero or greater than [`Property::MAX_ARRAY_LEN`].
+    ///
+    /// [`prop_index()`]: crate::Module::prop_index
+    pub fn add_array_property(
+        &mut self,
+        name: impl Into<String>,
+        default_value: Value,
+        len: u32,
+    ) -> PropertyHandle {
+        let name = name.into();
+        assert!(!self.properties.iter().any(|p| p.name() == name));
+        self.properties
+            .push(Property::new_array(name, default_value, len));
+        // SAFETY - We just pushed a new property into the array, so its length is
+        // non-zero.
+        #[allow(unsafe_code)]
+        unsafe {
+            PropertyHandle::new_unchecked(self.properties.len())
+        }
+    }
+
     /// Get an existing property by handle.
     ///
     /// Existing properties are properties previously created with
@@ -384,19 +615,76 @@ impl Module {
         }
     }
 
+    /// Append a new side-effect-free expression to the module, or return the
+    /// handle of an identical expression already stored in the module.
+    fn push_or_reuse(&mut self, expr: Expr) -> ExprHandle {
+        debug_assert!(!expr.has_side_effect(self));
+        let Some(key) = ReuseKey::new(&expr) else {
+            return self.push(expr);
+        };
+
+        // Index the expressions added since the last call, including any pushed
+        // without reuse or deserialized.
+        let cache = &mut self.reuse_cache;
+        for (index, expr) in self.expressions[cache.indexed_count..].iter().enumerate() {
+            if let Some(key) = ReuseKey::new(expr) {
+                cache
+                    .indices
+                    .entry(key)
+                    .or_insert(cache.indexed_count + index);
+            }
+        }
+        cache.indexed_count = self.expressions.len();
+
+        // The expression may have been modified with get_mut() since indexed, so
+        // check it still matches.
+        if let Some(&index) = cache.indices.get(&key) {
+            if ReuseKey::new(&self.expressions[index]).as_ref() == Some(&key) {
+                // SAFETY - Handles are one-based indices into the array.
+                #[allow(unsafe_code)]
+                return unsafe { ExprHandle::new_unchecked(index + 1) };
+            }
+        }
+        cache.indices.insert(key, self.expressions.len());
+        cache.indexed_count += 1;
+        self.push(expr)
+    }
+
     /// Build a literal expression and append it to the module.
+    ///
+    /// If the module already contains a literal expression with the same
+    /// value, compared by bit pattern, its handle is returned instead. Note
+    /// that modifying the expression of a reused handle with [`get_mut()`]
+    /// also modifies it for all the other users of that handle.
+    ///
+    /// [`get_mut()`]: Module::get_mut
     #[inline]
     pub fn lit<V>(&mut self, value: V) -> ExprHandle
     where
         Value: From<V>,
     {
-        self.push(Expr::Literal(LiteralExpr::new(value)))
+        self.push_or_reuse(Expr::Literal(LiteralExpr::new(value)))
     }
 
     /// Build an attribute expression and append it to the module.
+    ///
+    /// If the module already contains an expression reading the same
+    /// attribute, its handle is returned instead. Note that modifying the
+    /// expression of a reused handle with [`get_mut()`] also modifies it for
+    /// all the other users of that handle.
+    ///
+    /// [`get_mut()`]: Module::get_mut
     #[inline]
     pub fn attr(&mut self, attr: Attribute) -> ExprHandle {
-        self.push(Expr::Attribute(AttributeExpr::new(attr)))
+        self.push_or_reuse(Expr::Attribute(AttributeExpr::new(attr)))
+    }
+
+    /// Build a previous-frame attribute expression and append it to the module.
+    ///
+    /// See [`Expr::PrevAttribute`] for details.
+    #[inline]
+    pub fn prev_attr(&mut self, attr: Attribute) -> ExprHandle {
+        self.push(Expr::PrevAttribute(AttributeExpr::new(attr)))
     }
 
     /// Build a property expression and append it to the module.
@@ -407,10 +695,62 @@ impl Module {
         self.push(Expr::Property(PropertyExpr::new(property)))
     }
 
+    /// Build an expression reading an element of an array property, and append
+    /// it to the module.
+    ///
+    /// See [`PropertyIndexExpr`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the module has no property named `name`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut module = Module::default();
+    /// module.add_array_property("attractors", Vec3::ZERO.into(), 4);
+    /// let index = module.lit(2u32);
+    /// let pos = module.prop_index("attractors", index); // properties.attractors[2u]
+    /// ```
+    pub fn prop_index(&mut self, name: &str, index: ExprHandle) -> ExprHandle {
+        let property = self
+            .get_property_by_name(name)
+            .unwrap_or_else(|| panic!("Unknown property '{}' in module.", name));
+        self.push(Expr::PropertyIndex(PropertyIndexExpr::new(property, index)))
+    }
+
     /// Build a built-in expression and append it to the module.
+    ///
+    /// If the built-in has no side effect, and the module already contains an
+    /// expression with the same built-in, its handle is returned instead. Each
+    /// call with a random built-in like [`BuiltInOperator::Rand`] produces a
+    /// new expression, with a different random value. Note that modifying the
+    /// expression of a reused handle with [`get_mut()`] also modifies it for
+    /// all the other users of that handle.
+    ///
+    /// [`get_mut()`]: Module::get_mut
     #[inline]
     pub fn builtin(&mut self, op: BuiltInOperator) -> ExprHandle {
-        self.push(Expr::BuiltIn(BuiltInExpr::new(op)))
+        let expr = BuiltInExpr::new(op);
+        if expr.has_side_effect() {
+            self.push(Expr::BuiltIn(expr))
+        } else {
+            self.push_or_reuse(Expr::BuiltIn(expr))
+        }
+    }
+
+    /// Build a texture sample expression and append it to the module.
+    ///
+    /// See [`TextureSampleExpr`] for details. Texture sampling is only
+    /// available in the render context.
+    #[inline]
+    pub fn texture_sample(&mut self, image: ExprHandle, coordinates: ExprHandle) -> ExprHandle {
+        self.push(Expr::TextureSample(TextureSampleExpr::new(
+            image,
+            coordinates,
+        )))
     }
 
     /// Build a unary expression and append it to the module.
@@ -435,6 +775,7 @@ impl Module {
     }
 
     impl_module_unary!(abs, Abs);
+    impl_module_unary!(acos, Acos);
     impl_module_unary!(all, All);
     impl_module_unary!(any, Any);
     impl_module_unary!(ceil, Ceil);
@@ -445,15 +786,20 @@ impl Module {
     impl_module_unary!(fract, Fract);
     impl_module_unary!(inverse_sqrt, InvSqrt);
     impl_module_unary!(length, Length);
+    impl_module_unary!(linear_to_srgb, LinearToSrgb);
     impl_module_unary!(log, Log);
     impl_module_unary!(log2, Log2);
+    impl_module_unary!(neg, Neg);
     impl_module_unary!(normalize, Normalize);
     impl_module_unary!(pack4x8snorm, Pack4x8snorm);
     impl_module_unary!(pack4x8unorm, Pack4x8unorm);
+    impl_module_unary!(round, Round);
     impl_module_unary!(saturate, Saturate);
+    impl_module_unary!(seeded_rand, SeededRand);
     impl_module_unary!(sign, Sign);
     impl_module_unary!(sin, Sin);
     impl_module_unary!(sqrt, Sqrt);
+    impl_module_unary!(srgb_to_linear, SrgbToLinear);
     impl_module_unary!(tan, Tan);
     impl_module_unary!(unpack4x8snorm, Unpack4x8snorm);
     impl_module_unary!(unpack4x8unorm, Unpack4x8unorm);
@@ -490,10 +836,12 @@ impl Module {
     }
 
     impl_module_binary!(add, Add);
+    impl_module_binary!(atan2, Atan2);
     impl_module_binary!(cross, Cross);
     impl_module_binary!(distance, Distance);
     impl_module_binary!(div, Div);
     impl_module_binary!(dot, Dot);
+    impl_module_binary!(eq, Equal);
     impl_module_binary!(ge, GreaterThanOrEqual);
     impl_module_binary!(gt, GreaterThan);
     impl_module_binary!(le, LessThanOrEqual);
@@ -501,12 +849,14 @@ impl Module {
     impl_module_binary!(max, Max);
     impl_module_binary!(min, Min);
     impl_module_binary!(mul, Mul);
+    impl_module_binary!(pow, Pow);
     impl_module_binary!(rem, Remainder);
     impl_module_binary!(step, Step);
     impl_module_binary!(sub, Sub);
     impl_module_binary!(uniform, UniformRand);
     impl_module_binary!(normal, NormalRand);
     impl_module_binary!(vec2, Vec2);
+    impl_module_binary!(vec4, Vec4);
 
     /// Build a ternary expression and append it to the module.
     ///
@@ -542,8 +892,12 @@ impl Module {
         })
     }
 
+    impl_module_ternary!(clamp, Clamp);
     impl_module_ternary!(mix, Mix);
+    impl_module_ternary!(select, Select);
     impl_module_ternary!(smoothstep, SmoothStep);
+    impl_module_ternary!(vec3, Vec3);
+    impl_module_ternary!(mat3, Mat3);
 
     /// Build a cast expression and append it to the module.
     ///
@@ -573,6 +927,25 @@ impl Module {
         self.push(Expr::Cast(expr))
     }
 
+    /// Build a swizzle expression and append it to the module.
+    ///
+    /// The `mask` is a sequence of 1 to 4 vector components among `x`, `y`,
+    /// `z`, and `w`, like `"xy"` or `"zyx"`. See [`SwizzleExpr`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec4;
+    /// let mut module = Module::default();
+    /// let v = module.lit(Vec4::new(1., 2., 3., 4.));
+    /// let xy = module.swizzle(v, "xy").unwrap(); // (vec4<f32>(1.,2.,3.,4.)).xy
+    /// ```
+    pub fn swizzle(&mut self, inner: ExprHandle, mask: &str) -> Result<ExprHandle, ExprError> {
+        let expr = SwizzleExpr::new(inner, mask)?;
+        Ok(self.push(Expr::Swizzle(expr)))
+    }
+
     /// Get an existing expression from its handle.
     #[inline]
     pub fn get(&self, expr: ExprHandle) -> Option<&Expr> {
@@ -581,6 +954,9 @@ impl Module {
     }
 
     /// Get an existing expression from its handle.
+    ///
+    /// Note that literal, attribute, and built-in expressions may be shared by
+    /// several users of the module, so modifying them affects all those users.
     #[inline]
     pub fn get_mut(&mut self, expr: ExprHandle) -> Option<&mut Expr> {
         let index = expr.index();
@@ -633,10 +1009,404 @@ impl Module {
         expr.has_side_effect(self)
     }
 
+    /// Simplify trivial arithmetic operations of the module.
+    ///
+    /// This applies the following algebraic identities to all binary
+    /// operations of the module, replacing each simplified operation in place
+    /// such that all existing handles remain valid:
+    ///
+    /// - `x * 1` and `1 * x` become `x`
+    /// - `x + 0` and `0 + x` become `x`
+    /// - `x - 0` becomes `x`
+    /// - `x / 1` becomes `x`
+    /// - `x * 0` and `0 * x` become `0`
+    ///
+    /// The identity operand must be a literal whose components are all equal to
+    /// `0` or `1`. The operation is only simplified if this doesn't change the
+    /// type of the result, and if `x` has no side effect, to avoid duplicating
+    /// random values.
+    ///
+    /// The simplification never changes the numeric result of an expression.
+    /// In particular, `x * 0` is `NaN` if `x` is infinite or `NaN`, so that
+    /// operation is only simplified if `x` is an integer value, or a finite
+    /// floating-point literal. Otherwise it's left as is.
+    ///
+    /// Returns the number of operations simplified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let x = module.attr(Attribute::AGE);
+    /// let one = module.lit(1.);
+    /// let y = module.mul(x, one);
+    /// assert_eq!(module.simplify(), 1);
+    /// assert_eq!(module.get(y), module.get(x));
+    /// ```
+    pub fn simplify(&mut self) -> usize {
+        let mut count = 0;
+        // Operands always have a lower index than their operation, so a single pass in
+        // order simplifies the operands first, and the operation sees the result.
+        for index in 0..self.expressions.len() {
+            let Expr::Binary { op, left, right } = self.expressions[index] else {
+                continue;
+            };
+            if let Some(expr) = self.simplify_binary(op, left, right) {
+                self.expressions[index] = expr;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Fold the constant arithmetic operations of the module.
+    ///
+    /// This computes on the CPU the result of all binary operations whose
+    /// operands are both literals, and replaces each such operation in place
+    /// with a single literal of the result, such that all existing handles
+    /// remain valid. This reduces the work of the shader at runtime.
+    ///
+    /// Only the `+`, `-`, `*`, `/`, `min()`, and `max()` operators are folded,
+    /// for float, signed, and unsigned integer, scalar and vector operands.
+    /// Those operators have no side effect. An operation is left as is if its
+    /// result can't be represented exactly as a literal, like for an integer
+    /// overflow or division by zero, or a non-finite float result.
+    ///
+    /// Returns the number of operations folded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let three = module.lit(3.);
+    /// let two = module.lit(2.);
+    /// let sum = module.add(three, two);
+    /// assert_eq!(module.fold_constants(), 1);
+    /// assert_eq!(module.get(sum), Some(&Expr::Literal(LiteralExpr::new(5.))));
+    /// ```
+    pub fn fold_constants(&mut self) -> usize {
+        let mut count = 0;
+        // Operands always have a lower index than their operation, so a single pass in
+        // order folds the operands first, and the operation sees their literal.
+        for index in 0..self.expressions.len() {
+            let Expr::Binary { op, left, right } = self.expressions[index] else {
+                continue;
+            };
+            let (Some(Expr::Literal(left)), Some(Expr::Literal(right))) =
+                (self.get(left), self.get(right))
+            else {
+                continue;
+            };
+            if let Some(value) = fold_values(op, &left.value, &right.value) {
+                self.expressions[index] = Expr::Literal(LiteralExpr::new(value));
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn simplify_binary(
+        &self,
+        op: BinaryOperator,
+        left: ExprHandle,
+        right: ExprHandle,
+    ) -> Option<Expr> {
+        let literal = |expr: ExprHandle| match self.get(expr) {
+            Some(Expr::Literal(lit)) => Some(lit.value),
+            _ => None,
+        };
+        let is = |expr: ExprHandle, number: f32| {
+            literal(expr).is_some_and(|value| value_is(&value, number))
+        };
+
+        // Replace the operation with `kept`, if that doesn't change its type
+        let keep = |kept: ExprHandle, identity: ExprHandle| -> Option<Expr> {
+            let kept_expr = self.get(kept)?;
+            if self.has_side_effect(kept) {
+                return None;
+            }
+            let identity_type = literal(identity)?.value_type();
+            let compatible = match (identity_type, kept_expr.value_type()) {
+                (ValueType::Scalar(s), Some(ValueType::Scalar(k))) => s == k,
+                (ValueType::Scalar(s), Some(ValueType::Vector(k))) => s == k.elem_type(),
+                // Unknown type; a scalar broadcasts to any type anyway
+                (ValueType::Scalar(_), None) => true,
+                (identity_type, kept_type) => Some(identity_type) == kept_type,
+            };
+            compatible.then_some(*kept_expr)
+        };
+
+        // Replace the operation with the `zero` literal, if `x * 0` is always zero
+        let absorb = |x: ExprHandle, zero: ExprHandle| -> Option<Expr> {
+            let zero_expr = self.get(zero)?;
+            let x_expr = self.get(x)?;
+            if x_expr.value_type() != zero_expr.value_type() || self.has_side_effect(x) {
+                return None;
+            }
+            let is_finite = match literal(x) {
+                Some(value) => value_is_finite(&value),
+                // Integers are always finite
+                None => match x_expr.value_type() {
+                    Some(ValueType::Scalar(scalar_type)) => scalar_type != ScalarType::Float,
+                    Some(ValueType::Vector(vector_type)) => {
+                        vector_type.elem_type() != ScalarType::Float
+                    }
+                    _ => false,
+                },
+            };
+            is_finite.then_some(*zero_expr)
+        };
+
+        match op {
+            BinaryOperator::Add if is(right, 0.) => keep(left, right),
+            BinaryOperator::Add if is(left, 0.) => keep(right, left),
+            BinaryOperator::Sub if is(right, 0.) => keep(left, right),
+            BinaryOperator::Mul if is(right, 1.) => keep(left, right),
+            BinaryOperator::Mul if is(left, 1.) => keep(right, left),
+            BinaryOperator::Mul if is(right, 0.) => absorb(left, right),
+            BinaryOperator::Mul if is(left, 0.) => absorb(right, left),
+            BinaryOperator::Div if is(right, 1.) => keep(left, right),
+            _ => None,
+        }
+    }
+
     /// Get the texture layout of this module.
     pub fn texture_layout(&self) -> TextureLayout {
         self.texture_layout.clone()
     }
+
+    /// Estimate the per-particle cost of all the expressions of this module.
+    ///
+    /// This counts every expression stored in the module, whether or not it's
+    /// used by a modifier of the effect. To estimate the cost of the shaders of
+    /// an effect, use [`EffectAsset::estimate_cost()`] instead. See
+    /// [`GraphStats`] for details.
+    ///
+    /// [`EffectAsset::estimate_cost()`]: crate::EffectAsset::estimate_cost
+    pub fn stats(&self) -> GraphStats {
+        let mut stats = GraphStats::default();
+        for expr in &self.expressions {
+            match expr {
+                Expr::BuiltIn(expr) => {
+                    if let BuiltInOperator::Rand(_) = expr.operator {
+                        stats.alu_ops += 1;
+                    }
+                }
+                Expr::Literal(_)
+                | Expr::Property(_)
+                | Expr::PropertyIndex(_)
+                | Expr::Attribute(_)
+                | Expr::PrevAttribute(_) => {}
+                Expr::Unary { op, .. } => match op {
+                    UnaryOperator::Acos
+                    | UnaryOperator::Cos
+                    | UnaryOperator::Exp
+                    | UnaryOperator::Exp2
+                    | UnaryOperator::InvSqrt
+                    | UnaryOperator::LinearToSrgb
+                    | UnaryOperator::Log
+                    | UnaryOperator::Log2
+                    | UnaryOperator::Sin
+                    | UnaryOperator::Sqrt
+                    | UnaryOperator::SrgbToLinear
+                    | UnaryOperator::Tan => stats.transcendentals += 1,
+                    _ => stats.alu_ops += 1,
+                },
+                Expr::Binary { .. } | Expr::Ternary { .. } | Expr::Cast(_) => stats.alu_ops += 1,
+                Expr::Swizzle(_) => {}
+                Expr::TextureSample(_) => stats.texture_samples += 1,
+            }
+        }
+        stats
+    }
+}
+
+/// Statistics summarizing the estimated cost of some shader code.
+///
+/// The statistics are a rough, relative indicator of the per-particle cost of
+/// the expressions of a [`Module`], or of the shaders of an effect, useful to
+/// compare variants of an effect during performance tuning. They're not
+/// cycle-accurate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphStats {
+    /// Number of arithmetic and logic operations.
+    pub alu_ops: u32,
+    /// Number of texture samples.
+    pub texture_samples: u32,
+    /// Number of calls to transcendental functions (`sin()`, `exp()`,
+    /// `sqrt()`, ...).
+    pub transcendentals: u32,
+}
+
+impl GraphStats {
+    /// Relative cost of a transcendental function call, in ALU operations.
+    pub const TRANSCENDENTAL_COST: u32 = 4;
+
+    /// Relative cost of a texture sample, in ALU operations.
+    pub const TEXTURE_SAMPLE_COST: u32 = 16;
+
+    /// Get the total weighted cost, in equivalent ALU operations.
+    pub fn cost(&self) -> u32 {
+        self.alu_ops
+            + self.transcendentals * Self::TRANSCENDENTAL_COST
+            + self.texture_samples * Self::TEXTURE_SAMPLE_COST
+    }
+
+    /// Estimate the cost of a snippet of WGSL code.
+    ///
+    /// Each arithmetic, logic, or comparison operator, and each call to a
+    /// function other than a vector or matrix constructor, counts as one ALU
+    /// operation, unless the function is a transcendental function or a
+    /// texture sampling function. Helper functions defined in the snippet are
+    /// counted once, whatever the number of calls.
+    pub(crate) fn from_wgsl(code: &str) -> Self {
+        const TRANSCENDENTALS: &[&str] = &[
+            "acos",
+            "acosh",
+            "asin",
+            "asinh",
+            "atan",
+            "atan2",
+            "atanh",
+            "cos",
+            "cosh",
+            "exp",
+            "exp2",
+            "inverseSqrt",
+            "log",
+            "log2",
+            "pow",
+            "sin",
+            "sinh",
+            "sqrt",
+            "tan",
+            "tanh",
+        ];
+        const FREE_CALLS: &[&str] = &[
+            "array", "bool", "mat2x2", "mat2x3", "mat2x4", "mat3x2", "mat3x3", "mat3x4", "mat4x2",
+            "mat4x3", "mat4x4", "vec2", "vec3", "vec4",
+        ];
+        const KEYWORDS: &[&str] = &["for", "if", "loop", "return", "switch", "while"];
+
+        let mut stats = GraphStats::default();
+        for line in code.lines() {
+            let line = line.split("//").next().unwrap();
+            let bytes = line.as_bytes();
+            let mut i = 0;
+            // Whether the previous token ended an operand, to tell binary operators from
+            // unary ones and from the sign of a literal.
+            let mut after_operand = false;
+            let mut after_fn = false;
+            while i < bytes.len() {
+                let c = bytes[i];
+                if c.is_ascii_alphabetic() || c == b'_' {
+                    let start = i;
+                    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_')
+                    {
+                        i += 1;
+                    }
+                    let ident = &line[start..i];
+                    // Skip any template argument list, like in `vec3<f32>`
+                    if bytes.get(i) == Some(&b'<') {
+                        let mut depth = 0;
+                        let mut j = i;
+                        while j < bytes.len() {
+                            match bytes[j] {
+                                b'<' => depth += 1,
+                                b'>' => depth -= 1,
+                                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b',' | b' ' => {}
+                                _ => break,
+                            }
+                            j += 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        if depth == 0 {
+                            i = j;
+                        }
+                    }
+                    let is_call = line[i..].trim_start().starts_with('(');
+                    if is_call && !after_fn && !KEYWORDS.contains(&ident) {
+                        if TRANSCENDENTALS.contains(&ident) {
+                            stats.transcendentals += 1;
+                        } else if ident.starts_with("textureSample") || ident == "textureLoad" {
+                            stats.texture_samples += 1;
+                        } else if !FREE_CALLS.contains(&ident) {
+                            stats.alu_ops += 1;
+                        }
+                    }
+                    after_fn = ident == "fn";
+                    after_operand = !is_call && !KEYWORDS.contains(&ident);
+                    continue;
+                }
+                if !c.is_ascii_whitespace() {
+                    after_fn = false;
+                }
+                if c.is_ascii_digit()
+                    || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+                {
+                    while i < bytes.len()
+                        && (bytes[i].is_ascii_alphanumeric()
+                            || bytes[i] == b'.'
+                            || (matches!(bytes[i], b'+' | b'-')
+                                && matches!(bytes[i - 1], b'e' | b'E')))
+                    {
+                        i += 1;
+                    }
+                    after_operand = true;
+                    continue;
+                }
+                let next = bytes.get(i + 1).copied();
+                match c {
+                    b'-' if next == Some(b'>') => {
+                        i += 2;
+                        after_operand = false;
+                        continue;
+                    }
+                    b'+' | b'-' | b'*' | b'/' | b'%' | b'^' | b'|' | b'&' | b'<' | b'>' | b'!'
+                    | b'~' | b'=' => {
+                        // Consume compound operators like `+=`, `==`, or `<<` as a
+                        // single operator
+                        let len = if next == Some(b'=') || next == Some(c) && c != b'!' {
+                            2
+                        } else {
+                            1
+                        };
+                        let is_assign = c == b'=' && len == 1;
+                        let is_sign = matches!(c, b'-' | b'+')
+                            && !after_operand
+                            && bytes
+                                .get(i + 1)
+                                .is_some_and(|&b| b.is_ascii_digit() || b == b'.');
+                        // Address-of and dereference operators don't cost anything
+                        let is_pointer = matches!(c, b'&' | b'*') && len == 1 && !after_operand;
+                        if !is_assign && !is_sign && !is_pointer {
+                            stats.alu_ops += 1;
+                        }
+                        i += len;
+                        after_operand = false;
+                        continue;
+                    }
+                    b')' | b']' => after_operand = true,
+                    b' ' | b'\t' => {}
+                    _ => after_operand = false,
+                }
+                i += 1;
+            }
+        }
+        stats
+    }
+}
+
+impl std::ops::AddAssign for GraphStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.alu_ops += rhs.alu_ops;
+        self.texture_samples += rhs.texture_samples;
+        self.transcendentals += rhs.transcendentals;
+    }
 }
 
 /// Errors raised when manipulating expressions [`Expr`] and node graphs
@@ -789,12 +1559,31 @@ pub enum Expr {
     /// [`EffectAsset`]: crate::EffectAsset
     Property(PropertyExpr),
 
+    /// Array property element expression ([`PropertyIndexExpr`]).
+    ///
+    /// An array property element expression represents the value of a single
+    /// element of an [`EffectAsset`]'s array property.
+    ///
+    /// [`EffectAsset`]: crate::EffectAsset
+    PropertyIndex(PropertyIndexExpr),
+
     /// Attribute expression ([`AttributeExpr`]).
     ///
     /// An attribute expression represents the value of an attribute for a
     /// particle, like its position or velocity.
     Attribute(AttributeExpr),
 
+    /// Previous-frame attribute expression ([`AttributeExpr`]).
+    ///
+    /// A previous-frame attribute expression represents the value of an
+    /// attribute for a particle as it was at the end of the previous
+    /// simulation update, before any modification by the current update. This
+    /// allows for example deriving a velocity from the position delta.
+    ///
+    /// This expression is only valid in the [`ModifierContext::Update`]
+    /// context.
+    PrevAttribute(AttributeExpr),
+
     /// Unary operation expression.
     ///
     /// A unary operation transforms an expression into another expression.
@@ -836,6 +1625,11 @@ pub enum Expr {
     /// An expression to cast an expression to another type.
     Cast(CastExpr),
 
+    /// Swizzle expression.
+    ///
+    /// An expression to select and reorder the components of a vector.
+    Swizzle(SwizzleExpr),
+
     /// Access to textures.
     ///
     /// An expression to sample a texture from the effect's material. Currently
@@ -875,7 +1669,8 @@ impl Expr {
             Expr::BuiltIn(expr) => expr.is_const(),
             Expr::Literal(expr) => expr.is_const(),
             Expr::Property(expr) => expr.is_const(),
-            Expr::Attribute(expr) => expr.is_const(),
+            Expr::PropertyIndex(_) => false,
+            Expr::Attribute(expr) | Expr::PrevAttribute(expr) => expr.is_const(),
             Expr::Unary { expr, .. } => module.is_const(*expr),
             Expr::Binary { left, right, .. } => module.is_const(*left) && module.is_const(*right),
             Expr::Ternary {
@@ -885,6 +1680,7 @@ impl Expr {
                 ..
             } => module.is_const(*first) && module.is_const(*second) && module.is_const(*third),
             Expr::Cast(expr) => module.is_const(expr.inner),
+            Expr::Swizzle(expr) => module.is_const(expr.inner),
             Expr::TextureSample(_) => false,
         }
     }
@@ -899,8 +1695,12 @@ impl Expr {
             Expr::BuiltIn(expr) => expr.has_side_effect(),
             Expr::Literal(_) => false,
             Expr::Property(_) => false,
+            Expr::PropertyIndex(expr) => module.has_side_effect(expr.index),
             Expr::Attribute(_) => false,
-            Expr::Unary { expr, .. } => module.has_side_effect(*expr),
+            Expr::PrevAttribute(_) => false,
+            Expr::Unary { op, expr } => {
+                *op == UnaryOperator::SeededRand || module.has_side_effect(*expr)
+            }
             Expr::Binary { left, right, op } => {
                 (*op == BinaryOperator::UniformRand || *op == BinaryOperator::NormalRand)
                     || module.has_side_effect(*left)
@@ -917,6 +1717,7 @@ impl Expr {
                     || module.has_side_effect(*third)
             }
             Expr::Cast(expr) => module.has_side_effect(expr.inner),
+            Expr::Swizzle(expr) => module.has_side_effect(expr.inner),
             Expr::TextureSample(_) => false,
         }
     }
@@ -944,12 +1745,13 @@ impl Expr {
         match self {
             Expr::BuiltIn(expr) => Some(expr.value_type()),
             Expr::Literal(expr) => Some(expr.value_type()),
-            Expr::Property(_) => None,
-            Expr::Attribute(expr) => Some(expr.value_type()),
+            Expr::Property(_) | Expr::PropertyIndex(_) => None,
+            Expr::Attribute(expr) | Expr::PrevAttribute(expr) => Some(expr.value_type()),
             Expr::Unary { .. } => None,
             Expr::Binary { .. } => None,
             Expr::Ternary { .. } => None,
             Expr::Cast(expr) => Some(expr.value_type()),
+            Expr::Swizzle(_) => None,
             Expr::TextureSample(expr) => Some(expr.value_type()),
         }
     }
@@ -984,10 +1786,22 @@ impl Expr {
             Expr::BuiltIn(expr) => expr.eval(context),
             Expr::Literal(expr) => expr.eval(context),
             Expr::Property(expr) => expr.eval(module, context),
+            Expr::PropertyIndex(expr) => expr.eval(module, context),
             Expr::Attribute(expr) => expr.eval(context),
+            Expr::PrevAttribute(expr) => expr.eval_prev(context),
             Expr::Unary { op, expr } => {
+                // Swizzles need their operand in parentheses if it's an infix binary
+                // operation, like `(a + b).x`, otherwise they apply to the right operand only.
+                let needs_parens = !op.is_functional()
+                    && matches!(module.get(*expr), Some(Expr::Binary { op, .. }) if !op.is_functional());
+
                 // Recursively evaluate child expressions throught the context to ensure caching
                 let expr = context.eval(module, *expr)?;
+                let expr = if needs_parens {
+                    format!("({})", expr)
+                } else {
+                    expr
+                };
 
                 // if expr.value_type() != self.value_type() {
                 //     return Err(ExprError::TypeError(format!(
@@ -1101,6 +1915,12 @@ impl Expr {
 
                 Ok(format!("{}({})", expr.target.to_wgsl_string(), inner))
             }
+            Expr::Swizzle(expr) => {
+                // Recursively evaluate child expressions throught the context to ensure caching
+                let inner = context.eval(module, expr.inner)?;
+
+                Ok(format!("({}).{}", inner, expr.mask()))
+            }
             Expr::TextureSample(expr) => expr.eval(module, context),
         }
     }
@@ -1199,6 +2019,21 @@ impl AttributeExpr {
             Ok(format!("particle.{}", self.attr.name()))
         }
     }
+
+    /// Evaluate the expression as the value of the attribute at the previous
+    /// simulation update.
+    ///
+    /// This is only valid in the [`ModifierContext::Update`] context, where a
+    /// snapshot of the particle is taken before any update code runs.
+    pub fn eval_prev(&self, context: &dyn EvalContext) -> Result<String, ExprError> {
+        if context.modifier_context() != ModifierContext::Update {
+            return Err(ExprError::InvalidModifierContext(
+                context.modifier_context(),
+                ModifierContext::Update,
+            ));
+        }
+        Ok(format!("prev_particle.{}", self.attr.name()))
+    }
 }
 
 impl ToWgslString for AttributeExpr {
@@ -1255,11 +2090,99 @@ impl PropertyExpr {
                 prop.name()
             )));
         }
+        if prop.is_array() {
+            return Err(ExprError::PropertyError(format!(
+                "Array property '{}' must be indexed with Module::prop_index().",
+                prop.name()
+            )));
+        }
 
         Ok(format!("properties.{}", prop.name()))
     }
 }
 
+/// Expression representing the value of an element of an array property.
+///
+/// The index is an arbitrary expression of integer type. If it's a literal, the
+/// index is checked against the length of the array when the shader is
+/// generated. Otherwise, WGSL clamps at runtime any out-of-bounds index into
+/// the bounds of the array.
+///
+/// To create an array property to reference with an expression, use
+/// [`Module::add_array_property()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct PropertyIndexExpr {
+    /// The array property.
+    pub property: PropertyHandle,
+    /// The index of the element to read.
+    pub index: ExprHandle,
+}
+
+impl PropertyIndexExpr {
+    /// Create a new array property element expression.
+    #[inline]
+    pub fn new(property: PropertyHandle, index: ExprHandle) -> Self {
+        Self { property, index }
+    }
+
+    /// Evaluate the expression in the given context.
+    fn eval(&self, module: &Module, context: &mut dyn EvalContext) -> Result<String, ExprError> {
+        let prop = module
+            .get_property(self.property)
+            .ok_or(ExprError::PropertyError(format!(
+                "Unknown property handle {:?} in evaluation module.",
+                self.property
+            )))?;
+        if !context.property_layout().contains(prop.name()) {
+            return Err(ExprError::PropertyError(format!(
+                "Unknown property '{}' in evaluation layout.",
+                prop.name()
+            )));
+        }
+        let Some(len) = prop.array_len() else {
+            return Err(ExprError::PropertyError(format!(
+                "Property '{}' is not an array and cannot be indexed.",
+                prop.name()
+            )));
+        };
+
+        let index = module.try_get(self.index)?;
+        if let Expr::Literal(LiteralExpr {
+            value: Value::Scalar(value),
+        }) = index
+        {
+            let in_bounds = match *value {
+                ScalarValue::Int(i) => i >= 0 && (i as u32) < len,
+                ScalarValue::Uint(i) => i < len,
+                _ => true, // type checked below
+            };
+            if !in_bounds {
+                return Err(ExprError::PropertyError(format!(
+                    "Index {} out of bounds for array property '{}' of length {}.",
+                    value.to_wgsl_string(),
+                    prop.name(),
+                    len
+                )));
+            }
+        }
+        if let Some(value_type) = index.value_type() {
+            if !matches!(
+                value_type,
+                ValueType::Scalar(ScalarType::Int) | ValueType::Scalar(ScalarType::Uint)
+            ) {
+                return Err(ExprError::TypeError(format!(
+                    "Index of array property '{}' must be an integer, got {:?}.",
+                    prop.name(),
+                    value_type
+                )));
+            }
+        }
+
+        let index = context.eval(module, self.index)?;
+        Ok(format!("properties.{}[{}]", prop.name(), index))
+    }
+}
+
 /// Expression to cast an expression to another type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct CastExpr {
@@ -1319,6 +2242,87 @@ impl CastExpr {
     }
 }
 
+/// Expression to swizzle the components of a vector.
+///
+/// The swizzle mask selects 1 to 4 components of the `inner` vector, in any
+/// order and possibly repeated, like `"xy"`, `"zyx"`, or `"xxxx"`. The result
+/// is a vector with as many components as the mask, or a scalar for a
+/// single-component mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SwizzleExpr {
+    /// The operand expression being swizzled.
+    pub inner: ExprHandle,
+    /// Component indices in \[0:3\]. Only the first `count` ones are used.
+    components: [u8; 4],
+    /// Number of components of the mask, in \[1:4\].
+    count: u8,
+}
+
+impl SwizzleExpr {
+    /// Create a new swizzle expression.
+    ///
+    /// Returns an [`ExprError::SyntaxError`] if `mask` is empty, is longer than
+    /// 4 components, or contains a character other than `x`, `y`, `z`, or `w`.
+    pub fn new(inner: ExprHandle, mask: &str) -> Result<Self, ExprError> {
+        let (components, count) = Self::parse_mask(mask)?;
+        Ok(Self {
+            inner,
+            components,
+            count,
+        })
+    }
+
+    /// Parse a swizzle mask into its component indices and component count.
+    pub(crate) fn parse_mask(mask: &str) -> Result<([u8; 4], u8), ExprError> {
+        if mask.is_empty() || mask.len() > 4 {
+            return Err(ExprError::SyntaxError(format!(
+                "Invalid swizzle mask '{}': expected 1 to 4 components.",
+                mask
+            )));
+        }
+        let mut components = [0; 4];
+        for (index, c) in mask.chars().enumerate() {
+            components[index] = match c {
+                'x' => 0,
+                'y' => 1,
+                'z' => 2,
+                'w' => 3,
+                _ => {
+                    return Err(ExprError::SyntaxError(format!(
+                        "Invalid swizzle mask '{}': unknown component '{}'.",
+                        mask, c
+                    )))
+                }
+            };
+        }
+        Ok((components, mask.len() as u8))
+    }
+
+    /// Number of components of the swizzle mask, in \[1:4\].
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// Highest component index referenced by the mask, in \[0:3\].
+    ///
+    /// The `inner` vector must have strictly more components than this index.
+    pub fn max_component(&self) -> u8 {
+        self.components[..self.count as usize]
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get the swizzle mask as a string, like `"xy"`.
+    pub fn mask(&self) -> String {
+        self.components[..self.count as usize]
+            .iter()
+            .map(|&index| ['x', 'y', 'z', 'w'][index as usize])
+            .collect()
+    }
+}
+
 /// Expression to sample a texture from the effect's material.
 ///
 /// This currently supports only color textures, that is all textures which
@@ -1371,8 +2375,18 @@ impl TextureSampleExpr {
         module: &Module,
         context: &mut dyn EvalContext,
     ) -> Result<String, ExprError> {
-        let image = module.try_get(self.image)?;
-        let image = image.eval(module, context)?;
+        // The texture and sampler bindings are named after the slot index, so
+        // the image must be known when generating the shader.
+        let image = match module.try_get(self.image)? {
+            Expr::Literal(LiteralExpr {
+                value: Value::Scalar(ScalarValue::Uint(index)),
+            }) => *index,
+            _ => {
+                return Err(ExprError::TypeError(
+                    "Texture sample image must be a u32 literal texture slot index.".to_string(),
+                ))
+            }
+        };
         let coordinates = module.try_get(self.coordinates)?;
         let coordinates = coordinates.eval(module, context)?;
         Ok(format!(
@@ -1415,6 +2429,18 @@ pub enum BuiltInOperator {
     ///
     /// Type: `f32`
     RealDeltaTime,
+    /// Time of day, from the global [`EffectGlobals`] resource.
+    ///
+    /// Type: `f32`
+    ///
+    /// [`EffectGlobals`]: crate::EffectGlobals
+    TimeOfDay,
+    /// Weather intensity, from the global [`EffectGlobals`] resource.
+    ///
+    /// Type: `f32`
+    ///
+    /// [`EffectGlobals`]: crate::EffectGlobals
+    Weather,
     /// Random unit value of the given type.
     ///
     /// The type can be any scalar or vector type. Matrix types are not
@@ -1467,6 +2493,39 @@ pub enum BuiltInOperator {
     ///
     /// Type: `bool`
     IsAlive,
+    /// Index of the particle in the particle buffer of the effect.
+    ///
+    /// This is the index of the slot the particle occupies in the GPU buffer
+    /// storing all particles. It's stable for the entire lifetime of the
+    /// particle, and unique among all the particles alive at the same time in
+    /// the effect, so it can be used to derive some deterministic per-particle
+    /// patterns over the whole live set. However, the indices of the alive
+    /// particles are generally not contiguous, and when a particle dies its
+    /// slot is recycled, so the same index is eventually reused by a newly
+    /// spawned particle, possibly as soon as the next frame.
+    ///
+    /// This value is only available in the update pass and in the vertex
+    /// shader of the render pass, which is where the code of most render
+    /// modifiers is emitted. Attempting to use it inside the init pass or from
+    /// some fragment shader code will generate an invalid shader.
+    ///
+    /// Type: `u32`
+    ParticleIndex,
+    /// View-space depth of the particle.
+    ///
+    /// This is the distance of the particle position to the camera, measured
+    /// along the view direction, in world units. It's positive for particles in
+    /// front of the camera. The depth is evaluated from the particle's
+    /// [`Attribute::POSITION`] and the view of the camera currently rendering
+    /// the effect.
+    ///
+    /// This value is only available in the vertex shader of the render pass,
+    /// which is where the code of most render modifiers is emitted. Attempting
+    /// to use it inside either the init or update passes, or from some
+    /// fragment shader code, will generate an invalid shader.
+    ///
+    /// Type: `f32`
+    ViewDepth,
 }
 
 impl BuiltInOperator {
@@ -1479,6 +2538,8 @@ impl BuiltInOperator {
             BuiltInOperator::VirtualDeltaTime => "virtual_delta_time",
             BuiltInOperator::RealTime => "real_time",
             BuiltInOperator::RealDeltaTime => "real_delta_time",
+            BuiltInOperator::TimeOfDay => "time_of_day",
+            BuiltInOperator::Weather => "weather",
             BuiltInOperator::Rand(value_type) => match value_type {
                 ValueType::Scalar(s) => match s {
                     ScalarType::Bool => "brand",
@@ -1507,6 +2568,8 @@ impl BuiltInOperator {
             },
             BuiltInOperator::AlphaCutoff => "alpha_cutoff",
             BuiltInOperator::IsAlive => "is_alive",
+            BuiltInOperator::ParticleIndex => "particle_index",
+            BuiltInOperator::ViewDepth => "view_depth",
         }
     }
 
@@ -1519,9 +2582,13 @@ impl BuiltInOperator {
             BuiltInOperator::VirtualDeltaTime => ValueType::Scalar(ScalarType::Float),
             BuiltInOperator::RealTime => ValueType::Scalar(ScalarType::Float),
             BuiltInOperator::RealDeltaTime => ValueType::Scalar(ScalarType::Float),
+            BuiltInOperator::TimeOfDay => ValueType::Scalar(ScalarType::Float),
+            BuiltInOperator::Weather => ValueType::Scalar(ScalarType::Float),
             BuiltInOperator::Rand(value_type) => *value_type,
             BuiltInOperator::AlphaCutoff => ValueType::Scalar(ScalarType::Float),
             BuiltInOperator::IsAlive => ValueType::Scalar(ScalarType::Bool),
+            BuiltInOperator::ParticleIndex => ValueType::Scalar(ScalarType::Uint),
+            BuiltInOperator::ViewDepth => ValueType::Scalar(ScalarType::Float),
         }
     }
 
@@ -1538,6 +2605,12 @@ impl ToWgslString for BuiltInOperator {
         match self {
             BuiltInOperator::Rand(_) => format!("{}()", self.name()),
             BuiltInOperator::IsAlive => "is_alive".to_string(),
+            // Local variable of the update and render shaders
+            BuiltInOperator::ParticleIndex => "index".to_string(),
+            // Helper function of the render shader
+            BuiltInOperator::ViewDepth => {
+                format!("get_view_depth(particle.{})", Attribute::POSITION.name())
+            }
             _ => format!("sim_params.{}", self.name()),
         }
     }
@@ -1620,6 +2693,13 @@ pub enum UnaryOperator {
     /// Only valid for numeric operands.
     Abs,
 
+    /// Inverse cosine operator.
+    ///
+    /// Return the arc cosine of the operand, in radians, component-wise for
+    /// vectors. The operand must be in `[-1:1]`, otherwise the result is
+    /// `NaN`.
+    Acos,
+
     /// Logical ALL operator for bool vectors.
     ///
     /// Return `true` if all the components of the bool vector operand are
@@ -1681,6 +2761,14 @@ pub enum UnaryOperator {
     /// The output is always a floating point scalar.
     Length,
 
+    /// Linear to sRGB color conversion operator.
+    ///
+    /// Convert the RGB channels of a `vec4<f32>` color from linear space to
+    /// sRGB space, using the piecewise sRGB transfer function. The alpha
+    /// channel is passed through unchanged. Only valid for `vec4<f32>`
+    /// operands.
+    LinearToSrgb,
+
     /// Natural logarithm operator.
     ///
     /// Return the natural logarithm of the operand (`log(x)`), component-wise
@@ -1693,6 +2781,12 @@ pub enum UnaryOperator {
     /// for vectors.
     Log2,
 
+    /// Negation operator.
+    ///
+    /// Return the opposite of the operand (`-x`), component-wise for vectors.
+    /// Only valid for signed numeric operands.
+    Neg,
+
     /// Vector normalizing operator.
     ///
     /// Normalize the given numeric vector. Only valid for numeric vector
@@ -1715,12 +2809,35 @@ pub enum UnaryOperator {
     /// be in `[0:1]` before packing; values outside this range are clamped.
     Pack4x8unorm,
 
+    /// Rounding operator.
+    ///
+    /// Return the integral number `k` nearest to the operand, component-wise
+    /// for vectors. When the operand lies halfway between `k` and `k+1`, the
+    /// result is the even number among them.
+    Round,
+
     /// Saturate operator.
     ///
     /// Clamp the value of the operand to the \[0:1\] range, component-wise for
     /// vectors.
     Saturate,
 
+    /// Seeded random number operator.
+    ///
+    /// Return a random `f32` value uniformly distributed in `[0:1]`, drawn
+    /// from the independent random stream identified by the `u32` operand,
+    /// the stream seed. Two draws with different stream seeds are
+    /// decorrelated, even when made by the same particle in the same pass.
+    ///
+    /// Like [`BuiltInOperator::Rand`], each draw advances the per-particle
+    /// random state, which is itself seeded from the per-effect seed of the
+    /// [`Spawner`] and the particle index. The stream seed is hashed and mixed
+    /// into that state, so that the value drawn differs from the one a
+    /// [`BuiltInOperator::Rand`] draw would have produced.
+    ///
+    /// [`Spawner`]: crate::Spawner
+    SeededRand,
+
     /// Sign operator.
     ///
     /// Return a value representing the sign of a floating point scalar or
@@ -1741,6 +2858,14 @@ pub enum UnaryOperator {
     /// vectors.
     Sqrt,
 
+    /// sRGB to linear color conversion operator.
+    ///
+    /// Convert the RGB channels of a `vec4<f32>` color from sRGB space to
+    /// linear space, using the piecewise sRGB transfer function. The alpha
+    /// channel is passed through unchanged. Only valid for `vec4<f32>`
+    /// operands.
+    SrgbToLinear,
+
     /// Tangent operator.
     Tan,
 
@@ -1796,6 +2921,7 @@ impl ToWgslString for UnaryOperator {
     fn to_wgsl_string(&self) -> String {
         match *self {
             UnaryOperator::Abs => "abs".to_string(),
+            UnaryOperator::Acos => "acos".to_string(),
             UnaryOperator::All => "all".to_string(),
             UnaryOperator::Any => "any".to_string(),
             UnaryOperator::Ceil => "ceil".to_string(),
@@ -1806,15 +2932,20 @@ impl ToWgslString for UnaryOperator {
             UnaryOperator::Fract => "fract".to_string(),
             UnaryOperator::InvSqrt => "inverseSqrt".to_string(),
             UnaryOperator::Length => "length".to_string(),
+            UnaryOperator::LinearToSrgb => "linear_to_srgb".to_string(),
             UnaryOperator::Log => "log".to_string(),
             UnaryOperator::Log2 => "log2".to_string(),
+            UnaryOperator::Neg => "-".to_string(),
             UnaryOperator::Normalize => "normalize".to_string(),
             UnaryOperator::Pack4x8snorm => "pack4x8snorm".to_string(),
             UnaryOperator::Pack4x8unorm => "pack4x8unorm".to_string(),
+            UnaryOperator::Round => "round".to_string(),
             UnaryOperator::Saturate => "saturate".to_string(),
+            UnaryOperator::SeededRand => "frand_seeded".to_string(),
             UnaryOperator::Sign => "sign".to_string(),
             UnaryOperator::Sin => "sin".to_string(),
             UnaryOperator::Sqrt => "sqrt".to_string(),
+            UnaryOperator::SrgbToLinear => "srgb_to_linear".to_string(),
             UnaryOperator::Tan => "tan".to_string(),
             UnaryOperator::Unpack4x8snorm => "unpack4x8snorm".to_string(),
             UnaryOperator::Unpack4x8unorm => "unpack4x8unorm".to_string(),
@@ -1838,6 +2969,15 @@ pub enum BinaryOperator {
     /// Returns the sum of its operands. Only valid for numeric operands.
     Add,
 
+    /// Four-quadrant arctangent operator.
+    ///
+    /// Returns the angle in radians, in `[-π:π]`, whose tangent is the left
+    /// operand divided by the right one, that is `atan2(y, x)` with the left
+    /// operand `y` and the right operand `x`. Only valid for floating point
+    /// scalar or vectors of the same rank, and applied component-wise for
+    /// vectors. The result is undefined if both operands are zero.
+    Atan2,
+
     /// Cross product operator.
     ///
     /// Returns the cross product of the left and right operands. Only valid for
@@ -1863,6 +3003,13 @@ pub enum BinaryOperator {
     /// vector type operands. Always produce a scalar floating-point result.
     Dot,
 
+    /// Equality operator.
+    ///
+    /// Returns `true` if the left operand is equal to the right operand. If
+    /// the operands are vectors, they must be of the same rank, and the result
+    /// is a bool vector of that rank.
+    Equal,
+
     /// Greater-than operator.
     ///
     /// Returns `true` if the left operand is strictly greater than the right
@@ -1916,6 +3063,13 @@ pub enum BinaryOperator {
     /// Returns the product of its operands. Only valid for numeric operands.
     Mul,
 
+    /// Power operator.
+    ///
+    /// Returns the left operand raised to the power of the right operand. Only
+    /// valid for floating scalar or vectors of the same rank, and applied
+    /// component-wise for vectors.
+    Pow,
+
     /// Remainder operator.
     ///
     /// Returns the remainder of the division of the first operand by the
@@ -1964,6 +3118,12 @@ pub enum BinaryOperator {
     /// Given two scalar elements `x` and `y`, returns the vector consisting of
     /// those two elements `(x, y)`.
     Vec2,
+
+    /// Constructor for 4-element vectors.
+    ///
+    /// Given a 3-element vector `xyz` and a scalar element `w`, returns the
+    /// vector consisting of those four elements `(xyz.x, xyz.y, xyz.z, w)`.
+    Vec4,
 }
 
 impl BinaryOperator {
@@ -1977,6 +3137,7 @@ impl BinaryOperator {
         match *self {
             BinaryOperator::Add
             | BinaryOperator::Div
+            | BinaryOperator::Equal
             | BinaryOperator::GreaterThan
             | BinaryOperator::GreaterThanOrEqual
             | BinaryOperator::LessThan
@@ -1984,15 +3145,18 @@ impl BinaryOperator {
             | BinaryOperator::Mul
             | BinaryOperator::Remainder
             | BinaryOperator::Sub => false,
-            BinaryOperator::Cross
+            BinaryOperator::Atan2
+            | BinaryOperator::Cross
             | BinaryOperator::Distance
             | BinaryOperator::Dot
             | BinaryOperator::Max
             | BinaryOperator::Min
+            | BinaryOperator::Pow
             | BinaryOperator::Step
             | BinaryOperator::UniformRand
             | BinaryOperator::NormalRand
-            | BinaryOperator::Vec2 => true,
+            | BinaryOperator::Vec2
+            | BinaryOperator::Vec4 => true,
         }
     }
 
@@ -2015,10 +3179,12 @@ impl ToWgslString for BinaryOperator {
     fn to_wgsl_string(&self) -> String {
         match *self {
             BinaryOperator::Add => "+".to_string(),
+            BinaryOperator::Atan2 => "atan2".to_string(),
             BinaryOperator::Cross => "cross".to_string(),
             BinaryOperator::Distance => "distance".to_string(),
             BinaryOperator::Div => "/".to_string(),
             BinaryOperator::Dot => "dot".to_string(),
+            BinaryOperator::Equal => "==".to_string(),
             BinaryOperator::GreaterThan => ">".to_string(),
             BinaryOperator::GreaterThanOrEqual => ">=".to_string(),
             BinaryOperator::LessThan => "<".to_string(),
@@ -2026,12 +3192,14 @@ impl ToWgslString for BinaryOperator {
             BinaryOperator::Max => "max".to_string(),
             BinaryOperator::Min => "min".to_string(),
             BinaryOperator::Mul => "*".to_string(),
+            BinaryOperator::Pow => "pow".to_string(),
             BinaryOperator::Remainder => "%".to_string(),
             BinaryOperator::Step => "step".to_string(),
             BinaryOperator::Sub => "-".to_string(),
             BinaryOperator::UniformRand => "rand_uniform".to_string(),
             BinaryOperator::NormalRand => "rand_normal".to_string(),
             BinaryOperator::Vec2 => "vec2".to_string(),
+            BinaryOperator::Vec4 => "vec4".to_string(),
         }
     }
 }
@@ -2043,6 +3211,16 @@ impl ToWgslString for BinaryOperator {
 /// operator itself.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum TernaryOperator {
+    /// Clamping operator.
+    ///
+    /// Returns the first argument clamped between the second (lower bound) and
+    /// third (upper bound) arguments, component-wise for vectors. If the
+    /// operands are vectors, they must be of the same rank.
+    ///
+    /// The clamping of `x` between `low` and `high` is equivalent to
+    /// `min(max(x, low), high)`.
+    Clamp,
+
     /// Linear blend ("mix") operator.
     ///
     /// Returns the linear blend between the first and second argument, based on
@@ -2054,6 +3232,17 @@ pub enum TernaryOperator {
     /// (1 - t) + y * t`.
     Mix,
 
+    /// Selection operator.
+    ///
+    /// Returns the second argument if the third argument (the condition) is
+    /// `true`, or the first argument otherwise. If the operands are vectors,
+    /// the selection is applied component-wise, and the condition can be
+    /// either a scalar boolean or a boolean vector of the same rank.
+    ///
+    /// The selection of `t` or `f` based on `cond` is equivalent to `if cond {
+    /// t } else { f }`. Note that all arguments are always evaluated.
+    Select,
+
     /// Smooth stepping operator.
     ///
     /// Returns the smooth Hermitian interpolation between the first and second
@@ -2073,14 +3262,23 @@ pub enum TernaryOperator {
     /// Given three scalar elements `x`, `y`, and `z`, returns the vector
     /// consisting of those three elements `(x, y, z)`.
     Vec3,
+
+    /// Constructor for 3x3 float matrices.
+    ///
+    /// Given three `vec3<f32>` vectors, returns the `mat3x3<f32>` matrix
+    /// having those vectors as its three columns, in order.
+    Mat3,
 }
 
 impl ToWgslString for TernaryOperator {
     fn to_wgsl_string(&self) -> String {
         match *self {
+            TernaryOperator::Clamp => "clamp".to_string(),
             TernaryOperator::Mix => "mix".to_string(),
+            TernaryOperator::Select => "select".to_string(),
             TernaryOperator::SmoothStep => "smoothstep".to_string(),
             TernaryOperator::Vec3 => "vec3".to_string(),
+            TernaryOperator::Mat3 => "mat3x3<f32>".to_string(),
         }
     }
 }
@@ -2207,6 +3405,22 @@ impl ExprWriter {
         self.push(Expr::Attribute(AttributeExpr::new(attr)))
     }
 
+    /// Create a new writer expression from the value of an attribute at the
+    /// previous simulation update.
+    ///
+    /// See [`Expr::PrevAttribute`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// let x = w.prev_attr(Attribute::POSITION); // x = prev_particle.position;
+    /// ```
+    pub fn prev_attr(&self, attr: Attribute) -> WriterExpr {
+        self.push(Expr::PrevAttribute(AttributeExpr::new(attr)))
+    }
+
     /// Create a new writer expression from a property.
     ///
     /// # Example
@@ -2248,6 +3462,41 @@ impl ExprWriter {
         self.push(Expr::BuiltIn(BuiltInExpr::new(BuiltInOperator::DeltaTime)))
     }
 
+    /// Create a new writer expression representing the global time of day.
+    ///
+    /// See [`EffectGlobals`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// let x = w.time_of_day(); // x = sim_params.time_of_day;
+    /// ```
+    ///
+    /// [`EffectGlobals`]: crate::EffectGlobals
+    pub fn time_of_day(&self) -> WriterExpr {
+        self.push(Expr::BuiltIn(BuiltInExpr::new(BuiltInOperator::TimeOfDay)))
+    }
+
+    /// Create a new writer expression representing the global weather
+    /// intensity.
+    ///
+    /// See [`EffectGlobals`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// let x = w.weather(); // x = sim_params.weather;
+    /// ```
+    ///
+    /// [`EffectGlobals`]: crate::EffectGlobals
+    pub fn weather(&self) -> WriterExpr {
+        self.push(Expr::BuiltIn(BuiltInExpr::new(BuiltInOperator::Weather)))
+    }
+
     /// Create a new writer expression representing a random value of the given
     /// type.
     ///
@@ -2393,6 +3642,31 @@ impl WriterExpr {
         self.unary_op(UnaryOperator::Abs)
     }
 
+    /// Apply the "acos" operator to the current float scalar or vector
+    /// expression.
+    ///
+    /// This is a unary operator, which applies to float scalar or vector
+    /// operand expressions to produce a float scalar or vector. It applies
+    /// component-wise to vector operand expressions. The operand must be in
+    /// `[-1:1]`, otherwise the result is `NaN`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(1., 0., -1.);`.
+    /// let x = w.lit(Vec3::new(1., 0., -1.));
+    ///
+    /// // Acos: `y = acos(x);`
+    /// let y = x.acos();
+    /// ```
+    #[inline]
+    pub fn acos(self) -> Self {
+        self.unary_op(UnaryOperator::Acos)
+    }
+
     /// Apply the logical operator "all" to the current bool vector expression.
     ///
     /// This is a unary operator, which applies to vector operand expressions to
@@ -2677,6 +3951,31 @@ impl WriterExpr {
         self.unary_op(UnaryOperator::Log2)
     }
 
+    /// Apply the negation operator to the current scalar or vector expression.
+    ///
+    /// This is a unary operator, which applies to signed numeric scalar or
+    /// vector operand expressions to produce a value of the same type. It
+    /// applies component-wise to vector operand expressions. This is also
+    /// available with the `-` operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(1., 1., 1.);`.
+    /// let x = w.lit(Vec3::ONE);
+    ///
+    /// // Neg: `y = -(x);`
+    /// let y = x.neg();
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn neg(self) -> Self {
+        self.unary_op(UnaryOperator::Neg)
+    }
+
     /// Apply the "normalize" operator to the current float vector expression.
     ///
     /// This is a unary operator, which applies to float vector operand
@@ -2890,6 +4189,30 @@ impl WriterExpr {
         self.unary_op(UnaryOperator::Unpack4x8unorm)
     }
 
+    /// Apply the "round" operator to the current float scalar or vector
+    /// expression.
+    ///
+    /// This is a unary operator, which applies to float scalar or vector
+    /// operand expressions to produce a float scalar or vector. It applies
+    /// component-wise to vector operand expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(1., 1., 1.);`.
+    /// let x = w.lit(Vec3::ONE);
+    ///
+    /// // Round: `y = round(x);`
+    /// let y = x.round();
+    /// ```
+    #[inline]
+    pub fn round(self) -> Self {
+        self.unary_op(UnaryOperator::Round)
+    }
+
     /// Apply the "saturate" operator to the current float scalar or vector
     /// expression.
     ///
@@ -2901,17 +4224,63 @@ impl WriterExpr {
     ///
     /// ```
     /// # use bevy_hanabi::*;
-    /// # use bevy::math::Vec3;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(1., 1., 1.);`.
+    /// let x = w.lit(Vec3::ONE);
+    ///
+    /// // Saturate: `y = saturate(x);`
+    /// let y = x.saturate();
+    /// ```
+    #[inline]
+    pub fn saturate(self) -> Self {
+        self.unary_op(UnaryOperator::Saturate)
+    }
+
+    /// Convert the current `vec4<f32>` color expression from sRGB to linear
+    /// space.
+    ///
+    /// This uses the piecewise sRGB transfer function on the RGB channels, and
+    /// passes the alpha channel through unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec4;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec4<f32>(0.5, 0.5, 0.5, 1.);`.
+    /// let x = w.lit(Vec4::new(0.5, 0.5, 0.5, 1.));
+    ///
+    /// // Convert: `y = srgb_to_linear(x);`
+    /// let y = x.srgb_to_linear();
+    /// ```
+    #[inline]
+    pub fn srgb_to_linear(self) -> Self {
+        self.unary_op(UnaryOperator::SrgbToLinear)
+    }
+
+    /// Convert the current `vec4<f32>` color expression from linear to sRGB
+    /// space.
+    ///
+    /// This uses the piecewise sRGB transfer function on the RGB channels, and
+    /// passes the alpha channel through unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec4;
     /// # let mut w = ExprWriter::new();
-    /// // A literal expression `x = vec3<f32>(1., 1., 1.);`.
-    /// let x = w.lit(Vec3::ONE);
+    /// // A literal expression `x = vec4<f32>(0.5, 0.5, 0.5, 1.);`.
+    /// let x = w.lit(Vec4::new(0.5, 0.5, 0.5, 1.));
     ///
-    /// // Saturate: `y = saturate(x);`
-    /// let y = x.saturate();
+    /// // Convert: `y = linear_to_srgb(x);`
+    /// let y = x.linear_to_srgb();
     /// ```
     #[inline]
-    pub fn saturate(self) -> Self {
-        self.unary_op(UnaryOperator::Saturate)
+    pub fn linear_to_srgb(self) -> Self {
+        self.unary_op(UnaryOperator::LinearToSrgb)
     }
 
     /// Get the first component of a scalar or vector.
@@ -3087,6 +4456,30 @@ impl WriterExpr {
         self.binary_op(other, BinaryOperator::Dot)
     }
 
+    /// Calculate the four-quadrant arctangent of the current expression divided
+    /// by another expression.
+    ///
+    /// This is a binary operator, which returns `atan2(self, x)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `y = 1.;`.
+    /// let y = w.lit(1.);
+    ///
+    /// // Another literal expression `x = -1.;`.
+    /// let x = w.lit(-1.);
+    ///
+    /// // The angle of the (x, y) vector `z = atan2(y, x);`.
+    /// let z = y.atan2(x);
+    /// ```
+    #[inline]
+    pub fn atan2(self, x: Self) -> Self {
+        self.binary_op(x, BinaryOperator::Atan2)
+    }
+
     /// Calculate the distance between the current expression and another
     /// expression.
     ///
@@ -3328,6 +4721,31 @@ impl WriterExpr {
         self.binary_op(other, BinaryOperator::Mul)
     }
 
+    /// Raise the current expression to the power of another expression.
+    ///
+    /// This is a binary operator, which applies component-wise to vector
+    /// operand expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(3., 2., 1.);`.
+    /// let x = w.lit(Vec3::new(3., 2., 1.));
+    ///
+    /// // Another literal expression `y = vec3<f32>(2., 0.5, 3.);`.
+    /// let y = w.lit(Vec3::new(2., 0.5, 3.));
+    ///
+    /// // The component-wise power `z = pow(x, y);`.
+    /// let z = x.pow(y);
+    /// ```
+    #[inline]
+    pub fn pow(self, exponent: Self) -> Self {
+        self.binary_op(exponent, BinaryOperator::Pow)
+    }
+
     /// Calculate the remainder of the division of the current expression by
     /// another expression.
     ///
@@ -3487,6 +4905,32 @@ impl WriterExpr {
         }
     }
 
+    /// Clamp the current expression between a lower and an upper bound.
+    ///
+    /// This is a ternary operator, which applies component-wise to vector
+    /// operand expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec2;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec2<f32>(3., -2.);`.
+    /// let x = w.lit(Vec2::new(3., -2.));
+    ///
+    /// // The bounds `low = vec2<f32>(0., 0.);` and `high = vec2<f32>(1., 1.);`.
+    /// let low = w.lit(Vec2::ZERO);
+    /// let high = w.lit(Vec2::ONE);
+    ///
+    /// // The clamped value: `y = clamp(x, low, high);`
+    /// let y = x.clamp(low, high); // == vec2<f32>(1., 0.)
+    /// ```
+    #[inline]
+    pub fn clamp(self, low: Self, high: Self) -> Self {
+        self.ternary_op(low, high, TernaryOperator::Clamp)
+    }
+
     /// Blending linearly ("mix") two expressions with the fraction provided by
     /// a third expression.
     ///
@@ -3516,6 +4960,29 @@ impl WriterExpr {
         self.ternary_op(other, fraction, TernaryOperator::Mix)
     }
 
+    /// Select between two expressions based on the current boolean expression.
+    ///
+    /// This is a ternary operator, which applies component-wise to vector
+    /// operand expressions. Both `if_true` and `if_false` are always
+    /// evaluated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let mut w = ExprWriter::new();
+    /// // A condition `cond = age > 1.;`.
+    /// let cond = w.attr(Attribute::AGE).gt(w.lit(1.));
+    ///
+    /// // The selected value: `y = select(0., 2., cond);`
+    /// let y = cond.select(w.lit(2.), w.lit(0.));
+    /// ```
+    #[inline]
+    pub fn select(self, if_true: Self, if_false: Self) -> Self {
+        // WGSL's select() takes the false value first, and the condition last
+        if_false.ternary_op(if_true, self, TernaryOperator::Select)
+    }
+
     /// Calculate the smooth Hermite interpolation in \[0:1\] of the current
     /// value taken between the given bounds.
     ///
@@ -3546,6 +5013,40 @@ impl WriterExpr {
         low.ternary_op(high, self, TernaryOperator::SmoothStep)
     }
 
+    /// Calculate a radial falloff of the current position around a center.
+    ///
+    /// The falloff is `1.0` within the `inner` radius of `center`, `0.0`
+    /// beyond the `outer` radius, and smoothly interpolated in between with a
+    /// Hermite curve. The result is then raised to the power `curve`, which
+    /// sharpens (`curve > 1`) or softens (`curve < 1`) the transition.
+    ///
+    /// The generated expression is:
+    ///
+    /// ```wgsl
+    /// pow(1. - smoothstep(inner, outer, distance(self, center)), curve)
+    /// ```
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// let pos = w.attr(Attribute::POSITION);
+    /// let center = w.lit(Vec3::ZERO);
+    /// // Full strength within 1 unit of the origin, fading out to zero at 3
+    /// // units, with a quadratic falloff.
+    /// let falloff = pos.radial_falloff(center, w.lit(1.), w.lit(3.), w.lit(2.));
+    /// ```
+    pub fn radial_falloff(self, center: Self, inner: Self, outer: Self, curve: Self) -> Self {
+        let one = WriterExpr {
+            expr: self.module.borrow_mut().lit(1.),
+            module: Rc::clone(&self.module),
+        };
+        let t = self.distance(center).smoothstep(inner, outer);
+        (one - t).pow(curve)
+    }
+
     /// Construct a `Vec2` from two scalars.
     ///
     /// # Example
@@ -3580,6 +5081,26 @@ impl WriterExpr {
         self.ternary_op(y, z, TernaryOperator::Vec3)
     }
 
+    /// Construct a `mat3x3<f32>` matrix from three column vectors.
+    ///
+    /// The current expression is the first column of the matrix, and `col1`
+    /// and `col2` are the second and third columns, respectively. All columns
+    /// must be `vec3<f32>` vectors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // Build the identity matrix from the unit axes
+    /// let m = w.lit(Vec3::X).mat3(w.lit(Vec3::Y), w.lit(Vec3::Z));
+    /// ```
+    #[inline]
+    pub fn mat3(self, col1: Self, col2: Self) -> Self {
+        self.ternary_op(col1, col2, TernaryOperator::Mat3)
+    }
+
     /// Cast an expression to a different type.
     ///
     /// # Example
@@ -3670,6 +5191,15 @@ impl std::ops::Rem<WriterExpr> for WriterExpr {
     }
 }
 
+impl std::ops::Neg for WriterExpr {
+    type Output = WriterExpr;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        self.neg()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::{prelude::*, utils::HashSet};
@@ -3677,6 +5207,191 @@ mod tests {
     use super::*;
     use crate::{MatrixType, ScalarValue, ShaderWriter, VectorType};
 
+    #[test]
+    fn graph_stats_from_wgsl() {
+        let stats = |code| GraphStats::from_wgsl(code);
+        assert_eq!(stats(""), GraphStats::default());
+
+        // Literals, constructors, assignments, and swizzles are free
+        assert_eq!(
+            stats("particle.position = vec3<f32>(-1., 2.5e-3, 0.).xzy;\nvar x = -3;"),
+            GraphStats::default()
+        );
+
+        // Operators and built-in calls, including compound and unary operators
+        let s = stats("particle.velocity += normalize(a - b) * -c; // comment * + sin()");
+        assert_eq!(s.alu_ops, 5);
+        assert_eq!(s.transcendentals, 0);
+
+        // Comparisons aren't confused with template argument lists
+        assert_eq!(stats("let b = select(x, y, a < b && c >= d);").alu_ops, 4);
+
+        // Transcendentals and texture samples
+        let s = stats(
+            "fn helper(uv: vec2<f32>) -> vec4<f32> {\n    return textureSample(t, s, uv) * sqrt(uv.x);\n}",
+        );
+        assert_eq!(s.alu_ops, 1);
+        assert_eq!(s.transcendentals, 1);
+        assert_eq!(s.texture_samples, 1);
+
+        // Keywords and pointers
+        assert_eq!(
+            stats("if (is_alive) { (*particle).age = 0.; }"),
+            GraphStats::default()
+        );
+    }
+
+    #[test]
+    fn simplify() {
+        let mut m = Module::default();
+        let x = m.attr(Attribute::POSITION);
+        let zero = m.lit(0.);
+        let one = m.lit(1.);
+        let ones = m.lit(Vec3::ONE);
+        let zeros = m.lit(Vec3::ZERO);
+
+        let mul_one = m.mul(x, one);
+        let one_mul = m.mul(one, x);
+        let mul_ones = m.mul(x, ones);
+        let add_zero = m.add(x, zero);
+        let zero_add = m.add(zeros, x);
+        let sub_zero = m.sub(x, zero);
+        let div_one = m.div(x, one);
+        // Nested identities simplify in a single pass
+        let nested = m.add(mul_one, zeros);
+        // Not an identity
+        let zero_sub = m.sub(zero, x);
+        let two = m.lit(2.);
+        let mul_two = m.mul(x, two);
+
+        assert_eq!(m.simplify(), 8);
+        let x_expr = *m.get(x).unwrap();
+        for simplified in [
+            mul_one, one_mul, mul_ones, add_zero, zero_add, sub_zero, div_one, nested,
+        ] {
+            assert_eq!(*m.get(simplified).unwrap(), x_expr);
+        }
+        assert!(matches!(
+            m.get(zero_sub),
+            Some(Expr::Binary {
+                op: BinaryOperator::Sub,
+                ..
+            })
+        ));
+        assert!(matches!(
+            m.get(mul_two),
+            Some(Expr::Binary {
+                op: BinaryOperator::Mul,
+                ..
+            })
+        ));
+
+        // Nothing left to simplify
+        assert_eq!(m.simplify(), 0);
+    }
+
+    #[test]
+    fn fold_constants() {
+        let mut m = Module::default();
+        let three = m.lit(3.);
+        let two = m.lit(2.);
+        let ones = m.lit(Vec3::ONE);
+        let ithree = m.lit(3i32);
+        let izero = m.lit(0i32);
+        let umax = m.lit(u32::MAX);
+        let uone = m.lit(1u32);
+
+        let add = m.add(three, two);
+        let sub = m.sub(three, two);
+        let div = m.div(three, two);
+        let max = m.max(three, two);
+        // Nested operations fold in a single pass
+        let nested = m.mul(add, sub);
+        // Scalars broadcast to vectors
+        let broadcast = m.mul(two, ones);
+        let imin = m.min(ithree, izero);
+
+        // Not foldable
+        let x = m.attr(Attribute::AGE);
+        let non_lit = m.add(x, two);
+        let div_zero = m.div(ithree, izero);
+        let overflow = m.add(umax, uone);
+        let zero = m.lit(0.);
+        let inf = m.div(three, zero);
+        let mismatch = m.add(three, ithree);
+        let cmp = m.lt(three, two);
+
+        assert_eq!(m.fold_constants(), 7);
+        for (expr, value) in [
+            (add, Value::from(5.)),
+            (sub, 1.0.into()),
+            (div, 1.5.into()),
+            (max, 3.0.into()),
+            (nested, 5.0.into()),
+            (broadcast, Vec3::splat(2.).into()),
+            (imin, 0i32.into()),
+        ] {
+            assert_eq!(
+                *m.get(expr).unwrap(),
+                Expr::Literal(LiteralExpr::new(value))
+            );
+        }
+        for kept in [non_lit, div_zero, overflow, inf, mismatch, cmp] {
+            assert!(matches!(m.get(kept), Some(Expr::Binary { .. })));
+        }
+
+        // Nothing left to fold
+        assert_eq!(m.fold_constants(), 0);
+    }
+
+    #[test]
+    fn simplify_mul_zero() {
+        let mut m = Module::default();
+
+        // Integer values are always finite, so x * 0 == 0
+        let i = m.attr(Attribute::SPRITE_INDEX);
+        let izero = m.lit(0i32);
+        let imul = m.mul(i, izero);
+        let izero_mul = m.mul(izero, i);
+
+        // Finite float literal
+        let three = m.lit(3.);
+        let zero = m.lit(0.);
+        let fmul = m.mul(three, zero);
+
+        // A float which may be infinite or NaN. x * 0 is NaN in that case, so the
+        // operation must be kept as is, to preserve the numeric result.
+        let age = m.attr(Attribute::AGE);
+        let age_mul = m.mul(age, zero);
+        let inf = m.lit(f32::INFINITY);
+        let inf_mul = m.mul(inf, zero);
+        let nan = m.lit(f32::NAN);
+        let nan_mul = m.mul(zero, nan);
+
+        // Multiplying by a zero of another type changes the result type
+        let v = m.attr(Attribute::POSITION);
+        let vmul = m.mul(v, zero);
+
+        // Random values have side effects
+        let rand = m.builtin(BuiltInOperator::Rand(ScalarType::Int.into()));
+        let rand_mul = m.mul(rand, izero);
+
+        assert_eq!(m.simplify(), 3);
+        let izero_expr = *m.get(izero).unwrap();
+        assert_eq!(*m.get(imul).unwrap(), izero_expr);
+        assert_eq!(*m.get(izero_mul).unwrap(), izero_expr);
+        assert_eq!(*m.get(fmul).unwrap(), *m.get(zero).unwrap());
+        for kept in [age_mul, inf_mul, nan_mul, vmul, rand_mul] {
+            assert!(matches!(
+                m.get(kept),
+                Some(Expr::Binary {
+                    op: BinaryOperator::Mul,
+                    ..
+                })
+            ));
+        }
+    }
+
     #[test]
     fn module() {
         let mut m = Module::default();
@@ -3702,6 +5417,53 @@ mod tests {
         assert_eq!(m.try_get_mut(x), Ok(&mut expected));
     }
 
+    #[test]
+    fn module_reuse() {
+        let mut m = Module::default();
+
+        // Side-effect-free expressions are shared
+        let x = m.lit(5.);
+        assert_eq!(m.lit(5.), x);
+        assert_ne!(m.lit(5u32), x);
+        assert_ne!(m.lit(6.), x);
+        let pos = m.attr(Attribute::POSITION);
+        assert_eq!(m.attr(Attribute::POSITION), pos);
+        let time = m.builtin(BuiltInOperator::Time);
+        assert_eq!(m.builtin(BuiltInOperator::Time), time);
+
+        // Random built-ins always produce a new expression
+        let rand_type = ValueType::Scalar(ScalarType::Float);
+        let rand = m.builtin(BuiltInOperator::Rand(rand_type));
+        assert_ne!(m.builtin(BuiltInOperator::Rand(rand_type)), rand);
+
+        // Other expressions are never shared
+        let add = m.add(x, pos);
+        assert_ne!(m.add(x, pos), add);
+
+        // Literals are compared by bit pattern
+        let zero = m.lit(0.);
+        assert_ne!(m.lit(-0.), zero);
+        assert_eq!(m.lit(-0.), m.lit(-0.));
+        assert_eq!(m.lit(Vec3::ZERO), m.lit(Vec3::ZERO));
+        assert_ne!(m.lit(Vec3::ZERO), m.lit(Vec3::new(0., -0., 0.)));
+        assert_eq!(m.lit(true), m.lit(true));
+
+        // A modified expression is not reused for its previous value
+        *m.get_mut(x).unwrap() = Expr::Literal(LiteralExpr::new(7.));
+        let x2 = m.lit(5.);
+        assert_ne!(x2, x);
+        assert_eq!(m.lit(5.), x2);
+
+        // Expressions not added through the reusing helpers are reused too
+        let mut m = Module::from_raw(vec![
+            Expr::Literal(LiteralExpr::new(1.)),
+            Expr::Attribute(AttributeExpr::new(Attribute::AGE)),
+        ]);
+        assert_eq!(m.lit(1.).index(), 0);
+        assert_eq!(m.attr(Attribute::AGE).index(), 1);
+        assert_eq!(m.clone(), m);
+    }
+
     #[test]
     fn local_var() {
         let property_layout = PropertyLayout::default();
@@ -3759,6 +5521,94 @@ mod tests {
         assert!(!m.properties().iter().any(|p| p.name() == "do_not_exist"));
     }
 
+    #[test]
+    fn swizzle() {
+        let mut m = Module::default();
+        let v = m.lit(Vec4::new(1., 2., 3., 4.));
+        let x = m.swizzle(v, "wzx").unwrap();
+        assert!(m.is_const(x));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&m, x).unwrap(),
+            "(vec4<f32>(1.,2.,3.,4.)).wzx".to_string()
+        );
+
+        let Some(Expr::Swizzle(expr)) = m.get(x) else {
+            panic!("Expected a swizzle expression.");
+        };
+        assert_eq!(expr.count(), 3);
+        assert_eq!(expr.max_component(), 3);
+        assert_eq!(expr.mask(), "wzx");
+
+        for mask in ["", "xyzwx", "xa", "XY"] {
+            assert!(matches!(m.swizzle(v, mask), Err(ExprError::SyntaxError(_))));
+        }
+    }
+
+    #[test]
+    fn property_index() {
+        let mut m = Module::default();
+        let prop = m.add_array_property("attractors", Vec3::ZERO.into(), 4);
+        assert_eq!(m.get_property(prop).unwrap().array_len(), Some(4));
+        let _ = m.add_property("strength", 1.0.into());
+
+        let property_layout = PropertyLayout::new(m.properties());
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        let index = m.lit(3u32);
+        let x = m.prop_index("attractors", index);
+        assert_eq!(
+            context.eval(&m, x).unwrap(),
+            "properties.attractors[3u]".to_string()
+        );
+
+        // Runtime indices are not validated
+        let index = m.attr(Attribute::SPRITE_INDEX);
+        let x = m.prop_index("attractors", index);
+        assert_eq!(
+            context.eval(&m, x).unwrap(),
+            "properties.attractors[particle.sprite_index]".to_string()
+        );
+
+        // Out of bounds literal indices
+        let index = m.lit(4u32);
+        let x = m.prop_index("attractors", index);
+        assert!(matches!(
+            context.eval(&m, x),
+            Err(ExprError::PropertyError(_))
+        ));
+        let index = m.lit(-1i32);
+        let x = m.prop_index("attractors", index);
+        assert!(matches!(
+            context.eval(&m, x),
+            Err(ExprError::PropertyError(_))
+        ));
+
+        // Non-integer index
+        let index = m.lit(1.);
+        let x = m.prop_index("attractors", index);
+        assert!(matches!(context.eval(&m, x), Err(ExprError::TypeError(_))));
+
+        // Arrays must be indexed, and only arrays can be
+        let x = m.prop(prop);
+        assert!(matches!(
+            context.eval(&m, x),
+            Err(ExprError::PropertyError(_))
+        ));
+        let index = m.lit(0u32);
+        let x = m.prop_index("strength", index);
+        assert!(matches!(
+            context.eval(&m, x),
+            Err(ExprError::PropertyError(_))
+        ));
+    }
+
     #[test]
     fn writer() {
         // Get a module and its writer
@@ -3788,6 +5638,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn radial_falloff() {
+        let w = ExprWriter::new();
+        let x = w
+            .attr(Attribute::POSITION)
+            .radial_falloff(w.lit(Vec3::ZERO), w.lit(1.), w.lit(3.), w.lit(2.))
+            .expr();
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let m = w.finish();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        let s = context.eval(&m, x).unwrap();
+        assert_eq!(
+            "pow((1.) - (smoothstep(1., 3., distance(particle.position, vec3<f32>(0.,0.,0.)))), 2.)",
+            s
+        );
+    }
+
     #[test]
     fn type_error() {
         let l = Value::Scalar(3.5_f32.into());
@@ -3855,6 +5726,8 @@ mod tests {
             BuiltInOperator::VirtualDeltaTime,
             BuiltInOperator::RealTime,
             BuiltInOperator::RealDeltaTime,
+            BuiltInOperator::TimeOfDay,
+            BuiltInOperator::Weather,
         ] {
             let value = m.builtin(op);
 
@@ -3884,6 +5757,44 @@ mod tests {
             assert_eq!(expr, "is_alive");
         }
 
+        // particle_index
+        {
+            let value = m.builtin(BuiltInOperator::ParticleIndex);
+            assert_eq!(
+                m.get(value).unwrap().value_type(),
+                Some(ValueType::Scalar(ScalarType::Uint))
+            );
+
+            let property_layout = PropertyLayout::default();
+            let particle_layout = ParticleLayout::default();
+            let mut ctx =
+                ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+
+            let expr = ctx.eval(&m, value);
+            assert!(expr.is_ok());
+            let expr = expr.unwrap();
+            assert_eq!(expr, "index");
+        }
+
+        // view_depth
+        {
+            let value = m.builtin(BuiltInOperator::ViewDepth);
+            assert_eq!(
+                m.get(value).unwrap().value_type(),
+                Some(ValueType::Scalar(ScalarType::Float))
+            );
+
+            let property_layout = PropertyLayout::default();
+            let particle_layout = ParticleLayout::default();
+            let mut ctx =
+                ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+
+            let expr = ctx.eval(&m, value);
+            assert!(expr.is_ok());
+            let expr = expr.unwrap();
+            assert_eq!(expr, "get_view_depth(particle.position)");
+        }
+
         // BuiltInOperator::Rand (which has side effect)
         for (scalar_type, prefix) in [
             (ScalarType::Bool, "b"),
@@ -3943,6 +5854,7 @@ mod tests {
         let uu = m.lit(0x0u32);
 
         let abs = m.abs(x);
+        let acos = m.acos(y);
         let all = m.all(z);
         let any = m.any(z);
         let ceil = m.ceil(y);
@@ -3953,8 +5865,10 @@ mod tests {
         let fract = m.fract(y);
         let inv_sqrt = m.inverse_sqrt(y);
         let length = m.length(y);
+        let linear_to_srgb = m.linear_to_srgb(v);
         let log = m.log(y);
         let log2 = m.log2(y);
+        let neg = m.neg(y);
         let norm = m.normalize(y);
         let pack4x8snorm = m.pack4x8snorm(v);
         let pack4x8unorm = m.pack4x8unorm(v);
@@ -3962,6 +5876,7 @@ mod tests {
         let sign = m.sign(y);
         let sin = m.sin(y);
         let sqrt = m.sqrt(y);
+        let srgb_to_linear = m.srgb_to_linear(v);
         let tan = m.tan(y);
         let unpack4x8snorm = m.unpack4x8snorm(us);
         let unpack4x8unorm = m.unpack4x8unorm(uu);
@@ -3981,6 +5896,7 @@ mod tests {
                 "abs",
                 &format!("particle.{}", Attribute::POSITION.name())[..],
             ),
+            (acos, "acos", "vec3<f32>(1.,-3.1,6.99)"),
             (all, "all", "vec3<bool>(false,true,false)"),
             (any, "any", "vec3<bool>(false,true,false)"),
             (ceil, "ceil", "vec3<f32>(1.,-3.1,6.99)"),
@@ -3991,8 +5907,10 @@ mod tests {
             (fract, "fract", "vec3<f32>(1.,-3.1,6.99)"),
             (inv_sqrt, "inverseSqrt", "vec3<f32>(1.,-3.1,6.99)"),
             (length, "length", "vec3<f32>(1.,-3.1,6.99)"),
+            (linear_to_srgb, "linear_to_srgb", "vec4<f32>(-1.,1.,0.,7.2)"),
             (log, "log", "vec3<f32>(1.,-3.1,6.99)"),
             (log2, "log2", "vec3<f32>(1.,-3.1,6.99)"),
+            (neg, "-", "vec3<f32>(1.,-3.1,6.99)"),
             (norm, "normalize", "vec3<f32>(1.,-3.1,6.99)"),
             (pack4x8snorm, "pack4x8snorm", "vec4<f32>(-1.,1.,0.,7.2)"),
             (pack4x8unorm, "pack4x8unorm", "vec4<f32>(-1.,1.,0.,7.2)"),
@@ -4000,6 +5918,7 @@ mod tests {
             (sign, "sign", "vec3<f32>(1.,-3.1,6.99)"),
             (sin, "sin", "vec3<f32>(1.,-3.1,6.99)"),
             (sqrt, "sqrt", "vec3<f32>(1.,-3.1,6.99)"),
+            (srgb_to_linear, "srgb_to_linear", "vec4<f32>(-1.,1.,0.,7.2)"),
             (tan, "tan", "vec3<f32>(1.,-3.1,6.99)"),
             (unpack4x8snorm, "unpack4x8snorm", "0u"),
             (unpack4x8unorm, "unpack4x8unorm", "0u"),
@@ -4023,6 +5942,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn swizzle_binary_expr() {
+        let mut m = Module::default();
+
+        let x = m.attr(Attribute::POSITION);
+        let y = m.lit(Vec3::ONE);
+        let sum = m.add(x, y);
+        let sum_x = m.x(sum);
+        let max = m.max(x, y);
+        let max_x = m.x(max);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        // Infix operations are wrapped in parentheses, functional ones don't need to
+        let name = Attribute::POSITION.name();
+        assert_eq!(
+            ctx.eval(&m, sum_x).unwrap(),
+            format!("((particle.{name}) + (vec3<f32>(1.,1.,1.))).x")
+        );
+        assert_eq!(
+            ctx.eval(&m, max_x).unwrap(),
+            format!("max(particle.{name}, vec3<f32>(1.,1.,1.)).x")
+        );
+    }
+
     #[test]
     fn binary_expr() {
         let mut m = Module::default();
@@ -4030,12 +5977,14 @@ mod tests {
         let x = m.attr(Attribute::POSITION);
         let y = m.lit(Vec3::ONE);
 
+        let atan2 = m.atan2(x, y);
         let cross = m.cross(x, y);
         let dist = m.distance(x, y);
         let dot = m.dot(x, y);
         let min = m.min(x, y);
         let max = m.max(x, y);
         let step = m.step(x, y);
+        let pow = m.pow(x, y);
 
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
@@ -4043,12 +5992,14 @@ mod tests {
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
 
         for (expr, op) in [
+            (atan2, "atan2"),
             (cross, "cross"),
             (dist, "distance"),
             (dot, "dot"),
             (min, "min"),
             (max, "max"),
             (step, "step"),
+            (pow, "pow"),
         ] {
             let expr = ctx.eval(&m, expr);
             assert!(expr.is_ok());
@@ -4072,7 +6023,10 @@ mod tests {
         let y = m.lit(Vec3::ONE);
         let t = m.lit(0.3);
 
+        let clamp = m.clamp(x, y, x);
         let mix = m.mix(x, y, t);
+        let cond = m.gt(x, y);
+        let select = m.select(x, y, cond);
         let smoothstep = m.smoothstep(x, y, x);
 
         let property_layout = PropertyLayout::default();
@@ -4080,7 +6034,15 @@ mod tests {
         let mut ctx =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
 
-        for (expr, op, third) in [(mix, "mix", t), (smoothstep, "smoothstep", x)] {
+        let mat3 = m.mat3(x, y, x);
+
+        for (expr, op, third) in [
+            (clamp, "clamp", x),
+            (mix, "mix", t),
+            (select, "select", cond),
+            (smoothstep, "smoothstep", x),
+            (mat3, "mat3x3<f32>", x),
+        ] {
             let expr = ctx.eval(&m, expr);
             assert!(expr.is_ok());
             let expr = expr.unwrap();
@@ -43,8 +43,8 @@ use std::fmt::Debug;
 
 use bevy::{
     math::{
-        BVec2, BVec3, BVec4, FloatOrd, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4,
-        Vec2, Vec3, Vec3A, Vec4,
+        BVec2, BVec3, BVec4, FloatOrd, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, Quat, UVec2, UVec3,
+        UVec4, Vec2, Vec3, Vec3A, Vec4,
     },
     reflect::Reflect,
 };
@@ -57,11 +57,23 @@ pub mod node;
 
 pub use expr::{
     AttributeExpr, BinaryOperator, BuiltInExpr, BuiltInOperator, EvalContext, Expr, ExprError,
-    ExprHandle, ExprWriter, LiteralExpr, Module, PropertyExpr, UnaryOperator, WriterExpr,
+    ExprHandle, ExprWriter, GraphStats, LiteralExpr, Module, PropertyExpr, PropertyIndexExpr,
+    UnaryOperator, WriterExpr,
 };
 pub use node::{
-    AddNode, AttributeNode, DivNode, Graph, MulNode, Node, NormalizeNode, Slot, SlotDir, SlotId,
-    SubNode, TimeNode,
+    AddNode, AngleBetweenNode, AttractorNode, AttributeNode, AxisAngleMatrixNode, BarycentricNode,
+    BlendMode, CartesianToSphericalNode, CheckerNode, ClampNode, ColorBlendNode, ColorGradientNode,
+    ColorRampNode, CompactionMapping, ComponentMixNode, CosNode, CosineHemisphereNode, CrossNode,
+    DistanceNode, DistortUvNode, DivNode, DotNode, EqualNode, FogNode, Graph, GraphError,
+    GraphTemplate, GreaterNode, LengthNode, LessNode, LifetimeNode, LinearToSrgbNode, LiteralNode,
+    MatrixTransformNode, MaxNode, MinNode, MixNode, ModNode, ModfNode, ModifierNode, MulNode,
+    NegateNode, Node, NodeClone, NormalizeNode, OscillatorNode, ParticleNormalMode,
+    ParticleNormalNode, PathTangentNode, PingPongUvNode, PowNode, PrevAttributeNode, ProjectNode,
+    PropertyNode, QuatRotateNode, RandomNode, RejectNode, RemapNode, RoundedBoxSdfNode,
+    SeededRandNode, SelectNode, SelfShadowNode, SetAttributeNode, SinNode, Slot, SlotDir, SlotId,
+    SmoothGradientNode, SmoothstepNode, SpawnerNode, SphereSdfNode, SphericalToCartesianNode,
+    SrgbToLinearNode, StepNode, SubNode, SwizzleNode, TimeNode, UnaryMathNode, UnaryMathOp,
+    VecComposeNode, VecDecomposeNode, WaveShape, WeightedChoiceNode, WobbleLayer, WobbleNode,
 };
 
 /// Variant storage for a scalar value.
@@ -748,6 +760,18 @@ impl VectorValue {
         Vec4::from_slice(bytemuck::cast_slice::<u32, f32>(&self.storage))
     }
 
+    /// Cast this vector value to a [`Quat`].
+    ///
+    /// The vector components `(x, y, z, w)` are interpreted as the
+    /// corresponding quaternion components. The quaternion is not normalized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current vector type is not [`VectorType::VEC4F`].
+    pub fn as_quat(&self) -> Quat {
+        Quat::from_vec4(self.as_vec4())
+    }
+
     /// Cast this vector value to a [`IVec2`].
     ///
     /// # Panics
@@ -1203,6 +1227,18 @@ impl From<Vec4> for VectorValue {
     }
 }
 
+impl From<Quat> for VectorValue {
+    fn from(value: Quat) -> Self {
+        let mut s = Self {
+            vector_type: VectorType::VEC4F,
+            storage: [0u32; 4],
+        };
+        let v = bytemuck::cast_slice_mut::<u32, f32>(&mut s.storage);
+        value.write_to_slice(v);
+        s
+    }
+}
+
 /// Helper used as placeholder instead of [`VectorValue`] for serialization.
 ///
 /// This enables serializing [`VectorValue`] as a glam type enum, instead of the
@@ -1708,6 +1744,7 @@ impl_vec_value!(Vec2, VEC2F, as_vec2);
 impl_vec_value!(Vec3, VEC3F, as_vec3);
 impl_vec_value!(Vec3A, VEC3F, as_vec3a);
 impl_vec_value!(Vec4, VEC4F, as_vec4);
+impl_vec_value!(Quat, VEC4F, as_quat);
 impl_vec_value!(IVec2, VEC2I, as_ivec2);
 impl_vec_value!(IVec3, VEC3I, as_ivec3);
 impl_vec_value!(IVec4, VEC4I, as_ivec4);
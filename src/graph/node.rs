@@ -12,22 +12,123 @@
 //! downstream node.
 //!
 //! An effect [`Graph`] can be serialized as is, to retain its editing
-//! capabilities. Alternatively, once the user has finished building an effect,
-//! it can be converted to a runtime [`EffectAsset`] for use as a
-//! [`ParticleEffect`].
+//! capabilities. This requires the `serde` feature, which registers all
+//! built-in nodes with [`typetag`]; custom nodes must be annotated with
+//! `#[typetag::serde]` too. Alternatively, once the user has finished building an effect,
+//! it can be converted with [`Graph::to_effect_asset()`] to a runtime
+//! [`EffectAsset`] for use as a [`ParticleEffect`].
 //!
 //! [Expression API]: crate::graph::expr
+//! [`typetag`]: https://docs.rs/typetag
 //! [`Modifier`]: crate::Modifier
 //! [`Expr`]: crate::graph::expr::Expr
 //! [`EffectAsset`]: crate::EffectAsset
 //! [`ParticleEffect`]: crate::ParticleEffect
 
-use std::num::NonZeroU32;
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
 
-use crate::{Attribute, BuiltInOperator, ExprError, ExprHandle, Module, ValueType};
+use bevy::{
+    math::{Vec2, Vec3, Vec4},
+    reflect::{Reflect, ReflectMut, ReflectRef},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    graph::expr::{SwizzleExpr, TernaryOperator},
+    Attribute, BoxedModifier, BuiltInOperator, EffectAsset, ExprError, ExprHandle, Gradient,
+    MatrixType, MatrixValue, Modifier, ModifierContext, Module, ScalarType, ScalarValue,
+    SetAttributeModifier, Spawner, Value, ValueType, VectorType, VectorValue,
+};
+
+/// Error resulting from an invalid edit of a [`Graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum GraphError {
+    /// Linking an output slot of node `from` to an input slot of node `to`
+    /// would create a directed cycle, because `from` already depends on the
+    /// output of `to`, directly or indirectly.
+    #[error("Linking node {from:?} to node {to:?} would create a cycle.")]
+    CycleDetected {
+        /// Node owning the output slot of the rejected link.
+        from: NodeId,
+        /// Node owning the input slot of the rejected link.
+        to: NodeId,
+    },
+    /// The value type of the output slot is incompatible with the one of the
+    /// input slot.
+    #[error("Cannot link an output slot of type {output:?} to an input slot of type {input:?}.")]
+    TypeMismatch {
+        /// Output slot of the rejected link.
+        output_slot: SlotId,
+        /// Input slot of the rejected link.
+        input_slot: SlotId,
+        /// Value type of the output slot of the rejected link.
+        output: ValueType,
+        /// Value type of the input slot of the rejected link.
+        input: ValueType,
+    },
+    /// An input slot of a node is not linked to any output slot.
+    #[error("Input slot {slot:?} of node {node:?} is not linked.")]
+    UnlinkedInput {
+        /// Node owning the unlinked input slot.
+        node: NodeId,
+        /// Unlinked input slot.
+        slot: SlotId,
+    },
+    /// The link to edit doesn't exist.
+    #[error("Output slot {output:?} is not linked to input slot {input:?}.")]
+    LinkNotFound {
+        /// Output slot of the missing link.
+        output: SlotId,
+        /// Input slot of the missing link.
+        input: SlotId,
+    },
+    /// The input slot of a new link is already linked to another output slot.
+    ///
+    /// An input slot receives a single value, so the existing link must be
+    /// removed first with [`Graph::unlink()`], or replaced with
+    /// [`Graph::relink()`].
+    #[error("Input slot {input:?} is already linked to output slot {existing:?}.")]
+    InputAlreadyLinked {
+        /// Input slot of the rejected link.
+        input: SlotId,
+        /// Output slot the input slot is already linked to.
+        existing: SlotId,
+    },
+    /// A node doesn't have any slot with the requested name and direction.
+    #[error("Node has no {dir:?} slot with the requested name.")]
+    SlotNotFound {
+        /// Direction of the missing slot.
+        dir: SlotDir,
+    },
+    /// A node has a different number of input slots in the graph than the
+    /// number of input slots it declares, and cannot be evaluated.
+    #[error("Node {node:?} cannot be evaluated with {inputs} input(s).")]
+    InvalidInputCount {
+        /// Node failing to evaluate.
+        node: NodeId,
+        /// Number of input slots of the node in the graph.
+        inputs: usize,
+    },
+    /// The slot doesn't exist in the graph, or belongs to a removed node.
+    #[error("Slot {0:?} doesn't exist.")]
+    NoSuchSlot(SlotId),
+    /// The slot exists, but doesn't have the direction expected by the
+    /// operation, like an input slot used as the output of a link.
+    #[error("Slot {slot:?} is not an {expected:?} slot.")]
+    WrongDirection {
+        /// Slot with the wrong direction.
+        slot: SlotId,
+        /// Direction the slot was expected to have.
+        expected: SlotDir,
+    },
+}
 
 /// Identifier of a node in a graph.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NodeId(NonZeroU32);
 
 impl NodeId {
@@ -48,7 +149,7 @@ impl NodeId {
 }
 
 /// Identifier of a slot in a graph.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SlotId(NonZeroU32);
 
 impl SlotId {
@@ -69,7 +170,7 @@ impl SlotId {
 }
 
 /// Node slot direction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SlotDir {
     /// Input slot receiving data from outside the node.
     Input,
@@ -78,7 +179,7 @@ pub enum SlotDir {
 }
 
 /// Definition of a slot of a node.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlotDef {
     /// Slot name.
     name: String,
@@ -87,6 +188,10 @@ pub struct SlotDef {
     /// Type of values accepted by the slot. This may be `None` for variant
     /// slots, if the type depends on the inputs of the node during evaluation.
     value_type: Option<ValueType>,
+    /// Default value of an input slot, used when the slot is not linked.
+    default_value: Option<Value>,
+    /// Human-readable description of the slot, for display in an editor.
+    description: Option<String>,
 }
 
 impl SlotDef {
@@ -96,6 +201,55 @@ impl SlotDef {
             name: name.into(),
             dir: SlotDir::Input,
             value_type,
+            default_value: None,
+            description: None,
+        }
+    }
+
+    /// Create a new input slot with a description.
+    ///
+    /// The description is a human-readable help text, typically displayed as
+    /// a tooltip by an editor. It's not interpreted by the graph itself, and
+    /// never affects its evaluation.
+    pub fn input_described(
+        name: impl Into<String>,
+        value_type: Option<ValueType>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            description: Some(description.into()),
+            ..Self::input(name, value_type)
+        }
+    }
+
+    /// Create a new input slot with a default value.
+    ///
+    /// When the slot is not linked, the graph evaluates it as a literal
+    /// expression of the default value, instead of failing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value_type` is `Some` type different from the type of
+    /// `default_value`.
+    pub fn input_with_default(
+        name: impl Into<String>,
+        value_type: Option<ValueType>,
+        default_value: Value,
+    ) -> Self {
+        let name = name.into();
+        assert!(
+            value_type.is_none() || value_type == Some(default_value.value_type()),
+            "Default value of type {:?} doesn't match the type {:?} of input slot '{}'",
+            default_value.value_type(),
+            value_type,
+            name
+        );
+        Self {
+            name,
+            dir: SlotDir::Input,
+            value_type,
+            default_value: Some(default_value),
+            description: None,
         }
     }
 
@@ -105,6 +259,24 @@ impl SlotDef {
             name: name.into(),
             dir: SlotDir::Output,
             value_type,
+            default_value: None,
+            description: None,
+        }
+    }
+
+    /// Create a new output slot with a description.
+    ///
+    /// See [`input_described()`] for details.
+    ///
+    /// [`input_described()`]: SlotDef::input_described
+    pub fn output_described(
+        name: impl Into<String>,
+        value_type: Option<ValueType>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            description: Some(description.into()),
+            ..Self::output(name, value_type)
         }
     }
 
@@ -137,10 +309,31 @@ impl SlotDef {
     pub fn value_type(&self) -> Option<ValueType> {
         self.value_type
     }
+
+    /// Get the default value of the slot, if any.
+    ///
+    /// This is only ever `Some` for input slots created with
+    /// [`input_with_default()`].
+    ///
+    /// [`input_with_default()`]: SlotDef::input_with_default
+    #[inline]
+    pub fn default_value(&self) -> Option<Value> {
+        self.default_value
+    }
+
+    /// Get the description of the slot, if any.
+    ///
+    /// See [`input_described()`] for details.
+    ///
+    /// [`input_described()`]: SlotDef::input_described
+    #[inline]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
 }
 
 /// Single slot of a node.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Slot {
     /// Owner node identifier.
     node_id: NodeId,
@@ -242,13 +435,69 @@ impl Slot {
 /// complex expressions which are then assigned to the modifier inputs. Once the
 /// graph is ready, it can be converted into an [`EffectAsset`].
 ///
+/// Graphs are cloneable, cloning each node with [`NodeClone`], so an editor
+/// can for example snapshot a graph to implement undo and redo. The clone is
+/// a deep copy, independent from the original graph.
+///
 /// [`EffectAsset`]: crate::EffectAsset
 /// [`Modifier`]: crate::Modifier
 /// [`Expr`]: crate::graph::Expr
-#[derive(Default)]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Graph {
-    nodes: Vec<Box<dyn Node>>,
-    slots: Vec<Slot>,
+    /// Graph nodes. Removed nodes are left as `None` tombstones until the next
+    /// call to [`Graph::compact()`], to keep existing identifiers valid.
+    nodes: Vec<Option<Box<dyn Node>>>,
+    /// Slots of all nodes. Slots of removed nodes are left as `None`
+    /// tombstones until the next call to [`Graph::compact()`].
+    slots: Vec<Option<Slot>>,
+    /// Constants set with [`Graph::set_input_constant()`], indexed by the
+    /// input slot they feed, with the hidden [`LiteralNode`] holding them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    input_constants: HashMap<SlotId, (NodeId, Value)>,
+    /// Editor metadata of the nodes which have some. This is saved along with
+    /// the graph, but never affects its evaluation.
+    #[cfg_attr(feature = "serde", serde(default))]
+    node_metadata: HashMap<NodeId, NodeMetadata>,
+}
+
+/// Editor metadata of a single node of a [`Graph`].
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct NodeMetadata {
+    /// Position of the node on the editor canvas.
+    position: Option<Vec2>,
+    /// User-assigned label of the node.
+    label: Option<String>,
+}
+
+impl NodeMetadata {
+    fn is_empty(&self) -> bool {
+        self.position.is_none() && self.label.is_none()
+    }
+}
+
+/// Mapping from old to new identifiers produced by [`Graph::compact()`] and
+/// [`Graph::subgraph()`].
+///
+/// Use this mapping to update any [`NodeId`] or [`SlotId`] stored outside of
+/// the graph, which are invalidated by the compaction, or to find the nodes of
+/// an extracted subgraph.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompactionMapping {
+    nodes: HashMap<NodeId, NodeId>,
+    slots: HashMap<SlotId, SlotId>,
+}
+
+impl CompactionMapping {
+    /// Get the new identifier of a node, or `None` if the node was removed.
+    pub fn node(&self, old: NodeId) -> Option<NodeId> {
+        self.nodes.get(&old).copied()
+    }
+
+    /// Get the new identifier of a slot, or `None` if the slot was removed.
+    pub fn slot(&self, old: SlotId) -> Option<SlotId> {
+        self.slots.get(&old).copied()
+    }
 }
 
 impl std::fmt::Debug for Graph {
@@ -258,6 +507,12 @@ impl std::fmt::Debug for Graph {
 }
 
 impl Graph {
+    /// Spacing between the columns (X) and rows (Y) of nodes in the layout
+    /// computed by [`auto_layout()`].
+    ///
+    /// [`auto_layout()`]: Graph::auto_layout
+    pub const LAYOUT_SPACING: Vec2 = Vec2::new(200., 100.);
+
     /// Create a new empty graph.
     ///
     /// An empty graph doesn't represent a valid [`EffectAsset`]. You must add
@@ -291,678 +546,12541 @@ impl Graph {
     fn add_node_impl(&mut self, node: Box<dyn Node>) -> NodeId {
         let index = self.nodes.len() as u32;
         let node_id = NodeId::new(NonZeroU32::new(index + 1).unwrap());
+        self.add_slots(node_id, node.as_ref());
+        self.nodes.push(Some(node));
+        node_id
+    }
 
+    /// Create the slots of a node from its [`Node::slots()`].
+    fn add_slots(&mut self, node_id: NodeId, node: &dyn Node) {
         for slot_def in node.slots() {
             let slot_id = SlotId::new(NonZeroU32::new(self.slots.len() as u32 + 1).unwrap());
             let slot = Slot::new(node_id, slot_id, slot_def.clone());
-            self.slots.push(slot);
+            self.slots.push(Some(slot));
         }
-
-        self.nodes.push(node);
-
-        node_id
     }
 
-    /// Link an output slot of a node to an input slot of another node.
+    /// Replace a node of the graph with another one, keeping the links which
+    /// still apply.
     ///
-    /// # Panics
+    /// The new node takes the place of the old one, and keeps its
+    /// [`NodeId`] and editor metadata, like its position and label. The slots
+    /// of the old node are removed, and new slots are created from the
+    /// [`Node::slots()`] of the new node. The links and input constants of the
+    /// old node are then restored on the slots of the new node with the same
+    /// name and direction, for example the `lhs`, `rhs`, and `result` slots
+    /// when replacing an [`AddNode`] with a [`MulNode`]. Links to slots which
+    /// don't exist anymore, or which are rejected by [`try_link()`] for the new
+    /// slots, for example because of a type mismatch, are dropped.
     ///
-    /// Panics if the `output` argument doesn't reference an output slot of an
-    /// existing node, or the `input` argument doesn't reference an input slot
-    /// of an existing node.
-    pub fn link(&mut self, output: SlotId, input: SlotId) {
-        let out_slot = self.get_slot_mut(output);
-        assert!(out_slot.is_output());
-        out_slot.link_to(input);
-
-        let in_slot = self.get_slot_mut(input);
-        assert!(in_slot.is_input());
-        in_slot.link_input(output);
-    }
-
-    /// Unlink an output slot of a node from an input slot of another node.
+    /// Returns the replaced node, or `None` if the node doesn't exist, was
+    /// removed, or is the hidden [`LiteralNode`] of an input constant, in
+    /// which case the graph is left unchanged. Use [`set_input_constant()`] to
+    /// change the value of such a constant instead.
     ///
-    /// # Panics
+    /// # Example
     ///
-    /// Panics if the `output` argument doesn't reference an output slot of an
-    /// existing node, or the `input` argument doesn't reference an input slot
-    /// of an existing node.
-    pub fn unlink(&mut self, output: SlotId, input: SlotId) {
-        let out_slot = self.get_slot_mut(output);
-        assert!(out_slot.is_output());
-        if out_slot.unlink_from(input) {
-            let in_slot = self.get_slot_mut(input);
-            assert!(in_slot.is_input());
-            in_slot.unlink_input();
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let op = graph.add_node(AddNode::default());
+    /// graph.connect(time, "time", op, "lhs").unwrap();
+    /// graph.replace_node(op, MulNode::default());
+    /// assert_eq!(graph.node(op).unwrap().name(), "MulNode");
+    /// assert_eq!(graph.links().len(), 1);
+    /// ```
+    ///
+    /// [`try_link()`]: crate::graph::Graph::try_link
+    /// [`set_input_constant()`]: crate::graph::Graph::set_input_constant
+    pub fn replace_node<N>(&mut self, node_id: NodeId, new_node: N) -> Option<Box<dyn Node>>
+    where
+        N: Node + 'static,
+    {
+        self.node(node_id)?;
+        if self.is_input_constant_node(node_id) {
+            return None;
         }
-    }
 
-    /// Unlink all remote slots from a given slot.
-    pub fn unlink_all(&mut self, slot_id: SlotId) {
-        let slot = self.get_slot_mut(slot_id);
-        let linked_slots = std::mem::take(&mut slot.linked_slots);
-        for remote_id in &linked_slots {
-            let remote_slot = self.get_slot_mut(*remote_id);
-            if remote_slot.is_input() {
-                remote_slot.unlink_input();
+        // Save the links and constants of the old slots, by slot name
+        let mut constants = vec![];
+        let mut sources = vec![];
+        let mut targets = vec![];
+        for slot_id in self.slots(node_id) {
+            let slot = self.get_slot(slot_id);
+            let name = slot.def().name().to_string();
+            if slot.is_input() {
+                if let Some(value) = self.input_constant(slot_id) {
+                    constants.push((name, value));
+                } else if let Some(source) = self.input_source(slot_id) {
+                    sources.push((name, source));
+                }
             } else {
-                remote_slot.unlink_from(slot_id);
+                targets.push((name, slot.linked_slots.clone()));
             }
         }
-    }
 
-    /// Get all slots of a node.
-    pub fn slots(&self, node_id: NodeId) -> Vec<SlotId> {
-        self.slots
-            .iter()
-            .filter_map(|s| {
-                if s.node_id() == node_id {
-                    Some(s.id())
-                } else {
-                    None
+        // Remove the old slots, and create the new ones
+        for slot_id in self.slots(node_id) {
+            self.unlink_all(slot_id);
+            self.slots[slot_id.index()] = None;
+        }
+        let new_node: Box<dyn Node> = Box::new(new_node);
+        self.add_slots(node_id, new_node.as_ref());
+        let old_node = self.nodes[node_id.index()].replace(new_node);
+
+        // Restore what still applies to the new slots
+        for (name, value) in constants {
+            if let Some(input) = self.input_slot(node_id, name.as_str()) {
+                let value_type = self.get_slot(input).def().value_type();
+                if !value_type.is_some_and(|value_type| value_type != value.value_type()) {
+                    self.set_input_constant(input, value);
                 }
-            })
-            .collect()
-    }
+            }
+        }
+        for (name, source) in sources {
+            if let Some(input) = self.input_slot(node_id, name.as_str()) {
+                let _ = self.try_link(source, input);
+            }
+        }
+        for (name, inputs) in targets {
+            if let Some(output) = self.output_slot(node_id, name.as_str()) {
+                for input in inputs {
+                    let _ = self.try_link(output, input);
+                }
+            }
+        }
 
-    /// Get a given input slot of a node by name.
-    pub fn input_slot<'a, 'b: 'a, S: Into<&'b str>>(
-        &'a self,
-        node_id: NodeId,
-        name: S,
-    ) -> Option<SlotId> {
-        let name = name.into();
-        self.slots
-            .iter()
-            .find(|s| s.node_id() == node_id && s.is_input() && s.def().name() == name)
-            .map(|s| s.id)
+        old_node
     }
 
-    /// Get all input slots of a node.
-    pub fn input_slots(&self, node_id: NodeId) -> Vec<SlotId> {
-        self.slots
-            .iter()
-            .filter_map(|s| {
-                if s.node_id() == node_id && s.is_input() {
-                    Some(s.id())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    /// Remove a node from the graph, unlinking all its slots.
+    ///
+    /// The node and its slots are left as tombstones, so that the identifiers
+    /// of all other nodes and slots remain valid. Call [`compact()`] to
+    /// reclaim the storage and renumber the identifiers.
+    ///
+    /// Returns the removed node, or `None` if the node was already removed.
+    ///
+    /// [`compact()`]: crate::graph::Graph::compact
+    pub fn remove_node(&mut self, node_id: NodeId) -> Option<Box<dyn Node>> {
+        let node = self.nodes.get_mut(node_id.index())?.take()?;
+        self.input_constants
+            .retain(|_, (constant_node_id, _)| *constant_node_id != node_id);
+        self.node_metadata.remove(&node_id);
+        for slot_id in self.slots(node_id) {
+            self.unlink_all(slot_id);
+            self.slots[slot_id.index()] = None;
+        }
+        Some(node)
     }
 
-    /// Get a given output slot of a node by name.
-    pub fn output_slot<'a, 'b: 'a, S: Into<&'b str>>(
-        &'a self,
-        node_id: NodeId,
-        name: S,
-    ) -> Option<SlotId> {
-        let name = name.into();
-        self.slots
-            .iter()
-            .find(|s| s.node_id() == node_id && s.is_output() && s.def().name() == name)
-            .map(|s| s.id)
+    /// Get the number of nodes in the graph, excluding removed ones.
+    pub fn node_count(&self) -> usize {
+        self.nodes.iter().flatten().count()
     }
 
-    /// Get all output slots of a node.
-    pub fn output_slots(&self, node_id: NodeId) -> Vec<SlotId> {
-        self.slots
-            .iter()
-            .filter_map(|s| {
-                if s.node_id() == node_id && s.is_output() {
-                    Some(s.id())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    /// Check if the graph has no node, excluding removed ones.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.iter().all(Option::is_none)
     }
 
-    /// Find a slot ID by slot name.
-    pub fn get_slot_id<'a, 'b: 'a, S: Into<&'b str>>(&'a self, name: S) -> Option<SlotId> {
-        let name = name.into();
-        self.slots
-            .iter()
-            .find(|&s| s.def().name() == name)
-            .map(|s| s.id)
+    /// Remove all the nodes of the graph.
+    ///
+    /// This also removes all slots, links, input constants, and editor
+    /// metadata, and discards the tombstones of removed nodes, keeping the
+    /// allocated storage. New nodes are then numbered from `1` again, so the
+    /// graph behaves exactly like a brand new one created with [`new()`].
+    ///
+    /// [`new()`]: crate::graph::Graph::new
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.slots.clear();
+        self.input_constants.clear();
+        self.node_metadata.clear();
     }
 
-    #[allow(dead_code)] // TEMP
-    fn get_slot(&self, id: SlotId) -> &Slot {
-        let index = id.index();
-        assert!(index < self.slots.len());
-        &self.slots[index]
+    /// Iterate over the identifiers of all the nodes of the graph, excluding
+    /// removed ones.
+    ///
+    /// The nodes are enumerated in the order they were added.
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter_nodes().map(|(node_id, _)| node_id)
     }
 
-    fn get_slot_mut(&mut self, id: SlotId) -> &mut Slot {
-        let index = id.index();
-        assert!(index < self.slots.len());
-        &mut self.slots[index]
+    /// Iterate over all the nodes of the graph, excluding removed ones.
+    ///
+    /// The nodes are enumerated in the order they were added, along with their
+    /// identifier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// graph.add_node(TimeNode::default());
+    /// graph.add_node(AttributeNode::new(Attribute::POSITION));
+    /// for (node_id, node) in graph.iter_nodes() {
+    ///     println!("Node #{}: {}", node_id.id(), node.name());
+    /// }
+    /// ```
+    pub fn iter_nodes(&self) -> impl Iterator<Item = (NodeId, &dyn Node)> {
+        self.nodes.iter().enumerate().filter_map(|(index, node)| {
+            let node_id = NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap());
+            node.as_ref().map(|node| (node_id, node.as_ref()))
+        })
     }
-}
 
-/// Generic graph node.
-pub trait Node {
-    /// Get the list of slots of this node.
+    /// Get a node of the graph.
     ///
-    /// The list contains both input and output slots, without any guaranteed
-    /// order.
-    fn slots(&self) -> &[SlotDef];
+    /// Returns `None` if the node doesn't exist or was removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let node_id = graph.add_node(AttributeNode::new(Attribute::POSITION));
+    /// let node = graph.node(node_id).unwrap();
+    /// assert_eq!(node.slots().len(), 1);
+    /// ```
+    pub fn node(&self, node_id: NodeId) -> Option<&dyn Node> {
+        Some(self.nodes.get(node_id.index())?.as_ref()?.as_ref())
+    }
 
-    /// Evaluate the node from the given input expressions, and optionally
-    /// produce output expression(s).
+    /// Get a node of the graph, mutably.
     ///
-    /// The expressions themselves are not evaluated (that is, _e.g._ "3 + 2" is
-    /// _not_ reduced to "5").
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError>;
-}
+    /// The slots of the graph are created from [`Node::slots()`] when the node
+    /// is added, so modifying the node must not change its slots.
+    ///
+    /// Returns `None` if the node doesn't exist or was removed.
+    pub fn node_mut(&mut self, node_id: NodeId) -> Option<&mut dyn Node> {
+        Some(self.nodes.get_mut(node_id.index())?.as_mut()?.as_mut())
+    }
 
-/// Graph node to add two values.
-#[derive(Debug, Clone)]
-pub struct AddNode {
-    slots: [SlotDef; 3],
-}
+    /// Set the position of a node on the canvas of a visual editor.
+    ///
+    /// The position is editor metadata saved along with the graph. It's not
+    /// interpreted by the graph itself, and never affects its evaluation. Nodes
+    /// without a saved position can be placed with [`auto_layout()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec2;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// assert_eq!(graph.node_position(time), None);
+    /// graph.set_node_position(time, Vec2::new(120., 40.));
+    /// assert_eq!(graph.node_position(time), Some(Vec2::new(120., 40.)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node_id` doesn't reference an existing node.
+    ///
+    /// [`auto_layout()`]: Graph::auto_layout
+    pub fn set_node_position(&mut self, node_id: NodeId, position: Vec2) {
+        self.node_metadata_mut(node_id).position = Some(position);
+    }
 
-impl Default for AddNode {
-    fn default() -> Self {
-        Self {
-            slots: [
-                SlotDef::input("lhs", None),
-                SlotDef::input("rhs", None),
-                SlotDef::output("result", None),
-            ],
-        }
+    /// Get the position of a node on the canvas of a visual editor, if any.
+    ///
+    /// See [`set_node_position()`] for details.
+    ///
+    /// [`set_node_position()`]: Graph::set_node_position
+    pub fn node_position(&self, node_id: NodeId) -> Option<Vec2> {
+        self.node_metadata.get(&node_id)?.position
     }
-}
 
-impl Node for AddNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
+    /// Set the user-assigned label of a node, replacing any previous one.
+    ///
+    /// The label is editor metadata saved along with the graph, typically
+    /// displayed instead of the node name to describe its purpose. It's not
+    /// interpreted by the graph itself, and never affects its evaluation.
+    /// Labels don't need to be unique; several nodes can have the same label.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node_id` doesn't reference an existing node.
+    pub fn set_node_label(&mut self, node_id: NodeId, label: impl Into<String>) {
+        self.node_metadata_mut(node_id).label = Some(label.into());
     }
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if inputs.len() != 2 {
-            return Err(ExprError::GraphEvalError(format!(
-                "Unexpected input count to AddNode::eval(): expected 2, got {}",
-                inputs.len()
-            )));
-        }
-        let mut inputs = inputs.into_iter();
-        let left = inputs.next().unwrap();
-        let right = inputs.next().unwrap();
-        let add = module.add(left, right);
-        Ok(vec![add])
+    /// Get the user-assigned label of a node, if any.
+    ///
+    /// See [`set_node_label()`] for details.
+    ///
+    /// [`set_node_label()`]: Graph::set_node_label
+    pub fn node_label(&self, node_id: NodeId) -> Option<&str> {
+        self.node_metadata.get(&node_id)?.label.as_deref()
     }
-}
-
-/// Graph node to subtract two values.
-#[derive(Debug, Clone)]
-pub struct SubNode {
-    slots: [SlotDef; 3],
-}
 
-impl Default for SubNode {
-    fn default() -> Self {
-        Self {
-            slots: [
-                SlotDef::input("lhs", None),
-                SlotDef::input("rhs", None),
-                SlotDef::output("result", None),
-            ],
+    /// Remove the user-assigned label of a node, returning it if any.
+    ///
+    /// See [`set_node_label()`] for details.
+    ///
+    /// [`set_node_label()`]: Graph::set_node_label
+    pub fn clear_node_label(&mut self, node_id: NodeId) -> Option<String> {
+        let metadata = self.node_metadata.get_mut(&node_id)?;
+        let label = metadata.label.take();
+        if metadata.is_empty() {
+            self.node_metadata.remove(&node_id);
         }
+        label
     }
-}
 
-impl Node for SubNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
+    fn node_metadata_mut(&mut self, node_id: NodeId) -> &mut NodeMetadata {
+        assert!(
+            self.node(node_id).is_some(),
+            "Invalid node ID {:?}",
+            node_id
+        );
+        self.node_metadata.entry(node_id).or_default()
     }
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if inputs.len() != 2 {
-            return Err(ExprError::GraphEvalError(format!(
-                "Unexpected input count to SubNode::eval(): expected 2, got
-{}",
-                inputs.len()
-            )));
-        }
-        let mut inputs = inputs.into_iter();
-        let left = inputs.next().unwrap();
-        let right = inputs.next().unwrap();
-        let sub = module.sub(left, right);
-        Ok(vec![sub])
-    }
-}
+    /// Remove all tombstones left by [`remove_node()`], and renumber all
+    /// nodes and slots densely.
+    ///
+    /// The relative order of the remaining nodes and slots is preserved, and
+    /// all links are rewritten to use the new identifiers. This invalidates
+    /// all [`NodeId`] and [`SlotId`] previously returned by the graph; use the
+    /// returned [`CompactionMapping`] to update them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time1 = graph.add_node(TimeNode::default());
+    /// let time2 = graph.add_node(TimeNode::default());
+    /// graph.remove_node(time1);
+    /// let mapping = graph.compact();
+    /// assert_eq!(mapping.node(time1), None);
+    /// assert_eq!(mapping.node(time2).unwrap().index(), 0);
+    /// ```
+    ///
+    /// [`remove_node()`]: crate::graph::Graph::remove_node
+    pub fn compact(&mut self) -> CompactionMapping {
+        let mut mapping = CompactionMapping::default();
 
-/// Graph node to multiply two values.
-#[derive(Debug, Clone)]
-pub struct MulNode {
-    slots: [SlotDef; 3],
-}
+        let nodes = std::mem::take(&mut self.nodes);
+        for (index, node) in nodes.into_iter().enumerate() {
+            if let Some(node) = node {
+                let old_id = NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap());
+                let new_id = NodeId::new(NonZeroU32::new(self.nodes.len() as u32 + 1).unwrap());
+                mapping.nodes.insert(old_id, new_id);
+                self.nodes.push(Some(node));
+            }
+        }
 
-impl Default for MulNode {
-    fn default() -> Self {
-        Self {
-            slots: [
-                SlotDef::input("lhs", None),
-                SlotDef::input("rhs", None),
-                SlotDef::output("result", None),
-            ],
+        let slots = std::mem::take(&mut self.slots);
+        for slot in slots.into_iter().flatten() {
+            let new_id = SlotId::new(NonZeroU32::new(self.slots.len() as u32 + 1).unwrap());
+            mapping.slots.insert(slot.id, new_id);
+            self.slots.push(Some(slot));
         }
-    }
-}
 
-impl Node for MulNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
-    }
+        self.input_constants = std::mem::take(&mut self.input_constants)
+            .into_iter()
+            .map(|(input, (node_id, value))| {
+                (mapping.slots[&input], (mapping.nodes[&node_id], value))
+            })
+            .collect();
+        self.node_metadata = std::mem::take(&mut self.node_metadata)
+            .into_iter()
+            .map(|(node_id, metadata)| (mapping.nodes[&node_id], metadata))
+            .collect();
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if inputs.len() != 2 {
-            return Err(ExprError::GraphEvalError(format!(
-                "Unexpected input count to MulNode::eval(): expected 2, got
-{}",
-                inputs.len()
-            )));
+        for slot in self.slots.iter_mut().flatten() {
+            slot.id = mapping.slots[&slot.id];
+            slot.node_id = mapping.nodes[&slot.node_id];
+            // Links to removed slots were cleared by remove_node(), so all
+            // linked slots are still alive.
+            for linked_id in &mut slot.linked_slots {
+                *linked_id = mapping.slots[linked_id];
+            }
         }
-        let mut inputs = inputs.into_iter();
-        let left = inputs.next().unwrap();
-        let right = inputs.next().unwrap();
-        let mul = module.mul(left, right);
-        Ok(vec![mul])
+
+        mapping
     }
-}
 
-/// Graph node to divide two values.
-#[derive(Debug, Clone)]
-pub struct DivNode {
-    slots: [SlotDef; 3],
-}
+    /// Extract a set of nodes into a new graph.
+    ///
+    /// The new graph contains a clone of each of the given nodes, along with
+    /// their editor metadata and input constants. Links between two of those
+    /// nodes are preserved, while links crossing the boundary of the selection
+    /// are dropped, leaving the corresponding slots unlinked. Identifiers not
+    /// referencing an existing node are ignored.
+    ///
+    /// The nodes and slots of the new graph are numbered densely, in the same
+    /// relative order as in this graph. Use the returned [`CompactionMapping`]
+    /// to find the new identifier of each extracted node and slot, for example
+    /// to reconnect or reposition them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// graph.connect(time, "time", add, "lhs").unwrap();
+    ///
+    /// let (subgraph, mapping) = graph.subgraph(&[add]);
+    /// assert_eq!(subgraph.node_count(), 1);
+    /// assert_eq!(mapping.node(time), None);
+    /// assert!(mapping.node(add).is_some());
+    /// assert!(subgraph.links().is_empty());
+    /// ```
+    pub fn subgraph(&self, nodes: &[NodeId]) -> (Graph, CompactionMapping) {
+        let mut selected: HashSet<NodeId> = nodes.iter().copied().collect();
 
-impl Default for DivNode {
-    fn default() -> Self {
-        Self {
-            slots: [
-                SlotDef::input("lhs", None),
-                SlotDef::input("rhs", None),
-                SlotDef::output("result", None),
-            ],
+        // Keep the hidden literal nodes holding the constants of selected inputs
+        let constants: Vec<NodeId> = self
+            .input_constants
+            .iter()
+            .filter(|(input, _)| {
+                self.slots[input.index()]
+                    .as_ref()
+                    .is_some_and(|slot| selected.contains(&slot.node_id()))
+            })
+            .map(|(_, &(node_id, _))| node_id)
+            .collect();
+        selected.extend(constants);
+
+        let mut graph = self.clone();
+        for node_id in self.node_ids() {
+            if !selected.contains(&node_id) {
+                graph.remove_node(node_id);
+            }
         }
+        let mapping = graph.compact();
+        (graph, mapping)
     }
-}
 
-impl Node for DivNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
-    }
+    /// Append all the nodes of another graph into this graph.
+    ///
+    /// The nodes and slots of `other` are added after the existing ones, with
+    /// new identifiers, and the links, input constants, and editor metadata of
+    /// `other` are preserved under those new identifiers. The existing nodes,
+    /// slots, and links of this graph are left untouched. This allows for
+    /// example pasting back a graph extracted with [`subgraph()`].
+    ///
+    /// Returns the mapping from the identifier of each node in `other` to its
+    /// new identifier in this graph, to link the merged nodes to the existing
+    /// ones.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut fragment = Graph::new();
+    /// let time = fragment.add_node(TimeNode::default());
+    /// let sin = fragment.add_node(SinNode::default());
+    /// fragment.connect(time, "time", sin, "in").unwrap();
+    ///
+    /// let mut graph = Graph::new();
+    /// let cos = graph.add_node(CosNode::default());
+    /// let mapping = graph.merge(fragment);
+    /// assert_eq!(graph.node_count(), 3);
+    /// graph.connect(mapping[&sin], "out", cos, "in").unwrap();
+    /// ```
+    ///
+    /// [`subgraph()`]: crate::graph::Graph::subgraph
+    pub fn merge(&mut self, other: Graph) -> HashMap<NodeId, NodeId> {
+        let Graph {
+            nodes,
+            slots,
+            input_constants,
+            node_metadata,
+        } = other;
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if inputs.len() != 2 {
-            return Err(ExprError::GraphEvalError(format!(
-                "Unexpected input count to DivNode::eval(): expected 2, got
-{}",
-                inputs.len()
+        let mut node_mapping = HashMap::new();
+        for (index, node) in nodes.into_iter().enumerate() {
+            let Some(node) = node else {
+                continue;
+            };
+            let old_id = NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap());
+            let new_id = NodeId::new(NonZeroU32::new(self.nodes.len() as u32 + 1).unwrap());
+            self.nodes.push(Some(node));
+            node_mapping.insert(old_id, new_id);
+        }
+
+        // Copy the slots rather than re-creating them from the nodes, to keep
+        // any slot definition updated after the node was added.
+        let mut slot_mapping = HashMap::new();
+        for slot in slots.iter().flatten() {
+            let new_id = SlotId::new(NonZeroU32::new(self.slots.len() as u32 + 1).unwrap());
+            slot_mapping.insert(slot.id(), new_id);
+            self.slots.push(Some(Slot::new(
+                node_mapping[&slot.node_id()],
+                new_id,
+                slot.def().clone(),
             )));
         }
-        let mut inputs = inputs.into_iter();
-        let left = inputs.next().unwrap();
-        let right = inputs.next().unwrap();
-        let div = module.div(left, right);
-        Ok(vec![div])
-    }
-}
+        for slot in slots.into_iter().flatten() {
+            let new_slot = self.slots[slot_mapping[&slot.id()].index()]
+                .as_mut()
+                .unwrap();
+            new_slot.linked_slots = slot
+                .linked_slots
+                .iter()
+                .map(|slot_id| slot_mapping[slot_id])
+                .collect();
+        }
 
-/// Graph node to get any single particle attribute.
-#[derive(Debug, Clone)]
-pub struct AttributeNode {
-    /// The attribute to get.
-    attr: Attribute,
-    /// The output slot corresponding to the get value.
-    slots: [SlotDef; 1],
-}
+        self.input_constants.extend(input_constants.into_iter().map(
+            |(input, (node_id, value))| (slot_mapping[&input], (node_mapping[&node_id], value)),
+        ));
+        self.node_metadata.extend(
+            node_metadata
+                .into_iter()
+                .map(|(node_id, metadata)| (node_mapping[&node_id], metadata)),
+        );
 
-impl Default for AttributeNode {
-    fn default() -> Self {
-        Self::new(Attribute::POSITION)
+        node_mapping
     }
-}
 
-impl AttributeNode {
-    /// Create a new attribute node for the given [`Attribute`].
-    pub fn new(attr: Attribute) -> Self {
-        Self {
-            attr,
-            slots: [SlotDef::output(attr.name(), Some(attr.value_type()))],
+    /// Link an output slot of a node to an input slot of another node.
+    ///
+    /// This is a convenience wrapper around [`try_link()`] which panics if the
+    /// link is rejected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` argument doesn't reference an output slot of an
+    /// existing node, or the `input` argument doesn't reference an input slot
+    /// of an existing node, or if the link is rejected by [`try_link()`].
+    ///
+    /// [`try_link()`]: crate::graph::Graph::try_link
+    pub fn link(&mut self, output: SlotId, input: SlotId) {
+        if let Err(err) = self.try_link(output, input) {
+            panic!("{}", err);
         }
     }
-}
 
-impl AttributeNode {
-    /// Get the attribute this node reads.
-    pub fn attr(&self) -> Attribute {
-        self.attr
+    /// Try to link an output slot of a node to an input slot of another node.
+    ///
+    /// An input slot can only be linked to a single output slot. Linking an
+    /// input slot which is already linked to the same output slot does
+    /// nothing, while linking it to a different output slot is rejected; the
+    /// previous link must be removed first with [`unlink()`], or replaced with
+    /// [`relink()`]. Linking an input slot replaces any constant set on it with
+    /// [`set_input_constant()`] though.
+    ///
+    /// The link is rejected, leaving the graph unchanged, if:
+    /// - the input slot is already linked to another output slot, with
+    ///   [`GraphError::InputAlreadyLinked`].
+    /// - both slots have a [`SlotDef::value_type()`] and those types differ,
+    ///   with [`GraphError::TypeMismatch`]. Variant slots, without a value
+    ///   type, can be linked to any other slot, and their type is checked
+    ///   during evaluation instead.
+    /// - it would create a directed cycle between nodes, that is if the node
+    ///   owning `output` is already reachable downstream of the node owning
+    ///   `input`, with [`GraphError::CycleDetected`].
+    /// - either slot doesn't exist, with [`GraphError::NoSuchSlot`], or
+    ///   `output` is not an output slot or `input` not an input slot, with
+    ///   [`GraphError::WrongDirection`]. This allows linking slots from
+    ///   untrusted sources, like deserialized files, without panicking.
+    ///
+    /// [`unlink()`]: Graph::unlink
+    /// [`relink()`]: Graph::relink
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    pub fn try_link(&mut self, output: SlotId, input: SlotId) -> Result<(), GraphError> {
+        self.link_impl(output, input, false)
     }
 
-    /// Set the attribute this node reads.
-    pub fn set_attr(&mut self, attr: Attribute) {
-        self.attr = attr;
+    /// Link an output slot of a node to an input slot of another node,
+    /// replacing any previous link of the input slot.
+    ///
+    /// This behaves like [`try_link()`], except that if the input slot is
+    /// already linked to another output slot, that previous link is removed
+    /// instead of rejecting the new link. The previous link is only removed if
+    /// the new link is accepted; otherwise the graph is left unchanged.
+    ///
+    /// [`try_link()`]: Graph::try_link
+    pub fn relink(&mut self, output: SlotId, input: SlotId) -> Result<(), GraphError> {
+        self.link_impl(output, input, true)
     }
-}
 
-impl Node for AttributeNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
+    /// Link an output slot of a node to an input slot of another node, with the
+    /// slots referenced by name.
+    ///
+    /// This resolves the output slot named `output_name` of the node `from` and
+    /// the input slot named `input_name` of the node `to`, then links them with
+    /// [`try_link()`]. This avoids looking up each [`SlotId`] beforehand.
+    ///
+    /// # Errors
+    ///
+    /// The graph is left unchanged if:
+    /// - the node `from` doesn't have an output slot named `output_name`, or the
+    ///   node `to` doesn't have an input slot named `input_name`, with
+    ///   [`GraphError::SlotNotFound`].
+    /// - the link is rejected by [`try_link()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # fn main() -> Result<(), GraphError> {
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let sin = graph.add_node(SinNode::default());
+    /// graph.connect(time, "time", sin, "in")?;
+    /// assert!(graph.connect(time, "time", sin, "out").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`try_link()`]: Graph::try_link
+    pub fn connect(
+        &mut self,
+        from: NodeId,
+        output_name: &str,
+        to: NodeId,
+        input_name: &str,
+    ) -> Result<(), GraphError> {
+        let output = self
+            .output_slot(from, output_name)
+            .ok_or(GraphError::SlotNotFound {
+                dir: SlotDir::Output,
+            })?;
+        let input = self
+            .input_slot(to, input_name)
+            .ok_or(GraphError::SlotNotFound {
+                dir: SlotDir::Input,
+            })?;
+        self.try_link(output, input)
     }
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if !inputs.is_empty() {
-            return Err(ExprError::GraphEvalError(
-                "Unexpected non-empty input to
-AttributeNode::eval()."
-                    .to_string(),
-            ));
+    fn link_impl(
+        &mut self,
+        output: SlotId,
+        input: SlotId,
+        replace: bool,
+    ) -> Result<(), GraphError> {
+        self.check_link_slots(output, input)?;
+
+        if !replace && !self.input_constants.contains_key(&input) {
+            if let Some(existing) = self.input_source(input) {
+                if existing != output {
+                    return Err(GraphError::InputAlreadyLinked { input, existing });
+                }
+            }
         }
-        let attr = module.attr(self.attr);
-        Ok(vec![attr])
-    }
-}
 
-/// Graph node to get various time values related to the effect system.
-#[derive(Debug, Clone)]
-pub struct TimeNode {
-    /// Output slots corresponding to the various time-related quantities.
-    slots: [SlotDef; 2],
-}
+        let out_slot = self.get_slot(output);
+        let in_slot = self.get_slot(input);
+        if let (Some(output), Some(input)) =
+            (out_slot.def().value_type(), in_slot.def().value_type())
+        {
+            if output != input {
+                return Err(GraphError::TypeMismatch {
+                    output_slot: out_slot.id(),
+                    input_slot: in_slot.id(),
+                    output,
+                    input,
+                });
+            }
+        }
+
+        let from = out_slot.node_id();
+        let to = in_slot.node_id();
+        if self.is_downstream(from, to) {
+            return Err(GraphError::CycleDetected { from, to });
+        }
+
+        // Linking another output replaces any constant set on the input
+        if self
+            .input_constants
+            .get(&input)
+            .is_some_and(|&(node_id, _)| node_id != from)
+        {
+            self.clear_input_constant(input);
+        }
+
+        // An input has a single source; unlink any previous one
+        if let Some(prev_output) = self.input_source(input) {
+            self.get_slot_mut(prev_output).unlink_from(input);
+        }
+
+        let out_slot = self.get_slot_mut(output);
+        assert!(out_slot.is_output());
+        out_slot.link_to(input);
+
+        let in_slot = self.get_slot_mut(input);
+        assert!(in_slot.is_input());
+        in_slot.link_input(output);
+
+        Ok(())
+    }
+
+    /// Check if `node_id` is `start` or any of the nodes reachable downstream
+    /// of it by following links from output slots to input slots.
+    fn is_downstream(&self, node_id: NodeId, start: NodeId) -> bool {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![start];
+        while let Some(cur) = stack.pop() {
+            if cur == node_id {
+                return true;
+            }
+            if visited[cur.index()] {
+                continue;
+            }
+            visited[cur.index()] = true;
+            stack.extend(self.outgoing_nodes(cur));
+        }
+        false
+    }
+
+    /// Insert a new node onto an existing link, splitting it in two.
+    ///
+    /// The existing `(output, input)` link is removed, the `output` slot is
+    /// linked to the input slot of the new node named `in_slot_name`, and the
+    /// output slot of the new node named `out_slot_name` is linked to the
+    /// `input` slot. This is typically used by editors when dropping a node
+    /// onto a wire. On success, returns the identifier of the new node.
+    ///
+    /// If `output` is the hidden literal node of a constant set on `input` with
+    /// [`set_input_constant()`], the constant moves onto the input slot of the
+    /// new node instead.
+    ///
+    /// # Errors
+    ///
+    /// The graph is left unchanged, and the node not added, if:
+    /// - `output` is not linked to `input`, with [`GraphError::LinkNotFound`].
+    /// - the node doesn't have an input slot named `in_slot_name` or an output
+    ///   slot named `out_slot_name`, with [`GraphError::SlotNotFound`].
+    /// - the value type of one of the new links mismatches, with
+    ///   [`GraphError::TypeMismatch`], as for [`try_link()`].
+    /// - either slot doesn't exist, with [`GraphError::NoSuchSlot`], or
+    ///   `output` is not an output slot or `input` not an input slot, with
+    ///   [`GraphError::WrongDirection`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// let add_lhs = graph.input_slot(add, "lhs").unwrap();
+    /// graph.link(time_out, add_lhs);
+    ///
+    /// // time -> add becomes time -> sin -> add
+    /// let sin = graph
+    ///     .insert_node_between((time_out, add_lhs), SinNode::default(), "in", "out")
+    ///     .unwrap();
+    /// assert_eq!(graph.input_source(add_lhs), graph.output_slot(sin, "out"));
+    /// ```
+    ///
+    /// [`try_link()`]: Graph::try_link
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    pub fn insert_node_between<N>(
+        &mut self,
+        link: (SlotId, SlotId),
+        node: N,
+        in_slot_name: &str,
+        out_slot_name: &str,
+    ) -> Result<NodeId, GraphError>
+    where
+        N: Node + 'static,
+    {
+        let (output, input) = link;
+        self.check_link_slots(output, input)?;
+        let out_slot = self.get_slot(output);
+        let in_slot = self.get_slot(input);
+        if self.input_source(input) != Some(output) {
+            return Err(GraphError::LinkNotFound { output, input });
+        }
+
+        let find_slot = |dir: SlotDir, name: &str| {
+            node.slots()
+                .iter()
+                .position(|def| def.dir() == dir && def.name() == name)
+                .ok_or(GraphError::SlotNotFound { dir })
+        };
+        let new_in = find_slot(SlotDir::Input, in_slot_name)?;
+        let new_out = find_slot(SlotDir::Output, out_slot_name)?;
+
+        // Check the types before editing anything, to leave the graph unchanged
+        // on error. The slot identifiers of the new node are the ones it will
+        // be assigned once added.
+        let first_slot_index = self.slots.len() as u32 + 1;
+        let new_slot_id =
+            |index: usize| SlotId::new(NonZeroU32::new(first_slot_index + index as u32).unwrap());
+        let new_in_type = node.slots()[new_in].value_type();
+        let new_out_type = node.slots()[new_out].value_type();
+        for (output_slot, input_slot, output, input) in [
+            (
+                output,
+                new_slot_id(new_in),
+                out_slot.def().value_type(),
+                new_in_type,
+            ),
+            (
+                new_slot_id(new_out),
+                input,
+                new_out_type,
+                in_slot.def().value_type(),
+            ),
+        ] {
+            if let (Some(output), Some(input)) = (output, input) {
+                if output != input {
+                    return Err(GraphError::TypeMismatch {
+                        output_slot,
+                        input_slot,
+                        output,
+                        input,
+                    });
+                }
+            }
+        }
+
+        // A constant set on the input moves onto the input of the new node, since
+        // unlinking it removes its hidden literal node.
+        let constant = self.clear_input_constant(input);
+        if constant.is_none() {
+            self.unlink(output, input);
+        }
+        let node_id = self.add_node(node);
+        let new_in = self.input_slot(node_id, in_slot_name).unwrap();
+        let new_out = self.output_slot(node_id, out_slot_name).unwrap();
+        match constant {
+            Some(value) => self.set_input_constant(new_in, value),
+            None => self.link(output, new_in),
+        }
+        self.link(new_out, input);
+        Ok(node_id)
+    }
+
+    /// Unlink an output slot of a node from an input slot of another node.
+    ///
+    /// This is a convenience wrapper around [`try_unlink()`] which panics if
+    /// the slots are invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` argument doesn't reference an output slot of an
+    /// existing node, or the `input` argument doesn't reference an input slot
+    /// of an existing node.
+    ///
+    /// [`try_unlink()`]: crate::graph::Graph::try_unlink
+    pub fn unlink(&mut self, output: SlotId, input: SlotId) {
+        if let Err(err) = self.try_unlink(output, input) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Try to unlink an output slot of a node from an input slot of another
+    /// node.
+    ///
+    /// This does nothing if the slots are valid but not linked together.
+    ///
+    /// # Errors
+    ///
+    /// The graph is left unchanged if either slot doesn't exist, with
+    /// [`GraphError::NoSuchSlot`], or if `output` is not an output slot or
+    /// `input` not an input slot, with [`GraphError::WrongDirection`].
+    pub fn try_unlink(&mut self, output: SlotId, input: SlotId) -> Result<(), GraphError> {
+        self.check_link_slots(output, input)?;
+
+        if self
+            .input_constants
+            .get(&input)
+            .is_some_and(|&(node_id, _)| node_id == self.get_slot(output).node_id())
+        {
+            self.clear_input_constant(input);
+            return Ok(());
+        }
+        if self.get_slot_mut(output).unlink_from(input) {
+            self.get_slot_mut(input).unlink_input();
+        }
+        Ok(())
+    }
+
+    /// Unlink all remote slots from a given slot.
+    pub fn unlink_all(&mut self, slot_id: SlotId) {
+        if self.input_constants.contains_key(&slot_id) {
+            self.clear_input_constant(slot_id);
+        }
+        let slot = self.get_slot_mut(slot_id);
+        let linked_slots = std::mem::take(&mut slot.linked_slots);
+        for remote_id in &linked_slots {
+            let remote_slot = self.get_slot_mut(*remote_id);
+            if remote_slot.is_input() {
+                remote_slot.unlink_input();
+            } else {
+                remote_slot.unlink_from(slot_id);
+            }
+        }
+    }
+
+    /// Unlink all the slots of a node.
+    ///
+    /// This applies [`unlink_all()`] to each slot of the node, disconnecting
+    /// both its inputs from the outputs feeding them, and its outputs from all
+    /// the downstream inputs they feed. Any constant assigned to an input of
+    /// the node is cleared too. The node itself is left in the graph. This is a
+    /// no-op if the node is already disconnected, or doesn't exist.
+    ///
+    /// [`unlink_all()`]: crate::graph::Graph::unlink_all
+    pub fn unlink_node(&mut self, node_id: NodeId) {
+        for slot_id in self.slots(node_id) {
+            self.unlink_all(slot_id);
+        }
+    }
+
+    /// Get the output slot linked to an input slot, if any.
+    ///
+    /// An input slot is linked to at most one output slot, which provides the
+    /// value flowing into that input.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// let add_lhs = graph.input_slot(add, "lhs").unwrap();
+    /// let add_rhs = graph.input_slot(add, "rhs").unwrap();
+    /// graph.link(time_out, add_lhs);
+    /// assert_eq!(graph.input_source(add_lhs), Some(time_out));
+    /// assert_eq!(graph.input_source(add_rhs), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `input` argument doesn't reference an input slot of an
+    /// existing node.
+    pub fn input_source(&self, input: SlotId) -> Option<SlotId> {
+        let slot = self.get_slot(input);
+        assert!(slot.is_input());
+        slot.linked_slots.first().copied()
+    }
+
+    /// Get all the input slots linked to an output slot.
+    ///
+    /// An output slot can be linked to any number of input slots, which all
+    /// receive the value produced by that output. The slots are returned in the
+    /// order they were linked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` argument doesn't reference an output slot of an
+    /// existing node.
+    pub fn output_targets(&self, output: SlotId) -> Vec<SlotId> {
+        let slot = self.get_slot(output);
+        assert!(slot.is_output());
+        slot.linked_slots.clone()
+    }
+
+    /// Get all the links of the graph, as `(output, input)` slot pairs.
+    ///
+    /// Each link is returned exactly once. The links are ordered by output
+    /// slot, in the order the slots were created, then for each output slot in
+    /// the order its inputs were linked, like [`output_targets()`]. So an output
+    /// slot linked to three input slots yields three pairs. This is typically
+    /// used by editors to draw the wires of the graph; use [`linked_output()`]
+    /// and [`linked_inputs()`] to query the links of a single slot.
+    ///
+    /// The links include the wires from the hidden [`LiteralNode`]s holding
+    /// the constants set with [`set_input_constant()`]. Editors showing those
+    /// constants directly on the input slot should skip the links from the
+    /// output slots of the nodes for which [`is_input_constant_node()`] is
+    /// `true`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// let add_lhs = graph.input_slot(add, "lhs").unwrap();
+    /// let add_rhs = graph.input_slot(add, "rhs").unwrap();
+    /// graph.link(time_out, add_lhs);
+    /// graph.link(time_out, add_rhs);
+    /// assert_eq!(graph.links(), vec![(time_out, add_lhs), (time_out, add_rhs)]);
+    ///
+    /// // Hide the wire of an input constant
+    /// graph.set_input_constant(add_rhs, 1.0.into());
+    /// let hidden: Vec<SlotId> = graph
+    ///     .node_ids()
+    ///     .filter(|&node_id| graph.is_input_constant_node(node_id))
+    ///     .flat_map(|node_id| graph.output_slots(node_id))
+    ///     .collect();
+    /// let wires: Vec<_> = graph
+    ///     .links()
+    ///     .into_iter()
+    ///     .filter(|(output, _)| !hidden.contains(output))
+    ///     .collect();
+    /// assert_eq!(wires, vec![(time_out, add_lhs)]);
+    /// ```
+    ///
+    /// [`linked_output()`]: Graph::linked_output
+    /// [`linked_inputs()`]: Graph::linked_inputs
+    /// [`output_targets()`]: Graph::output_targets
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    /// [`is_input_constant_node()`]: Graph::is_input_constant_node
+    pub fn links(&self) -> Vec<(SlotId, SlotId)> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|slot| slot.is_output())
+            .flat_map(|slot| slot.linked_slots.iter().map(|&input| (slot.id(), input)))
+            .collect()
+    }
+
+    /// Get all the input slots linked to an output slot.
+    ///
+    /// This is the same as [`output_targets()`], and returns the inputs of all
+    /// the pairs of [`links()`] with that output, in the same order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` argument doesn't reference an output slot of an
+    /// existing node.
+    ///
+    /// [`output_targets()`]: Graph::output_targets
+    /// [`links()`]: Graph::links
+    pub fn linked_inputs(&self, output: SlotId) -> Vec<SlotId> {
+        self.output_targets(output)
+    }
+
+    /// Get the output slot linked to an input slot, if any.
+    ///
+    /// This is the same as [`input_source()`], and returns the output of the
+    /// single pair of [`links()`] with that input, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `input` argument doesn't reference an input slot of an
+    /// existing node.
+    ///
+    /// [`input_source()`]: Graph::input_source
+    /// [`links()`]: Graph::links
+    pub fn linked_output(&self, input: SlotId) -> Option<SlotId> {
+        self.input_source(input)
+    }
+
+    /// Set a constant value on an input slot.
+    ///
+    /// The constant is held by a hidden [`LiteralNode`] linked to the input,
+    /// which the graph creates the first time a constant is set, and updates
+    /// in place afterward. Unlike a [`SlotDef::default_value()`], which is
+    /// fixed by the node type, the constant is part of the graph, so can be
+    /// edited per input and is saved along with the graph. Any previous link
+    /// of the input is replaced.
+    ///
+    /// The constant is removed with [`clear_input_constant()`], or when the
+    /// input is linked to another output, or unlinked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let add = graph.add_node(AddNode::default());
+    /// let add_lhs = graph.input_slot(add, "lhs").unwrap();
+    /// graph.set_input_constant(add_lhs, 3_f32.into());
+    /// assert_eq!(graph.input_constant(add_lhs), Some(3_f32.into()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `input` argument doesn't reference an input slot of an
+    /// existing node, or if the input slot has a [`SlotDef::value_type()`]
+    /// different from the type of `value`.
+    ///
+    /// [`clear_input_constant()`]: Graph::clear_input_constant
+    pub fn set_input_constant(&mut self, input: SlotId, value: Value) {
+        let in_slot = self.get_slot(input);
+        assert!(in_slot.is_input());
+        if let Some(input_type) = in_slot.def().value_type() {
+            assert_eq!(
+                input_type,
+                value.value_type(),
+                "Cannot set a constant of type {:?} on input slot '{}' of type {:?}",
+                value.value_type(),
+                in_slot.def().name(),
+                input_type
+            );
+        }
+
+        if let Some((node_id, constant)) = self.input_constants.get_mut(&input) {
+            let node_id = *node_id;
+            *constant = value;
+            self.node_mut(node_id)
+                .and_then(|node| node.as_literal_mut())
+                .unwrap()
+                .set_value(value);
+            // The type of the literal may change with its value
+            let output = self.output_slot(node_id, "value").unwrap();
+            self.get_slot_mut(output).def = SlotDef::output("value", Some(value.value_type()));
+        } else {
+            let node_id = self.add_node(LiteralNode::new(value));
+            let output = self.output_slot(node_id, "value").unwrap();
+            // A literal node has no input, so can't create a cycle
+            self.relink(output, input).unwrap();
+            self.input_constants.insert(input, (node_id, value));
+        }
+    }
+
+    /// Remove the constant value set on an input slot.
+    ///
+    /// The hidden [`LiteralNode`] holding the constant is removed, leaving the
+    /// input slot unlinked. Returns the removed constant value, or `None` if no
+    /// constant was set on the input.
+    ///
+    /// See [`set_input_constant()`] for details.
+    ///
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    pub fn clear_input_constant(&mut self, input: SlotId) -> Option<Value> {
+        let (node_id, value) = self.input_constants.remove(&input)?;
+        self.remove_node(node_id);
+        Some(value)
+    }
+
+    /// Get the constant value set on an input slot, if any.
+    ///
+    /// See [`set_input_constant()`] for details.
+    ///
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    pub fn input_constant(&self, input: SlotId) -> Option<Value> {
+        self.input_constants.get(&input).map(|&(_, value)| value)
+    }
+
+    /// Check if a node is a hidden [`LiteralNode`] holding the constant of an
+    /// input slot, set with [`set_input_constant()`].
+    ///
+    /// Editors typically don't display those nodes, and show the constant
+    /// directly on the input slot instead.
+    ///
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    pub fn is_input_constant_node(&self, node_id: NodeId) -> bool {
+        self.input_constants
+            .values()
+            .any(|&(constant_node_id, _)| constant_node_id == node_id)
+    }
+
+    /// Infer the value type of a slot from the links of the graph.
+    ///
+    /// If the slot has a [`SlotDef::value_type()`], that type is returned.
+    /// Otherwise the slot is a variant slot, and its type is resolved as:
+    /// - for an input slot, the inferred type of the output slot it's linked
+    ///   to, if any;
+    /// - for an output slot, the inferred type of all the variant input slots
+    ///   of the same node. This matches arithmetic nodes like [`AddNode`] or
+    ///   [`NormalizeNode`], whose output has the type of their inputs.
+    ///
+    /// Returns `None` if the type can't be resolved, either because a variant
+    /// input slot is not linked, or because the variant inputs of a node
+    /// resolve to different types. In the latter case the type depends on the
+    /// node, and is only known by evaluating it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let pos = graph.add_node(AttributeNode::new(Attribute::POSITION));
+    /// let add = graph.add_node(AddNode::default());
+    /// let pos_out = graph.output_slot(pos, "position").unwrap();
+    /// let add_out = graph.output_slot(add, "result").unwrap();
+    /// assert_eq!(graph.infer_slot_type(add_out), None);
+    /// graph.link(pos_out, graph.input_slot(add, "lhs").unwrap());
+    /// graph.link(pos_out, graph.input_slot(add, "rhs").unwrap());
+    /// assert_eq!(
+    ///     graph.infer_slot_type(add_out),
+    ///     Some(ValueType::Vector(VectorType::VEC3F))
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `slot_id` argument doesn't reference an existing slot.
+    pub fn infer_slot_type(&self, slot_id: SlotId) -> Option<ValueType> {
+        let slot = self.get_slot(slot_id);
+        if let Some(value_type) = slot.def().value_type() {
+            return Some(value_type);
+        }
+
+        if slot.is_input() {
+            return self
+                .input_source(slot_id)
+                .and_then(|output| self.infer_slot_type(output));
+        }
+
+        // All variant inputs of the node must agree on a single type
+        let mut inferred = None;
+        for input in self.input_slots(slot.node_id()) {
+            if self.get_slot(input).def().value_type().is_some() {
+                continue;
+            }
+            let value_type = self.infer_slot_type(input)?;
+            if inferred.is_some_and(|inferred| inferred != value_type) {
+                return None;
+            }
+            inferred = Some(value_type);
+        }
+        inferred
+    }
+
+    /// Get the value type of a slot, for display and validation in an editor.
+    ///
+    /// This is the type returned by [`infer_slot_type()`], combining the
+    /// declared type of the slot with the type inferred from the links for
+    /// variant slots. Unlike [`infer_slot_type()`], this returns `None` instead
+    /// of panicking if the slot doesn't exist.
+    ///
+    /// [`infer_slot_type()`]: Graph::infer_slot_type
+    pub fn slot_value_type(&self, slot_id: SlotId) -> Option<ValueType> {
+        self.try_get_slot(slot_id).ok()?;
+        self.infer_slot_type(slot_id)
+    }
+
+    /// Check if the value type of a slot is a vector type.
+    ///
+    /// Returns `false` if the slot doesn't exist or its type is unknown. See
+    /// [`slot_value_type()`] for details.
+    ///
+    /// [`slot_value_type()`]: Graph::slot_value_type
+    pub fn slot_is_vector(&self, slot_id: SlotId) -> bool {
+        self.slot_value_type(slot_id)
+            .is_some_and(|value_type| value_type.is_vector())
+    }
+
+    /// Get the number of components of the value of a slot.
+    ///
+    /// This is `1` for scalars, and the number of components of vectors.
+    /// Returns `None` for matrices, or if the slot doesn't exist or its type
+    /// is unknown. See [`slot_value_type()`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let pos = graph.add_node(AttributeNode::new(Attribute::POSITION));
+    /// let pos_out = graph.output_slot(pos, "position").unwrap();
+    /// assert!(graph.slot_is_vector(pos_out));
+    /// assert_eq!(graph.slot_component_count(pos_out), Some(3));
+    /// ```
+    ///
+    /// [`slot_value_type()`]: Graph::slot_value_type
+    pub fn slot_component_count(&self, slot_id: SlotId) -> Option<usize> {
+        match self.slot_value_type(slot_id)? {
+            ValueType::Scalar(_) => Some(1),
+            ValueType::Vector(vector_type) => Some(vector_type.count()),
+            ValueType::Matrix(_) => None,
+        }
+    }
+
+    /// Get all slots of a node.
+    pub fn slots(&self, node_id: NodeId) -> Vec<SlotId> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|s| {
+                if s.node_id() == node_id {
+                    Some(s.id())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Get a given input slot of a node by name.
+    pub fn input_slot<'a, 'b: 'a, S: Into<&'b str>>(
+        &'a self,
+        node_id: NodeId,
+        name: S,
+    ) -> Option<SlotId> {
+        let name = name.into();
+        self.slots
+            .iter()
+            .flatten()
+            .find(|s| s.node_id() == node_id && s.is_input() && s.def().name() == name)
+            .map(|s| s.id)
+    }
+
+    /// Get all input slots of a node.
+    pub fn input_slots(&self, node_id: NodeId) -> Vec<SlotId> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|s| {
+                if s.node_id() == node_id && s.is_input() {
+                    Some(s.id())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Get a given output slot of a node by name.
+    pub fn output_slot<'a, 'b: 'a, S: Into<&'b str>>(
+        &'a self,
+        node_id: NodeId,
+        name: S,
+    ) -> Option<SlotId> {
+        let name = name.into();
+        self.slots
+            .iter()
+            .flatten()
+            .find(|s| s.node_id() == node_id && s.is_output() && s.def().name() == name)
+            .map(|s| s.id)
+    }
+
+    /// Get all output slots of a node.
+    pub fn output_slots(&self, node_id: NodeId) -> Vec<SlotId> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|s| {
+                if s.node_id() == node_id && s.is_output() {
+                    Some(s.id())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Find a slot ID by slot name.
+    pub fn get_slot_id<'a, 'b: 'a, S: Into<&'b str>>(&'a self, name: S) -> Option<SlotId> {
+        let name = name.into();
+        self.slots
+            .iter()
+            .flatten()
+            .find(|&s| s.def().name() == name)
+            .map(|s| s.id)
+    }
+
+    /// Evaluate all the nodes of the graph into expressions of a module.
+    ///
+    /// The nodes are evaluated in dependency order, each node receiving as
+    /// inputs the expressions produced by the output slots its input slots are
+    /// linked to. Unlinked input slots with a [`SlotDef::default_value()`]
+    /// receive a literal expression of that value. Other unlinked input slots
+    /// are skipped, so a node with such inputs receives fewer inputs than it
+    /// has input slots, which generally produces an error.
+    ///
+    /// On success, returns the expression produced by each output slot of
+    /// each node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ExprError::GraphEvalError`] if a node fails to evaluate.
+    /// The error message contains the name, [`NodeId`], and label of the node,
+    /// as well as the names of its unlinked input slots if any, for example:
+    ///
+    /// ```txt
+    /// AddNode(#2 'gravity'): expected 2 inputs, got 1 (unlinked input slots: rhs)
+    /// ```
+    ///
+    /// Also returns an [`ExprError::GraphEvalError`] if the graph contains a
+    /// cycle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// graph.link(time_out, graph.input_slot(add, "lhs").unwrap());
+    /// graph.link(time_out, graph.input_slot(add, "rhs").unwrap());
+    ///
+    /// let mut module = Module::default();
+    /// let outputs = graph.eval_all(&mut module).unwrap();
+    /// let sum = outputs[&graph.output_slot(add, "result").unwrap()];
+    /// ```
+    pub fn eval_all(&self, module: &mut Module) -> Result<HashMap<SlotId, ExprHandle>, ExprError> {
+        let pending = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_some())
+            .map(|(index, _)| NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap()))
+            .collect();
+        self.eval_nodes(module, pending)
+    }
+
+    /// Evaluate a set of nodes, like [`eval_all()`] does for all nodes of the
+    /// graph. The upstream nodes of each node must be part of the set.
+    ///
+    /// [`eval_all()`]: Graph::eval_all
+    fn eval_nodes(
+        &self,
+        module: &mut Module,
+        nodes: Vec<NodeId>,
+    ) -> Result<HashMap<SlotId, ExprHandle>, ExprError> {
+        let order = self.sort_nodes(nodes).map_err(cycle_error)?;
+        let mut outputs = HashMap::new();
+        for node_id in order {
+            let input_slots = self.input_slots(node_id);
+            let node = self.nodes[node_id.index()].as_ref().unwrap();
+            let inputs = input_slots
+                .iter()
+                .filter_map(|&input| self.input_expr(module, input, &outputs))
+                .collect();
+            let wrap_error = |err: ExprError| {
+                let msg = match err {
+                    ExprError::GraphEvalError(msg) => msg,
+                    err => err.to_string(),
+                };
+                let unlinked = input_slots
+                    .iter()
+                    .filter(|&&input| {
+                        self.input_source(input).is_none()
+                            && self.get_slot(input).def().default_value().is_none()
+                    })
+                    .map(|&input| self.get_slot(input).def().name())
+                    .collect::<Vec<_>>();
+                let msg = if unlinked.is_empty() {
+                    msg
+                } else {
+                    format!("{} (unlinked input slots: {})", msg, unlinked.join(", "))
+                };
+                ExprError::GraphEvalError(format!("{}: {}", self.node_error_context(node_id), msg))
+            };
+
+            let exprs = node.eval(module, inputs).map_err(wrap_error)?;
+            let output_slots = self.output_slots(node_id);
+            if exprs.len() != output_slots.len() {
+                return Err(wrap_error(ExprError::GraphEvalError(format!(
+                    "produced {} output(s) for {} output slot(s)",
+                    exprs.len(),
+                    output_slots.len()
+                ))));
+            }
+            outputs.extend(output_slots.into_iter().zip(exprs));
+        }
+
+        Ok(outputs)
+    }
+
+    /// Get all the nodes of the graph in dependency order.
+    ///
+    /// Each node is placed after all the nodes it depends on, that is all the
+    /// nodes owning an output slot linked to one of its input slots. So the
+    /// source nodes, like [`AttributeNode`] or [`TimeNode`], come first. This
+    /// is the order in which [`eval_all()`] evaluates the nodes.
+    ///
+    /// The order is deterministic for a given graph: among the nodes whose
+    /// dependencies are all placed, nodes are placed in increasing [`NodeId`]
+    /// order, in successive passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GraphError::CycleDetected`] with one of the links of a
+    /// directed cycle if the graph contains any. Such links can't be created
+    /// with [`try_link()`], but may be present in a graph loaded from a file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let add = graph.add_node(AddNode::default());
+    /// let time = graph.add_node(TimeNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// graph.link(time_out, graph.input_slot(add, "lhs").unwrap());
+    /// graph.link(time_out, graph.input_slot(add, "rhs").unwrap());
+    ///
+    /// assert_eq!(graph.topological_order(), Ok(vec![time, add]));
+    /// ```
+    ///
+    /// [`eval_all()`]: Graph::eval_all
+    /// [`try_link()`]: Graph::try_link
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, GraphError> {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_some())
+            .map(|(index, _)| NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap()))
+            .collect();
+        self.sort_nodes(nodes)
+    }
+
+    /// Sort a set of nodes in dependency order, like [`topological_order()`]
+    /// does for all nodes of the graph. The upstream nodes of each node must
+    /// be part of the set.
+    ///
+    /// [`topological_order()`]: Graph::topological_order
+    fn sort_nodes(&self, mut pending: Vec<NodeId>) -> Result<Vec<NodeId>, GraphError> {
+        let upstream = |node_id: NodeId| {
+            self.input_slots(node_id)
+                .into_iter()
+                .filter_map(|input| self.input_source(input))
+                .map(|output| self.get_slot(output).node_id())
+        };
+
+        pending.sort_unstable_by_key(|node_id| node_id.id());
+        let mut placed = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let count = pending.len();
+            pending.retain(|&node_id| {
+                if !upstream(node_id).all(|source| placed[source.index()]) {
+                    return true;
+                }
+                placed[node_id.index()] = true;
+                order.push(node_id);
+                false
+            });
+
+            // All remaining nodes depend on another remaining node, so walking
+            // upstream from any of them eventually closes a cycle.
+            if pending.len() == count {
+                let mut visited = vec![false; self.nodes.len()];
+                let mut to = pending[0];
+                loop {
+                    visited[to.index()] = true;
+                    let from = upstream(to).find(|source| !placed[source.index()]).unwrap();
+                    if visited[from.index()] {
+                        return Err(GraphError::CycleDetected { from, to });
+                    }
+                    to = from;
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Evaluate the expression produced by a single output slot.
+    ///
+    /// Unlike [`eval_all()`], only the nodes upstream of the node owning
+    /// `output` are evaluated. Those nodes are sorted like
+    /// [`topological_order()`] does, and each
+    /// of them is evaluated exactly once, receiving as inputs the expressions
+    /// produced by the output slots its input slots are linked to, in input
+    /// slot order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ExprError::GraphEvalError`] if an input slot of one of
+    /// the evaluated nodes is not linked, if those nodes form a cycle, or if
+    /// one of them fails to evaluate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` doesn't reference an output slot of an existing
+    /// node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// graph.link(time_out, graph.input_slot(add, "lhs").unwrap());
+    /// graph.link(time_out, graph.input_slot(add, "rhs").unwrap());
+    ///
+    /// let mut module = Module::default();
+    /// let sum = graph
+    ///     .eval(&mut module, graph.output_slot(add, "result").unwrap())
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`eval_all()`]: Graph::eval_all
+    /// [`topological_order()`]: Graph::topological_order
+    pub fn eval(&self, module: &mut Module, output: SlotId) -> Result<ExprHandle, ExprError> {
+        let slot = self.get_slot(output);
+        assert!(slot.is_output());
+
+        // Only the upstream nodes are evaluated, so only their unlinked inputs
+        // matter.
+        let nodes = self
+            .reachable_nodes(&[slot.node_id()])
+            .into_iter()
+            .collect();
+        let order = self.sort_nodes(nodes).map_err(cycle_error)?;
+        for &node_id in &order {
+            for input in self.input_slots(node_id) {
+                if self.input_source(input).is_none()
+                    && self.get_slot(input).def().default_value().is_none()
+                {
+                    return Err(ExprError::GraphEvalError(format!(
+                        "{}: input slot '{}' is not linked",
+                        self.node_error_context(node_id),
+                        self.get_slot(input).def().name()
+                    )));
+                }
+            }
+        }
+
+        let outputs = self.eval_nodes(module, order)?;
+        Ok(outputs[&output])
+    }
+
+    /// Evaluate the expression produced by a single output slot, folding its
+    /// constant operations.
+    ///
+    /// This is equivalent to [`eval()`] followed by [`Module::fold_constants()`],
+    /// so any subgraph made only of literals feeding some arithmetic nodes like
+    /// [`AddNode`] or [`MulNode`] produces a single literal, computed at build
+    /// time on the CPU, instead of an expression evaluated by the shader. Note
+    /// that this folds all the constant operations of `module`, including the
+    /// ones not produced by this graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`eval()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let lhs = graph.add_node(LiteralNode::new(3.));
+    /// let rhs = graph.add_node(LiteralNode::new(2.));
+    /// let add = graph.add_node(AddNode::default());
+    /// graph.link(
+    ///     graph.output_slot(lhs, "value").unwrap(),
+    ///     graph.input_slot(add, "lhs").unwrap(),
+    /// );
+    /// graph.link(
+    ///     graph.output_slot(rhs, "value").unwrap(),
+    ///     graph.input_slot(add, "rhs").unwrap(),
+    /// );
+    ///
+    /// let mut module = Module::default();
+    /// let sum = graph
+    ///     .eval_folded(&mut module, graph.output_slot(add, "result").unwrap())
+    ///     .unwrap();
+    /// assert_eq!(module.get(sum), Some(&Expr::Literal(LiteralExpr::new(5.))));
+    /// ```
+    ///
+    /// [`eval()`]: Graph::eval
+    pub fn eval_folded(
+        &self,
+        module: &mut Module,
+        output: SlotId,
+    ) -> Result<ExprHandle, ExprError> {
+        let expr = self.eval(module, output)?;
+        module.fold_constants();
+        Ok(expr)
+    }
+
+    /// Check the graph for structural errors, and return all of them.
+    ///
+    /// Unlike [`eval_all()`] which stops on the first error, this collects
+    /// every problem found, to allow a UI to highlight all the offending nodes
+    /// and slots at once. The errors reported are:
+    /// - [`GraphError::UnlinkedInput`] for each input slot not linked to any
+    ///   output slot.
+    /// - [`GraphError::TypeMismatch`] for each link between two slots with
+    ///   different value types.
+    /// - [`GraphError::CycleDetected`] for each link part of a directed cycle.
+    /// - [`GraphError::InvalidInputCount`] for each node whose number of input
+    ///   slots in the graph differs from the number of input slots declared by
+    ///   its [`Node::slots()`].
+    ///
+    /// Links with mismatching types or forming a cycle can't be created with
+    /// [`try_link()`], but may be present in a graph loaded from a file. An
+    /// empty list means the graph is structurally valid, although its
+    /// evaluation may still fail if a node rejects the type of its variant
+    /// inputs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// let rhs = graph.input_slot(add, "rhs").unwrap();
+    /// graph.link(time_out, graph.input_slot(add, "lhs").unwrap());
+    ///
+    /// let errors = graph.validate();
+    /// assert!(errors.contains(&GraphError::UnlinkedInput {
+    ///     node: add,
+    ///     slot: rhs
+    /// }));
+    /// ```
+    ///
+    /// [`eval_all()`]: Graph::eval_all
+    /// [`try_link()`]: Graph::try_link
+    pub fn validate(&self) -> Vec<GraphError> {
+        let mut errors = vec![];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let Some(node) = node else {
+                continue;
+            };
+            let node_id = NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap());
+
+            let inputs = self.input_slots(node_id);
+            for &input in &inputs {
+                let Some(output) = self.input_source(input) else {
+                    if self.get_slot(input).def().default_value().is_none() {
+                        errors.push(GraphError::UnlinkedInput {
+                            node: node_id,
+                            slot: input,
+                        });
+                    }
+                    continue;
+                };
+
+                let out_slot = self.get_slot(output);
+                let out_type = out_slot.def().value_type();
+                let in_type = self.get_slot(input).def().value_type();
+                if let (Some(out_type), Some(in_type)) = (out_type, in_type) {
+                    if out_type != in_type {
+                        errors.push(GraphError::TypeMismatch {
+                            output_slot: output,
+                            input_slot: input,
+                            output: out_type,
+                            input: in_type,
+                        });
+                    }
+                }
+
+                // The link closes a cycle if this node reaches back its source
+                let from = out_slot.node_id();
+                if self.is_downstream(from, node_id) {
+                    errors.push(GraphError::CycleDetected { from, to: node_id });
+                }
+            }
+
+            // The node evaluates one input per input slot it declares, so a
+            // graph loaded from a file with a different number of slots for
+            // that node can't be evaluated.
+            let expected = node.slots().iter().filter(|s| s.is_input()).count();
+            if inputs.len() != expected {
+                errors.push(GraphError::InvalidInputCount {
+                    node: node_id,
+                    inputs: inputs.len(),
+                });
+            }
+        }
+        errors
+    }
+
+    /// Suggest a particle capacity for the effect described by this graph.
+    ///
+    /// The capacity is estimated from the [`Spawner`]s of all the nodes
+    /// configuring one, like [`SpawnerNode`], and the maximum particle lifetime
+    /// of all the nodes setting one, like [`LifetimeNode`]. The estimate is the
+    /// maximum number of particles spawned over a duration equal to that
+    /// lifetime, which is the maximum number of particles alive at once.
+    ///
+    /// Returns `None` if the graph doesn't contain both a spawner-configuring
+    /// node and a lifetime-setting node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// graph.add_node(SpawnerNode::new(Spawner::rate(30.0.into())));
+    /// graph.add_node(LifetimeNode::new(2.));
+    /// assert_eq!(graph.suggest_capacity(), Some(60));
+    /// ```
+    pub fn suggest_capacity(&self) -> Option<u32> {
+        let lifetime = self
+            .nodes
+            .iter()
+            .flatten()
+            .filter_map(|node| node.max_lifetime())
+            .reduce(f32::max)?;
+        self.nodes
+            .iter()
+            .flatten()
+            .filter_map(|node| node.spawner())
+            .map(|spawner| {
+                let count = spawner.count().range()[1].max(0.);
+                let period = spawner.period().range()[0];
+                let spawn_time = spawner.spawn_time().range()[0];
+                let capacity = if spawn_time > 0. && spawn_time >= period {
+                    // Steady stream of particles
+                    count / period * lifetime
+                } else {
+                    // Bursts, all alive at once if the lifetime overlaps several periods
+                    count * (lifetime / period).ceil().max(1.)
+                };
+                capacity.ceil() as u32
+            })
+            .reduce(|a, b| a.saturating_add(b))
+    }
+
+    /// Get the set of particle attributes the graph reads or writes.
+    ///
+    /// This collects the attributes read by the [`AttributeNode`]s and
+    /// [`PrevAttributeNode`]s of the graph, the attributes written by its
+    /// [`SetAttributeNode`]s, and the attributes required by the modifiers of
+    /// its [`ModifierNode`]s, as reported by [`Node::attributes()`]. This allows
+    /// validating a graph against a target particle layout before converting
+    /// it. All nodes are scanned, including the ones not contributing to any
+    /// terminal node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// graph.add_node(AttributeNode::new(Attribute::POSITION));
+    /// graph.add_node(SetAttributeNode::new(
+    ///     ModifierContext::Init,
+    ///     Attribute::LIFETIME,
+    /// ));
+    /// let attributes = graph.required_attributes();
+    /// assert_eq!(attributes.len(), 2);
+    /// assert!(attributes.contains(&Attribute::POSITION));
+    /// assert!(attributes.contains(&Attribute::LIFETIME));
+    /// ```
+    pub fn required_attributes(&self) -> HashSet<Attribute> {
+        self.iter_nodes()
+            .flat_map(|(_, node)| node.attributes().iter().copied())
+            .collect()
+    }
+
+    /// Get the set of names of the properties the graph reads.
+    ///
+    /// This collects the names of the properties read by the [`PropertyNode`]s
+    /// of the graph, as reported by [`Node::property_name()`]. All nodes are
+    /// scanned, including the ones not contributing to any terminal node.
+    pub fn required_properties(&self) -> HashSet<String> {
+        self.iter_nodes()
+            .filter_map(|(_, node)| node.property_name().map(str::to_string))
+            .collect()
+    }
+
+    /// Find the nodes which don't contribute to any terminal node.
+    ///
+    /// A node is terminal if [`Node::is_terminal()`] returns `true`, which by
+    /// default is the case of nodes without any output slot, like modifier
+    /// nodes. Terminal nodes are the sinks of the graph, and are always
+    /// considered used. Any other node is dead if none of its outputs reaches a
+    /// terminal node, either directly or through other nodes. Dead nodes
+    /// contribute nothing when converting the graph to an effect, and can be
+    /// safely removed.
+    ///
+    /// The dead nodes are returned in increasing identifier order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// assert_eq!(graph.dead_nodes(), vec![time]);
+    /// ```
+    pub fn dead_nodes(&self) -> Vec<NodeId> {
+        let terminals: Vec<NodeId> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.as_ref().is_some_and(|node| node.is_terminal()))
+            .map(|(index, _)| NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap()))
+            .collect();
+        let live = self.reachable_nodes(&terminals);
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_some())
+            .map(|(index, _)| NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap()))
+            .filter(|node_id| !live.contains(node_id))
+            .collect()
+    }
+
+    /// Get the nodes feeding any input slot of a node.
+    ///
+    /// Each node is returned once, even if it's linked to several input slots
+    /// of the node, in the order of the input slots. This includes the hidden
+    /// [`LiteralNode`] holding the constants set with
+    /// [`set_input_constant()`], which can be filtered out with
+    /// [`is_input_constant_node()`].
+    ///
+    /// See also [`outgoing_nodes()`] to walk the graph downstream.
+    ///
+    /// [`set_input_constant()`]: Graph::set_input_constant
+    /// [`is_input_constant_node()`]: Graph::is_input_constant_node
+    /// [`outgoing_nodes()`]: Graph::outgoing_nodes
+    pub fn incoming_nodes(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut nodes = vec![];
+        for input in self.input_slots(node_id) {
+            for &output in &self.get_slot(input).linked_slots {
+                let source = self.get_slot(output).node_id();
+                if !nodes.contains(&source) {
+                    nodes.push(source);
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Get the nodes consuming any output slot of a node.
+    ///
+    /// Each node is returned once, even if several of its input slots are
+    /// linked to outputs of the node, in the order the links were made from
+    /// each output slot, and in the order of the output slots.
+    ///
+    /// See also [`incoming_nodes()`] to walk the graph upstream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let sin = graph.add_node(SinNode::default());
+    /// graph.connect(time, "time", sin, "in").unwrap();
+    /// assert_eq!(graph.outgoing_nodes(time), vec![sin]);
+    /// assert_eq!(graph.incoming_nodes(sin), vec![time]);
+    /// ```
+    ///
+    /// [`incoming_nodes()`]: Graph::incoming_nodes
+    pub fn outgoing_nodes(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut nodes = vec![];
+        for output in self.output_slots(node_id) {
+            for &input in &self.get_slot(output).linked_slots {
+                let target = self.get_slot(input).node_id();
+                if !nodes.contains(&target) {
+                    nodes.push(target);
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Find the nodes contributing to some given nodes.
+    ///
+    /// This walks the links of the graph upstream from the nodes in `from`,
+    /// and returns all the nodes visited, including the nodes of `from`
+    /// themselves. Those are the only nodes which need to be evaluated to
+    /// produce the inputs of the nodes of `from`; any other node can be skipped.
+    /// A UI can use this to grey out the nodes not contributing to a
+    /// selection of nodes.
+    ///
+    /// See also [`dead_nodes()`] for the nodes not contributing to any
+    /// terminal node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node of `from` doesn't exist in the graph.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let age = graph.add_node(AttributeNode::new(Attribute::AGE));
+    /// let add = graph.add_node(AddNode::default());
+    /// graph.link(
+    ///     graph.output_slot(age, "age").unwrap(),
+    ///     graph.input_slot(add, "lhs").unwrap(),
+    /// );
+    /// let reachable = graph.reachable_nodes(&[add]);
+    /// assert!(reachable.contains(&add));
+    /// assert!(reachable.contains(&age));
+    /// assert!(!reachable.contains(&time));
+    /// ```
+    ///
+    /// [`dead_nodes()`]: Graph::dead_nodes
+    pub fn reachable_nodes(&self, from: &[NodeId]) -> HashSet<NodeId> {
+        let mut reachable = HashSet::new();
+        let mut stack = from.to_vec();
+        while let Some(node_id) = stack.pop() {
+            if !reachable.insert(node_id) {
+                continue;
+            }
+            stack.extend(self.incoming_nodes(node_id));
+        }
+        reachable
+    }
+
+    /// Compute a 2D position for each node, to visually lay out the graph.
+    ///
+    /// This is a simple layered layout, intended for editors importing a graph
+    /// without any saved node position. Each node is assigned to a column
+    /// (layer) equal to the length of the longest chain of nodes upstream of
+    /// it, so that the sources of the graph are on the left and all links go
+    /// from left to right. Within a column, nodes are ordered by the average
+    /// row of the nodes they're linked from, to reduce link crossings, then by
+    /// increasing [`NodeId`].
+    ///
+    /// Columns and rows are spaced by [`Graph::LAYOUT_SPACING`], with the first
+    /// node of the first column at the origin.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GraphError::CycleDetected`] if the graph contains a directed
+    /// cycle, like [`topological_order()`] does, since its nodes can't be
+    /// ordered into columns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec2;
+    /// let mut graph = Graph::new();
+    /// let time = graph.add_node(TimeNode::default());
+    /// let add = graph.add_node(AddNode::default());
+    /// let time_out = graph.output_slot(time, "time").unwrap();
+    /// graph.link(time_out, graph.input_slot(add, "lhs").unwrap());
+    ///
+    /// let layout = graph.auto_layout().unwrap();
+    /// assert_eq!(layout[&time], Vec2::ZERO);
+    /// assert_eq!(layout[&add], Vec2::new(Graph::LAYOUT_SPACING.x, 0.));
+    /// ```
+    ///
+    /// [`topological_order()`]: Graph::topological_order
+    pub fn auto_layout(&self) -> Result<HashMap<NodeId, Vec2>, GraphError> {
+        let upstream = |node_id: NodeId| -> Vec<NodeId> {
+            self.input_slots(node_id)
+                .into_iter()
+                .filter_map(|input| self.input_source(input))
+                .map(|output| self.get_slot(output).node_id())
+                .collect()
+        };
+
+        // Assign each node to a layer, after all its upstream nodes
+        let mut ranks: HashMap<NodeId, usize> = HashMap::new();
+        for node_id in self.topological_order()? {
+            let rank = upstream(node_id)
+                .iter()
+                .map(|source| ranks[source] + 1)
+                .max()
+                .unwrap_or(0);
+            ranks.insert(node_id, rank);
+        }
+
+        let layer_count = ranks.values().max().map_or(0, |&rank| rank + 1);
+        let mut layers = vec![vec![]; layer_count];
+        for (&node_id, &rank) in &ranks {
+            layers[rank].push(node_id);
+        }
+
+        // Order each layer by barycenter of the upstream nodes' rows
+        let mut rows: HashMap<NodeId, usize> = HashMap::new();
+        let mut layout = HashMap::with_capacity(ranks.len());
+        for (rank, layer) in layers.iter_mut().enumerate() {
+            let mut keyed: Vec<(f32, NodeId)> = layer
+                .iter()
+                .map(|&node_id| {
+                    let sources = upstream(node_id);
+                    let barycenter = if sources.is_empty() {
+                        0.
+                    } else {
+                        sources
+                            .iter()
+                            .map(|source| rows[source] as f32)
+                            .sum::<f32>()
+                            / sources.len() as f32
+                    };
+                    (barycenter, node_id)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.id().cmp(&b.1.id())));
+            for (row, (_, node_id)) in keyed.into_iter().enumerate() {
+                rows.insert(node_id, row);
+                layout.insert(
+                    node_id,
+                    Vec2::new(rank as f32, row as f32) * Self::LAYOUT_SPACING,
+                );
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Convert the graph into an effect asset.
+    ///
+    /// The nodes contributing to the modifier nodes, that is the nodes
+    /// returning some [`Node::modifier_context()`], are evaluated like
+    /// [`eval_all()`] does into the expressions of a new [`Module`]. Any other
+    /// node, as reported by [`reachable_nodes()`], is skipped, so that orphan
+    /// nodes left in the graph neither add any expression to the effect nor
+    /// fail the conversion. Then each modifier node builds its modifier from
+    /// the expressions linked to its input slots. The modifiers are added to the
+    /// init, update, or render modifiers of the effect according to their
+    /// context, in increasing [`NodeId`] order.
+    ///
+    /// The effect has a single particle group of the given capacity, spawned
+    /// by the [`Spawner`] of the first node configuring one, like a
+    /// [`SpawnerNode`], or by the default [`Spawner`] if there's none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ExprError::GraphEvalError`] if a node fails to evaluate,
+    /// including if a modifier node has some unlinked input slot, or if the
+    /// graph doesn't contain any modifier node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut graph = Graph::new();
+    /// graph.add_node(SpawnerNode::new(Spawner::rate(30.0.into())));
+    /// let lifetime = graph.add_node(LifetimeNode::new(2.));
+    /// let init_lifetime =
+    ///     graph.add_node(SetAttributeNode::new(ModifierContext::Init, Attribute::LIFETIME));
+    /// graph.link(
+    ///     graph.output_slot(lifetime, "lifetime").unwrap(),
+    ///     graph.input_slot(init_lifetime, "lifetime").unwrap(),
+    /// );
+    ///
+    /// let asset = graph.to_effect_asset("my_effect", 64).unwrap();
+    /// assert_eq!(asset.init_modifiers().count(), 1);
+    /// ```
+    ///
+    /// [`eval_all()`]: Graph::eval_all
+    /// [`reachable_nodes()`]: Graph::reachable_nodes
+    pub fn to_effect_asset(
+        &self,
+        name: impl Into<String>,
+        capacity: u32,
+    ) -> Result<EffectAsset, ExprError> {
+        let modifier_nodes: Vec<NodeId> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                node.as_ref()
+                    .is_some_and(|node| node.modifier_context().is_some())
+            })
+            .map(|(index, _)| NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap()))
+            .collect();
+        let reachable = self.reachable_nodes(&modifier_nodes);
+        let mut module = Module::default();
+        let outputs = self.eval_nodes(&mut module, reachable.into_iter().collect())?;
+
+        let mut modifiers = vec![];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let Some(node) = node else {
+                continue;
+            };
+            let Some(context) = node.modifier_context() else {
+                continue;
+            };
+            let node_id = NodeId::new(NonZeroU32::new(index as u32 + 1).unwrap());
+            let mut inputs = vec![];
+            for input in self.input_slots(node_id) {
+                let Some(expr) = self.input_expr(&mut module, input, &outputs) else {
+                    return Err(ExprError::GraphEvalError(format!(
+                        "{}: unlinked input slot {}",
+                        self.node_error_context(node_id),
+                        self.get_slot(input).def().name()
+                    )));
+                };
+                inputs.push(expr);
+            }
+            let modifier = node.build_modifier(inputs).map_err(|err| {
+                ExprError::GraphEvalError(format!("{}: {}", self.node_error_context(node_id), err))
+            })?;
+            let supported = modifier.context().contains(context)
+                && (context != ModifierContext::Render || modifier.as_render().is_some());
+            if !supported {
+                return Err(ExprError::GraphEvalError(format!(
+                    "{}: modifier doesn't support the {} context",
+                    self.node_error_context(node_id),
+                    context
+                )));
+            }
+            modifiers.push((context, modifier));
+        }
+        if modifiers.is_empty() {
+            return Err(ExprError::GraphEvalError(
+                "Graph doesn't contain any modifier node.".to_string(),
+            ));
+        }
+
+        let spawner = self
+            .nodes
+            .iter()
+            .flatten()
+            .find_map(|node| node.spawner().copied())
+            .unwrap_or_default();
+        let mut asset = EffectAsset::new(capacity, spawner, module).with_name(name);
+        for (context, modifier) in modifiers {
+            asset = if context == ModifierContext::Render {
+                asset.add_render_modifier(modifier.as_render().unwrap().boxed_render_clone())
+            } else {
+                asset.add_modifier(context, modifier)
+            };
+        }
+        Ok(asset)
+    }
+
+    /// Get the expression feeding an input slot, which is the expression
+    /// produced by the output slot it's linked to, or a literal of its default
+    /// value if unlinked. Returns `None` if the slot is unlinked without any
+    /// default value.
+    fn input_expr(
+        &self,
+        module: &mut Module,
+        input: SlotId,
+        outputs: &HashMap<SlotId, ExprHandle>,
+    ) -> Option<ExprHandle> {
+        match self.input_source(input) {
+            Some(source) => Some(outputs[&source]),
+            None => self
+                .get_slot(input)
+                .def()
+                .default_value()
+                .map(|value| module.lit(value)),
+        }
+    }
+
+    /// Describe a node for error messages, with its name, [`NodeId`], and
+    /// user-assigned label if any, for example `AddNode(#3 'gravity')`.
+    fn node_error_context(&self, node_id: NodeId) -> String {
+        let name = self.nodes[node_id.index()].as_ref().unwrap().name();
+        match self.node_label(node_id) {
+            Some(label) => format!("{}(#{} '{}')", name, node_id.id(), label),
+            None => format!("{}(#{})", name, node_id.id()),
+        }
+    }
+
+    /// Check that `output` and `input` reference an existing output slot and
+    /// an existing input slot, respectively.
+    fn check_link_slots(&self, output: SlotId, input: SlotId) -> Result<(), GraphError> {
+        if !self.try_get_slot(output)?.is_output() {
+            return Err(GraphError::WrongDirection {
+                slot: output,
+                expected: SlotDir::Output,
+            });
+        }
+        if !self.try_get_slot(input)?.is_input() {
+            return Err(GraphError::WrongDirection {
+                slot: input,
+                expected: SlotDir::Input,
+            });
+        }
+        Ok(())
+    }
+
+    fn try_get_slot(&self, id: SlotId) -> Result<&Slot, GraphError> {
+        self.slots
+            .get(id.index())
+            .and_then(Option::as_ref)
+            .ok_or(GraphError::NoSuchSlot(id))
+    }
+
+    fn get_slot(&self, id: SlotId) -> &Slot {
+        let index = id.index();
+        assert!(index < self.slots.len());
+        self.slots[index].as_ref().expect("slot of removed node")
+    }
+
+    fn get_slot_mut(&mut self, id: SlotId) -> &mut Slot {
+        let index = id.index();
+        assert!(index < self.slots.len());
+        self.slots[index].as_mut().expect("slot of removed node")
+    }
+}
+
+/// Helper trait to clone a boxed [`Node`].
+///
+/// This is automatically implemented for all nodes implementing [`Clone`].
+pub trait NodeClone {
+    /// Clone the node into a new boxed node.
+    fn clone_box(&self) -> Box<dyn Node>;
+}
+
+impl<T: Node + Clone + 'static> NodeClone for T {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Node> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Generic graph node.
+#[cfg_attr(feature = "serde", typetag::serde)]
+pub trait Node: NodeClone {
+    /// Get the list of slots of this node.
+    ///
+    /// The list contains both input and output slots, without any guaranteed
+    /// order.
+    fn slots(&self) -> &[SlotDef];
+
+    /// Get the name of this node, used in error messages.
+    ///
+    /// By default this is the unqualified type name of the node, like
+    /// `AddNode`.
+    fn name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// Get the human-readable name of this node, for display in an editor.
+    ///
+    /// By default this is the same as [`name()`]. Nodes can override it with
+    /// a friendlier name, like `Add` for an [`AddNode`]. This never affects
+    /// the evaluation of the node.
+    ///
+    /// [`name()`]: Node::name
+    fn display_name(&self) -> &str {
+        self.name()
+    }
+
+    /// Is this node a terminal node of the graph?
+    ///
+    /// Terminal nodes, like modifier nodes, consume their inputs to configure
+    /// the effect, and don't produce any expression for other nodes. They're
+    /// never reported by [`Graph::dead_nodes()`]. By default, a node is
+    /// terminal if it doesn't have any output slot.
+    fn is_terminal(&self) -> bool {
+        !self.slots().iter().any(SlotDef::is_output)
+    }
+
+    /// Get the context of the modifier this node represents, if it's a
+    /// modifier node.
+    ///
+    /// Modifier nodes are converted into a modifier by
+    /// [`Graph::to_effect_asset()`], using [`build_modifier()`]. The context
+    /// must be a single one of [`ModifierContext::Init`],
+    /// [`ModifierContext::Update`], or [`ModifierContext::Render`].
+    ///
+    /// [`build_modifier()`]: Node::build_modifier
+    fn modifier_context(&self) -> Option<ModifierContext> {
+        None
+    }
+
+    /// Build the modifier this node represents from the expressions linked to
+    /// its input slots, in slot order.
+    ///
+    /// This is only called for modifier nodes, which return some
+    /// [`modifier_context()`].
+    ///
+    /// [`modifier_context()`]: Node::modifier_context
+    fn build_modifier(&self, _inputs: Vec<ExprHandle>) -> Result<BoxedModifier, ExprError> {
+        Err(ExprError::GraphEvalError(format!(
+            "{} is not a modifier node.",
+            self.name()
+        )))
+    }
+
+    /// Get the spawner configured by this node, if any.
+    ///
+    /// This is used by [`Graph::suggest_capacity()`].
+    fn spawner(&self) -> Option<&Spawner> {
+        None
+    }
+
+    /// Get the maximum lifetime of the particles, in seconds, if this node
+    /// sets the lifetime of the particles.
+    ///
+    /// This is used by [`Graph::suggest_capacity()`].
+    fn max_lifetime(&self) -> Option<f32> {
+        None
+    }
+
+    /// Get the particle attributes this node reads or writes.
+    ///
+    /// This is used by [`Graph::required_attributes()`].
+    fn attributes(&self) -> &[Attribute] {
+        &[]
+    }
+
+    /// Get the name of the property this node reads, if any.
+    ///
+    /// This is used by [`Graph::required_properties()`].
+    fn property_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Get this node as a mutable [`LiteralNode`], if it's one.
+    ///
+    /// This is used by [`GraphTemplate::instantiate()`] to assign the value of
+    /// the template parameters.
+    fn as_literal_mut(&mut self) -> Option<&mut LiteralNode> {
+        None
+    }
+
+    /// Evaluate the node from the given input expressions, and optionally
+    /// produce output expression(s).
+    ///
+    /// The expressions themselves are not evaluated (that is, _e.g._ "3 + 2" is
+    /// _not_ reduced to "5").
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError>;
+}
+
+/// Parameterized graph, to instantiate families of similar graphs.
+///
+/// A template wraps a base [`Graph`], and marks some of its [`LiteralNode`]s as
+/// named parameters. Each call to [`instantiate()`] produces a new concrete
+/// graph where the value of those parameters is replaced by the provided
+/// values, while all other nodes and links are copied as is. This allows a
+/// single template to generate many effects differing only in a few constants.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// let mut graph = Graph::new();
+/// let time = graph.add_node(TimeNode::default());
+/// let speed = graph.add_node(LiteralNode::new(1.));
+/// let mul = graph.add_node(MulNode::default());
+/// graph.link(
+///     graph.output_slot(time, "time").unwrap(),
+///     graph.input_slot(mul, "lhs").unwrap(),
+/// );
+/// graph.link(
+///     graph.output_slot(speed, "value").unwrap(),
+///     graph.input_slot(mul, "rhs").unwrap(),
+/// );
+///
+/// let template = GraphTemplate::new(graph).with_parameter("speed", speed);
+/// let fast = template.instantiate(&[("speed", 10_f32.into())]).unwrap();
+/// let slow = template.instantiate(&[("speed", 0.1_f32.into())]).unwrap();
+/// ```
+///
+/// [`instantiate()`]: GraphTemplate::instantiate
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GraphTemplate {
+    /// Base graph copied by each instantiation.
+    graph: Graph,
+    /// Parameters, as (name, literal node) pairs.
+    parameters: Vec<(String, NodeId)>,
+}
+
+impl GraphTemplate {
+    /// Create a new template from a base graph, without any parameter.
+    pub fn new(graph: Graph) -> Self {
+        Self {
+            graph,
+            parameters: vec![],
+        }
+    }
+
+    /// Mark a [`LiteralNode`] of the base graph as a named parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a parameter with the same name already exists, or if the node
+    /// is not a [`LiteralNode`] of the base graph.
+    pub fn with_parameter(mut self, name: impl Into<String>, node_id: NodeId) -> Self {
+        let name = name.into();
+        assert!(
+            !self.parameters.iter().any(|(n, _)| *n == name),
+            "Duplicate template parameter '{}'",
+            name
+        );
+        assert!(
+            self.graph
+                .node_mut(node_id)
+                .and_then(|node| node.as_literal_mut())
+                .is_some(),
+            "Template parameter '{}' must reference a LiteralNode",
+            name
+        );
+        self.parameters.push((name, node_id));
+        self
+    }
+
+    /// Get the base graph of the template.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Get the node of a parameter by name.
+    pub fn parameter(&self, name: &str) -> Option<NodeId> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, node_id)| *node_id)
+    }
+
+    /// Instantiate a concrete graph by assigning the given parameter values.
+    ///
+    /// Parameters not present in `values` keep the value of the base graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ExprError::GraphEvalError`] if a value references an
+    /// unknown parameter, or an [`ExprError::TypeError`] if a value has a type
+    /// different from the type of the literal of the base graph.
+    pub fn instantiate(&self, values: &[(&str, Value)]) -> Result<Graph, ExprError> {
+        let mut graph = self.graph.clone();
+        for (name, value) in values {
+            let node_id = self.parameter(name).ok_or_else(|| {
+                ExprError::GraphEvalError(format!("Unknown template parameter '{}'", name))
+            })?;
+            let literal = graph
+                .node_mut(node_id)
+                .and_then(|node| node.as_literal_mut())
+                .unwrap();
+            if literal.value().value_type() != value.value_type() {
+                return Err(ExprError::TypeError(format!(
+                    "Cannot assign value of type {:?} to template parameter '{}' of type {:?}",
+                    value.value_type(),
+                    name,
+                    literal.value().value_type()
+                )));
+            }
+            literal.set_value(*value);
+        }
+        Ok(graph)
+    }
+}
+
+/// Graph node producing a constant value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiteralNode {
+    /// Output value.
+    slots: [SlotDef; 1],
+    /// Constant value.
+    value: Value,
+}
+
+impl Default for LiteralNode {
+    fn default() -> Self {
+        Self::new(0_f32)
+    }
+}
+
+impl LiteralNode {
+    /// Create a new node producing the given constant value.
+    pub fn new(value: impl Into<Value>) -> Self {
+        let value = value.into();
+        Self {
+            slots: [SlotDef::output("value", Some(value.value_type()))],
+            value,
+        }
+    }
+
+    /// Get the constant value.
+    pub fn value(&self) -> Value {
+        self.value
+    }
+
+    /// Set the constant value.
+    ///
+    /// The slot type is updated to match the type of the new value.
+    pub fn set_value(&mut self, value: Value) {
+        self.slots = [SlotDef::output("value", Some(value.value_type()))];
+        self.value = value;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for LiteralNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![module.lit(self.value)])
+    }
+
+    fn as_literal_mut(&mut self) -> Option<&mut LiteralNode> {
+        Some(self)
+    }
+}
+
+/// Graph node to add two values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for AddNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for AddNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn display_name(&self) -> &str {
+        "Add"
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let add = module.add(left, right);
+        Ok(vec![add])
+    }
+}
+
+/// Graph node to subtract two values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for SubNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SubNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn display_name(&self) -> &str {
+        "Subtract"
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let sub = module.sub(left, right);
+        Ok(vec![sub])
+    }
+}
+
+/// Graph node to multiply two values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MulNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for MulNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for MulNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn display_name(&self) -> &str {
+        "Multiply"
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let mul = module.mul(left, right);
+        Ok(vec![mul])
+    }
+}
+
+/// Graph node to divide two values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DivNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for DivNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for DivNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn display_name(&self) -> &str {
+        "Divide"
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let div = module.div(left, right);
+        Ok(vec![div])
+    }
+}
+
+/// Graph node to calculate the remainder of the division of two values.
+///
+/// The node emits the WGSL `%` operator, which for floating-point values is
+/// equivalent to C's `fmod()`: the result is `lhs - rhs * trunc(lhs / rhs)`,
+/// and has the sign of `lhs`. For vectors, the remainder is component-wise.
+/// This is typically used to produce a pattern repeating every `rhs` units of
+/// `lhs`, for example over the age of a particle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for ModNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ModNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let rem = module.rem(left, right);
+        Ok(vec![rem])
+    }
+}
+
+/// Graph node to remap a value from one range to another.
+///
+/// The node linearly maps `value` from the `[in_min:in_max]` range to the
+/// `[out_min:out_max]` range, that is it outputs:
+///
+/// ```txt
+/// out_min + (value - in_min) * (out_max - out_min) / (in_max - in_min)
+/// ```
+///
+/// The result is not clamped, so a `value` outside of the input range maps
+/// to a result outside of the output range. The result is undefined if
+/// `in_min == in_max`. All inputs can be scalars or vectors, but must have the
+/// same type, which is also the type of the result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemapNode {
+    slots: [SlotDef; 6],
+}
+
+impl Default for RemapNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("value", None),
+                SlotDef::input("in_min", None),
+                SlotDef::input("in_max", None),
+                SlotDef::input("out_min", None),
+                SlotDef::input("out_max", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for RemapNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 5 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 5 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let value = inputs.next().unwrap();
+        let in_min = inputs.next().unwrap();
+        let in_max = inputs.next().unwrap();
+        let out_min = inputs.next().unwrap();
+        let out_max = inputs.next().unwrap();
+        let offset = module.sub(value, in_min);
+        let out_range = module.sub(out_max, out_min);
+        let scaled = module.mul(offset, out_range);
+        let in_range = module.sub(in_max, in_min);
+        let scaled = module.div(scaled, in_range);
+        let result = module.add(out_min, scaled);
+        Ok(vec![result])
+    }
+}
+
+/// Check the operands of a comparison node.
+///
+/// Comparison nodes only compare scalars, to produce a single `bool` output.
+/// Although WGSL also compares vectors component-wise, such a comparison
+/// produces a bool vector, which doesn't fit the scalar output slot of those
+/// nodes. So this returns an [`ExprError::TypeError`] if any operand is not a
+/// scalar, or if both are scalars of different types. Operands whose type is
+/// not known until shader generation, like properties, are not validated.
+fn check_comparison_operands(
+    module: &Module,
+    node_name: &str,
+    left: ExprHandle,
+    right: ExprHandle,
+) -> Result<(), ExprError> {
+    let left_type = module.try_get(left)?.value_type();
+    let right_type = module.try_get(right)?.value_type();
+    for value_type in [left_type, right_type].into_iter().flatten() {
+        if !matches!(value_type, ValueType::Scalar(_)) {
+            return Err(ExprError::TypeError(format!(
+                "{} can only compare scalar values, got {:?}",
+                node_name, value_type
+            )));
+        }
+    }
+    if let (Some(left_type), Some(right_type)) = (left_type, right_type) {
+        if left_type != right_type {
+            return Err(ExprError::TypeError(format!(
+                "{} operands must have the same type, got {:?} and {:?}",
+                node_name, left_type, right_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Graph node to check if a value is strictly less than another one.
+///
+/// The operands must be scalars of the same type, and the comparison produces
+/// a single `bool` value `lhs < rhs`. See [`check_comparison_operands()`]
+/// for details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LessNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for LessNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", Some(ValueType::Scalar(ScalarType::Bool))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for LessNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        check_comparison_operands(module, "LessNode", left, right)?;
+        let cmp = module.lt(left, right);
+        Ok(vec![cmp])
+    }
+}
+
+/// Graph node to check if a value is strictly greater than another one.
+///
+/// The operands must be scalars of the same type, and the comparison produces
+/// a single `bool` value `lhs > rhs`. See [`check_comparison_operands()`]
+/// for details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GreaterNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for GreaterNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", Some(ValueType::Scalar(ScalarType::Bool))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for GreaterNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        check_comparison_operands(module, "GreaterNode", left, right)?;
+        let cmp = module.gt(left, right);
+        Ok(vec![cmp])
+    }
+}
+
+/// Graph node to check if a value is equal to another one.
+///
+/// The operands must be scalars of the same type, and the comparison produces
+/// a single `bool` value `lhs == rhs`. See [`check_comparison_operands()`]
+/// for details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqualNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for EqualNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", None),
+                SlotDef::input("rhs", None),
+                SlotDef::output("result", Some(ValueType::Scalar(ScalarType::Bool))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for EqualNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        check_comparison_operands(module, "EqualNode", left, right)?;
+        let cmp = module.eq(left, right);
+        Ok(vec![cmp])
+    }
+}
+
+/// Graph node to select one of two values based on a condition.
+///
+/// The result is `if_true` if `cond` is `true`, or `if_false` otherwise. This
+/// is evaluated without branching with the WGSL `select()` function, so both
+/// values are always evaluated. The two values must have the same type, which
+/// is also the type of the result. The condition is typically the output of a
+/// comparison node like [`LessNode`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectNode {
+    slots: [SlotDef; 4],
+}
+
+impl Default for SelectNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("cond", Some(ValueType::Scalar(ScalarType::Bool))),
+                SlotDef::input("if_true", None),
+                SlotDef::input("if_false", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SelectNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let cond = inputs.next().unwrap();
+        let if_true = inputs.next().unwrap();
+        let if_false = inputs.next().unwrap();
+        let true_type = module.try_get(if_true)?.value_type();
+        let false_type = module.try_get(if_false)?.value_type();
+        if let (Some(true_type), Some(false_type)) = (true_type, false_type) {
+            if true_type != false_type {
+                return Err(ExprError::TypeError(format!(
+                    "SelectNode values must have the same type, got {:?} and {:?}",
+                    true_type, false_type
+                )));
+            }
+        }
+        // WGSL's select() takes the value for false first
+        let select = module.select(if_false, if_true, cond);
+        Ok(vec![select])
+    }
+}
+
+/// Graph node to swizzle the components of a vector.
+///
+/// The node has a single `vec` input and a single `result` output. The swizzle
+/// mask selects 1 to 4 components of the input vector among `x`, `y`, `z`, and
+/// `w`, in any order and possibly repeated. The output is an `f32` vector with
+/// as many components as the mask, or a float scalar for a single-component
+/// mask.
+///
+/// See [`SwizzleExpr`] for details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwizzleNode {
+    /// Vector input and swizzled output.
+    slots: [SlotDef; 2],
+    /// Swizzle mask, validated on construction.
+    mask: String,
+}
+
+impl SwizzleNode {
+    /// Create a new node swizzling its input with the given mask, like `"xy"`.
+    ///
+    /// Returns an [`ExprError::SyntaxError`] if the mask is empty, is longer
+    /// than 4 components, or contains a character other than `x`, `y`, `z`, or
+    /// `w`.
+    pub fn new(mask: &str) -> Result<Self, ExprError> {
+        let (_, count) = SwizzleExpr::parse_mask(mask)?;
+        let output_type = if count == 1 {
+            ValueType::Scalar(ScalarType::Float)
+        } else {
+            ValueType::Vector(VectorType::new(ScalarType::Float, count))
+        };
+        Ok(Self {
+            slots: [
+                SlotDef::input("vec", None),
+                SlotDef::output("result", Some(output_type)),
+            ],
+            mask: mask.to_string(),
+        })
+    }
+
+    /// Get the swizzle mask.
+    pub fn mask(&self) -> &str {
+        &self.mask
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SwizzleNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let vec = inputs[0];
+        let expr = SwizzleExpr::new(vec, &self.mask)?;
+        if let Some(value_type) = module.try_get(vec)?.value_type() {
+            let ValueType::Vector(vector_type) = value_type else {
+                return Err(ExprError::TypeError(format!(
+                    "SwizzleNode input must be a vector, got {:?}",
+                    value_type
+                )));
+            };
+            if expr.max_component() as usize >= vector_type.count() {
+                return Err(ExprError::TypeError(format!(
+                    "Swizzle mask '{}' out of bounds for input vector {:?}",
+                    self.mask, vector_type
+                )));
+            }
+        }
+        Ok(vec![module.swizzle(vec, &self.mask)?])
+    }
+}
+
+/// Graph node to clamp a value between a lower and an upper bound.
+///
+/// The value is clamped component-wise for vectors, and the result is
+/// undefined if `min > max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClampNode {
+    slots: [SlotDef; 4],
+}
+
+impl Default for ClampNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("value", None),
+                SlotDef::input("min", None),
+                SlotDef::input("max", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ClampNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let value = inputs.next().unwrap();
+        let min = inputs.next().unwrap();
+        let max = inputs.next().unwrap();
+        let clamp = module.clamp(value, min, max);
+        Ok(vec![clamp])
+    }
+}
+
+/// Graph node to calculate the minimum of two values.
+///
+/// The values `a` and `b` can be scalars or vectors, but must have the same
+/// type, which is also the type of the result. For vectors, the minimum is
+/// calculated component-wise.
+///
+/// See also [`ClampNode`] to bound a value on both sides at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for MinNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", None),
+                SlotDef::input("b", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for MinNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+        let min = module.min(a, b);
+        Ok(vec![min])
+    }
+}
+
+/// Graph node to calculate the maximum of two values.
+///
+/// The values `a` and `b` can be scalars or vectors, but must have the same
+/// type, which is also the type of the result. For vectors, the maximum is
+/// calculated component-wise.
+///
+/// See also [`ClampNode`] to bound a value on both sides at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for MaxNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", None),
+                SlotDef::input("b", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for MaxNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+        let max = module.max(a, b);
+        Ok(vec![max])
+    }
+}
+
+/// Graph node to compare a value against a threshold.
+///
+/// The result is `1` if `edge <= x`, or `0` otherwise. The values `edge` and
+/// `x` can be scalars or vectors, but must have the same type, which is also
+/// the type of the result. For vectors, the comparison is component-wise.
+///
+/// See also [`SmoothstepNode`] for a smooth transition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for StepNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("edge", None),
+                SlotDef::input("x", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for StepNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let edge = inputs.next().unwrap();
+        let x = inputs.next().unwrap();
+        let step = module.step(edge, x);
+        Ok(vec![step])
+    }
+}
+
+/// Graph node to smoothly interpolate between 0 and 1 over a range.
+///
+/// The result is `0` if `x <= edge0`, `1` if `x >= edge1`, and a smooth Hermite
+/// interpolation between `0` and `1` in between. The values `edge0`, `edge1`,
+/// and `x` can be scalars or vectors, but must have the same type, which is
+/// also the type of the result. For vectors, the interpolation is
+/// component-wise. The result is undefined if `edge0 >= edge1`.
+///
+/// See also [`StepNode`] for a hard threshold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothstepNode {
+    slots: [SlotDef; 4],
+}
+
+impl Default for SmoothstepNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("edge0", None),
+                SlotDef::input("edge1", None),
+                SlotDef::input("x", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SmoothstepNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let edge0 = inputs.next().unwrap();
+        let edge1 = inputs.next().unwrap();
+        let x = inputs.next().unwrap();
+        let smoothstep = module.smoothstep(edge0, edge1, x);
+        Ok(vec![smoothstep])
+    }
+}
+
+/// Graph node to linearly interpolate between two values.
+///
+/// The result is `a * (1 - t) + b * t`. The values `a` and `b` can be scalars
+/// or vectors, but must have the same type, while the interpolation factor `t`
+/// is always a scalar `f32`, applied to all components of vector values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MixNode {
+    slots: [SlotDef; 4],
+}
+
+impl Default for MixNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", None),
+                SlotDef::input("b", None),
+                SlotDef::input("t", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for MixNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+        let t = inputs.next().unwrap();
+        // The type of some expressions is only known once written to a shader, so
+        // only validate the interpolation factor when its type is already known.
+        let t_type = module.try_get(t)?.value_type();
+        if let Some(t_type) = t_type {
+            if t_type != ValueType::Scalar(ScalarType::Float) {
+                return Err(ExprError::TypeError(format!(
+                    "MixNode interpolation factor must be a scalar f32, got {:?}",
+                    t_type
+                )));
+            }
+        }
+        let mix = module.mix(a, b, t);
+        Ok(vec![mix])
+    }
+}
+
+/// Graph node to linearly interpolate between two vectors with per-component
+/// weights.
+///
+/// The result is `a * (1 - w) + b * w`, evaluated componentwise. Unlike
+/// [`MixNode`] whose interpolation factor is a single scalar applied to all
+/// components, the weights `w` are a `Vec3`, so the values `a` and `b` must be
+/// `Vec3` too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentMixNode {
+    slots: [SlotDef; 4],
+}
+
+impl Default for ComponentMixNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("b", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("w", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("result", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ComponentMixNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+        let w = inputs.next().unwrap();
+        // Same as MixNode, only validate the weights when their type is known.
+        let w_type = module.try_get(w)?.value_type();
+        if let Some(w_type) = w_type {
+            if w_type != ValueType::Vector(VectorType::VEC3F) {
+                return Err(ExprError::TypeError(format!(
+                    "ComponentMixNode weights must be a vec3<f32>, got {:?}",
+                    w_type
+                )));
+            }
+        }
+        let mix = module.mix(a, b, w);
+        Ok(vec![mix])
+    }
+}
+
+/// Graph node to interpolate three values with barycentric weights.
+///
+/// The result is `w.x * a + w.y * b + w.z * c`, where `w` is the `Vec3` of
+/// weights. The values `a`, `b`, and `c` can be scalars or vectors, but must
+/// all have the same type. This is typically used to interpolate per-vertex
+/// values across a triangle.
+///
+/// If [`normalize()`] is `true`, the weights are divided by their sum before
+/// being applied, so they don't need to sum up to one. The result is undefined
+/// if that sum is zero.
+///
+/// [`normalize()`]: crate::graph::BarycentricNode::normalize
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarycentricNode {
+    /// Input values and weights, and output interpolated value.
+    slots: [SlotDef; 5],
+    /// Normalize the weights before interpolating.
+    normalize: bool,
+}
+
+impl Default for BarycentricNode {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl BarycentricNode {
+    /// Create a new node, optionally normalizing the weights.
+    pub fn new(normalize: bool) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", None),
+                SlotDef::input("b", None),
+                SlotDef::input("c", None),
+                SlotDef::input("weights", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("result", None),
+            ],
+            normalize,
+        }
+    }
+
+    /// Check whether the weights are normalized before interpolating.
+    pub fn normalize(&self) -> bool {
+        self.normalize
+    }
+
+    /// Set whether the weights are normalized before interpolating.
+    pub fn set_normalize(&mut self, normalize: bool) {
+        self.normalize = normalize;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for BarycentricNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 4 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 4 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+        let c = inputs.next().unwrap();
+        let mut weights = inputs.next().unwrap();
+        if self.normalize {
+            let one = module.lit(Vec3::ONE);
+            let sum = module.dot(weights, one);
+            weights = module.div(weights, sum);
+        }
+        let wx = module.x(weights);
+        let wy = module.y(weights);
+        let wz = module.z(weights);
+        let a = module.mul(wx, a);
+        let b = module.mul(wy, b);
+        let c = module.mul(wz, c);
+        let ab = module.add(a, b);
+        let result = module.add(ab, c);
+        Ok(vec![result])
+    }
+}
+
+/// Graph node to get any single particle attribute.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeNode {
+    /// The attribute to get.
+    attr: Attribute,
+    /// The output slot corresponding to the get value.
+    slots: [SlotDef; 1],
+}
+
+impl Default for AttributeNode {
+    fn default() -> Self {
+        Self::new(Attribute::POSITION)
+    }
+}
+
+impl AttributeNode {
+    /// Create a new attribute node for the given [`Attribute`].
+    pub fn new(attr: Attribute) -> Self {
+        Self {
+            attr,
+            slots: [SlotDef::output(attr.name(), Some(attr.value_type()))],
+        }
+    }
+}
+
+impl AttributeNode {
+    /// Get the attribute this node reads.
+    pub fn attr(&self) -> Attribute {
+        self.attr
+    }
+
+    /// Set the attribute this node reads.
+    pub fn set_attr(&mut self, attr: Attribute) {
+        self.attr = attr;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for AttributeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        std::slice::from_ref(&self.attr)
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let attr = module.attr(self.attr);
+        Ok(vec![attr])
+    }
+}
+
+/// Graph node to get the value of an effect property.
+///
+/// The property is referenced by name. When the node is evaluated, the property
+/// is declared in the [`Module`] if it doesn't exist yet, with a zero default
+/// value of the node's type. This way, the properties of the [`EffectAsset`]
+/// produced by [`Graph::to_effect_asset()`] are the ones referenced by its
+/// property nodes. Evaluating the node fails with an [`ExprError::TypeError`]
+/// if the module already declares a property with that name but of another
+/// type.
+///
+/// [`EffectAsset`]: crate::EffectAsset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyNode {
+    /// The name of the property to get.
+    name: String,
+    /// The type of the property.
+    value_type: ValueType,
+    /// The output slot corresponding to the get value.
+    slots: [SlotDef; 1],
+}
+
+impl PropertyNode {
+    /// Create a new property node for the property with the given name and
+    /// type.
+    pub fn new(name: impl Into<String>, value_type: ValueType) -> Self {
+        let name = name.into();
+        Self {
+            slots: [SlotDef::output(name.clone(), Some(value_type))],
+            name,
+            value_type,
+        }
+    }
+
+    /// Get the name of the property this node reads.
+    pub fn property_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the type of the property this node reads.
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+}
+
+/// Convert a cycle found while sorting the nodes of a [`Graph`] into an
+/// evaluation error.
+fn cycle_error(err: GraphError) -> ExprError {
+    match err {
+        GraphError::CycleDetected { from, to } => ExprError::GraphEvalError(format!(
+            "Cycle detected in graph between nodes #{} and #{}",
+            from.id(),
+            to.id()
+        )),
+        err => ExprError::GraphEvalError(err.to_string()),
+    }
+}
+
+/// Get the zero value of the given type, used as property default value.
+fn zero_value(value_type: ValueType) -> Value {
+    let zero_scalar = |scalar_type| match scalar_type {
+        ScalarType::Bool => ScalarValue::Bool(false),
+        ScalarType::Float => ScalarValue::Float(0.),
+        ScalarType::Int => ScalarValue::Int(0),
+        ScalarType::Uint => ScalarValue::Uint(0),
+    };
+    match value_type {
+        ValueType::Scalar(scalar_type) => Value::Scalar(zero_scalar(scalar_type)),
+        ValueType::Vector(vector_type) => Value::Vector(VectorValue::splat(
+            &zero_scalar(vector_type.elem_type()),
+            vector_type.count() as u8,
+        )),
+        ValueType::Matrix(matrix_type) => {
+            let (cols, rows) = (matrix_type.cols(), matrix_type.rows());
+            Value::Matrix(MatrixValue::new(cols, rows, &[0.; 16][..cols * rows]))
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PropertyNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn property_name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let property = match module.get_property_by_name(&self.name) {
+            Some(property) => {
+                let value_type = module.get_property(property).unwrap().value_type();
+                if value_type != self.value_type {
+                    return Err(ExprError::TypeError(format!(
+                        "Property '{}' has type {:?} but PropertyNode expects {:?}.",
+                        self.name, value_type, self.value_type
+                    )));
+                }
+                property
+            }
+            None => module.add_property(self.name.clone(), zero_value(self.value_type)),
+        };
+        Ok(vec![module.prop(property)])
+    }
+}
+
+/// Graph node to get the value of a particle attribute at the previous
+/// simulation update.
+///
+/// The node reads the attribute from a snapshot of the particle taken at the
+/// start of the update pass, before any update modifier runs, so the value is
+/// the one written at the end of the previous update. This is only valid in
+/// the [`ModifierContext::Update`] context.
+///
+/// [`ModifierContext::Update`]: crate::ModifierContext::Update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrevAttributeNode {
+    /// The attribute to get.
+    attr: Attribute,
+    /// The output slot corresponding to the get value.
+    slots: [SlotDef; 1],
+}
+
+impl Default for PrevAttributeNode {
+    fn default() -> Self {
+        Self::new(Attribute::POSITION)
+    }
+}
+
+impl PrevAttributeNode {
+    /// Create a new previous-frame attribute node for the given [`Attribute`].
+    pub fn new(attr: Attribute) -> Self {
+        Self {
+            attr,
+            slots: [SlotDef::output(attr.name(), Some(attr.value_type()))],
+        }
+    }
+
+    /// Get the attribute this node reads.
+    pub fn attr(&self) -> Attribute {
+        self.attr
+    }
+
+    /// Set the attribute this node reads.
+    pub fn set_attr(&mut self, attr: Attribute) {
+        self.attr = attr;
+        self.slots = [SlotDef::output(attr.name(), Some(attr.value_type()))];
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PrevAttributeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        std::slice::from_ref(&self.attr)
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![module.prev_attr(self.attr)])
+    }
+}
+
+/// Graph node setting a particle attribute.
+///
+/// This is a modifier node, converted by [`Graph::to_effect_asset()`] into a
+/// [`SetAttributeModifier`] assigning the value linked to its single input
+/// slot, named after the attribute, to the attribute.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetAttributeNode {
+    /// The attribute to set.
+    attr: Attribute,
+    /// The context of the modifier.
+    context: ModifierContext,
+    /// The input slot corresponding to the set value.
+    slots: [SlotDef; 1],
+}
+
+impl SetAttributeNode {
+    /// Create a new node setting the given [`Attribute`] in the given
+    /// context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is not [`ModifierContext::Init`] or
+    /// [`ModifierContext::Update`].
+    pub fn new(context: ModifierContext, attr: Attribute) -> Self {
+        assert!(
+            context == ModifierContext::Init || context == ModifierContext::Update,
+            "SetAttributeNode context must be either Init or Update"
+        );
+        Self {
+            attr,
+            context,
+            slots: [SlotDef::input(attr.name(), Some(attr.value_type()))],
+        }
+    }
+
+    /// Get the attribute this node sets.
+    pub fn attr(&self) -> Attribute {
+        self.attr
+    }
+
+    /// Get the context of the modifier.
+    pub fn context(&self) -> ModifierContext {
+        self.context
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SetAttributeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        std::slice::from_ref(&self.attr)
+    }
+
+    fn eval(
+        &self,
+        _module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![])
+    }
+
+    fn modifier_context(&self) -> Option<ModifierContext> {
+        Some(self.context)
+    }
+
+    fn build_modifier(&self, inputs: Vec<ExprHandle>) -> Result<BoxedModifier, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(Box::new(SetAttributeModifier::new(self.attr, inputs[0])))
+    }
+}
+
+/// Graph node wrapping an arbitrary [`Modifier`].
+///
+/// This is a modifier node, converted by [`Graph::to_effect_asset()`] into a
+/// copy of the wrapped modifier where each expression field is assigned the
+/// value linked to the corresponding input slot.
+///
+/// The node has one input slot per expression field of the modifier, named
+/// after the field, in declaration order. The fields are discovered through
+/// reflection: any [`ExprHandle`] field is an expression field, as well as any
+/// `Option<ExprHandle>` field which is set on the wrapped modifier. An unset
+/// optional field doesn't produce any slot, and stays unset. For example an
+/// [`AccelModifier`] has a single `accel` slot, and an additional `max_speed`
+/// one if created with [`AccelModifier::with_max_speed()`]. The expressions
+/// stored in the fields of the wrapped modifier are never used, and can be any
+/// placeholder handle.
+///
+/// [`AccelModifier`]: crate::AccelModifier
+/// [`AccelModifier::with_max_speed()`]: crate::AccelModifier::with_max_speed
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModifierNode {
+    /// The wrapped modifier.
+    modifier: BoxedModifier,
+    /// The context of the modifier.
+    context: ModifierContext,
+    /// The input slots, one per expression field.
+    slots: Vec<SlotDef>,
+}
+
+impl std::fmt::Debug for ModifierNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModifierNode")
+            .field("modifier", &self.modifier.as_reflect())
+            .field("context", &self.context)
+            .field("slots", &self.slots)
+            .finish()
+    }
+}
+
+impl ModifierNode {
+    /// Create a new node wrapping the given modifier in the given context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is not a single one of [`ModifierContext::Init`],
+    /// [`ModifierContext::Update`], or [`ModifierContext::Render`], or if the
+    /// modifier doesn't support that context.
+    pub fn new(context: ModifierContext, modifier: impl Modifier) -> Self {
+        assert!(
+            context == ModifierContext::Init
+                || context == ModifierContext::Update
+                || context == ModifierContext::Render,
+            "ModifierNode context must be either Init, Update, or Render"
+        );
+        assert!(
+            modifier.context().contains(context),
+            "ModifierNode modifier doesn't support the {} context",
+            context
+        );
+        let slots = Self::expr_fields(&modifier)
+            .into_iter()
+            .map(|name| SlotDef::input(name, None))
+            .collect();
+        Self {
+            modifier: Box::new(modifier),
+            context,
+            slots,
+        }
+    }
+
+    /// Get the wrapped modifier.
+    pub fn modifier(&self) -> &dyn Modifier {
+        self.modifier.as_ref()
+    }
+
+    /// Get the context of the modifier.
+    pub fn context(&self) -> ModifierContext {
+        self.context
+    }
+
+    /// Get the names of the expression fields of a modifier, in declaration
+    /// order.
+    fn expr_fields(modifier: &dyn Reflect) -> Vec<String> {
+        let ReflectRef::Struct(s) = modifier.reflect_ref() else {
+            return vec![];
+        };
+        (0..s.field_len())
+            .filter(|&index| {
+                let field = s.field_at(index).unwrap();
+                field.is::<ExprHandle>()
+                    || field
+                        .downcast_ref::<Option<ExprHandle>>()
+                        .is_some_and(Option::is_some)
+            })
+            .map(|index| s.name_at(index).unwrap().to_string())
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ModifierNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        self.modifier.attributes()
+    }
+
+    fn eval(
+        &self,
+        _module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != self.slots.len() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected {} inputs, got {}",
+                self.slots.len(),
+                inputs.len()
+            )));
+        }
+        Ok(vec![])
+    }
+
+    fn modifier_context(&self) -> Option<ModifierContext> {
+        Some(self.context)
+    }
+
+    fn build_modifier(&self, inputs: Vec<ExprHandle>) -> Result<BoxedModifier, ExprError> {
+        if inputs.len() != self.slots.len() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected {} inputs, got {}",
+                self.slots.len(),
+                inputs.len()
+            )));
+        }
+        let mut modifier = self.modifier.clone();
+        if let ReflectMut::Struct(s) = modifier.as_reflect_mut().reflect_mut() {
+            for (slot, input) in self.slots.iter().zip(inputs) {
+                let field = s.field_mut(slot.name()).unwrap();
+                if let Some(handle) = field.downcast_mut::<ExprHandle>() {
+                    *handle = input;
+                } else if let Some(handle) = field.downcast_mut::<Option<ExprHandle>>() {
+                    *handle = Some(input);
+                }
+            }
+        }
+        Ok(modifier)
+    }
+}
+
+/// Graph node to draw per-particle random values.
+///
+/// Each evaluation of the node produces a random value with each component
+/// uniformly distributed in \[0:1\], drawn from the per-particle random state
+/// with [`BuiltInOperator::Rand`]. The output is a `f32` scalar, or a `Vec2`,
+/// `Vec3`, or `Vec4` vector, as selected when creating the node. Vector values
+/// are drawn with a single call (`frand2()` to `frand4()`), each component
+/// being independent from the others. The default node produces a `f32`.
+///
+/// See also [`SeededRandNode`] to draw from an independent stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomNode {
+    /// Output random value.
+    slots: [SlotDef; 1],
+}
+
+impl Default for RandomNode {
+    fn default() -> Self {
+        Self::new(ValueType::Scalar(ScalarType::Float))
+    }
+}
+
+impl RandomNode {
+    /// Create a new node producing random values of the given type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value_type` is not a `f32` scalar or vector.
+    pub fn new(value_type: ValueType) -> Self {
+        let is_float = match value_type {
+            ValueType::Scalar(scalar_type) => scalar_type == ScalarType::Float,
+            ValueType::Vector(vector_type) => vector_type.elem_type() == ScalarType::Float,
+            ValueType::Matrix(_) => false,
+        };
+        assert!(
+            is_float,
+            "Invalid value type {:?} for RandomNode, must be a f32 scalar or vector.",
+            value_type
+        );
+        Self {
+            slots: [SlotDef::output("rand", Some(value_type))],
+        }
+    }
+
+    /// Get the type of the random values produced by the node.
+    pub fn value_type(&self) -> ValueType {
+        self.slots[0].value_type().unwrap()
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for RandomNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![
+            module.builtin(BuiltInOperator::Rand(self.value_type()))
+        ])
+    }
+}
+
+/// Graph node to draw random values from an independent, seeded stream.
+///
+/// Each evaluation of the node produces a random `f32` value uniformly
+/// distributed in \[0:1\], drawn from the stream identified by the node's
+/// stream seed. Unlike multiple uses of [`BuiltInOperator::Rand`], which all
+/// draw from a single stream, nodes with different stream seeds produce
+/// decorrelated values, even when used by the same particle.
+///
+/// The stream seed doesn't replace the other sources of randomness: each draw
+/// still advances the per-particle random state, itself seeded from the
+/// per-effect seed of the [`Spawner`] and the particle index, then mixes in the
+/// hashed stream seed. So two particles, or two effect instances, drawing from
+/// the same stream still get different values. See
+/// [`UnaryOperator::SeededRand`] for details.
+///
+/// [`UnaryOperator::SeededRand`]: crate::graph::UnaryOperator::SeededRand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeededRandNode {
+    /// Output random value.
+    slots: [SlotDef; 1],
+    /// Seed of the random stream.
+    seed: u32,
+}
+
+impl Default for SeededRandNode {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl SeededRandNode {
+    /// Create a new node drawing from the stream with the given seed.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            slots: [SlotDef::output(
+                "rand",
+                Some(ValueType::Scalar(ScalarType::Float)),
+            )],
+            seed,
+        }
+    }
+
+    /// Get the seed of the random stream.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Set the seed of the random stream.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SeededRandNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let seed = module.lit(self.seed);
+        Ok(vec![module.seeded_rand(seed)])
+    }
+}
+
+/// Graph node to get various time values related to the effect system.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeNode {
+    /// Output slots corresponding to the various time-related quantities.
+    slots: [SlotDef; 2],
+}
+
+impl Default for TimeNode {
+    fn default() -> Self {
+        Self {
+            slots: [BuiltInOperator::Time, BuiltInOperator::DeltaTime]
+                .map(|op| SlotDef::output(op.name(), Some(op.value_type()))),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for TimeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok([BuiltInOperator::Time, BuiltInOperator::DeltaTime]
+            .map(|op| module.builtin(op))
+            .to_vec())
+    }
+}
+
+/// Graph node to negate a value.
+///
+/// The node outputs the opposite `-(in)` of its single input. The input can be
+/// any signed numeric scalar or vector, and the output has the same type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegateNode {
+    /// Input and output values.
+    slots: [SlotDef; 2],
+}
+
+impl Default for NegateNode {
+    fn default() -> Self {
+        Self {
+            slots: [SlotDef::input("in", None), SlotDef::output("out", None)],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for NegateNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let neg = module.neg(input);
+        Ok(vec![neg])
+    }
+}
+
+/// Graph node to normalize a vector value.
+///
+/// By default the node emits a plain `normalize()`, which produces NaN if the
+/// input is the zero vector. In safe mode (see [`with_safe()`]), the node
+/// instead outputs the zero vector for inputs with a length not greater than
+/// the [`epsilon()`] of the node:
+///
+/// ```txt
+/// out = select(in * 0., normalize(in), length(in) > epsilon)
+/// ```
+///
+/// This is useful for example to normalize a velocity which may be zero.
+///
+/// [`with_safe()`]: NormalizeNode::with_safe
+/// [`epsilon()`]: NormalizeNode::epsilon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalizeNode {
+    /// Input and output vectors.
+    slots: [SlotDef; 2],
+    /// Guard against the normalizing of zero vectors.
+    #[cfg_attr(feature = "serde", serde(default))]
+    safe: bool,
+    /// Length under which vectors are considered zero, in safe mode.
+    #[cfg_attr(feature = "serde", serde(default = "NormalizeNode::default_epsilon"))]
+    epsilon: f32,
+}
+
+impl Default for NormalizeNode {
+    fn default() -> Self {
+        Self {
+            slots: [SlotDef::input("in", None), SlotDef::output("out", None)],
+            safe: false,
+            epsilon: Self::DEFAULT_EPSILON,
+        }
+    }
+}
+
+impl NormalizeNode {
+    /// Default length under which vectors are considered zero in safe mode.
+    pub const DEFAULT_EPSILON: f32 = 1e-6;
+
+    #[cfg(feature = "serde")]
+    fn default_epsilon() -> f32 {
+        Self::DEFAULT_EPSILON
+    }
+
+    /// Enable or disable the safe mode, which outputs the zero vector instead
+    /// of NaN when normalizing a zero vector.
+    pub fn with_safe(mut self, safe: bool) -> Self {
+        self.safe = safe;
+        self
+    }
+
+    /// Set the length under which vectors are considered zero in safe mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `epsilon` is negative.
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        assert!(epsilon >= 0.);
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Is the safe mode enabled?
+    pub fn is_safe(&self) -> bool {
+        self.safe
+    }
+
+    /// Get the length under which vectors are considered zero in safe mode.
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for NormalizeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let norm = module.normalize(input);
+        if !self.safe {
+            return Ok(vec![norm]);
+        }
+        let zero = module.lit(0.);
+        let zero = module.mul(input, zero);
+        let length = module.length(input);
+        let epsilon = module.lit(self.epsilon);
+        let is_valid = module.gt(length, epsilon);
+        let norm = module.select(zero, norm, is_valid);
+        Ok(vec![norm])
+    }
+}
+
+/// Graph node to calculate the dot product of two vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for DotNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("rhs", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("result", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for DotNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let dot = module.dot(left, right);
+        Ok(vec![dot])
+    }
+}
+
+/// Graph node to calculate the cross product of two vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for CrossNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("lhs", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("rhs", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("result", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for CrossNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let left = inputs.next().unwrap();
+        let right = inputs.next().unwrap();
+        let cross = module.cross(left, right);
+        Ok(vec![cross])
+    }
+}
+
+/// Graph node to calculate the distance between two points.
+///
+/// The two input points `a` and `b` are float vectors of any width, but must
+/// have the same type. The output is the scalar `distance(a, b)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistanceNode {
+    slots: [SlotDef; 3],
+}
+
+impl Default for DistanceNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", None),
+                SlotDef::input("b", None),
+                SlotDef::output("distance", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for DistanceNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+
+        // Validate the input types where they're known at build time
+        let a_type = module.get(a).and_then(|expr| expr.value_type());
+        let b_type = module.get(b).and_then(|expr| expr.value_type());
+        for value_type in [a_type, b_type].into_iter().flatten() {
+            if !matches!(value_type, ValueType::Vector(vector_type) if vector_type.elem_type() == ScalarType::Float)
+            {
+                return Err(ExprError::GraphEvalError(format!(
+                    "invalid input type: expected a float vector, got {:?}",
+                    value_type
+                )));
+            }
+        }
+        if let (Some(a_type), Some(b_type)) = (a_type, b_type) {
+            if a_type != b_type {
+                return Err(ExprError::GraphEvalError(format!(
+                    "mismatching input types {:?} and {:?}",
+                    a_type, b_type
+                )));
+            }
+        }
+
+        let distance = module.distance(a, b);
+        Ok(vec![distance])
+    }
+}
+
+/// Graph node to calculate the length of a vector value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LengthNode {
+    /// Input vector and output length.
+    slots: [SlotDef; 2],
+}
+
+impl Default for LengthNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("in", None),
+                SlotDef::output("length", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for LengthNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let length = module.length(input);
+        Ok(vec![length])
+    }
+}
+
+/// Operator applied by a [`UnaryMathNode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnaryMathOp {
+    /// Absolute value, with WGSL's `abs()`. This is the default.
+    #[default]
+    Abs,
+    /// Round down to the nearest integral value, with WGSL's `floor()`.
+    Floor,
+    /// Round up to the nearest integral value, with WGSL's `ceil()`.
+    Ceil,
+    /// Round to the nearest integral value, with WGSL's `round()`. Values
+    /// halfway between two integral values are rounded to the even one.
+    Round,
+    /// Sign of the value, with WGSL's `sign()`: `-1` for negative values, `1`
+    /// for positive ones, and `0` for zero.
+    Sign,
+}
+
+/// Graph node to apply a single-operand math operator to a value.
+///
+/// The node applies the [`UnaryMathOp`] of the node to its single `in` input,
+/// and outputs the result. The node accepts both scalar and vector values, and
+/// applies component-wise to vectors. The output has the same type as the
+/// input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnaryMathNode {
+    /// Input and output values.
+    slots: [SlotDef; 2],
+    /// Operator applied to the input.
+    op: UnaryMathOp,
+}
+
+impl Default for UnaryMathNode {
+    fn default() -> Self {
+        Self::new(UnaryMathOp::default())
+    }
+}
+
+impl UnaryMathNode {
+    /// Create a new node applying the given operator.
+    pub fn new(op: UnaryMathOp) -> Self {
+        Self {
+            slots: [SlotDef::input("in", None), SlotDef::output("out", None)],
+            op,
+        }
+    }
+
+    /// Get the operator applied by the node.
+    pub fn op(&self) -> UnaryMathOp {
+        self.op
+    }
+
+    /// Set the operator applied by the node.
+    pub fn set_op(&mut self, op: UnaryMathOp) {
+        self.op = op;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for UnaryMathNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let output = match self.op {
+            UnaryMathOp::Abs => module.abs(input),
+            UnaryMathOp::Floor => module.floor(input),
+            UnaryMathOp::Ceil => module.ceil(input),
+            UnaryMathOp::Round => module.round(input),
+            UnaryMathOp::Sign => module.sign(input),
+        };
+        Ok(vec![output])
+    }
+}
+
+/// Graph node to calculate the sine of a value.
+///
+/// The input angle is expressed in radians. The node accepts both scalar and
+/// vector values, and applies component-wise to vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinNode {
+    /// Input angle and output sine.
+    slots: [SlotDef; 2],
+}
+
+impl Default for SinNode {
+    fn default() -> Self {
+        Self {
+            slots: [SlotDef::input("in", None), SlotDef::output("out", None)],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SinNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let sin = module.sin(input);
+        Ok(vec![sin])
+    }
+}
+
+/// Graph node to calculate the cosine of a value.
+///
+/// The input angle is expressed in radians. The node accepts both scalar and
+/// vector values, and applies component-wise to vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosNode {
+    /// Input angle and output cosine.
+    slots: [SlotDef; 2],
+}
+
+impl Default for CosNode {
+    fn default() -> Self {
+        Self {
+            slots: [SlotDef::input("in", None), SlotDef::output("out", None)],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for CosNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let input = inputs.into_iter().next().unwrap();
+        let cos = module.cos(input);
+        Ok(vec![cos])
+    }
+}
+
+/// Graph node to raise a value to a power.
+///
+/// The node accepts both floating-point scalar and vector values, and
+/// applies component-wise to vectors, in which case both inputs must have the
+/// same rank.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowNode {
+    /// Input base and exponent, and output result.
+    slots: [SlotDef; 3],
+}
+
+impl Default for PowNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("base", None),
+                SlotDef::input("exponent", None),
+                SlotDef::output("result", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PowNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let base = inputs.next().unwrap();
+        let exponent = inputs.next().unwrap();
+        let pow = module.pow(base, exponent);
+        Ok(vec![pow])
+    }
+}
+
+/// Graph node to split a value into its integer and fractional parts.
+///
+/// The integer part is rounded toward negative infinity, so that the two parts
+/// always sum back to the input, and the fractional part is in \[0:1\[ even
+/// for negative values:
+///
+/// ```txt
+/// integer = floor(value)
+/// fractional = fract(value)
+/// ```
+///
+/// Note that this differs from the WGSL `modf()` built-in, which truncates
+/// toward zero. The node accepts both scalar and vector values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModfNode {
+    /// Input value, and output integer and fractional parts.
+    slots: [SlotDef; 3],
+}
+
+impl Default for ModfNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("value", None),
+                SlotDef::output("integer", None),
+                SlotDef::output("fractional", None),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ModfNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let value = inputs.into_iter().next().unwrap();
+        let integer = module.floor(value);
+        let fractional = module.fract(value);
+        Ok(vec![integer, fractional])
+    }
+}
+
+/// Names of the vector component slots of [`VecComposeNode`] and
+/// [`VecDecomposeNode`], in order.
+const VEC_COMPONENTS: [&str; 4] = ["x", "y", "z", "w"];
+
+/// Graph node to build a vector from its scalar components.
+///
+/// The node has 2 to 4 `f32` inputs named `x`, `y`, `z`, and `w`, depending on
+/// the number of components of the vector, and a single `vec` output. The
+/// default node builds a `Vec3`.
+///
+/// See also [`VecDecomposeNode`] for the reverse operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VecComposeNode {
+    /// Component inputs and vector output.
+    slots: Vec<SlotDef>,
+}
+
+impl Default for VecComposeNode {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl VecComposeNode {
+    /// Create a new node building a vector of `count` components.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is not 2, 3, or 4.
+    pub fn new(count: u8) -> Self {
+        assert!(
+            (2..=4).contains(&count),
+            "Invalid vector component count {}, must be 2, 3, or 4.",
+            count
+        );
+        let mut slots = VEC_COMPONENTS[..count as usize]
+            .iter()
+            .map(|name| SlotDef::input(*name, Some(ValueType::Scalar(ScalarType::Float))))
+            .collect::<Vec<_>>();
+        slots.push(SlotDef::output(
+            "vec",
+            Some(ValueType::Vector(VectorType::new(ScalarType::Float, count))),
+        ));
+        Self { slots }
+    }
+
+    /// Get the number of components of the vector built by the node.
+    pub fn count(&self) -> u8 {
+        (self.slots.len() - 1) as u8
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for VecComposeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        let count = self.count() as usize;
+        if inputs.len() != count {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected {} inputs, got {}",
+                count,
+                inputs.len()
+            )));
+        }
+        let vec = match inputs[..] {
+            [x, y] => module.vec2(x, y),
+            [x, y, z] => module.vec3(x, y, z),
+            [x, y, z, w] => {
+                let xyz = module.vec3(x, y, z);
+                module.vec4(xyz, w)
+            }
+            _ => unreachable!(),
+        };
+        Ok(vec![vec])
+    }
+}
+
+/// Graph node to split a vector into its scalar components.
+///
+/// The node has a single `vec` input, and 2 to 4 `f32` outputs named `x`, `y`,
+/// `z`, and `w`, depending on the number of components of the vector. The
+/// default node splits a `Vec3`.
+///
+/// See also [`VecComposeNode`] for the reverse operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VecDecomposeNode {
+    /// Vector input and component outputs.
+    slots: Vec<SlotDef>,
+}
+
+impl Default for VecDecomposeNode {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl VecDecomposeNode {
+    /// Create a new node splitting a vector of `count` components.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is not 2, 3, or 4.
+    pub fn new(count: u8) -> Self {
+        assert!(
+            (2..=4).contains(&count),
+            "Invalid vector component count {}, must be 2, 3, or 4.",
+            count
+        );
+        let mut slots = vec![SlotDef::input(
+            "vec",
+            Some(ValueType::Vector(VectorType::new(ScalarType::Float, count))),
+        )];
+        slots.extend(
+            VEC_COMPONENTS[..count as usize]
+                .iter()
+                .map(|name| SlotDef::output(*name, Some(ValueType::Scalar(ScalarType::Float)))),
+        );
+        Self { slots }
+    }
+
+    /// Get the number of components of the vector split by the node.
+    pub fn count(&self) -> u8 {
+        (self.slots.len() - 1) as u8
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for VecDecomposeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let vec = inputs[0];
+        Ok(
+            [Module::x, Module::y, Module::z, Module::w][..self.count() as usize]
+                .iter()
+                .map(|component| component(module, vec))
+                .collect(),
+        )
+    }
+}
+
+/// Graph node to convert spherical coordinates into a cartesian vector.
+///
+/// The node uses the Y-up convention of Bevy. The azimuth `theta` is the angle
+/// in radians around the Y axis, measured from the +Z axis toward the +X axis.
+/// The elevation `phi` is the angle in radians between the vector and the XZ
+/// plane, positive toward +Y. The `radius` is the length of the vector.
+///
+/// ```txt
+/// x = radius * cos(phi) * sin(theta)
+/// y = radius * sin(phi)
+/// z = radius * cos(phi) * cos(theta)
+/// ```
+///
+/// See [`CartesianToSphericalNode`] for the inverse conversion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SphericalToCartesianNode {
+    /// Input angles and radius, and output vector.
+    slots: [SlotDef; 4],
+}
+
+impl Default for SphericalToCartesianNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("theta", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::input("phi", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::input("radius", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("vector", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SphericalToCartesianNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let theta = inputs.next().unwrap();
+        let phi = inputs.next().unwrap();
+        let radius = inputs.next().unwrap();
+
+        let cos_phi = module.cos(phi);
+        let sin_phi = module.sin(phi);
+        let cos_theta = module.cos(theta);
+        let sin_theta = module.sin(theta);
+        let r_cos_phi = module.mul(radius, cos_phi);
+        let x = module.mul(r_cos_phi, sin_theta);
+        let y = module.mul(radius, sin_phi);
+        let z = module.mul(r_cos_phi, cos_theta);
+        let vector = module.ternary(TernaryOperator::Vec3, x, y, z);
+        Ok(vec![vector])
+    }
+}
+
+/// Graph node to convert a cartesian vector into spherical coordinates.
+///
+/// This is the inverse of [`SphericalToCartesianNode`], and uses the same
+/// convention for the azimuth `theta` and elevation `phi` angles. The azimuth
+/// is in `[-π:π]` and the elevation in `[-π/2:π/2]`.
+///
+/// ```txt
+/// radius = length(vector)
+/// theta = atan2(x, z)
+/// phi = atan2(y, length(vec2(x, z)))
+/// ```
+///
+/// For the zero vector, both angles are zero. For a vector aligned with the Y
+/// axis, the azimuth `theta` is zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CartesianToSphericalNode {
+    /// Input vector, and output angles and radius.
+    slots: [SlotDef; 4],
+}
+
+impl Default for CartesianToSphericalNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("vector", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("theta", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("phi", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("radius", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for CartesianToSphericalNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let vector = inputs.into_iter().next().unwrap();
+
+        let x = module.x(vector);
+        let y = module.y(vector);
+        let z = module.z(vector);
+        let radius = module.length(vector);
+        let xz = module.vec2(x, z);
+        let len_xz = module.length(xz);
+
+        // atan2(0, 0) is undefined, so offset the denominator by one when the
+        // numerator and denominator are both zero. step(e, 0.) is 1. if e <= 0.
+        let zero = module.lit(0_f32);
+        let is_on_y_axis = module.step(len_xz, zero);
+        let z_guarded = module.add(z, is_on_y_axis);
+        let theta = module.atan2(x, z_guarded);
+        let is_zero = module.step(radius, zero);
+        let len_xz_guarded = module.add(len_xz, is_zero);
+        let phi = module.atan2(y, len_xz_guarded);
+
+        Ok(vec![theta, phi, radius])
+    }
+}
+
+/// Graph node to compute the angle between two vectors.
+///
+/// The node outputs the unsigned angle, in radians in `[0:π]`, between its two
+/// input vectors:
+///
+/// ```txt
+/// angle = acos(clamp(dot(normalize(a), normalize(b)), -1, 1))
+/// ```
+///
+/// The dot product is clamped because, due to floating-point rounding, the dot
+/// product of two normalized vectors can land slightly outside of `[-1:1]`,
+/// where `acos()` returns `NaN`. The angle is undefined if any of the vectors
+/// is zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AngleBetweenNode {
+    /// Input vectors, and output angle.
+    slots: [SlotDef; 3],
+}
+
+impl Default for AngleBetweenNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("b", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("angle", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for AngleBetweenNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+
+        let a = module.normalize(a);
+        let b = module.normalize(b);
+        let cos_angle = module.dot(a, b);
+        let minus_one = module.lit(-1_f32);
+        let one = module.lit(1_f32);
+        let cos_angle = module.clamp(cos_angle, minus_one, one);
+        let angle = module.acos(cos_angle);
+        Ok(vec![angle])
+    }
+}
+
+/// Build the expression of the projection of `a` onto `b`.
+///
+/// The squared length of `b` is bounded below by a small epsilon, so that a
+/// zero `b` projects any vector to zero instead of producing `NaN`.
+fn project_expr(module: &mut Module, a: ExprHandle, b: ExprHandle) -> ExprHandle {
+    let dot_ab = module.dot(a, b);
+    let dot_bb = module.dot(b, b);
+    let epsilon = module.lit(1e-6_f32);
+    let dot_bb = module.max(dot_bb, epsilon);
+    let scale = module.div(dot_ab, dot_bb);
+    module.mul(b, scale)
+}
+
+/// Graph node to compute the projection of a vector onto another.
+///
+/// The node outputs the component of `a` parallel to `b`:
+///
+/// ```txt
+/// projection = b * dot(a, b) / dot(b, b)
+/// ```
+///
+/// This is typically used to constrain a motion along an axis. If `b` is
+/// zero, the projection is zero.
+///
+/// See also [`RejectNode`] for the component of `a` orthogonal to `b`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectNode {
+    /// Input vectors, and output vector.
+    slots: [SlotDef; 3],
+}
+
+impl Default for ProjectNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("b", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("projection", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ProjectNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+
+        Ok(vec![project_expr(module, a, b)])
+    }
+}
+
+/// Graph node to compute the rejection of a vector from another.
+///
+/// The node outputs the component of `a` orthogonal to `b`, that is `a` minus
+/// its projection onto `b`:
+///
+/// ```txt
+/// rejection = a - b * dot(a, b) / dot(b, b)
+/// ```
+///
+/// This is typically used to make particles slide along a surface of normal
+/// `b`. If `b` is zero, the rejection is `a` itself.
+///
+/// See also [`ProjectNode`] for the component of `a` parallel to `b`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectNode {
+    /// Input vectors, and output vector.
+    slots: [SlotDef; 3],
+}
+
+impl Default for RejectNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("b", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("rejection", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for RejectNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+
+        let projection = project_expr(module, a, b);
+        Ok(vec![module.sub(a, projection)])
+    }
+}
+
+/// Graph node to compute the acceleration toward an attractor point.
+///
+/// The node outputs the acceleration of a particle at `position` attracted by
+/// a point `center`, with an inverse-square falloff:
+///
+/// ```txt
+/// delta = center - position
+/// acceleration = normalize(delta) * strength / max(dot(delta, delta), epsilon)
+/// ```
+///
+/// The `epsilon` bounds the acceleration near the center, where the distance
+/// goes to zero; see [`with_epsilon()`]. A negative `strength` repels the
+/// particles instead. The output is typically added to the
+/// [`Attribute::VELOCITY`] of the particle, scaled by the delta time, to make
+/// particles orbit or converge toward the center.
+///
+/// [`with_epsilon()`]: AttractorNode::with_epsilon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttractorNode {
+    /// Position, center, and strength inputs, and acceleration output.
+    slots: [SlotDef; 4],
+    /// Minimum squared distance used for the falloff.
+    #[cfg_attr(feature = "serde", serde(default = "AttractorNode::default_epsilon"))]
+    epsilon: f32,
+}
+
+impl Default for AttractorNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("position", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("center", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("strength", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("acceleration", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+            epsilon: Self::DEFAULT_EPSILON,
+        }
+    }
+}
+
+impl AttractorNode {
+    /// Default minimum squared distance used for the falloff.
+    pub const DEFAULT_EPSILON: f32 = 1e-3;
+
+    #[cfg(feature = "serde")]
+    fn default_epsilon() -> f32 {
+        Self::DEFAULT_EPSILON
+    }
+
+    /// Set the minimum squared distance used for the falloff.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `epsilon` is not strictly positive.
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        assert!(epsilon > 0.);
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Get the minimum squared distance used for the falloff.
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for AttractorNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let position = inputs.next().unwrap();
+        let center = inputs.next().unwrap();
+        let strength = inputs.next().unwrap();
+
+        let delta = module.sub(center, position);
+        let dist_sq = module.dot(delta, delta);
+        let epsilon = module.lit(self.epsilon);
+        let dist_sq = module.max(dist_sq, epsilon);
+        let dir = module.normalize(delta);
+        let accel = module.mul(dir, strength);
+        let accel = module.div(accel, dist_sq);
+        Ok(vec![accel])
+    }
+}
+
+/// Graph node to sample a direction in a cosine-weighted hemisphere.
+///
+/// The node maps two independent random values `u1` and `u2`, uniformly
+/// distributed in \[0:1\], to a unit direction in the hemisphere around
+/// `normal`. The probability density of the direction is proportional to the
+/// cosine of its angle with the normal, which is the correct distribution for
+/// diffuse (Lambertian) scattering, unlike a uniform hemisphere sampling. The
+/// random values are typically produced by a [`BuiltInOperator::Rand`] or a
+/// [`SeededRandNode`].
+///
+/// The `normal` is normalized before use. The direction is first sampled in a
+/// local frame where the normal is the Z axis, then transformed by a tangent
+/// basis built from the normal without any singularity, even for a normal
+/// along the Z axis (Duff et al., "Building an Orthonormal Basis, Revisited"):
+///
+/// ```txt
+/// r = sqrt(u1)
+/// phi = 2 * PI * u2
+/// local = vec3(r * cos(phi), r * sin(phi), sqrt(1 - u1))
+/// s = select(-1, 1, normal.z >= 0)
+/// a = -1 / (s + normal.z)
+/// b = normal.x * normal.y * a
+/// tangent = vec3(1 + s * normal.x * normal.x * a, s * b, -s * normal.x)
+/// bitangent = vec3(b, s + normal.y * normal.y * a, -normal.y)
+/// direction = tangent * local.x + bitangent * local.y + normal * local.z
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosineHemisphereNode {
+    /// Normal and random inputs, and direction output.
+    slots: [SlotDef; 4],
+}
+
+impl Default for CosineHemisphereNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("normal", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("u1", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::input("u2", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("direction", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for CosineHemisphereNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let normal = inputs.next().unwrap();
+        let u1 = inputs.next().unwrap();
+        let u2 = inputs.next().unwrap();
+
+        // Cosine-weighted sample in the local frame, around the Z axis
+        let one = module.lit(1.);
+        let r = module.sqrt(u1);
+        let tau = module.lit(std::f32::consts::TAU);
+        let phi = module.mul(tau, u2);
+        let cos_phi = module.cos(phi);
+        let sin_phi = module.sin(phi);
+        let local_x = module.mul(r, cos_phi);
+        let local_y = module.mul(r, sin_phi);
+        let one_minus_u1 = module.sub(one, u1);
+        let local_z = module.sqrt(one_minus_u1);
+
+        // Orthonormal basis around the normal
+        let n = module.normalize(normal);
+        let nx = module.x(n);
+        let ny = module.y(n);
+        let nz = module.z(n);
+        let zero = module.lit(0.);
+        let minus_one = module.lit(-1.);
+        let is_positive = module.ge(nz, zero);
+        let s = module.select(minus_one, one, is_positive);
+        let s_plus_nz = module.add(s, nz);
+        let a = module.div(minus_one, s_plus_nz);
+        let nxny = module.mul(nx, ny);
+        let b = module.mul(nxny, a);
+        let s_nx = module.mul(s, nx);
+        let s_nx_nx = module.mul(s_nx, nx);
+        let s_nx_nx_a = module.mul(s_nx_nx, a);
+        let tx = module.add(one, s_nx_nx_a);
+        let ty = module.mul(s, b);
+        let tz = module.mul(minus_one, s_nx);
+        let tangent = module.vec3(tx, ty, tz);
+        let ny_ny = module.mul(ny, ny);
+        let ny_ny_a = module.mul(ny_ny, a);
+        let by = module.add(s, ny_ny_a);
+        let bz = module.mul(minus_one, ny);
+        let bitangent = module.vec3(b, by, bz);
+
+        let x = module.mul(tangent, local_x);
+        let y = module.mul(bitangent, local_y);
+        let z = module.mul(n, local_z);
+        let xy = module.add(x, y);
+        let direction = module.add(xy, z);
+        Ok(vec![direction])
+    }
+}
+
+/// Graph node to compute the signed distance from a point to a sphere.
+///
+/// The signed distance is negative inside the sphere, zero on its surface, and
+/// positive outside:
+///
+/// ```txt
+/// distance = length(point - center) - radius
+/// ```
+///
+/// The output can drive force or kill logic, for example to confine particles
+/// to a volume.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SphereSdfNode {
+    /// Input point and sphere parameters, and output signed distance.
+    slots: [SlotDef; 4],
+}
+
+impl Default for SphereSdfNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("point", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("center", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("radius", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("distance", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SphereSdfNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let point = inputs.next().unwrap();
+        let center = inputs.next().unwrap();
+        let radius = inputs.next().unwrap();
+
+        let p = module.sub(point, center);
+        let len = module.length(p);
+        let distance = module.sub(len, radius);
+        Ok(vec![distance])
+    }
+}
+
+/// Graph node to compute the signed distance from a point to a rounded box.
+///
+/// The box is axis-aligned, centered at `center`, extends `half_extents` along
+/// each axis, and has its edges and corners rounded by `radius`. The rounding
+/// is applied inside the box, so the box never extends beyond `half_extents`.
+/// The signed distance is negative inside the box, zero on its surface, and
+/// positive outside:
+///
+/// ```txt
+/// q = abs(point - center) - half_extents + radius
+/// distance = length(max(q, 0)) + min(max(q.x, max(q.y, q.z)), 0) - radius
+/// ```
+///
+/// A zero `radius` produces a box with sharp edges.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundedBoxSdfNode {
+    /// Input point and box parameters, and output signed distance.
+    slots: [SlotDef; 5],
+}
+
+impl Default for RoundedBoxSdfNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("point", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("center", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("half_extents", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("radius", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("distance", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for RoundedBoxSdfNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 4 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 4 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let point = inputs.next().unwrap();
+        let center = inputs.next().unwrap();
+        let half_extents = inputs.next().unwrap();
+        let radius = inputs.next().unwrap();
+
+        let p = module.sub(point, center);
+        let p = module.abs(p);
+        let q = module.sub(p, half_extents);
+        let q = module.add(q, radius);
+        let zero3 = module.lit(Vec3::ZERO);
+        let outside = module.max(q, zero3);
+        let outside = module.length(outside);
+        let qx = module.x(q);
+        let qy = module.y(q);
+        let qz = module.z(q);
+        let max_yz = module.max(qy, qz);
+        let max_q = module.max(qx, max_yz);
+        let zero = module.lit(0_f32);
+        let inside = module.min(max_q, zero);
+        let distance = module.add(outside, inside);
+        let distance = module.sub(distance, radius);
+        Ok(vec![distance])
+    }
+}
+
+/// Graph node to rotate a vector by a quaternion.
+///
+/// The quaternion is passed as a `vec4<f32>` value storing the `(x, y, z, w)`
+/// components of the quaternion, in the same order as Bevy's [`Quat`]. A
+/// [`Quat`] can be converted into a [`Value`] with `From<Quat>`. The
+/// quaternion is expected to be normalized.
+///
+/// The node evaluates the standard quaternion-vector rotation formula:
+///
+/// ```txt
+/// t = 2 * cross(q.xyz, v)
+/// rotated = v + q.w * t + cross(q.xyz, t)
+/// ```
+///
+/// [`Quat`]: bevy::math::Quat
+/// [`Value`]: crate::graph::Value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuatRotateNode {
+    /// Input quaternion and vector, and output rotated vector.
+    slots: [SlotDef; 3],
+}
+
+impl Default for QuatRotateNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("quat", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::input("vector", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("rotated", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for QuatRotateNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let quat = inputs.next().unwrap();
+        let vector = inputs.next().unwrap();
+
+        let qx = module.x(quat);
+        let qy = module.y(quat);
+        let qz = module.z(quat);
+        let qw = module.w(quat);
+        let axis = module.ternary(TernaryOperator::Vec3, qx, qy, qz);
+
+        let two = module.lit(2_f32);
+        let c = module.cross(axis, vector);
+        let t = module.mul(two, c);
+        let wt = module.mul(qw, t);
+        let ct = module.cross(axis, t);
+        let v_wt = module.add(vector, wt);
+        let rotated = module.add(v_wt, ct);
+        Ok(vec![rotated])
+    }
+}
+
+/// Graph node to build a rotation matrix from an axis and an angle.
+///
+/// The node outputs the `mat3x3<f32>` matrix of the rotation of `angle`
+/// radians around `axis`, using the right-hand rule. The axis doesn't need to
+/// be normalized, but must not be zero. Each column `i` of the matrix is the
+/// rotation of the unit vector `e_i` along the i-th coordinate axis, given by
+/// the Rodrigues rotation formula:
+///
+/// ```txt
+/// k = normalize(axis)
+/// col_i = cos(angle) * e_i + sin(angle) * cross(k, e_i) + (1 - cos(angle)) * k_i * k
+/// ```
+///
+/// The output matrix can be used with a [`MatrixTransformNode`] to rotate some
+/// vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxisAngleMatrixNode {
+    /// Input axis and angle, and output matrix.
+    slots: [SlotDef; 3],
+}
+
+impl Default for AxisAngleMatrixNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("axis", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("angle", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("matrix", Some(ValueType::Matrix(MatrixType::MAT3X3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for AxisAngleMatrixNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let axis = inputs.next().unwrap();
+        let angle = inputs.next().unwrap();
+
+        let k = module.normalize(axis);
+        let cos = module.cos(angle);
+        let sin = module.sin(angle);
+        let one = module.lit(1_f32);
+        let one_minus_cos = module.sub(one, cos);
+
+        let columns = [Vec3::X, Vec3::Y, Vec3::Z].map(|e| {
+            let e = module.lit(e);
+            let k_i = module.dot(k, e);
+            let c = module.mul(cos, e);
+            let kxe = module.cross(k, e);
+            let s = module.mul(sin, kxe);
+            let t = module.mul(one_minus_cos, k_i);
+            let t = module.mul(t, k);
+            let col = module.add(c, s);
+            module.add(col, t)
+        });
+        let [x, y, z] = columns;
+        let matrix = module.mat3(x, y, z);
+        Ok(vec![matrix])
+    }
+}
+
+/// Graph node to transform a vector by a matrix.
+///
+/// The node outputs the product `matrix * vector` of a `mat3x3<f32>` matrix
+/// and a `vec3<f32>` column vector, for example to rotate a vector with a
+/// matrix produced by an [`AxisAngleMatrixNode`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixTransformNode {
+    /// Input matrix and vector, and output transformed vector.
+    slots: [SlotDef; 3],
+}
+
+impl Default for MatrixTransformNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("matrix", Some(ValueType::Matrix(MatrixType::MAT3X3F))),
+                SlotDef::input("vector", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::output("transformed", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for MatrixTransformNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let matrix = inputs.next().unwrap();
+        let vector = inputs.next().unwrap();
+        let transformed = module.mul(matrix, vector);
+        Ok(vec![transformed])
+    }
+}
+
+/// Graph node to sample a piecewise-linear color gradient.
+///
+/// The gradient is defined by a list of color stops, as (key, color) pairs
+/// sorted by increasing key. The input ratio `t` is clamped to \[0:1\], then
+/// the output color is linearly interpolated between the two stops surrounding
+/// it. Ratios before the first stop or after the last one output the color of
+/// that stop. This is typically used for color-over-lifetime effects, with the
+/// ratio of the age of the particle to its lifetime as input.
+///
+/// Several stops can share the same key to produce a hard transition; the
+/// output is the color of the first of those stops at the key exactly, and the
+/// color of the last one after it. The node emits a chain of `mix()`
+/// expressions, one per pair of consecutive stops.
+///
+/// See also [`ColorRampNode`] for discrete color bands without interpolation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorGradientNode {
+    /// Input ratio and output color.
+    slots: [SlotDef; 2],
+    /// Color stops, as (key, color) pairs sorted by increasing key.
+    stops: Vec<(f32, Vec4)>,
+}
+
+impl Default for ColorGradientNode {
+    fn default() -> Self {
+        Self::new([(0., Vec4::new(0., 0., 0., 1.)), (1., Vec4::ONE)])
+    }
+}
+
+impl ColorGradientNode {
+    /// Create a new gradient from a list of (key, color) stops.
+    ///
+    /// The stops don't need to be sorted; they're sorted by increasing key,
+    /// preserving the order of stops sharing the same key.
+    pub fn new(stops: impl IntoIterator<Item = (f32, Vec4)>) -> Self {
+        let mut stops: Vec<_> = stops.into_iter().collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            slots: [
+                SlotDef::input("t", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+            stops,
+        }
+    }
+
+    /// Add a new color stop.
+    ///
+    /// The stop is inserted after any existing stop with the same key.
+    pub fn with_stop(mut self, key: f32, color: Vec4) -> Self {
+        let index = self.stops.partition_point(|&(k, _)| k <= key);
+        self.stops.insert(index, (key, color));
+        self
+    }
+
+    /// Get the color stops, as (key, color) pairs sorted by increasing key.
+    pub fn stops(&self) -> &[(f32, Vec4)] {
+        &self.stops
+    }
+
+    /// Sample the gradient on CPU.
+    ///
+    /// This returns the same color as the one produced on GPU by the
+    /// expression emitted by the node, or `None` if the gradient has no stop.
+    pub fn sample(&self, t: f32) -> Option<Vec4> {
+        let t = t.clamp(0., 1.);
+        let mut color = self.stops.first()?.1;
+        for pair in self.stops.windows(2) {
+            let ((key0, _), (key1, color1)) = (pair[0], pair[1]);
+            if key1 > key0 {
+                let factor = ((t - key0) / (key1 - key0)).clamp(0., 1.);
+                color = color.lerp(color1, factor);
+            } else if t > key1 {
+                color = color1;
+            }
+        }
+        Some(color)
+    }
+}
+
+/// Emit the expression sampling a piecewise-linear gradient at ratio `t`.
+///
+/// Each segment between two consecutive stops is blended in turn; the blend
+/// factor saturates to 1 once past the segment, so the last segment containing
+/// the ratio wins. Stops sharing the same key produce a hard transition.
+fn mix_color_stops(module: &mut Module, t: ExprHandle, stops: &[(f32, Vec4)]) -> ExprHandle {
+    let mut color = module.lit(stops[0].1);
+    for pair in stops.windows(2) {
+        let ((key0, _), (key1, color1)) = (pair[0], pair[1]);
+        let value1 = module.lit(color1);
+        let ratio1 = module.lit(key1);
+        if key1 > key0 {
+            let ratio0 = module.lit(key0);
+            let delta = module.lit(key1 - key0);
+            let offset = module.sub(t, ratio0);
+            let factor = module.div(offset, delta);
+            let factor = module.saturate(factor);
+            color = module.mix(color, value1, factor);
+        } else {
+            // Hard transition between two keys with the same ratio
+            let past = module.gt(t, ratio1);
+            color = module.select(color, value1, past);
+        }
+    }
+    color
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ColorGradientNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        if self.stops.is_empty() {
+            return Err(ExprError::GraphEvalError("no color stop".to_string()));
+        }
+        let t = inputs.into_iter().next().unwrap();
+
+        // A single stop is a constant color, independent of the ratio
+        if self.stops.len() == 1 {
+            return Ok(vec![module.lit(self.stops[0].1)]);
+        }
+
+        let t = module.saturate(t);
+        Ok(vec![mix_color_stops(module, t, &self.stops)])
+    }
+}
+
+/// Graph node to calculate the tangent of a path at a given parameter.
+///
+/// The path is a Catmull-Rom spline passing through a list of control points,
+/// in order. The input parameter `t` is clamped to \[0:1\], and spans the
+/// whole path, each segment between two consecutive control points covering
+/// an equal range of `t`. The output is the normalized derivative of the
+/// spline at `t`, that is the direction in which the path moves at that point.
+/// This is typically used to orient particles following the path.
+///
+/// The derivative at an interior control point is the central difference of
+/// its neighbors, so the tangent is continuous along the path. At the first
+/// and last control points, which only have one neighbor, the derivative is
+/// the one-sided difference with that neighbor instead.
+///
+/// The node emits one expression per segment, then selects the segment
+/// containing `t`. The result is undefined where the derivative vanishes, for
+/// example between coincident control points.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathTangentNode {
+    /// Input parameter and output tangent.
+    slots: [SlotDef; 2],
+    /// Control points the path passes through, in order.
+    control_points: Vec<Vec3>,
+}
+
+impl Default for PathTangentNode {
+    fn default() -> Self {
+        Self::new([Vec3::ZERO, Vec3::X])
+    }
+}
+
+impl PathTangentNode {
+    /// Create a new node for the path passing through the given control
+    /// points, in order.
+    ///
+    /// The path needs at least two control points to have a tangent;
+    /// evaluating the node with fewer points fails.
+    pub fn new(control_points: impl IntoIterator<Item = Vec3>) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("t", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("tangent", Some(ValueType::Vector(VectorType::VEC3F))),
+            ],
+            control_points: control_points.into_iter().collect(),
+        }
+    }
+
+    /// Get the control points of the path.
+    pub fn control_points(&self) -> &[Vec3] {
+        &self.control_points
+    }
+
+    /// Get the derivative of the spline at each control point.
+    fn point_derivatives(&self) -> Vec<Vec3> {
+        let points = &self.control_points;
+        let last = points.len() - 1;
+        (0..=last)
+            .map(|i| match i {
+                0 => points[1] - points[0],
+                i if i == last => points[last] - points[last - 1],
+                i => (points[i + 1] - points[i - 1]) * 0.5,
+            })
+            .collect()
+    }
+
+    /// Calculate the tangent on CPU.
+    ///
+    /// This returns the same tangent as the one produced on GPU by the
+    /// expression emitted by the node, or `None` if the path has fewer than
+    /// two control points.
+    pub fn tangent(&self, t: f32) -> Option<Vec3> {
+        if self.control_points.len() < 2 {
+            return None;
+        }
+        let derivatives = self.point_derivatives();
+        let segment_count = self.control_points.len() - 1;
+        let s = t.clamp(0., 1.) * segment_count as f32;
+        let i = (s.floor() as usize).min(segment_count - 1);
+        let u = (s - i as f32).clamp(0., 1.);
+        let u2 = u * u;
+        let p = self.control_points[i] - self.control_points[i + 1];
+        let w_m0 = 3f32.mul_add(u2, (-4f32).mul_add(u, 1.));
+        let w_m1 = 3f32.mul_add(u2, -2. * u);
+        let tangent = p * (6. * (u2 - u)) + derivatives[i] * w_m0 + derivatives[i + 1] * w_m1;
+        Some(tangent.normalize())
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PathTangentNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        if self.control_points.len() < 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected at least 2 control points, got {}",
+                self.control_points.len()
+            )));
+        }
+        let t = inputs.into_iter().next().unwrap();
+
+        // Parameter along the path, in [0:segment_count]
+        let segment_count = self.control_points.len() - 1;
+        let t = module.saturate(t);
+        let count = module.lit(segment_count as f32);
+        let s = module.mul(t, count);
+
+        // Derivative of the cubic Hermite segment between points i and i+1, at
+        // the local parameter u in [0:1]:
+        // 6(u^2 - u)(p_i - p_i+1) + (3u^2 - 4u + 1)m_i + (3u^2 - 2u)m_i+1
+        let derivatives = self.point_derivatives();
+        let mut tangent = None;
+        for (i, pair) in self.control_points.windows(2).enumerate() {
+            let index = module.lit(i as f32);
+            let u = module.sub(s, index);
+            let u = module.saturate(u);
+            let u2 = module.mul(u, u);
+            let six = module.lit(6.);
+            let three = module.lit(3.);
+            let four = module.lit(4.);
+            let two = module.lit(2.);
+            let one = module.lit(1.);
+
+            let w_p = module.sub(u2, u);
+            let w_p = module.mul(six, w_p);
+            let u2_3 = module.mul(three, u2);
+            let u_4 = module.mul(four, u);
+            let w_m0 = module.sub(u2_3, u_4);
+            let w_m0 = module.add(w_m0, one);
+            let u_2 = module.mul(two, u);
+            let w_m1 = module.sub(u2_3, u_2);
+
+            let p0 = module.lit(pair[0]);
+            let p1 = module.lit(pair[1]);
+            let p = module.sub(p0, p1);
+            let m0 = module.lit(derivatives[i]);
+            let m1 = module.lit(derivatives[i + 1]);
+            let p = module.mul(w_p, p);
+            let m0 = module.mul(w_m0, m0);
+            let m1 = module.mul(w_m1, m1);
+            let segment = module.add(p, m0);
+            let segment = module.add(segment, m1);
+
+            // Use this segment once past its start
+            tangent = Some(match tangent {
+                None => segment,
+                Some(tangent) => {
+                    let past = module.gt(s, index);
+                    module.select(tangent, segment, past)
+                }
+            });
+        }
+        let tangent = module.normalize(tangent.unwrap());
+        Ok(vec![tangent])
+    }
+}
+
+/// Graph node to map a scalar value to a color from a set of discrete bands.
+///
+/// Unlike a smooth [`Gradient`], the color ramp doesn't interpolate between its
+/// colors. Instead, the input value is snapped to the band containing it, and
+/// the color of that band is output as is. This is useful for cel-shaded
+/// ("toon") effects.
+///
+/// The ramp starts with a base color, used for all values below the first band.
+/// Each band added with [`with_band()`] starts at a given threshold and extends
+/// up to the threshold of the next band, if any:
+///
+/// ```txt
+/// color = base_color
+/// if value >= threshold_0 { color = color_0 }
+/// if value >= threshold_1 { color = color_1 }
+/// ...
+/// ```
+///
+/// The node emits a chain of `select()` expressions, one per band.
+///
+/// [`Gradient`]: crate::Gradient
+/// [`with_band()`]: ColorRampNode::with_band
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorRampNode {
+    /// Input value and output color.
+    slots: [SlotDef; 2],
+    /// Color for values below the first band.
+    base_color: Vec4,
+    /// Bands, as (threshold, color) pairs sorted by increasing threshold.
+    bands: Vec<(f32, Vec4)>,
+}
+
+impl Default for ColorRampNode {
+    fn default() -> Self {
+        Self::new(Vec4::ONE)
+    }
+}
+
+impl ColorRampNode {
+    /// Create a new color ramp with a single base color and no band.
+    pub fn new(base_color: Vec4) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("value", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+            base_color,
+            bands: vec![],
+        }
+    }
+
+    /// Add a new band starting at the given threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the threshold is not greater than the threshold of the
+    /// previous band, if any.
+    pub fn with_band(mut self, threshold: f32, color: Vec4) -> Self {
+        if let Some(&(last, _)) = self.bands.last() {
+            assert!(
+                threshold > last,
+                "ColorRampNode band thresholds must be increasing"
+            );
+        }
+        self.bands.push((threshold, color));
+        self
+    }
+
+    /// Get the base color, used for values below the first band.
+    pub fn base_color(&self) -> Vec4 {
+        self.base_color
+    }
+
+    /// Get the bands, as (threshold, color) pairs sorted by increasing
+    /// threshold.
+    pub fn bands(&self) -> &[(f32, Vec4)] {
+        &self.bands
+    }
+
+    /// Sample the color ramp on CPU.
+    ///
+    /// This returns the same color as the one produced on GPU by the
+    /// expression emitted by the node.
+    pub fn sample(&self, value: f32) -> Vec4 {
+        self.bands
+            .iter()
+            .fold(self.base_color, |color, &(threshold, band_color)| {
+                if value >= threshold {
+                    band_color
+                } else {
+                    color
+                }
+            })
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ColorRampNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let value = inputs.into_iter().next().unwrap();
+
+        let mut color = module.lit(self.base_color);
+        for &(threshold, band_color) in &self.bands {
+            let threshold = module.lit(threshold);
+            let band_color = module.lit(band_color);
+            let in_band = module.ge(value, threshold);
+            color = module.select(color, band_color, in_band);
+        }
+        Ok(vec![color])
+    }
+}
+
+/// Graph node to randomly pick one value among several weighted options.
+///
+/// Each option is a (weight, value) pair, and is selected with a probability
+/// proportional to its weight. The node takes a uniformly distributed random
+/// value in \[0:1\[ as input, typically a [`BuiltInOperator::Rand`], and
+/// compares it against the normalized cumulative weights of the options:
+///
+/// ```txt
+/// value = value_0
+/// if random >= (w_0) / total { value = value_1 }
+/// if random >= (w_0 + w_1) / total { value = value_2 }
+/// ...
+/// ```
+///
+/// The node emits a chain of `select()` expressions, one per option after the
+/// first one. All options must have the same value type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightedChoiceNode {
+    /// Input random value and output value.
+    slots: [SlotDef; 2],
+    /// Options, as (weight, value) pairs.
+    options: Vec<(f32, Value)>,
+}
+
+impl WeightedChoiceNode {
+    /// Create a new node with a single option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weight is negative or not finite.
+    pub fn new(weight: f32, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        Self {
+            slots: [
+                SlotDef::input("random", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("value", Some(value.value_type())),
+            ],
+            options: vec![],
+        }
+        .with_option(weight, value)
+    }
+
+    /// Add a new option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weight is negative or not finite, or if the value type
+    /// differs from the one of the other options.
+    pub fn with_option(mut self, weight: f32, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        assert!(
+            weight.is_finite() && weight >= 0.,
+            "WeightedChoiceNode weights must be positive and finite"
+        );
+        assert_eq!(
+            Some(value.value_type()),
+            self.slots[1].value_type(),
+            "WeightedChoiceNode options must all have the same value type"
+        );
+        self.options.push((weight, value));
+        self
+    }
+
+    /// Get the options, as (weight, value) pairs.
+    pub fn options(&self) -> &[(f32, Value)] {
+        &self.options
+    }
+
+    /// Get the sum of the weights of all options.
+    pub fn total_weight(&self) -> f32 {
+        self.options.iter().map(|(weight, _)| weight).sum()
+    }
+
+    /// Pick an option on CPU.
+    ///
+    /// This returns the same value as the one produced on GPU by the expression
+    /// emitted by the node for the given random value.
+    pub fn choose(&self, random: f32) -> Value {
+        let total = self.total_weight();
+        let mut cumul = 0.;
+        let mut choice = self.options[0].1;
+        for (&(prev_weight, _), &(_, value)) in self.options.iter().zip(&self.options[1..]) {
+            cumul += prev_weight;
+            if random >= cumul / total {
+                choice = value;
+            }
+        }
+        choice
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for WeightedChoiceNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let random = inputs.into_iter().next().unwrap();
+
+        let total = self.total_weight();
+        if total <= 0. {
+            return Err(ExprError::GraphEvalError("zero total weight".to_string()));
+        }
+
+        let mut value = module.lit(self.options[0].1);
+        let mut cumul = 0.;
+        for (&(prev_weight, _), &(_, option)) in self.options.iter().zip(&self.options[1..]) {
+            cumul += prev_weight;
+            let threshold = module.lit(cumul / total);
+            let option = module.lit(option);
+            let is_picked = module.ge(random, threshold);
+            value = module.select(value, option, is_picked);
+        }
+        Ok(vec![value])
+    }
+}
+
+/// Waveform produced by an [`OscillatorNode`].
+///
+/// All waveforms have a period of 1 and an amplitude of 1, and start at zero
+/// going up, like a sine wave.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WaveShape {
+    /// Sine wave. This is the default.
+    #[default]
+    Sine,
+    /// Square wave, `1` on the first half period and `-1` on the second one.
+    Square,
+    /// Triangle wave, rising linearly from `-1` to `1` then falling back.
+    Triangle,
+    /// Sawtooth wave, rising linearly from `-1` to `1` then jumping back.
+    Sawtooth,
+}
+
+/// Graph node producing a periodic waveform over time.
+///
+/// The node outputs the waveform selected by its [`WaveShape`], evaluated at
+/// the given time:
+///
+/// ```txt
+/// value = amplitude * wave(time * frequency + phase)
+/// ```
+///
+/// where the `phase` is expressed in fraction of a period. By default the time
+/// is the effect time [`BuiltInOperator::Time`]; use [`with_time_input()`] to
+/// instead read it from an input slot, for example to oscillate along the
+/// particle age.
+///
+/// [`with_time_input()`]: OscillatorNode::with_time_input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OscillatorNode {
+    /// Optional input time and output value.
+    slots: [SlotDef; 2],
+    /// Shape of the waveform.
+    shape: WaveShape,
+    /// Frequency, in periods per unit of time.
+    frequency: f32,
+    /// Amplitude.
+    amplitude: f32,
+    /// Phase offset, in fraction of a period.
+    phase: f32,
+    /// Read the time from the input slot instead of the built-in effect time.
+    time_input: bool,
+}
+
+impl Default for OscillatorNode {
+    fn default() -> Self {
+        Self::new(WaveShape::default())
+    }
+}
+
+impl OscillatorNode {
+    /// Create a new oscillator of the given shape, with a frequency and an
+    /// amplitude of 1, and no phase offset.
+    pub fn new(shape: WaveShape) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("time", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("value", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+            shape,
+            frequency: 1.,
+            amplitude: 1.,
+            phase: 0.,
+            time_input: false,
+        }
+    }
+
+    /// Set the frequency, in periods per unit of time.
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Set the amplitude.
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Set the phase offset, in fraction of a period.
+    pub fn with_phase(mut self, phase: f32) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Read the time from a `time` input slot instead of the built-in effect
+    /// time.
+    pub fn with_time_input(mut self) -> Self {
+        self.time_input = true;
+        self
+    }
+
+    /// Get the shape of the waveform.
+    pub fn shape(&self) -> WaveShape {
+        self.shape
+    }
+
+    /// Get the frequency, in periods per unit of time.
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Get the amplitude.
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
+    }
+
+    /// Get the phase offset, in fraction of a period.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Does the node read the time from its input slot?
+    pub fn has_time_input(&self) -> bool {
+        self.time_input
+    }
+
+    /// Sample the waveform on CPU.
+    ///
+    /// This returns the same value as the one produced on GPU by the
+    /// expression emitted by the node for the given time.
+    pub fn sample(&self, time: f32) -> f32 {
+        let fract = |x: f32| x - x.floor();
+        let x = time.mul_add(self.frequency, self.phase);
+        let wave = match self.shape {
+            WaveShape::Sine => (x * std::f32::consts::TAU).sin(),
+            WaveShape::Square => {
+                if fract(x) >= 0.5 {
+                    -1.
+                } else {
+                    1.
+                }
+            }
+            WaveShape::Triangle => 4.0f32.mul_add(-(fract(x + 0.25) - 0.5).abs(), 1.),
+            WaveShape::Sawtooth => 2.0f32.mul_add(fract(x + 0.5), -1.),
+        };
+        self.amplitude * wave
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for OscillatorNode {
+    fn slots(&self) -> &[SlotDef] {
+        if self.time_input {
+            &self.slots
+        } else {
+            &self.slots[1..]
+        }
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        let expected = if self.time_input { 1 } else { 0 };
+        if inputs.len() != expected {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected {} inputs, got {}",
+                expected,
+                inputs.len()
+            )));
+        }
+        let time = inputs
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| module.builtin(BuiltInOperator::Time));
+
+        let frequency = module.lit(self.frequency);
+        let phase = module.lit(self.phase);
+        let x = module.mul(time, frequency);
+        let x = module.add(x, phase);
+        let wave = match self.shape {
+            WaveShape::Sine => {
+                let tau = module.lit(std::f32::consts::TAU);
+                let x = module.mul(x, tau);
+                module.sin(x)
+            }
+            WaveShape::Square => {
+                // 1 - 2 * step(0.5, fract(x))
+                let half = module.lit(0.5);
+                let t = module.fract(x);
+                let t = module.step(half, t);
+                let two = module.lit(2.);
+                let t = module.mul(two, t);
+                let one = module.lit(1.);
+                module.sub(one, t)
+            }
+            WaveShape::Triangle => {
+                // 1 - 4 * abs(fract(x + 0.25) - 0.5)
+                let quarter = module.lit(0.25);
+                let t = module.add(x, quarter);
+                let t = module.fract(t);
+                let half = module.lit(0.5);
+                let t = module.sub(t, half);
+                let t = module.abs(t);
+                let four = module.lit(4.);
+                let t = module.mul(four, t);
+                let one = module.lit(1.);
+                module.sub(one, t)
+            }
+            WaveShape::Sawtooth => {
+                // 2 * fract(x + 0.5) - 1
+                let half = module.lit(0.5);
+                let t = module.add(x, half);
+                let t = module.fract(t);
+                let two = module.lit(2.);
+                let t = module.mul(two, t);
+                let one = module.lit(1.);
+                module.sub(t, one)
+            }
+        };
+        let amplitude = module.lit(self.amplitude);
+        Ok(vec![module.mul(amplitude, wave)])
+    }
+}
+
+/// Single sine layer of a [`WobbleNode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WobbleLayer {
+    /// Frequency, in periods per unit of time.
+    pub frequency: f32,
+    /// Amplitude.
+    pub amplitude: f32,
+    /// Phase offset, in fraction of a period.
+    pub phase: f32,
+}
+
+impl WobbleLayer {
+    /// Create a new sine layer.
+    pub fn new(frequency: f32, amplitude: f32, phase: f32) -> Self {
+        Self {
+            frequency,
+            amplitude,
+            phase,
+        }
+    }
+}
+
+/// Graph node producing a smooth wobble from the sum of several sine layers.
+///
+/// The node outputs the sum of its [`WobbleLayer`]s evaluated at the given
+/// time:
+///
+/// ```txt
+/// value = sum(amplitude * sin((time * frequency + phase) * tau))
+/// ```
+///
+/// where the `phase` is expressed in fraction of a period. Layers of unrelated
+/// frequencies produce an organic motion which doesn't visibly repeat. The
+/// number of layers is bounded by [`WobbleNode::MAX_LAYERS`].
+///
+/// By default the time is the effect time [`BuiltInOperator::Time`]; use
+/// [`with_time_input()`] to instead read it from an input slot, for example to
+/// wobble along the particle age so that each particle wobbles differently.
+///
+/// With [`with_per_axis()`], the node outputs a `Vec3` instead of a scalar,
+/// offsetting the phase of each axis by a third of a period so that the axes
+/// wobble independently.
+///
+/// [`with_time_input()`]: WobbleNode::with_time_input
+/// [`with_per_axis()`]: WobbleNode::with_per_axis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WobbleNode {
+    /// Optional input time and output value.
+    slots: [SlotDef; 2],
+    /// Sine layers summed together.
+    layers: Vec<WobbleLayer>,
+    /// Read the time from the input slot instead of the built-in effect time.
+    time_input: bool,
+    /// Output a `Vec3` with one wobble per axis instead of a scalar.
+    per_axis: bool,
+}
+
+impl Default for WobbleNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WobbleNode {
+    /// Maximum number of sine layers of a node.
+    pub const MAX_LAYERS: usize = 8;
+
+    /// Create a new wobble node without any layer, which outputs zero.
+    pub fn new() -> Self {
+        Self {
+            slots: Self::make_slots(false),
+            layers: vec![],
+            time_input: false,
+            per_axis: false,
+        }
+    }
+
+    fn make_slots(per_axis: bool) -> [SlotDef; 2] {
+        let value_type = if per_axis {
+            ValueType::Vector(VectorType::VEC3F)
+        } else {
+            ValueType::Scalar(ScalarType::Float)
+        };
+        [
+            SlotDef::input("time", Some(ValueType::Scalar(ScalarType::Float))),
+            SlotDef::output("value", Some(value_type)),
+        ]
+    }
+
+    /// Add a sine layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node already has [`WobbleNode::MAX_LAYERS`] layers.
+    pub fn with_layer(mut self, frequency: f32, amplitude: f32, phase: f32) -> Self {
+        assert!(
+            self.layers.len() < Self::MAX_LAYERS,
+            "WobbleNode supports at most {} layers.",
+            Self::MAX_LAYERS
+        );
+        self.layers
+            .push(WobbleLayer::new(frequency, amplitude, phase));
+        self
+    }
+
+    /// Read the time from a `time` input slot instead of the built-in effect
+    /// time.
+    pub fn with_time_input(mut self) -> Self {
+        self.time_input = true;
+        self
+    }
+
+    /// Output a `Vec3` with an independent wobble per axis instead of a
+    /// scalar.
+    pub fn with_per_axis(mut self) -> Self {
+        self.per_axis = true;
+        self.slots = Self::make_slots(true);
+        self
+    }
+
+    /// Get the sine layers.
+    pub fn layers(&self) -> &[WobbleLayer] {
+        &self.layers
+    }
+
+    /// Does the node read the time from its input slot?
+    pub fn has_time_input(&self) -> bool {
+        self.time_input
+    }
+
+    /// Does the node output one wobble per axis?
+    pub fn is_per_axis(&self) -> bool {
+        self.per_axis
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for WobbleNode {
+    fn slots(&self) -> &[SlotDef] {
+        if self.time_input {
+            &self.slots
+        } else {
+            &self.slots[1..]
+        }
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        let expected = if self.time_input { 1 } else { 0 };
+        if inputs.len() != expected {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected {} inputs, got {}",
+                expected,
+                inputs.len()
+            )));
+        }
+        let time = inputs
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| module.builtin(BuiltInOperator::Time));
+
+        let mut sum = None;
+        for layer in &self.layers {
+            let frequency = module.lit(layer.frequency);
+            let phase = if self.per_axis {
+                let p = layer.phase;
+                module.lit(Vec3::new(p, p + 1. / 3., p + 2. / 3.))
+            } else {
+                module.lit(layer.phase)
+            };
+            let x = module.mul(time, frequency);
+            let x = module.add(x, phase);
+            let tau = module.lit(std::f32::consts::TAU);
+            let x = module.mul(x, tau);
+            let wave = module.sin(x);
+            let amplitude = module.lit(layer.amplitude);
+            let wave = module.mul(amplitude, wave);
+            sum = Some(match sum {
+                Some(sum) => module.add(sum, wave),
+                None => wave,
+            });
+        }
+        let sum = sum.unwrap_or_else(|| {
+            if self.per_axis {
+                module.lit(Vec3::ZERO)
+            } else {
+                module.lit(0.)
+            }
+        });
+        Ok(vec![sum])
+    }
+}
+
+/// Blend mode of a [`ColorBlendNode`].
+///
+/// Each mode combines a base color `a` and a blend color `b`, independently for
+/// each channel, with channel values generally in \[0:1\].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Multiply the colors: `a * b`. This is the default.
+    #[default]
+    Multiply,
+    /// Multiply the inverted colors, and invert the result:
+    /// `1 - (1 - a) * (1 - b)`.
+    Screen,
+    /// Multiply dark base channels and screen light ones, doubling the
+    /// result: `2 * a * b` if `a < 0.5`, or `1 - 2 * (1 - a) * (1 - b)`
+    /// otherwise.
+    Overlay,
+    /// Add the colors: `a + b`.
+    Add,
+}
+
+/// Graph node to combine two colors with a [`BlendMode`].
+///
+/// The node takes a base color `a` and a blend color `b`, and outputs the
+/// color resulting from blending them. The blend formula is applied to all
+/// four RGBA channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorBlendNode {
+    /// Input colors and output color.
+    slots: [SlotDef; 3],
+    /// Blend mode.
+    mode: BlendMode,
+}
+
+impl Default for ColorBlendNode {
+    fn default() -> Self {
+        Self::new(BlendMode::default())
+    }
+}
+
+impl ColorBlendNode {
+    /// Create a new node blending colors with the given mode.
+    pub fn new(mode: BlendMode) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("a", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::input("b", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+            mode,
+        }
+    }
+
+    /// Get the blend mode.
+    pub fn mode(&self) -> BlendMode {
+        self.mode
+    }
+
+    /// Set the blend mode.
+    pub fn set_mode(&mut self, mode: BlendMode) {
+        self.mode = mode;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ColorBlendNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let a = inputs.next().unwrap();
+        let b = inputs.next().unwrap();
+
+        let color = match self.mode {
+            BlendMode::Multiply => module.mul(a, b),
+            BlendMode::Screen => {
+                let one = module.lit(Vec4::ONE);
+                let inv_a = module.sub(one, a);
+                let inv_b = module.sub(one, b);
+                let inv = module.mul(inv_a, inv_b);
+                module.sub(one, inv)
+            }
+            BlendMode::Overlay => {
+                let one = module.lit(Vec4::ONE);
+                let two = module.lit(Vec4::splat(2.));
+                let half = module.lit(Vec4::splat(0.5));
+                // Dark channels: 2 * a * b
+                let ab = module.mul(a, b);
+                let dark = module.mul(two, ab);
+                // Light channels: 1 - 2 * (1 - a) * (1 - b)
+                let inv_a = module.sub(one, a);
+                let inv_b = module.sub(one, b);
+                let inv = module.mul(inv_a, inv_b);
+                let inv = module.mul(two, inv);
+                let light = module.sub(one, inv);
+                let is_dark = module.lt(a, half);
+                module.select(light, dark, is_dark)
+            }
+            BlendMode::Add => module.add(a, b),
+        };
+        Ok(vec![color])
+    }
+}
+
+/// Graph node to convert a color from linear to sRGB space.
+///
+/// The RGB channels of the input color are converted with the piecewise sRGB
+/// transfer function, and the alpha channel is passed through unchanged. Use
+/// this node to output a color computed in linear space to a destination
+/// expecting sRGB values.
+///
+/// See also [`SrgbToLinearNode`] for the reverse conversion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearToSrgbNode {
+    /// Input and output colors.
+    slots: [SlotDef; 2],
+}
+
+impl Default for LinearToSrgbNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("color", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::output("srgb", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for LinearToSrgbNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let color = inputs.into_iter().next().unwrap();
+        Ok(vec![module.linear_to_srgb(color)])
+    }
+}
+
+/// Graph node to convert a color from sRGB to linear space.
+///
+/// The RGB channels of the input color are converted with the piecewise sRGB
+/// transfer function, and the alpha channel is passed through unchanged. Use
+/// this node on hand-specified sRGB colors before mixing them with other
+/// colors or doing any other arithmetic on them.
+///
+/// See also [`LinearToSrgbNode`] for the reverse conversion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SrgbToLinearNode {
+    /// Input and output colors.
+    slots: [SlotDef; 2],
+}
+
+impl Default for SrgbToLinearNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("srgb", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SrgbToLinearNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let srgb = inputs.into_iter().next().unwrap();
+        Ok(vec![module.srgb_to_linear(srgb)])
+    }
+}
+
+/// Shape used by a [`ParticleNormalNode`] to derive the normal of a particle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParticleNormalMode {
+    /// The particle is shaded as a flat quad, and the normal is the Z axis of
+    /// its billboard, which faces the camera for camera-facing particles. This
+    /// is the default.
+    #[default]
+    Billboard,
+    /// The particle is shaded as a sphere (impostor) inscribed in its quad.
+    /// The normal is the one of the sphere surface seen through the fragment,
+    /// derived from the quad UV coordinates of the fragment.
+    SphericalImpostor,
+}
+
+/// Graph node to blend a color toward a fog color with the view depth.
+///
+/// The node applies an exponential fog to the input `color`, based on the
+/// view-space depth of the particle (see [`BuiltInOperator::ViewDepth`]). The
+/// fog factor grows from `0` at the camera toward `1` at infinity, and the
+/// output color is linearly interpolated from the input color to the fog color
+/// by that factor:
+///
+/// ```txt
+/// factor = 1 - exp(-density * depth)
+/// color = mix(color, fog_color, factor)
+/// ```
+///
+/// The interpolation also applies to the alpha component, so the alpha of
+/// `fog_color` is the alpha of particles fully inside the fog. Because it reads
+/// the view depth, this node can only be used in the render context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FogNode {
+    /// Color, fog color, and fog density inputs, and fogged color output.
+    slots: [SlotDef; 4],
+}
+
+impl Default for FogNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("color", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::input("fog_color", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::input("density", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for FogNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 3 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 3 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let color = inputs.next().unwrap();
+        let fog_color = inputs.next().unwrap();
+        let density = inputs.next().unwrap();
+
+        let depth = module.builtin(BuiltInOperator::ViewDepth);
+        let minus_one = module.lit(-1.);
+        let one = module.lit(1.);
+        let density = module.mul(minus_one, density);
+        let exponent = module.mul(density, depth);
+        let transmittance = module.exp(exponent);
+        let factor = module.sub(one, transmittance);
+        let color = module.mix(color, fog_color, factor);
+        Ok(vec![color])
+    }
+}
+
+/// Graph node to approximate the self-shadowing of a volume of particles.
+///
+/// The node darkens the input `color` by the amount of volume between the
+/// particle and a directional light, as an approximation of the light absorbed
+/// by the other particles of the effect. The volume is the bounding sphere of
+/// the effect bounds given to [`SelfShadowNode::new()`], in the same space as
+/// the `position` input. The `light_dir` input is the direction in which the
+/// light travels, and doesn't need to be normalized.
+///
+/// The optical depth is the distance from the particle to the side of the
+/// volume facing the light, normalized to \[0:1\] by the diameter of the
+/// volume, and the transmittance follows the Beer-Lambert law:
+///
+/// ```txt
+/// h = dot(position - center, -normalize(light_dir))
+/// depth = saturate((radius - h) / (2 * radius))
+/// color.rgb *= exp(-density * depth)
+/// ```
+///
+/// so `density` is the optical depth of the whole volume. The alpha component
+/// of the color is left unchanged. This is a cheap approximation which
+/// considers the volume uniformly filled with particles, and doesn't ray-march
+/// the actual particle distribution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfShadowNode {
+    /// Color, position, light direction, and density inputs, and shadowed color
+    /// output.
+    slots: [SlotDef; 5],
+    /// Center of the volume.
+    center: Vec3,
+    /// Radius of the volume.
+    radius: f32,
+}
+
+impl Default for SelfShadowNode {
+    fn default() -> Self {
+        Self::new(Vec3::splat(-0.5), Vec3::splat(0.5))
+    }
+}
+
+impl SelfShadowNode {
+    /// Create a new node for a volume bounded by the given axis-aligned bounds.
+    ///
+    /// The bounds are typically the same as the [`PositionBounds`] of the
+    /// effect, if any.
+    ///
+    /// [`PositionBounds`]: crate::PositionBounds
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self {
+            slots: [
+                SlotDef::input("color", Some(ValueType::Vector(VectorType::VEC4F))),
+                SlotDef::input("position", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("light_dir", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("density", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+            center: (min + max) * 0.5,
+            radius: (max - min).length() * 0.5,
+        }
+    }
+
+    /// Get the center of the bounding sphere of the volume.
+    pub fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    /// Get the radius of the bounding sphere of the volume.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Evaluate on the CPU the fraction of light reaching a particle.
+    ///
+    /// This returns the factor `exp(-density * depth)` applied to the color
+    /// by the node, or `1` if the light direction is zero.
+    pub fn transmittance(&self, position: Vec3, light_dir: Vec3, density: f32) -> f32 {
+        let depth = self.optical_depth(position, light_dir);
+        (-density * depth).exp()
+    }
+
+    fn optical_depth(&self, position: Vec3, light_dir: Vec3) -> f32 {
+        let to_light = -light_dir.normalize_or_zero();
+        if to_light == Vec3::ZERO {
+            return 0.;
+        }
+        let h = (position - self.center).dot(to_light);
+        ((self.radius - h) * self.inv_diameter()).clamp(0., 1.)
+    }
+
+    fn inv_diameter(&self) -> f32 {
+        if self.radius > 0. {
+            0.5 / self.radius
+        } else {
+            0.
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SelfShadowNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 4 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 4 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let color = inputs.next().unwrap();
+        let position = inputs.next().unwrap();
+        let light_dir = inputs.next().unwrap();
+        let density = inputs.next().unwrap();
+
+        let minus_one = module.lit(-1.);
+        let light_dir = module.normalize(light_dir);
+        let to_light = module.mul(minus_one, light_dir);
+        let center = module.lit(self.center);
+        let offset = module.sub(position, center);
+        let h = module.dot(offset, to_light);
+        let radius = module.lit(self.radius);
+        let depth = module.sub(radius, h);
+        let inv_diameter = module.lit(self.inv_diameter());
+        let depth = module.mul(depth, inv_diameter);
+        let depth = module.saturate(depth);
+        let density = module.mul(minus_one, density);
+        let exponent = module.mul(density, depth);
+        let transmittance = module.exp(exponent);
+        let rgb = module.swizzle(color, "xyz")?;
+        let rgb = module.mul(rgb, transmittance);
+        let alpha = module.w(color);
+        let color = module.vec4(rgb, alpha);
+        Ok(vec![color])
+    }
+}
+
+/// Graph node to compute the normal of a particle, for lit particles.
+///
+/// The node takes the orientation of the particle quad as its three axes
+/// `axis_x`, `axis_y`, and `axis_z`, and outputs the unit `normal` of the
+/// particle with the [`ParticleNormalMode`] of the node. The normal is in the
+/// same space as the axes, so is a world-space normal for axes in world space,
+/// like the [`Attribute::AXIS_X`], [`Attribute::AXIS_Y`], and
+/// [`Attribute::AXIS_Z`] of a particle simulated in global space. The axis
+/// inputs default to the X, Y, and Z unit vectors if unlinked.
+///
+/// In [`ParticleNormalMode::SphericalImpostor`] mode, the `uv` input is the
+/// coordinates of the fragment inside the quad, in \[0:1\] with V pointing
+/// down, and not the UV into a flipbook sprite sheet. The normal is then:
+///
+/// ```txt
+/// p = vec2(uv.x * 2 - 1, 1 - uv.y * 2)
+/// normal = normalize(axis_x * p.x + axis_y * p.y + axis_z * sqrt(max(1 - dot(p, p), 0)))
+/// ```
+///
+/// In [`ParticleNormalMode::Billboard`] mode, the `uv` input is ignored, and the
+/// normal is `normalize(axis_z)`.
+///
+/// This node is intended for the render context, where the normal can be used
+/// to evaluate some lighting of the fragments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticleNormalNode {
+    /// Particle axes and UV inputs, and normal output.
+    slots: [SlotDef; 5],
+    /// Shape of the particle.
+    mode: ParticleNormalMode,
+}
+
+impl Default for ParticleNormalNode {
+    fn default() -> Self {
+        Self::new(ParticleNormalMode::default())
+    }
+}
+
+impl ParticleNormalNode {
+    /// Create a new node computing the particle normal with the given mode.
+    pub fn new(mode: ParticleNormalMode) -> Self {
+        let vec3 = Some(ValueType::Vector(VectorType::VEC3F));
+        Self {
+            slots: [
+                SlotDef::input_with_default("axis_x", vec3, Vec3::X.into()),
+                SlotDef::input_with_default("axis_y", vec3, Vec3::Y.into()),
+                SlotDef::input_with_default("axis_z", vec3, Vec3::Z.into()),
+                SlotDef::input_with_default(
+                    "uv",
+                    Some(ValueType::Vector(VectorType::VEC2F)),
+                    Vec2::splat(0.5).into(),
+                ),
+                SlotDef::output("normal", vec3),
+            ],
+            mode,
+        }
+    }
+
+    /// Get the normal mode.
+    pub fn mode(&self) -> ParticleNormalMode {
+        self.mode
+    }
+
+    /// Set the normal mode.
+    pub fn set_mode(&mut self, mode: ParticleNormalMode) {
+        self.mode = mode;
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for ParticleNormalNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 4 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 4 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let axis_x = inputs.next().unwrap();
+        let axis_y = inputs.next().unwrap();
+        let axis_z = inputs.next().unwrap();
+        let uv = inputs.next().unwrap();
+
+        let normal = match self.mode {
+            ParticleNormalMode::Billboard => module.normalize(axis_z),
+            ParticleNormalMode::SphericalImpostor => {
+                // Position on the unit disc inscribed in the quad, Y up
+                let scale = module.lit(Vec2::new(2., -2.));
+                let offset = module.lit(Vec2::new(-1., 1.));
+                let p = module.mul(uv, scale);
+                let p = module.add(p, offset);
+                // Height of the unit sphere above that position, zero outside
+                let one = module.lit(1.);
+                let zero = module.lit(0.);
+                let d2 = module.dot(p, p);
+                let h2 = module.sub(one, d2);
+                let h2 = module.max(h2, zero);
+                let h = module.sqrt(h2);
+                let px = module.x(p);
+                let py = module.y(p);
+                let nx = module.mul(axis_x, px);
+                let ny = module.mul(axis_y, py);
+                let nz = module.mul(axis_z, h);
+                let n = module.add(nx, ny);
+                let n = module.add(n, nz);
+                module.normalize(n)
+            }
+        };
+        Ok(vec![normal])
+    }
+}
+
+/// Graph node to distort screen-space UVs from a normal map, for refraction-like
+/// effects such as heat haze or water.
+///
+/// The node samples the normal map texture at `normal_uv`, decodes the normal
+/// from the \[0:1\] texture range to \[-1:1\], and offsets `screen_uv` by
+/// the XY components of that normal scaled by `strength`:
+///
+/// ```txt
+/// uv = screen_uv + (sample(normal_map, normal_uv).xy * 2 - 1) * strength
+/// ```
+///
+/// The output UV is typically used to sample a background texture, like a copy
+/// of the scene color. The `normal_map` input is the index of the texture slot
+/// of the normal map in the [`Module`], and must be a `u32` literal. Because it samples a texture, this node
+/// can only be used in the render context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistortUvNode {
+    /// Normal map, sampling coordinates, screen UV, and strength inputs, and
+    /// distorted UV output.
+    slots: [SlotDef; 5],
+}
+
+impl Default for DistortUvNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("normal_map", Some(ValueType::Scalar(ScalarType::Uint))),
+                SlotDef::input("normal_uv", Some(ValueType::Vector(VectorType::VEC2F))),
+                SlotDef::input("screen_uv", Some(ValueType::Vector(VectorType::VEC2F))),
+                SlotDef::input("strength", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("uv", Some(ValueType::Vector(VectorType::VEC2F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for DistortUvNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 4 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 4 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let normal_map = inputs.next().unwrap();
+        let normal_uv = inputs.next().unwrap();
+        let screen_uv = inputs.next().unwrap();
+        let strength = inputs.next().unwrap();
+
+        let normal = module.texture_sample(normal_map, normal_uv);
+        let x = module.x(normal);
+        let y = module.y(normal);
+        let xy = module.vec2(x, y);
+        let two = module.lit(2.);
+        let one = module.lit(1.);
+        let xy = module.mul(xy, two);
+        let xy = module.sub(xy, one);
+        let offset = module.mul(xy, strength);
+        let uv = module.add(screen_uv, offset);
+        Ok(vec![uv])
+    }
+}
+
+/// Graph node to compute a 3D checkerboard pattern from a position.
+///
+/// The space is divided into cubic cells of size `1 / scale`, and the output
+/// alternates between `0` and `1` from one cell to the next along each axis:
+///
+/// ```txt
+/// checker = abs((floor(p.x * scale) + floor(p.y * scale) + floor(p.z * scale)) % 2)
+/// ```
+///
+/// The `abs()` ensures the output is `1` and not `-1` in the odd cells with
+/// negative coordinates, because the WGSL `%` operator keeps the sign of its
+/// left operand. To get a 2D checkerboard, set one of the components of the
+/// position to a constant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckerNode {
+    /// Position and scale inputs, and checker value output.
+    slots: [SlotDef; 3],
+}
+
+impl Default for CheckerNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("position", Some(ValueType::Vector(VectorType::VEC3F))),
+                SlotDef::input("scale", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("checker", Some(ValueType::Scalar(ScalarType::Float))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for CheckerNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let position = inputs.next().unwrap();
+        let scale = inputs.next().unwrap();
+
+        let scaled = module.mul(position, scale);
+        let cell = module.floor(scaled);
+        let x = module.x(cell);
+        let y = module.y(cell);
+        let z = module.z(cell);
+        let sum = module.add(x, y);
+        let sum = module.add(sum, z);
+        let two = module.lit(2.);
+        let parity = module.rem(sum, two);
+        let checker = module.abs(parity);
+        Ok(vec![checker])
+    }
+}
+
+/// Graph node to generate a UV offset oscillating back and forth over time.
+///
+/// The offset follows a triangle wave, moving linearly from zero to `range` then
+/// back to zero, `speed` times per second, based on the effect time:
+///
+/// ```txt
+/// offset = range * (1 - abs(fract(time * speed) * 2 - 1))
+/// ```
+///
+/// Compared to a sawtooth scrolling, which wraps from `range` back to zero,
+/// the offset doesn't jump, so scrolling a texture which doesn't tile
+/// perfectly shows no seam. The offset is typically added to the UV of the
+/// particles before sampling a texture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingPongUvNode {
+    /// Speed and range inputs, and UV offset output.
+    slots: [SlotDef; 3],
+}
+
+impl Default for PingPongUvNode {
+    fn default() -> Self {
+        Self {
+            slots: [
+                SlotDef::input("speed", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::input("range", Some(ValueType::Vector(VectorType::VEC2F))),
+                SlotDef::output("offset", Some(ValueType::Vector(VectorType::VEC2F))),
+            ],
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PingPongUvNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 2 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs.into_iter();
+        let speed = inputs.next().unwrap();
+        let range = inputs.next().unwrap();
+
+        // Triangle wave in [0:1] from the phase in [0:1]
+        let time = module.builtin(BuiltInOperator::Time);
+        let phase = module.mul(time, speed);
+        let phase = module.fract(phase);
+        let two = module.lit(2.);
+        let one = module.lit(1.);
+        let wave = module.mul(phase, two);
+        let wave = module.sub(wave, one);
+        let wave = module.abs(wave);
+        let wave = module.sub(one, wave);
+        let offset = module.mul(range, wave);
+        Ok(vec![offset])
+    }
+}
+
+/// Graph node to sample a color gradient after a smoothstep easing.
+///
+/// This applies a smooth Hermite interpolation to the input ratio `t`, then
+/// samples the gradient of the node with the eased ratio:
+///
+/// ```txt
+/// color = gradient.sample(smoothstep(edge0, edge1, t))
+/// ```
+///
+/// This is equivalent to wiring a smoothstep node into a gradient sampling, but
+/// the easing edges are stored in the node itself. The gradient is sampled with
+/// a chain of `mix()` expressions, one per gradient segment, so the size of the
+/// emitted expression grows linearly with the number of gradient keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothGradientNode {
+    /// Input ratio and output color.
+    slots: [SlotDef; 2],
+    /// Gradient to sample.
+    gradient: Gradient<Vec4>,
+    /// Lower edge of the smoothstep.
+    edge0: f32,
+    /// Upper edge of the smoothstep.
+    edge1: f32,
+}
+
+impl Default for SmoothGradientNode {
+    fn default() -> Self {
+        Self::new(Gradient::linear(Vec4::ZERO, Vec4::ONE))
+    }
+}
+
+impl SmoothGradientNode {
+    /// Create a new node sampling the given gradient, with smoothstep edges
+    /// `0.` and `1.`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gradient is empty.
+    pub fn new(gradient: Gradient<Vec4>) -> Self {
+        assert!(
+            !gradient.is_empty(),
+            "SmoothGradientNode requires a non-empty gradient"
+        );
+        Self {
+            slots: [
+                SlotDef::input("t", Some(ValueType::Scalar(ScalarType::Float))),
+                SlotDef::output("color", Some(ValueType::Vector(VectorType::VEC4F))),
+            ],
+            gradient,
+            edge0: 0.,
+            edge1: 1.,
+        }
+    }
+
+    /// Set the smoothstep edges.
+    pub fn with_edges(mut self, edge0: f32, edge1: f32) -> Self {
+        self.edge0 = edge0;
+        self.edge1 = edge1;
+        self
+    }
+
+    /// Get the gradient sampled by the node.
+    pub fn gradient(&self) -> &Gradient<Vec4> {
+        &self.gradient
+    }
+
+    /// Get the lower and upper smoothstep edges.
+    pub fn edges(&self) -> (f32, f32) {
+        (self.edge0, self.edge1)
+    }
+
+    /// Sample the node on CPU.
+    ///
+    /// This returns the same color as the one produced on GPU by the
+    /// expression emitted by the node.
+    pub fn sample(&self, t: f32) -> Vec4 {
+        let x = ((t - self.edge0) / (self.edge1 - self.edge0)).clamp(0., 1.);
+        let t = x * x * (-2_f32).mul_add(x, 3.);
+        self.gradient.sample(t)
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SmoothGradientNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if inputs.len() != 1 {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 1 input, got {}",
+                inputs.len()
+            )));
+        }
+        let t = inputs.into_iter().next().unwrap();
+
+        let edge0 = module.lit(self.edge0);
+        let edge1 = module.lit(self.edge1);
+        let t = module.smoothstep(edge0, edge1, t);
+
+        let stops: Vec<_> = self
+            .gradient
+            .keys()
+            .iter()
+            .map(|key| (key.ratio(), key.value))
+            .collect();
+        Ok(vec![mix_color_stops(module, t, &stops)])
+    }
+}
+
+/// Graph node configuring the spawner of the effect.
+///
+/// This node has no slot. It only carries the [`Spawner`] of the effect, which
+/// is used by [`Graph::suggest_capacity()`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SpawnerNode {
+    spawner: Spawner,
+}
+
+impl SpawnerNode {
+    /// Create a new node configuring the given spawner.
+    pub fn new(spawner: Spawner) -> Self {
+        Self { spawner }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for SpawnerNode {
+    fn slots(&self) -> &[SlotDef] {
+        &[]
+    }
+
+    fn eval(
+        &self,
+        _module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![])
+    }
+
+    fn spawner(&self) -> Option<&Spawner> {
+        Some(&self.spawner)
+    }
+}
+
+/// Graph node producing the lifetime of the particles.
+///
+/// The constant lifetime is output as a literal expression, generally used to
+/// initialize the [`Attribute::LIFETIME`] of the particles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifetimeNode {
+    /// Output lifetime.
+    slots: [SlotDef; 1],
+    /// Lifetime, in seconds.
+    lifetime: f32,
+}
+
+impl Default for LifetimeNode {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+impl LifetimeNode {
+    /// Create a new node producing the given particle lifetime, in seconds.
+    pub fn new(lifetime: f32) -> Self {
+        Self {
+            slots: [SlotDef::output(
+                "lifetime",
+                Some(ValueType::Scalar(ScalarType::Float)),
+            )],
+            lifetime,
+        }
+    }
+
+    /// Get the particle lifetime, in seconds.
+    pub fn lifetime(&self) -> f32 {
+        self.lifetime
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for LifetimeNode {
+    fn slots(&self) -> &[SlotDef] {
+        &self.slots
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        inputs: Vec<ExprHandle>,
+    ) -> Result<Vec<ExprHandle>, ExprError> {
+        if !inputs.is_empty() {
+            return Err(ExprError::GraphEvalError(format!(
+                "expected 0 inputs, got {}",
+                inputs.len()
+            )));
+        }
+        Ok(vec![module.lit(self.lifetime)])
+    }
+
+    fn max_lifetime(&self) -> Option<f32> {
+        Some(self.lifetime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::*;
+    use crate::{
+        AccelModifier, BinaryOperator, CpuValue, EvalContext, Expr, LinearDragModifier,
+        ModifierContext, ParticleLayout, PropertyLayout, ShaderWriter, ToWgslString, UnaryOperator,
+        Value,
+    };
+
+    #[test]
+    fn add() {
+        let node = AddNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "(3.) + (2.)".to_string());
+    }
+
+    #[test]
+    fn sub() {
+        let node = SubNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "(3.) - (2.)".to_string());
+    }
+
+    #[test]
+    fn mul() {
+        let node = MulNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "(3.) * (2.)".to_string());
+    }
+
+    #[test]
+    fn div() {
+        let node = DivNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "(3.) / (2.)".to_string());
+    }
+
+    #[test]
+    fn modulo() {
+        let node = ModNode::default();
+
+        let mut module = Module::default();
+
+        let three = module.lit(3.5);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "(3.5) % (2.)".to_string());
+    }
+
+    #[test]
+    fn remap() {
+        let node = RemapNode::default();
+
+        let mut module = Module::default();
+        let value = module.attr(Attribute::AGE);
+        let in_min = module.lit(1.);
+        let in_max = module.lit(3.);
+        let out_min = module.lit(10.);
+        let out_max = module.lit(20.);
+
+        let ret = node.eval(&mut module, vec![value, in_min, in_max, out_min]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node
+            .eval(&mut module, vec![value, in_min, in_max, out_min, out_max])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "(10.) + ((((particle.age) - (1.)) * ((20.) - (10.))) / ((3.) - (1.)))".to_string()
+        );
+    }
+
+    #[test]
+    fn clamp() {
+        let node = ClampNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let three = module.lit(3.);
+        let zero = module.lit(0.);
+        let ret = node.eval(&mut module, vec![three, zero]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let two = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![three, zero, two]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "clamp(3., 0., 2.)".to_string());
+    }
+
+    #[test]
+    fn min() {
+        let node = MinNode::default();
+
+        let mut module = Module::default();
+        let a = module.lit(0.);
+        let b = module.lit(Vec3::ONE);
+
+        let ret = node.eval(&mut module, vec![a]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![a, a]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "min(0., 0.)".to_string());
+
+        let outputs = node.eval(&mut module, vec![b, b]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "min(vec3<f32>(1.,1.,1.), vec3<f32>(1.,1.,1.))".to_string()
+        );
+
+        // The result follows the type of the inputs
+        let mut g = Graph::new();
+        let nid_a = g.add_node(LiteralNode::new(Vec3::ZERO));
+        let nid_b = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_min = g.add_node(MinNode::default());
+        let sid_result = g.output_slot(nid_min, "result").unwrap();
+        g.link(
+            g.output_slot(nid_a, "value").unwrap(),
+            g.input_slot(nid_min, "a").unwrap(),
+        );
+        assert_eq!(g.infer_slot_type(sid_result), None);
+        g.link(
+            g.output_slot(nid_b, "velocity").unwrap(),
+            g.input_slot(nid_min, "b").unwrap(),
+        );
+        assert_eq!(
+            g.infer_slot_type(sid_result),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+    }
+
+    #[test]
+    fn max() {
+        let node = MaxNode::default();
+
+        let mut module = Module::default();
+        let a = module.lit(0.);
+        let b = module.lit(Vec3::ONE);
+
+        let ret = node.eval(&mut module, vec![a]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![a, a]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "max(0., 0.)".to_string());
+
+        let outputs = node.eval(&mut module, vec![b, b]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "max(vec3<f32>(1.,1.,1.), vec3<f32>(1.,1.,1.))".to_string()
+        );
+
+        // The result follows the type of the inputs
+        let mut g = Graph::new();
+        let nid_a = g.add_node(LiteralNode::new(Vec3::ZERO));
+        let nid_b = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_max = g.add_node(MaxNode::default());
+        let sid_result = g.output_slot(nid_max, "result").unwrap();
+        g.link(
+            g.output_slot(nid_a, "value").unwrap(),
+            g.input_slot(nid_max, "a").unwrap(),
+        );
+        assert_eq!(g.infer_slot_type(sid_result), None);
+        g.link(
+            g.output_slot(nid_b, "velocity").unwrap(),
+            g.input_slot(nid_max, "b").unwrap(),
+        );
+        assert_eq!(
+            g.infer_slot_type(sid_result),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+    }
+
+    #[test]
+    fn mix() {
+        let node = MixNode::default();
+
+        let mut module = Module::default();
+
+        let a = module.lit(Vec3::ZERO);
+        let b = module.lit(Vec3::ONE);
+        let ret = node.eval(&mut module, vec![a, b]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // The interpolation factor must be a scalar
+        let t = module.lit(Vec3::splat(0.5));
+        let ret = node.eval(&mut module, vec![a, b, t]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+
+        let t = module.lit(0.5);
+        let outputs = node.eval(&mut module, vec![a, b, t]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(
+            str,
+            "mix(vec3<f32>(0.,0.,0.), vec3<f32>(1.,1.,1.), 0.5)".to_string()
+        );
+
+        // Factors of unknown type are only checked by the shader compiler
+        let t = module.binary(BinaryOperator::Mul, t, t);
+        assert!(node.eval(&mut module, vec![a, b, t]).is_ok());
+    }
+
+    #[test]
+    fn comparison() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let nodes: [(Box<dyn Node>, &str); 3] = [
+            (Box::<LessNode>::default(), "(3.) < (2.)"),
+            (Box::<GreaterNode>::default(), "(3.) > (2.)"),
+            (Box::<EqualNode>::default(), "(3.) == (2.)"),
+        ];
+        for (node, expected) in nodes {
+            assert_eq!(
+                node.slots()[2].value_type(),
+                Some(ValueType::Scalar(ScalarType::Bool))
+            );
+
+            let mut module = Module::default();
+            let x = module.lit(3.);
+            let y = module.lit(2.);
+            let ret = node.eval(&mut module, vec![x]);
+            assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+            // Operands must be scalars of the same type
+            let i = module.lit(2i32);
+            let ret = node.eval(&mut module, vec![x, i]);
+            assert!(matches!(ret, Err(ExprError::TypeError(_))));
+            let v = module.lit(Vec3::ONE);
+            let ret = node.eval(&mut module, vec![v, v]);
+            assert!(matches!(ret, Err(ExprError::TypeError(_))));
+
+            let outputs = node.eval(&mut module, vec![x, y]).unwrap();
+            assert_eq!(outputs.len(), 1);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, expected);
+        }
+    }
+
+    #[test]
+    fn select() {
+        let node = SelectNode::default();
+
+        let mut module = Module::default();
+        let x = module.lit(3.);
+        let cond = module.lit(true);
+        let ret = node.eval(&mut module, vec![cond, x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // Both values must have the same type
+        let v = module.lit(Vec3::ONE);
+        let ret = node.eval(&mut module, vec![cond, x, v]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+
+        // Condition from a comparison node
+        let mut g = Graph::new();
+        let nid_x = g.add_node(LiteralNode::new(3.));
+        let nid_y = g.add_node(LiteralNode::new(2.));
+        let nid_less = g.add_node(LessNode::default());
+        let nid_select = g.add_node(SelectNode::default());
+        let sid_x = g.output_slot(nid_x, "value").unwrap();
+        let sid_y = g.output_slot(nid_y, "value").unwrap();
+        g.link(sid_x, g.input_slot(nid_less, "lhs").unwrap());
+        g.link(sid_y, g.input_slot(nid_less, "rhs").unwrap());
+        g.link(
+            g.output_slot(nid_less, "result").unwrap(),
+            g.input_slot(nid_select, "cond").unwrap(),
+        );
+        g.link(sid_x, g.input_slot(nid_select, "if_true").unwrap());
+        g.link(sid_y, g.input_slot(nid_select, "if_false").unwrap());
+
+        let mut module = Module::default();
+        let out = g
+            .eval(&mut module, g.output_slot(nid_select, "result").unwrap())
+            .unwrap();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, "select(2., 3., (3.) < (2.))");
+    }
+
+    #[test]
+    fn vec_compose() {
+        let node = VecComposeNode::default();
+        assert_eq!(node.count(), 3);
+        assert_eq!(
+            node.slots()[3].value_type(),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+
+        let mut module = Module::default();
+        let x = module.lit(1.);
+        let y = module.lit(2.);
+        let z = module.lit(3.);
+        let w = module.lit(4.);
+        let ret = node.eval(&mut module, vec![x, y]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        for (count, expected) in [
+            (2, "vec2(1., 2.)"),
+            (3, "vec3(1., 2., 3.)"),
+            (4, "vec4(vec3(1., 2., 3.), 4.)"),
+        ] {
+            let node = VecComposeNode::new(count);
+            let outputs = node
+                .eval(&mut module, [x, y, z, w][..count as usize].to_vec())
+                .unwrap();
+            assert_eq!(outputs.len(), 1);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, expected);
+        }
+    }
+
+    #[test]
+    fn vec_decompose() {
+        let node = VecDecomposeNode::default();
+        assert_eq!(node.count(), 3);
+
+        let mut module = Module::default();
+        let pos = module.attr(Attribute::POSITION);
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![pos]).unwrap();
+        assert_eq!(outputs.len(), 3);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        for (output, expected) in outputs.into_iter().zip([
+            "particle.position.x",
+            "particle.position.y",
+            "particle.position.z",
+        ]) {
+            let str = context.eval(&module, output).unwrap();
+            assert_eq!(str, expected);
+        }
+
+        let node = VecDecomposeNode::new(4);
+        assert_eq!(
+            node.slots()[0].value_type(),
+            Some(ValueType::Vector(VectorType::VEC4F))
+        );
+        let color = module.lit(Vec4::new(1., 2., 3., 4.));
+        let outputs = node.eval(&mut module, vec![color]).unwrap();
+        assert_eq!(outputs.len(), 4);
+        let str = context.eval(&module, outputs[3]).unwrap();
+        assert_eq!(str, "vec4<f32>(1.,2.,3.,4.).w");
+    }
+
+    #[test]
+    fn swizzle() {
+        assert!(matches!(
+            SwizzleNode::new("xyzwx"),
+            Err(ExprError::SyntaxError(_))
+        ));
+        let node = SwizzleNode::new("xy").unwrap();
+        assert_eq!(node.mask(), "xy");
+        assert_eq!(
+            node.slots()[1].value_type(),
+            Some(ValueType::Vector(VectorType::VEC2F))
+        );
+
+        let mut module = Module::default();
+        let v = module.lit(Vec4::new(1., 2., 3., 4.));
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![v]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "(vec4<f32>(1.,2.,3.,4.)).xy");
+
+        // Mask component out of the bounds of the input vector
+        let node = SwizzleNode::new("xz").unwrap();
+        let v = module.lit(Vec2::ONE);
+        let ret = node.eval(&mut module, vec![v]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+        let x = module.lit(1.);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+    }
+
+    #[test]
+    #[should_panic]
+    fn vec_compose_invalid_count() {
+        let _ = VecComposeNode::new(5);
+    }
+
+    #[test]
+    fn component_mix() {
+        let node = ComponentMixNode::default();
+
+        let mut module = Module::default();
+
+        let a = module.lit(Vec3::ZERO);
+        let b = module.lit(Vec3::ONE);
+        let ret = node.eval(&mut module, vec![a, b]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // The weights must be a Vec3, not a scalar
+        let w = module.lit(0.5);
+        let ret = node.eval(&mut module, vec![a, b, w]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+
+        let w = module.lit(Vec3::new(0., 0.5, 1.));
+        let outputs = node.eval(&mut module, vec![a, b, w]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(
+            str,
+            "mix(vec3<f32>(0.,0.,0.), vec3<f32>(1.,1.,1.), vec3<f32>(0.,0.5,1.))".to_string()
+        );
+    }
+
+    #[test]
+    fn particle_normal() {
+        let node = ParticleNormalNode::default();
+        assert_eq!(node.mode(), ParticleNormalMode::Billboard);
+
+        let mut module = Module::default();
+        let axis_x = module.attr(Attribute::AXIS_X);
+        let axis_y = module.attr(Attribute::AXIS_Y);
+        let axis_z = module.attr(Attribute::AXIS_Z);
+        let uv = module.lit(Vec2::new(0.25, 0.5));
+        let ret = node.eval(&mut module, vec![axis_x, axis_y, axis_z]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        for (mode, expected) in [
+            (ParticleNormalMode::Billboard, "normalize(particle.axis_z)"),
+            (
+                ParticleNormalMode::SphericalImpostor,
+                "normalize((((particle.axis_x) * ((((vec2<f32>(0.25,0.5)) * (vec2<f32>(2.,-2.))) + (vec2<f32>(-1.,1.))).x)) + ((particle.axis_y) * ((((vec2<f32>(0.25,0.5)) * (vec2<f32>(2.,-2.))) + (vec2<f32>(-1.,1.))).y))) + ((particle.axis_z) * (sqrt(max((1.) - (dot(((vec2<f32>(0.25,0.5)) * (vec2<f32>(2.,-2.))) + (vec2<f32>(-1.,1.)), ((vec2<f32>(0.25,0.5)) * (vec2<f32>(2.,-2.))) + (vec2<f32>(-1.,1.)))), 0.)))))",
+            ),
+        ] {
+            let node = ParticleNormalNode::new(mode);
+            let outputs = node
+                .eval(&mut module, vec![axis_x, axis_y, axis_z, uv])
+                .unwrap();
+            assert_eq!(outputs.len(), 1);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, expected, "mode={:?}", mode);
+        }
+    }
+
+    #[test]
+    fn attractor() {
+        let node = AttractorNode::default();
+        assert_eq!(node.epsilon(), AttractorNode::DEFAULT_EPSILON);
+        let node = node.with_epsilon(0.01);
+        assert_eq!(node.epsilon(), 0.01);
+
+        let mut module = Module::default();
+        let position = module.attr(Attribute::POSITION);
+        let center = module.lit(Vec3::Y);
+        let strength = module.lit(5.);
+        let ret = node.eval(&mut module, vec![position, center]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node
+            .eval(&mut module, vec![position, center, strength])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let delta = "(vec3<f32>(0.,1.,0.)) - (particle.position)";
+        assert_eq!(
+            str,
+            format!("((normalize({delta})) * (5.)) / (max(dot({delta}, {delta}), 0.01))")
+        );
+
+        // The output is a Vec3 acceleration
+        let mut g = Graph::new();
+        let nid = g.add_node(AttractorNode::default());
+        assert_eq!(
+            g.infer_slot_type(g.output_slot(nid, "acceleration").unwrap()),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+    }
+
+    #[test]
+    fn cosine_hemisphere() {
+        let node = CosineHemisphereNode::default();
+
+        let mut module = Module::default();
+        let normal = module.lit(Vec3::Y);
+        let u1 = module.lit(0.25);
+        let u2 = module.lit(0.5);
+        let ret = node.eval(&mut module, vec![normal, u1]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![normal, u1, u2]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+
+        // Cosine-weighted sampling: radius sqrt(u1), height sqrt(1 - u1)
+        let phi = "(6.283185) * (0.5)";
+        let local_x = format!("(sqrt(0.25)) * (cos({phi}))");
+        let local_y = format!("(sqrt(0.25)) * (sin({phi}))");
+        let local_z = "sqrt((1.) - (0.25))";
+        // Tangent basis built from the sign of the normal's Z component
+        let n = "normalize(vec3<f32>(0.,1.,0.))";
+        let s = format!("select(-1., 1., ({n}.z) >= (0.))");
+        let a = format!("(-1.) / (({s}) + ({n}.z))");
+        let b = format!("(({n}.x) * ({n}.y)) * ({a})");
+        let tangent = format!(
+            "vec3((1.) + (((({s}) * ({n}.x)) * ({n}.x)) * ({a})), ({s}) * ({b}), (-1.) * (({s}) * ({n}.x)))"
+        );
+        let bitangent =
+            format!("vec3({b}, ({s}) + ((({n}.y) * ({n}.y)) * ({a})), (-1.) * ({n}.y))");
+        assert_eq!(
+            str,
+            format!(
+                "((({tangent}) * ({local_x})) + (({bitangent}) * ({local_y}))) + (({n}) * ({local_z}))"
+            )
+        );
+    }
+
+    #[test]
+    fn fog() {
+        let node = FogNode::default();
+
+        let mut module = Module::default();
+        let color = module.lit(Vec4::ONE);
+        let fog_color = module.lit(Vec4::new(0.5, 0.5, 0.5, 1.));
+        let density = module.lit(0.25);
+        let ret = node.eval(&mut module, vec![color, fog_color]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node
+            .eval(&mut module, vec![color, fog_color, density])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "mix(vec4<f32>(1.,1.,1.,1.), vec4<f32>(0.5,0.5,0.5,1.), \
+             (1.) - (exp(((-1.) * (0.25)) * (get_view_depth(particle.position)))))"
+        );
+    }
+
+    #[test]
+    fn self_shadow() {
+        let node = SelfShadowNode::new(Vec3::new(-1., -2., -2.), Vec3::new(1., 2., 2.));
+        assert_eq!(node.center(), Vec3::ZERO);
+        assert_eq!(node.radius(), 3.);
+
+        let mut module = Module::default();
+        let color = module.lit(Vec4::ONE);
+        let position = module.lit(Vec3::ZERO);
+        let light_dir = module.lit(Vec3::NEG_Y);
+        let density = module.lit(2.);
+        let ret = node.eval(&mut module, vec![color, position, light_dir]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node
+            .eval(&mut module, vec![color, position, light_dir, density])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "vec4(((vec4<f32>(1.,1.,1.,1.)).xyz) * (exp(((-1.) * (2.)) * (saturate(((3.) - \
+             (dot((vec3<f32>(0.,0.,0.)) - (vec3<f32>(0.,0.,0.)), \
+             (-1.) * (normalize(vec3<f32>(0.,-1.,0.)))))) * (0.166667))))), \
+             vec4<f32>(1.,1.,1.,1.).w)"
+        );
+
+        // The light travels downward, so particles higher in the volume receive
+        // more light.
+        let light_dir = Vec3::NEG_Y;
+        let top = node.transmittance(Vec3::new(0., 3., 0.), light_dir, 2.);
+        let center = node.transmittance(Vec3::ZERO, light_dir, 2.);
+        let bottom = node.transmittance(Vec3::new(0., -3., 0.), light_dir, 2.);
+        assert_eq!(top, 1.);
+        assert!((center - (-1_f32).exp()).abs() < 1e-5);
+        assert!((bottom - (-2_f32).exp()).abs() < 1e-5);
+
+        // Lighting from below reverses the attenuation
+        let light_dir = Vec3::Y * 5.;
+        assert_eq!(
+            node.transmittance(Vec3::new(0., -3., 0.), light_dir, 2.),
+            1.
+        );
+        assert!(node.transmittance(Vec3::new(0., 3., 0.), light_dir, 2.) < center);
+
+        // No light direction, no attenuation
+        assert_eq!(node.transmittance(Vec3::ZERO, Vec3::ZERO, 2.), 1.);
+    }
+
+    #[test]
+    fn distort_uv() {
+        let node = DistortUvNode::default();
+
+        let mut module = Module::default();
+        module.add_texture("normal_map");
+        let normal_map = module.lit(0u32);
+        let normal_uv = module.lit(Vec2::ZERO);
+        let screen_uv = module.lit(Vec2::ONE);
+        let strength = module.lit(0.1);
+        let ret = node.eval(&mut module, vec![normal_map, normal_uv, screen_uv]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node
+            .eval(
+                &mut module,
+                vec![normal_map, normal_uv, screen_uv, strength],
+            )
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let sample = "textureSample(material_texture_0, material_sampler_0, vec2<f32>(0.,0.))";
+        assert_eq!(
+            str,
+            format!(
+                "(vec2<f32>(1.,1.)) + ((((vec2({sample}.x, {sample}.y)) * (2.)) - (1.)) * (0.1))"
+            )
+        );
+    }
+
+    #[test]
+    fn checker() {
+        let node = CheckerNode::default();
+        assert_eq!(
+            node.slots()[2].value_type(),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+
+        let mut module = Module::default();
+        let position = module.attr(Attribute::POSITION);
+        let scale = module.lit(4.);
+        let ret = node.eval(&mut module, vec![position]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![position, scale]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let cell = format!("floor((particle.{}) * (4.))", Attribute::POSITION.name());
+        assert_eq!(
+            str,
+            format!("abs(((({cell}.x) + ({cell}.y)) + ({cell}.z)) % (2.))")
+        );
+    }
+
+    #[test]
+    fn ping_pong_uv() {
+        let node = PingPongUvNode::default();
+
+        let mut module = Module::default();
+        let speed = module.lit(0.25);
+        let range = module.lit(Vec2::new(0.5, 0.));
+        let ret = node.eval(&mut module, vec![speed]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![speed, range]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Render, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "(vec2<f32>(0.5,0.)) * ((1.) - (abs(((fract((sim_params.time) * (0.25))) * (2.)) - (1.))))"
+        );
+    }
+
+    #[test]
+    fn barycentric() {
+        let node = BarycentricNode::default();
+        assert!(!node.normalize());
+
+        let mut module = Module::default();
+
+        let a = module.lit(1.);
+        let b = module.lit(2.);
+        let c = module.lit(3.);
+        let ret = node.eval(&mut module, vec![a, b, c]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let w = module.lit(Vec3::new(0.25, 0.25, 0.5));
+        let outputs = node.eval(&mut module, vec![a, b, c, w]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(
+            str,
+            "(((vec3<f32>(0.25,0.25,0.5).x) * (1.)) + ((vec3<f32>(0.25,0.25,0.5).y) * (2.))) + ((vec3<f32>(0.25,0.25,0.5).z) * (3.))"
+                .to_string()
+        );
+
+        // Normalized weights
+        let mut node = node;
+        node.set_normalize(true);
+        assert!(node.normalize());
+        let outputs = node.eval(&mut module, vec![a, b, c, w]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let w =
+            "((vec3<f32>(0.25,0.25,0.5)) / (dot(vec3<f32>(0.25,0.25,0.5), vec3<f32>(1.,1.,1.))))";
+        assert_eq!(
+            str,
+            format!("((({w}.x) * (1.)) + (({w}.y) * (2.))) + (({w}.z) * (3.))")
+        );
+    }
+
+    #[test]
+    fn attr() {
+        let node = AttributeNode::new(Attribute::POSITION);
+
+        let mut module = Module::default();
+
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, format!("particle.{}", Attribute::POSITION.name()));
+    }
+
+    #[test]
+    fn attr_shared() {
+        let node0 = AttributeNode::new(Attribute::POSITION);
+        let node1 = AttributeNode::new(Attribute::POSITION);
+        let node2 = AttributeNode::new(Attribute::VELOCITY);
+
+        let mut module = Module::default();
+        let out0 = node0.eval(&mut module, vec![]).unwrap();
+        let out1 = node1.eval(&mut module, vec![]).unwrap();
+        let out2 = node2.eval(&mut module, vec![]).unwrap();
+        assert_eq!(out0, out1);
+        assert_ne!(out0, out2);
+        assert_eq!(module.get(out0[0]), module.get(out1[0]));
+    }
+
+    #[test]
+    fn prev_attr() {
+        let node = PrevAttributeNode::new(Attribute::POSITION);
+        assert_eq!(node.attr(), Attribute::POSITION);
+
+        let mut module = Module::default();
+
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let out = outputs[0];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // The update pass reads from the previous-frame snapshot
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, out).unwrap();
+        assert_eq!(str, format!("prev_particle.{}", Attribute::POSITION.name()));
+
+        // There's no previous frame at init
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, out),
+            Err(ExprError::InvalidModifierContext(
+                ModifierContext::Init,
+                ModifierContext::Update
+            ))
+        );
+    }
+
+    #[test]
+    fn time() {
+        let node = TimeNode::default();
+
+        let mut module = Module::default();
+
+        let three = module.lit(3.);
+        let ret = node.eval(&mut module, vec![three]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 2);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str0 = context.eval(&module, outputs[0]).unwrap();
+        let str1 = context.eval(&module, outputs[1]).unwrap();
+        assert_eq!(str0, format!("sim_params.{}", BuiltInOperator::Time.name()));
+        assert_eq!(
+            str1,
+            format!("sim_params.{}", BuiltInOperator::DeltaTime.name())
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let node = NormalizeNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let ones = module.lit(Vec3::ONE);
+        let outputs = node.eval(&mut module, vec![ones]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "normalize(vec3<f32>(1.,1.,1.))".to_string());
+
+        // Safe mode guards against zero vectors
+        let node = NormalizeNode::default().with_safe(true);
+        assert!(node.is_safe());
+        assert_eq!(node.epsilon(), NormalizeNode::DEFAULT_EPSILON);
+        let node = node.with_epsilon(0.001);
+        assert_eq!(node.epsilon(), 0.001);
+        let outputs = node.eval(&mut module, vec![ones]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "select((vec3<f32>(1.,1.,1.)) * (0.), normalize(vec3<f32>(1.,1.,1.)), \
+             (length(vec3<f32>(1.,1.,1.))) > (0.001))"
+        );
+
+        // Disabling it again emits the plain normalize()
+        let node = node.with_safe(false);
+        let outputs = node.eval(&mut module, vec![ones]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "normalize(vec3<f32>(1.,1.,1.))");
+
+        // The node has one input and one output, so can be linked to
+        let mut g = Graph::new();
+        let nid = g.add_node(NormalizeNode::default());
+        assert_eq!(g.input_slots(nid).len(), 1);
+        assert_eq!(g.output_slots(nid).len(), 1);
+        assert!(g.input_slot(nid, "in").is_some());
+        assert!(g.output_slot(nid, "out").is_some());
+    }
+
+    #[test]
+    fn negate() {
+        let node = NegateNode::default();
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let x = module.lit(Vec3::new(1., -2., 0.5));
+        let outputs = node.eval(&mut module, vec![x]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "-(vec3<f32>(1.,-2.,0.5))".to_string());
+
+        // The output follows the type of the input
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_neg = g.add_node(NegateNode::default());
+        g.connect(nid_pos, "position", nid_neg, "in").unwrap();
+        assert_eq!(g.input_slots(nid_neg).len(), 1);
+        assert_eq!(
+            g.infer_slot_type(g.output_slot(nid_neg, "out").unwrap()),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+    }
+
+    #[test]
+    fn unary_math() {
+        let node = UnaryMathNode::default();
+        assert_eq!(node.op(), UnaryMathOp::Abs);
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let x = module.lit(Vec3::new(-1.5, 0., 2.5));
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        for (op, expected) in [
+            (UnaryMathOp::Abs, "abs(vec3<f32>(-1.5,0.,2.5))"),
+            (UnaryMathOp::Floor, "floor(vec3<f32>(-1.5,0.,2.5))"),
+            (UnaryMathOp::Ceil, "ceil(vec3<f32>(-1.5,0.,2.5))"),
+            (UnaryMathOp::Round, "round(vec3<f32>(-1.5,0.,2.5))"),
+            (UnaryMathOp::Sign, "sign(vec3<f32>(-1.5,0.,2.5))"),
+        ] {
+            let mut node = UnaryMathNode::default();
+            node.set_op(op);
+            assert_eq!(node.op(), op);
+            let outputs = node.eval(&mut module, vec![x]).unwrap();
+            assert_eq!(outputs.len(), 1);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, expected, "op={:?}", op);
+        }
+    }
+
+    #[test]
+    fn step() {
+        let node = StepNode::default();
+
+        let mut module = Module::default();
+        let edge = module.lit(0.5);
+        let x = module.lit(0.7);
+
+        let ret = node.eval(&mut module, vec![edge]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![edge, x]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "step(0.5, 0.7)".to_string());
+    }
+
+    #[test]
+    fn smoothstep() {
+        let node = SmoothstepNode::default();
+
+        let mut module = Module::default();
+        let edge0 = module.lit(0.);
+        let edge1 = module.lit(1.);
+        let x = module.lit(0.25);
+
+        let ret = node.eval(&mut module, vec![edge0, edge1]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let outputs = node.eval(&mut module, vec![edge0, edge1, x]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "smoothstep(0., 1., 0.25)".to_string());
+
+        // The result follows the type of the inputs
+        let mut g = Graph::new();
+        let nid_edge = g.add_node(LiteralNode::new(Vec3::ZERO));
+        let nid_x = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_smoothstep = g.add_node(SmoothstepNode::default());
+        let sid_edge = g.output_slot(nid_edge, "value").unwrap();
+        g.link(sid_edge, g.input_slot(nid_smoothstep, "edge0").unwrap());
+        g.link(sid_edge, g.input_slot(nid_smoothstep, "edge1").unwrap());
+        g.link(
+            g.output_slot(nid_x, "position").unwrap(),
+            g.input_slot(nid_smoothstep, "x").unwrap(),
+        );
+        assert_eq!(
+            g.infer_slot_type(g.output_slot(nid_smoothstep, "result").unwrap()),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let node = DotNode::default();
+        assert_eq!(
+            node.slots()[2].value_type(),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let x = module.lit(Vec3::X);
+        let ones = module.lit(Vec3::ONE);
+        let outputs = node.eval(&mut module, vec![x, ones]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "dot(vec3<f32>(1.,0.,0.), vec3<f32>(1.,1.,1.))".to_string()
+        );
+    }
+
+    #[test]
+    fn distance() {
+        let node = DistanceNode::default();
+        assert_eq!(
+            node.slots()[2].value_type(),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let a = module.lit(Vec3::X);
+        let b = module.lit(Vec3::ONE);
+        let outputs = node.eval(&mut module, vec![a, b]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "distance(vec3<f32>(1.,0.,0.), vec3<f32>(1.,1.,1.))".to_string()
+        );
+
+        // Known input types must be float vectors of the same width
+        let c = module.lit(Vec2::ONE);
+        let ret = node.eval(&mut module, vec![a, c]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let s = module.lit(1.);
+        let ret = node.eval(&mut module, vec![s, s]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // Unknown input types are not validated
+        let p = module.add_property("p", Vec3::ZERO.into());
+        let p = module.prop(p);
+        assert!(node.eval(&mut module, vec![a, p]).is_ok());
+
+        // In a graph, the output is a float scalar
+        let mut g = Graph::new();
+        let nid_a = g.add_node(LiteralNode::new(Vec3::X));
+        let nid_b = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_distance = g.add_node(DistanceNode::default());
+        g.connect(nid_a, "value", nid_distance, "a").unwrap();
+        g.connect(nid_b, "position", nid_distance, "b").unwrap();
+        assert_eq!(
+            g.infer_slot_type(g.output_slot(nid_distance, "distance").unwrap()),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+    }
+
+    #[test]
+    fn cross() {
+        let node = CrossNode::default();
+        assert_eq!(
+            node.slots()[2].value_type(),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+
+        let mut module = Module::default();
+
+        let x = module.lit(Vec3::X);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let y = module.lit(Vec3::Y);
+        let outputs = node.eval(&mut module, vec![x, y]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "cross(vec3<f32>(1.,0.,0.), vec3<f32>(0.,1.,0.))".to_string()
+        );
+    }
+
+    #[test]
+    fn length() {
+        let node = LengthNode::default();
+        assert_eq!(
+            node.slots()[1].value_type(),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let ones = module.lit(Vec3::ONE);
+        let outputs = node.eval(&mut module, vec![ones]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "length(vec3<f32>(1.,1.,1.))".to_string());
+    }
+
+    #[test]
+    fn literal() {
+        let mut node = LiteralNode::new(Vec3::X);
+        assert_eq!(node.value(), Vec3::X.into());
+        assert_eq!(
+            node.slots()[0].value_type(),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+
+        let mut module = Module::default();
+
+        let x = module.lit(1.);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        node.set_value(3_u32.into());
+        assert_eq!(
+            node.slots()[0].value_type(),
+            Some(ValueType::Scalar(ScalarType::Uint))
+        );
+
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "3u".to_string());
+    }
+
+    #[test]
+    fn property() {
+        let node = PropertyNode::new("my_prop", ValueType::Vector(VectorType::VEC3F));
+        assert_eq!(node.property_name(), "my_prop");
+        assert_eq!(node.slots()[0].name(), "my_prop");
+        assert_eq!(
+            node.slots()[0].value_type(),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+
+        let mut module = Module::default();
+
+        let x = module.lit(1.);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // The property is declared on first evaluation
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(module.properties().len(), 1);
+        assert_eq!(module.properties()[0].name(), "my_prop");
+        assert_eq!(
+            *module.properties()[0].default_value(),
+            Value::Vector(Vec3::ZERO.into())
+        );
+
+        // and reused afterward
+        assert!(node.eval(&mut module, vec![]).is_ok());
+        assert_eq!(module.properties().len(), 1);
+
+        let property_layout = PropertyLayout::new(module.properties());
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "properties.my_prop".to_string());
+
+        // Mismatching type
+        let node = PropertyNode::new("my_prop", ValueType::Scalar(ScalarType::Float));
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::TypeError(_))));
+    }
+
+    #[test]
+    fn sin_cos() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        let nodes: [(Box<dyn Node>, &str); 2] = [
+            (Box::new(SinNode::default()), "sin"),
+            (Box::new(CosNode::default()), "cos"),
+        ];
+        for (node, op) in nodes {
+            let ret = node.eval(&mut module, vec![]);
+            assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+            let x = module.lit(0.5);
+            let outputs = node.eval(&mut module, vec![x]).unwrap();
+            assert_eq!(outputs.len(), 1);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, format!("{}(0.5)", op));
+        }
+    }
+
+    #[test]
+    fn pow() {
+        let node = PowNode::default();
+
+        let mut module = Module::default();
+
+        let base = module.lit(Vec3::ONE);
+        let ret = node.eval(&mut module, vec![base]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let exponent = module.lit(Vec3::new(2., 2., 2.));
+        let outputs = node.eval(&mut module, vec![base, exponent]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "pow(vec3<f32>(1.,1.,1.), vec3<f32>(2.,2.,2.))".to_string()
+        );
+    }
+
+    #[test]
+    fn graph() {
+        let mut g = Graph::new();
+
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let sid_pos = g.output_slots(nid_pos)[0];
+        let sid_add_lhs = g.input_slots(nid_add)[0];
+        let sid_add_rhs = g.input_slots(nid_add)[1];
+        g.link(sid_pos, sid_add_lhs);
+
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        let sid_vel = g.output_slots(nid_vel)[0];
+        let sid_dt = g
+            .output_slot(nid_dt, BuiltInOperator::DeltaTime.name())
+            .unwrap();
+        let sid_mul_lhs = g.input_slots(nid_mul)[0];
+        let sid_mul_rhs = g.input_slots(nid_mul)[1];
+        g.link(sid_vel, sid_mul_lhs);
+        g.link(sid_dt, sid_mul_rhs);
+
+        let sid_mul_out = g.output_slots(nid_mul)[0];
+        g.link(sid_mul_out, sid_add_rhs);
+
+        assert_eq!(
+            g.links(),
+            vec![
+                (sid_pos, sid_add_lhs),
+                (sid_vel, sid_mul_lhs),
+                (sid_mul_out, sid_add_rhs),
+                (sid_dt, sid_mul_rhs),
+            ]
+        );
+
+        // Fan-out yields one link per input, and relinking an input replaces its link
+        let nid_sub = g.add_node(SubNode::default());
+        let sid_sub_lhs = g.input_slot(nid_sub, "lhs").unwrap();
+        let sid_sub_rhs = g.input_slot(nid_sub, "rhs").unwrap();
+        g.link(sid_pos, sid_sub_lhs);
+        g.link(sid_pos, sid_sub_rhs);
+        g.relink(sid_pos, sid_mul_lhs).unwrap();
+        assert_eq!(
+            g.links(),
+            vec![
+                (sid_pos, sid_add_lhs),
+                (sid_pos, sid_sub_lhs),
+                (sid_pos, sid_sub_rhs),
+                (sid_pos, sid_mul_lhs),
+                (sid_mul_out, sid_add_rhs),
+                (sid_dt, sid_mul_rhs),
+            ]
+        );
+        assert_eq!(g.links().len(), g.output_targets(sid_pos).len() + 2);
+        assert_eq!(
+            g.linked_inputs(sid_pos),
+            vec![sid_add_lhs, sid_sub_lhs, sid_sub_rhs, sid_mul_lhs]
+        );
+        assert_eq!(g.linked_inputs(sid_mul_out), vec![sid_add_rhs]);
+        for (output, input) in g.links() {
+            assert_eq!(g.linked_output(input), Some(output));
+            assert!(g.linked_inputs(output).contains(&input));
+        }
+
+        g.remove_node(nid_sub);
+        g.unlink(sid_dt, sid_mul_rhs);
+        assert_eq!(
+            g.links(),
+            vec![
+                (sid_pos, sid_add_lhs),
+                (sid_pos, sid_mul_lhs),
+                (sid_mul_out, sid_add_rhs),
+            ]
+        );
+        assert_eq!(g.linked_output(sid_mul_rhs), None);
+        assert!(g.linked_inputs(sid_dt).is_empty());
+
+        // Input constants are linked from their hidden literal node
+        g.set_input_constant(sid_mul_rhs, 2.0.into());
+        let links = g.links();
+        assert_eq!(links.len(), 4);
+        let sid_constant = g.linked_output(sid_mul_rhs).unwrap();
+        assert!(links.contains(&(sid_constant, sid_mul_rhs)));
+        assert!(g.is_input_constant_node(g.get_slot(sid_constant).node_id()));
+    }
+
+    #[test]
+    fn graph_topological_order() {
+        // Same graph as the `graph` test
+        let mut g = Graph::new();
+        assert_eq!(g.topological_order(), Ok(vec![]));
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_mul, "lhs").unwrap();
+        g.connect(nid_dt, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+
+        // Sources first, then the arithmetic nodes consuming them
+        let order = g.topological_order().unwrap();
+        assert_eq!(order, vec![nid_pos, nid_vel, nid_dt, nid_mul, nid_add]);
+        assert_eq!(g.topological_order().unwrap(), order);
+
+        // Simulate a cycle mul -> add -> mul
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        let sid_mul_lhs = g.input_slot(nid_mul, "lhs").unwrap();
+        g.unlink_all(sid_mul_lhs);
+        g.get_slot_mut(sid_add_out).link_to(sid_mul_lhs);
+        g.get_slot_mut(sid_mul_lhs).link_input(sid_add_out);
+        assert_eq!(
+            g.topological_order(),
+            Err(GraphError::CycleDetected {
+                from: nid_add,
+                to: nid_mul
+            })
+        );
+        let mut module = Module::default();
+        assert!(matches!(
+            g.eval_all(&mut module),
+            Err(ExprError::GraphEvalError(_))
+        ));
+    }
+
+    #[test]
+    fn graph_subgraph() {
+        // Same graph as the `graph` test
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_mul, "lhs").unwrap();
+        g.connect(nid_dt, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+        g.set_node_label(nid_mul, "scaled velocity");
+
+        let (sub, mapping) = g.subgraph(&[nid_mul, nid_dt]);
+        assert_eq!(sub.node_count(), 2);
+        assert_eq!(mapping.node(nid_pos), None);
+        assert_eq!(mapping.node(nid_add), None);
+        assert_eq!(mapping.node(nid_vel), None);
+        let sub_mul = mapping.node(nid_mul).unwrap();
+        let sub_dt = mapping.node(nid_dt).unwrap();
+        assert_eq!(sub.node(sub_mul).unwrap().name(), "MulNode");
+        assert_eq!(sub.node(sub_dt).unwrap().name(), "TimeNode");
+        assert_eq!(sub.node_label(sub_mul), Some("scaled velocity"));
+
+        // The internal time -> mul link survives, while the vel -> mul and
+        // mul -> add boundary links are dropped
+        let sid_dt = g.output_slot(nid_dt, "delta_time").unwrap();
+        let sid_mul_rhs = g.input_slot(nid_mul, "rhs").unwrap();
+        assert_eq!(
+            sub.links(),
+            vec![(
+                mapping.slot(sid_dt).unwrap(),
+                mapping.slot(sid_mul_rhs).unwrap()
+            )]
+        );
+        assert_eq!(
+            sub.input_source(sub.input_slot(sub_mul, "lhs").unwrap()),
+            None
+        );
+        assert!(sub
+            .output_targets(sub.output_slot(sub_mul, "result").unwrap())
+            .is_empty());
+
+        // The source graph is unchanged
+        assert_eq!(g.node_count(), 5);
+        assert_eq!(g.links().len(), 4);
+
+        // Input constants of the extracted nodes are preserved
+        let nid_sub = g.add_node(SubNode::default());
+        let sid_sub_rhs = g.input_slot(nid_sub, "rhs").unwrap();
+        g.set_input_constant(sid_sub_rhs, Value::from(2.));
+        let (sub, mapping) = g.subgraph(&[nid_sub]);
+        assert_eq!(sub.node_count(), 2); // with the hidden literal node
+        assert_eq!(
+            sub.input_constant(mapping.slot(sid_sub_rhs).unwrap()),
+            Some(Value::from(2.))
+        );
+    }
+
+    #[test]
+    fn graph_try_link() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        let sid_time = g.output_slot(nid_time, "time").unwrap();
+        let sid_in = g.input_slot(nid_sin, "in").unwrap();
+        let sid_out = g.output_slot(nid_sin, "out").unwrap();
+
+        // Out-of-range slot
+        let sid_bad = SlotId::new(NonZeroU32::new(999).unwrap());
+        assert_eq!(
+            g.try_link(sid_bad, sid_in),
+            Err(GraphError::NoSuchSlot(sid_bad))
+        );
+        assert_eq!(
+            g.try_link(sid_time, sid_bad),
+            Err(GraphError::NoSuchSlot(sid_bad))
+        );
+        assert_eq!(
+            g.try_unlink(sid_bad, sid_in),
+            Err(GraphError::NoSuchSlot(sid_bad))
+        );
+
+        // Input slot used as output, and output used as input
+        assert_eq!(
+            g.try_link(sid_in, sid_out),
+            Err(GraphError::WrongDirection {
+                slot: sid_in,
+                expected: SlotDir::Output
+            })
+        );
+        assert_eq!(
+            g.try_link(sid_time, sid_out),
+            Err(GraphError::WrongDirection {
+                slot: sid_out,
+                expected: SlotDir::Input
+            })
+        );
+        assert!(g.links().is_empty());
+
+        // Valid slots
+        g.try_link(sid_time, sid_in).unwrap();
+        assert_eq!(g.links(), vec![(sid_time, sid_in)]);
+        assert_eq!(
+            g.try_unlink(sid_in, sid_time),
+            Err(GraphError::WrongDirection {
+                slot: sid_in,
+                expected: SlotDir::Output
+            })
+        );
+        g.try_unlink(sid_time, sid_in).unwrap();
+        assert!(g.links().is_empty());
+
+        // Slots of a removed node don't exist anymore
+        g.remove_node(nid_sin);
+        assert_eq!(
+            g.try_link(sid_time, sid_in),
+            Err(GraphError::NoSuchSlot(sid_in))
+        );
+    }
+
+    #[test]
+    fn graph_replace_node() {
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_op = g.add_node(AddNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        g.connect(nid_pos, "position", nid_op, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_op, "rhs").unwrap();
+        g.connect(nid_op, "result", nid_sin, "in").unwrap();
+        g.set_node_label(nid_op, "combine");
+
+        let old = g.replace_node(nid_op, MulNode::default()).unwrap();
+        assert_eq!(old.name(), "AddNode");
+        assert_eq!(g.node(nid_op).unwrap().name(), "MulNode");
+        assert_eq!(g.node_label(nid_op), Some("combine"));
+
+        // The lhs/rhs/result links carried over to the new slots
+        let sid_lhs = g.input_slot(nid_op, "lhs").unwrap();
+        let sid_rhs = g.input_slot(nid_op, "rhs").unwrap();
+        let sid_result = g.output_slot(nid_op, "result").unwrap();
+        assert_eq!(g.input_source(sid_lhs), g.output_slot(nid_pos, "position"));
+        assert_eq!(g.input_source(sid_rhs), g.output_slot(nid_vel, "velocity"));
+        assert_eq!(
+            g.input_source(g.input_slot(nid_sin, "in").unwrap()),
+            Some(sid_result)
+        );
+        assert_eq!(g.links().len(), 3);
+
+        // Links to slots which don't exist anymore are dropped
+        g.set_input_constant(sid_rhs, Value::Scalar(2_f32.into()));
+        g.replace_node(nid_op, NormalizeNode::default()).unwrap();
+        assert_eq!(g.node(nid_op).unwrap().name(), "NormalizeNode");
+        assert!(g
+            .input_slots(nid_op)
+            .iter()
+            .all(|&s| g.input_source(s).is_none()));
+        assert_eq!(g.input_source(g.input_slot(nid_sin, "in").unwrap()), None);
+        assert!(g.links().is_empty());
+
+        // Hidden constant nodes can't be replaced
+        let sid_sin_in = g.input_slot(nid_sin, "in").unwrap();
+        g.set_input_constant(sid_sin_in, Value::Scalar(3_f32.into()));
+        let nid_const = g.get_slot(g.input_source(sid_sin_in).unwrap()).node_id();
+        assert!(g.replace_node(nid_const, TimeNode::default()).is_none());
+        assert_eq!(g.node(nid_const).unwrap().name(), "LiteralNode");
+        assert!(g.is_input_constant_node(nid_const));
+        assert_eq!(
+            g.input_constant(sid_sin_in),
+            Some(Value::Scalar(3_f32.into()))
+        );
+
+        // Unknown node
+        assert!(g
+            .replace_node(
+                NodeId::new(NonZeroU32::new(99).unwrap()),
+                AddNode::default()
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn graph_merge() {
+        // Same graph as the `graph` test
+        let mut fixture = Graph::new();
+        let nid_pos = fixture.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = fixture.add_node(AddNode::default());
+        let nid_vel = fixture.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = fixture.add_node(MulNode::default());
+        let nid_dt = fixture.add_node(TimeNode::default());
+        fixture
+            .connect(nid_pos, "position", nid_add, "lhs")
+            .unwrap();
+        fixture
+            .connect(nid_vel, "velocity", nid_mul, "lhs")
+            .unwrap();
+        fixture
+            .connect(nid_dt, "delta_time", nid_mul, "rhs")
+            .unwrap();
+        fixture.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+        fixture.set_node_label(nid_mul, "scaled velocity");
+
+        // Merging into an empty graph
+        let mut g = Graph::new();
+        let mapping = g.merge(fixture.clone());
+        assert_eq!(g.node_count(), 5);
+        assert_eq!(mapping.len(), 5);
+        assert_eq!(g.links().len(), 4);
+        assert_eq!(g.node_label(mapping[&nid_mul]), Some("scaled velocity"));
+
+        // Merging into a non-empty graph, with a removed node and an input
+        // constant, keeps the existing identifiers and links
+        let mut g = Graph::new();
+        let nid_removed = g.add_node(TimeNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        let nid_sub = g.add_node(SubNode::default());
+        g.connect(nid_sin, "out", nid_sub, "lhs").unwrap();
+        let sid_sub_rhs = g.input_slot(nid_sub, "rhs").unwrap();
+        g.set_input_constant(sid_sub_rhs, Value::from(2.));
+        g.remove_node(nid_removed);
+        let existing_links = g.links();
+
+        let mut fragment = fixture.clone();
+        let sid_mul_rhs = fragment.input_slot(nid_mul, "rhs").unwrap();
+        fragment.unlink(
+            fragment.output_slot(nid_dt, "delta_time").unwrap(),
+            sid_mul_rhs,
+        );
+        fragment.set_input_constant(sid_mul_rhs, Value::from(0.5));
+        let mapping = g.merge(fragment);
+        assert_eq!(g.node_count(), 2 + 1 + 5 + 1);
+        assert_eq!(g.node(nid_sin).unwrap().name(), "SinNode");
+        assert_eq!(g.input_constant(sid_sub_rhs), Some(Value::from(2.)));
+        for link in &existing_links {
+            assert!(g.links().contains(link));
+        }
+
+        // The links of the merged graph are remapped to the new identifiers
+        let new_add = mapping[&nid_add];
+        let new_mul = mapping[&nid_mul];
+        assert_eq!(
+            g.input_source(g.input_slot(new_add, "lhs").unwrap()),
+            g.output_slot(mapping[&nid_pos], "position")
+        );
+        assert_eq!(
+            g.input_source(g.input_slot(new_add, "rhs").unwrap()),
+            g.output_slot(new_mul, "result")
+        );
+        assert_eq!(
+            g.input_source(g.input_slot(new_mul, "lhs").unwrap()),
+            g.output_slot(mapping[&nid_vel], "velocity")
+        );
+        assert_eq!(
+            g.input_constant(g.input_slot(new_mul, "rhs").unwrap()),
+            Some(Value::from(0.5))
+        );
+        assert_eq!(g.links().len(), existing_links.len() + 4);
+
+        // The merged nodes can be linked to the existing ones
+        g.connect(mapping[&nid_dt], "time", nid_sin, "in").unwrap();
+    }
+
+    #[test]
+    fn graph_slot_value_type() {
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_dot = g.add_node(DotNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let sid_pos = g.output_slot(nid_pos, "position").unwrap();
+        let sid_dot = g.output_slot(nid_dot, "result").unwrap();
+        let sid_add = g.output_slot(nid_add, "result").unwrap();
+
+        assert_eq!(
+            g.slot_value_type(sid_pos),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+        assert!(g.slot_is_vector(sid_pos));
+        assert_eq!(g.slot_component_count(sid_pos), Some(3));
+
+        assert_eq!(
+            g.slot_value_type(sid_dot),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+        assert!(!g.slot_is_vector(sid_dot));
+        assert_eq!(g.slot_component_count(sid_dot), Some(1));
+
+        // Variant slots use the inferred type
+        assert_eq!(g.slot_value_type(sid_add), None);
+        assert!(!g.slot_is_vector(sid_add));
+        assert_eq!(g.slot_component_count(sid_add), None);
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_pos, "position", nid_add, "rhs").unwrap();
+        assert_eq!(g.slot_component_count(sid_add), Some(3));
+
+        // Invalid slots
+        let sid_bad = SlotId::new(NonZeroU32::new(999).unwrap());
+        assert_eq!(g.slot_value_type(sid_bad), None);
+        assert!(!g.slot_is_vector(sid_bad));
+        assert_eq!(g.slot_component_count(sid_bad), None);
+    }
+
+    #[test]
+    fn graph_clear() {
+        let mut g = Graph::new();
+        assert!(g.is_empty());
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        g.connect(nid_time, "time", nid_sin, "in").unwrap();
+        g.set_node_label(nid_sin, "wave");
+        assert!(!g.is_empty());
+
+        // Removed nodes don't count
+        let mut g2 = g.clone();
+        g2.remove_node(nid_time);
+        g2.remove_node(nid_sin);
+        assert!(g2.is_empty());
+
+        g.clear();
+        assert!(g.is_empty());
+        assert_eq!(g.node_count(), 0);
+        assert!(g.links().is_empty());
+
+        // Identifiers restart from one, like in a new graph
+        let nid = g.add_node(TimeNode::default());
+        assert_eq!(nid.id().get(), 1);
+        assert_eq!(g.slots(nid)[0].id().get(), 1);
+        assert_eq!(g.node_label(nid), None);
+        let mut fresh = Graph::new();
+        assert_eq!(fresh.add_node(TimeNode::default()), nid);
+    }
+
+    #[test]
+    fn graph_adjacency() {
+        // Same graph as the `graph` test
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_mul, "lhs").unwrap();
+        g.connect(nid_dt, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+
+        assert_eq!(g.incoming_nodes(nid_mul), vec![nid_vel, nid_dt]);
+        assert_eq!(g.outgoing_nodes(nid_mul), vec![nid_add]);
+        assert_eq!(g.incoming_nodes(nid_add), vec![nid_pos, nid_mul]);
+        assert!(g.outgoing_nodes(nid_add).is_empty());
+        assert!(g.incoming_nodes(nid_dt).is_empty());
+
+        // Nodes linked through several slots are listed once
+        let nid_sub = g.add_node(SubNode::default());
+        g.connect(nid_pos, "position", nid_sub, "lhs").unwrap();
+        g.connect(nid_pos, "position", nid_sub, "rhs").unwrap();
+        let nid_cross = g.add_node(CrossNode::default());
+        g.connect(nid_sub, "result", nid_cross, "lhs").unwrap();
+        g.connect(nid_sub, "result", nid_cross, "rhs").unwrap();
+        assert_eq!(g.incoming_nodes(nid_sub), vec![nid_pos]);
+        assert_eq!(g.outgoing_nodes(nid_sub), vec![nid_cross]);
+        assert_eq!(g.outgoing_nodes(nid_pos), vec![nid_add, nid_sub]);
+    }
+
+    #[test]
+    fn graph_connect() {
+        // Same graph as the `graph` test, linking slots by name
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_mul, "lhs").unwrap();
+        g.connect(nid_dt, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+
+        assert_eq!(
+            g.links(),
+            vec![
+                (g.output_slots(nid_pos)[0], g.input_slots(nid_add)[0]),
+                (g.output_slots(nid_vel)[0], g.input_slots(nid_mul)[0]),
+                (g.output_slots(nid_mul)[0], g.input_slots(nid_add)[1]),
+                (
+                    g.output_slot(nid_dt, "delta_time").unwrap(),
+                    g.input_slots(nid_mul)[1]
+                ),
+            ]
+        );
+
+        // Unknown slot names, or names of slots in the wrong direction, are
+        // rejected without editing the graph
+        let links = g.links();
+        assert_eq!(
+            g.connect(nid_pos, "velocity", nid_add, "lhs"),
+            Err(GraphError::SlotNotFound {
+                dir: SlotDir::Output
+            })
+        );
+        assert_eq!(
+            g.connect(nid_add, "lhs", nid_mul, "lhs"),
+            Err(GraphError::SlotNotFound {
+                dir: SlotDir::Output
+            })
+        );
+        assert_eq!(
+            g.connect(nid_pos, "position", nid_add, "result"),
+            Err(GraphError::SlotNotFound {
+                dir: SlotDir::Input
+            })
+        );
+        assert_eq!(g.links(), links);
+
+        // Links are checked like with try_link()
+        assert!(matches!(
+            g.connect(nid_vel, "velocity", nid_add, "lhs"),
+            Err(GraphError::InputAlreadyLinked { .. })
+        ));
+    }
+
+    #[test]
+    fn graph_required_attributes() {
+        let mut g = Graph::new();
+        assert!(g.required_attributes().is_empty());
+        assert!(g.required_properties().is_empty());
+
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_set = g.add_node(SetAttributeNode::new(
+            ModifierContext::Update,
+            Attribute::POSITION,
+        ));
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_add, "rhs").unwrap();
+        g.connect(nid_add, "result", nid_set, "position").unwrap();
+        assert_eq!(
+            g.required_attributes(),
+            HashSet::from([Attribute::POSITION, Attribute::VELOCITY])
+        );
+
+        // Written attributes, previous values, and modifier attributes are included
+        g.add_node(SetAttributeNode::new(
+            ModifierContext::Init,
+            Attribute::LIFETIME,
+        ));
+        g.add_node(PrevAttributeNode::new(Attribute::AGE));
+        assert_eq!(
+            g.required_attributes(),
+            HashSet::from([
+                Attribute::POSITION,
+                Attribute::VELOCITY,
+                Attribute::LIFETIME,
+                Attribute::AGE,
+            ])
+        );
+        let mut m = Module::default();
+        let drag = m.lit(1.);
+        let mut g_drag = Graph::new();
+        g_drag.add_node(ModifierNode::new(
+            ModifierContext::Update,
+            LinearDragModifier::new(drag),
+        ));
+        assert_eq!(
+            g_drag.required_attributes(),
+            HashSet::from([Attribute::VELOCITY])
+        );
+
+        // Properties are reported by name, once each
+        g.add_node(PropertyNode::new(
+            "speed",
+            ValueType::Scalar(ScalarType::Float),
+        ));
+        g.add_node(PropertyNode::new(
+            "speed",
+            ValueType::Scalar(ScalarType::Float),
+        ));
+        g.add_node(PropertyNode::new(
+            "color",
+            ValueType::Vector(VectorType::VEC4F),
+        ));
+        assert_eq!(
+            g.required_properties(),
+            HashSet::from(["speed".to_string(), "color".to_string()])
+        );
+    }
+
+    #[test]
+    fn graph_unlink_node() {
+        // Same graph as the `graph` test
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_mul, "lhs").unwrap();
+        g.connect(nid_dt, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_add, "rhs").unwrap();
+        let sid_pos = g.output_slot(nid_pos, "position").unwrap();
+        let sid_vel = g.output_slot(nid_vel, "velocity").unwrap();
+        let sid_dt = g.output_slot(nid_dt, "delta_time").unwrap();
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_mul_lhs = g.input_slot(nid_mul, "lhs").unwrap();
+        let sid_mul_rhs = g.input_slot(nid_mul, "rhs").unwrap();
+
+        // Unlinking the multiply node disconnects both its inputs and the add input
+        // it fed, but leaves the other links and the node itself
+        g.unlink_node(nid_mul);
+        assert!(g.node(nid_mul).is_some());
+        assert_eq!(g.input_source(sid_mul_lhs), None);
+        assert_eq!(g.input_source(sid_mul_rhs), None);
+        assert_eq!(g.input_source(sid_add_rhs), None);
+        assert!(g.output_targets(sid_vel).is_empty());
+        assert!(g.output_targets(sid_dt).is_empty());
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_pos));
+        assert_eq!(g.links(), vec![(sid_pos, sid_add_lhs)]);
+
+        // Unlinking an already disconnected node is a no-op
+        g.unlink_node(nid_mul);
+        assert_eq!(g.links(), vec![(sid_pos, sid_add_lhs)]);
+    }
+
+    #[test]
+    fn graph_validate() {
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_spherical = g.add_node(SphericalToCartesianNode::default());
+        let sid_pos = g.output_slot(nid_pos, "position").unwrap();
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        g.link(sid_pos, sid_add_lhs);
+        for name in ["theta", "phi", "radius"] {
+            g.link(sid_add_out, g.input_slot(nid_spherical, name).unwrap());
+        }
+        assert_eq!(
+            g.validate(),
+            vec![GraphError::UnlinkedInput {
+                node: nid_add,
+                slot: sid_add_rhs
+            }]
+        );
+
+        // Simulate a type mismatch, as loaded from a hand-edited file, which
+        // try_link() would reject.
+        let sid_theta = g.input_slot(nid_spherical, "theta").unwrap();
+        g.unlink(sid_add_out, sid_theta);
+        g.get_slot_mut(sid_pos).link_to(sid_theta);
+        g.get_slot_mut(sid_theta).link_input(sid_pos);
+        let errors = g.validate();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.contains(&GraphError::UnlinkedInput {
+            node: nid_add,
+            slot: sid_add_rhs
+        }));
+        assert!(errors.contains(&GraphError::TypeMismatch {
+            output_slot: sid_pos,
+            input_slot: sid_theta,
+            output: ValueType::Vector(VectorType::VEC3F),
+            input: ValueType::Scalar(ScalarType::Float),
+        }));
+
+        // Fixing the graph clears all errors
+        g.relink(sid_add_out, sid_theta).unwrap();
+        g.link(sid_pos, sid_add_rhs);
+        assert!(g.validate().is_empty());
+
+        // Simulate a cycle A -> B -> A
+        let nid_a = g.add_node(AddNode::default());
+        let nid_b = g.add_node(AddNode::default());
+        let a_out = g.output_slot(nid_a, "result").unwrap();
+        let b_out = g.output_slot(nid_b, "result").unwrap();
+        let a_lhs = g.input_slot(nid_a, "lhs").unwrap();
+        g.link(sid_pos, g.input_slot(nid_a, "rhs").unwrap());
+        g.link(sid_pos, g.input_slot(nid_b, "rhs").unwrap());
+        g.link(a_out, g.input_slot(nid_b, "lhs").unwrap());
+        g.get_slot_mut(b_out).link_to(a_lhs);
+        g.get_slot_mut(a_lhs).link_input(b_out);
+        assert_eq!(
+            g.validate(),
+            vec![
+                GraphError::CycleDetected {
+                    from: nid_b,
+                    to: nid_a
+                },
+                GraphError::CycleDetected {
+                    from: nid_a,
+                    to: nid_b
+                },
+            ]
+        );
+
+        // A node whose input slot went missing from the graph, as loaded from a
+        // hand-edited file, can't be evaluated.
+        let mut g = Graph::new();
+        let nid_add = g.add_node(AddNode::default());
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        g.slots[sid_add_rhs.index()] = None;
+        assert_eq!(
+            g.validate(),
+            vec![
+                GraphError::UnlinkedInput {
+                    node: nid_add,
+                    slot: g.input_slot(nid_add, "lhs").unwrap()
+                },
+                GraphError::InvalidInputCount {
+                    node: nid_add,
+                    inputs: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_validate_generic_output() {
+        // The untyped output of a generic node feeding another generic node is
+        // valid, and doesn't need to be evaluated to be validated.
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_dist = g.add_node(DistanceNode::default());
+        g.connect(nid_pos, "position", nid_add, "lhs").unwrap();
+        g.connect(nid_vel, "velocity", nid_add, "rhs").unwrap();
+        g.connect(nid_add, "result", nid_dist, "a").unwrap();
+        g.connect(nid_pos, "position", nid_dist, "b").unwrap();
+        assert!(g.validate().is_empty());
+
+        // Unlinked inputs are only reported once each
+        g.unlink_node(nid_add);
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_dist_a = g.input_slot(nid_dist, "a").unwrap();
+        assert_eq!(
+            g.validate(),
+            vec![
+                GraphError::UnlinkedInput {
+                    node: nid_add,
+                    slot: sid_add_lhs
+                },
+                GraphError::UnlinkedInput {
+                    node: nid_add,
+                    slot: sid_add_rhs
+                },
+                GraphError::UnlinkedInput {
+                    node: nid_dist,
+                    slot: sid_dist_a
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_node_metadata() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        g.connect(nid_time, "time", nid_mul, "lhs").unwrap();
+        g.connect(nid_time, "delta_time", nid_mul, "rhs").unwrap();
+        g.connect(nid_mul, "result", nid_sin, "in").unwrap();
+        let mut module = Module::default();
+        g.eval_all(&mut module).unwrap();
+        let plain_module = module;
+
+        assert_eq!(g.node_position(nid_mul), None);
+        assert_eq!(g.node_label(nid_mul), None);
+        g.set_node_position(nid_mul, Vec2::new(200., 50.));
+        g.set_node_label(nid_mul, "Wobble");
+        g.set_node_label(nid_sin, "Wobble");
+        assert_eq!(g.node_position(nid_mul), Some(Vec2::new(200., 50.)));
+        assert_eq!(g.node_position(nid_sin), None);
+        assert_eq!(g.node_label(nid_mul), Some("Wobble"));
+        assert_eq!(g.node_label(nid_sin), Some("Wobble"));
+
+        // Metadata doesn't affect the evaluation
+        let mut module = Module::default();
+        g.eval_all(&mut module).unwrap();
+        assert_eq!(module, plain_module);
+
+        // Labels are independent, even when equal
+        g.set_node_label(nid_sin, "Sine");
+        assert_eq!(g.node_label(nid_mul), Some("Wobble"));
+        assert_eq!(g.clear_node_label(nid_sin), Some("Sine".to_string()));
+        assert_eq!(g.node_label(nid_sin), None);
+        assert_eq!(g.node_label(nid_mul), Some("Wobble"));
+
+        // Metadata follows the nodes through cloning and compaction
+        let mut g2 = g.clone();
+        g2.remove_node(nid_time);
+        let mapping = g2.compact();
+        let nid_mul2 = mapping.node(nid_mul).unwrap();
+        assert_ne!(nid_mul2, nid_mul);
+        assert_eq!(g2.node_position(nid_mul2), Some(Vec2::new(200., 50.)));
+        assert_eq!(g2.node_label(nid_mul2), Some("Wobble"));
+        assert_eq!(g2.node_label(mapping.node(nid_sin).unwrap()), None);
+
+        // Metadata is removed along with its node
+        g.remove_node(nid_mul);
+        assert_eq!(g.node_position(nid_mul), None);
+        assert_eq!(g.node_label(nid_mul), None);
+
+        // Metadata round-trips through serialization
+        #[cfg(feature = "serde")]
+        {
+            let s = ron::to_string(&g2).unwrap();
+            let g_serde: Graph = ron::from_str(&s).unwrap();
+            assert_eq!(g_serde.node_position(nid_mul2), Some(Vec2::new(200., 50.)));
+            assert_eq!(g_serde.node_label(nid_mul2), Some("Wobble"));
+            assert_eq!(g_serde.node_label(mapping.node(nid_sin).unwrap()), None);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn graph_serde() {
+        let mut g = Graph::new();
+
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let sid_pos = g.output_slots(nid_pos)[0];
+        let sid_add_lhs = g.input_slots(nid_add)[0];
+        let sid_add_rhs = g.input_slots(nid_add)[1];
+        g.link(sid_pos, sid_add_lhs);
+
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_dt = g.add_node(TimeNode::default());
+        let sid_vel = g.output_slots(nid_vel)[0];
+        let sid_dt = g
+            .output_slot(nid_dt, BuiltInOperator::DeltaTime.name())
+            .unwrap();
+        let sid_mul_lhs = g.input_slots(nid_mul)[0];
+        let sid_mul_rhs = g.input_slots(nid_mul)[1];
+        g.link(sid_vel, sid_mul_lhs);
+        g.link(sid_dt, sid_mul_rhs);
+
+        let sid_mul_out = g.output_slots(nid_mul)[0];
+        g.link(sid_mul_out, sid_add_rhs);
+
+        let s = ron::to_string(&g).unwrap();
+        let g_serde: Graph = ron::from_str(&s).unwrap();
+        assert_eq!(g_serde.node_count(), 5);
+        assert_eq!(g_serde.node(nid_pos).unwrap().name(), "AttributeNode");
+        assert_eq!(g_serde.node(nid_dt).unwrap().name(), "TimeNode");
+        assert_eq!(g_serde.input_source(sid_add_lhs), Some(sid_pos));
+        assert_eq!(g_serde.input_source(sid_add_rhs), Some(sid_mul_out));
+        assert_eq!(g_serde.input_source(sid_mul_lhs), Some(sid_vel));
+        assert_eq!(g_serde.input_source(sid_mul_rhs), Some(sid_dt));
+        assert_eq!(format!("{:?}", g_serde), format!("{:?}", g));
+
+        // The deserialized nodes are functional
+        let mut module = Module::default();
+        let outputs = g_serde.eval_all(&mut module).unwrap();
+        assert!(outputs.contains_key(&g_serde.output_slots(nid_add)[0]));
+    }
+
+    #[test]
+    fn graph_to_effect_asset() {
+        // position = position + velocity * dt
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_dt = g.add_node(TimeNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        g.link(
+            g.output_slots(nid_vel)[0],
+            g.input_slot(nid_mul, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_dt, BuiltInOperator::DeltaTime.name())
+                .unwrap(),
+            g.input_slot(nid_mul, "rhs").unwrap(),
+        );
+        g.link(
+            g.output_slots(nid_pos)[0],
+            g.input_slot(nid_add, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_mul, "result").unwrap(),
+            g.input_slot(nid_add, "rhs").unwrap(),
+        );
+
+        // No modifier node yet
+        assert!(matches!(
+            g.to_effect_asset("effect", 32),
+            Err(ExprError::GraphEvalError(_))
+        ));
+
+        // Unlinked modifier input
+        let nid_set_pos = g.add_node(SetAttributeNode::new(
+            ModifierContext::Update,
+            Attribute::POSITION,
+        ));
+        let Err(ExprError::GraphEvalError(msg)) = g.to_effect_asset("effect", 32) else {
+            panic!("Expected GraphEvalError");
+        };
+        assert!(msg.contains(&format!("(#{})", nid_set_pos.id())));
+
+        g.link(
+            g.output_slot(nid_add, "result").unwrap(),
+            g.input_slot(nid_set_pos, Attribute::POSITION.name())
+                .unwrap(),
+        );
+        g.add_node(SpawnerNode::new(Spawner::rate(16.0.into())));
+        let asset = g.to_effect_asset("effect", 32).unwrap();
+        assert_eq!(asset.name, "effect");
+        assert_eq!(asset.capacities(), &[32]);
+        assert_eq!(asset.init_modifiers().count(), 0);
+        assert_eq!(asset.update_modifiers().count(), 1);
+        assert_eq!(asset.render_modifiers().count(), 0);
+        assert_eq!(asset.init, vec![Spawner::rate(16.0.into()).into()]);
+
+        // The modifier assigns the expression built from the graph
+        let modifier = asset.update_modifiers().next().unwrap();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = asset.particle_layout();
+        let mut module = asset.module().clone();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        assert_eq!(
+            context.main_code,
+            format!(
+                "particle.{0} = (particle.{0}) + ((particle.{1}) * (sim_params.delta_time));\n",
+                Attribute::POSITION.name(),
+                Attribute::VELOCITY.name()
+            )
+        );
+    }
+
+    #[test]
+    fn modifier_node() {
+        let mut m = Module::default();
+        let placeholder = m.lit(0.);
+
+        let node = ModifierNode::new(ModifierContext::Update, AccelModifier::new(placeholder));
+        assert_eq!(node.slots().len(), 1);
+        assert_eq!(node.slots()[0].name(), "accel");
+        assert!(node.slots()[0].is_input());
+        assert!(node.is_terminal());
+
+        let node = ModifierNode::new(
+            ModifierContext::Update,
+            AccelModifier::new(placeholder).with_max_speed(placeholder),
+        );
+        assert_eq!(node.slots().len(), 2);
+        assert_eq!(node.slots()[1].name(), "max_speed");
+        let ret = node.eval(&mut m, vec![placeholder]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let mut g = Graph::new();
+        let nid_accel = g.add_node(LiteralNode::new(Vec3::new(0., -9.8, 0.)));
+        let nid_max_speed = g.add_node(LiteralNode::new(5.));
+        let nid_modifier = g.add_node(node);
+        g.link(
+            g.output_slots(nid_accel)[0],
+            g.input_slot(nid_modifier, "accel").unwrap(),
+        );
+        g.link(
+            g.output_slots(nid_max_speed)[0],
+            g.input_slot(nid_modifier, "max_speed").unwrap(),
+        );
+        let asset = g.to_effect_asset("effect", 32).unwrap();
+        assert_eq!(asset.update_modifiers().count(), 1);
+
+        // The modifier reads the expressions linked to the node
+        let modifier = asset.update_modifiers().next().unwrap();
+        let modifier = modifier
+            .as_reflect()
+            .downcast_ref::<AccelModifier>()
+            .unwrap();
+        let max_speed = modifier.max_speed().unwrap();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = asset.particle_layout();
+        let mut module = asset.module().clone();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(context.eval(&module, max_speed).unwrap(), "5.");
+        modifier.apply(&mut module, &mut context).unwrap();
+        assert!(context.main_code.starts_with(&format!(
+            "particle.{} += (vec3<f32>(0.,-9.8,0.)) * sim_params.delta_time;",
+            Attribute::VELOCITY.name()
+        )));
+    }
+
+    #[test]
+    #[should_panic]
+    fn modifier_node_invalid_context() {
+        let mut m = Module::default();
+        let accel = m.lit(Vec3::ZERO);
+        let _ = ModifierNode::new(ModifierContext::Init, AccelModifier::new(accel));
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_attribute_node_render_context() {
+        let _ = SetAttributeNode::new(ModifierContext::Render, Attribute::POSITION);
+    }
+
+    #[test]
+    fn graph_link_queries() {
+        let mut g = Graph::new();
+
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let sid_time = g
+            .output_slot(nid_time, BuiltInOperator::Time.name())
+            .unwrap();
+        let sid_dt = g
+            .output_slot(nid_time, BuiltInOperator::DeltaTime.name())
+            .unwrap();
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        let sid_mul_lhs = g.input_slot(nid_mul, "lhs").unwrap();
+
+        // Unconnected slots
+        assert_eq!(g.input_source(sid_add_lhs), None);
+        assert!(g.output_targets(sid_time).is_empty());
+
+        // Single link, visible from both ends
+        g.link(sid_time, sid_add_lhs);
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_time));
+        assert_eq!(g.output_targets(sid_time), vec![sid_add_lhs]);
+        assert_eq!(g.input_source(sid_add_rhs), None);
+        assert!(g.output_targets(sid_dt).is_empty());
+        assert!(g.output_targets(sid_add_out).is_empty());
+
+        // Output fanning out to multiple inputs, in link order
+        g.link(sid_time, sid_mul_lhs);
+        assert_eq!(g.output_targets(sid_time), vec![sid_add_lhs, sid_mul_lhs]);
+        assert_eq!(g.input_source(sid_mul_lhs), Some(sid_time));
+
+        // Relinking an input replaces its source
+        g.relink(sid_dt, sid_add_lhs).unwrap();
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_dt));
+        assert_eq!(g.output_targets(sid_dt), vec![sid_add_lhs]);
+        assert_eq!(g.output_targets(sid_time), vec![sid_mul_lhs]);
+
+        // Unlinking clears both ends
+        g.unlink(sid_time, sid_mul_lhs);
+        assert_eq!(g.input_source(sid_mul_lhs), None);
+        assert!(g.output_targets(sid_time).is_empty());
+        g.unlink_all(sid_add_lhs);
+        assert_eq!(g.input_source(sid_add_lhs), None);
+        assert!(g.output_targets(sid_dt).is_empty());
+    }
+
+    #[test]
+    fn graph_link_input_already_linked() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        let sid_time = g
+            .output_slot(nid_time, BuiltInOperator::Time.name())
+            .unwrap();
+        let sid_dt = g
+            .output_slot(nid_time, BuiltInOperator::DeltaTime.name())
+            .unwrap();
+        let sid_sin_in = g.input_slot(nid_sin, "in").unwrap();
+        assert!(g.try_link(sid_time, sid_sin_in).is_ok());
+
+        // Linking the same output again does nothing
+        assert!(g.try_link(sid_time, sid_sin_in).is_ok());
+        assert_eq!(g.output_targets(sid_time), vec![sid_sin_in]);
+
+        // Linking another output is rejected, and reports the existing link
+        assert_eq!(
+            g.try_link(sid_dt, sid_sin_in),
+            Err(GraphError::InputAlreadyLinked {
+                input: sid_sin_in,
+                existing: sid_time
+            })
+        );
+        assert_eq!(g.input_source(sid_sin_in), Some(sid_time));
+        assert!(g.output_targets(sid_dt).is_empty());
+
+        // Once unlinked, the input accepts another output
+        g.unlink(sid_time, sid_sin_in);
+        assert!(g.try_link(sid_dt, sid_sin_in).is_ok());
+        assert_eq!(g.input_source(sid_sin_in), Some(sid_dt));
+
+        // Relinking replaces the existing link
+        assert!(g.relink(sid_time, sid_sin_in).is_ok());
+        assert_eq!(g.input_source(sid_sin_in), Some(sid_time));
+        assert!(g.output_targets(sid_dt).is_empty());
+
+        // A constant is replaced by a link
+        g.set_input_constant(sid_sin_in, 1_f32.into());
+        assert!(g.try_link(sid_dt, sid_sin_in).is_ok());
+        assert_eq!(g.input_constant(sid_sin_in), None);
+        assert_eq!(g.input_source(sid_sin_in), Some(sid_dt));
+    }
+
+    #[test]
+    #[should_panic]
+    fn graph_input_source_of_output() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let sid_time = g.output_slots(nid_time)[0];
+        g.input_source(sid_time);
+    }
+
+    #[test]
+    #[should_panic]
+    fn graph_output_targets_of_input() {
+        let mut g = Graph::new();
+        let nid_add = g.add_node(AddNode::default());
+        let sid_add_lhs = g.input_slots(nid_add)[0];
+        g.output_targets(sid_add_lhs);
+    }
+
+    #[test]
+    fn graph_insert_node_between() {
+        let mut g = Graph::new();
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_add = g.add_node(AddNode::default());
+        let sid_vel = g.output_slots(nid_vel)[0];
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        g.link(sid_vel, sid_add_lhs);
+
+        // Invalid link or slot names leave the graph unchanged
+        assert_eq!(
+            g.insert_node_between((sid_vel, sid_add_rhs), MulNode::default(), "lhs", "result"),
+            Err(GraphError::LinkNotFound {
+                output: sid_vel,
+                input: sid_add_rhs
+            })
+        );
+        assert_eq!(
+            g.insert_node_between((sid_vel, sid_add_lhs), MulNode::default(), "foo", "result"),
+            Err(GraphError::SlotNotFound {
+                dir: SlotDir::Input
+            })
+        );
+        assert_eq!(
+            g.insert_node_between((sid_vel, sid_add_lhs), MulNode::default(), "lhs", "rhs"),
+            Err(GraphError::SlotNotFound {
+                dir: SlotDir::Output
+            })
+        );
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_vel));
+
+        // vel -> add becomes vel -> mul -> add
+        let nid_mul = g
+            .insert_node_between((sid_vel, sid_add_lhs), MulNode::default(), "lhs", "result")
+            .unwrap();
+        assert_eq!(g.node_count(), 3);
+        let sid_mul_lhs = g.input_slot(nid_mul, "lhs").unwrap();
+        let sid_mul_rhs = g.input_slot(nid_mul, "rhs").unwrap();
+        let sid_mul_out = g.output_slot(nid_mul, "result").unwrap();
+        assert_eq!(g.input_source(sid_mul_lhs), Some(sid_vel));
+        assert_eq!(g.input_source(sid_mul_rhs), None);
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_mul_out));
+        assert_eq!(g.output_targets(sid_vel), vec![sid_mul_lhs]);
+        assert_eq!(g.output_targets(sid_mul_out), vec![sid_add_lhs]);
+
+        // Typed slots are checked like try_link()
+        let nid_sdf = g.add_node(SphereSdfNode::default());
+        let sid_point = g.input_slot(nid_sdf, "point").unwrap();
+        g.link(sid_vel, sid_point);
+        assert!(matches!(
+            g.insert_node_between(
+                (sid_vel, sid_point),
+                LengthNode::default(),
+                "in",
+                "length"
+            ),
+            Err(GraphError::TypeMismatch {
+                input_slot,
+                output: ValueType::Scalar(ScalarType::Float),
+                input: ValueType::Vector(VectorType::VEC3F),
+                ..
+            }) if input_slot == sid_point
+        ));
+        assert_eq!(g.node_count(), 4);
+        assert_eq!(g.input_source(sid_point), Some(sid_vel));
+    }
+
+    #[test]
+    fn graph_insert_node_between_constant() {
+        let mut g = Graph::new();
+        let nid_add = g.add_node(AddNode::default());
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        g.set_input_constant(sid_add_lhs, Value::Scalar(2.0.into()));
+        let sid_const = g.input_source(sid_add_lhs).unwrap();
+
+        // const -> add becomes const -> sin -> add
+        let nid_sin = g
+            .insert_node_between((sid_const, sid_add_lhs), SinNode::default(), "in", "out")
+            .unwrap();
+        let sid_sin_in = g.input_slot(nid_sin, "in").unwrap();
+        let sid_sin_out = g.output_slot(nid_sin, "out").unwrap();
+        assert_eq!(g.input_constant(sid_add_lhs), None);
+        assert_eq!(
+            g.input_constant(sid_sin_in),
+            Some(Value::Scalar(2.0.into()))
+        );
+        assert_eq!(g.input_source(sid_add_lhs), Some(sid_sin_out));
+        let sid_new_const = g.input_source(sid_sin_in).unwrap();
+        assert!(g.is_input_constant_node(g.get_slot(sid_new_const).node_id()));
+        // Only the add, the sin, and the hidden literal remain
+        assert_eq!(g.node_count(), 3);
+    }
+
+    #[test]
+    fn graph_insert_node_between_invalid_slots() {
+        let mut g = Graph::new();
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_add = g.add_node(AddNode::default());
+        let sid_vel = g.output_slots(nid_vel)[0];
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        g.link(sid_vel, sid_add_lhs);
+
+        // Swapped directions are rejected instead of panicking
+        assert_eq!(
+            g.insert_node_between((sid_add_lhs, sid_vel), MulNode::default(), "lhs", "result"),
+            Err(GraphError::WrongDirection {
+                slot: sid_add_lhs,
+                expected: SlotDir::Output,
+            })
+        );
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        assert_eq!(
+            g.insert_node_between((sid_vel, sid_add_out), MulNode::default(), "lhs", "result"),
+            Err(GraphError::WrongDirection {
+                slot: sid_add_out,
+                expected: SlotDir::Input,
+            })
+        );
+
+        // Slots of removed nodes are rejected too
+        g.remove_node(nid_vel);
+        assert_eq!(
+            g.insert_node_between((sid_vel, sid_add_lhs), MulNode::default(), "lhs", "result"),
+            Err(GraphError::NoSuchSlot(sid_vel))
+        );
+        assert_eq!(g.node_count(), 1);
+    }
+
+    #[test]
+    fn graph_eval() {
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_dt = g.add_node(TimeNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let sid_mul_out = g.output_slot(nid_mul, "result").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        g.link(
+            g.output_slots(nid_vel)[0],
+            g.input_slot(nid_mul, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_dt, BuiltInOperator::DeltaTime.name())
+                .unwrap(),
+            g.input_slot(nid_mul, "rhs").unwrap(),
+        );
+        // Link the rhs first, to check inputs are ordered by slot and not by link
+        g.link(sid_mul_out, g.input_slot(nid_add, "rhs").unwrap());
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Only upstream nodes are evaluated, so the unlinked input of the
+        // downstream AddNode doesn't matter.
+        let mut module = Module::default();
+        let mul = g.eval(&mut module, sid_mul_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, mul).unwrap();
+        assert_eq!(str, "(particle.velocity) * (sim_params.delta_time)");
+
+        // Unlinked input
+        let mut module = Module::default();
+        let err = g.eval(&mut module, sid_add_out).unwrap_err();
+        assert_eq!(
+            err,
+            ExprError::GraphEvalError(format!(
+                "AddNode(#{}): input slot 'lhs' is not linked",
+                nid_add.id()
+            ))
+        );
+
+        g.link(
+            g.output_slots(nid_pos)[0],
+            g.input_slot(nid_add, "lhs").unwrap(),
+        );
+        let mut module = Module::default();
+        let add = g.eval(&mut module, sid_add_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, add).unwrap();
+        assert_eq!(
+            str,
+            "(particle.position) + ((particle.velocity) * (sim_params.delta_time))"
+        );
+
+        // Each node is evaluated once, even if used by several downstream nodes
+        let nid_sq = g.add_node(MulNode::default());
+        let sid_sq_out = g.output_slot(nid_sq, "result").unwrap();
+        g.link(sid_add_out, g.input_slot(nid_sq, "lhs").unwrap());
+        g.link(sid_add_out, g.input_slot(nid_sq, "rhs").unwrap());
+        let mut module = Module::default();
+        let sq = g.eval(&mut module, sid_sq_out).unwrap();
+        let Some(Expr::Binary { left, right, .. }) = module.get(sq) else {
+            panic!("Unexpected expression");
+        };
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn graph_input_constant() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let mut g = Graph::new();
+        let nid_add = g.add_node(AddNode::default());
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let sid_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        assert_eq!(g.input_constant(sid_lhs), None);
+
+        g.set_input_constant(sid_lhs, 3_f32.into());
+        g.set_input_constant(sid_rhs, 2_f32.into());
+        assert_eq!(g.input_constant(sid_lhs), Some(3_f32.into()));
+        assert_eq!(g.node_count(), 4);
+        let nid_lit = g.get_slot(g.input_source(sid_lhs).unwrap()).node_id();
+        assert!(g.is_input_constant_node(nid_lit));
+        assert!(!g.is_input_constant_node(nid_add));
+
+        let mut module = Module::default();
+        let add = g.eval(&mut module, sid_add_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(context.eval(&module, add).unwrap(), "(3.) + (2.)");
+
+        // Setting again updates the same hidden node in place, even with a new type
+        g.set_input_constant(sid_lhs, Vec3::ONE.into());
+        g.set_input_constant(sid_rhs, Vec3::ZERO.into());
+        assert_eq!(g.node_count(), 4);
+        assert_eq!(
+            g.get_slot(g.input_source(sid_lhs).unwrap()).node_id(),
+            nid_lit
+        );
+        assert_eq!(
+            g.infer_slot_type(sid_add_out),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+        let mut module = Module::default();
+        let add = g.eval(&mut module, sid_add_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, add).unwrap(),
+            "(vec3<f32>(1.,1.,1.)) + (vec3<f32>(0.,0.,0.))"
+        );
+
+        // Linking another output replaces the constant
+        g.link(g.output_slot(nid_pos, "position").unwrap(), sid_rhs);
+        assert_eq!(g.input_constant(sid_rhs), None);
+        assert_eq!(g.node_count(), 3);
+
+        // Clearing removes the hidden node and leaves the input unlinked
+        assert_eq!(g.clear_input_constant(sid_lhs), Some(Vec3::ONE.into()));
+        assert_eq!(g.clear_input_constant(sid_lhs), None);
+        assert_eq!(g.input_source(sid_lhs), None);
+        assert_eq!(g.node_count(), 2);
+        assert!(!g.is_input_constant_node(nid_lit));
+
+        // Constants survive compaction
+        g.set_input_constant(sid_lhs, 1_f32.into());
+        g.remove_node(nid_pos);
+        let mapping = g.compact();
+        let sid_lhs = mapping.slot(sid_lhs).unwrap();
+        assert_eq!(g.input_constant(sid_lhs), Some(1_f32.into()));
+        let nid_lit = g.get_slot(g.input_source(sid_lhs).unwrap()).node_id();
+        assert!(g.is_input_constant_node(nid_lit));
+
+        // Removing the owner node removes its constants
+        g.remove_node(mapping.node(nid_add).unwrap());
+        assert_eq!(g.node_count(), 0);
+    }
+
+    #[test]
+    fn graph_eval_folded() {
+        let mut g = Graph::new();
+        let nid_three = g.add_node(LiteralNode::new(3_f32));
+        let nid_two = g.add_node(LiteralNode::new(2_f32));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_age = g.add_node(AttributeNode::new(Attribute::AGE));
+        let nid_mul = g.add_node(MulNode::default());
+        g.link(
+            g.output_slot(nid_three, "value").unwrap(),
+            g.input_slot(nid_add, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_two, "value").unwrap(),
+            g.input_slot(nid_add, "rhs").unwrap(),
+        );
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        g.link(sid_add_out, g.input_slot(nid_mul, "lhs").unwrap());
+        g.link(
+            g.output_slot(nid_age, "age").unwrap(),
+            g.input_slot(nid_mul, "rhs").unwrap(),
+        );
+        let sid_mul_out = g.output_slot(nid_mul, "result").unwrap();
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Without folding, the sum is evaluated by the shader
+        let mut module = Module::default();
+        let add = g.eval(&mut module, sid_add_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(context.eval(&module, add).unwrap(), "(3.) + (2.)");
+
+        // With folding, the sum is a single literal
+        let mut module = Module::default();
+        let add = g.eval_folded(&mut module, sid_add_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(context.eval(&module, add).unwrap(), "5.");
+
+        // Operations with a non-literal operand are kept
+        let mut module = Module::default();
+        let mul = g.eval_folded(&mut module, sid_mul_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(context.eval(&module, mul).unwrap(), "(5.) * (particle.age)");
+    }
+
+    #[test]
+    fn graph_eval_all() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let sid_time = g
+            .output_slot(nid_time, BuiltInOperator::Time.name())
+            .unwrap();
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        g.link(sid_time, sid_add_lhs);
+
+        // Misconfigured node with an unlinked input
+        let mut module = Module::default();
+        let err = g.eval_all(&mut module).unwrap_err();
+        let ExprError::GraphEvalError(msg) = err else {
+            panic!("Unexpected error {:?}", err);
+        };
+        assert_eq!(
+            msg,
+            format!(
+                "AddNode(#{}): expected 2 inputs, got 1 (unlinked input slots: rhs)",
+                nid_add.id()
+            )
+        );
+
+        // The node label, if any, is part of the error
+        g.set_node_label(nid_add, "gravity");
+        let mut module = Module::default();
+        let err = g.eval_all(&mut module).unwrap_err();
+        assert_eq!(
+            err,
+            ExprError::GraphEvalError(format!(
+                "AddNode(#{} 'gravity'): expected 2 inputs, got 1 (unlinked input slots: rhs)",
+                nid_add.id()
+            ))
+        );
+
+        // Fixed graph
+        g.link(sid_time, sid_add_rhs);
+        let mut module = Module::default();
+        let outputs = g.eval_all(&mut module).unwrap();
+        assert_eq!(outputs.len(), 3); // time, delta_time, result
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[&sid_add_out]).unwrap();
+        assert_eq!(str, "(sim_params.time) + (sim_params.time)");
+
+        // Cycles can't be linked, so can't be evaluated
+        let nid_add2 = g.add_node(AddNode::default());
+        let sid_add2_out = g.output_slot(nid_add2, "result").unwrap();
+        g.link(sid_add_out, g.input_slot(nid_add2, "lhs").unwrap());
+        assert!(matches!(
+            g.try_link(sid_add2_out, g.input_slot(nid_add2, "rhs").unwrap()),
+            Err(GraphError::CycleDetected { .. })
+        ));
+    }
+
+    #[test]
+    fn graph_suggest_capacity() {
+        let mut g = Graph::new();
+        assert_eq!(g.suggest_capacity(), None);
+
+        // Need both a spawner and a lifetime
+        let nid_spawner = g.add_node(SpawnerNode::new(Spawner::rate(40.0.into())));
+        assert_eq!(g.suggest_capacity(), None);
+        let nid_lifetime = g.add_node(LifetimeNode::new(2.5));
+        assert_eq!(g.suggest_capacity(), Some(100)); // 40 p/s * 2.5 s
+
+        // Only the longest lifetime matters
+        g.add_node(LifetimeNode::new(0.5));
+        assert_eq!(g.suggest_capacity(), Some(100));
+
+        // Bursts overlapping each other
+        g.remove_node(nid_spawner);
+        g.add_node(SpawnerNode::new(Spawner::burst(16.0.into(), 1.0.into())));
+        assert_eq!(g.suggest_capacity(), Some(48)); // 3 bursts alive at once
+
+        // Single burst, plus randomized rate of up to 10 p/s
+        g.add_node(SpawnerNode::new(Spawner::once(32.0.into(), true)));
+        g.add_node(SpawnerNode::new(Spawner::rate(CpuValue::Uniform((
+            5., 10.,
+        )))));
+        assert_eq!(g.suggest_capacity(), Some(48 + 32 + 25));
+
+        g.remove_node(nid_lifetime);
+        g.compact();
+        assert_eq!(g.suggest_capacity(), Some(16 + 32 + 5)); // 0.5 s
+
+        let mut module = Module::default();
+        let outputs = g.eval_all(&mut module).unwrap();
+        assert_eq!(outputs.len(), 1);
+    }
+
+    /// Terminal node consuming a value, like a modifier node would.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct SinkNode {
+        slots: [SlotDef; 1],
+    }
+
+    impl Default for SinkNode {
+        fn default() -> Self {
+            Self {
+                slots: [SlotDef::input("value", None)],
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "serde", typetag::serde)]
+    impl Node for SinkNode {
+        fn slots(&self) -> &[SlotDef] {
+            &self.slots
+        }
+
+        fn eval(
+            &self,
+            _module: &mut Module,
+            _inputs: Vec<ExprHandle>,
+        ) -> Result<Vec<ExprHandle>, ExprError> {
+            Ok(vec![])
+        }
+    }
+
+    /// Node scaling a value, with a default scale of 1.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct ScaleNode {
+        slots: [SlotDef; 3],
+    }
+
+    impl Default for ScaleNode {
+        fn default() -> Self {
+            Self {
+                slots: [
+                    SlotDef::input("value", None),
+                    SlotDef::input_with_default(
+                        "scale",
+                        Some(ValueType::Scalar(ScalarType::Float)),
+                        1_f32.into(),
+                    ),
+                    SlotDef::output("result", None),
+                ],
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "serde", typetag::serde)]
+    impl Node for ScaleNode {
+        fn slots(&self) -> &[SlotDef] {
+            &self.slots
+        }
+
+        fn eval(
+            &self,
+            module: &mut Module,
+            inputs: Vec<ExprHandle>,
+        ) -> Result<Vec<ExprHandle>, ExprError> {
+            if inputs.len() != 2 {
+                return Err(ExprError::GraphEvalError(format!(
+                    "expected 2 inputs, got {}",
+                    inputs.len()
+                )));
+            }
+            Ok(vec![module.mul(inputs[0], inputs[1])])
+        }
+    }
+
+    #[test]
+    fn graph_default_input() {
+        let slot_def = ScaleNode::default().slots[1].clone();
+        assert_eq!(slot_def.default_value(), Some(1_f32.into()));
+        assert_eq!(SlotDef::input("value", None).default_value(), None);
+
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_scale = g.add_node(ScaleNode::default());
+        let sid_time = g
+            .output_slot(nid_time, BuiltInOperator::Time.name())
+            .unwrap();
+        let sid_value = g.input_slot(nid_scale, "value").unwrap();
+        let sid_scale = g.input_slot(nid_scale, "scale").unwrap();
+        let sid_out = g.output_slot(nid_scale, "result").unwrap();
+
+        // Unlinked input without default is still an error
+        let mut module = Module::default();
+        assert!(matches!(
+            g.eval(&mut module, sid_out),
+            Err(ExprError::GraphEvalError(_))
+        ));
+        assert!(g.eval_all(&mut module).is_err());
+        assert_eq!(
+            g.validate()[0],
+            GraphError::UnlinkedInput {
+                node: nid_scale,
+                slot: sid_value
+            }
+        );
+
+        // Unlinked input with default uses a literal of that value
+        g.link(sid_time, sid_value);
+        assert!(g.validate().is_empty());
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut module = Module::default();
+        let out = g.eval(&mut module, sid_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, out).unwrap(),
+            "(sim_params.time) * (1.)"
+        );
+        let mut module = Module::default();
+        let outputs = g.eval_all(&mut module).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, outputs[&sid_out]).unwrap(),
+            "(sim_params.time) * (1.)"
+        );
+
+        // Linking the input overrides the default
+        g.link(sid_time, sid_scale);
+        let mut module = Module::default();
+        let out = g.eval(&mut module, sid_out).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, out).unwrap(),
+            "(sim_params.time) * (sim_params.time)"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn slot_def_default_type_mismatch() {
+        SlotDef::input_with_default(
+            "scale",
+            Some(ValueType::Scalar(ScalarType::Float)),
+            Vec3::ONE.into(),
+        );
+    }
+
+    #[test]
+    fn slot_def_description() {
+        let slot = SlotDef::input_described(
+            "scale",
+            Some(ValueType::Scalar(ScalarType::Float)),
+            "Factor multiplying the input value.",
+        );
+        assert_eq!(slot.name(), "scale");
+        assert!(slot.is_input());
+        assert_eq!(
+            slot.description(),
+            Some("Factor multiplying the input value.")
+        );
+        let slot = SlotDef::output_described("result", None, "Scaled value.");
+        assert_eq!(slot.name(), "result");
+        assert!(slot.is_output());
+        assert_eq!(slot.description(), Some("Scaled value."));
+        assert_eq!(SlotDef::input("scale", None).description(), None);
+
+        // Display names default to the node name
+        assert_eq!(AddNode::default().name(), "AddNode");
+        assert_eq!(AddNode::default().display_name(), "Add");
+        assert_eq!(TimeNode::default().display_name(), "TimeNode");
+    }
+
+    #[test]
+    fn graph_link_cycle() {
+        let mut g = Graph::new();
+        let nid_a = g.add_node(AddNode::default());
+        let nid_b = g.add_node(AddNode::default());
+        let nid_c = g.add_node(AddNode::default());
+        let a_out = g.output_slot(nid_a, "result").unwrap();
+        let b_out = g.output_slot(nid_b, "result").unwrap();
+        let c_out = g.output_slot(nid_c, "result").unwrap();
+        let a_lhs = g.input_slot(nid_a, "lhs").unwrap();
+        let a_rhs = g.input_slot(nid_a, "rhs").unwrap();
+
+        // A -> B -> C is fine, but closing the loop with C -> A is rejected
+        assert!(g
+            .try_link(a_out, g.input_slot(nid_b, "lhs").unwrap())
+            .is_ok());
+        assert!(g
+            .try_link(b_out, g.input_slot(nid_c, "lhs").unwrap())
+            .is_ok());
+        assert_eq!(
+            g.try_link(c_out, a_lhs),
+            Err(GraphError::CycleDetected {
+                from: nid_c,
+                to: nid_a
+            })
+        );
+        assert_eq!(g.input_source(a_lhs), None);
+        assert!(g.output_targets(c_out).is_empty());
+
+        // Self-loops are cycles too
+        assert_eq!(
+            g.try_link(a_out, a_rhs),
+            Err(GraphError::CycleDetected {
+                from: nid_a,
+                to: nid_a
+            })
+        );
+        assert_eq!(
+            g.output_targets(a_out),
+            vec![g.input_slot(nid_b, "lhs").unwrap()]
+        );
+
+        // Diamond: T feeds both A and C, which both feed D
+        let nid_t = g.add_node(TimeNode::default());
+        let nid_d = g.add_node(AddNode::default());
+        let t_out = g.output_slot(nid_t, "time").unwrap();
+        assert!(g.try_link(t_out, a_lhs).is_ok());
+        assert!(g
+            .try_link(t_out, g.input_slot(nid_c, "rhs").unwrap())
+            .is_ok());
+        assert!(g
+            .try_link(a_out, g.input_slot(nid_d, "lhs").unwrap())
+            .is_ok());
+        assert!(g
+            .try_link(c_out, g.input_slot(nid_d, "rhs").unwrap())
+            .is_ok());
+        assert_eq!(g.output_targets(a_out).len(), 2);
+        assert_eq!(g.output_targets(c_out).len(), 1);
+    }
+
+    #[test]
+    fn graph_infer_slot_type() {
+        let vec3 = ValueType::Vector(VectorType::VEC3F);
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_age = g.add_node(AttributeNode::new(Attribute::AGE));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_norm = g.add_node(NormalizeNode::default());
+        let sid_pos = g.output_slot(nid_pos, "position").unwrap();
+        let sid_vel = g.output_slot(nid_vel, "velocity").unwrap();
+        let sid_age = g.output_slot(nid_age, "age").unwrap();
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        let sid_norm_in = g.input_slot(nid_norm, "in").unwrap();
+        let sid_norm_out = g.output_slot(nid_norm, "out").unwrap();
+
+        // Typed slots resolve to their own type
+        assert_eq!(g.infer_slot_type(sid_pos), Some(vec3));
+        assert_eq!(
+            g.infer_slot_type(sid_age),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+
+        // Unlinked variant slots can't be resolved
+        assert_eq!(g.infer_slot_type(sid_add_lhs), None);
+        assert_eq!(g.infer_slot_type(sid_add_out), None);
+
+        // Partially linked
+        g.link(sid_pos, sid_add_lhs);
+        assert_eq!(g.infer_slot_type(sid_add_lhs), Some(vec3));
+        assert_eq!(g.infer_slot_type(sid_add_out), None);
+
+        // Fully linked, and propagated downstream
+        g.link(sid_vel, sid_add_rhs);
+        g.link(sid_add_out, sid_norm_in);
+        assert_eq!(g.infer_slot_type(sid_add_out), Some(vec3));
+        assert_eq!(g.infer_slot_type(sid_norm_in), Some(vec3));
+        assert_eq!(g.infer_slot_type(sid_norm_out), Some(vec3));
+
+        // Conflicting inputs
+        g.relink(sid_age, sid_add_rhs).unwrap();
+        assert_eq!(
+            g.infer_slot_type(sid_add_rhs),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+        assert_eq!(g.infer_slot_type(sid_add_out), None);
+        assert_eq!(g.infer_slot_type(sid_norm_out), None);
+    }
+
+    #[test]
+    fn graph_link_type_check() {
+        let mut g = Graph::new();
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_sdf = g.add_node(SphereSdfNode::default());
+        let nid_spherical = g.add_node(SphericalToCartesianNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let sid_pos = g.output_slot(nid_pos, "position").unwrap();
+
+        // Vec3 -> Vec3
+        let sid_point = g.input_slot(nid_sdf, "point").unwrap();
+        assert!(g.try_link(sid_pos, sid_point).is_ok());
+        assert_eq!(g.input_source(sid_point), Some(sid_pos));
+
+        // Vec3 -> f32
+        let sid_theta = g.input_slot(nid_spherical, "theta").unwrap();
+        assert_eq!(
+            g.try_link(sid_pos, sid_theta),
+            Err(GraphError::TypeMismatch {
+                output_slot: sid_pos,
+                input_slot: sid_theta,
+                output: ValueType::Vector(VectorType::VEC3F),
+                input: ValueType::Scalar(ScalarType::Float),
+            })
+        );
+        assert_eq!(g.input_source(sid_theta), None);
+        assert_eq!(g.output_targets(sid_pos), vec![sid_point]);
+
+        // Variant slots link to anything
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        assert!(g.try_link(sid_pos, sid_add_lhs).is_ok());
+        assert!(g.try_link(sid_add_out, sid_theta).is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn graph_link_cycle_panics() {
+        let mut g = Graph::new();
+        let nid_a = g.add_node(AddNode::default());
+        let nid_b = g.add_node(AddNode::default());
+        g.link(
+            g.output_slot(nid_a, "result").unwrap(),
+            g.input_slot(nid_b, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_b, "result").unwrap(),
+            g.input_slot(nid_a, "lhs").unwrap(),
+        );
+    }
+
+    #[test]
+    fn graph_dead_nodes() {
+        let mut g = Graph::new();
+        assert!(g.dead_nodes().is_empty());
+
+        // Unlinked terminal nodes are never dead
+        let nid_sink = g.add_node(SinkNode::default());
+        let nid_spawner = g.add_node(SpawnerNode::default());
+        assert!(g.dead_nodes().is_empty());
+
+        // Time feeds the terminal node, the multiply feeds nothing
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let time_out = g.output_slot(nid_time, "time").unwrap();
+        g.link(time_out, g.input_slot(nid_sink, "value").unwrap());
+        g.link(time_out, g.input_slot(nid_mul, "lhs").unwrap());
+        g.link(time_out, g.input_slot(nid_mul, "rhs").unwrap());
+        assert_eq!(g.dead_nodes(), vec![nid_mul]);
+
+        // Nodes only feeding dead nodes are dead too
+        let nid_attr = g.add_node(AttributeNode::new(Attribute::AGE));
+        let nid_add = g.add_node(AddNode::default());
+        g.link(
+            g.output_slot(nid_attr, "age").unwrap(),
+            g.input_slot(nid_add, "lhs").unwrap(),
+        );
+        g.relink(
+            g.output_slot(nid_add, "result").unwrap(),
+            g.input_slot(nid_mul, "rhs").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(g.dead_nodes(), vec![nid_mul, nid_attr, nid_add]);
+
+        // Connecting the multiply to a terminal node revives the whole chain
+        let nid_sink2 = g.add_node(SinkNode::default());
+        g.link(
+            g.output_slot(nid_mul, "result").unwrap(),
+            g.input_slot(nid_sink2, "value").unwrap(),
+        );
+        assert!(g.dead_nodes().is_empty());
+
+        // Removed nodes are ignored
+        g.remove_node(nid_sink2);
+        g.remove_node(nid_spawner);
+        assert_eq!(g.dead_nodes(), vec![nid_mul, nid_attr, nid_add]);
+    }
+
+    #[test]
+    fn graph_reachable_nodes() {
+        // Update the particle age with a literal, next to some orphan nodes
+        let mut g = Graph::new();
+        let nid_age = g.add_node(LiteralNode::new(0.5_f32));
+        let nid_set_age = g.add_node(SetAttributeNode::new(
+            ModifierContext::Update,
+            Attribute::AGE,
+        ));
+        g.link(
+            g.output_slot(nid_age, "value").unwrap(),
+            g.input_slot(nid_set_age, "age").unwrap(),
+        );
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_sin = g.add_node(SinNode::default());
+        g.link(
+            g.output_slot(nid_time, "time").unwrap(),
+            g.input_slot(nid_sin, "in").unwrap(),
+        );
+        // Fails to evaluate, if ever evaluated
+        let nid_add = g.add_node(AddNode::default());
+
+        let reachable = g.reachable_nodes(&[nid_set_age]);
+        assert_eq!(reachable, HashSet::from([nid_age, nid_set_age]));
+        let reachable = g.reachable_nodes(&[nid_sin, nid_add]);
+        assert_eq!(reachable, HashSet::from([nid_time, nid_sin, nid_add]));
+        assert!(g.reachable_nodes(&[]).is_empty());
+
+        // The orphan nodes are not evaluated
+        assert!(g.eval_all(&mut Module::default()).is_err());
+        let asset = g.to_effect_asset("effect", 32).unwrap();
+        assert_eq!(asset.update_modifiers().count(), 1);
+        assert_eq!(asset.module().stats(), crate::graph::GraphStats::default());
+    }
+
+    #[test]
+    fn graph_node() {
+        let mut g = Graph::new();
+        let nid_attr = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_time = g.add_node(TimeNode::default());
+
+        let node = g.node(nid_attr).unwrap();
+        assert_eq!(node.slots().len(), 1);
+        assert_eq!(node.name(), "AttributeNode");
+        assert!(g.node_mut(nid_attr).is_some());
+
+        // Unknown and removed nodes
+        let nid_unknown = NodeId::new(NonZeroU32::new(42).unwrap());
+        assert!(g.node(nid_unknown).is_none());
+        assert!(g.node_mut(nid_unknown).is_none());
+        g.remove_node(nid_time);
+        assert!(g.node(nid_time).is_none());
+        assert!(g.node_mut(nid_time).is_none());
+        assert!(g.node(nid_attr).is_some());
+    }
+
+    #[test]
+    fn graph_auto_layout() {
+        let mut g = Graph::new();
+        assert!(g.auto_layout().unwrap().is_empty());
+
+        // time ──┬─> add1 ──> add2 ──> sink
+        // pos ───┘     time ──┘
+        let nid_sink = g.add_node(SinkNode::default());
+        let nid_add2 = g.add_node(AddNode::default());
+        let nid_add1 = g.add_node(AddNode::default());
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_unlinked = g.add_node(SpawnerNode::default());
+        let time_out = g.output_slot(nid_time, "time").unwrap();
+        g.link(time_out, g.input_slot(nid_add1, "lhs").unwrap());
+        g.link(
+            g.output_slot(nid_pos, "position").unwrap(),
+            g.input_slot(nid_add1, "rhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_add1, "result").unwrap(),
+            g.input_slot(nid_add2, "lhs").unwrap(),
+        );
+        g.link(time_out, g.input_slot(nid_add2, "rhs").unwrap());
+        g.link(
+            g.output_slot(nid_add2, "result").unwrap(),
+            g.input_slot(nid_sink, "value").unwrap(),
+        );
+
+        let layout = g.auto_layout().unwrap();
+        assert_eq!(layout.len(), 6);
+
+        // Upstream nodes are always on the left of downstream ones
+        let links = [
+            (nid_time, nid_add1),
+            (nid_pos, nid_add1),
+            (nid_add1, nid_add2),
+            (nid_time, nid_add2),
+            (nid_add2, nid_sink),
+        ];
+        for (from, to) in links {
+            assert!(layout[&from].x < layout[&to].x);
+        }
+
+        // Columns are the longest upstream chain
+        let spacing = Graph::LAYOUT_SPACING;
+        assert_eq!(layout[&nid_time].x, 0.);
+        assert_eq!(layout[&nid_pos].x, 0.);
+        assert_eq!(layout[&nid_unlinked].x, 0.);
+        assert_eq!(layout[&nid_add1].x, spacing.x);
+        assert_eq!(layout[&nid_add2].x, spacing.x * 2.);
+        assert_eq!(layout[&nid_sink].x, spacing.x * 3.);
+
+        // Rows within a column are distinct
+        let mut first_column: Vec<f32> = [nid_time, nid_pos, nid_unlinked]
+            .iter()
+            .map(|node_id| layout[node_id].y)
+            .collect();
+        first_column.sort_by(f32::total_cmp);
+        assert_eq!(first_column, vec![0., spacing.y, spacing.y * 2.]);
+
+        // Removed nodes are ignored
+        g.remove_node(nid_unlinked);
+        assert_eq!(g.auto_layout().unwrap().len(), 5);
+
+        // A cycle, as loaded from a hand-edited file, can't be laid out
+        let add2_out = g.output_slot(nid_add2, "result").unwrap();
+        let add1_lhs = g.input_slot(nid_add1, "lhs").unwrap();
+        g.unlink(time_out, add1_lhs);
+        g.get_slot_mut(add2_out).link_to(add1_lhs);
+        g.get_slot_mut(add1_lhs).link_input(add2_out);
+        assert!(matches!(
+            g.auto_layout(),
+            Err(GraphError::CycleDetected { .. })
+        ));
+    }
+
+    #[test]
+    fn graph_template() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_scale = g.add_node(LiteralNode::new(1.));
+        let nid_mul = g.add_node(MulNode::default());
+        let sid_mul_out = g.output_slot(nid_mul, "result").unwrap();
+        g.link(
+            g.output_slot(nid_time, BuiltInOperator::Time.name())
+                .unwrap(),
+            g.input_slot(nid_mul, "lhs").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_scale, "value").unwrap(),
+            g.input_slot(nid_mul, "rhs").unwrap(),
+        );
+
+        let template = GraphTemplate::new(g).with_parameter("scale", nid_scale);
+        assert_eq!(template.parameter("scale"), Some(nid_scale));
+        assert_eq!(template.parameter("other"), None);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let eval = |g: &Graph| {
+            let mut module = Module::default();
+            let outputs = g.eval_all(&mut module).unwrap();
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            context.eval(&module, outputs[&sid_mul_out]).unwrap()
+        };
+
+        let g2 = template.instantiate(&[("scale", 2_f32.into())]).unwrap();
+        let g5 = template.instantiate(&[("scale", 5_f32.into())]).unwrap();
+        assert_eq!(eval(&g2), "(sim_params.time) * (2.)");
+        assert_eq!(eval(&g5), "(sim_params.time) * (5.)");
+
+        // The template itself is unchanged
+        assert_eq!(eval(template.graph()), "(sim_params.time) * (1.)");
+        assert_eq!(
+            eval(&template.instantiate(&[]).unwrap()),
+            eval(template.graph())
+        );
+
+        // Errors
+        assert!(matches!(
+            template.instantiate(&[("other", 2_f32.into())]),
+            Err(ExprError::GraphEvalError(_))
+        ));
+        assert!(matches!(
+            template.instantiate(&[("scale", 2_u32.into())]),
+            Err(ExprError::TypeError(_))
+        ));
+    }
+
+    #[test]
+    fn graph_remove_middle_node() {
+        let mut g = Graph::new();
+
+        // pos -> add.lhs, add -> mul.lhs, pos -> mul.rhs
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        let nid_mul = g.add_node(MulNode::default());
+        let sid_pos = g.output_slots(nid_pos)[0];
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_out = g.output_slot(nid_add, "result").unwrap();
+        let sid_mul_lhs = g.input_slot(nid_mul, "lhs").unwrap();
+        let sid_mul_rhs = g.input_slot(nid_mul, "rhs").unwrap();
+        let sid_mul_out = g.output_slot(nid_mul, "result").unwrap();
+        g.link(sid_pos, sid_add_lhs);
+        g.link(sid_add_out, sid_mul_lhs);
+        g.link(sid_pos, sid_mul_rhs);
+
+        assert!(g.remove_node(nid_add).is_some());
+        assert_eq!(g.node_count(), 2);
+        assert!(g.slots(nid_add).is_empty());
+
+        // Other nodes keep their identifiers, slots, and unrelated links
+        assert_eq!(g.output_slots(nid_pos), vec![sid_pos]);
+        assert_eq!(g.input_slots(nid_mul), vec![sid_mul_lhs, sid_mul_rhs]);
+        assert_eq!(g.output_slots(nid_mul), vec![sid_mul_out]);
+        assert_eq!(g.output_targets(sid_pos), vec![sid_mul_rhs]);
+        assert_eq!(g.input_source(sid_mul_rhs), Some(sid_pos));
+
+        // No dangling link to the removed node remains
+        assert_eq!(g.input_source(sid_mul_lhs), None);
+        for slot in g.slots.iter().flatten() {
+            assert!(!slot.linked_slots.contains(&sid_add_lhs));
+            assert!(!slot.linked_slots.contains(&sid_add_out));
+        }
+
+        // New nodes don't reuse the identifiers of removed ones
+        let nid_time = g.add_node(TimeNode::default());
+        assert_ne!(nid_time, nid_add);
+        let sid_time = g.output_slot(nid_time, "time").unwrap();
+        assert_ne!(sid_time, sid_add_lhs);
+        assert_ne!(sid_time, sid_add_out);
+        g.link(sid_time, sid_mul_lhs);
+        assert_eq!(g.input_source(sid_mul_lhs), Some(sid_time));
+    }
+
+    #[test]
+    fn graph_iter_nodes() {
+        let mut g = Graph::new();
+        assert_eq!(g.node_count(), 0);
+        assert_eq!(g.node_ids().count(), 0);
+
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_add = g.add_node(AddNode::default());
+        assert_eq!(g.node_count(), 3);
+        assert_eq!(
+            g.node_ids().collect::<Vec<_>>(),
+            vec![nid_time, nid_pos, nid_add]
+        );
+        let slot_counts: Vec<_> = g
+            .iter_nodes()
+            .map(|(node_id, node)| (node_id, node.slots().len()))
+            .collect();
+        assert_eq!(slot_counts, vec![(nid_time, 2), (nid_pos, 1), (nid_add, 3)]);
+
+        // Removed nodes are skipped
+        g.remove_node(nid_pos);
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(g.node_ids().collect::<Vec<_>>(), vec![nid_time, nid_add]);
+        assert_eq!(g.iter_nodes().count(), 2);
+    }
+
+    #[test]
+    fn graph_clone() {
+        let mut g = Graph::new();
+        let nid_time = g.add_node(TimeNode::default());
+        let nid_add = g.add_node(AddNode::default());
+        let sid_time = g.output_slot(nid_time, "time").unwrap();
+        let sid_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        g.link(sid_time, sid_lhs);
+
+        let snapshot = g.clone();
+        assert_eq!(snapshot.node_count(), 2);
+        assert_eq!(snapshot.links(), g.links());
+
+        // Mutating the original leaves the clone untouched
+        let nid_lit = g.add_node(LiteralNode::new(1_f32));
+        g.link(g.output_slot(nid_lit, "value").unwrap(), sid_rhs);
+        g.unlink(sid_time, sid_lhs);
+        g.node_mut(nid_lit)
+            .and_then(|node| node.as_literal_mut())
+            .unwrap()
+            .set_value(2_f32.into());
+        assert_eq!(g.node_count(), 3);
+        assert_eq!(snapshot.node_count(), 2);
+        assert_eq!(snapshot.links(), vec![(sid_time, sid_lhs)]);
+        assert_eq!(snapshot.input_source(sid_rhs), None);
+        assert!(snapshot.node(nid_lit).is_none());
+        assert_eq!(snapshot.node(nid_add).unwrap().name(), "AddNode");
+    }
+
+    #[test]
+    fn graph_compact() {
+        let mut g = Graph::new();
+
+        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
+        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
+        let nid_add = g.add_node(AddNode::default());
+        let sid_pos = g.output_slots(nid_pos)[0];
+        let sid_vel = g.output_slots(nid_vel)[0];
+        let sid_add_lhs = g.input_slot(nid_add, "lhs").unwrap();
+        let sid_add_rhs = g.input_slot(nid_add, "rhs").unwrap();
+        g.link(sid_pos, sid_add_lhs);
+        g.link(sid_vel, sid_add_rhs);
+
+        // Removing a node unlinks it, but leaves a tombstone
+        assert!(g.remove_node(nid_pos).is_some());
+        assert!(g.remove_node(nid_pos).is_none());
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(g.nodes.len(), 3);
+        assert!(g.get_slot(sid_add_lhs).linked_slots.is_empty());
+
+        let mapping = g.compact();
+        assert_eq!(mapping.node(nid_pos), None);
+        assert_eq!(mapping.slot(sid_pos), None);
+
+        // Identifiers are dense
+        assert_eq!(g.nodes.len(), 2);
+        assert!(g.nodes.iter().all(Option::is_some));
+        for (index, slot) in g.slots.iter().enumerate() {
+            let slot = slot.as_ref().unwrap();
+            assert_eq!(slot.id().index(), index);
+            assert!(slot.node_id().index() < g.nodes.len());
+        }
+        let nid_vel = mapping.node(nid_vel).unwrap();
+        let nid_add = mapping.node(nid_add).unwrap();
+        assert_eq!(nid_vel.index(), 0);
+        assert_eq!(nid_add.index(), 1);
+
+        // Links are rewritten
+        let sid_vel = mapping.slot(sid_vel).unwrap();
+        let sid_add_lhs = mapping.slot(sid_add_lhs).unwrap();
+        let sid_add_rhs = mapping.slot(sid_add_rhs).unwrap();
+        assert_eq!(g.output_slots(nid_vel), vec![sid_vel]);
+        assert_eq!(g.input_slot(nid_add, "rhs"), Some(sid_add_rhs));
+        assert_eq!(g.get_slot(sid_vel).linked_slots, vec![sid_add_rhs]);
+        assert_eq!(g.get_slot(sid_add_rhs).linked_slots, vec![sid_vel]);
+        assert!(g.get_slot(sid_add_lhs).linked_slots.is_empty());
+    }
+
+    #[test]
+    fn color_ramp() {
+        let red = Vec4::new(1., 0., 0., 1.);
+        let green = Vec4::new(0., 1., 0., 1.);
+        let blue = Vec4::new(0., 0., 1., 1.);
+        let node = ColorRampNode::new(red)
+            .with_band(0.3, green)
+            .with_band(0.7, blue);
+
+        // Representative inputs in each of the three bands, and on the boundaries
+        for (value, color) in [
+            (-1., red),
+            (0., red),
+            (0.29, red),
+            (0.3, green),
+            (0.5, green),
+            (0.7, blue),
+            (1., blue),
+            (100., blue),
+        ] {
+            assert_eq!(node.sample(value), color, "value={}", value);
+        }
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let value = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![value]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "select(select(vec4<f32>(1.,0.,0.,1.), vec4<f32>(0.,1.,0.,1.), (particle.{0}) >= (0.3)), vec4<f32>(0.,0.,1.,1.), (particle.{0}) >= (0.7))",
+                Attribute::AGE.name()
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn color_ramp_unsorted() {
+        let _ = ColorRampNode::default()
+            .with_band(0.5, Vec4::ZERO)
+            .with_band(0.2, Vec4::ONE);
+    }
+
+    #[test]
+    fn weighted_choice() {
+        let red = Vec4::new(1., 0., 0., 1.);
+        let green = Vec4::new(0., 1., 0., 1.);
+        let blue = Vec4::new(0., 0., 1., 1.);
+        let node = WeightedChoiceNode::new(1., red)
+            .with_option(2., green)
+            .with_option(1., blue);
+        assert_eq!(node.options().len(), 3);
+        assert_eq!(node.total_weight(), 4.);
+
+        for (random, color) in [
+            (0., red),
+            (0.24, red),
+            (0.25, green),
+            (0.74, green),
+            (0.75, blue),
+            (0.99, blue),
+        ] {
+            assert_eq!(node.choose(random), color.into(), "random={}", random);
+        }
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let random = module.builtin(BuiltInOperator::Rand(ScalarType::Float.into()));
+        let outputs = node.eval(&mut module, vec![random]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "select(select(vec4<f32>(1.,0.,0.,1.), vec4<f32>(0.,1.,0.,1.), (var0) >= (0.25)), vec4<f32>(0.,0.,1.,1.), (var0) >= (0.75))"
+        );
+        // The random value is drawn once, and shared by all comparisons
+        assert_eq!(context.main_code, "let var0 = frand();\n");
+    }
+
+    #[test]
+    fn wobble() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let tau = std::f32::consts::TAU.to_wgsl_string();
+
+        let node = WobbleNode::new()
+            .with_layer(2., 3., 0.)
+            .with_layer(5., 0.5, 0.25);
+        assert_eq!(node.layers().len(), 2);
+        assert_eq!(node.slots().len(), 1);
+
+        let mut module = Module::default();
+        let time = module.lit(0.);
+        let ret = node.eval(&mut module, vec![time]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "((3.) * (sin((((sim_params.time) * (2.)) + (0.)) * ({0})))) + ((0.5) * (sin((((sim_params.time) * (5.)) + (0.25)) * ({0}))))",
+                tau
+            )
+        );
+
+        // Per-axis, reading the time from the input
+        let node = WobbleNode::new()
+            .with_layer(1., 1., 0.)
+            .with_time_input()
+            .with_per_axis();
+        assert_eq!(node.slots().len(), 2);
+        assert_eq!(
+            node.slots()[1].value_type(),
+            Some(ValueType::Vector(VectorType::VEC3F))
+        );
+
+        let mut module = Module::default();
+        let age = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![age]).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "(1.) * (sin((((particle.{}) * (1.)) + ({})) * ({})))",
+                Attribute::AGE.name(),
+                Vec3::new(0., 1. / 3., 2. / 3.).to_wgsl_string(),
+                tau
+            )
+        );
+
+        // No layer
+        let mut module = Module::default();
+        let outputs = WobbleNode::new().eval(&mut module, vec![]).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "0.".to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn wobble_max_layers() {
+        let mut node = WobbleNode::new();
+        for _ in 0..=WobbleNode::MAX_LAYERS {
+            node = node.with_layer(1., 1., 0.);
+        }
+    }
+
+    #[test]
+    fn oscillator() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Sine, reading the built-in time
+        let node = OscillatorNode::new(WaveShape::Sine)
+            .with_frequency(2.)
+            .with_amplitude(3.)
+            .with_phase(0.25);
+        assert_eq!(node.slots().len(), 1);
+        assert!((node.sample(0.) - 3.).abs() < 1e-5);
+        assert!((node.sample(0.25) + 3.).abs() < 1e-5);
+
+        let mut module = Module::default();
+        let time = module.lit(0.);
+        let ret = node.eval(&mut module, vec![time]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let outputs = node.eval(&mut module, vec![]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let sine = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            sine,
+            format!(
+                "(3.) * (sin((((sim_params.time) * (2.)) + (0.25)) * ({})))",
+                std::f32::consts::TAU.to_wgsl_string()
+            )
+        );
+
+        // Square, reading the time from the input
+        let node = OscillatorNode::new(WaveShape::Square).with_time_input();
+        assert_eq!(node.slots().len(), 2);
+        for (time, value) in [(0., 1.), (0.49, 1.), (0.5, -1.), (0.99, -1.), (1., 1.)] {
+            assert_eq!(node.sample(time), value, "time={}", time);
+        }
+
+        let mut module = Module::default();
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let age = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![age]).unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let square = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            square,
+            format!(
+                "(1.) * ((1.) - ((2.) * (step(0.5, fract(((particle.{}) * (1.)) + (0.))))))",
+                Attribute::AGE.name()
+            )
+        );
+        assert_ne!(sine, square);
+
+        // Triangle and sawtooth, on CPU
+        let node = OscillatorNode::new(WaveShape::Triangle);
+        for (time, value) in [(0., 0.), (0.25, 1.), (0.5, 0.), (0.75, -1.)] {
+            assert!((node.sample(time) - value).abs() < 1e-5, "time={}", time);
+        }
+        let node = OscillatorNode::new(WaveShape::Sawtooth);
+        for (time, value) in [(0., 0.), (0.25, 0.5), (0.75, -0.5)] {
+            assert!((node.sample(time) - value).abs() < 1e-5, "time={}", time);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn weighted_choice_type_mismatch() {
+        let _ = WeightedChoiceNode::new(1., Vec4::ONE).with_option(1., 3.);
+    }
+
+    #[test]
+    fn random() {
+        let node = RandomNode::default();
+        assert_eq!(node.value_type(), ValueType::Scalar(ScalarType::Float));
+
+        let mut module = Module::default();
+        let x = module.lit(1.);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        for (value_type, call) in [
+            (ValueType::Scalar(ScalarType::Float), "frand()"),
+            (ValueType::Vector(VectorType::VEC2F), "frand2()"),
+            (ValueType::Vector(VectorType::VEC3F), "frand3()"),
+            (ValueType::Vector(VectorType::VEC4F), "frand4()"),
+        ] {
+            let node = RandomNode::new(value_type);
+            assert_eq!(node.slots()[0].value_type(), Some(value_type));
+            let outputs = node.eval(&mut module, vec![]).unwrap();
+            assert_eq!(outputs.len(), 1);
+            assert_eq!(
+                module.get(outputs[0]).unwrap().value_type(),
+                Some(value_type)
+            );
+            assert!(module.has_side_effect(outputs[0]));
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, "var0");
+            assert_eq!(context.main_code, format!("let var0 = {};\n", call));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn random_invalid_type() {
+        let _ = RandomNode::new(ValueType::Vector(VectorType::VEC3U));
+    }
+
+    #[test]
+    fn seeded_rand() {
+        let node = SeededRandNode::default();
+        assert_eq!(node.seed(), 0);
+
+        let mut module = Module::default();
+        let x = module.lit(1.);
+        let ret = node.eval(&mut module, vec![x]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let mut node = SeededRandNode::new(42);
+        let rand42 = node.eval(&mut module, vec![]).unwrap()[0];
+        node.set_seed(7);
+        let rand7 = node.eval(&mut module, vec![]).unwrap()[0];
+        let str42 = context.eval(&module, rand42).unwrap();
+        let str7 = context.eval(&module, rand7).unwrap();
+        assert_eq!(str42, "frand_seeded(42u)");
+        assert_eq!(str7, "frand_seeded(7u)");
+        assert!(module.has_side_effect(rand42));
+    }
+
+    #[test]
+    fn srgb_conversion() {
+        let mut module = Module::default();
+        let a = module.attr(Attribute::HDR_COLOR);
+        let b = module.attr(Attribute::COLOR);
+        let nodes: [Box<dyn Node>; 2] = [
+            Box::new(LinearToSrgbNode::default()),
+            Box::new(SrgbToLinearNode::default()),
+        ];
+        for node in nodes {
+            assert_eq!(node.slots().iter().filter(|s| s.is_input()).count(), 1);
+            assert_eq!(node.slots().iter().filter(|s| s.is_output()).count(), 1);
+            let ret = node.eval(&mut module, vec![a, b]);
+            assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        }
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let color = Attribute::HDR_COLOR.name();
+        let to_srgb = LinearToSrgbNode::default()
+            .eval(&mut module, vec![a])
+            .unwrap();
+        let to_linear = SrgbToLinearNode::default()
+            .eval(&mut module, vec![a])
+            .unwrap();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            context.eval(&module, to_srgb[0]).unwrap(),
+            format!("linear_to_srgb(particle.{color})")
+        );
+        assert_eq!(
+            context.eval(&module, to_linear[0]).unwrap(),
+            format!("srgb_to_linear(particle.{color})")
+        );
+
+        // The conversion functions are defined once in the common shader module, and
+        // use the piecewise transfer function instead of a gamma approximation.
+        let common = include_str!("../render/vfx_common.wgsl");
+        for (func, threshold, slope) in [
+            (
+                "fn linear_to_srgb(",
+                "rgb <= vec3<f32>(0.0031308)",
+                "rgb * 12.92",
+            ),
+            (
+                "fn srgb_to_linear(",
+                "rgb <= vec3<f32>(0.04045)",
+                "rgb / 12.92",
+            ),
+        ] {
+            assert_eq!(common.matches(func).count(), 1);
+            let start = common.find(func).unwrap();
+            let body = &common[start..start + common[start..].find("\n}").unwrap()];
+            assert!(body.contains(threshold));
+            assert!(body.contains(slope));
+            assert!(body.contains("vec3<f32>(2.4)") || body.contains("1.0 / 2.4"));
+            assert!(!body.contains("2.2"));
+            // Alpha is passed through
+            assert!(body.contains(", c.a);"));
+        }
+    }
+
+    #[test]
+    fn color_blend() {
+        let node = ColorBlendNode::default();
+        assert_eq!(node.mode(), BlendMode::Multiply);
+
+        let mut module = Module::default();
+        let a = module.attr(Attribute::HDR_COLOR);
+        let ret = node.eval(&mut module, vec![a]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let b = module.lit(Vec4::new(1., 0.5, 0., 1.));
+        let color = Attribute::HDR_COLOR.name();
+        for (mode, expected) in [
+            (
+                BlendMode::Multiply,
+                format!("(particle.{color}) * (vec4<f32>(1.,0.5,0.,1.))"),
+            ),
+            (
+                BlendMode::Overlay,
+                format!(
+                    "select((vec4<f32>(1.,1.,1.,1.)) - ((vec4<f32>(2.,2.,2.,2.)) * (((vec4<f32>(1.,1.,1.,1.)) - (particle.{color})) * ((vec4<f32>(1.,1.,1.,1.)) - (vec4<f32>(1.,0.5,0.,1.))))), (vec4<f32>(2.,2.,2.,2.)) * ((particle.{color}) * (vec4<f32>(1.,0.5,0.,1.))), (particle.{color}) < (vec4<f32>(0.5,0.5,0.5,0.5)))"
+                ),
+            ),
+            (
+                BlendMode::Screen,
+                format!("(vec4<f32>(1.,1.,1.,1.)) - (((vec4<f32>(1.,1.,1.,1.)) - (particle.{color})) * ((vec4<f32>(1.,1.,1.,1.)) - (vec4<f32>(1.,0.5,0.,1.))))"),
+            ),
+            (
+                BlendMode::Add,
+                format!("(particle.{color}) + (vec4<f32>(1.,0.5,0.,1.))"),
+            ),
+        ] {
+            let node = ColorBlendNode::new(mode);
+            let outputs = node.eval(&mut module, vec![a, b]).unwrap();
+            assert_eq!(outputs.len(), 1);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            let str = context.eval(&module, outputs[0]).unwrap();
+            assert_eq!(str, expected, "mode={:?}", mode);
+        }
+    }
+
+    #[test]
+    fn color_gradient() {
+        let black = Vec4::new(0., 0., 0., 1.);
+        let white = Vec4::ONE;
+        let node = ColorGradientNode::default();
+        assert_eq!(node.stops(), &[(0., black), (1., white)]);
+        assert_eq!(node.sample(-1.), Some(black));
+        assert_eq!(node.sample(0.), Some(black));
+        assert_eq!(node.sample(0.5), Some(Vec4::new(0.5, 0.5, 0.5, 1.)));
+        assert_eq!(node.sample(1.), Some(white));
+        assert_eq!(node.sample(2.), Some(white));
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // The ratio is clamped, then mixes from black at t=0 to white at t=1
+        let t = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![t]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!("mix(vec4<f32>(0.,0.,0.,1.), vec4<f32>(1.,1.,1.,1.), saturate(((saturate(particle.{0})) - (0.)) / (1.)))", Attribute::AGE.name())
+        );
+
+        // Stops are sorted on construction
+        let red = Vec4::new(1., 0., 0., 1.);
+        let node = ColorGradientNode::new([(1., white), (0.5, red), (0., black)]);
+        assert_eq!(node.stops(), &[(0., black), (0.5, red), (1., white)]);
+        let node = ColorGradientNode::new([(0., black), (1., white)]).with_stop(0.5, red);
+        assert_eq!(node.stops(), &[(0., black), (0.5, red), (1., white)]);
+        assert_eq!(node.sample(0.25), Some(Vec4::new(0.5, 0., 0., 1.)));
+        assert_eq!(node.sample(0.75), Some(Vec4::new(1., 0.5, 0.5, 1.)));
+
+        // Zero stops is an error
+        let node = ColorGradientNode::new([]);
+        assert_eq!(node.sample(0.5), None);
+        let ret = node.eval(&mut module, vec![t]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // A single stop is a constant color
+        let node = ColorGradientNode::new([(0.3, red)]);
+        assert_eq!(node.sample(0.), Some(red));
+        let outputs = node.eval(&mut module, vec![t]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(str, "vec4<f32>(1.,0.,0.,1.)");
+
+        // Duplicate keys produce a hard transition
+        let node = ColorGradientNode::new([(0., black), (0.5, black), (0.5, white), (1., white)]);
+        assert_eq!(node.sample(0.5), Some(black));
+        assert_eq!(node.sample(0.51), Some(white));
+        let outputs = node.eval(&mut module, vec![t]).unwrap();
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert!(str.contains(&format!(
+            "(saturate(particle.{0})) > (0.5)",
+            Attribute::AGE.name()
+        )));
+    }
+
+    #[test]
+    fn path_tangent() {
+        let node = PathTangentNode::default();
+        assert_eq!(node.control_points(), &[Vec3::ZERO, Vec3::X]);
+        assert_eq!(node.tangent(0.), Some(Vec3::X));
+        assert_eq!(node.tangent(1.), Some(Vec3::X));
+
+        let mut module = Module::default();
+
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+
+        // The tangent references the control points and the parameter
+        let p0 = Vec3::ZERO;
+        let p1 = Vec3::new(1., 1., 0.);
+        let p2 = Vec3::new(2., 0., 0.);
+        let node = PathTangentNode::new([p0, p1, p2]);
+        let t = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![t]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert!(str.starts_with("normalize("));
+        assert!(str.contains(&format!(
+            "(saturate(particle.{0})) * (2.)",
+            Attribute::AGE.name()
+        )));
+        assert!(str.contains("(vec3<f32>(0.,0.,0.)) - (vec3<f32>(1.,1.,0.))"));
+        assert!(str.contains("(vec3<f32>(1.,1.,0.)) - (vec3<f32>(2.,0.,0.))"));
+        assert!(str.contains("select("));
+
+        // One-sided derivatives at the endpoints, central one in between
+        assert_eq!(node.tangent(0.), Some((p1 - p0).normalize()));
+        assert_eq!(node.tangent(-1.), Some((p1 - p0).normalize()));
+        assert_eq!(node.tangent(1.), Some((p2 - p1).normalize()));
+        assert_eq!(node.tangent(0.5), Some(Vec3::X));
+        let tangent = node.tangent(0.25).unwrap();
+        assert!(tangent.x > 0. && tangent.y > 0.);
+        let tangent = node.tangent(0.75).unwrap();
+        assert!(tangent.x > 0. && tangent.y < 0.);
 
-impl Default for TimeNode {
-    fn default() -> Self {
-        Self {
-            slots: [BuiltInOperator::Time, BuiltInOperator::DeltaTime]
-                .map(|op| SlotDef::output(op.name(), Some(op.value_type()))),
-        }
+        // At least two control points are needed
+        let node = PathTangentNode::new([Vec3::ZERO]);
+        assert_eq!(node.tangent(0.5), None);
+        let ret = node.eval(&mut module, vec![t]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
     }
-}
 
-impl Node for TimeNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
-    }
+    #[test]
+    fn smooth_gradient() {
+        let gradient = Gradient::new()
+            .with_key(0., Vec4::ZERO)
+            .with_key(1., Vec4::ONE);
+        let node = SmoothGradientNode::new(gradient.clone()).with_edges(0.2, 0.8);
+        assert_eq!(node.edges(), (0.2, 0.8));
+        assert_eq!(node.gradient(), &gradient);
+        assert_eq!(node.sample(0.), Vec4::ZERO);
+        assert_eq!(node.sample(0.5), Vec4::splat(0.5));
+        assert_eq!(node.sample(1.), Vec4::ONE);
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if !inputs.is_empty() {
-            return Err(ExprError::GraphEvalError(
-                "Unexpected non-empty input to
-TimeNode::eval()."
-                    .to_string(),
-            ));
-        }
-        Ok([BuiltInOperator::Time, BuiltInOperator::DeltaTime]
-            .map(|op| module.builtin(op))
-            .to_vec())
-    }
-}
+        let mut module = Module::default();
 
-/// Graph node to normalize a vector value.
-#[derive(Debug, Clone)]
-pub struct NormalizeNode {
-    /// Input and output vectors.
-    slots: [SlotDef; 2],
-}
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-impl Default for NormalizeNode {
-    fn default() -> Self {
-        Self {
-            slots: [SlotDef::output("in", None), SlotDef::output("out", None)],
-        }
+        let t = module.lit(0.3);
+        let outputs = node.eval(&mut module, vec![t]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        // The smoothstep is applied first, then its result is the gradient ratio
+        assert_eq!(
+            str,
+            "mix(vec4<f32>(0.,0.,0.,0.), vec4<f32>(1.,1.,1.,1.), saturate(((smoothstep(0.2, 0.8, 0.3)) - (0.)) / (1.)))"
+        );
     }
-}
 
-impl Node for NormalizeNode {
-    fn slots(&self) -> &[SlotDef] {
-        &self.slots
-    }
+    #[test]
+    fn modf() {
+        let node = ModfNode::default();
 
-    fn eval(
-        &self,
-        module: &mut Module,
-        inputs: Vec<ExprHandle>,
-    ) -> Result<Vec<ExprHandle>, ExprError> {
-        if inputs.len() != 1 {
-            return Err(ExprError::GraphEvalError(
-                "Unexpected input slot count to NormalizeNode::eval() not
-equal to one."
-                    .to_string(),
-            ));
-        }
-        let input = inputs.into_iter().next().unwrap();
-        let norm = module.normalize(input);
-        Ok(vec![norm])
-    }
-}
+        let mut module = Module::default();
 
-#[cfg(test)]
-mod tests {
-    use bevy::prelude::*;
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-    use super::*;
-    use crate::{EvalContext, ModifierContext, ParticleLayout, PropertyLayout, ShaderWriter};
+        let value = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![value]).unwrap();
+        assert_eq!(outputs.len(), 2);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let integer = context.eval(&module, outputs[0]).unwrap();
+        let fractional = context.eval(&module, outputs[1]).unwrap();
+        assert_eq!(
+            integer,
+            format!("floor(particle.{})", Attribute::AGE.name())
+        );
+        assert_eq!(
+            fractional,
+            format!("fract(particle.{})", Attribute::AGE.name())
+        );
+    }
 
     #[test]
-    fn add() {
-        let node = AddNode::default();
+    fn spherical_to_cartesian() {
+        let node = SphericalToCartesianNode::default();
 
         let mut module = Module::default();
 
         let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
-        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let two = module.lit(2.);
-        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        // theta = 0, phi = 0, radius = 1 => +Z
+        let zero = module.lit(0.);
+        let one = module.lit(1.);
+        let outputs = node.eval(&mut module, vec![zero, zero, one]).unwrap();
         assert_eq!(outputs.len(), 1);
-        let out = outputs[0];
-
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str = context.eval(&module, out).unwrap();
-        assert_eq!(str, "(3.) + (2.)".to_string());
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            "vec3(((1.) * (cos(0.))) * (sin(0.)), (1.) * (sin(0.)), ((1.) * (cos(0.))) * (cos(0.)))"
+        );
     }
 
     #[test]
-    fn sub() {
-        let node = SubNode::default();
+    fn angle_between() {
+        let node = AngleBetweenNode::default();
 
         let mut module = Module::default();
 
         let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
-        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let two = module.lit(2.);
-        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        let a = module.attr(Attribute::VELOCITY);
+        let b = module.lit(Vec3::Y);
+        let outputs = node.eval(&mut module, vec![a, b]).unwrap();
         assert_eq!(outputs.len(), 1);
-        let out = outputs[0];
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str = context.eval(&module, out).unwrap();
-        assert_eq!(str, "(3.) - (2.)".to_string());
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "acos(clamp(dot(normalize(particle.{}), normalize(vec3<f32>(0.,1.,0.))), -1., 1.))",
+                Attribute::VELOCITY.name()
+            )
+        );
     }
 
     #[test]
-    fn mul() {
-        let node = MulNode::default();
+    fn project_reject() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut module = Module::default();
+        let a = module.attr(Attribute::VELOCITY);
+        let b = module.lit(Vec3::Y);
+        let projection = format!(
+            "(vec3<f32>(0.,1.,0.)) * ((dot(particle.{0}, vec3<f32>(0.,1.,0.))) / (max(dot(vec3<f32>(0.,1.,0.), vec3<f32>(0.,1.,0.)), 0.000001)))",
+            Attribute::VELOCITY.name()
+        );
+
+        let node = ProjectNode::default();
+        let ret = node.eval(&mut module, vec![a]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let outputs = node.eval(&mut module, vec![a, b]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        // The squared length of b is bounded, so a zero b doesn't divide by zero
+        assert_eq!(str, projection);
+
+        let node = RejectNode::default();
+        let ret = node.eval(&mut module, vec![a]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
+        let outputs = node.eval(&mut module, vec![a, b]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "(particle.{}) - ({})",
+                Attribute::VELOCITY.name(),
+                projection
+            )
+        );
+    }
+
+    #[test]
+    fn sphere_sdf() {
+        let node = SphereSdfNode::default();
 
         let mut module = Module::default();
 
         let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
-        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let two = module.lit(2.);
-        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        let point = module.attr(Attribute::POSITION);
+        let center = module.lit(Vec3::ZERO);
+        let radius = module.lit(2.);
+        let outputs = node.eval(&mut module, vec![point, center, radius]).unwrap();
         assert_eq!(outputs.len(), 1);
-        let out = outputs[0];
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str = context.eval(&module, out).unwrap();
-        assert_eq!(str, "(3.) * (2.)".to_string());
+        let str = context.eval(&module, outputs[0]).unwrap();
+        assert_eq!(
+            str,
+            format!(
+                "(length((particle.{}) - (vec3<f32>(0.,0.,0.)))) - (2.)",
+                Attribute::POSITION.name()
+            )
+        );
     }
 
     #[test]
-    fn div() {
-        let node = DivNode::default();
+    fn rounded_box_sdf() {
+        let node = RoundedBoxSdfNode::default();
 
         let mut module = Module::default();
 
         let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
-        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let two = module.lit(2.);
-        let outputs = node.eval(&mut module, vec![three, two]).unwrap();
+        let point = module.attr(Attribute::POSITION);
+        let center = module.lit(Vec3::ZERO);
+        let half_extents = module.lit(Vec3::ONE);
+        let radius = module.lit(0.1);
+        let outputs = node
+            .eval(&mut module, vec![point, center, half_extents, radius])
+            .unwrap();
         assert_eq!(outputs.len(), 1);
-        let out = outputs[0];
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str = context.eval(&module, out).unwrap();
-        assert_eq!(str, "(3.) / (2.)".to_string());
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let q = format!(
+            "((abs((particle.{}) - (vec3<f32>(0.,0.,0.)))) - (vec3<f32>(1.,1.,1.))) + (0.1)",
+            Attribute::POSITION.name()
+        );
+        assert_eq!(
+            str,
+            format!(
+                "((length(max({q}, vec3<f32>(0.,0.,0.)))) + (min(max(({q}).x, max(({q}).y, ({q}).z)), 0.))) - (0.1)"
+            )
+        );
     }
 
     #[test]
-    fn attr() {
-        let node = AttributeNode::new(Attribute::POSITION);
+    fn quat_rotate() {
+        let node = QuatRotateNode::default();
 
         let mut module = Module::default();
 
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
+        let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let outputs = node.eval(&mut module, vec![]).unwrap();
+        // Identity quaternion
+        let value: Value = Quat::IDENTITY.into();
+        assert_eq!(value, Value::Vector(Vec4::new(0., 0., 0., 1.).into()));
+        let quat = module.lit(value);
+        let vector = module.lit(Vec3::X);
+        let outputs = node.eval(&mut module, vec![quat, vector]).unwrap();
         assert_eq!(outputs.len(), 1);
-        let out = outputs[0];
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str = context.eval(&module, out).unwrap();
-        assert_eq!(str, format!("particle.{}", Attribute::POSITION.name()));
+        let str = context.eval(&module, outputs[0]).unwrap();
+        let q = "vec4<f32>(0.,0.,0.,1.)";
+        let axis = format!("vec3({q}.x, {q}.y, {q}.z)");
+        let t = format!("(2.) * (cross({axis}, vec3<f32>(1.,0.,0.)))");
+        assert_eq!(
+            str,
+            format!("((vec3<f32>(1.,0.,0.)) + (({q}.w) * ({t}))) + (cross({axis}, {t}))")
+        );
     }
 
     #[test]
-    fn time() {
-        let node = TimeNode::default();
+    fn axis_angle_matrix() {
+        let node = AxisAngleMatrixNode::default();
 
         let mut module = Module::default();
 
-        let three = module.lit(3.);
-        let ret = node.eval(&mut module, vec![three]);
+        let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let outputs = node.eval(&mut module, vec![]).unwrap();
-        assert_eq!(outputs.len(), 2);
+        let axis = module.lit(Vec3::Z);
+        let angle = module.attr(Attribute::AGE);
+        let outputs = node.eval(&mut module, vec![axis, angle]).unwrap();
+        assert_eq!(outputs.len(), 1);
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
-        let str0 = context.eval(&module, outputs[0]).unwrap();
-        let str1 = context.eval(&module, outputs[1]).unwrap();
-        assert_eq!(str0, format!("sim_params.{}", BuiltInOperator::Time.name()));
+        let str = context.eval(&module, outputs[0]).unwrap();
+
+        // Rodrigues formula for the rotation of each unit axis
+        let k = "normalize(vec3<f32>(0.,0.,1.))";
+        let cos = "cos(particle.age)";
+        let sin = "sin(particle.age)";
+        let col = |e: &str| {
+            format!(
+                "((({cos}) * ({e})) + (({sin}) * (cross({k}, {e})))) + \
+                 ((((1.) - ({cos})) * (dot({k}, {e}))) * ({k}))"
+            )
+        };
+        let (x, y, z) = (
+            col("vec3<f32>(1.,0.,0.)"),
+            col("vec3<f32>(0.,1.,0.)"),
+            col("vec3<f32>(0.,0.,1.)"),
+        );
+        assert_eq!(str, format!("mat3x3<f32>({x}, {y}, {z})"));
+
+        // The output matrix can be fed into a MatrixTransformNode
+        let mut g = Graph::new();
+        let nid_axis = g.add_node(LiteralNode::new(Vec3::Y));
+        let nid_angle = g.add_node(LiteralNode::new(1.5_f32));
+        let nid_matrix = g.add_node(AxisAngleMatrixNode::default());
+        let nid_transform = g.add_node(MatrixTransformNode::default());
+        g.link(
+            g.output_slot(nid_axis, "value").unwrap(),
+            g.input_slot(nid_matrix, "axis").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_angle, "value").unwrap(),
+            g.input_slot(nid_matrix, "angle").unwrap(),
+        );
+        g.link(
+            g.output_slot(nid_matrix, "matrix").unwrap(),
+            g.input_slot(nid_transform, "matrix").unwrap(),
+        );
         assert_eq!(
-            str1,
-            format!("sim_params.{}", BuiltInOperator::DeltaTime.name())
+            g.infer_slot_type(g.output_slot(nid_matrix, "matrix").unwrap()),
+            Some(ValueType::Matrix(MatrixType::MAT3X3F))
         );
     }
 
     #[test]
-    fn normalize() {
-        let node = NormalizeNode::default();
+    fn matrix_transform() {
+        let node = MatrixTransformNode::default();
 
         let mut module = Module::default();
 
         let ret = node.eval(&mut module, vec![]);
         assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let ones = module.lit(Vec3::ONE);
-        let outputs = node.eval(&mut module, vec![ones]).unwrap();
+        let matrix = module.lit(Value::Matrix(Mat3::IDENTITY.into()));
+        let vector = module.attr(Attribute::POSITION);
+        let outputs = node.eval(&mut module, vec![matrix, vector]).unwrap();
         assert_eq!(outputs.len(), 1);
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context =
             ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
         let str = context.eval(&module, outputs[0]).unwrap();
-        assert_eq!(str, "normalize(vec3<f32>(1.,1.,1.))".to_string());
+        assert_eq!(
+            str,
+            "(mat3x3<f32>(1.,0.,0.,0.,1.,0.,0.,0.,1.)) * (particle.position)".to_string()
+        );
     }
 
     #[test]
-    fn graph() {
-        let mut g = Graph::new();
+    fn cartesian_to_spherical() {
+        let node = CartesianToSphericalNode::default();
 
-        let nid_pos = g.add_node(AttributeNode::new(Attribute::POSITION));
-        let nid_add = g.add_node(AddNode::default());
-        let sid_pos = g.output_slots(nid_pos)[0];
-        let sid_add_lhs = g.input_slots(nid_add)[0];
-        let sid_add_rhs = g.input_slots(nid_add)[1];
-        g.link(sid_pos, sid_add_lhs);
+        let mut module = Module::default();
 
-        let nid_vel = g.add_node(AttributeNode::new(Attribute::VELOCITY));
-        let nid_mul = g.add_node(MulNode::default());
-        let nid_dt = g.add_node(TimeNode::default());
-        let sid_vel = g.output_slots(nid_vel)[0];
-        let sid_dt = g
-            .output_slot(nid_dt, BuiltInOperator::DeltaTime.name())
-            .unwrap();
-        let sid_mul_lhs = g.input_slots(nid_mul)[0];
-        let sid_mul_rhs = g.input_slots(nid_mul)[1];
-        g.link(sid_vel, sid_mul_lhs);
-        g.link(sid_dt, sid_mul_rhs);
+        let ret = node.eval(&mut module, vec![]);
+        assert!(matches!(ret, Err(ExprError::GraphEvalError(_))));
 
-        let sid_mul_out = g.output_slots(nid_mul)[0];
-        g.link(sid_mul_out, sid_add_rhs);
+        let v = module.attr(Attribute::VELOCITY);
+        let outputs = node.eval(&mut module, vec![v]).unwrap();
+        assert_eq!(outputs.len(), 3);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let theta = context.eval(&module, outputs[0]).unwrap();
+        let phi = context.eval(&module, outputs[1]).unwrap();
+        let radius = context.eval(&module, outputs[2]).unwrap();
+        let v = format!("particle.{}", Attribute::VELOCITY.name());
+        let len_xz = format!("length(vec2({v}.x, {v}.z))");
+        assert_eq!(
+            theta,
+            format!("atan2({v}.x, ({v}.z) + (step({len_xz}, 0.)))")
+        );
+        assert_eq!(
+            phi,
+            format!("atan2({v}.y, ({len_xz}) + (step(length({v}), 0.)))")
+        );
+        assert_eq!(radius, format!("length({v})"));
+    }
+
+    #[test]
+    fn spherical_round_trip() {
+        let mut module = Module::default();
+        let theta = module.lit(0.3);
+        let phi = module.lit(-1.2);
+        let radius = module.lit(2.5);
+
+        let fwd = SphericalToCartesianNode::default();
+        let vector = fwd.eval(&mut module, vec![theta, phi, radius]).unwrap()[0];
+        assert!(matches!(
+            module.get(vector),
+            Some(Expr::Ternary {
+                op: TernaryOperator::Vec3,
+                ..
+            })
+        ));
+
+        // The inverse conversion reads back the components of the vector produced by
+        // the forward conversion.
+        let inv = CartesianToSphericalNode::default();
+        let outputs = inv.eval(&mut module, vec![vector]).unwrap();
+        assert_eq!(outputs.len(), 3);
+        for (output, op) in [
+            (outputs[0], UnaryOperator::X),
+            (outputs[1], UnaryOperator::Y),
+        ] {
+            let Some(&Expr::Binary {
+                op: BinaryOperator::Atan2,
+                left,
+                ..
+            }) = module.get(output)
+            else {
+                panic!("Expected atan2() expression");
+            };
+            assert_eq!(module.get(left), Some(&Expr::Unary { op, expr: vector }));
+        }
+        assert_eq!(
+            module.get(outputs[2]),
+            Some(&Expr::Unary {
+                op: UnaryOperator::Length,
+                expr: vector
+            })
+        );
     }
 }
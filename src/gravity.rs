@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+
+/// Gravity applied to the particles of a single effect instance.
+///
+/// Insert this component on the same entity as the [`ParticleEffect`] to
+/// accelerate all its particles by the given gravity vector, in units per
+/// second squared. This is a lightweight alternative to authoring an
+/// [`AccelModifier`] expression for effects which only need a constant
+/// acceleration.
+///
+/// The gravity is uploaded each frame to the GPU alongside the other
+/// per-effect spawn parameters, so changing its value takes effect on the next
+/// frame without recompiling the effect's shaders. This allows for example
+/// switching particles to zero-G while inside a given zone. However adding or
+/// removing the component triggers a recompiling of the effect's shaders.
+///
+/// The update pass applies the gravity after all update modifiers, before any
+/// motion integration. The gravity has no effect on effects without
+/// [`Attribute::VELOCITY`].
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// # use bevy::prelude::*;
+/// fn zero_g(mut q_gravity: Query<(&mut EffectGravity, &GlobalTransform)>) {
+///     for (mut gravity, transform) in &mut q_gravity {
+///         gravity.0 = if transform.translation().y > 100. {
+///             Vec3::ZERO
+///         } else {
+///             Vec3::new(0., -9.81, 0.)
+///         };
+///     }
+/// }
+/// ```
+///
+/// [`ParticleEffect`]: crate::ParticleEffect
+/// [`AccelModifier`]: crate::AccelModifier
+/// [`Attribute::VELOCITY`]: crate::Attribute::VELOCITY
+#[derive(Debug, Default, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct EffectGravity(pub Vec3);
@@ -176,32 +176,55 @@ use thiserror::Error;
 mod asset;
 pub mod attributes;
 mod bundle;
+mod globals;
 mod gradient;
 pub mod graph;
+mod gravity;
+#[cfg(feature = "lights")]
+mod light;
 pub mod modifier;
 mod plugin;
 pub mod properties;
 mod render;
 mod spawn;
+mod thumbnail;
 mod time;
+mod wind;
 
 #[cfg(test)]
 mod test_utils;
 
-pub use asset::{AlphaMode, EffectAsset, MotionIntegration, SimulationCondition};
+pub use asset::{
+    AlphaMode, EffectAsset, EffectRenderLayer, MotionIntegration, PositionBounds,
+    PositionBoundsMode, RngAlgorithm, SimulationCondition,
+};
 pub use attributes::*;
 pub use bundle::ParticleEffectBundle;
+pub use globals::EffectGlobals;
 pub use gradient::{Gradient, GradientKey};
 pub use graph::*;
+pub use gravity::EffectGravity;
+#[cfg(feature = "lights")]
+pub use light::{
+    update_particle_lights, ParticleLight, ParticleLightSample, ParticleLightSamples,
+    ParticleLights,
+};
 pub use modifier::*;
-pub use plugin::{EffectSystems, HanabiPlugin};
+pub use plugin::{main_graph, simulate_graph, EffectSystems, HanabiPlugin};
 pub use properties::*;
-pub use render::{LayoutFlags, ShaderCache};
+pub use render::{
+    LayoutFlags, ShaderCache, SimulationOnlyBuffer, SimulationOnlyBuffers, VfxSimulateNode,
+};
 pub use spawn::{
-    tick_spawners, Cloner, CpuValue, EffectCloner, EffectInitializer, EffectInitializers,
-    EffectSpawner, Random, Spawner,
+    apply_particle_budget, tick_spawners, Cloner, CpuValue, EffectCloner, EffectInitializer,
+    EffectInitializers, EffectSpawner, GlobalParticleBudget, Random, Spawner,
+};
+pub use thumbnail::{
+    EffectThumbnail, EffectThumbnailCopyLabel, EffectThumbnailPlugin, EffectThumbnailReady,
+    EffectThumbnailState,
 };
 pub use time::{EffectSimulation, EffectSimulationTime};
+pub use wind::{AffectedByWind, WindZone};
 
 #[allow(missing_docs)]
 pub mod prelude {
@@ -711,6 +734,8 @@ pub(crate) struct EffectShader {
     pub init: Handle<Shader>,
     pub update: Handle<Shader>,
     pub render: Handle<Shader>,
+    /// Render shader of the secondary render layer, if any.
+    pub secondary_render: Option<Handle<Shader>>,
 }
 
 /// Source code (WGSL) of an effect.
@@ -722,7 +747,11 @@ pub(crate) struct EffectShader {
 #[derive(Debug)]
 struct EffectShaderSource {
     pub shaders: Vec<EffectGroupShaderSource>,
+    /// Points of the point clouds of all groups, in group order.
+    pub point_cloud: Vec<Vec3>,
     pub layout_flags: LayoutFlags,
+    /// Layout flags of the secondary render layer, if the effect has one.
+    pub secondary_layout_flags: Option<LayoutFlags>,
 }
 
 #[derive(Debug)]
@@ -730,6 +759,40 @@ struct EffectGroupShaderSource {
     init: String,
     update: String,
     render: String,
+    /// Render shader of the secondary render layer, if the effect has one.
+    secondary_render: Option<String>,
+}
+
+/// WGSL source code of the shaders of an effect.
+///
+/// Each field contains one shader per particle group, in group order. See
+/// [`EffectAsset::generated_wgsl()`] for details.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompiledShaders {
+    /// Source code of the init shader of each group.
+    pub init: Vec<String>,
+    /// Source code of the update shader of each group.
+    pub update: Vec<String>,
+    /// Source code of the render shader of each group.
+    pub render: Vec<String>,
+    /// Source code of the render shader of the secondary render layer of each
+    /// group, or empty if the effect doesn't have a secondary render layer.
+    ///
+    /// See [`EffectAsset::render_layer()`] for details.
+    pub secondary_render: Vec<String>,
+}
+
+impl From<EffectShaderSource> for CompiledShaders {
+    fn from(source: EffectShaderSource) -> Self {
+        let mut shaders = Self::default();
+        for group in source.shaders {
+            shaders.init.push(group.init);
+            shaders.update.push(group.update);
+            shaders.render.push(group.render);
+            shaders.secondary_render.extend(group.secondary_render);
+        }
+        shaders
+    }
 }
 
 /// Error resulting from the generating of the WGSL shader code of an
@@ -748,7 +811,16 @@ impl EffectShaderSource {
     ///
     /// This takes a base asset effect and generate the WGSL code for the
     /// various shaders (init/update/render).
-    pub fn generate(asset: &EffectAsset) -> Result<EffectShaderSource, ShaderGenerateError> {
+    ///
+    /// If `affected_by_wind` is `true`, the update shader also applies the
+    /// global [`WindZone`] to the particles. Similarly, if `has_gravity` is
+    /// `true`, the update shader applies the [`EffectGravity`] of the effect
+    /// instance.
+    pub fn generate(
+        asset: &EffectAsset,
+        affected_by_wind: bool,
+        has_gravity: bool,
+    ) -> Result<EffectShaderSource, ShaderGenerateError> {
         let particle_layout = asset.particle_layout();
 
         // The particle layout cannot be empty currently because we always emit some
@@ -778,8 +850,7 @@ impl EffectShaderSource {
         // present, or a default value.
         let mut inputs_code = String::new();
         // All required attributes, except the size/color which are variadic
-        let required_attributes =
-            HashSet::from_iter([Attribute::AXIS_X, Attribute::AXIS_Y, Attribute::AXIS_Z]);
+        let required_attributes = [Attribute::AXIS_X, Attribute::AXIS_Y, Attribute::AXIS_Z];
         let mut present_attributes = HashSet::new();
         let mut has_size = false;
         let mut has_color = false;
@@ -839,7 +910,11 @@ impl EffectShaderSource {
                 Attribute::HDR_COLOR.default_value().to_wgsl_string() // TODO - or COLOR?
             );
         }
-        for &attr in required_attributes.difference(&present_attributes) {
+        // Note: iterate in a fixed order to ensure the generated code is deterministic.
+        for attr in required_attributes
+            .into_iter()
+            .filter(|attr| !present_attributes.contains(attr))
+        {
             inputs_code += &format!(
                 "var {} = {};\n",
                 attr.name(),
@@ -871,22 +946,164 @@ impl EffectShaderSource {
         if asset.ribbon_group.is_some() {
             layout_flags |= LayoutFlags::RIBBONS;
         }
+        if asset.simulation_only {
+            layout_flags |= LayoutFlags::SIMULATION_ONLY;
+        }
+        if asset.double_buffered {
+            if asset.ribbon_group.is_some()
+                || particle_layout.contains(Attribute::PREV)
+                || particle_layout.contains(Attribute::NEXT)
+            {
+                return Err(ShaderGenerateError::Validate(format!(
+                    "Asset {} uses double-buffering, which is not supported with ribbons and trails linking particles with Attribute::PREV and Attribute::NEXT.",
+                    asset.name
+                )));
+            }
+            layout_flags |= LayoutFlags::DOUBLE_BUFFERED;
+        }
+        if asset.premultiply_alpha {
+            if asset.alpha_mode == AlphaMode::Blend {
+                layout_flags |= LayoutFlags::PREMULTIPLY_ALPHA;
+            } else {
+                warn!(
+                    "Asset {} premultiplies alpha but uses the {:?} alpha mode instead of AlphaMode::Blend; ignored.",
+                    asset.name, asset.alpha_mode
+                );
+            }
+        }
+
+        // Generate the shader code declaring the user-defined constants, if any. This
+        // is injected into all shaders.
+        let mut shader_defines_code = String::new();
+        for (name, value) in asset.shader_defines() {
+            let is_ident = matches!(name.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_ident {
+                return Err(ShaderGenerateError::Validate(format!(
+                    "Asset {} has invalid shader define name '{}'. The name must be a valid WGSL identifier.",
+                    asset.name, name
+                )));
+            }
+            shader_defines_code += &format!(
+                "const {}: {} = {};\n",
+                name,
+                value.value_type().to_wgsl_string(),
+                value.to_wgsl_string()
+            );
+        }
+
+        // Select the PRNG algorithm of all shaders. This is a preprocessor define
+        // consumed by vfx_common.wgsl, so must come first in the top-level shader.
+        let rng_define_code = asset
+            .rng_algorithm
+            .shader_define()
+            .map(|define| format!("#define {}\n", define))
+            .unwrap_or_default();
+
+        // Render flags derived from the render modifiers are tracked separately for the
+        // secondary render layer, which has its own render pipeline.
+        let mut secondary_layout_flags = layout_flags;
+        let render_layers: &[EffectRenderLayer] = if asset.has_secondary_render_layer() {
+            &[EffectRenderLayer::Primary, EffectRenderLayer::Secondary]
+        } else {
+            &[EffectRenderLayer::Primary]
+        };
 
         let mut group_shader_sources = vec![];
 
+        // Points of the point clouds of all groups, uploaded into a single storage
+        // buffer shared by all init shaders.
+        let mut point_cloud = vec![];
+
         // Configure the init shader template, and make sure a corresponding shader
         // asset exists
         for dest_group_index in 0..(asset.init.len() as u32) {
             // Generate the shader code for the initializing shader
             let (init_code, init_extra, init_sim_space_transform_code) = {
                 let mut init_context =
-                    ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+                    ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout)
+                        .with_point_cloud_base(point_cloud.len() as u32);
                 for m in asset.init_modifiers_for_group(dest_group_index) {
                     if let Err(err) = m.apply(&mut module, &mut init_context) {
                         error!("Failed to compile effect, error in init context: {:?}", err);
                         return Err(ShaderGenerateError::Expr(err));
                     }
                 }
+                if !init_context.point_cloud.is_empty() {
+                    init_context.extra_code.insert_str(
+                        0,
+                        "@group(1) @binding(5) var<storage, read> point_cloud : array<vec4<f32>>;\n\n",
+                    );
+                    point_cloud.append(&mut init_context.point_cloud);
+                }
+
+                // Offset the spawn position after all init modifiers, so the jitter
+                // applies whatever the shape modifier used.
+                if let (Some(position_jitter), Initializer::Spawner(_)) = (
+                    asset.position_jitter,
+                    &asset.init[dest_group_index as usize],
+                ) {
+                    let jitter = init_context.eval(&module, position_jitter).map_err(|err| {
+                        error!("Failed to compile effect's position jitter: {:?}", err);
+                        ShaderGenerateError::Expr(err)
+                    })?;
+                    init_context.main_code +=
+                        &format!("particle.{} += {};\n", Attribute::POSITION.name(), jitter);
+                }
+
+                // Snap the spawn position last, so it catches the jitter too. Axes with a
+                // zero spacing are left untouched, and use a dummy spacing to avoid any
+                // division by zero.
+                if let (Some(grid), Initializer::Spawner(_)) =
+                    (asset.snap_to_grid, &asset.init[dest_group_index as usize])
+                {
+                    let grid = grid.abs();
+                    let mask = grid.cmpne(Vec3::ZERO);
+                    if mask.any() {
+                        let safe_grid = Vec3::select(mask, grid, Vec3::ONE);
+                        let snapped = format!(
+                            "round(particle.{0} / {1}) * {1}",
+                            Attribute::POSITION.name(),
+                            safe_grid.to_wgsl_string()
+                        );
+                        let snapped = if mask.all() {
+                            snapped
+                        } else {
+                            format!(
+                                "select(particle.{}, {}, vec3<bool>({}, {}, {}))",
+                                Attribute::POSITION.name(),
+                                snapped,
+                                mask.x,
+                                mask.y,
+                                mask.z
+                            )
+                        };
+                        init_context.main_code +=
+                            &format!("particle.{} = {};\n", Attribute::POSITION.name(), snapped);
+                    }
+                }
+
+                // Assign the initial age after all init modifiers, so the lifetime the age
+                // is clamped to is known.
+                if let (Some(initial_age), Initializer::Spawner(_)) =
+                    (asset.initial_age, &asset.init[dest_group_index as usize])
+                {
+                    let initial_age = init_context.eval(&module, initial_age).map_err(|err| {
+                        error!("Failed to compile effect's initial age: {:?}", err);
+                        ShaderGenerateError::Expr(err)
+                    })?;
+                    let initial_age = if particle_layout.contains(Attribute::LIFETIME) {
+                        format!(
+                            "clamp({}, 0., particle.{})",
+                            initial_age,
+                            Attribute::LIFETIME.name()
+                        )
+                    } else {
+                        format!("max({}, 0.)", initial_age)
+                    };
+                    init_context.main_code +=
+                        &format!("particle.{} = {};\n", Attribute::AGE.name(), initial_age);
+                }
 
                 let sim_space_transform_code =
                     asset.simulation_space.eval(&init_context).map_err(|err| {
@@ -896,7 +1113,7 @@ impl EffectShaderSource {
 
                 (
                     init_context.main_code,
-                    init_context.extra_code,
+                    shader_defines_code.clone() + &init_context.extra_code,
                     sim_space_transform_code,
                 )
             };
@@ -932,12 +1149,49 @@ impl EffectShaderSource {
                         return Err(ShaderGenerateError::Expr(err));
                     }
                 }
-                (update_context.main_code, update_context.extra_code)
+                (
+                    update_context.main_code,
+                    shader_defines_code.clone() + &update_context.extra_code,
+                )
             };
 
-            // Insert Euler motion integration if needed.
             let has_position = present_attributes.contains(&Attribute::POSITION);
             let has_velocity = present_attributes.contains(&Attribute::VELOCITY);
+
+            // Apply the global wind after all update modifiers, before any motion
+            // integration. The wind parameters are part of the simulation params, so
+            // changing them doesn't require recompiling the shader.
+            if affected_by_wind {
+                if has_velocity {
+                    update_code += &format!(
+                        "\n// Wind\nparticle.{0} += (sim_params.wind_direction * sim_params.wind_strength + (frand3() * 2. - 1.) * sim_params.wind_turbulence) * sim_params.delta_time;\n",
+                        Attribute::VELOCITY.name()
+                    );
+                } else {
+                    warn!(
+                        "Asset {} is affected by wind but is missing Attribute::VELOCITY.",
+                        asset.name
+                    );
+                }
+            }
+
+            // Apply the per-effect gravity. Like the wind, the gravity is uploaded each
+            // frame with the spawner params, so changing it doesn't require recompiling.
+            if has_gravity {
+                if has_velocity {
+                    update_code += &format!(
+                        "\n// Gravity\nparticle.{0} += spawner.gravity * sim_params.delta_time;\n",
+                        Attribute::VELOCITY.name()
+                    );
+                } else {
+                    warn!(
+                        "Asset {} has an EffectGravity but is missing Attribute::VELOCITY.",
+                        asset.name
+                    );
+                }
+            }
+
+            // Insert Euler motion integration if needed.
             if asset.motion_integration != MotionIntegration::None {
                 if has_position && has_velocity {
                     // Note the prepended "\n" to prevent appending to a comment line.
@@ -964,83 +1218,61 @@ impl EffectShaderSource {
                 }
             }
 
-            // Generate the shader code for the render shader
-            let (
-                vertex_code,
-                fragment_code,
-                render_extra,
-                alpha_cutoff_code,
-                flipbook_scale_code,
-                flipbook_row_count_code,
-                material_bindings_code,
-            ) = {
-                let texture_layout = module.texture_layout();
-                let mut render_context =
-                    RenderContext::new(&property_layout, &particle_layout, &texture_layout);
-                for m in asset.render_modifiers_for_group(dest_group_index) {
-                    m.apply_render(&mut module, &mut render_context)
-                        .map_err(ShaderGenerateError::Expr)?;
-                }
-
-                if render_context.needs_uv {
-                    layout_flags |= LayoutFlags::NEEDS_UV;
-                }
-
-                let alpha_cutoff_code = if let AlphaMode::Mask(cutoff) = &asset.alpha_mode {
-                    render_context.eval(&module, *cutoff).unwrap_or_else(|err| {
-                        error!(
-                            "Failed to evaluate the expression for AlphaMode::Mask, error: {:?}",
-                            err
+            // Move the particles along with a fraction of the emitter motion. The motion
+            // is uploaded each frame with the spawner params.
+            if asset.simulation_space == SimulationSpace::Global
+                && asset.simulation_space_blend > 0.
+            {
+                if has_position {
+                    let blend = asset.simulation_space_blend.to_wgsl_string();
+                    update_code += &format!(
+                        "\n// Emitter motion\n{{\n    let emitter_motion = transpose(mat4x4(spawner.emitter_motion[0], spawner.emitter_motion[1], spawner.emitter_motion[2], vec4<f32>(0.0, 0.0, 0.0, 1.0)));\n    particle.{0} = mix(particle.{0}, (emitter_motion * vec4<f32>(particle.{0}, 1.0)).xyz, {1});\n",
+                        Attribute::POSITION.name(),
+                        blend
+                    );
+                    if has_velocity {
+                        update_code += &format!(
+                            "    particle.{0} = mix(particle.{0}, (emitter_motion * vec4<f32>(particle.{0}, 0.0)).xyz, {1});\n",
+                            Attribute::VELOCITY.name(),
+                            blend
                         );
-
-                        // In Debug, show everything to help diagnosing
-                        #[cfg(debug_assertions)]
-                        return 1_f32.to_wgsl_string();
-
-                        // In Release, hide everything with an error
-                        #[cfg(not(debug_assertions))]
-                        return 0_f32.to_wgsl_string();
-                    })
+                    }
+                    update_code += "}\n";
                 } else {
-                    String::new()
-                };
+                    warn!(
+                        "Asset {} specifies a simulation space blend but is missing Attribute::POSITION.",
+                        asset.name
+                    );
+                }
+            }
 
-                let (flipbook_scale_code, flipbook_row_count_code) =
-                    if let Some(grid_size) = render_context.sprite_grid_size {
-                        layout_flags |= LayoutFlags::FLIPBOOK;
-                        // Note: row_count needs to be i32, not u32, because of sprite_index
-                        let flipbook_row_count_code = (grid_size.x as i32).to_wgsl_string();
-                        let flipbook_scale_code =
-                            Vec2::new(1.0 / grid_size.x as f32, 1.0 / grid_size.y as f32)
-                                .to_wgsl_string();
-                        (flipbook_scale_code, flipbook_row_count_code)
-                    } else {
-                        (String::new(), String::new())
+            // Apply the position bounds last, so they also catch any runaway position
+            // produced by the motion integration.
+            if let Some(bounds) = &asset.position_bounds {
+                if has_position {
+                    let min = bounds.min.to_wgsl_string();
+                    let max = bounds.max.to_wgsl_string();
+                    update_code += &match bounds.mode {
+                        PositionBoundsMode::Clamp => format!(
+                            "\n// Position bounds\nparticle.{0} = clamp(particle.{0}, {1}, {2});\n",
+                            Attribute::POSITION.name(),
+                            min,
+                            max
+                        ),
+                        PositionBoundsMode::Kill => format!(
+                            "\n// Position bounds\nif (any(particle.{0} < {1}) || any(particle.{0} > {2})) {{\n    is_alive = false;\n}}\n",
+                            Attribute::POSITION.name(),
+                            min,
+                            max
+                        ),
                     };
-
-                trace!(
-                    "Generating material bindings code for layout: {:?}",
-                    texture_layout
-                );
-                let mut material_bindings_code = String::new();
-                for (slot, _) in texture_layout.layout.iter().enumerate() {
-                    material_bindings_code.push_str(&format!(
-                        "@group(2) @binding(0) var material_texture_{slot}: texture_2d<f32>;
-@group(2) @binding(1) var material_sampler_{slot}: sampler;
-"
-                    ));
+                } else {
+                    warn!(
+                        "Asset {} specifies position bounds but is missing Attribute::POSITION.",
+                        asset.name
+                    );
                 }
-
-                (
-                    render_context.vertex_code,
-                    render_context.fragment_code,
-                    render_context.render_extra,
-                    alpha_cutoff_code,
-                    flipbook_scale_code,
-                    flipbook_row_count_code,
-                    material_bindings_code,
-                )
-            };
+            }
 
             // Configure aging code
             let has_age = present_attributes.contains(&Attribute::AGE);
@@ -1082,10 +1314,20 @@ impl EffectShaderSource {
             // assignment. Otherwise we might race on `PREV` and `NEXT`
             // attributes, which might be updated behind our back when adjacent
             // particles die.
+            //
+            // Iterate in the order of the particle layout rather than the (unordered)
+            // set of present attributes, to ensure the generated code is deterministic.
             let mut writeback_code = "".to_owned();
-            for attribute in present_attributes.iter().filter(|attribute| {
-                **attribute != Attribute::PREV && **attribute != Attribute::NEXT
-            }) {
+            for attribute in particle_layout
+                .attributes()
+                .iter()
+                .map(|attr_layout| attr_layout.attribute)
+                .filter(|attribute| {
+                    present_attributes.contains(attribute)
+                        && *attribute != Attribute::PREV
+                        && *attribute != Attribute::NEXT
+                })
+            {
                 writeln!(
                     &mut writeback_code,
                     "    particle_buffer.particles[index].{0} = particle.{0};",
@@ -1094,6 +1336,22 @@ impl EffectShaderSource {
                 .unwrap();
             }
 
+            // Clamp the delta time of all the update code, including the aging, if
+            // requested. The simulation params are shared by all effects, so this is
+            // patched into the shader instead.
+            let (age_code, update_code, update_extra) = match asset.max_delta_time {
+                Some(max_delta_time) => {
+                    let clamped_delta_time = format!(
+                        "min(sim_params.delta_time, {})",
+                        max_delta_time.to_wgsl_string()
+                    );
+                    let clamp =
+                        |code: String| code.replace("sim_params.delta_time", &clamped_delta_time);
+                    (clamp(age_code), clamp(update_code), clamp(update_extra))
+                }
+                None => (age_code, update_code, update_extra),
+            };
+
             let dest_group_index_code = format!("{}", dest_group_index);
 
             // Configure the update shader template, and make sure a corresponding shader
@@ -1110,30 +1368,195 @@ impl EffectShaderSource {
                 .replace("{{GROUP_INDEX}}", &dest_group_index_code);
             trace!("Configured update shader:\n{}", update_shader_source);
 
-            // Configure the render shader template, and make sure a corresponding shader
-            // asset exists
-            let render_shader_source = PARTICLES_RENDER_SHADER_TEMPLATE
-                .replace("{{ATTRIBUTES}}", &attributes_code)
-                .replace("{{INPUTS}}", &inputs_code)
-                .replace("{{MATERIAL_BINDINGS}}", &material_bindings_code)
-                .replace("{{VERTEX_MODIFIERS}}", &vertex_code)
-                .replace("{{FRAGMENT_MODIFIERS}}", &fragment_code)
-                .replace("{{RENDER_EXTRA}}", &render_extra)
-                .replace("{{ALPHA_CUTOFF}}", &alpha_cutoff_code)
-                .replace("{{FLIPBOOK_SCALE}}", &flipbook_scale_code)
-                .replace("{{FLIPBOOK_ROW_COUNT}}", &flipbook_row_count_code);
-            trace!("Configured render shader:\n{}", render_shader_source);
+            // Generate the shader code for the render shader of each render layer, and
+            // configure the render shader template. All layers draw the same particles,
+            // and only differ by their render modifiers, so by their render flags.
+            let mut render_shader_sources = Vec::with_capacity(render_layers.len());
+            for &layer in render_layers {
+                let layer_flags = match layer {
+                    EffectRenderLayer::Primary => &mut layout_flags,
+                    EffectRenderLayer::Secondary => &mut secondary_layout_flags,
+                };
+                let (
+                    interpolants_code,
+                    fragment_interpolants_code,
+                    vertex_code,
+                    fragment_code,
+                    render_extra,
+                    alpha_cutoff_code,
+                    flipbook_scale_code,
+                    flipbook_row_count_code,
+                    flipbook_frame_count_code,
+                    material_bindings_code,
+                ) = {
+                    let texture_layout = module.texture_layout();
+                    let mut render_context =
+                        RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+                    // Simulation-only effects never draw, so their render modifiers are
+                    // ignored and the render shader is left with its defaults.
+                    let render_modifiers: Vec<_> = if asset.simulation_only {
+                        if asset
+                            .render_modifiers_for_layer(dest_group_index, layer)
+                            .next()
+                            .is_some()
+                        {
+                            warn!(
+                                "Asset {} is simulation-only but has render modifiers in layer {:?} for group #{}; they will be ignored.",
+                                asset.name, layer, dest_group_index
+                            );
+                        }
+                        vec![]
+                    } else {
+                        asset
+                            .render_modifiers_for_layer(dest_group_index, layer)
+                            .collect()
+                    };
+                    for m in render_modifiers {
+                        m.apply_render(&mut module, &mut render_context)
+                            .map_err(ShaderGenerateError::Expr)?;
+                    }
+
+                    if render_context.needs_uv {
+                        *layer_flags |= LayoutFlags::NEEDS_UV;
+
+                        // Texture sampling helper, which cross-fades between the current and next
+                        // flipbook sprites if frame blending is enabled.
+                        let sample_code = if render_context.flipbook_blend {
+                            "let color = textureSample(tex, samp, uv);
+    let color_next = textureSample(tex, samp, uv + flipbook_uv_next_offset);
+    return mix(color, color_next, flipbook_blend);"
+                        } else {
+                            "return textureSample(tex, samp, uv);"
+                        };
+                        render_context.render_extra += &format!(
+                            "fn sample_material(tex: texture_2d<f32>, samp: sampler, uv: vec2<f32>) -> vec4<f32> {{
+    {sample_code}
+}}
+"
+                        );
+                    }
+                    if render_context.needs_world_normal {
+                        *layer_flags |= LayoutFlags::NEEDS_WORLD_NORMAL;
+                    }
+
+                    let alpha_cutoff_code = if let AlphaMode::Mask(cutoff) = &asset.alpha_mode {
+                        render_context.eval(&module, *cutoff).unwrap_or_else(|err| {
+                            error!(
+                                "Failed to evaluate the expression for AlphaMode::Mask, error: {:?}",
+                                err
+                            );
+
+                            // In Debug, show everything to help diagnosing
+                            #[cfg(debug_assertions)]
+                            return 1_f32.to_wgsl_string();
+
+                            // In Release, hide everything with an error
+                            #[cfg(not(debug_assertions))]
+                            return 0_f32.to_wgsl_string();
+                        })
+                    } else {
+                        String::new()
+                    };
+
+                    let (flipbook_scale_code, flipbook_row_count_code, flipbook_frame_count_code) =
+                        if let Some(grid_size) = render_context.sprite_grid_size {
+                            *layer_flags |= LayoutFlags::FLIPBOOK;
+                            if render_context.flipbook_blend {
+                                *layer_flags |= LayoutFlags::FLIPBOOK_BLEND;
+                            }
+                            // Note: row_count needs to be i32, not u32, because of sprite_index
+                            let flipbook_row_count_code = (grid_size.x as i32).to_wgsl_string();
+                            let flipbook_frame_count_code =
+                                ((grid_size.x * grid_size.y) as i32).to_wgsl_string();
+                            let flipbook_scale_code =
+                                Vec2::new(1.0 / grid_size.x as f32, 1.0 / grid_size.y as f32)
+                                    .to_wgsl_string();
+                            (
+                                flipbook_scale_code,
+                                flipbook_row_count_code,
+                                flipbook_frame_count_code,
+                            )
+                        } else {
+                            (String::new(), String::new(), String::new())
+                        };
+
+                    trace!(
+                        "Generating material bindings code for layout: {:?}",
+                        texture_layout
+                    );
+                    let mut material_bindings_code = String::new();
+                    for (slot, _) in texture_layout.layout.iter().enumerate() {
+                        material_bindings_code.push_str(&format!(
+                            "@group(2) @binding(0) var material_texture_{slot}: texture_2d<f32>;
+@group(2) @binding(1) var material_sampler_{slot}: sampler;
+"
+                        ));
+                    }
+
+                    // Premultiply once all modifiers, including any texture sampling, have
+                    // produced the final straight alpha color.
+                    if layer_flags.contains(LayoutFlags::PREMULTIPLY_ALPHA) {
+                        render_context.fragment_code +=
+                            "color = vec4<f32>(color.rgb * color.a, color.a);\n";
+                    }
+
+                    (
+                        render_context.interpolant_fields_code(),
+                        render_context.interpolant_reads_code(),
+                        render_context.vertex_code,
+                        render_context.fragment_code,
+                        shader_defines_code.clone() + &render_context.render_extra,
+                        alpha_cutoff_code,
+                        flipbook_scale_code,
+                        flipbook_row_count_code,
+                        flipbook_frame_count_code,
+                        material_bindings_code,
+                    )
+                };
+
+                let render_shader_source = PARTICLES_RENDER_SHADER_TEMPLATE
+                    .replace("{{ATTRIBUTES}}", &attributes_code)
+                    .replace("{{INPUTS}}", &inputs_code)
+                    .replace("{{MATERIAL_BINDINGS}}", &material_bindings_code)
+                    .replace("{{INTERPOLANTS}}", &interpolants_code)
+                    .replace("{{FRAGMENT_INTERPOLANTS}}", &fragment_interpolants_code)
+                    .replace("{{VERTEX_MODIFIERS}}", &vertex_code)
+                    .replace("{{FRAGMENT_MODIFIERS}}", &fragment_code)
+                    .replace("{{RENDER_EXTRA}}", &render_extra)
+                    .replace("{{ALPHA_CUTOFF}}", &alpha_cutoff_code)
+                    .replace("{{FLIPBOOK_SCALE}}", &flipbook_scale_code)
+                    .replace("{{FLIPBOOK_ROW_COUNT}}", &flipbook_row_count_code)
+                    .replace("{{FLIPBOOK_FRAME_COUNT}}", &flipbook_frame_count_code);
+                trace!(
+                    "Configured render shader for layer {:?}:\n{}",
+                    layer,
+                    render_shader_source
+                );
+                render_shader_sources.push(render_shader_source);
+            }
+            let mut render_shader_sources = render_shader_sources
+                .into_iter()
+                .map(|source| rng_define_code.clone() + &source);
 
             group_shader_sources.push(EffectGroupShaderSource {
-                init: init_shader_source,
-                update: update_shader_source,
-                render: render_shader_source,
+                init: rng_define_code.clone() + &init_shader_source,
+                update: rng_define_code.clone() + &update_shader_source,
+                render: render_shader_sources.next().unwrap(),
+                secondary_render: render_shader_sources.next(),
             });
         }
 
+        if !point_cloud.is_empty() {
+            layout_flags |= LayoutFlags::POINT_CLOUD;
+        }
+
         Ok(EffectShaderSource {
             shaders: group_shader_sources,
+            point_cloud,
             layout_flags,
+            secondary_layout_flags: asset
+                .has_secondary_render_layer()
+                .then_some(secondary_layout_flags),
         })
     }
 }
@@ -1164,13 +1587,22 @@ pub struct CompiledParticleEffect {
     effect_shaders: Vec<EffectShader>,
     /// Textures used by the effect, if any.
     textures: Vec<Handle<Image>>,
+    /// Points of the point clouds of the effect, if any, uploaded into a
+    /// storage buffer.
+    point_cloud: Vec<Vec3>,
     /// 2D layer for the effect instance.
     #[cfg(feature = "2d")]
     z_layer_2d: FloatOrd,
     /// Layout flags.
     layout_flags: LayoutFlags,
+    /// Layout flags of the secondary render layer, if any.
+    secondary_layout_flags: Option<LayoutFlags>,
     /// Alpha mode.
     alpha_mode: AlphaMode,
+    /// Is the effect instance affected by the global [`WindZone`]?
+    affected_by_wind: bool,
+    /// Does the effect instance have an [`EffectGravity`]?
+    has_gravity: bool,
 }
 
 impl Default for CompiledParticleEffect {
@@ -1180,10 +1612,14 @@ impl Default for CompiledParticleEffect {
             simulation_condition: SimulationCondition::default(),
             effect_shaders: vec![],
             textures: vec![],
+            point_cloud: vec![],
             #[cfg(feature = "2d")]
             z_layer_2d: FloatOrd(0.0),
             layout_flags: LayoutFlags::NONE,
+            secondary_layout_flags: None,
             alpha_mode: default(),
+            affected_by_wind: false,
+            has_gravity: false,
         }
     }
 }
@@ -1194,6 +1630,7 @@ impl CompiledParticleEffect {
         self.asset = Handle::default();
         self.effect_shaders.clear();
         self.textures.clear();
+        self.point_cloud.clear();
     }
 
     /// Update the compiled effect from its asset and instance.
@@ -1203,6 +1640,8 @@ impl CompiledParticleEffect {
         #[cfg(feature = "2d")] z_layer_2d: FloatOrd,
         instance: &ParticleEffect,
         material: Option<&EffectMaterial>,
+        affected_by_wind: bool,
+        has_gravity: bool,
         asset: &EffectAsset,
         shaders: &mut ResMut<Assets<Shader>>,
         shader_cache: &mut ResMut<ShaderCache>,
@@ -1236,6 +1675,9 @@ impl CompiledParticleEffect {
             // and rebuild from scratch all three shaders together.
             self.effect_shaders.clear();
 
+            self.affected_by_wind = affected_by_wind;
+            self.has_gravity = has_gravity;
+
             // Update the 2D layer
             #[cfg(feature = "2d")]
             {
@@ -1248,18 +1690,21 @@ impl CompiledParticleEffect {
             return;
         }
 
-        let shader_source = match EffectShaderSource::generate(asset) {
-            Ok(shader_source) => shader_source,
-            Err(err) => {
-                error!(
-                    "Failed to generate shaders for effect asset {}: {:?}",
-                    asset.name, err
-                );
-                return;
-            }
-        };
+        let shader_source =
+            match EffectShaderSource::generate(asset, self.affected_by_wind, self.has_gravity) {
+                Ok(shader_source) => shader_source,
+                Err(err) => {
+                    error!(
+                        "Failed to generate shaders for effect asset {}: {:?}",
+                        asset.name, err
+                    );
+                    return;
+                }
+            };
 
         self.layout_flags = shader_source.layout_flags;
+        self.secondary_layout_flags = shader_source.secondary_layout_flags;
+        self.point_cloud = shader_source.point_cloud;
         self.alpha_mode = asset.alpha_mode;
 
         // TODO - Replace with Option<EffectShader { handle: Handle<Shader>, hash:
@@ -1288,10 +1733,18 @@ impl CompiledParticleEffect {
                     &effect_group_shader_source.render,
                     shaders,
                 );
+                let secondary_render =
+                    effect_group_shader_source
+                        .secondary_render
+                        .as_ref()
+                        .map(|secondary_render| {
+                            shader_cache.get_or_insert(&asset.name, secondary_render, shaders)
+                        });
                 EffectShader {
                     init,
                     update,
                     render,
+                    secondary_render,
                 }
             })
             .collect();
@@ -1425,28 +1878,42 @@ fn compile_effects(
         Entity,
         Ref<ParticleEffect>,
         Option<Ref<EffectMaterial>>,
+        Has<AffectedByWind>,
+        Has<EffectGravity>,
         &mut CompiledParticleEffect,
     )>,
 ) {
     trace!("compile_effects");
 
     // Loop over all existing effects to update them, including invisible ones
-    for (asset, entity, effect, material, mut compiled_effect) in
-        q_effects
-            .iter_mut()
-            .filter_map(|(entity, effect, material, compiled_effect)| {
+    for (asset, entity, effect, material, affected_by_wind, has_gravity, mut compiled_effect) in
+        q_effects.iter_mut().filter_map(
+            |(entity, effect, material, affected_by_wind, has_gravity, compiled_effect)| {
                 // Check if asset is available, otherwise silently ignore as we can't check for
                 // changes, and conceptually it makes no sense to render a particle effect whose
                 // asset was unloaded.
                 let asset = effects.get(&effect.handle)?;
 
-                Some((asset, entity, effect, material, compiled_effect))
-            })
+                Some((
+                    asset,
+                    entity,
+                    effect,
+                    material,
+                    affected_by_wind,
+                    has_gravity,
+                    compiled_effect,
+                ))
+            },
+        )
     {
         // If the ParticleEffect didn't change, and the compiled one is for the correct
-        // asset, then there's nothing to do.
-        let need_rebuild =
-            effect.is_changed() || material.as_ref().map_or(false, |r| r.is_changed());
+        // asset, then there's nothing to do. Adding or removing the AffectedByWind
+        // marker or the EffectGravity changes the generated shaders, so also forces
+        // a rebuild.
+        let need_rebuild = effect.is_changed()
+            || material.as_ref().map_or(false, |r| r.is_changed())
+            || compiled_effect.affected_by_wind != affected_by_wind
+            || compiled_effect.has_gravity != has_gravity;
         if !need_rebuild && (compiled_effect.asset == effect.handle) {
             continue;
         }
@@ -1468,6 +1935,8 @@ fn compile_effects(
             z_layer_2d,
             &effect,
             material.map(|r| r.into_inner()),
+            affected_by_wind,
+            has_gravity,
             asset,
             &mut shaders,
             &mut shader_cache,
@@ -1475,7 +1944,7 @@ fn compile_effects(
     }
 
     // Clear removed effects, to allow them to be released by the asset server
-    for (_, effect, _, mut compiled_effect) in q_effects.iter_mut() {
+    for (_, effect, _, _, _, mut compiled_effect) in q_effects.iter_mut() {
         if effects.get(&effect.handle).is_none() {
             compiled_effect.clear();
         }
@@ -1553,7 +2022,11 @@ mod tests {
             },
             AssetServerMode,
         },
-        render::view::{VisibilityPlugin, VisibilitySystems},
+        render::{
+            primitives::Aabb,
+            render_resource::Source,
+            view::{VisibilityPlugin, VisibilitySystems},
+        },
         tasks::{IoTaskPool, TaskPoolBuilder},
     };
     use naga_oil::compose::{Composer, NagaModuleDescriptor, ShaderDefValue};
@@ -1815,6 +2288,82 @@ else { return c1; }
         }
     }
 
+    /// Compose and validate a generated effect shader with naga, panicking on
+    /// error.
+    fn compile_effect_shader(name: &str, code: &str) {
+        let mut shader_defs = std::collections::HashMap::<String, ShaderDefValue>::new();
+        shader_defs.insert("LOCAL_SPACE_SIMULATION".into(), ShaderDefValue::Bool(true));
+        shader_defs.insert("NEEDS_UV".into(), ShaderDefValue::Bool(true));
+        shader_defs.insert("NEEDS_WORLD_NORMAL".into(), ShaderDefValue::Bool(true));
+        shader_defs.insert("SCENE_DEPTH".into(), ShaderDefValue::Bool(true));
+        shader_defs.insert("RENDER_NEEDS_SPAWNER".into(), ShaderDefValue::Bool(true));
+        shader_defs.insert(
+            "PARTICLE_SCREEN_SPACE_SIZE".into(),
+            ShaderDefValue::Bool(true),
+        );
+        if name == "Update" {
+            shader_defs.insert("REM_MAX_SPAWN_ATOMIC".into(), ShaderDefValue::Bool(true));
+        }
+        if name != "Render" {
+            shader_defs.insert("DOUBLE_BUFFERED".into(), ShaderDefValue::Bool(true));
+        }
+        let mut composer = Composer::default();
+
+        // Import bevy_render::view for the render shader
+        {
+            // It's reasonably hard to retrieve the source code for view.wgsl in
+            // bevy_render. We use a few tricks to get a Shader that we can
+            // then convert into a composable module (which is how imports work in Bevy
+            // itself).
+            let mut dummy_app = App::new();
+            dummy_app.init_resource::<Assets<Shader>>();
+            dummy_app.add_plugins(bevy::render::view::ViewPlugin);
+            let shaders = dummy_app.world().get_resource::<Assets<Shader>>().unwrap();
+            let view_shader = shaders.get(&bevy::render::view::VIEW_TYPE_HANDLE).unwrap();
+
+            let res = composer.add_composable_module(view_shader.into());
+            assert!(res.is_ok());
+        }
+
+        // Import bevy_hanabi::vfx_common
+        {
+            let min_storage_buffer_offset_alignment = 256;
+            let common_shader =
+                HanabiPlugin::make_common_shader(min_storage_buffer_offset_alignment);
+            let res = composer.add_composable_module((&common_shader).into());
+            assert!(res.is_ok());
+        }
+
+        match composer.make_naga_module(NagaModuleDescriptor {
+            source: code,
+            file_path: &format!("{}.wgsl", name),
+            shader_defs,
+            ..Default::default()
+        }) {
+            Ok(module) => {
+                // println!("shader: {:#?}", module);
+                let info = naga::valid::Validator::new(
+                    naga::valid::ValidationFlags::all(),
+                    naga::valid::Capabilities::default(),
+                )
+                .validate(&module)
+                .unwrap();
+                let wgsl = naga::back::wgsl::write_string(
+                    &module,
+                    &info,
+                    naga::back::wgsl::WriterFlags::EXPLICIT_TYPES,
+                )
+                .unwrap();
+                println!("Final wgsl from naga:\n\n{}", wgsl);
+                // Ok(module)
+            }
+            Err(e) => {
+                panic!("{}", e.emit_to_string(&composer));
+                // Err(e)
+            }
+        }
+    }
+
     #[test]
     fn test_effect_shader_source() {
         // Empty particle layout
@@ -1822,7 +2371,7 @@ else { return c1; }
         let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
             .with_simulation_space(SimulationSpace::Local);
         assert_eq!(asset.simulation_space, SimulationSpace::Local);
-        let res = EffectShaderSource::generate(&asset);
+        let res = EffectShaderSource::generate(&asset, false, false);
         assert!(res.is_err());
         let err = res.err().unwrap();
         assert!(matches!(err, ShaderGenerateError::Validate(_)));
@@ -1833,7 +2382,7 @@ else { return c1; }
         let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
             .init(SetAttributeModifier::new(Attribute::VELOCITY, zero));
         assert!(asset.particle_layout().size() > 0);
-        let res = EffectShaderSource::generate(&asset);
+        let res = EffectShaderSource::generate(&asset, false, false);
         assert!(res.is_err());
         let err = res.err().unwrap();
         assert!(matches!(err, ShaderGenerateError::Validate(_)));
@@ -1845,7 +2394,7 @@ else { return c1; }
             .with_simulation_space(SimulationSpace::Local)
             .init(SetAttributeModifier::new(Attribute::POSITION, zero));
         assert_eq!(asset.simulation_space, SimulationSpace::Local);
-        let res = EffectShaderSource::generate(&asset);
+        let res = EffectShaderSource::generate(&asset, false, false);
         assert!(res.is_ok());
         let shader_source = res.unwrap();
         for (name, code) in shader_source
@@ -1867,72 +2416,7 @@ else { return c1; }
         {
             println!("{} shader:\n\n{}", name, code);
 
-            let mut shader_defs = std::collections::HashMap::<String, ShaderDefValue>::new();
-            shader_defs.insert("LOCAL_SPACE_SIMULATION".into(), ShaderDefValue::Bool(true));
-            shader_defs.insert("NEEDS_UV".into(), ShaderDefValue::Bool(true));
-            shader_defs.insert("RENDER_NEEDS_SPAWNER".into(), ShaderDefValue::Bool(true));
-            shader_defs.insert(
-                "PARTICLE_SCREEN_SPACE_SIZE".into(),
-                ShaderDefValue::Bool(true),
-            );
-            if name == "Update" {
-                shader_defs.insert("REM_MAX_SPAWN_ATOMIC".into(), ShaderDefValue::Bool(true));
-            }
-            let mut composer = Composer::default();
-
-            // Import bevy_render::view for the render shader
-            {
-                // It's reasonably hard to retrieve the source code for view.wgsl in
-                // bevy_render. We use a few tricks to get a Shader that we can
-                // then convert into a composable module (which is how imports work in Bevy
-                // itself).
-                let mut dummy_app = App::new();
-                dummy_app.init_resource::<Assets<Shader>>();
-                dummy_app.add_plugins(bevy::render::view::ViewPlugin);
-                let shaders = dummy_app.world().get_resource::<Assets<Shader>>().unwrap();
-                let view_shader = shaders.get(&bevy::render::view::VIEW_TYPE_HANDLE).unwrap();
-
-                let res = composer.add_composable_module(view_shader.into());
-                assert!(res.is_ok());
-            }
-
-            // Import bevy_hanabi::vfx_common
-            {
-                let min_storage_buffer_offset_alignment = 256;
-                let common_shader =
-                    HanabiPlugin::make_common_shader(min_storage_buffer_offset_alignment);
-                let res = composer.add_composable_module((&common_shader).into());
-                assert!(res.is_ok());
-            }
-
-            match composer.make_naga_module(NagaModuleDescriptor {
-                source: code,
-                file_path: &format!("{}.wgsl", name),
-                shader_defs,
-                ..Default::default()
-            }) {
-                Ok(module) => {
-                    // println!("shader: {:#?}", module);
-                    let info = naga::valid::Validator::new(
-                        naga::valid::ValidationFlags::all(),
-                        naga::valid::Capabilities::default(),
-                    )
-                    .validate(&module)
-                    .unwrap();
-                    let wgsl = naga::back::wgsl::write_string(
-                        &module,
-                        &info,
-                        naga::back::wgsl::WriterFlags::EXPLICIT_TYPES,
-                    )
-                    .unwrap();
-                    println!("Final wgsl from naga:\n\n{}", wgsl);
-                    // Ok(module)
-                }
-                Err(e) => {
-                    panic!("{}", e.emit_to_string(&composer));
-                    // Err(e)
-                }
-            }
+            compile_effect_shader(name, code);
 
             // let mut frontend = Frontend::new();
             // let res = frontend.parse(code);
@@ -1944,6 +2428,934 @@ else { return c1; }
         }
     }
 
+    #[test]
+    fn test_effect_shader_source_position_jitter() {
+        let writer = ExprWriter::new();
+        let center = writer.lit(Vec3::ZERO).expr();
+        let radius = writer.lit(1.).expr();
+        let jitter = (writer.rand(ValueType::Vector(VectorType::VEC3F)) * writer.lit(0.1)).expr();
+        let init_pos = SetPositionSphereModifier {
+            center,
+            radius,
+            dimension: ShapeDimension::Volume,
+        };
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .with_position_jitter(jitter);
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let init = &shader_source.shaders[0].init;
+        let jitter_code = format!("particle.{} += (var0) * (0.1);", Attribute::POSITION.name());
+        // The init code is emitted in both the CLONE and non-CLONE variants; the last
+        // occurrence is the spawner one.
+        let shape_call_pos = init.rfind("set_position_sphere_").unwrap();
+        let jitter_pos = init.rfind(&jitter_code).unwrap();
+        assert!(shape_call_pos < jitter_pos);
+
+        // Without jitter, the position is left untouched
+        let mut asset = asset;
+        asset.position_jitter = None;
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0].init.contains(&jitter_code));
+    }
+
+    #[test]
+    fn test_effect_shader_source_point_cloud() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let asset =
+            EffectAsset::new(256, Spawner::rate(32.0.into()), writer.finish()).init(init_pos);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(shader_source.point_cloud.is_empty());
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::POINT_CLOUD));
+        assert!(!shader_source.shaders[0].init.contains("point_cloud"));
+
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), Module::default())
+            .init(SetPositionPointCloudModifier::new([Vec3::X, Vec3::Y]))
+            .init(SetPositionPointCloudModifier::new([Vec3::Z]));
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert_eq!(shader_source.point_cloud, vec![Vec3::X, Vec3::Y, Vec3::Z]);
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::POINT_CLOUD));
+        let init = &shader_source.shaders[0].init;
+        assert_eq!(
+            init.matches("@group(1) @binding(5) var<storage, read> point_cloud")
+                .count(),
+            1
+        );
+        assert!(init.contains("let index = 2u + (spawner.spawn_index + point_index) % 1u;"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_initial_age() {
+        let writer = ExprWriter::new();
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, writer.lit(2.).expr());
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let initial_age = (writer.rand(ScalarType::Float) * writer.lit(1.5)).expr();
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_lifetime)
+            .with_initial_age(initial_age);
+        // The age is stored even if no modifier uses it
+        assert!(asset.particle_layout().contains(Attribute::AGE));
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let init = &shader_source.shaders[0].init;
+        let age_code = format!(
+            "particle.{} = clamp((var0) * (1.5), 0., particle.{});",
+            Attribute::AGE.name(),
+            Attribute::LIFETIME.name()
+        );
+        let lifetime_pos = init
+            .rfind(&format!("particle.{} = 2.;", Attribute::LIFETIME.name()))
+            .unwrap();
+        let age_pos = init.rfind(&age_code).unwrap();
+        assert!(lifetime_pos < age_pos);
+
+        // Without an initial age, the age is left untouched
+        let mut asset = asset;
+        asset.initial_age = None;
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0].init.contains(&age_code));
+    }
+
+    #[test]
+    fn test_effect_shader_source_simulation_only() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let accel = AccelModifier::new(writer.lit(Vec3::NEG_Y).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .update(accel)
+            .render(ColorOverLifetimeModifier::default());
+        let sim_asset = asset.clone().with_simulation_only(true);
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::SIMULATION_ONLY));
+        let sim_shader_source = EffectShaderSource::generate(&sim_asset, false, false).unwrap();
+        assert!(sim_shader_source
+            .layout_flags
+            .contains(LayoutFlags::SIMULATION_ONLY));
+
+        // The simulation is unchanged, but render modifiers are ignored
+        let shaders = &shader_source.shaders[0];
+        let sim_shaders = &sim_shader_source.shaders[0];
+        assert_eq!(shaders.init, sim_shaders.init);
+        assert_eq!(shaders.update, sim_shaders.update);
+        assert_ne!(shaders.render, sim_shaders.render);
+    }
+
+    #[test]
+    fn test_effect_shader_source_double_buffering() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .update(init_pos);
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::DOUBLE_BUFFERED));
+
+        let db_asset = asset.clone().with_double_buffering(true);
+        let shader_source = EffectShaderSource::generate(&db_asset, false, false).unwrap();
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::DOUBLE_BUFFERED));
+        let update = &shader_source.shaders[0].update;
+        assert!(update.contains("prev_particle_buffer.particles[index]"));
+
+        // Not supported with linked particles
+        let ribbon_asset = db_asset.with_ribbons(256, 0.1, 1.0, 0);
+        assert!(matches!(
+            EffectShaderSource::generate(&ribbon_asset, false, false),
+            Err(ShaderGenerateError::Validate(_))
+        ));
+    }
+
+    #[test]
+    fn test_effect_shader_source_rng_algorithm() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.rand(VectorType::VEC3F).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module).init(init_pos);
+        assert_eq!(asset.rng_algorithm, RngAlgorithm::Pcg);
+
+        for (rng_algorithm, hash_fn) in [
+            (RngAlgorithm::Pcg, "pcg_hash"),
+            (RngAlgorithm::Xorshift, "xorshift_hash"),
+            (RngAlgorithm::Wang, "wang_hash"),
+        ] {
+            let asset = asset.clone().with_rng_algorithm(rng_algorithm);
+            let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+            let shader = &shader_source.shaders[0];
+            if let Some(define) = rng_algorithm.shader_define() {
+                let define = format!("#define {}\n", define);
+                assert!(shader.init.starts_with(&define));
+                assert!(shader.update.starts_with(&define));
+                assert!(shader.render.starts_with(&define));
+            } else {
+                assert!(!shader.init.contains("#define"));
+            }
+
+            // Compose the init shader, and check that the hash function of the selected
+            // algorithm is the one injected from vfx_common.wgsl.
+            let mut composer = Composer::default();
+            let common_shader = HanabiPlugin::make_common_shader(256);
+            composer
+                .add_composable_module((&common_shader).into())
+                .unwrap();
+            let module = composer
+                .make_naga_module(NagaModuleDescriptor {
+                    source: &shader.init,
+                    file_path: "init.wgsl",
+                    ..Default::default()
+                })
+                .unwrap_or_else(|e| panic!("{}", e.emit_to_string(&composer)));
+            let has_fn = |name: &str| {
+                module
+                    .functions
+                    .iter()
+                    .any(|(_, f)| f.name.as_ref().is_some_and(|n| n.starts_with(name)))
+            };
+            assert!(has_fn(hash_fn));
+            for other_fn in ["xorshift_hash", "wang_hash"] {
+                if other_fn != hash_fn {
+                    assert!(!has_fn(other_fn));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_effect_shader_source_premultiply_alpha() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let slot_zero = writer.lit(0u32).expr();
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .render(ParticleTextureModifier::new(slot_zero));
+        let premultiply = "color = vec4<f32>(color.rgb * color.a, color.a);";
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::PREMULTIPLY_ALPHA));
+        assert!(!shader_source.shaders[0].render.contains(premultiply));
+
+        let premul_asset = asset.clone().with_premultiply_alpha(true);
+        let shader_source = EffectShaderSource::generate(&premul_asset, false, false).unwrap();
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::PREMULTIPLY_ALPHA));
+        let render = &shader_source.shaders[0].render;
+        // The color is premultiplied after sampling the texture
+        let sample = render.find("textureSample(").unwrap();
+        let premul = render.find(premultiply).unwrap();
+        assert!(sample < premul);
+
+        // Ignored for alpha modes other than AlphaMode::Blend
+        let add_asset = premul_asset.with_alpha_mode(AlphaMode::Add);
+        let shader_source = EffectShaderSource::generate(&add_asset, false, false).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::PREMULTIPLY_ALPHA));
+        assert!(!shader_source.shaders[0].render.contains(premultiply));
+    }
+
+    #[test]
+    fn test_effect_shader_source_snap_to_grid() {
+        let writer = ExprWriter::new();
+        let center = writer.lit(Vec3::ZERO).expr();
+        let radius = writer.lit(1.).expr();
+        let init_pos = SetPositionSphereModifier {
+            center,
+            radius,
+            dimension: ShapeDimension::Volume,
+        };
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .with_snap_to_grid(Vec3::new(0.5, 0.5, -2.));
+        let pos = Attribute::POSITION.name();
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let init = &shader_source.shaders[0].init;
+        let snap_code = format!(
+            "particle.{pos} = round(particle.{pos} / {0}) * {0};",
+            Vec3::new(0.5, 0.5, 2.).to_wgsl_string()
+        );
+        // The init code is emitted in both the CLONE and non-CLONE variants; the last
+        // occurrence is the spawner one.
+        let shape_call_pos = init.rfind("set_position_sphere_").unwrap();
+        let snap_pos = init.rfind(&snap_code).unwrap();
+        assert!(shape_call_pos < snap_pos);
+
+        // Zero-spacing axes are masked out
+        let mut asset = asset;
+        asset.snap_to_grid = Some(Vec3::new(0.5, 0., 0.5));
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let snap_code = format!(
+            "particle.{pos} = select(particle.{pos}, round(particle.{pos} / {0}) * {0}, vec3<bool>(true, false, true));",
+            Vec3::new(0.5, 1., 0.5).to_wgsl_string()
+        );
+        assert!(shader_source.shaders[0].init.contains(&snap_code));
+
+        // A zero grid disables snapping altogether
+        for grid in [Some(Vec3::ZERO), None] {
+            asset.snap_to_grid = grid;
+            let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+            assert!(!shader_source.shaders[0].init.contains("round("));
+        }
+    }
+
+    #[test]
+    fn test_effect_shader_source_gravity() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::Y).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_vel);
+
+        // Effects with an EffectGravity read it from the spawner params, before the
+        // motion integration
+        let shader_source = EffectShaderSource::generate(&asset, false, true).unwrap();
+        let update = &shader_source.shaders[0].update;
+        let gravity = update
+            .find("particle.velocity += spawner.gravity * sim_params.delta_time;")
+            .unwrap();
+        let integration = update
+            .find("particle.position += particle.velocity * sim_params.delta_time;")
+            .unwrap();
+        assert!(gravity < integration);
+        compile_effect_shader("Update", update);
+
+        // Other effects don't
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0].update.contains("spawner.gravity"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_simulation_space_blend() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::Y).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_vel);
+        assert_eq!(asset.simulation_space_blend, 0.);
+
+        // By default, particles don't follow the emitter
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0]
+            .update
+            .contains("spawner.emitter_motion"));
+
+        // A blended effect applies the configured fraction of the emitter motion to
+        // both the position and velocity, after the motion integration
+        let asset = asset.with_simulation_space_blend(0.3);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        let integration = update
+            .find("particle.position += particle.velocity * sim_params.delta_time;")
+            .unwrap();
+        let position = update
+            .find("particle.position = mix(particle.position, (emitter_motion * vec4<f32>(particle.position, 1.0)).xyz, 0.3);")
+            .unwrap();
+        assert!(integration < position);
+        assert!(update.contains(
+            "particle.velocity = mix(particle.velocity, (emitter_motion * vec4<f32>(particle.velocity, 0.0)).xyz, 0.3);"
+        ));
+        compile_effect_shader("Update", update);
+
+        // Effects simulated in local space already follow the emitter
+        let asset = asset.with_simulation_space(SimulationSpace::Local);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0]
+            .update
+            .contains("spawner.emitter_motion"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_effect_simulation_space_blend_invalid() {
+        let _ = EffectAsset::default().with_simulation_space_blend(1.5);
+    }
+
+    #[test]
+    fn test_effect_shader_source_wind() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::Y).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_vel);
+
+        // Opted-in effects sample the wind parameters in their update pass
+        let shader_source = EffectShaderSource::generate(&asset, true, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        for param in [
+            "sim_params.wind_direction",
+            "sim_params.wind_strength",
+            "sim_params.wind_turbulence",
+        ] {
+            assert!(update.contains(param));
+        }
+
+        // Other effects don't
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0].update.contains("sim_params.wind_"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_globals() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let night = writer.time_of_day().mul(writer.lit(2.));
+        let rain = writer.weather().mul(writer.lit(Vec3::NEG_Y));
+        let update_size = SetAttributeModifier::new(Attribute::SIZE, night.expr());
+        let update_vel = SetAttributeModifier::new(Attribute::VELOCITY, rain.expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .update(update_size)
+            .update(update_vel);
+
+        // The globals are read from the simulation parameters in the update pass
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        assert!(update.contains("(sim_params.time_of_day) * (2.)"));
+        assert!(update.contains("(sim_params.weather) * (vec3<f32>(0.,-1.,0.))"));
+
+        // The simulation parameters declare all globals
+        let common = include_str!("render/vfx_common.wgsl");
+        assert!(common.contains("time_of_day: f32,"));
+        assert!(common.contains("weather: f32,"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_position_bounds() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::Y).expr());
+        let module = writer.finish();
+        let aabb = Aabb::from_min_max(Vec3::splat(-10.), Vec3::splat(10.));
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_vel)
+            .with_position_bounds(aabb);
+        let pos = Attribute::POSITION.name();
+        let min = Vec3::splat(-10.).to_wgsl_string();
+        let max = Vec3::splat(10.).to_wgsl_string();
+
+        // Clamp (default), after motion integration
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        let clamp_code = format!("particle.{pos} = clamp(particle.{pos}, {min}, {max});");
+        let integration_pos = update
+            .find(&format!("particle.{pos} += particle."))
+            .unwrap();
+        let clamp_pos = update.find(&clamp_code).unwrap();
+        assert!(integration_pos < clamp_pos);
+        assert!(!update.contains("is_alive = false;"));
+
+        // Kill
+        let mut asset = asset;
+        asset.position_bounds =
+            Some(PositionBounds::from(aabb).with_mode(PositionBoundsMode::Kill));
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        let kill_code = format!(
+            "if (any(particle.{pos} < {min}) || any(particle.{pos} > {max})) {{\n    is_alive = false;\n}}"
+        );
+        assert!(update.contains(&kill_code));
+        assert!(!update.contains(&clamp_code));
+
+        // No bounds
+        asset.position_bounds = None;
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0].update.contains("Position bounds"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_max_delta_time() {
+        let writer = ExprWriter::new();
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::Y).expr());
+        let init_age = SetAttributeModifier::new(Attribute::AGE, writer.lit(0.).expr());
+        let accel = AccelModifier::new(writer.lit(Vec3::new(0., -9.8, 0.)).expr());
+        let module = writer.finish();
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_vel)
+            .init(init_age)
+            .update(accel);
+
+        // Unclamped by default
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        assert!(update.contains("sim_params.delta_time"));
+        assert!(!update.contains("min(sim_params.delta_time"));
+
+        // After a hitch, the huge delta time reported in the simulation params must
+        // be clamped everywhere before use, including for aging, integration, and
+        // modifiers.
+        let asset = asset.with_max_delta_time(0.1);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let update = &shader_source.shaders[0].update;
+        let clamped = "min(sim_params.delta_time, 0.1)";
+        let age = Attribute::AGE.name();
+        let pos = Attribute::POSITION.name();
+        let vel = Attribute::VELOCITY.name();
+        assert!(update.contains(&format!("particle.{age} = particle.{age} + {clamped};")));
+        assert!(update.contains(&format!("particle.{pos} += particle.{vel} * {clamped};")));
+        assert!(update.contains(&format!(" * {clamped};")));
+        assert_eq!(
+            update.matches("sim_params.delta_time").count(),
+            update.matches(clamped).count()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_effect_max_delta_time_invalid() {
+        let _ = EffectAsset::default().with_max_delta_time(0.);
+    }
+
+    #[test]
+    fn test_effect_shader_source_particle_index() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let index = module.builtin(BuiltInOperator::ParticleIndex);
+        let index = module.cast(index, ScalarType::Float);
+        let scale = module.lit(0.1);
+        let rotation = module.mul(index, scale);
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .render(OrientModifier::new(OrientMode::FaceCameraPosition).with_rotation(rotation));
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let render = &shader_source.shaders[0].render;
+        let code_pos = render.find("(f32(index)) * (0.1)").unwrap();
+        assert!(render.find("let index = ").unwrap() < code_pos);
+        assert!(code_pos < render.find("fn fragment(").unwrap());
+        compile_effect_shader("Render", render);
+    }
+
+    #[test]
+    fn test_effect_shader_source_view_depth() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let depth = module.builtin(BuiltInOperator::ViewDepth);
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .render(OrientModifier::new(OrientMode::FaceCameraPosition).with_rotation(depth));
+
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let render = &shader_source.shaders[0].render;
+        let code_pos = render.find("get_view_depth(particle.position)").unwrap();
+        assert!(render.find("fn get_view_depth(").unwrap() < code_pos);
+        assert!(code_pos < render.find("fn fragment(").unwrap());
+        compile_effect_shader("Render", render);
+    }
+
+    #[test]
+    fn test_effect_shader_source_secondary_render_layer() {
+        let mut module = Module::default();
+        module.add_texture("glow");
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, module.lit(Vec3::ZERO));
+        let texture_slot = module.lit(0u32);
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module).init(init_pos);
+
+        // Without any secondary render modifier, a single render shader
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!asset.has_secondary_render_layer());
+        assert!(shader_source.secondary_layout_flags.is_none());
+        assert!(shader_source.shaders[0].secondary_render.is_none());
+
+        // A secondary layer gets its own render shader and render flags, while the
+        // simulation is shared
+        let asset = asset
+            .render(SetColorModifier {
+                color: Vec4::new(0.25, 0.5, 0.75, 1.).into(),
+            })
+            .render_layer(
+                EffectRenderLayer::Secondary,
+                ParticleTextureModifier::new(texture_slot),
+            )
+            .render_layer(
+                EffectRenderLayer::Secondary,
+                SetSizeModifier {
+                    size: Vec2::splat(4.).into(),
+                },
+            );
+        assert!(asset.has_secondary_render_layer());
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert_eq!(shader_source.shaders.len(), 1);
+        assert!(!shader_source.layout_flags.contains(LayoutFlags::NEEDS_UV));
+        let secondary_layout_flags = shader_source.secondary_layout_flags.unwrap();
+        assert!(secondary_layout_flags.contains(LayoutFlags::NEEDS_UV));
+
+        let shaders = &shader_source.shaders[0];
+        assert!(shaders.render.contains("vec4<f32>(0.25,0.5,0.75,1.)"));
+        assert!(!shaders.render.contains("vec2<f32>(4.,4.)"));
+        let secondary_render = shaders.secondary_render.as_ref().unwrap();
+        assert!(secondary_render.contains("vec2<f32>(4.,4.)"));
+        assert!(!secondary_render.contains("vec4<f32>(0.25,0.5,0.75,1.)"));
+        assert!(secondary_render.contains("material_texture_0"));
+        compile_effect_shader("Render", &shaders.render);
+        compile_effect_shader("Render", secondary_render);
+    }
+
+    #[test]
+    fn test_effect_shader_source_flipbook_blend() {
+        let mut module = Module::default();
+        module.add_texture("color");
+        let texture_slot = module.lit(0u32);
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, module.lit(Vec3::ZERO));
+        let make_asset = |module: Module, blend_frames: bool| {
+            EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+                .init(init_pos)
+                .render(ParticleTextureModifier::new(texture_slot))
+                .render(FlipbookModifier::new(UVec2::new(2, 3)).with_blend_frames(blend_frames))
+        };
+
+        // Blending samples both the current and next sprites, and mixes them by the
+        // fractional frame position
+        let asset = make_asset(module.clone(), true);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(shader_source.layout_flags.contains(LayoutFlags::FLIPBOOK));
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::FLIPBOOK_BLEND));
+        let render = &shader_source.shaders[0].render;
+        assert!(render.contains("let color = textureSample(tex, samp, uv);"));
+        assert!(render
+            .contains("let color_next = textureSample(tex, samp, uv + flipbook_uv_next_offset);"));
+        assert!(render.contains("return mix(color, color_next, flipbook_blend);"));
+        assert!(render.contains("sample_material(material_texture_0, material_sampler_0, uv)"));
+        // Wrap from last to first sprite
+        assert!(render.contains("(particle.sprite_index + 1) % 6;"));
+        assert!(render.contains("sprite_blend: f32"));
+
+        // Without blending, a single sample
+        let asset = make_asset(module, false);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(shader_source.layout_flags.contains(LayoutFlags::FLIPBOOK));
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::FLIPBOOK_BLEND));
+        let render = &shader_source.shaders[0].render;
+        assert_eq!(render.matches("textureSample(").count(), 1);
+        assert!(!render.contains("color_next"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_random_sprite_index() {
+        let writer = ExprWriter::new();
+        let grid = UVec2::new(2, 3);
+        let init_pos =
+            SetAttributeModifier::new(Attribute::POSITION, writer.lit(Vec3::ZERO).expr());
+        let init_cell = SetAttributeModifier::random_sprite_index(&writer, grid);
+        let texture_slot = writer.lit(0u32).expr();
+        let mut module = writer.finish();
+        module.add_texture("color");
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .init(init_cell)
+            .render(ParticleTextureModifier::new(texture_slot))
+            .render(FlipbookModifier::new(grid));
+
+        // Each particle picks a random cell once at spawn
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let shaders = &shader_source.shaders[0];
+        assert!(shaders.init.contains("let var0 = frand();"));
+        assert!(shaders
+            .init
+            .contains("particle.sprite_index = min(i32(floor((var0) * (6.))), 5);"));
+        assert!(!shaders.update.contains("particle.sprite_index ="));
+
+        // The render pass remaps the UVs to that cell
+        assert!(shader_source.layout_flags.contains(LayoutFlags::FLIPBOOK));
+        assert!(shaders.render.contains("let row_count = 2;"));
+        assert!(shaders.render.contains("uv = (ij + uv) * "));
+    }
+
+    #[test]
+    fn test_effect_shader_source_anchor() {
+        let mut module = Module::default();
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, module.lit(Vec3::ZERO));
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(init_pos)
+            .render(AnchorModifier::new(Vec2::new(0.5, 0.)));
+        let expand_code = "let vpos = (vertex_position + vec3<f32>(vec2<f32>(0.5) - anchor, 0.0)) * vec3<f32>(size.x, size.y, 1.0);";
+
+        // The anchor is assigned by the vertex modifiers, and offsets the mesh expansion
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let render = &shader_source.shaders[0].render;
+        let default_pos = render.find("var anchor = vec2<f32>(0.5, 0.5);").unwrap();
+        let anchor_pos = render.find("anchor = vec2<f32>(0.5,0.);").unwrap();
+        let expand_pos = render.find(expand_code).unwrap();
+        assert!(default_pos < anchor_pos);
+        assert!(anchor_pos < expand_pos);
+
+        // Without the modifier, the mesh stays centered
+        let mut module = Module::default();
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, module.lit(Vec3::ZERO));
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module).init(init_pos);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        let render = &shader_source.shaders[0].render;
+        assert!(render.contains(expand_code));
+        assert!(!render.contains("anchor = vec2<f32>(0.5,0.);"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_deterministic() {
+        fn make_asset() -> EffectAsset {
+            let writer = ExprWriter::new();
+            let accel = writer.add_property("accel", Vec3::new(0., -3., 0.).into());
+            let drag = writer.add_property("drag", 2.5.into());
+            let init_pos = SetPositionSphereModifier {
+                center: writer.lit(Vec3::ZERO).expr(),
+                radius: writer.lit(1.).expr(),
+                dimension: ShapeDimension::Volume,
+            };
+            let init_vel = SetAttributeModifier::new(
+                Attribute::VELOCITY,
+                writer.rand(VectorType::VEC3F).expr(),
+            );
+            let init_age = SetAttributeModifier::new(Attribute::AGE, writer.lit(0.).expr());
+            let init_lifetime =
+                SetAttributeModifier::new(Attribute::LIFETIME, writer.lit(5.).expr());
+            let init_axis =
+                SetAttributeModifier::new(Attribute::AXIS_Z, writer.lit(Vec3::Z).expr());
+            let init_f32 = SetAttributeModifier::new(Attribute::F32_0, writer.lit(1.).expr());
+            let update_accel = AccelModifier::new(writer.prop(accel).expr());
+            let update_drag = LinearDragModifier::new(writer.prop(drag).expr());
+            let module = writer.finish();
+            EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+                .init(init_pos)
+                .init(init_vel)
+                .init(init_age)
+                .init(init_lifetime)
+                .init(init_axis)
+                .init(init_f32)
+                .update(update_accel)
+                .update(update_drag)
+                .render(ColorOverLifetimeModifier::default())
+                .render(SizeOverLifetimeModifier::default())
+        }
+
+        let source1 = EffectShaderSource::generate(&make_asset(), false, false).unwrap();
+        let source2 = EffectShaderSource::generate(&make_asset(), false, false).unwrap();
+        assert_eq!(source1.shaders.len(), source2.shaders.len());
+        for (shader1, shader2) in source1.shaders.iter().zip(source2.shaders.iter()) {
+            assert_eq!(shader1.init, shader2.init);
+            assert_eq!(shader1.update, shader2.update);
+            assert_eq!(shader1.render, shader2.render);
+        }
+    }
+
+    #[test]
+    fn test_effect_shader_source_shader_define() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(256, Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .with_shader_define("MY_DEFINE", 3_u32)
+            .with_shader_define("MY_TOGGLE", true);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        for code in [
+            &shader_source.shaders[0].init,
+            &shader_source.shaders[0].update,
+            &shader_source.shaders[0].render,
+        ] {
+            assert!(code.contains("const MY_DEFINE: u32 = 3u;"));
+            assert!(code.contains("const MY_TOGGLE: bool = true;"));
+        }
+
+        // Redefining replaces the existing value
+        let asset = asset.with_shader_define("MY_DEFINE", 5_u32);
+        assert_eq!(asset.shader_defines().len(), 2);
+        let shader_source = EffectShaderSource::generate(&asset, false, false).unwrap();
+        assert!(!shader_source.shaders[0]
+            .init
+            .contains("const MY_DEFINE: u32 = 3u;"));
+        assert!(shader_source.shaders[0]
+            .init
+            .contains("const MY_DEFINE: u32 = 5u;"));
+
+        // Invalid identifier
+        let asset = asset.with_shader_define("0_INVALID", 1_f32);
+        let res = EffectShaderSource::generate(&asset, false, false);
+        assert!(matches!(res, Err(ShaderGenerateError::Validate(_))));
+    }
+
+    #[test]
+    fn test_compile_effect_shader_define_changed() {
+        let mut app = make_test_app();
+
+        let handle = {
+            let world = app.world_mut();
+
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let mut module = Module::default();
+            let init_pos = module.lit(Vec3::ZERO);
+            let mut asset = EffectAsset::new(64, Spawner::once(32.0.into(), true), module)
+                .init(SetAttributeModifier::new(Attribute::POSITION, init_pos))
+                .with_shader_define("MY_DEFINE", 1_f32);
+            asset.simulation_condition = SimulationCondition::Always;
+            let handle = assets.add(asset);
+
+            world.spawn((
+                ParticleEffect::new(handle.clone()),
+                CompiledParticleEffect::default(),
+            ));
+            world.spawn(Camera3dBundle::default());
+
+            handle
+        };
+
+        app.update();
+
+        let get_shaders = |app: &mut App| {
+            let world = app.world_mut();
+            let compiled_particle_effect = world
+                .query::<&CompiledParticleEffect>()
+                .iter(world)
+                .next()
+                .unwrap();
+            compiled_particle_effect.effect_shaders[0].clone()
+        };
+        let old_shaders = get_shaders(&mut app);
+
+        // Change the define of the asset, and notify the effect instance
+        {
+            let world = app.world_mut();
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let asset = assets.get_mut(&handle).unwrap();
+            *asset = asset.clone().with_shader_define("MY_DEFINE", 2_f32);
+
+            let mut particle_effect = world
+                .query::<&mut ParticleEffect>()
+                .iter_mut(world)
+                .next()
+                .unwrap();
+            particle_effect.deref_mut();
+        }
+
+        app.update();
+
+        // The shaders were recompiled into new shader assets
+        let new_shaders = get_shaders(&mut app);
+        assert_ne!(old_shaders.init, new_shaders.init);
+        assert_ne!(old_shaders.update, new_shaders.update);
+        assert_ne!(old_shaders.render, new_shaders.render);
+    }
+
+    #[test]
+    fn test_compile_effect_gravity() {
+        let mut app = make_test_app();
+
+        {
+            let world = app.world_mut();
+
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let mut module = Module::default();
+            let init_pos = module.lit(Vec3::ZERO);
+            let init_vel = module.lit(Vec3::Y);
+            let mut asset = EffectAsset::new(64, Spawner::once(32.0.into(), true), module)
+                .init(SetAttributeModifier::new(Attribute::POSITION, init_pos))
+                .init(SetAttributeModifier::new(Attribute::VELOCITY, init_vel));
+            asset.simulation_condition = SimulationCondition::Always;
+            let handle = assets.add(asset);
+
+            world.spawn((
+                ParticleEffect::new(handle),
+                CompiledParticleEffect::default(),
+                EffectGravity(Vec3::new(0., -9.81, 0.)),
+            ));
+            world.spawn(Camera3dBundle::default());
+        }
+
+        app.update();
+
+        let get_update_shader = |app: &mut App| {
+            let world = app.world_mut();
+            let compiled_particle_effect = world
+                .query::<&CompiledParticleEffect>()
+                .iter(world)
+                .next()
+                .unwrap();
+            let update = compiled_particle_effect.effect_shaders[0].update.clone();
+            let shaders = world.resource::<Assets<Shader>>();
+            let Source::Wgsl(code) = &shaders.get(&update).unwrap().source else {
+                panic!("Expected WGSL shader source");
+            };
+            (update, code.to_string())
+        };
+
+        // The update shader applies the uploaded gravity
+        let (update, code) = get_update_shader(&mut app);
+        assert!(code.contains("particle.velocity += spawner.gravity * sim_params.delta_time;"));
+
+        // Changing the gravity value only changes the uploaded value, without
+        // recompiling the shaders
+        {
+            let world = app.world_mut();
+            let mut gravity = world
+                .query::<&mut EffectGravity>()
+                .iter_mut(world)
+                .next()
+                .unwrap();
+            gravity.0 = Vec3::ZERO;
+        }
+        app.update();
+        assert_eq!(get_update_shader(&mut app).0, update);
+
+        // Removing the gravity recompiles the update shader without it
+        {
+            let world = app.world_mut();
+            let entity = world
+                .query_filtered::<Entity, With<EffectGravity>>()
+                .iter(world)
+                .next()
+                .unwrap();
+            world.entity_mut(entity).remove::<EffectGravity>();
+        }
+        app.update();
+        let (new_update, code) = get_update_shader(&mut app);
+        assert_ne!(new_update, update);
+        assert!(!code.contains("spawner.gravity"));
+    }
+
     // Regression test for #343
     #[test]
     fn test_compile_effect_invalid_handle() {
@@ -0,0 +1,327 @@
+use bevy::prelude::*;
+
+/// Component emitting point lights from the brightest particles of an effect.
+///
+/// Add this component to an entity with a [`ParticleEffect`]. After the
+/// particles are simulated each frame, a compute pass selects the brightest
+/// particles of each group of the effect on GPU, and reads back their position
+/// and color into the [`ParticleLightSamples`] component of the entity. The
+/// brightest samples are then each assigned a [`PointLight`], whose color and
+/// intensity derive from the color of the particle. The lights are spawned as
+/// children of the entity, marked with the [`ParticleLight`] component, and
+/// despawned when this component is removed.
+///
+/// The color of a particle is read from its [`Attribute::HDR_COLOR`], or
+/// otherwise its [`Attribute::COLOR`]. Particles without any of those
+/// attributes are all considered white. Colors applied by render modifiers,
+/// like [`ColorOverLifetimeModifier`], are not taken into account. The
+/// readback doesn't stall the GPU, so the lights lag the particles by a few
+/// frames.
+///
+/// Because each light has a sizeable cost on the lighting of the whole scene,
+/// the number of lights is capped by [`max_lights`], itself bounded by
+/// [`ParticleLights::MAX_LIGHTS`].
+///
+/// This requires the `lights` feature.
+///
+/// [`ParticleEffect`]: crate::ParticleEffect
+/// [`Attribute::HDR_COLOR`]: crate::Attribute::HDR_COLOR
+/// [`Attribute::COLOR`]: crate::Attribute::COLOR
+/// [`ColorOverLifetimeModifier`]: crate::ColorOverLifetimeModifier
+/// [`max_lights`]: ParticleLights::max_lights
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleLights {
+    /// Maximum number of lights emitted by the effect.
+    ///
+    /// This is clamped to [`ParticleLights::MAX_LIGHTS`]. The brightest
+    /// particles are selected per group, so an effect with several groups
+    /// still emits at most this number of lights overall.
+    pub max_lights: u32,
+    /// Intensity of the light emitted by a particle of brightness `1.0`, in
+    /// lumens. This is scaled by the brightness of each particle.
+    pub intensity: f32,
+    /// Range of the lights, in world units.
+    pub range: f32,
+}
+
+impl Default for ParticleLights {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl ParticleLights {
+    /// Maximum number of lights a single effect can emit.
+    ///
+    /// This is also the maximum number of particles selected per group on GPU.
+    pub const MAX_LIGHTS: u32 = 16;
+
+    /// Create a new component emitting up to the given number of lights, with
+    /// an intensity of 800 lumens and a range of 5 units.
+    pub fn new(max_lights: u32) -> Self {
+        Self {
+            max_lights,
+            intensity: 800.,
+            range: 5.,
+        }
+    }
+
+    /// Set the intensity of the light emitted by a particle of brightness
+    /// `1.0`, in lumens.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the range of the lights, in world units.
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Get the effective maximum number of lights, after clamping.
+    pub fn light_count(&self) -> u32 {
+        self.max_lights.min(Self::MAX_LIGHTS)
+    }
+}
+
+/// Sample of a single light-emitting particle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub struct ParticleLightSample {
+    /// Position of the sample, relative to the entity of the
+    /// [`ParticleLights`].
+    pub position: Vec3,
+    /// HDR color of the sample. The brightness of the sample is the largest
+    /// of its RGB components, multiplied by its alpha.
+    pub color: Vec4,
+}
+
+impl ParticleLightSample {
+    /// Create a new sample.
+    pub fn new(position: Vec3, color: Vec4) -> Self {
+        Self { position, color }
+    }
+
+    /// Get the brightness of the sample.
+    pub fn brightness(&self) -> f32 {
+        self.color.truncate().max_element().max(0.) * self.color.w.max(0.)
+    }
+}
+
+/// Samples from which a [`ParticleLights`] picks its light emitters.
+///
+/// This is inserted and updated by Hanabi on each entity with both a
+/// [`ParticleLights`] and a [`ParticleEffect`], each time the brightest
+/// particles of the effect are read back from GPU. See [`ParticleLights`] for
+/// details.
+///
+/// [`ParticleEffect`]: crate::ParticleEffect
+#[derive(Debug, Default, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleLightSamples {
+    /// The brightest particles of the effect, in any order.
+    pub samples: Vec<ParticleLightSample>,
+}
+
+/// Marker component for the lights spawned by a [`ParticleLights`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleLight;
+
+/// Update the lights emitted by all effects with a [`ParticleLights`]
+/// component.
+///
+/// Existing lights are reused from one frame to the next, and only spawned or
+/// despawned when the number of lights changes. All the lights of an entity are
+/// despawned when its [`ParticleLights`] component is removed.
+pub fn update_particle_lights(
+    mut commands: Commands,
+    q_effects: Query<(
+        Entity,
+        &ParticleLights,
+        Option<&ParticleLightSamples>,
+        Option<&Children>,
+    )>,
+    q_children: Query<&Children>,
+    mut q_lights: Query<(&mut PointLight, &mut Transform), With<ParticleLight>>,
+    mut removed: RemovedComponents<ParticleLights>,
+) {
+    #[cfg(feature = "trace")]
+    let _span = bevy::utils::tracing::info_span!("update_particle_lights").entered();
+
+    // Despawn the lights of the entities which stopped emitting. Ignore entities
+    // where the component was re-inserted since, which still own their lights.
+    for entity in removed.read() {
+        if q_effects.contains(entity) {
+            continue;
+        }
+        let Ok(children) = q_children.get(entity) else {
+            continue;
+        };
+        for &child in children {
+            if q_lights.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+    }
+
+    for (entity, particle_lights, samples, children) in &q_effects {
+        // Pick the brightest particles
+        let mut samples: Vec<_> = samples
+            .map(|samples| samples.samples.iter().collect())
+            .unwrap_or_default();
+        samples.sort_by(|a, b| b.brightness().total_cmp(&a.brightness()));
+        samples.truncate(particle_lights.light_count() as usize);
+
+        let mut lights = children
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|&child| q_lights.contains(child))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new)
+            .into_iter();
+
+        for sample in samples {
+            let brightness = sample.brightness();
+            let rgb = if brightness > 0. {
+                sample.color.truncate() / sample.color.truncate().max_element()
+            } else {
+                Vec3::ZERO
+            };
+            let point_light = PointLight {
+                color: Color::linear_rgb(rgb.x, rgb.y, rgb.z),
+                intensity: brightness * particle_lights.intensity,
+                range: particle_lights.range,
+                shadows_enabled: false,
+                ..default()
+            };
+            let transform = Transform::from_translation(sample.position);
+            if let Some(light) = lights.next() {
+                let (mut light, mut light_transform) = q_lights.get_mut(light).unwrap();
+                *light = point_light;
+                *light_transform = transform;
+            } else {
+                let light = commands
+                    .spawn((
+                        ParticleLight,
+                        PointLightBundle {
+                            point_light,
+                            transform,
+                            ..default()
+                        },
+                    ))
+                    .id();
+                commands.entity(entity).add_child(light);
+            }
+        }
+
+        // Remove the excess lights
+        for light in lights {
+            commands.entity(light).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light_intensities(world: &mut World) -> Vec<f32> {
+        let mut intensities: Vec<f32> = world
+            .query_filtered::<&PointLight, With<ParticleLight>>()
+            .iter(world)
+            .map(|light| light.intensity)
+            .collect();
+        intensities.sort_by(|a, b| b.total_cmp(a));
+        intensities
+    }
+
+    #[test]
+    fn particle_lights() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_particle_lights);
+
+        let samples = ParticleLightSamples {
+            samples: [0.5, 3., 1., 0., 2.]
+                .into_iter()
+                .enumerate()
+                .map(|(i, brightness)| {
+                    ParticleLightSample::new(Vec3::X * i as f32, Vec4::new(brightness, 0., 0., 1.))
+                })
+                .collect(),
+        };
+        let effect = world
+            .spawn((ParticleLights::new(3).with_intensity(10.), samples))
+            .id();
+
+        // Only the brightest particles emit, up to the cap
+        schedule.run(&mut world);
+        assert_eq!(light_intensities(&mut world), vec![30., 20., 10.]);
+        let children = world.entity(effect).get::<Children>().unwrap();
+        assert_eq!(children.len(), 3);
+
+        // Lights are reused, and despawned when there's fewer particles
+        world
+            .entity_mut(effect)
+            .get_mut::<ParticleLightSamples>()
+            .unwrap()
+            .samples
+            .truncate(1);
+        schedule.run(&mut world);
+        assert_eq!(light_intensities(&mut world), vec![5.]);
+
+        // The cap is bounded
+        let mut particle_lights = ParticleLights::new(1000);
+        assert_eq!(particle_lights.light_count(), ParticleLights::MAX_LIGHTS);
+        particle_lights.intensity = 1.;
+        let samples = ParticleLightSamples {
+            samples: vec![ParticleLightSample::new(Vec3::ZERO, Vec4::ONE); 100],
+        };
+        world.entity_mut(effect).insert((particle_lights, samples));
+        schedule.run(&mut world);
+        assert_eq!(
+            light_intensities(&mut world).len(),
+            ParticleLights::MAX_LIGHTS as usize
+        );
+
+        // Without samples, no light
+        world.entity_mut(effect).remove::<ParticleLightSamples>();
+        schedule.run(&mut world);
+        assert!(light_intensities(&mut world).is_empty());
+    }
+
+    #[test]
+    fn particle_lights_removed() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_particle_lights);
+
+        let samples = ParticleLightSamples {
+            samples: vec![ParticleLightSample::new(Vec3::ZERO, Vec4::ONE); 2],
+        };
+        let effect = world.spawn((ParticleLights::new(4), samples)).id();
+        let other_child = world.spawn_empty().set_parent(effect).id();
+        schedule.run(&mut world);
+        assert_eq!(light_intensities(&mut world).len(), 2);
+
+        // Removing the component despawns the lights, but not the other children
+        world.entity_mut(effect).remove::<ParticleLights>();
+        schedule.run(&mut world);
+        assert!(light_intensities(&mut world).is_empty());
+        let children = world.entity(effect).get::<Children>().unwrap();
+        assert_eq!(
+            children.iter().copied().collect::<Vec<_>>(),
+            vec![other_child]
+        );
+
+        // Re-inserting the component spawns new lights
+        world.entity_mut(effect).insert(ParticleLights::new(1));
+        schedule.run(&mut world);
+        assert_eq!(light_intensities(&mut world).len(), 1);
+    }
+}
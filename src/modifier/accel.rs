@@ -15,6 +15,7 @@ use crate::{
     expr::PropertyHandle,
     graph::{BuiltInExpr, EvalContext, ExprError},
     Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    UpdateModifier,
 };
 
 /// A modifier to apply a uniform acceleration to all particles each frame, to
@@ -28,38 +29,64 @@ use crate::{
 /// particle.velocity += acceleration * simulation.delta_time;
 /// ```
 ///
+/// Optionally, a maximum speed can be specified with [`with_max_speed()`] to
+/// simulate a terminal velocity. In that case, the magnitude of the particle
+/// velocity is clamped to that maximum speed after the acceleration is applied,
+/// preserving its direction.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::VELOCITY`]
+///
+/// [`with_max_speed()`]: crate::AccelModifier::with_max_speed
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct AccelModifier {
     /// The acceleration to apply to all particles in the effect each frame.
     ///
     /// Expression type: `Vec3`
     accel: ExprHandle,
+    /// Optional maximum speed the particles can reach.
+    ///
+    /// Expression type: `f32`
+    max_speed: Option<ExprHandle>,
 }
 
 impl AccelModifier {
     /// Create a new modifier from an acceleration expression.
     pub fn new(accel: ExprHandle) -> Self {
-        Self { accel }
+        Self {
+            accel,
+            max_speed: None,
+        }
     }
 
     /// Create a new modifier with an acceleration derived from a property.
     ///
     /// To create a new property, use [`Module::add_property()`].
     pub fn via_property(module: &mut Module, property: PropertyHandle) -> Self {
-        Self {
-            accel: module.prop(property),
-        }
+        Self::new(module.prop(property))
     }
 
     /// Create a new modifier with a constant acceleration.
     pub fn constant(module: &mut Module, acceleration: Vec3) -> Self {
-        Self {
-            accel: module.lit(acceleration),
-        }
+        Self::new(module.lit(acceleration))
+    }
+
+    /// Set the maximum speed the particles can reach.
+    ///
+    /// After the acceleration is applied, the magnitude of the particle
+    /// velocity is clamped to this value, simulating a terminal velocity.
+    ///
+    /// Expression type: `f32`
+    pub fn with_max_speed(mut self, max_speed: ExprHandle) -> Self {
+        self.max_speed = Some(max_speed);
+        self
+    }
+
+    /// Get the optional maximum speed the particles can reach.
+    pub fn max_speed(&self) -> Option<ExprHandle> {
+        self.max_speed
     }
 }
 
@@ -83,10 +110,28 @@ impl Modifier for AccelModifier {
         let expr = context.eval(module, self.accel)?;
         let dt = BuiltInExpr::new(crate::graph::BuiltInOperator::DeltaTime).eval(context)?;
         context.main_code += &format!("{} += ({}) * {};", attr, expr, dt);
+        if let Some(max_speed) = self.max_speed {
+            let max_speed = context.eval(module, max_speed)?;
+            let speed = context.make_local_var();
+            let max_speed_var = context.make_local_var();
+            // Clamp the speed to the terminal velocity. The check against the
+            // (non-negative) max speed also guards against a zero velocity.
+            context.main_code += &format!(
+                r#"
+let {speed} = length({attr});
+let {max_speed_var} = max({max_speed}, 0.0);
+if ({speed} > {max_speed_var}) {{
+    {attr} *= {max_speed_var} / {speed};
+}}
+"#
+            );
+        }
         Ok(())
     }
 }
 
+impl UpdateModifier for AccelModifier {}
+
 /// A modifier to apply a radial acceleration to all particles each frame.
 ///
 /// The acceleration is the same for all particles of the effect, and is applied
@@ -191,6 +236,8 @@ impl Modifier for RadialAccelModifier {
     }
 }
 
+impl UpdateModifier for RadialAccelModifier {}
+
 /// A modifier to apply a tangential acceleration to all particles each frame.
 ///
 /// The acceleration is the same for all particles of the effect, and is applied
@@ -310,6 +357,8 @@ impl Modifier for TangentAccelModifier {
     }
 }
 
+impl UpdateModifier for TangentAccelModifier {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -328,6 +377,37 @@ mod tests {
         assert!(modifier.apply(&mut module, &mut context).is_ok());
 
         assert!(context.main_code.contains(&accel.to_wgsl_string()));
+        assert!(!context.main_code.contains("length("));
+    }
+
+    #[test]
+    fn mod_accel_max_speed() {
+        let mut module = Module::default();
+        let accel = Vec3::new(0., -9.81, 0.);
+        let max_speed = module.lit(5.);
+        let modifier = AccelModifier::constant(&mut module, accel).with_max_speed(max_speed);
+        assert_eq!(modifier.max_speed(), Some(max_speed));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        let velocity = format!("particle.{}", Attribute::VELOCITY.name());
+        assert_eq!(
+            context.main_code,
+            format!(
+                r#"{velocity} += ({}) * sim_params.delta_time;
+let var0 = length({velocity});
+let var1 = max(5., 0.0);
+if (var0 > var1) {{
+    {velocity} *= var1 / var0;
+}}
+"#,
+                accel.to_wgsl_string()
+            )
+        );
     }
 
     #[test]
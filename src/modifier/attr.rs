@@ -9,8 +9,9 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    graph::{EvalContext, ExprError},
-    Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    graph::{EvalContext, ExprError, ExprWriter},
+    Attribute, BoxedModifier, ExprHandle, InitModifier, Modifier, ModifierContext, Module,
+    ScalarType, ShaderWriter, UpdateModifier,
 };
 
 /// A modifier to assign a value to a particle attribute.
@@ -81,6 +82,67 @@ impl SetAttributeModifier {
         Self { attribute, value }
     }
 
+    /// Create a modifier writing the current simulation time into
+    /// [`Attribute::BIRTH_TIME`].
+    ///
+    /// Use this as an init modifier to record the absolute time at which each
+    /// particle spawned. The birth time can then be read back in update or
+    /// render modifiers, for example to derive the time elapsed since a global
+    /// event.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let writer = ExprWriter::new();
+    /// let init_birth_time = SetAttributeModifier::birth_time(&writer);
+    ///
+    /// // Each frame, compute the simulation time elapsed since the spawn
+    /// let elapsed = writer.time() - writer.attr(Attribute::BIRTH_TIME);
+    /// ```
+    pub fn birth_time(writer: &ExprWriter) -> Self {
+        let time = writer.time().expr();
+        Self::new(Attribute::BIRTH_TIME, time)
+    }
+
+    /// Create a modifier writing a random sprite index into
+    /// [`Attribute::SPRITE_INDEX`].
+    ///
+    /// Use this as an init modifier to have each particle pick a random cell
+    /// of a texture atlas when it spawns, and keep it for its entire lifetime.
+    /// The index is uniformly distributed among the `sprite_grid_size.x *
+    /// sprite_grid_size.y` cells of the atlas. This is the static counterpart
+    /// of a flipbook animation, and uses the same [`FlipbookModifier`] with the
+    /// same grid size to remap the particle UVs to the selected cell during
+    /// rendering.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::math::UVec2;
+    /// # use bevy_hanabi::*;
+    /// let writer = ExprWriter::new();
+    /// let grid = UVec2::new(4, 2);
+    /// let init_cell = SetAttributeModifier::random_sprite_index(&writer, grid);
+    /// let render_cell = FlipbookModifier::new(grid);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid doesn't contain any cell.
+    ///
+    /// [`FlipbookModifier`]: crate::FlipbookModifier
+    pub fn random_sprite_index(writer: &ExprWriter, sprite_grid_size: UVec2) -> Self {
+        let count = sprite_grid_size.x * sprite_grid_size.y;
+        assert!(count > 0, "Sprite grid must contain at least one cell");
+        // Clamp in case the random value is exactly 1
+        let index = (writer.rand(ScalarType::Float) * writer.lit(count as f32))
+            .floor()
+            .cast(ScalarType::Int)
+            .min(writer.lit(count as i32 - 1));
+        Self::new(Attribute::SPRITE_INDEX, index.expr())
+    }
+
     fn eval(
         &self,
         module: &mut Module,
@@ -114,3 +176,7 @@ impl Modifier for SetAttributeModifier {
         Ok(())
     }
 }
+
+impl InitModifier for SetAttributeModifier {}
+
+impl UpdateModifier for SetAttributeModifier {}
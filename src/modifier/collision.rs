@@ -0,0 +1,358 @@
+//! Modifiers to make particles collide with the scene.
+//!
+//! These modifiers provide cheap approximations of collisions against the
+//! scene geometry, without any physics engine, suitable for effects like sparks
+//! bouncing off surfaces.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graph::{EvalContext, ExprError},
+    Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    UpdateModifier,
+};
+
+/// Response of a particle colliding with the scene.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum DepthCollisionResponse {
+    /// Kill the particle on collision.
+    #[default]
+    Kill,
+    /// Reflect the particle velocity about the surface normal, and move the
+    /// particle back onto the surface.
+    Reflect {
+        /// Fraction of the velocity along the surface normal preserved after
+        /// the collision. A value of `1.0` produces a perfectly elastic
+        /// bounce, while a value of `0.0` makes the particle slide along the
+        /// surface.
+        ///
+        /// Expression type: `f32`
+        restitution: ExprHandle,
+    },
+}
+
+/// A modifier colliding particles with the scene depth buffer.
+///
+/// Each frame, the position of each particle is projected onto the screen, and
+/// the world position of the scene surface behind the particle is
+/// reconstructed from the depth buffer. When the particle penetrates that
+/// surface by less than [`thickness`], the modifier applies the collision
+/// [`response`]. The thickness prevents particles located far behind a
+/// surface, which are merely occluded, from being considered as colliding.
+///
+/// This gives a cheap approximation of a collision with the visible geometry.
+/// Particles outside of the view, or occluded by some other geometry, don't
+/// collide. The depth buffer is the one of the previous frame, so fast moving
+/// geometry might lag by one frame.
+///
+/// The scene depth is only available to the update pass when the effect is
+/// rendered by a view with a depth texture bound to the simulation, which is
+/// signaled in the shader by the `SCENE_DEPTH` shader definition. When the
+/// depth is not available, the modifier has no effect and particles never
+/// collide.
+///
+/// This modifier assumes the effect is simulated in
+/// [`SimulationSpace::Global`].
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`thickness`]: DepthCollisionModifier::thickness
+/// [`response`]: DepthCollisionModifier::response
+/// [`SimulationSpace::Global`]: crate::SimulationSpace::Global
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct DepthCollisionModifier {
+    /// Maximum penetration depth, in world units, behind the reconstructed
+    /// surface for a particle to be considered colliding.
+    ///
+    /// Expression type: `f32`
+    pub thickness: ExprHandle,
+    /// Response of the particle on collision.
+    pub response: DepthCollisionResponse,
+}
+
+impl DepthCollisionModifier {
+    /// Create a new modifier killing the particles on collision.
+    pub fn new(thickness: impl Into<ExprHandle>) -> Self {
+        Self {
+            thickness: thickness.into(),
+            response: DepthCollisionResponse::Kill,
+        }
+    }
+
+    /// Set the collision response.
+    pub fn with_response(mut self, response: DepthCollisionResponse) -> Self {
+        self.response = response;
+        self
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for DepthCollisionModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let pos = module.attr(Attribute::POSITION);
+        let pos = context.eval(module, pos)?;
+        let vel = module.attr(Attribute::VELOCITY);
+        let vel = context.eval(module, vel)?;
+        let thickness = context.eval(module, self.thickness)?;
+
+        let proj = context.make_local_var();
+        let scene_depth = context.make_local_var();
+        let surface = context.make_local_var();
+
+        let response = match self.response {
+            DepthCollisionResponse::Kill => "        is_alive = false;\n".to_string(),
+            DepthCollisionResponse::Reflect { restitution } => {
+                let restitution = context.eval(module, restitution)?;
+                let normal = context.make_local_var();
+                let vn = context.make_local_var();
+                format!(
+                    r#"        let {normal} = scene_depth_normal({proj}.xy);
+        let {vn} = dot({vel}, {normal});
+        if ({vn} < 0.0) {{
+            {vel} -= (1.0 + ({restitution})) * {vn} * {normal};
+        }}
+        {pos} = {surface};
+"#
+                )
+            }
+        };
+
+        // The scene depth helpers are defined by the update shader template. They
+        // fall back to no depth when no depth texture is bound.
+        context.main_code += &format!(
+            r#"if (scene_depth_available()) {{
+    // xy: screen UV, z: particle depth, w: 1 if on screen or 0 otherwise
+    let {proj} = scene_depth_project({pos});
+    if ({proj}.w > 0.0) {{
+        let {scene_depth} = scene_depth_sample({proj}.xy);
+        let {surface} = scene_depth_unproject({proj}.xy, {scene_depth});
+        // Reverse-Z: the particle is behind the surface if its depth is smaller
+        if ({proj}.z < {scene_depth} && distance({pos}, {surface}) < ({thickness})) {{
+{response}        }}
+    }}
+}}
+"#
+        );
+
+        Ok(())
+    }
+}
+
+impl UpdateModifier for DepthCollisionModifier {}
+
+/// A modifier colliding particles with an infinite plane.
+///
+/// The plane is defined by a point and a normal, and particles collide with
+/// the side of the plane the normal points away from. When a particle crosses
+/// the plane while moving toward it, its velocity is split into a normal part,
+/// along the plane normal, and a tangential part, parallel to the plane. The
+/// normal part is reflected and scaled by the [`restitution`], and the
+/// tangential part is damped by the [`friction`]. The particle is then moved
+/// back onto the plane.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`restitution`]: CollisionPlaneModifier::restitution
+/// [`friction`]: CollisionPlaneModifier::friction
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct CollisionPlaneModifier {
+    /// A point on the plane, in simulation space.
+    ///
+    /// Expression type: `Vec3`
+    pub point: ExprHandle,
+    /// The plane normal, in simulation space, pointing toward the side of the
+    /// plane where particles are allowed. This doesn't need to be normalized.
+    ///
+    /// Expression type: `Vec3`
+    pub normal: ExprHandle,
+    /// Fraction of the normal velocity preserved after the collision. A value
+    /// of `1.0` produces a perfectly elastic bounce, while a value of `0.0`
+    /// makes the particle stick to the plane.
+    ///
+    /// Expression type: `f32`
+    pub restitution: ExprHandle,
+    /// Fraction of the tangential velocity removed on collision, if any. A
+    /// value of `0.0` makes the particle slide without friction, while a value
+    /// of `1.0` stops any sliding. If `None`, the tangential velocity is
+    /// preserved, like with a value of `0.0`.
+    ///
+    /// Expression type: `f32`
+    pub friction: Option<ExprHandle>,
+}
+
+impl CollisionPlaneModifier {
+    /// Create a new modifier colliding with the given plane, without friction.
+    pub fn new(
+        point: impl Into<ExprHandle>,
+        normal: impl Into<ExprHandle>,
+        restitution: impl Into<ExprHandle>,
+    ) -> Self {
+        Self {
+            point: point.into(),
+            normal: normal.into(),
+            restitution: restitution.into(),
+            friction: None,
+        }
+    }
+
+    /// Set the friction coefficient damping the tangential velocity on
+    /// collision.
+    pub fn with_friction(mut self, friction: impl Into<ExprHandle>) -> Self {
+        self.friction = Some(friction.into());
+        self
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for CollisionPlaneModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let pos = module.attr(Attribute::POSITION);
+        let pos = context.eval(module, pos)?;
+        let vel = module.attr(Attribute::VELOCITY);
+        let vel = context.eval(module, vel)?;
+        let point = context.eval(module, self.point)?;
+        let normal = context.eval(module, self.normal)?;
+        let restitution = context.eval(module, self.restitution)?;
+        let tangent_factor = if let Some(friction) = self.friction {
+            let friction = context.eval(module, friction)?;
+            format!(" * saturate(1.0 - ({friction}))")
+        } else {
+            String::new()
+        };
+
+        let n = context.make_local_var();
+        let dist = context.make_local_var();
+        let vn = context.make_local_var();
+        let v_normal = context.make_local_var();
+        let v_tangent = context.make_local_var();
+
+        context.main_code += &format!(
+            r#"{{
+    let {n} = normalize({normal});
+    let {dist} = dot({pos} - ({point}), {n});
+    if ({dist} < 0.0) {{
+        let {vn} = dot({vel}, {n});
+        if ({vn} < 0.0) {{
+            let {v_normal} = {vn} * {n};
+            let {v_tangent} = {vel} - {v_normal};
+            {vel} = {v_tangent}{tangent_factor} - ({restitution}) * {v_normal};
+        }}
+        {pos} -= {dist} * {n};
+    }}
+}}
+"#
+        );
+
+        Ok(())
+    }
+}
+
+impl UpdateModifier for CollisionPlaneModifier {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn mod_depth_collision() {
+        let mut module = Module::default();
+        let thickness = module.lit(0.1);
+        let restitution = module.lit(0.5);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Kill
+        let modifier = DepthCollisionModifier::new(thickness);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        let code = &context.main_code;
+        assert!(code.contains("if (scene_depth_available())"));
+        assert!(code.contains("scene_depth_project(particle.position)"));
+        assert!(code.contains("scene_depth_sample("));
+        assert!(code.contains("scene_depth_unproject("));
+        assert!(code.contains("is_alive = false;"));
+        assert!(!code.contains("scene_depth_normal("));
+
+        // Reflect
+        let modifier = DepthCollisionModifier::new(thickness)
+            .with_response(DepthCollisionResponse::Reflect { restitution });
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        let code = &context.main_code;
+        assert!(code.contains("scene_depth_unproject("));
+        assert!(code.contains("scene_depth_normal("));
+        assert!(code.contains("particle.velocity -= (1.0 + (0.5)) * "));
+        assert!(!code.contains("is_alive = false;"));
+    }
+
+    #[test]
+    fn mod_collision_plane() {
+        let mut module = Module::default();
+        let point = module.lit(Vec3::ZERO);
+        let normal = module.lit(Vec3::Y);
+        let restitution = module.lit(0.5);
+        let friction = module.lit(0.25);
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Without friction, the tangential velocity is preserved
+        let modifier = CollisionPlaneModifier::new(point, normal, restitution);
+        assert!(modifier.friction.is_none());
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        let code = &context.main_code;
+        assert!(code.contains("let var0 = normalize(vec3<f32>(0.,1.,0.));"));
+        assert!(code.contains("let var2 = dot(particle.velocity, var0);"));
+        assert!(code.contains("let var3 = var2 * var0;"));
+        assert!(code.contains("let var4 = particle.velocity - var3;"));
+        assert!(code.contains("particle.velocity = var4 - (0.5) * var3;"));
+        assert!(code.contains("particle.position -= var1 * var0;"));
+
+        // With friction, the tangential velocity is damped while the normal one is
+        // still reflected by the restitution
+        let modifier = modifier.with_friction(friction);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        let code = &context.main_code;
+        assert!(code.contains("let var4 = particle.velocity - var3;"));
+        assert!(code.contains("particle.velocity = var4 * saturate(1.0 - (0.25)) - (0.5) * var3;"));
+    }
+}
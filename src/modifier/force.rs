@@ -12,6 +12,7 @@ use crate::{
     calc_func_id,
     graph::{BuiltInOperator, EvalContext, ExprError},
     Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    UpdateModifier,
 };
 
 /// A modifier to apply a force to the particle which makes it conform ("stick")
@@ -239,6 +240,8 @@ impl Modifier for ConformToSphereModifier {
     }
 }
 
+impl UpdateModifier for ConformToSphereModifier {}
+
 /// A modifier to apply a linear drag force to all particles each frame. The
 /// force slows down the particles without changing their direction.
 ///
@@ -299,6 +302,8 @@ impl Modifier for LinearDragModifier {
     }
 }
 
+impl UpdateModifier for LinearDragModifier {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
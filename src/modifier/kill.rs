@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     graph::{EvalContext, ExprError},
     Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    UpdateModifier,
 };
 
 /// A modifier killing all particles that enter or exit a sphere.
@@ -97,6 +98,8 @@ impl Modifier for KillSphereModifier {
     }
 }
 
+impl UpdateModifier for KillSphereModifier {}
+
 /// A modifier killing all particles that enter or exit an AABB.
 ///
 /// This enables confining particles to a region in space, or preventing
@@ -183,6 +186,210 @@ impl Modifier for KillAabbModifier {
     }
 }
 
+impl UpdateModifier for KillAabbModifier {}
+
+/// A modifier capping the density of particles by killing the oldest ones.
+///
+/// Each frame, if the number of particles alive in the group exceeds
+/// [`max_count`], the modifier kills all particles whose age is in the oldest
+/// [`fraction`] of their lifetime, that is particles for which:
+///
+/// ```txt
+/// age >= lifetime * (1 - fraction)
+/// ```
+///
+/// The alive count is read on GPU at the start of the update pass, so the
+/// modifier reacts on the same frame the count exceeds the target, without
+/// any CPU readback. Because all particles are tested in parallel, the number
+/// of particles killed is only approximately the requested fraction, and
+/// depends on the age distribution. This is best suited to ambient effects
+/// with a steady spawn rate, to avoid a sudden saturation of the effect.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::AGE`]
+/// - [`Attribute::LIFETIME`]
+///
+/// [`max_count`]: DensityCapModifier::max_count
+/// [`fraction`]: DensityCapModifier::fraction
+#[derive(Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub struct DensityCapModifier {
+    /// Maximum number of alive particles in the group before the oldest ones
+    /// start to be killed.
+    pub max_count: u32,
+    /// Fraction of the lifetime, in \[0:1\], defining the oldest particles
+    /// killed when the alive count exceeds [`max_count`].
+    ///
+    /// Expression type: `f32`
+    ///
+    /// [`max_count`]: DensityCapModifier::max_count
+    pub fraction: ExprHandle,
+}
+
+impl DensityCapModifier {
+    /// Create a new instance of a [`DensityCapModifier`].
+    pub fn new(max_count: u32, fraction: ExprHandle) -> Self {
+        Self {
+            max_count,
+            fraction,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for DensityCapModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::AGE, Attribute::LIFETIME]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let age = module.attr(Attribute::AGE);
+        let age = context.eval(module, age)?;
+        let lifetime = module.attr(Attribute::LIFETIME);
+        let lifetime = context.eval(module, lifetime)?;
+        let fraction = context.eval(module, self.fraction)?;
+
+        context.main_code += &format!(
+            r#"if (group_alive_count() > {max_count}u && {age} >= {lifetime} * (1.0 - ({fraction}))) {{
+    is_alive = false;
+}}
+"#,
+            max_count = self.max_count,
+        );
+
+        Ok(())
+    }
+}
+
+impl UpdateModifier for DensityCapModifier {}
+
+/// A modifier killing the particles which faded out to a zero alpha.
+///
+/// Fully transparent particles are invisible, but still occupy a slot in the
+/// particle buffer and are still simulated and rendered until their lifetime
+/// ends. This modifier kills them as soon as their alpha falls below
+/// [`epsilon`], to free their slot early.
+///
+/// The alpha is read from the [`attribute`], which must be updated in the
+/// update context before this modifier, for example by a
+/// [`SetAttributeModifier`]. Render modifiers like [`ColorOverLifetimeModifier`]
+/// only affect the rendered color, not the particle attributes, so can't be
+/// used to drive this modifier. This modifier is opt-in, because most effects
+/// don't fade particles out before their death.
+///
+/// # Attributes
+///
+/// This modifier requires the attribute specified in the [`attribute`] field,
+/// which is one of:
+/// - [`Attribute::ALPHA`]
+/// - [`Attribute::HDR_COLOR`], using its alpha component.
+/// - [`Attribute::COLOR`], using the alpha component of the unpacked color.
+///
+/// [`epsilon`]: KillAlphaModifier::epsilon
+/// [`attribute`]: KillAlphaModifier::attribute
+/// [`SetAttributeModifier`]: crate::SetAttributeModifier
+/// [`ColorOverLifetimeModifier`]: crate::ColorOverLifetimeModifier
+#[derive(Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub struct KillAlphaModifier {
+    /// The attribute containing the particle alpha.
+    pub attribute: Attribute,
+    /// Alpha value below which particles are killed.
+    pub epsilon: f32,
+}
+
+impl Default for KillAlphaModifier {
+    fn default() -> Self {
+        Self::new(Attribute::ALPHA)
+    }
+}
+
+impl KillAlphaModifier {
+    /// Default alpha value below which particles are killed.
+    pub const DEFAULT_EPSILON: f32 = 1e-3;
+
+    /// Create a new instance of a [`KillAlphaModifier`] reading the alpha from
+    /// the given attribute.
+    ///
+    /// The created instance has a default [`DEFAULT_EPSILON`] threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attribute is not one of [`Attribute::ALPHA`],
+    /// [`Attribute::HDR_COLOR`], or [`Attribute::COLOR`].
+    ///
+    /// [`DEFAULT_EPSILON`]: KillAlphaModifier::DEFAULT_EPSILON
+    pub fn new(attribute: Attribute) -> Self {
+        assert!(
+            attribute == Attribute::ALPHA
+                || attribute == Attribute::HDR_COLOR
+                || attribute == Attribute::COLOR,
+            "KillAlphaModifier cannot read the alpha from attribute '{}'",
+            attribute.name()
+        );
+        Self {
+            attribute,
+            epsilon: Self::DEFAULT_EPSILON,
+        }
+    }
+
+    /// Set the alpha value below which particles are killed.
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for KillAlphaModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        std::slice::from_ref(&self.attribute)
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let attr = module.attr(self.attribute);
+        let alpha = if self.attribute == Attribute::ALPHA {
+            attr
+        } else if self.attribute == Attribute::HDR_COLOR {
+            module.w(attr)
+        } else {
+            let color = module.unpack4x8unorm(attr);
+            module.w(color)
+        };
+        let epsilon = module.lit(self.epsilon);
+        let cmp = module.lt(alpha, epsilon);
+        let expr = context.eval(module, cmp)?;
+
+        context.main_code += &format!(
+            r#"if ({}) {{
+    is_alive = false;
+}}
+"#,
+            expr
+        );
+
+        Ok(())
+    }
+}
+
+impl UpdateModifier for KillAlphaModifier {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,4 +411,66 @@ mod tests {
         assert!(context.main_code.contains("is_alive = false")); // TODO - less
                                                                  // weak check
     }
+
+    #[test]
+    fn mod_kill_alpha() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        for (attribute, alpha) in [
+            (
+                Attribute::ALPHA,
+                format!("particle.{}", Attribute::ALPHA.name()),
+            ),
+            (
+                Attribute::HDR_COLOR,
+                format!("particle.{}.w", Attribute::HDR_COLOR.name()),
+            ),
+            (
+                Attribute::COLOR,
+                format!("unpack4x8unorm(particle.{}).w", Attribute::COLOR.name()),
+            ),
+        ] {
+            let mut module = Module::default();
+            let modifier = KillAlphaModifier::new(attribute).with_epsilon(0.01);
+            assert_eq!(modifier.attributes(), &[attribute]);
+
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+            assert_eq!(
+                context.main_code,
+                format!("if (({}) < (0.01)) {{\n    is_alive = false;\n}}\n", alpha)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn mod_kill_alpha_invalid_attribute() {
+        KillAlphaModifier::new(Attribute::POSITION);
+    }
+
+    #[test]
+    fn mod_density_cap() {
+        let mut module = Module::default();
+        let fraction = module.lit(0.25);
+        let modifier = DensityCapModifier::new(500, fraction);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        assert_eq!(
+            context.main_code,
+            format!(
+                "if (group_alive_count() > 500u && particle.{0} >= particle.{1} * (1.0 - (0.25))) {{\n    is_alive = false;\n}}\n",
+                Attribute::AGE.name(),
+                Attribute::LIFETIME.name()
+            )
+        );
+    }
 }
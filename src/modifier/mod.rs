@@ -27,7 +27,7 @@ use std::{
 
 use bevy::{
     asset::Handle,
-    math::{UVec2, Vec2, Vec4},
+    math::{UVec2, Vec2, Vec3, Vec4},
     reflect::Reflect,
     render::texture::Image,
     utils::HashMap,
@@ -37,6 +37,7 @@ use serde::{Deserialize, Serialize};
 
 pub mod accel;
 pub mod attr;
+pub mod collision;
 pub mod force;
 pub mod kill;
 pub mod output;
@@ -45,6 +46,7 @@ pub mod velocity;
 
 pub use accel::*;
 pub use attr::*;
+pub use collision::*;
 pub use force::*;
 pub use kill::*;
 pub use output::*;
@@ -53,7 +55,7 @@ pub use velocity::*;
 
 use crate::{
     Attribute, EvalContext, ExprError, ExprHandle, Gradient, Module, ParticleLayout,
-    PropertyLayout, TextureLayout,
+    PropertyLayout, ScalarType, TextureLayout, ToWgslString, ValueType,
 };
 
 /// The dimension of a shape to consider.
@@ -78,7 +80,8 @@ pub(crate) fn calc_func_id<T: Hash>(value: &T) -> u64 {
 
 bitflags! {
     /// Context a modifier applies to.
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(transparent)]
     pub struct ModifierContext : u8 {
         /// Particle initializing on spawning.
         ///
@@ -255,6 +258,12 @@ pub struct ShaderWriter<'a> {
     expr_cache: HashMap<ExprHandle, String>,
     /// Is the attribute struct a pointer?
     is_attribute_pointer: bool,
+    /// Points emitted by the modifiers, uploaded into the point cloud storage
+    /// buffer of the effect.
+    pub(crate) point_cloud: Vec<Vec3>,
+    /// Index into the point cloud storage buffer of the first point of
+    /// `point_cloud`.
+    point_cloud_base: u32,
 }
 
 impl<'a> ShaderWriter<'a> {
@@ -273,6 +282,8 @@ impl<'a> ShaderWriter<'a> {
             var_counter: 0,
             expr_cache: Default::default(),
             is_attribute_pointer: false,
+            point_cloud: vec![],
+            point_cloud_base: 0,
         }
     }
 
@@ -281,6 +292,26 @@ impl<'a> ShaderWriter<'a> {
         self.is_attribute_pointer = true;
         self
     }
+
+    /// Set the index into the point cloud storage buffer of the first point
+    /// pushed with [`push_point_cloud()`].
+    ///
+    /// This is used when the storage buffer is shared by several shaders, each
+    /// pushing its own points.
+    ///
+    /// [`push_point_cloud()`]: ShaderWriter::push_point_cloud
+    pub(crate) fn with_point_cloud_base(mut self, base: u32) -> Self {
+        self.point_cloud_base = base;
+        self
+    }
+
+    /// Append some points to the point cloud storage buffer, and return the
+    /// index into that buffer of the first point.
+    pub(crate) fn push_point_cloud(&mut self, points: &[Vec3]) -> u32 {
+        let offset = self.point_cloud_base + self.point_cloud.len() as u32;
+        self.point_cloud.extend_from_slice(points);
+        offset
+    }
 }
 
 impl<'a> EvalContext for ShaderWriter<'a> {
@@ -356,6 +387,17 @@ impl<'a> EvalContext for ShaderWriter<'a> {
     }
 }
 
+/// Extra value passed from the vertex shader to the fragment shader.
+///
+/// See [`RenderContext::add_interpolant()`] for details.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpolant {
+    /// Name of the interpolant in the fragment code.
+    pub name: String,
+    /// Type of the interpolated value.
+    pub value_type: ValueType,
+}
+
 /// Particle rendering shader code generation context.
 #[derive(Debug, PartialEq)]
 pub struct RenderContext<'a> {
@@ -376,12 +418,19 @@ pub struct RenderContext<'a> {
     pub textures: Vec<Handle<Image>>,
     /// Flipbook sprite sheet grid size, if any.
     pub sprite_grid_size: Option<UVec2>,
+    /// Cross-fade between consecutive flipbook sprites.
+    pub flipbook_blend: bool,
     /// Color gradients.
     pub gradients: HashMap<u64, Gradient<Vec4>>,
     /// Size gradients.
     pub size_gradients: HashMap<u64, Gradient<Vec2>>,
     /// The particle needs UV coordinates to sample one or more texture(s).
     pub needs_uv: bool,
+    /// The particle needs the world-space position and normal of its fragments,
+    /// for example for world-space projected texturing.
+    pub needs_world_normal: bool,
+    /// Extra values passed from the vertex shader to the fragment shader.
+    pub interpolants: Vec<Interpolant>,
     /// Counter for unique variable names.
     var_counter: u32,
     /// Cache of evaluated expressions.
@@ -391,6 +440,17 @@ pub struct RenderContext<'a> {
 }
 
 impl<'a> RenderContext<'a> {
+    /// Maximum number of interpolants of a render shader.
+    ///
+    /// See [`add_interpolant()`] for details.
+    ///
+    /// [`add_interpolant()`]: RenderContext::add_interpolant
+    pub const MAX_INTERPOLANTS: usize = 8;
+
+    /// First `@location()` of the interpolants in the vertex output, after the
+    /// ones used by the built-in render features.
+    const FIRST_INTERPOLANT_LOCATION: usize = 6;
+
     /// Create a new update context.
     pub fn new(
         property_layout: &'a PropertyLayout,
@@ -406,9 +466,12 @@ impl<'a> RenderContext<'a> {
             texture_layout,
             textures: vec![],
             sprite_grid_size: None,
+            flipbook_blend: false,
             gradients: HashMap::new(),
             size_gradients: HashMap::new(),
             needs_uv: false,
+            needs_world_normal: false,
+            interpolants: vec![],
             var_counter: 0,
             expr_cache: Default::default(),
             is_attribute_pointer: false,
@@ -420,6 +483,12 @@ impl<'a> RenderContext<'a> {
         self.needs_uv = true;
     }
 
+    /// Mark the rendering shader as needing the world-space position and normal
+    /// of the fragments.
+    fn set_needs_world_normal(&mut self) {
+        self.needs_world_normal = true;
+    }
+
     /// Add a color gradient.
     ///
     /// # Returns
@@ -451,6 +520,128 @@ impl<'a> RenderContext<'a> {
         self.is_attribute_pointer = true;
         self
     }
+
+    /// Pass a per-particle value from the vertex shader to the fragment shader.
+    ///
+    /// The `value` expression is evaluated in the vertex shader, and written to
+    /// an extra field of the vertex output. In the fragment shader, the value
+    /// interpolated across the particle is then available to the
+    /// `fragment_code` of all render modifiers as a local variable named
+    /// `name`. This allows custom render modifiers to read in their fragment
+    /// code any attribute or value computed per particle.
+    ///
+    /// Float values are interpolated linearly; integer values are not
+    /// interpolated, but taken from one of the vertices of the particle, which
+    /// all share the same value anyway when the value only depends on the
+    /// particle. Boolean and matrix values can't be interpolated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let property_layout = PropertyLayout::default();
+    /// # let particle_layout = ParticleLayout::default();
+    /// # let texture_layout = TextureLayout::default();
+    /// # let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+    /// let mut module = Module::default();
+    /// let age = module.attr(Attribute::AGE);
+    /// context
+    ///     .add_interpolant(&module, "age", Attribute::AGE.value_type(), age)
+    ///     .unwrap();
+    /// context.fragment_code += "color.a *= saturate(1. - age);\n";
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` is not a valid identifier or is already used
+    /// by another interpolant, if `value_type` can't be interpolated, if the
+    /// context already has [`MAX_INTERPOLANTS`] interpolants, or if the
+    /// `value` expression fails to evaluate.
+    ///
+    /// [`MAX_INTERPOLANTS`]: RenderContext::MAX_INTERPOLANTS
+    pub fn add_interpolant(
+        &mut self,
+        module: &Module,
+        name: &str,
+        value_type: ValueType,
+        value: ExprHandle,
+    ) -> Result<(), ExprError> {
+        let is_identifier = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(ExprError::SyntaxError(format!(
+                "Invalid interpolant name '{}'.",
+                name
+            )));
+        }
+        if self.interpolants.iter().any(|i| i.name == name) {
+            return Err(ExprError::SyntaxError(format!(
+                "Duplicate interpolant name '{}'.",
+                name
+            )));
+        }
+        let elem_type = match value_type {
+            ValueType::Scalar(s) => s,
+            ValueType::Vector(v) => v.elem_type(),
+            ValueType::Matrix(_) => ScalarType::Bool,
+        };
+        if !elem_type.is_numeric() {
+            return Err(ExprError::TypeError(format!(
+                "Interpolant '{}' has type {:?}, which can't be interpolated.",
+                name, value_type
+            )));
+        }
+        if self.interpolants.len() >= Self::MAX_INTERPOLANTS {
+            return Err(ExprError::GraphEvalError(format!(
+                "Too many interpolants, the maximum is {}.",
+                Self::MAX_INTERPOLANTS
+            )));
+        }
+
+        let value = self.eval(module, value)?;
+        self.vertex_code += &format!("out.interp_{} = {};\n", name, value);
+        self.interpolants.push(Interpolant {
+            name: name.to_string(),
+            value_type,
+        });
+        Ok(())
+    }
+
+    /// Generate the fields of the vertex output storing the interpolants.
+    pub(crate) fn interpolant_fields_code(&self) -> String {
+        self.interpolants
+            .iter()
+            .enumerate()
+            .map(|(index, interpolant)| {
+                let location = Self::FIRST_INTERPOLANT_LOCATION + index;
+                let is_float = match interpolant.value_type {
+                    ValueType::Scalar(s) => s == ScalarType::Float,
+                    ValueType::Vector(v) => v.elem_type() == ScalarType::Float,
+                    ValueType::Matrix(_) => true,
+                };
+                let interpolate = if is_float { "" } else { " @interpolate(flat)" };
+                format!(
+                    "    @location({}){} interp_{}: {},\n",
+                    location,
+                    interpolate,
+                    interpolant.name,
+                    interpolant.value_type.to_wgsl_string()
+                )
+            })
+            .collect()
+    }
+
+    /// Generate the fragment code reading the interpolants from the vertex
+    /// output into local variables.
+    pub(crate) fn interpolant_reads_code(&self) -> String {
+        self.interpolants
+            .iter()
+            .map(|interpolant| format!("let {0} = in.interp_{0};\n", interpolant.name))
+            .collect()
+    }
 }
 
 impl<'a> EvalContext for RenderContext<'a> {
@@ -544,6 +735,22 @@ pub trait RenderModifier: Modifier {
     fn as_modifier(&self) -> &dyn Modifier;
 }
 
+/// Marker trait for modifiers which can be used in the init context.
+///
+/// This allows [`EffectAsset::init()`] to reject at compile time any modifier
+/// not supporting [`ModifierContext::Init`].
+///
+/// [`EffectAsset::init()`]: crate::EffectAsset::init
+pub trait InitModifier: Modifier {}
+
+/// Marker trait for modifiers which can be used in the update context.
+///
+/// This allows [`EffectAsset::update()`] to reject at compile time any
+/// modifier not supporting [`ModifierContext::Update`].
+///
+/// [`EffectAsset::update()`]: crate::EffectAsset::update
+pub trait UpdateModifier: Modifier {}
+
 impl Clone for Box<dyn RenderModifier> {
     fn clone(&self) -> Self {
         self.boxed_render_clone()
@@ -678,6 +885,7 @@ mod tests {
         let center = module.lit(Vec3::ZERO);
         let axis = module.lit(Vec3::Y);
         let radius = module.lit(1.);
+        let point_cloud = SetPositionPointCloudModifier::new([Vec3::ZERO, Vec3::ONE]);
         let modifiers: &[&dyn Modifier] = &[
             &SetPositionCircleModifier {
                 center,
@@ -696,6 +904,10 @@ mod tests {
                 height: radius,
                 dimension: ShapeDimension::Volume,
             },
+            &point_cloud,
+            &point_cloud
+                .clone()
+                .with_sampling(PointCloudSampling::Random),
             &SetVelocityCircleModifier {
                 center,
                 axis,
@@ -735,6 +947,14 @@ mod tests {
 
 const tau: f32 = 6.283185307179586476925286766559;
 
+struct Spawner {{
+    spawn_index: u32,
+}};
+
+var<private> spawner: Spawner;
+
+@group(0) @binding(0) var<storage, read> point_cloud : array<vec4<f32>>;
+
 struct Particle {{
     {attributes_code}
 }};
@@ -743,6 +963,7 @@ struct Particle {{
 
 @compute @workgroup_size(64)
 fn main() {{
+    let thread_index = 0u;
     var particle = Particle();
     var transform: mat4x4<f32> = mat4x4<f32>();
 {main_code}
@@ -775,11 +996,20 @@ fn main() {{
         let radius = one;
         let modifiers: &[&dyn Modifier] = &[
             &AccelModifier::new(origin),
+            &AccelModifier::new(origin).with_max_speed(one),
             &RadialAccelModifier::new(origin, one),
             &TangentAccelModifier::new(origin, y_axis, one),
             &ConformToSphereModifier::new(origin, one, one, one, one),
             &LinearDragModifier::new(writer.lit(3.5).expr()),
             &KillAabbModifier::new(writer.lit(Vec3::ZERO).expr(), writer.lit(Vec3::ONE).expr()),
+            &DensityCapModifier::new(1000, writer.lit(0.1).expr()),
+            &KillAlphaModifier::default(),
+            &KillAlphaModifier::new(Attribute::COLOR),
+            &DepthCollisionModifier::new(one),
+            &DepthCollisionModifier::new(one)
+                .with_response(DepthCollisionResponse::Reflect { restitution: one }),
+            &CollisionPlaneModifier::new(origin, y_axis, one),
+            &CollisionPlaneModifier::new(origin, y_axis, one).with_friction(one),
             &SetPositionCircleModifier {
                 center,
                 axis,
@@ -866,6 +1096,13 @@ fn proj(u: vec3<f32>, v: vec3<f32>) -> vec3<f32> {{
     return dot(v, u) / dot(u,u) * u;
 }}
 
+fn scene_depth_available() -> bool {{ return false; }}
+fn scene_depth_project(world_position: vec3<f32>) -> vec4<f32> {{ return vec4<f32>(0.0); }}
+fn scene_depth_sample(uv: vec2<f32>) -> f32 {{ return 0.0; }}
+fn scene_depth_unproject(uv: vec2<f32>, depth: f32) -> vec3<f32> {{ return vec3<f32>(0.0); }}
+fn scene_depth_normal(uv: vec2<f32>) -> vec3<f32> {{ return vec3<f32>(0.0, 1.0, 0.0); }}
+fn group_alive_count() -> u32 {{ return 0u; }}
+
 {update_extra}
 
 @group(0) @binding(0) var<uniform> sim_params : SimParams;
@@ -899,13 +1136,16 @@ fn main() {{
     fn validate_render() {
         let mut base_module = Module::default();
         let slot_zero = base_module.lit(0u32);
+        let one = base_module.lit(1.);
         let modifiers: &[&dyn RenderModifier] = &[
             &ParticleTextureModifier::new(slot_zero),
+            &TriplanarTextureModifier::new(slot_zero, one),
             &ColorOverLifetimeModifier::default(),
             &SizeOverLifetimeModifier::default(),
             &OrientModifier::new(OrientMode::ParallelCameraDepthPlane),
             &OrientModifier::new(OrientMode::FaceCameraPosition),
             &OrientModifier::new(OrientMode::AlongVelocity),
+            &OrientModifier::new(OrientMode::AlongVelocityUp),
         ];
         for &modifier in modifiers.iter() {
             let mut module = base_module.clone();
@@ -973,6 +1213,8 @@ struct Particle {{
 struct VertexOutput {{
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
 }};
 
 @group(0) @binding(0) var<uniform> view: View;
@@ -1040,4 +1282,94 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {{
             assert_eq!(s2, s);
         }
     }
+
+    #[test]
+    fn interpolant() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new().append(Attribute::AGE).build();
+        let texture_layout = module.texture_layout();
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+
+        let age = module.attr(Attribute::AGE);
+        let value_type = Attribute::AGE.value_type();
+        context
+            .add_interpolant(&module, "age", value_type, age)
+            .unwrap();
+        assert_eq!(
+            context.interpolants,
+            vec![Interpolant {
+                name: "age".to_string(),
+                value_type
+            }]
+        );
+
+        // Written in the vertex shader to the vertex output...
+        assert_eq!(context.vertex_code, "out.interp_age = particle.age;\n");
+        assert_eq!(
+            context.interpolant_fields_code(),
+            "    @location(6) interp_age: f32,\n"
+        );
+        // ...and read back in the fragment shader
+        assert_eq!(
+            context.interpolant_reads_code(),
+            "let age = in.interp_age;\n"
+        );
+
+        // Invalid or duplicate names
+        for name in ["", "1age", "my age", "age"] {
+            assert!(matches!(
+                context.add_interpolant(&module, name, value_type, age),
+                Err(ExprError::SyntaxError(_))
+            ));
+        }
+
+        // Integers are not interpolated, booleans can't be
+        let index = module.lit(3u32);
+        context
+            .add_interpolant(&module, "index", ScalarType::Uint.into(), index)
+            .unwrap();
+        assert!(context
+            .interpolant_fields_code()
+            .ends_with("    @location(7) @interpolate(flat) interp_index: u32,\n"));
+        let flag = module.lit(true);
+        assert!(matches!(
+            context.add_interpolant(&module, "flag", ScalarType::Bool.into(), flag),
+            Err(ExprError::TypeError(_))
+        ));
+
+        // The number of interpolants is bounded
+        for i in context.interpolants.len()..RenderContext::MAX_INTERPOLANTS {
+            context
+                .add_interpolant(&module, &format!("x{}", i), value_type, age)
+                .unwrap();
+        }
+        assert!(matches!(
+            context.add_interpolant(&module, "extra", value_type, age),
+            Err(ExprError::GraphEvalError(_))
+        ));
+    }
+
+    #[test]
+    fn birth_time() {
+        let writer = ExprWriter::new();
+        let modifier = SetAttributeModifier::birth_time(&writer);
+        assert_eq!(modifier.attribute, Attribute::BIRTH_TIME);
+        assert_eq!(modifier.attributes(), &[Attribute::BIRTH_TIME]);
+
+        let mut module = writer.finish();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new().append(Attribute::BIRTH_TIME).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        assert_eq!(
+            context.main_code,
+            format!(
+                "particle.{} = sim_params.{};\n",
+                Attribute::BIRTH_TIME.name(),
+                BuiltInOperator::Time.name()
+            )
+        );
+    }
 }
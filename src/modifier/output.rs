@@ -135,7 +135,7 @@ impl ParticleTextureModifier {
         let count = module.texture_layout().layout.len() as u32;
         for index in 0..count {
             let wgsl_index = index.to_wgsl_string();
-            code += &format!("      case {wgsl_index}: {{ texColor = textureSample(material_texture_{index}, material_sampler_{index}, uv); }}\n");
+            code += &format!("      case {wgsl_index}: {{ texColor = sample_material(material_texture_{index}, material_sampler_{index}, uv); }}\n");
         }
         code += "      default: {{ texColor = vec4<f32>(0.0); }}\n";
         code += &format!(
@@ -147,6 +147,271 @@ impl ParticleTextureModifier {
     }
 }
 
+/// A modifier modulating each particle's color by sampling a texture with
+/// triplanar mapping.
+///
+/// Instead of using the particle's UV coordinates, the texture is projected
+/// along the three world axes X, Y, and Z, and the three samples are blended
+/// based on the world-space normal of the particle's fragment. This avoids the
+/// stretching of UV mapping on large volumetric particles, and makes the
+/// texture appear fixed in world space as the particle moves.
+///
+/// The texture coordinates of each projection are the world-space position of
+/// the fragment multiplied by [`scale`].
+///
+/// # Attributes
+///
+/// This modifier does not require any specific particle attribute.
+///
+/// [`scale`]: TriplanarTextureModifier::scale
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct TriplanarTextureModifier {
+    /// Index of the texture slot containing the texture to use. The slot is
+    /// defined in the [`Module`], and the actual texture is bound via the
+    /// [`EffectMaterial`] component.
+    ///
+    /// [`EffectMaterial`]: crate::EffectMaterial
+    pub texture_slot: ExprHandle,
+
+    /// Scale factor applied to the world-space position to obtain the texture
+    /// coordinates of each projection.
+    ///
+    /// Expression type: `f32`
+    pub scale: ExprHandle,
+
+    /// The mapping of the texture image samples to the base particle color.
+    pub sample_mapping: ImageSampleMapping,
+}
+
+impl TriplanarTextureModifier {
+    /// Create a new modifier with the default [`ImageSampleMapping`].
+    pub fn new(texture_slot: ExprHandle, scale: ExprHandle) -> Self {
+        Self {
+            texture_slot,
+            scale,
+            sample_mapping: default(),
+        }
+    }
+}
+
+impl_mod_render!(TriplanarTextureModifier, &[]);
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl RenderModifier for TriplanarTextureModifier {
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.set_needs_world_normal();
+        let code = self.eval(module, context)?;
+        context.fragment_code += &code;
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+impl TriplanarTextureModifier {
+    /// Evaluate the modifier to generate the shader code.
+    pub fn eval(
+        &self,
+        module: &Module,
+        context: &mut dyn EvalContext,
+    ) -> Result<String, ExprError> {
+        let texture_slot = module.try_get(self.texture_slot)?;
+        let texture_slot = texture_slot.eval(module, context)?;
+        let scale = module.try_get(self.scale)?;
+        let scale = scale.eval(module, context)?;
+        let sample_mapping = self.sample_mapping.to_wgsl_string();
+
+        let sample_mapping_name = format!("{:?}", self.sample_mapping);
+
+        // Blend weights from the world-space normal, normalized such that they sum
+        // to one. The projection along an axis is the plane orthogonal to it.
+        let mut code = String::with_capacity(1024);
+        code += &format!(
+            "    // TriplanarTextureModifier
+    let triplanar_pos = in.world_position * ({scale});
+    var triplanar_weights = abs(in.world_normal);
+    triplanar_weights /= max(triplanar_weights.x + triplanar_weights.y + triplanar_weights.z, 1e-5);
+    var texColor: vec4<f32>;
+    switch ({texture_slot}) {{\n"
+        );
+        let count = module.texture_layout().layout.len() as u32;
+        for index in 0..count {
+            let wgsl_index = index.to_wgsl_string();
+            code += &format!(
+                "      case {wgsl_index}: {{
+        let tex_x = textureSample(material_texture_{index}, material_sampler_{index}, triplanar_pos.zy);
+        let tex_y = textureSample(material_texture_{index}, material_sampler_{index}, triplanar_pos.xz);
+        let tex_z = textureSample(material_texture_{index}, material_sampler_{index}, triplanar_pos.xy);
+        texColor = tex_x * triplanar_weights.x + tex_y * triplanar_weights.y + tex_z * triplanar_weights.z;
+      }}\n"
+            );
+        }
+        code += "      default: {{ texColor = vec4<f32>(0.0); }}\n";
+        code += &format!(
+            "    }}
+    // Sample mapping: {sample_mapping_name}
+    {sample_mapping}"
+        );
+        Ok(code)
+    }
+}
+
+/// A modifier rendering each particle as a glyph of a signed distance field
+/// (SDF) font atlas.
+///
+/// The atlas texture is a grid of [`atlas_grid_size`] glyphs, whose red
+/// channel stores the distance to the glyph outline, remapped such that the
+/// outline is at [`threshold`]. Each particle renders the glyph of the atlas
+/// at its [`Attribute::SPRITE_INDEX`], which is typically set per particle
+/// when spawning, for example to render floating damage numbers.
+///
+/// The opacity of the particle is modulated by the coverage of the glyph,
+/// obtained from the sampled distance with a smooth transition of half-width
+/// [`smoothing`] around the threshold:
+///
+/// ```wgsl
+/// let sdf_alpha = smoothstep(threshold - smoothing, threshold + smoothing, distance);
+/// color.a = color.a * sdf_alpha;
+/// ```
+///
+/// This modifier overrides the UV coordinates of the particle, and therefore
+/// is not compatible with the [`FlipbookModifier`].
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::SPRITE_INDEX`]
+///
+/// [`atlas_grid_size`]: SdfGlyphModifier::atlas_grid_size
+/// [`threshold`]: SdfGlyphModifier::threshold
+/// [`smoothing`]: SdfGlyphModifier::smoothing
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct SdfGlyphModifier {
+    /// Index of the texture slot containing the SDF font atlas. The slot is
+    /// defined in the [`Module`], and the actual texture is bound via the
+    /// [`EffectMaterial`] component.
+    ///
+    /// [`EffectMaterial`]: crate::EffectMaterial
+    pub texture_slot: ExprHandle,
+
+    /// Glyph grid size of the font atlas, in number of glyphs.
+    pub atlas_grid_size: UVec2,
+
+    /// Distance value of the glyph outline.
+    ///
+    /// Expression type: `f32`
+    pub threshold: ExprHandle,
+
+    /// Half-width of the smooth transition around the glyph outline, in
+    /// distance units.
+    ///
+    /// Expression type: `f32`
+    pub smoothing: ExprHandle,
+}
+
+impl SdfGlyphModifier {
+    /// Create a new modifier.
+    pub fn new(
+        texture_slot: ExprHandle,
+        atlas_grid_size: UVec2,
+        threshold: ExprHandle,
+        smoothing: ExprHandle,
+    ) -> Self {
+        Self {
+            texture_slot,
+            atlas_grid_size,
+            threshold,
+            smoothing,
+        }
+    }
+}
+
+impl_mod_render!(SdfGlyphModifier, &[Attribute::SPRITE_INDEX]);
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl RenderModifier for SdfGlyphModifier {
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.set_needs_uv();
+
+        // Select the glyph cell of the atlas
+        let row_count = (self.atlas_grid_size.x as i32).to_wgsl_string();
+        let scale = Vec2::new(
+            1.0 / self.atlas_grid_size.x as f32,
+            1.0 / self.atlas_grid_size.y as f32,
+        )
+        .to_wgsl_string();
+        let glyph_index = format!("particle.{}", Attribute::SPRITE_INDEX.name());
+        context.vertex_code += &format!(
+            "    // SdfGlyphModifier
+    let glyph_ij = vec2<f32>(f32({glyph_index} % {row_count}), f32({glyph_index} / {row_count}));
+    out.uv = (glyph_ij + vertex_uv) * {scale};\n"
+        );
+
+        let code = self.eval(module, context)?;
+        context.fragment_code += &code;
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+impl SdfGlyphModifier {
+    /// Evaluate the modifier to generate the fragment shader code.
+    pub fn eval(
+        &self,
+        module: &Module,
+        context: &mut dyn EvalContext,
+    ) -> Result<String, ExprError> {
+        let texture_slot = module.try_get(self.texture_slot)?;
+        let texture_slot = texture_slot.eval(module, context)?;
+        let threshold = module.try_get(self.threshold)?;
+        let threshold = threshold.eval(module, context)?;
+        let smoothing = module.try_get(self.smoothing)?;
+        let smoothing = smoothing.eval(module, context)?;
+
+        let mut code = String::with_capacity(1024);
+        code += &format!(
+            "    // SdfGlyphModifier
+    var sdf_distance: f32;
+    switch ({texture_slot}) {{\n"
+        );
+        let count = module.texture_layout().layout.len() as u32;
+        for index in 0..count {
+            let wgsl_index = index.to_wgsl_string();
+            code += &format!("      case {wgsl_index}: {{ sdf_distance = textureSample(material_texture_{index}, material_sampler_{index}, uv).r; }}\n");
+        }
+        code += "      default: { sdf_distance = 0.0; }\n";
+        code += &format!(
+            "    }}
+    let sdf_threshold = {threshold};
+    let sdf_smoothing = {smoothing};
+    let sdf_alpha = smoothstep(sdf_threshold - sdf_smoothing, sdf_threshold + sdf_smoothing, sdf_distance);
+    color.a = color.a * sdf_alpha;\n"
+        );
+        Ok(code)
+    }
+}
+
 /// A modifier to set the rendering color of all particles.
 ///
 /// This modifier assigns a _single_ color to all particles. That color can be
@@ -303,6 +568,108 @@ impl_mod_render!(
     &[Attribute::AGE, Attribute::LIFETIME]
 );
 
+impl SizeOverLifetimeModifier {
+    /// Maximum number of times a segment is subdivided by
+    /// [`from_curve_adaptive()`].
+    ///
+    /// [`from_curve_adaptive()`]: SizeOverLifetimeModifier::from_curve_adaptive
+    const MAX_CURVE_SUBDIVISIONS: u32 = 8;
+
+    /// Create a modifier from a size curve, sampled uniformly.
+    ///
+    /// The curve maps the ratio of the age of the particle to its lifetime, in
+    /// \[0:1\], to the particle size, which is applied to both axes. The curve
+    /// is sampled at `resolution + 1` evenly spaced ratios, including `0.` and
+    /// `1.`, to build the equivalent gradient, which linearly interpolates
+    /// between those samples.
+    ///
+    /// Any function can be used as a curve, for example a Bevy [`CubicCurve`]
+    /// with `|t| curve.position(t)`, or an easing function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is zero.
+    ///
+    /// [`CubicCurve`]: bevy::math::cubic_splines::CubicCurve
+    pub fn from_curve(curve: impl Fn(f32) -> f32, resolution: u32) -> Self {
+        Self::from_curve_impl(&curve, resolution, None)
+    }
+
+    /// Create a modifier from a size curve, sampled adaptively.
+    ///
+    /// This is similar to [`from_curve()`], but each of the `resolution`
+    /// uniform segments is recursively subdivided as long as the curve
+    /// deviates from the linear interpolation of the segment by more than
+    /// `tolerance` at its midpoint, up to a maximum subdivision depth. This
+    /// adds keys only in the steep or highly curved regions of the curve,
+    /// keeping the gradient small elsewhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is zero, or `tolerance` is not positive.
+    ///
+    /// [`from_curve()`]: SizeOverLifetimeModifier::from_curve
+    pub fn from_curve_adaptive(
+        curve: impl Fn(f32) -> f32,
+        resolution: u32,
+        tolerance: f32,
+    ) -> Self {
+        assert!(
+            tolerance > 0.,
+            "Invalid curve tolerance {}, must be positive.",
+            tolerance
+        );
+        Self::from_curve_impl(&curve, resolution, Some(tolerance))
+    }
+
+    fn from_curve_impl(
+        curve: &impl Fn(f32) -> f32,
+        resolution: u32,
+        tolerance: Option<f32>,
+    ) -> Self {
+        assert!(resolution > 0, "Invalid zero curve resolution.");
+        let mut keys = vec![(0., curve(0.))];
+        for index in 0..resolution {
+            let start = keys.last().copied().unwrap();
+            let t1 = (index + 1) as f32 / resolution as f32;
+            let end = (t1, curve(t1));
+            Self::subdivide_curve(curve, start, end, tolerance, 0, &mut keys);
+        }
+        Self {
+            gradient: Gradient::from_keys(
+                keys.into_iter()
+                    .map(|(ratio, size)| (ratio, Vec2::splat(size))),
+            ),
+            screen_space_size: false,
+        }
+    }
+
+    /// Append the keys sampling the curve over a segment, excluding its start
+    /// key, which is assumed already appended.
+    fn subdivide_curve(
+        curve: &impl Fn(f32) -> f32,
+        start: (f32, f32),
+        end: (f32, f32),
+        tolerance: Option<f32>,
+        depth: u32,
+        keys: &mut Vec<(f32, f32)>,
+    ) {
+        if let Some(tolerance) = tolerance {
+            if depth < Self::MAX_CURVE_SUBDIVISIONS {
+                let t = (start.0 + end.0) * 0.5;
+                let mid = (t, curve(t));
+                let linear = (start.1 + end.1) * 0.5;
+                if (mid.1 - linear).abs() > tolerance {
+                    Self::subdivide_curve(curve, start, mid, Some(tolerance), depth + 1, keys);
+                    Self::subdivide_curve(curve, mid, end, Some(tolerance), depth + 1, keys);
+                    return;
+                }
+            }
+        }
+        keys.push(end);
+    }
+}
+
 #[cfg_attr(feature = "serde", typetag::serde)]
 impl RenderModifier for SizeOverLifetimeModifier {
     fn apply_render(
@@ -388,6 +755,27 @@ pub enum OrientMode {
     ///
     /// With this mode, any provided [`OrientModifier::rotation`] is ignored.
     AlongVelocity,
+
+    /// Orient a particle's forward axis alongside its velocity, with the roll
+    /// stabilized by a world up vector.
+    ///
+    /// The local Z axis (forward) points alongside the velocity. The local X
+    /// axis is the cross product of the up vector with that forward axis, and
+    /// the local Y axis completes the orthonormal basis, so it's the closest
+    /// direction to the up vector perpendicular to the velocity. Unlike
+    /// [`AlongVelocity`], the frame doesn't depend on the camera, which makes
+    /// this mode suited for 3D mesh particles.
+    ///
+    /// The up vector is given by [`OrientModifier::up`], and defaults to
+    /// `Vec3::Y`. When the velocity is near-parallel to the up vector, so
+    /// that the basis is degenerate, a fallback up vector is used instead
+    /// (the world X axis, or the world Z axis if the velocity is itself
+    /// near-parallel to the X axis).
+    ///
+    /// With this mode, any provided [`OrientModifier::rotation`] is ignored.
+    ///
+    /// [`AlongVelocity`]: crate::modifier::output::OrientMode::AlongVelocity
+    AlongVelocityUp,
 }
 
 /// Orients the particle's local frame.
@@ -405,6 +793,8 @@ pub enum OrientMode {
 ///   [`Attribute::POSITION`] attribute.
 /// - [`OrientMode::AlongVelocity`]: This modifier requires the
 ///   [`Attribute::POSITION`] and [`Attribute::VELOCITY`] attributes.
+/// - [`OrientMode::AlongVelocityUp`]: This modifier requires the
+///   [`Attribute::VELOCITY`] attribute.
 ///
 /// [`mode`]: crate::modifier::output::OrientModifier::mode
 /// [`Attribute::POSITION`]: crate::attributes::Attribute::POSITION
@@ -418,6 +808,12 @@ pub struct OrientModifier {
     /// The actual meaning depends on [`OrientMode`], and the rotation may be
     /// ignored for some mode(s).
     pub rotation: Option<ExprHandle>,
+    /// Optional world up vector expression, as a `vec3<f32>`.
+    ///
+    /// Only used by [`OrientMode::AlongVelocityUp`] to stabilize the roll of
+    /// the particle around its velocity. If `None`, defaults to `Vec3::Y`.
+    /// The vector doesn't need to be normalized.
+    pub up: Option<ExprHandle>,
 }
 
 impl OrientModifier {
@@ -434,6 +830,12 @@ impl OrientModifier {
         self.rotation = Some(rotation);
         self
     }
+
+    /// Set the world up vector expression for the particles.
+    pub fn with_up(mut self, up: ExprHandle) -> Self {
+        self.up = Some(up);
+        self
+    }
 }
 
 #[cfg_attr(feature = "serde", typetag::serde)]
@@ -458,6 +860,7 @@ impl Modifier for OrientModifier {
             OrientMode::ParallelCameraDepthPlane => &[],
             OrientMode::FaceCameraPosition => &[Attribute::POSITION],
             OrientMode::AlongVelocity => &[Attribute::POSITION, Attribute::VELOCITY],
+            OrientMode::AlongVelocityUp => &[Attribute::VELOCITY],
         }
     }
 
@@ -529,6 +932,25 @@ axis_y = cross(dir, axis_x);
 axis_z = cross(axis_x, axis_y);
 "#;
             }
+            OrientMode::AlongVelocityUp => {
+                let up = if let Some(up) = self.up {
+                    context.eval(module, up)?
+                } else {
+                    "vec3<f32>(0., 1., 0.)".to_string()
+                };
+                context.vertex_code += &format!(
+                    r#"let fwd = normalize(particle.velocity);
+var up = normalize({});
+if (abs(dot(fwd, up)) > 0.999) {{
+    up = select(vec3<f32>(0., 0., 1.), vec3<f32>(1., 0., 0.), abs(fwd.x) < 0.9);
+}}
+axis_z = fwd;
+axis_x = normalize(cross(up, fwd));
+axis_y = cross(fwd, axis_x);
+"#,
+                    up
+                );
+            }
         }
 
         Ok(())
@@ -604,16 +1026,27 @@ axis_z = cross(axis_x, axis_y);
 ///     texture_slot,
 ///     sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
 /// })
-/// .render(FlipbookModifier {
-///     sprite_grid_size: UVec2::new(2, 2), // 4 frames
-/// });
+/// .render(FlipbookModifier::new(UVec2::new(2, 2))); // 4 frames
 /// ```
 ///
+/// # Frame blending
+///
+/// By default the sprite snaps from one frame to the next, which can look
+/// choppy when the animation has few frames. When [`blend_frames`] is enabled,
+/// the texture is sampled at both the current sprite and the next one, and the
+/// two samples are cross-faded based on the [`Attribute::SPRITE_BLEND`] of the
+/// particle. The next sprite after the last one of the grid is the first one,
+/// so looping animations blend seamlessly. In that case the sprite index is
+/// typically set to the integer part of a frame position, and the blend factor
+/// to its fractional part.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::SPRITE_INDEX`]
+/// - [`Attribute::SPRITE_BLEND`], only if [`blend_frames`] is enabled.
 ///
+/// [`blend_frames`]: FlipbookModifier::blend_frames
 /// [`SetAttributeModifier`]: crate::modifier::attr::SetAttributeModifier
 #[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct FlipbookModifier {
@@ -630,18 +1063,71 @@ pub struct FlipbookModifier {
     /// produce any error, but will yield texture UV coordinates outside the
     /// `[0:1]` range.
     pub sprite_grid_size: UVec2,
+    /// Cross-fade between the current sprite and the next one.
+    ///
+    /// When enabled, the texture is sampled twice, and the samples are mixed
+    /// based on the [`Attribute::SPRITE_BLEND`] of the particle.
+    #[serde(default)]
+    pub blend_frames: bool,
 }
 
 impl Default for FlipbookModifier {
     fn default() -> Self {
         // Default to something which animates, to help debug mistakes.
+        Self::new(UVec2::ONE * 2)
+    }
+}
+
+impl FlipbookModifier {
+    /// Create a new modifier with the given sprite sheet grid size, without
+    /// frame blending.
+    pub fn new(sprite_grid_size: UVec2) -> Self {
         Self {
-            sprite_grid_size: UVec2::ONE * 2,
+            sprite_grid_size,
+            blend_frames: false,
         }
     }
+
+    /// Enable or disable cross-fading between consecutive sprites.
+    pub fn with_blend_frames(mut self, blend_frames: bool) -> Self {
+        self.blend_frames = blend_frames;
+        self
+    }
 }
 
-impl_mod_render!(FlipbookModifier, &[Attribute::SPRITE_INDEX]);
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for FlipbookModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Render
+    }
+
+    fn as_render(&self) -> Option<&dyn RenderModifier> {
+        Some(self)
+    }
+
+    fn as_render_mut(&mut self) -> Option<&mut dyn RenderModifier> {
+        Some(self)
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        if self.blend_frames {
+            &[Attribute::SPRITE_INDEX, Attribute::SPRITE_BLEND]
+        } else {
+            &[Attribute::SPRITE_INDEX]
+        }
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, _module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        Err(ExprError::InvalidModifierContext(
+            context.modifier_context(),
+            ModifierContext::Render,
+        ))
+    }
+}
 
 #[cfg_attr(feature = "serde", typetag::serde)]
 impl RenderModifier for FlipbookModifier {
@@ -651,6 +1137,11 @@ impl RenderModifier for FlipbookModifier {
         context: &mut RenderContext,
     ) -> Result<(), ExprError> {
         context.sprite_grid_size = Some(self.sprite_grid_size);
+        if self.blend_frames {
+            // The next frame offset is passed to the fragment shader along the UVs
+            context.set_needs_uv();
+            context.flipbook_blend = true;
+        }
         Ok(())
     }
 
@@ -725,6 +1216,128 @@ impl RenderModifier for ScreenSpaceSizeModifier {
     }
 }
 
+/// A modifier to partially compensate the perspective shrinkage of particles.
+///
+/// This modifier scales the size of all particles with their distance to the
+/// camera, to interpolate between a size in world units and a constant
+/// apparent size. With a [`perspective_compensation`] of `0.0` the size is left
+/// untouched and particles shrink with distance like any other object. With a
+/// value of `1.0` the perspective divide is fully negated, and particles keep
+/// the apparent size they would have one world unit away from the camera.
+/// Intermediate values let distant particles shrink less than they would
+/// otherwise, which helps keeping important effects readable at a distance.
+///
+/// Unlike the [`ScreenSpaceSizeModifier`], the size is not interpreted in
+/// pixels, and the result doesn't depend on the viewport size.
+///
+/// Like the [`ScreenSpaceSizeModifier`], this modifier should generally be
+/// placed last in the stack.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// [`perspective_compensation`]: PerspectiveSizeModifier::perspective_compensation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct PerspectiveSizeModifier {
+    /// Fraction of the perspective divide to compensate, from `0.0` (world
+    /// size) to `1.0` (constant apparent size).
+    pub perspective_compensation: f32,
+}
+
+impl PerspectiveSizeModifier {
+    /// Create a new modifier with the given perspective compensation.
+    pub fn new(perspective_compensation: f32) -> Self {
+        Self {
+            perspective_compensation,
+        }
+    }
+}
+
+impl_mod_render!(PerspectiveSizeModifier, &[Attribute::POSITION]);
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl RenderModifier for PerspectiveSizeModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        // Scaling by w_cs negates the perspective divide happening after the vertex
+        // shader; interpolate between no scaling and that full scaling.
+        context.vertex_code += &format!(
+            "let perspective_w_cs = transform_position_simulation_to_clip(particle.{0}).w;\nsize = size * mix(1.0, perspective_w_cs, {1});\n",
+            Attribute::POSITION.name(),
+            self.perspective_compensation.to_wgsl_string()
+        );
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// A modifier to set the pivot point of the particle mesh.
+///
+/// By default the particle mesh is centered on the particle position. This
+/// modifier offsets the mesh so that the given anchor point of the mesh lies at
+/// the particle position instead. The anchor is expressed in normalized quad
+/// space, with `(0, 0)` the bottom left corner and `(1, 1)` the top right one,
+/// along the particle's local X and Y axes. The default `(0.5, 0.5)` is the
+/// quad center; for example `(0.5, 0.0)` pivots on the bottom edge, which is
+/// useful for flames rising from their base.
+///
+/// The anchor is applied during the mesh expansion, after all render modifiers,
+/// so it affects both the position and the rotation pivot of the particle.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct AnchorModifier {
+    /// Anchor point of the particle mesh, in normalized quad space.
+    pub anchor: Vec2,
+}
+
+impl Default for AnchorModifier {
+    fn default() -> Self {
+        Self {
+            anchor: Vec2::splat(0.5),
+        }
+    }
+}
+
+impl AnchorModifier {
+    /// Create a new modifier with the given anchor.
+    pub fn new(anchor: Vec2) -> Self {
+        Self { anchor }
+    }
+}
+
+impl_mod_render!(AnchorModifier, &[]);
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl RenderModifier for AnchorModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.vertex_code += &format!("anchor = {};\n", self.anchor.to_wgsl_string());
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
 /// Makes particles round.
 ///
 /// The shape of each particle is a [squircle] (like a rounded rectangle, but
@@ -827,11 +1440,81 @@ mod tests {
         assert_eq!(context.textures.len(), 0); // we "forgot" the EffectMaterial
     }
 
+    #[test]
+    fn mod_triplanar_texture() {
+        let mut module = Module::default();
+        module.add_texture("color");
+        let slot = module.lit(0u32);
+        let scale = module.lit(0.5);
+        let modifier = TriplanarTextureModifier::new(slot, scale);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let texture_layout = module.texture_layout();
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert!(context.needs_world_normal);
+        assert!(!context.needs_uv);
+
+        // Three projections, one per world axis
+        let code = &context.fragment_code;
+        assert!(code.contains("let triplanar_pos = in.world_position * (0.5);"));
+        for proj in ["zy", "xz", "xy"] {
+            assert!(code.contains(&format!(
+                "textureSample(material_texture_0, material_sampler_0, triplanar_pos.{proj})"
+            )));
+        }
+
+        // Normal-based blend
+        assert!(code.contains("var triplanar_weights = abs(in.world_normal);"));
+        assert!(code.contains(
+            "texColor = tex_x * triplanar_weights.x + tex_y * triplanar_weights.y + tex_z * triplanar_weights.z;"
+        ));
+    }
+
+    #[test]
+    fn mod_sdf_glyph() {
+        let mut module = Module::default();
+        module.add_texture("font");
+        let slot = module.lit(0u32);
+        let threshold = module.lit(0.5);
+        let smoothing = module.lit(0.1);
+        let modifier = SdfGlyphModifier::new(slot, UVec2::new(16, 8), threshold, smoothing);
+        assert_eq!(modifier.attributes(), &[Attribute::SPRITE_INDEX]);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let texture_layout = module.texture_layout();
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert!(context.needs_uv);
+
+        // Glyph index selects the atlas cell
+        let code = &context.vertex_code;
+        assert!(code.contains(&format!(
+            "let glyph_ij = vec2<f32>(f32(particle.{0} % 16), f32(particle.{0} / 16));",
+            Attribute::SPRITE_INDEX.name()
+        )));
+        assert!(code.contains("out.uv = (glyph_ij + vertex_uv) * vec2<f32>(0.0625,0.125);"));
+
+        // Distance sampled from the atlas, then thresholded with smoothing
+        let code = &context.fragment_code;
+        assert!(code.contains(
+            "sdf_distance = textureSample(material_texture_0, material_sampler_0, uv).r;"
+        ));
+        assert!(code.contains("let sdf_threshold = 0.5;"));
+        assert!(code.contains("let sdf_smoothing = 0.1;"));
+        assert!(code.contains("let sdf_alpha = smoothstep(sdf_threshold - sdf_smoothing, sdf_threshold + sdf_smoothing, sdf_distance);"));
+        assert!(code.contains("color.a = color.a * sdf_alpha;"));
+    }
+
     #[test]
     fn mod_flipbook() {
-        let modifier = FlipbookModifier {
-            sprite_grid_size: UVec2::new(3, 4),
-        };
+        let modifier = FlipbookModifier::new(UVec2::new(3, 4));
+        assert!(!modifier.blend_frames);
+        assert_eq!(modifier.attributes(), &[Attribute::SPRITE_INDEX]);
 
         let mut module = Module::default();
         let property_layout = PropertyLayout::default();
@@ -842,6 +1525,17 @@ mod tests {
 
         assert!(context.sprite_grid_size.is_some());
         assert_eq!(context.sprite_grid_size.unwrap(), UVec2::new(3, 4));
+        assert!(!context.flipbook_blend);
+
+        let modifier = modifier.with_blend_frames(true);
+        assert_eq!(
+            modifier.attributes(),
+            &[Attribute::SPRITE_INDEX, Attribute::SPRITE_BLEND]
+        );
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.flipbook_blend);
+        assert!(context.needs_uv);
     }
 
     #[test]
@@ -891,6 +1585,63 @@ mod tests {
             .contains(&gradient.to_shader_code("key")));
     }
 
+    #[test]
+    fn mod_size_over_lifetime_from_curve() {
+        // Maximum absolute error of the gradient against the curve
+        let max_error = |modifier: &SizeOverLifetimeModifier, curve: &dyn Fn(f32) -> f32| {
+            (0..=1000)
+                .map(|i| {
+                    let t = i as f32 / 1000.;
+                    let size = modifier.gradient.sample(t);
+                    assert_eq!(size.x, size.y);
+                    (size.x - curve(t)).abs()
+                })
+                .fold(0., f32::max)
+        };
+
+        // Uniform sampling
+        let curve = |t: f32| (2. * t).mul_add(t, 1.);
+        let modifier = SizeOverLifetimeModifier::from_curve(curve, 16);
+        assert!(!modifier.screen_space_size);
+        let keys = modifier.gradient.keys();
+        assert_eq!(keys.len(), 17);
+        assert_eq!(keys[0].ratio(), 0.);
+        assert_eq!(keys[0].value, Vec2::ONE);
+        assert_eq!(keys[8].ratio(), 0.5);
+        assert_eq!(keys[8].value, Vec2::splat(1.5));
+        assert_eq!(keys[16].ratio(), 1.);
+        assert_eq!(keys[16].value, Vec2::splat(3.));
+        assert!(max_error(&modifier, &curve) < 2e-3);
+
+        // A steep curve is poorly approximated at a low resolution, unless sampled
+        // adaptively, which only adds keys around the steep region
+        let curve = |t: f32| 1. / (1. + (-100. * (t - 0.4)).exp());
+        let uniform = SizeOverLifetimeModifier::from_curve(curve, 8);
+        assert!(max_error(&uniform, &curve) > 0.1);
+        let adaptive = SizeOverLifetimeModifier::from_curve_adaptive(curve, 8, 1e-3);
+        assert!(max_error(&adaptive, &curve) < 1e-2);
+        let keys = adaptive.gradient.keys();
+        assert!(keys.len() < 8 * 2_usize.pow(SizeOverLifetimeModifier::MAX_CURVE_SUBDIVISIONS));
+        assert!(keys
+            .windows(2)
+            .all(|pair| pair[0].ratio() < pair[1].ratio()));
+        let steep_keys = keys
+            .iter()
+            .filter(|key| (0.25..=0.55).contains(&key.ratio()))
+            .count();
+        assert!(steep_keys > keys.len() / 2);
+
+        // A linear curve needs no subdivision
+        let linear = SizeOverLifetimeModifier::from_curve_adaptive(|t| t, 4, 1e-3);
+        assert_eq!(linear.gradient.len(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mod_size_over_lifetime_from_curve_zero_resolution() {
+        let _ = SizeOverLifetimeModifier::from_curve(|t| t, 0);
+    }
+
     #[test]
     fn mod_set_color() {
         let mut modifier = SetColorModifier::default();
@@ -929,6 +1680,27 @@ mod tests {
         assert_eq!(context.vertex_code, "size = vec2<f32>(0.,0.);\n");
     }
 
+    #[test]
+    fn mod_perspective_size() {
+        let modifier = PerspectiveSizeModifier::new(0.5);
+        assert_eq!(modifier.attributes(), &[Attribute::POSITION]);
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let texture_layout = module.texture_layout();
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert_eq!(
+            context.vertex_code,
+            format!(
+                "let perspective_w_cs = transform_position_simulation_to_clip(particle.{}).w;\nsize = size * mix(1.0, perspective_w_cs, 0.5);\n",
+                Attribute::POSITION.name()
+            )
+        );
+    }
+
     #[test]
     fn mod_orient() {
         let mut modifier = OrientModifier::default();
@@ -998,4 +1770,42 @@ mod tests {
             .contains("cos(particle_rot_in_cam_space)"));
         assert!(context.vertex_code.contains("let axis_x0 ="));
     }
+
+    #[test]
+    fn mod_orient_along_velocity_up() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let texture_layout = module.texture_layout();
+
+        // Default up vector
+        let modifier = OrientModifier::new(OrientMode::AlongVelocityUp);
+        assert_eq!(modifier.attributes(), &[Attribute::VELOCITY]);
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        let code = &context.vertex_code;
+        assert!(code.contains("let fwd = normalize(particle.velocity);"));
+        assert!(code.contains("var up = normalize(vec3<f32>(0., 1., 0.));"));
+        assert!(code.contains("axis_z = fwd;"));
+        assert!(code.contains("axis_x = normalize(cross(up, fwd));"));
+        assert!(code.contains("axis_y = cross(fwd, axis_x);"));
+        // Fallback when the velocity is near-parallel to the up vector
+        assert!(code.contains("if (abs(dot(fwd, up)) > 0.999) {"));
+        assert!(code.contains(
+            "up = select(vec3<f32>(0., 0., 1.), vec3<f32>(1., 0., 0.), abs(fwd.x) < 0.9);"
+        ));
+        // The frame is independent of the camera
+        assert!(!code.contains("get_camera_position_effect_space"));
+
+        // Custom up vector
+        let up = module.lit(Vec3::Z);
+        let modifier = OrientModifier::new(OrientMode::AlongVelocityUp).with_up(up);
+        let mut context = RenderContext::new(&property_layout, &particle_layout, &texture_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        let up = context.eval(&module, up).unwrap();
+        assert!(context
+            .vertex_code
+            .contains(&format!("var up = normalize({});", up)));
+        assert!(!context.vertex_code.contains("vec3<f32>(0., 1., 0.)"));
+    }
 }
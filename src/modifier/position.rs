@@ -4,12 +4,15 @@
 //! useful to initialize the position at spawn time, but can occasionally be
 //! used during simulation update to enforce a particular position.
 
-use bevy::prelude::*;
+use std::hash::{Hash, Hasher};
+
+use bevy::{math::FloatOrd, prelude::*};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     calc_func_id, graph::ExprError, modifier::ShapeDimension, Attribute, BoxedModifier,
-    EvalContext, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    EvalContext, ExprHandle, InitModifier, Modifier, ModifierContext, Module, ShaderWriter,
+    UpdateModifier,
 };
 
 /// A modifier to set the position of particles on or inside a circle/disc,
@@ -129,6 +132,10 @@ impl Modifier for SetPositionCircleModifier {
     }
 }
 
+impl InitModifier for SetPositionCircleModifier {}
+
+impl UpdateModifier for SetPositionCircleModifier {}
+
 /// A modifier to set the position of particles on or inside a sphere, randomly.
 ///
 /// # Attributes
@@ -232,6 +239,10 @@ impl Modifier for SetPositionSphereModifier {
     }
 }
 
+impl InitModifier for SetPositionSphereModifier {}
+
+impl UpdateModifier for SetPositionSphereModifier {}
+
 /// A modifier to set the position of particles on a truncated 3D cone.
 ///
 /// The 3D cone is oriented along the Y axis, with its origin at the center of
@@ -346,3 +357,254 @@ impl Modifier for SetPositionCone3dModifier {
         Ok(())
     }
 }
+
+impl InitModifier for SetPositionCone3dModifier {}
+
+impl UpdateModifier for SetPositionCone3dModifier {}
+
+/// Order in which the points of a [`SetPositionPointCloudModifier`] are
+/// assigned to spawned particles.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum PointCloudSampling {
+    /// Assign the points in order, one per spawned particle, continuing each
+    /// frame after the last point assigned the previous frame. When more
+    /// particles are spawned than there are points, the index wraps around and
+    /// points are reused from the start of the point cloud.
+    ///
+    /// The cursor is kept per spawner, and advances by the number of particles
+    /// the spawner requested, so skips the points of any particle which
+    /// couldn't be spawned because the effect was at capacity.
+    ///
+    /// This is the default.
+    #[default]
+    Sequential,
+
+    /// Assign a point chosen uniformly at random to each spawned particle.
+    /// Points may be reused by several particles, even if there are more points
+    /// than spawned particles.
+    Random,
+}
+
+/// A modifier to set the position of particles from a point cloud.
+///
+/// The points are uploaded into a GPU storage buffer when the effect is
+/// spawned, and each spawned particle reads its position from that buffer,
+/// either sequentially or at random depending on [`sampling`]. The positions
+/// are relative to the emitter, like for the other position modifiers.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// [`sampling`]: crate::SetPositionPointCloudModifier::sampling
+#[derive(Debug, Default, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct SetPositionPointCloudModifier {
+    /// The points of the cloud, relative to the emitter position.
+    pub points: Vec<Vec3>,
+    /// The order in which points are assigned to spawned particles.
+    pub sampling: PointCloudSampling,
+}
+
+impl Hash for SetPositionPointCloudModifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.points.len().hash(state);
+        for p in &self.points {
+            FloatOrd(p.x).hash(state);
+            FloatOrd(p.y).hash(state);
+            FloatOrd(p.z).hash(state);
+        }
+        self.sampling.hash(state);
+    }
+}
+
+impl SetPositionPointCloudModifier {
+    /// Create a new modifier from a list of points, with the default
+    /// [`PointCloudSampling`].
+    pub fn new(points: impl Into<Vec<Vec3>>) -> Self {
+        Self {
+            points: points.into(),
+            sampling: default(),
+        }
+    }
+
+    /// Create a new modifier from a raw binary point cloud, with the default
+    /// [`PointCloudSampling`].
+    ///
+    /// The binary format is a tightly packed sequence of points, each made of
+    /// three little-endian `f32` values (X, Y, Z).
+    ///
+    /// Returns `None` if the input size is not a multiple of the size of a
+    /// single point (12 bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let chunks = bytes.chunks_exact(12);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let points = chunks
+            .map(|c| {
+                let f = |i: usize| f32::from_le_bytes([c[i], c[i + 1], c[i + 2], c[i + 3]]);
+                Vec3::new(f(0), f(4), f(8))
+            })
+            .collect::<Vec<_>>();
+        Some(Self::new(points))
+    }
+
+    /// Set the order in which points are assigned to spawned particles.
+    pub fn with_sampling(mut self, sampling: PointCloudSampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    fn eval(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<String, ExprError> {
+        if self.points.is_empty() {
+            return Err(ExprError::GraphEvalError(
+                "SetPositionPointCloudModifier has an empty point cloud.".to_string(),
+            ));
+        }
+
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_position_point_cloud_{0:016X}", func_id);
+        let count = self.points.len() as u32;
+        let offset = context.push_point_cloud(&self.points);
+
+        let index = match self.sampling {
+            PointCloudSampling::Sequential => {
+                format!("(spawner.spawn_index + point_index) % {count}u")
+            }
+            PointCloudSampling::Random => {
+                format!("min(u32(frand() * f32({count})), {}u)", count - 1)
+            }
+        };
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>, point_index: u32",
+            module,
+            &mut |_m: &mut Module, _ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                Ok(format!(
+                    r##"    let index = {}u + {};
+    (*particle).{} = point_cloud[index].xyz;
+"##,
+                    offset,
+                    index,
+                    Attribute::POSITION.name(),
+                ))
+            },
+        )?;
+
+        // The thread index is the index of the particle among all particles
+        // spawned this frame by the current dispatch. The spawner offsets it by
+        // the number of particles spawned in previous frames.
+        let code = format!("{}(&particle, thread_index);\n", func_name);
+
+        Ok(code)
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Modifier for SetPositionPointCloudModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let code = self.eval(module, context)?;
+        context.main_code += &code;
+        Ok(())
+    }
+}
+
+impl InitModifier for SetPositionPointCloudModifier {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn point_cloud_from_bytes() {
+        let mut bytes = vec![];
+        for f in [1_f32, 2., 3., -4., 5., 0.5] {
+            bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        let m = SetPositionPointCloudModifier::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            m.points,
+            vec![Vec3::new(1., 2., 3.), Vec3::new(-4., 5., 0.5)]
+        );
+        assert_eq!(m.sampling, PointCloudSampling::Sequential);
+
+        assert!(SetPositionPointCloudModifier::from_bytes(&bytes[..10]).is_none());
+    }
+
+    #[test]
+    fn point_cloud_indexed() {
+        let points = [Vec3::X, Vec3::Y, Vec3::Z];
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        for (sampling, index) in [
+            (
+                PointCloudSampling::Sequential,
+                "(spawner.spawn_index + point_index) % 3u",
+            ),
+            (PointCloudSampling::Random, "min(u32(frand() * f32(3)), 2u)"),
+        ] {
+            let mut module = Module::default();
+            let modifier = SetPositionPointCloudModifier::new(points).with_sampling(sampling);
+            let mut context =
+                ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+            modifier.apply(&mut module, &mut context).unwrap();
+
+            let func_id = calc_func_id(&modifier);
+            let func_name = format!("set_position_point_cloud_{0:016X}", func_id);
+            assert_eq!(
+                context.main_code,
+                format!("{func_name}(&particle, thread_index);\n")
+            );
+            assert_eq!(context.point_cloud, points);
+            assert!(!context.extra_code.contains("var<private>"));
+            assert!(context
+                .extra_code
+                .contains(&format!("let index = 0u + {index};")));
+            assert!(context
+                .extra_code
+                .contains("(*particle).position = point_cloud[index].xyz;"));
+        }
+
+        // Points of subsequent modifiers are appended after the base offset
+        let mut module = Module::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout)
+                .with_point_cloud_base(5);
+        SetPositionPointCloudModifier::new(points)
+            .apply(&mut module, &mut context)
+            .unwrap();
+        SetPositionPointCloudModifier::new([Vec3::ONE])
+            .apply(&mut module, &mut context)
+            .unwrap();
+        assert_eq!(context.point_cloud.len(), 4);
+        assert!(context.extra_code.contains("let index = 5u + "));
+        assert!(context.extra_code.contains("let index = 8u + "));
+    }
+
+    #[test]
+    fn point_cloud_empty() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut module = Module::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        let modifier = SetPositionPointCloudModifier::default();
+        assert!(modifier.apply(&mut module, &mut context).is_err());
+    }
+}
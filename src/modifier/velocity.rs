@@ -13,8 +13,8 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    calc_func_id, graph::ExprError, Attribute, BoxedModifier, EvalContext, ExprHandle, Modifier,
-    ModifierContext, Module, ShaderWriter,
+    calc_func_id, graph::ExprError, Attribute, BoxedModifier, EvalContext, ExprHandle,
+    InitModifier, Modifier, ModifierContext, Module, ShaderWriter, UpdateModifier,
 };
 
 /// A modifier to set the velocity of particles radially on a circle.
@@ -101,6 +101,10 @@ impl Modifier for SetVelocityCircleModifier {
     }
 }
 
+impl InitModifier for SetVelocityCircleModifier {}
+
+impl UpdateModifier for SetVelocityCircleModifier {}
+
 /// A modifier to set the velocity of particles to a spherical distribution.
 ///
 /// # Attributes
@@ -161,6 +165,10 @@ impl Modifier for SetVelocitySphereModifier {
     }
 }
 
+impl InitModifier for SetVelocitySphereModifier {}
+
+impl UpdateModifier for SetVelocitySphereModifier {}
+
 /// A modifier to set the velocity of particles along the tangent to an axis.
 ///
 /// # Attributes
@@ -245,3 +253,7 @@ impl Modifier for SetVelocityTangentModifier {
         Ok(())
     }
 }
+
+impl InitModifier for SetVelocityTangentModifier {}
+
+impl UpdateModifier for SetVelocityTangentModifier {}
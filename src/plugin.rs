@@ -1,3 +1,5 @@
+use std::sync::{mpsc::channel, Mutex};
+
 #[cfg(feature = "2d")]
 use bevy::core_pipeline::core_2d::Transparent2d;
 #[cfg(feature = "3d")]
@@ -16,22 +18,26 @@ use bevy::{
 };
 
 use crate::{
+    apply_particle_budget,
     asset::EffectAsset,
     compile_effects, gather_removed_effects,
-    properties::EffectProperties,
+    properties::{run_properties_callbacks, EffectProperties, PropertyStore, SharedProperties},
     render::{
-        extract_effect_events, extract_effects, prepare_bind_groups, prepare_effects,
-        prepare_resources, queue_effects, DispatchIndirectPipeline, DrawEffects, EffectAssetEvents,
-        EffectBindGroups, EffectCache, EffectsMeta, ExtractedEffects, GpuDispatchIndirect,
-        GpuParticleGroup, GpuRenderEffectMetadata, GpuRenderGroupIndirect, GpuSpawnerParams,
-        ParticlesInitPipeline, ParticlesRenderPipeline, ParticlesUpdatePipeline, ShaderCache,
-        SimParams, StorageType as _, VfxSimulateDriverNode, VfxSimulateNode,
+        extract_effect_events, extract_effects, prepare_alive_count_readback, prepare_bind_groups,
+        prepare_effects, prepare_resources, queue_effects, readback_alive_counts,
+        receive_alive_counts, AliveCountReadback, AliveCountReceiver, AliveCountSender,
+        DispatchIndirectPipeline, DrawEffects, EffectAssetEvents, EffectBindGroups, EffectCache,
+        EffectsMeta, ExtractedEffects, GpuDispatchIndirect, GpuParticleGroup,
+        GpuRenderEffectMetadata, GpuRenderGroupIndirect, GpuSpawnerParams, ParticlesInitPipeline,
+        ParticlesRenderPipeline, ParticlesUpdatePipeline, ShaderCache, SimParams,
+        SimulationOnlyBuffers, StorageType as _, VfxSimulateDriverNode, VfxSimulateNode,
     },
     spawn::{self, Random},
     tick_spawners,
     time::effect_simulation_time_system,
-    update_properties_from_asset, CompiledParticleEffect, EffectSimulation, ParticleEffect,
-    RemovedEffectsEvent, Spawner,
+    update_properties_from_asset, AffectedByWind, CompiledParticleEffect, EffectGlobals,
+    EffectGravity, EffectSimulation, GlobalParticleBudget, ParticleEffect, RemovedEffectsEvent,
+    Spawner, WindZone,
 };
 
 #[cfg(feature = "serde")]
@@ -66,6 +72,17 @@ pub enum EffectSystems {
     /// be taken into account in the same frame.
     UpdatePropertiesFromAsset,
 
+    /// Invoke the [`EffectPropertiesCallback`] of all effect instances, which
+    /// write into their [`EffectProperties`].
+    ///
+    /// This system runs during the [`PostUpdate`] schedule, after
+    /// [`EffectSystems::UpdatePropertiesFromAsset`]. The property values
+    /// written are uploaded to the GPU during the same frame, before the
+    /// particles are simulated.
+    ///
+    /// [`EffectPropertiesCallback`]: crate::EffectPropertiesCallback
+    RunPropertiesCallbacks,
+
     /// Gather all removed [`ParticleEffect`] components during the
     /// [`PostUpdate`] set, to clean-up unused GPU resources.
     ///
@@ -90,7 +107,9 @@ pub enum EffectSystems {
     PrepareBindGroups,
 }
 
+/// Labels of the nodes added by Hanabi to the main render graph.
 pub mod main_graph {
+    /// Labels of the nodes of the main render graph.
     pub mod node {
         use bevy::render::render_graph::RenderLabel;
 
@@ -100,6 +119,62 @@ pub mod main_graph {
     }
 }
 
+/// Simulation sub-graph of the render graph.
+///
+/// The [`HanabiPlugin`] adds the [`HanabiSimulateGraph`] sub-graph to the
+/// render graph. That sub-graph runs once per frame, before the cameras render
+/// their views, and contains a single [`VfxSimulateNode`] labelled
+/// [`HanabiSimulateNode`], which simulates all effects.
+///
+/// Custom render graph nodes can be scheduled relative to the simulation by
+/// adding them to the sub-graph, along with a node edge from or to the
+/// [`HanabiSimulateNode`]. For example, a node consuming the particle buffers
+/// of simulation-only effects, exposed by the [`SimulationOnlyBuffers`]
+/// resource, can run right after the simulation, before any view is rendered.
+/// The sub-graph is created in [`HanabiPlugin::finish()`], so any custom node
+/// must be added after that, typically in the [`Plugin::finish()`] of a plugin
+/// added after the [`HanabiPlugin`].
+///
+/// # Example
+///
+/// ```
+/// # use bevy::{prelude::*, render::{render_graph::{self, *}, renderer::RenderContext, RenderApp}};
+/// # use bevy_hanabi::{simulate_graph, SimulationOnlyBuffers};
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash, RenderLabel)]
+/// struct ConsumerLabel;
+///
+/// struct ConsumerNode;
+///
+/// impl render_graph::Node for ConsumerNode {
+///     fn run(
+///         &self,
+///         _graph: &mut RenderGraphContext,
+///         _render_context: &mut RenderContext,
+///         world: &World,
+///     ) -> Result<(), NodeRunError> {
+///         for (_entity, buffer) in world.resource::<SimulationOnlyBuffers>().iter() {
+///             // Bind buffer.buffer, and read the particles in buffer.byte_range(0)
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// fn add_consumer_node(app: &mut App) {
+///     let render_world = app.sub_app_mut(RenderApp).world_mut();
+///     let mut render_graph = render_world.resource_mut::<RenderGraph>();
+///     let sim_graph = render_graph.sub_graph_mut(simulate_graph::HanabiSimulateGraph);
+///     sim_graph.add_node(ConsumerLabel, ConsumerNode);
+///     sim_graph.add_node_edge(simulate_graph::node::HanabiSimulateNode, ConsumerLabel);
+/// }
+/// ```
+///
+/// [`HanabiPlugin`]: crate::HanabiPlugin
+/// [`HanabiPlugin::finish()`]: crate::HanabiPlugin
+/// [`Plugin::finish()`]: bevy::app::Plugin::finish
+/// [`HanabiSimulateGraph`]: simulate_graph::HanabiSimulateGraph
+/// [`HanabiSimulateNode`]: simulate_graph::node::HanabiSimulateNode
+/// [`VfxSimulateNode`]: crate::VfxSimulateNode
+/// [`SimulationOnlyBuffers`]: crate::SimulationOnlyBuffers
 pub mod simulate_graph {
     use bevy::render::render_graph::RenderSubGraph;
 
@@ -107,11 +182,16 @@ pub mod simulate_graph {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, RenderSubGraph)]
     pub struct HanabiSimulateGraph;
 
+    /// Labels of the nodes of the simulation sub-graph.
     pub mod node {
         use bevy::render::render_graph::RenderLabel;
 
         /// Label for the simulation node (init and update compute passes;
         /// view-independent).
+        ///
+        /// See [`VfxSimulateNode`] for details.
+        ///
+        /// [`VfxSimulateNode`]: crate::VfxSimulateNode
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, RenderLabel)]
         pub struct HanabiSimulateNode;
     }
@@ -177,12 +257,24 @@ pub type WithCompiledParticleEffect = With<CompiledParticleEffect>;
 
 impl Plugin for HanabiPlugin {
     fn build(&self, app: &mut App) {
+        // Channel sending the number of particles alive read back from GPU to the
+        // main world
+        let (alive_count_sender, alive_count_receiver) = channel();
+        // Channel sending the brightest particles read back from GPU to the main
+        // world
+        #[cfg(feature = "lights")]
+        let (particle_lights_sender, particle_lights_receiver) = channel();
+
         // Register asset
         app.init_asset::<EffectAsset>()
+            .insert_resource(AliveCountReceiver(Mutex::new(alive_count_receiver)))
             .add_event::<RemovedEffectsEvent>()
             .insert_resource(Random(spawn::new_rng()))
             .init_resource::<ShaderCache>()
             .init_resource::<Time<EffectSimulation>>()
+            .init_resource::<PropertyStore>()
+            .init_resource::<WindZone>()
+            .init_resource::<EffectGlobals>()
             .configure_sets(
                 PostUpdate,
                 (
@@ -207,22 +299,58 @@ impl Plugin for HanabiPlugin {
             .add_systems(
                 PostUpdate,
                 (
+                    apply_particle_budget
+                        .in_set(EffectSystems::TickSpawners)
+                        .before(tick_spawners),
                     tick_spawners.in_set(EffectSystems::TickSpawners),
                     compile_effects.in_set(EffectSystems::CompileEffects),
                     update_properties_from_asset.in_set(EffectSystems::UpdatePropertiesFromAsset),
+                    run_properties_callbacks
+                        .in_set(EffectSystems::RunPropertiesCallbacks)
+                        .after(update_properties_from_asset),
                     gather_removed_effects.in_set(EffectSystems::GatherRemovedEffects),
                     check_visibility::<WithCompiledParticleEffect>
                         .in_set(VisibilitySystems::CheckVisibility),
                 ),
-            );
+            )
+            .add_systems(Last, receive_alive_counts);
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(AliveCountSender(alive_count_sender));
+            #[cfg(feature = "lights")]
+            render_app.insert_resource(crate::render::ParticleLightsSender(particle_lights_sender));
+        }
 
         #[cfg(feature = "serde")]
         app.init_asset_loader::<EffectAssetLoader>();
 
+        #[cfg(feature = "lights")]
+        app.insert_resource(crate::render::ParticleLightsReceiver(Mutex::new(
+            particle_lights_receiver,
+        )))
+        .add_systems(
+            PostUpdate,
+            (
+                crate::render::receive_particle_lights,
+                crate::update_particle_lights,
+            )
+                .chain()
+                .before(bevy::transform::TransformSystem::TransformPropagate),
+        )
+        .register_type::<crate::ParticleLights>()
+        .register_type::<crate::ParticleLightSamples>()
+        .register_type::<crate::ParticleLight>();
+
         // Register types with reflection
         app.register_type::<EffectAsset>()
             .register_type::<ParticleEffect>()
             .register_type::<EffectProperties>()
+            .register_type::<SharedProperties>()
+            .register_type::<WindZone>()
+            .register_type::<EffectGlobals>()
+            .register_type::<AffectedByWind>()
+            .register_type::<EffectGravity>()
+            .register_type::<GlobalParticleBudget>()
             .register_type::<Spawner>()
             .register_type::<Time<EffectSimulation>>();
     }
@@ -269,6 +397,7 @@ impl Plugin for HanabiPlugin {
             .insert_resource(effects_meta)
             .insert_resource(effect_cache)
             .init_resource::<EffectBindGroups>()
+            .init_resource::<SimulationOnlyBuffers>()
             .init_resource::<DispatchIndirectPipeline>()
             .init_resource::<ParticlesInitPipeline>()
             .init_resource::<SpecializedComputePipelines<ParticlesInitPipeline>>()
@@ -281,6 +410,7 @@ impl Plugin for HanabiPlugin {
             .init_resource::<ExtractedEffects>()
             .init_resource::<EffectAssetEvents>()
             .init_resource::<SimParams>()
+            .init_resource::<AliveCountReadback>()
             .configure_sets(
                 Render,
                 (
@@ -296,6 +426,9 @@ impl Plugin for HanabiPlugin {
             .add_systems(
                 Render,
                 (
+                    prepare_alive_count_readback
+                        .in_set(EffectSystems::PrepareEffectAssets)
+                        .before(prepare_effects),
                     prepare_effects.in_set(EffectSystems::PrepareEffectAssets),
                     queue_effects
                         .in_set(EffectSystems::QueueEffects)
@@ -306,9 +439,39 @@ impl Plugin for HanabiPlugin {
                     prepare_bind_groups
                         .in_set(EffectSystems::PrepareBindGroups)
                         .after(queue_effects),
+                    readback_alive_counts.in_set(RenderSet::Cleanup),
                 ),
             );
 
+        #[cfg(feature = "lights")]
+        {
+            use crate::render::{
+                extract_particle_lights, prepare_particle_lights,
+                prepare_particle_lights_bind_groups, readback_particle_lights,
+                ExtractedParticleLights, ParticleLightsMeta, ParticleLightsPipeline,
+            };
+
+            render_app
+                .init_resource::<ExtractedParticleLights>()
+                .init_resource::<ParticleLightsPipeline>()
+                .init_resource::<ParticleLightsMeta>()
+                .edit_schedule(ExtractSchedule, |schedule| {
+                    schedule.add_systems(extract_particle_lights);
+                })
+                .add_systems(
+                    Render,
+                    (
+                        prepare_particle_lights
+                            .in_set(EffectSystems::PrepareEffectAssets)
+                            .before(prepare_effects),
+                        prepare_particle_lights_bind_groups
+                            .in_set(EffectSystems::PrepareBindGroups)
+                            .after(prepare_bind_groups),
+                        readback_particle_lights.in_set(RenderSet::Cleanup),
+                    ),
+                );
+        }
+
         // Register the draw function for drawing the particles. This will be called
         // during the main 2D/3D pass, at the Transparent2d/3d phase, after the
         // opaque objects have been rendered (or, rather, commands for those
@@ -342,24 +505,94 @@ impl Plugin for HanabiPlugin {
                 .add(draw_particles);
         }
 
-        // Add the simulation sub-graph. This render graph runs once per frame no matter
-        // how many cameras/views are active (view-independent).
-        let mut simulate_graph = RenderGraph::default();
-        let simulate_node = VfxSimulateNode::new(render_app.world_mut());
-        simulate_graph.add_node(simulate_graph::node::HanabiSimulateNode, simulate_node);
-        let mut graph = render_app
-            .world_mut()
-            .get_resource_mut::<RenderGraph>()
+        add_simulate_graph(render_app.world_mut());
+    }
+}
+
+/// Add the simulation sub-graph and its driver node to the [`RenderGraph`] of
+/// the render world.
+fn add_simulate_graph(render_world: &mut World) {
+    // Add the simulation sub-graph. This render graph runs once per frame no matter
+    // how many cameras/views are active (view-independent).
+    let mut simulate_graph = RenderGraph::default();
+    let simulate_node = VfxSimulateNode::new(render_world);
+    simulate_graph.add_node(simulate_graph::node::HanabiSimulateNode, simulate_node);
+    let mut graph = render_world.get_resource_mut::<RenderGraph>().unwrap();
+    graph.add_sub_graph(simulate_graph::HanabiSimulateGraph, simulate_graph);
+
+    // Add the simulation driver node which executes the simulation sub-graph. It
+    // runs before the camera driver, since rendering needs to access simulated
+    // particles.
+    graph.add_node(main_graph::node::HanabiDriverNode, VfxSimulateDriverNode {});
+    graph.add_node_edge(
+        main_graph::node::HanabiDriverNode,
+        bevy::render::graph::CameraDriverLabel,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::{
+        render_graph::{self, Edge, EmptyNode, NodeRunError, RenderGraphContext, RenderLabel},
+        renderer::RenderContext,
+    };
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, RenderLabel)]
+    struct ConsumerLabel;
+
+    /// Custom node reading the particle buffers of simulation-only effects.
+    struct ConsumerNode;
+
+    impl render_graph::Node for ConsumerNode {
+        fn run(
+            &self,
+            _graph: &mut RenderGraphContext,
+            _render_context: &mut RenderContext,
+            world: &World,
+        ) -> Result<(), NodeRunError> {
+            for (_entity, buffer) in world.resource::<SimulationOnlyBuffers>().iter() {
+                assert!(buffer.byte_range(0).is_some());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn simulate_graph_custom_node() {
+        let mut world = World::new();
+        world.init_resource::<SimulationOnlyBuffers>();
+        let mut graph = RenderGraph::default();
+        graph.add_node(bevy::render::graph::CameraDriverLabel, EmptyNode);
+        world.insert_resource(graph);
+        add_simulate_graph(&mut world);
+
+        // The simulation runs before the camera driver
+        let mut graph = world.resource_mut::<RenderGraph>();
+        assert!(graph.has_edge(&Edge::NodeEdge {
+            output_node: main_graph::node::HanabiDriverNode.intern(),
+            input_node: bevy::render::graph::CameraDriverLabel.intern(),
+        }));
+
+        // The simulation node can be queried, and doesn't have any slot
+        let sim_graph = graph.sub_graph_mut(simulate_graph::HanabiSimulateGraph);
+        assert!(sim_graph
+            .get_node::<VfxSimulateNode>(simulate_graph::node::HanabiSimulateNode)
+            .is_ok());
+        let sim_node = sim_graph
+            .get_node_state(simulate_graph::node::HanabiSimulateNode)
             .unwrap();
-        graph.add_sub_graph(simulate_graph::HanabiSimulateGraph, simulate_graph);
-
-        // Add the simulation driver node which executes the simulation sub-graph. It
-        // runs before the camera driver, since rendering needs to access simulated
-        // particles.
-        graph.add_node(main_graph::node::HanabiDriverNode, VfxSimulateDriverNode {});
-        graph.add_node_edge(
-            main_graph::node::HanabiDriverNode,
-            bevy::render::graph::CameraDriverLabel,
-        );
+        assert!(sim_node.input_slots.is_empty());
+        assert!(sim_node.output_slots.is_empty());
+
+        // A custom node can be scheduled after it
+        sim_graph.add_node(ConsumerLabel, ConsumerNode);
+        sim_graph.add_node_edge(simulate_graph::node::HanabiSimulateNode, ConsumerLabel);
+        assert!(sim_graph.has_edge(&Edge::NodeEdge {
+            output_node: simulate_graph::node::HanabiSimulateNode.intern(),
+            input_node: ConsumerLabel.intern(),
+        }));
+        assert!(sim_graph.get_node::<ConsumerNode>(ConsumerLabel).is_ok());
     }
 }
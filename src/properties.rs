@@ -22,6 +22,18 @@
 //! value to the property requires assigning a value of the same type as the
 //! default value passed to [`Module::add_property()`].
 //!
+//! # Arrays
+//!
+//! A property can also hold a fixed-size array of values of the same type, for
+//! example the positions of several attractors. Create such a property with
+//! [`Module::add_array_property()`], and read an element in an expression with
+//! [`Module::prop_index()`]. The array is uploaded as a contiguous block, with
+//! the same element stride as WGSL arrays. Its length can't exceed
+//! [`Property::MAX_ARRAY_LEN`].
+//!
+//! Array elements are assigned individually at runtime with
+//! [`EffectProperties::set_element()`].
+//!
 //! # Use case
 //!
 //! Use properties to ensure a value from an [expression](crate::expr) can be
@@ -86,8 +98,77 @@
 //! }
 //! ```
 //!
+//! # CPU callbacks
+//!
+//! Some property values are easier to compute in Rust than in WGSL. Instead of
+//! writing a system querying the [`EffectProperties`] of each effect, you can
+//! attach an [`EffectPropertiesCallback`] component to the effect entity. The
+//! callback is invoked once per frame with the current [`EffectProperties`] of
+//! the effect, and can write any property value.
+//!
+//! The callbacks run during the [`EffectSystems::RunPropertiesCallbacks`] set
+//! of the [`PostUpdate`] schedule, after the properties were synchronized with
+//! the [`EffectAsset`]. The values written are extracted at the end of the same
+//! frame, and uploaded to the GPU before the init and update passes simulate
+//! the particles for that frame.
+//!
+//! ```
+//! # use bevy_hanabi::*;
+//! # use bevy::prelude::*;
+//! fn spawn_effect(mut commands: Commands, effect: Handle<EffectAsset>) {
+//!     commands.spawn((
+//!         ParticleEffectBundle::new(effect),
+//!         EffectPropertiesCallback::new(|_entity, time, properties| {
+//!             let angle = time.elapsed_seconds();
+//!             let dir = Vec3::new(angle.cos(), 0., angle.sin());
+//!             EffectProperties::set_if_changed(properties, "my_dir", dir.into());
+//!         }),
+//!     ));
+//! }
+//! ```
+//!
+//! # Shared properties
+//!
+//! Several effect instances can read their properties from a single shared
+//! source, for example to make multiple fountains pulse together. Instead of
+//! setting the same values on the [`EffectProperties`] of each instance, store
+//! the values in a named block of the [`PropertyStore`] resource, and add a
+//! [`SharedProperties`] component referencing that block to each instance.
+//!
+//! A shared block is serialized once per frame when it changes, whatever the
+//! number of instances referencing it, and uploaded once per GPU property
+//! buffer instead of once per instance. The per-instance [`EffectProperties`]
+//! component, if any, is ignored while the [`SharedProperties`] component is
+//! present.
+//!
+//! All the effect instances referencing a same block must have the same
+//! property layout, which is easiest to guarantee by instantiating the same
+//! [`EffectAsset`].
+//!
+//! ```
+//! # use bevy_hanabi::*;
+//! # use bevy::prelude::*;
+//! fn spawn_fountains(mut commands: Commands, effect: Handle<EffectAsset>) {
+//!     for _ in 0..3 {
+//!         commands.spawn((
+//!             ParticleEffectBundle::new(effect.clone()),
+//!             SharedProperties::new("fountains"),
+//!         ));
+//!     }
+//! }
+//!
+//! fn pulse_fountains(time: Res<Time>, mut store: ResMut<PropertyStore>) {
+//!     let strength = time.elapsed_seconds().sin() * 0.5 + 0.5;
+//!     store.set("fountains", "strength", strength.into());
+//! }
+//! ```
+//!
 //! [`Module`]: crate::Module
 //! [`Module::add_property()`]: crate::Module::add_property
+//! [`Module::add_array_property()`]: crate::Module::add_array_property
+//! [`Module::prop_index()`]: crate::Module::prop_index
+//! [`EffectSystems::RunPropertiesCallbacks`]: crate::EffectSystems::RunPropertiesCallbacks
+//! [`PostUpdate`]: bevy::app::PostUpdate
 //! [`EffectAsset`]: crate::EffectAsset
 //! [`add_property()`]: crate::Module::add_property
 //! [`ParticleEffect`]: crate::ParticleEffect
@@ -95,14 +176,22 @@
 use std::num::NonZeroU64;
 
 use bevy::{
-    ecs::{component::Component, reflect::ReflectComponent, world::Mut},
-    log::trace,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        reflect::ReflectComponent,
+        system::{Query, Res, Resource},
+        world::{Mut, Ref},
+    },
+    log::{trace, warn},
     reflect::Reflect,
-    utils::HashSet,
+    time::Time,
+    utils::{HashMap, HashSet},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{graph::Value, next_multiple_of, ToWgslString, ValueType};
+use crate::{graph::Value, next_multiple_of, EffectSimulation, ToWgslString, ValueType};
 
 /// A single property of an [`EffectAsset`].
 ///
@@ -113,9 +202,15 @@ use crate::{graph::Value, next_multiple_of, ToWgslString, ValueType};
 pub struct Property {
     name: String,
     default_value: Value,
+    /// Number of elements of an array property, or `None` for a single value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    array_len: Option<u32>,
 }
 
 impl Property {
+    /// Maximum number of elements of an array property.
+    pub const MAX_ARRAY_LEN: u32 = 256;
+
     /// Create a new property.
     ///
     /// In general properties are created internally by the [`EffectAsset`]
@@ -128,6 +223,33 @@ impl Property {
         Self {
             name: name.into(),
             default_value: default_value.into(),
+            array_len: None,
+        }
+    }
+
+    /// Create a new array property.
+    ///
+    /// All `len` elements of the array are initialized with `default_value`,
+    /// whose type defines the element type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than [`MAX_ARRAY_LEN`].
+    ///
+    /// [`MAX_ARRAY_LEN`]: Property::MAX_ARRAY_LEN
+    pub fn new_array(name: impl Into<String>, default_value: impl Into<Value>, len: u32) -> Self {
+        let name = name.into();
+        assert!(
+            len > 0 && len <= Self::MAX_ARRAY_LEN,
+            "Invalid length {} for array property '{}', must be in [1:{}].",
+            len,
+            name,
+            Self::MAX_ARRAY_LEN
+        );
+        Self {
+            name,
+            default_value: default_value.into(),
+            array_len: Some(len),
         }
     }
 
@@ -142,7 +264,8 @@ impl Property {
 
     /// The default value of the property.
     ///
-    /// The default value is used to initialize the property.
+    /// The default value is used to initialize the property, or each element of
+    /// an array property.
     #[inline]
     pub fn default_value(&self) -> &Value {
         &self.default_value
@@ -150,18 +273,56 @@ impl Property {
 
     /// The property type.
     ///
-    /// This is the type of the value stored in the property.
+    /// This is the type of the value stored in the property, or of each
+    /// element of an array property.
     #[inline]
     pub fn value_type(&self) -> ValueType {
         self.default_value.value_type()
     }
 
+    /// The number of elements of an array property, or `None` if the property
+    /// holds a single value.
+    #[inline]
+    pub fn array_len(&self) -> Option<u32> {
+        self.array_len
+    }
+
+    /// Is this an array property?
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        self.array_len.is_some()
+    }
+
     /// The property size, in bytes.
     ///
-    /// This is a shortcut for `self.value_type().size()`.
+    /// For a single value, this is a shortcut for `self.value_type().size()`.
+    /// For an array, this is the element stride multiplied by the number of
+    /// elements.
     #[inline]
     pub fn size(&self) -> usize {
-        self.default_value.value_type().size()
+        match self.array_len {
+            Some(len) => self.stride() * len as usize,
+            None => self.value_type().size(),
+        }
+    }
+
+    /// Distance in bytes between two consecutive elements of an array
+    /// property.
+    ///
+    /// Like in WGSL, this is the element size rounded up to its alignment, so
+    /// for example a `vec3<f32>` element is padded to 16 bytes.
+    pub(crate) fn stride(&self) -> usize {
+        let value_type = self.value_type();
+        next_multiple_of(value_type.size(), value_type.align())
+    }
+
+    /// Get the WGSL type of the property in the `Properties` struct.
+    pub(crate) fn wgsl_type(&self) -> String {
+        let value_type = self.value_type().to_wgsl_string();
+        match self.array_len {
+            Some(len) => format!("array<{}, {}>", value_type, len),
+            None => value_type,
+        }
     }
 }
 
@@ -181,8 +342,26 @@ impl ToWgslString for Property {
 pub(crate) struct PropertyInstance {
     /// The property definition, including its default value.
     pub def: Property,
-    /// The current runtime value of the property.
+    /// The current runtime value of the property. For array properties, this is
+    /// unused and stays equal to the default value of the property.
     pub value: Value,
+    /// The current runtime values of the elements of an array property, or an
+    /// empty list if the property is not an array.
+    pub elements: Vec<Value>,
+}
+
+impl PropertyInstance {
+    /// Create a new instance of a property, initialized with its default
+    /// value.
+    fn new(def: Property) -> Self {
+        let value = *def.default_value();
+        let elements = vec![value; def.array_len().unwrap_or(0) as usize];
+        Self {
+            def,
+            value,
+            elements,
+        }
+    }
 }
 
 /// Runtime storage component for the properties of a [`ParticleEffect`].
@@ -239,7 +418,8 @@ impl EffectProperties {
     ///
     /// Panics if a property with the same name already exists in the
     /// `EffectProperties`, and the new property type, as derived from its
-    /// default value, is different from the type of the existing property.
+    /// default value, is different from the type of the existing property, or
+    /// the existing property is an array.
     pub fn with_properties(
         mut self,
         properties: impl IntoIterator<Item = (String, Value)>,
@@ -247,6 +427,11 @@ impl EffectProperties {
         let iter = properties.into_iter();
         for (name, value) in iter {
             if let Some(index) = self.properties.iter().position(|p| p.def.name() == name) {
+                assert!(
+                    !self.properties[index].def.is_array(),
+                    "Cannot assign a single value to array property '{}'.",
+                    name
+                );
                 assert_eq!(
                     self.properties[index].value.value_type(),
                     value.value_type(),
@@ -257,10 +442,8 @@ impl EffectProperties {
                 );
                 self.properties[index].value = value;
             } else {
-                self.properties.push(PropertyInstance {
-                    def: Property::new(name, value),
-                    value,
-                });
+                self.properties
+                    .push(PropertyInstance::new(Property::new(name, value)));
             }
         }
         self
@@ -290,18 +473,86 @@ impl EffectProperties {
     /// Note that this behavior is not symmetric with [`set()`], which allows
     /// setting any property even if not declared.
     ///
+    /// This returns `None` for array properties, whose elements are read with
+    /// [`get_stored_element()`] instead.
+    ///
     /// [`Module`]: crate::Module
     /// [`EffectAsset`]: crate::asset::EffectAsset
     /// [`EffectSystems::UpdatePropertiesFromAsset`]: crate::EffectSystems::UpdatePropertiesFromAsset
     /// [`get_stored()`]: crate::EffectProperties::get_stored
+    /// [`get_stored_element()`]: crate::EffectProperties::get_stored_element
     /// [`set()`]: crate::EffectProperties::set
     pub fn get_stored(&self, name: &str) -> Option<Value> {
         self.properties
             .iter()
-            .find(|prop| prop.def.name() == name)
+            .find(|prop| prop.def.name() == name && !prop.def.is_array())
             .map(|prop| prop.value)
     }
 
+    /// Get the value of an element of a stored array property.
+    ///
+    /// This is the equivalent of [`get_stored()`] for array properties. Returns
+    /// `None` if no array property with that name is stored, or if `index` is
+    /// out of bounds.
+    ///
+    /// [`get_stored()`]: crate::EffectProperties::get_stored
+    pub fn get_stored_element(&self, name: &str, index: u32) -> Option<Value> {
+        self.properties
+            .iter()
+            .find(|prop| prop.def.name() == name)
+            .and_then(|prop| prop.elements.get(index as usize).copied())
+    }
+
+    /// Set the value of an element of an array property.
+    ///
+    /// Unlike [`set()`], the property must already be stored in this
+    /// component, since its length is only known from its declaration in the
+    /// [`Module`] of the effect. This means the property must have been added
+    /// by the [`EffectSystems::UpdatePropertiesFromAsset`] stage. Otherwise the
+    /// value is discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stored property is not an array, if `index` is out of
+    /// bounds, or if the type of `value` is different from the element type of
+    /// the array.
+    ///
+    /// [`set()`]: crate::EffectProperties::set
+    /// [`Module`]: crate::Module
+    /// [`EffectSystems::UpdatePropertiesFromAsset`]: crate::EffectSystems::UpdatePropertiesFromAsset
+    pub fn set_element(&mut self, name: &str, index: u32, value: Value) {
+        let Some(prop) = self
+            .properties
+            .iter_mut()
+            .find(|prop| prop.def.name() == name)
+        else {
+            warn!(
+                "Discarding value for element #{} of unknown array property '{}'.",
+                index, name
+            );
+            return;
+        };
+        let Some(len) = prop.def.array_len() else {
+            panic!("Property '{}' is not an array.", name);
+        };
+        assert!(
+            index < len,
+            "Index {} out of bounds for array property '{}' of length {}.",
+            index,
+            name,
+            len
+        );
+        assert_eq!(
+            prop.def.value_type(),
+            value.value_type(),
+            "Cannot assign value of type {:?} to element of array property '{}' of type {:?}",
+            value.value_type(),
+            prop.def.name(),
+            prop.def.value_type()
+        );
+        prop.elements[index as usize] = value;
+    }
+
     /// Set the value of a property.
     ///
     /// The property will be matched by name against the properties of the
@@ -323,6 +574,11 @@ impl EffectProperties {
             .position(|prop| prop.def.name() == name)
         {
             let prop = &mut self.properties[index];
+            assert!(
+                !prop.def.is_array(),
+                "Cannot assign a single value to array property '{}', use set_element() instead.",
+                name
+            );
             assert_eq!(
                 prop.def.value_type(),
                 value.value_type(),
@@ -333,10 +589,8 @@ impl EffectProperties {
             );
             prop.value = value;
         } else {
-            self.properties.push(PropertyInstance {
-                def: Property::new(name, value),
-                value,
-            });
+            self.properties
+                .push(PropertyInstance::new(Property::new(name, value)));
         }
     }
 
@@ -361,6 +615,11 @@ impl EffectProperties {
             .position(|prop| prop.def.name() == name)
         {
             let prop = &this.properties[index];
+            assert!(
+                !prop.def.is_array(),
+                "Cannot assign a single value to array property '{}', use set_element() instead.",
+                name
+            );
             assert_eq!(
                 prop.def.value_type(),
                 value.value_type(),
@@ -373,10 +632,8 @@ impl EffectProperties {
                 this.properties[index].value = value;
             }
         } else {
-            this.properties.push(PropertyInstance {
-                def: Property::new(name, value),
-                value,
-            });
+            this.properties
+                .push(PropertyInstance::new(Property::new(name, value)));
         }
 
         this
@@ -409,10 +666,7 @@ impl EffectProperties {
                 intersect.insert(prop.name());
                 continue;
             }
-            new_props.push(PropertyInstance {
-                def: prop.clone(),
-                value: *prop.default_value(),
-            });
+            new_props.push(PropertyInstance::new(prop.clone()));
         }
 
         // Only mutate if needed to avoid triggering change detection
@@ -441,15 +695,215 @@ impl EffectProperties {
         for property in &self.properties {
             if let Some(offset) = layout.offset(property.def.name()) {
                 let offset = offset as usize;
-                let size = property.def.size();
-                let src = property.value.as_bytes();
-                debug_assert_eq!(src.len(), size);
-                let dst = &mut data[offset..offset + size];
-                dst.copy_from_slice(src);
+                if property.def.is_array() {
+                    let stride = property.def.stride();
+                    for (index, value) in property.elements.iter().enumerate() {
+                        let src = value.as_bytes();
+                        let offset = offset + index * stride;
+                        let dst = &mut data[offset..offset + src.len()];
+                        dst.copy_from_slice(src);
+                    }
+                } else {
+                    let size = property.def.size();
+                    let src = property.value.as_bytes();
+                    debug_assert_eq!(src.len(), size);
+                    let dst = &mut data[offset..offset + size];
+                    dst.copy_from_slice(src);
+                }
             }
         }
         data
     }
+
+    /// Serialize properties for GPU upload, only if they changed.
+    ///
+    /// Return the binary blob of [`serialize()`] if the component changed
+    /// since the last run of the calling system, or `None` if the properties
+    /// already uploaded to GPU are up to date. This also returns `None` if the
+    /// layout is empty, as there's nothing to upload in that case.
+    ///
+    /// Note that change detection is per component, so changing any single
+    /// property triggers a re-upload of the entire property block of the effect
+    /// instance.
+    ///
+    /// [`serialize()`]: crate::EffectProperties::serialize
+    pub(crate) fn serialize_if_changed(
+        this: &Ref<'_, EffectProperties>,
+        layout: &PropertyLayout,
+    ) -> Option<Vec<u8>> {
+        // Note: must check that property layout is not empty, because the
+        // EffectProperties component is marked as changed when added but contains an
+        // empty Vec if there's no property, which would later raise an error if we
+        // don't return None here.
+        if this.is_changed() && !layout.is_empty() {
+            trace!("Detected property change, re-serializing...");
+            Some(this.serialize(layout))
+        } else {
+            None
+        }
+    }
+}
+
+/// Component binding an effect instance to a shared block of properties of the
+/// [`PropertyStore`].
+///
+/// While this component is present, the properties of the effect instance are
+/// read from the named block of the [`PropertyStore`] resource, and any
+/// [`EffectProperties`] component on the same entity is ignored.
+///
+/// See the [`properties`](crate::properties#shared-properties) module
+/// documentation for details.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub struct SharedProperties {
+    /// Name of the block in the [`PropertyStore`].
+    pub block: String,
+}
+
+impl SharedProperties {
+    /// Create a new component referencing the given block of the
+    /// [`PropertyStore`].
+    pub fn new(block: impl Into<String>) -> Self {
+        Self {
+            block: block.into(),
+        }
+    }
+}
+
+/// A named block of properties stored in the [`PropertyStore`].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PropertyBlock {
+    /// The property values.
+    pub properties: EffectProperties,
+    /// Generation counter, incremented each time a property value changes.
+    pub generation: u64,
+}
+
+/// Resource storing blocks of properties shared by multiple effect instances.
+///
+/// Each block is identified by a name, and referenced by effect instances via
+/// their [`SharedProperties`] component. A block is created the first time a
+/// property is [`set()`] on it.
+///
+/// See the [`properties`](crate::properties#shared-properties) module
+/// documentation for details.
+///
+/// [`set()`]: crate::PropertyStore::set
+#[derive(Debug, Default, Clone, Resource)]
+pub struct PropertyStore {
+    blocks: HashMap<String, PropertyBlock>,
+}
+
+impl PropertyStore {
+    /// Set the value of a property of a shared block.
+    ///
+    /// The block is created if it doesn't exist yet. The instances referencing
+    /// the block are only re-uploaded to GPU if the value is different from the
+    /// current one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the property already exists in the block with a different
+    /// type.
+    pub fn set(&mut self, block: &str, name: &str, value: Value) {
+        let block = self.blocks.entry_ref(block).or_default();
+        if block.properties.get_stored(name) != Some(value) {
+            block.properties.set(name, value);
+            block.generation += 1;
+        }
+    }
+
+    /// Get the value of a property of a shared block.
+    ///
+    /// Returns `None` if the block doesn't exist, or doesn't contain the
+    /// property.
+    pub fn get_stored(&self, block: &str, name: &str) -> Option<Value> {
+        self.blocks
+            .get(block)
+            .and_then(|block| block.properties.get_stored(name))
+    }
+
+    /// Remove a shared block.
+    ///
+    /// Returns `true` if the block existed. Effect instances referencing a
+    /// removed block keep the last property values uploaded.
+    pub fn remove_block(&mut self, block: &str) -> bool {
+        self.blocks.remove(block).is_some()
+    }
+
+    /// Get a shared block by name.
+    pub(crate) fn block(&self, block: &str) -> Option<&PropertyBlock> {
+        self.blocks.get(block)
+    }
+}
+
+/// Signature of the callback of an [`EffectPropertiesCallback`].
+pub type EffectPropertiesCallbackFn =
+    dyn Fn(Entity, &Time<EffectSimulation>, Mut<'_, EffectProperties>) + Send + Sync + 'static;
+
+/// Per-frame CPU callback writing into the [`EffectProperties`] of an effect.
+///
+/// Attach this component to an entity with a [`ParticleEffect`] to invoke a
+/// closure once per frame with the [`EffectProperties`] of that effect. The
+/// closure receives the entity of the effect, the effect simulation time, and a
+/// [`Mut`] reference to the properties. Prefer
+/// [`EffectProperties::set_if_changed()`] to write into the properties, to
+/// avoid a GPU re-upload when a value doesn't change.
+///
+/// The callback runs during the [`EffectSystems::RunPropertiesCallbacks`] set,
+/// after [`EffectSystems::UpdatePropertiesFromAsset`], so any property declared
+/// in the [`EffectAsset`] is already present in the [`EffectProperties`]. The
+/// callback is not invoked while the entity doesn't have an
+/// [`EffectProperties`] component.
+///
+/// See the [`properties`](crate::properties) module documentation for details.
+///
+/// [`ParticleEffect`]: crate::ParticleEffect
+/// [`EffectAsset`]: crate::asset::EffectAsset
+/// [`EffectSystems::RunPropertiesCallbacks`]: crate::EffectSystems::RunPropertiesCallbacks
+/// [`EffectSystems::UpdatePropertiesFromAsset`]: crate::EffectSystems::UpdatePropertiesFromAsset
+#[derive(Component)]
+pub struct EffectPropertiesCallback {
+    callback: Box<EffectPropertiesCallbackFn>,
+}
+
+impl EffectPropertiesCallback {
+    /// Create a new component invoking the given callback each frame.
+    pub fn new(
+        callback: impl Fn(Entity, &Time<EffectSimulation>, Mut<'_, EffectProperties>)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl std::fmt::Debug for EffectPropertiesCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EffectPropertiesCallback")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Invoke the [`EffectPropertiesCallback`] of all effects.
+///
+/// This system executes inside the [`EffectSystems::RunPropertiesCallbacks`]
+/// set of the [`PostUpdate`] schedule.
+///
+/// [`EffectSystems::RunPropertiesCallbacks`]: crate::EffectSystems::RunPropertiesCallbacks
+/// [`PostUpdate`]: bevy::app::PostUpdate
+pub(crate) fn run_properties_callbacks(
+    time: Res<Time<EffectSimulation>>,
+    mut q_effects: Query<(Entity, &EffectPropertiesCallback, &mut EffectProperties)>,
+) {
+    trace!("run_properties_callbacks");
+
+    for (entity, callback, properties) in q_effects.iter_mut() {
+        (callback.callback)(entity, &time, properties);
+    }
 }
 
 #[derive(Clone)]
@@ -465,6 +919,7 @@ impl PartialEq for PropertyLayoutEntry {
         // name is unique).
         self.property.name() == other.property.name()
             && self.property.value_type() == other.property.value_type()
+            && self.property.array_len() == other.property.array_len()
             && self.offset == other.offset
     }
 }
@@ -478,6 +933,7 @@ impl std::hash::Hash for PropertyLayoutEntry {
         // name is unique).
         self.property.name().hash(state);
         self.property.value_type().hash(state);
+        self.property.array_len().hash(state);
         self.offset.hash(state);
     }
 }
@@ -489,7 +945,7 @@ impl std::fmt::Debug for PropertyLayoutEntry {
             "(+{}) {}: {}",
             self.offset,
             self.property.name(),
-            self.property.value_type().to_wgsl_string(),
+            self.property.wgsl_type(),
         ))
     }
 }
@@ -559,15 +1015,28 @@ impl PropertyLayout {
     ///
     /// [`EffectAsset::property_layout()`]: crate::EffectAsset::property_layout
     pub fn new<'a>(iter: impl IntoIterator<Item = &'a Property>) -> Self {
-        let mut properties = iter.into_iter().collect::<Vec<_>>();
+        let (arrays, mut properties): (Vec<_>, Vec<_>) =
+            iter.into_iter().partition(|prop| prop.is_array());
 
-        // Sort by size
-        properties.sort_unstable_by_key(|prop| prop.size());
+        // Sort by size. Use a stable sort to keep the properties of a same size in
+        // declaration order, so the layout is deterministic.
+        properties.sort_by_key(|prop| prop.size());
         let properties = properties; // un-mut
 
         let mut layout = vec![];
         let mut offset = 0;
 
+        // Enqueue all arrays first, in declaration order, each padded to the
+        // maximum alignment so the offset stays aligned for the next ones.
+        for prop in arrays {
+            let entry = PropertyLayoutEntry {
+                property: prop.clone(),
+                offset,
+            };
+            offset += next_multiple_of(prop.size(), 16) as u32;
+            layout.push(entry);
+        }
+
         // Enqueue all Float4, which are already aligned
         let index4 = properties.partition_point(|prop| prop.size() < 16);
         for &prop in properties.iter().skip(index4) {
@@ -835,7 +1304,7 @@ impl PropertyLayout {
                 format!(
                     "    {}: {},",
                     entry.property.name(),
-                    entry.property.value_type().to_wgsl_string()
+                    entry.property.wgsl_type()
                 )
             })
             .fold(String::new(), |mut a, b| {
@@ -879,7 +1348,8 @@ mod tests {
     };
 
     use bevy::{
-        ecs::component::Tick,
+        app::{App, PostUpdate},
+        ecs::{component::Tick, schedule::Schedule, system::ResMut, world::World},
         math::{Vec2, Vec3, Vec4},
     };
 
@@ -896,6 +1366,34 @@ mod tests {
         assert_eq!(p.to_wgsl_string(), format!("properties.{}", p.name()));
     }
 
+    #[test]
+    fn property_array() {
+        let p = Property::new_array("my_array", Vec3::ZERO, 4);
+        assert!(p.is_array());
+        assert_eq!(p.array_len(), Some(4));
+        assert_eq!(p.value_type(), Value::from(Vec3::ZERO).value_type());
+        // vec3<f32> elements are padded to 16 bytes
+        assert_eq!(p.stride(), 16);
+        assert_eq!(p.size(), 64);
+        assert_eq!(p.wgsl_type(), "array<vec3<f32>, 4>");
+
+        let p = Property::new("my_prop", 3_f32);
+        assert!(!p.is_array());
+        assert_eq!(p.array_len(), None);
+        assert_eq!(p.wgsl_type(), "f32");
+
+        let p = Property::new_array("my_array", 3_f32, 3);
+        let s = ron::to_string(&p).unwrap();
+        let p_serde: Property = ron::from_str(&s).unwrap();
+        assert_eq!(p_serde, p);
+    }
+
+    #[test]
+    #[should_panic]
+    fn property_array_too_long() {
+        let _ = Property::new_array("my_array", 3_f32, Property::MAX_ARRAY_LEN + 1);
+    }
+
     #[test]
     fn property_serde() {
         let p = Property::new("my_prop", Value::Scalar(3_f32.into()));
@@ -1027,6 +1525,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn layout_array() {
+        let prop1 = Property::new("f32", 3.4_f32);
+        let prop2 = Property::new_array("floats", 1_f32, 3);
+        let prop3 = Property::new("vec4", Vec4::Y);
+        let prop4 = Property::new_array("points", Vec3::ONE, 2);
+        let layout = PropertyLayout::new([&prop1, &prop2, &prop3, &prop4]);
+        assert_eq!(layout.size(), 68);
+        assert_eq!(layout.align(), 16);
+        let mut it = layout.properties();
+        // arrays go first, padded to 16 bytes
+        assert_eq!(it.next(), Some((0, &prop2)));
+        assert_eq!(it.next(), Some((16, &prop4)));
+        assert_eq!(it.next(), Some((48, &prop3)));
+        assert_eq!(it.next(), Some((64, &prop1)));
+        assert_eq!(it.next(), None);
+        let s = layout.generate_code();
+        assert_eq!(
+            s,
+            r#"struct Properties {
+    floats: array<f32, 3>,
+    points: array<vec3<f32>, 2>,
+    vec4: vec4<f32>,
+    f32: f32,
+}
+"#
+        );
+
+        let mut ep = EffectProperties::default();
+        ep.properties.extend(
+            [&prop1, &prop2, &prop3, &prop4].map(|prop| PropertyInstance::new(prop.clone())),
+        );
+        assert_eq!(ep.get_stored("points"), None);
+        assert_eq!(ep.get_stored_element("points", 1), Some(Vec3::ONE.into()));
+        assert_eq!(ep.get_stored_element("points", 2), None);
+        ep.set_element("points", 1, Vec3::new(2., 3., 4.).into());
+        ep.set_element("floats", 2, 5_f32.into());
+        assert_eq!(
+            ep.get_stored_element("points", 1),
+            Some(Vec3::new(2., 3., 4.).into())
+        );
+
+        let blob = ep.serialize(&layout);
+        let floats: Vec<f32> = blob
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(
+            floats,
+            vec![
+                1., 1., 5., 0., // floats + padding
+                1., 1., 1., 0., // points[0]
+                2., 3., 4., 0., // points[1]
+                0., 1., 0., 0.,  // vec4
+                3.4, // f32
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn effect_properties_set_element_out_of_bounds() {
+        let mut ep = EffectProperties::default();
+        ep.properties
+            .push(PropertyInstance::new(Property::new_array(
+                "my_array", 3_f32, 2,
+            )));
+        ep.set_element("my_array", 2, 1_f32.into());
+    }
+
     #[test]
     fn layout_tail_332() {
         let prop1 = Property::new("vec2", Vec2::NEG_Y);
@@ -1321,4 +1889,94 @@ mod tests {
         let raw_ref: &[u8; 12] = unsafe { std::mem::transmute(&[1_f32, 1_f32, 1_f32]) };
         assert_eq!(raw, raw_ref);
     }
+
+    #[test]
+    fn effect_properties_callback() {
+        let mut app = App::new();
+        app.init_resource::<Time<EffectSimulation>>();
+        app.add_systems(PostUpdate, run_properties_callbacks);
+
+        let entity = app
+            .world_mut()
+            .spawn((
+                EffectProperties::default().with_properties([("a".to_string(), 0_f32.into())]),
+                EffectPropertiesCallback::new(|_entity, _time, properties| {
+                    EffectProperties::set_if_changed(properties, "a", 42_f32.into());
+                }),
+            ))
+            .id();
+
+        app.update();
+
+        let ep = app.world().get::<EffectProperties>(entity).unwrap();
+        assert_eq!(ep.get_stored("a"), Some(42_f32.into()));
+
+        // The value written by the callback is the one uploaded to GPU
+        let layout = PropertyLayout::new(ep.properties().iter().map(|pi| &pi.def));
+        let blob = ep.serialize(&layout);
+        let offset = layout.offset("a").unwrap() as usize;
+        assert_eq!(&blob[offset..offset + 4], &42_f32.to_ne_bytes()[..]);
+    }
+
+    #[derive(Default, Resource)]
+    struct PropertyUploads(Vec<Vec<u8>>);
+
+    #[test]
+    fn effect_properties_upload_on_change() {
+        let mut world = World::new();
+        world.init_resource::<PropertyUploads>();
+
+        let entity = world
+            .spawn(EffectProperties::default().with_properties([
+                ("a".to_string(), 0_f32.into()),
+                ("b".to_string(), 1_f32.into()),
+            ]))
+            .id();
+
+        // Mimic the extraction of the properties for GPU upload
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            |query: Query<Ref<EffectProperties>>, mut uploads: ResMut<PropertyUploads>| {
+                for properties in &query {
+                    let layout =
+                        PropertyLayout::new(properties.properties().iter().map(|pi| &pi.def));
+                    if let Some(data) = EffectProperties::serialize_if_changed(&properties, &layout)
+                    {
+                        uploads.0.push(data);
+                    }
+                }
+            },
+        );
+
+        // Newly added properties are uploaded
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PropertyUploads>().0.len(), 1);
+        world.resource_mut::<PropertyUploads>().0.clear();
+
+        // Unchanged properties are not re-uploaded
+        schedule.run(&mut world);
+        assert!(world.resource::<PropertyUploads>().0.is_empty());
+
+        // Assigning the same value doesn't trigger a re-upload
+        let ep = world.get_mut::<EffectProperties>(entity).unwrap();
+        EffectProperties::set_if_changed(ep, "a", 0_f32.into());
+        schedule.run(&mut world);
+        assert!(world.resource::<PropertyUploads>().0.is_empty());
+
+        // Changing a single property triggers exactly one upload
+        let ep = world.get_mut::<EffectProperties>(entity).unwrap();
+        EffectProperties::set_if_changed(ep, "b", 5_f32.into());
+        schedule.run(&mut world);
+        let uploads = &world.resource::<PropertyUploads>().0;
+        assert_eq!(uploads.len(), 1);
+        let ep = world.get::<EffectProperties>(entity).unwrap();
+        let layout = PropertyLayout::new(ep.properties().iter().map(|pi| &pi.def));
+        let offset = layout.offset("b").unwrap() as usize;
+        assert_eq!(&uploads[0][offset..offset + 4], &5_f32.to_ne_bytes()[..]);
+        world.resource_mut::<PropertyUploads>().0.clear();
+
+        // And nothing more on the next frame
+        schedule.run(&mut world);
+        assert!(world.resource::<PropertyUploads>().0.is_empty());
+    }
 }
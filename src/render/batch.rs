@@ -12,8 +12,8 @@ use super::{
     EffectCacheId, GpuCompressedTransform, LayoutFlags,
 };
 use crate::{
-    spawn::EffectInitializer, AlphaMode, EffectAsset, EffectShader, ParticleLayout, PropertyLayout,
-    TextureLayout,
+    spawn::EffectInitializer, AlphaMode, EffectAsset, EffectRenderLayer, EffectShader,
+    ParticleLayout, PropertyLayout, TextureLayout,
 };
 
 /// Data needed to render all batches pertaining to a specific effect.
@@ -42,6 +42,9 @@ pub(crate) struct EffectBatches {
     pub particle_layout: ParticleLayout,
     /// Flags describing the render layout.
     pub layout_flags: LayoutFlags,
+    /// Flags describing the render layout of the secondary render layer, if
+    /// the effect has one.
+    pub secondary_layout_flags: Option<LayoutFlags>,
     /// Texture layout.
     pub texture_layout: TextureLayout,
     /// Textures.
@@ -57,6 +60,10 @@ pub(crate) struct EffectBatches {
     /// Note that we don't need to keep the init/update shaders alive because
     /// their pipeline specialization is doing it via the specialization key.
     pub render_shaders: Vec<Handle<Shader>>,
+    /// Configured shaders used for the particle rendering of the secondary
+    /// render layer of this batch, or empty if the effect doesn't have a
+    /// secondary render layer.
+    pub secondary_render_shaders: Vec<Handle<Shader>>,
     /// Init and update compute pipelines specialized for this batch.
     pub init_and_update_pipeline_ids: Vec<InitAndUpdatePipelineIds>,
     /// The order in which we evaluate groups.
@@ -81,6 +88,9 @@ impl Index<u32> for EffectBatches {
 pub(crate) struct EffectDrawBatch {
     /// Group index of the batch.
     pub group_index: u32,
+    /// Render layer drawn by the batch. All the layers of a group draw the
+    /// same slice of particles.
+    pub layer: EffectRenderLayer,
     /// Entity holding the [`EffectBatches`] this batch is part of.
     pub batches_entity: Entity,
     /// For 2D rendering, the Z coordinate used as the sort key. Ignored for 3D
@@ -128,6 +138,7 @@ impl EffectBatches {
                 .collect(),
             handle: input.handle,
             layout_flags: input.layout_flags,
+            secondary_layout_flags: input.secondary_layout_flags,
             texture_layout: input.texture_layout,
             textures: input.textures,
             alpha_mode: input.alpha_mode,
@@ -136,11 +147,41 @@ impl EffectBatches {
                 .iter()
                 .map(|shaders| shaders.render.clone())
                 .collect(),
+            secondary_render_shaders: input
+                .effect_shaders
+                .iter()
+                .filter_map(|shaders| shaders.secondary_render.clone())
+                .collect(),
             init_and_update_pipeline_ids,
             entities: vec![input.entity.index()],
             group_order: input.group_order,
         }
     }
+
+    /// Get the layout flags of the given render layer.
+    ///
+    /// If the effect has no secondary render layer, this returns the layout
+    /// flags of the primary layer.
+    pub fn layer_layout_flags(&self, layer: EffectRenderLayer) -> LayoutFlags {
+        match layer {
+            EffectRenderLayer::Primary => self.layout_flags,
+            EffectRenderLayer::Secondary => {
+                self.secondary_layout_flags.unwrap_or(self.layout_flags)
+            }
+        }
+    }
+
+    /// Get the render shader of the given group and render layer.
+    pub fn layer_render_shader(
+        &self,
+        group_index: u32,
+        layer: EffectRenderLayer,
+    ) -> &Handle<Shader> {
+        match layer {
+            EffectRenderLayer::Primary => &self.render_shaders[group_index as usize],
+            EffectRenderLayer::Secondary => &self.secondary_render_shaders[group_index as usize],
+        }
+    }
 }
 
 /// Effect batching input, obtained from extracted effects.
@@ -159,6 +200,8 @@ pub(crate) struct BatchesInput {
     pub effect_shaders: Vec<EffectShader>,
     /// Various flags related to the effect.
     pub layout_flags: LayoutFlags,
+    /// Render flags of the secondary render layer, if any.
+    pub secondary_layout_flags: Option<LayoutFlags>,
     /// Texture layout.
     pub texture_layout: TextureLayout,
     /// Textures.
@@ -173,6 +216,10 @@ pub(crate) struct BatchesInput {
     pub transform: GpuCompressedTransform,
     /// Emitter inverse transform.
     pub inverse_transform: GpuCompressedTransform,
+    /// Gravity applied to the particles of the effect.
+    pub gravity: Vec3,
+    /// Emitter motion since the previous frame.
+    pub emitter_motion: GpuCompressedTransform,
     /// GPU buffer where properties for this batch need to be written.
     pub property_buffer: Option<Buffer>,
     /// Serialized property data.
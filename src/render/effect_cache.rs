@@ -9,10 +9,11 @@ use bevy::{
     asset::Handle,
     ecs::system::Resource,
     log::{trace, warn},
+    math::{Vec3, Vec4},
     render::{render_resource::*, renderer::RenderDevice},
     utils::HashMap,
 };
-use bytemuck::cast_slice_mut;
+use bytemuck::{cast_slice, cast_slice_mut};
 
 use super::buffer_table::BufferTableId;
 use crate::{
@@ -100,8 +101,15 @@ impl SliceRef {
 /// the future to improve batching.
 #[derive(Debug)]
 pub struct EffectBuffer {
-    /// GPU buffer holding all particles for the entire group of effects.
-    particle_buffer: Buffer,
+    /// GPU buffers holding all particles for the entire group of effects.
+    ///
+    /// This contains a single buffer, or two buffers alternatively read and
+    /// written by the update pass if the effects are double-buffered (see
+    /// [`LayoutFlags::DOUBLE_BUFFERED`]).
+    particle_buffers: Vec<Buffer>,
+    /// Index into `particle_buffers` of the buffer written by the update pass
+    /// this frame. The other buffer, if any, is read by the update pass.
+    particle_buffer_index: usize,
     /// GPU buffer holding the indirection indices for the entire group of
     /// effects. This is a triple buffer containing:
     /// - the ping-pong alive particles and render indirect indices at offsets 0
@@ -111,6 +119,9 @@ pub struct EffectBuffer {
     /// GPU buffer holding the properties of the effect(s), if any. This is
     /// always `None` if the property layout is empty.
     properties_buffer: Option<Buffer>,
+    /// GPU buffer holding the points of the point clouds of the effect(s), if
+    /// any.
+    point_cloud_buffer: Option<Buffer>,
     /// Layout of particles.
     particle_layout: ParticleLayout,
     /// Layout of properties of the effect(s), if using properties.
@@ -135,9 +146,9 @@ pub struct EffectBuffer {
     /// Handle of all effects common in this buffer. TODO - replace with
     /// compatible layout.
     asset: Handle<EffectAsset>,
-    /// Bind group for the per-buffer data (group @1) of the init and update
-    /// passes.
-    simulate_bind_group: Option<BindGroup>,
+    /// Bind groups for the per-buffer data (group @1) of the init and update
+    /// passes, one per particle buffer written by the update pass.
+    simulate_bind_groups: Vec<BindGroup>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -166,6 +177,7 @@ impl EffectBuffer {
         particle_layout: ParticleLayout,
         property_layout: PropertyLayout,
         layout_flags: LayoutFlags,
+        point_cloud: &[Vec3],
         render_device: &RenderDevice,
         label: Option<&str>,
     ) -> Self {
@@ -185,14 +197,26 @@ impl EffectBuffer {
             "Attempted to create a zero-sized effect buffer."
         );
 
+        let double_buffered = layout_flags.contains(LayoutFlags::DOUBLE_BUFFERED);
         let particle_capacity_bytes: BufferAddress =
             capacity as u64 * particle_layout.min_binding_size().get();
-        let particle_buffer = render_device.create_buffer(&BufferDescriptor {
-            label,
-            size: particle_capacity_bytes,
-            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
+        let particle_buffers = (0..if double_buffered { 2 } else { 1 })
+            .map(|index| {
+                let label = label.map(|label| {
+                    if index == 0 {
+                        label.to_owned()
+                    } else {
+                        format!("{label}_back")
+                    }
+                });
+                render_device.create_buffer(&BufferDescriptor {
+                    label: label.as_deref(),
+                    size: particle_capacity_bytes,
+                    usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
 
         let capacity_bytes: BufferAddress = capacity as u64 * 4;
 
@@ -239,6 +263,26 @@ impl EffectBuffer {
             Some(properties_buffer)
         };
 
+        // Upload the point cloud once, it never changes for a given effect. Points are
+        // padded to vec4 to match the array stride of the storage buffer.
+        let point_cloud_buffer = if point_cloud.is_empty() {
+            None
+        } else {
+            let point_cloud_label = if let Some(label) = label {
+                format!("{}_point_cloud", label)
+            } else {
+                "hanabi:buffer:effect_point_cloud".to_owned()
+            };
+            let points: Vec<Vec4> = point_cloud.iter().map(|p| p.extend(0.)).collect();
+            Some(
+                render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some(&point_cloud_label),
+                    contents: cast_slice(&points),
+                    usage: BufferUsages::STORAGE,
+                }),
+            )
+        };
+
         // TODO - Cache particle_layout and associated bind group layout, instead of
         // creating one bind group layout per buffer using that layout...
         let particle_group_size = GpuParticleGroup::aligned_size(
@@ -293,6 +337,32 @@ impl EffectBuffer {
                 count: None,
             });
         }
+        if double_buffered {
+            // @binding(4) var<storage, read_write> prev_particle_buffer : ParticleBuffer
+            entries.push(BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(particle_layout.min_binding_size()),
+                },
+                count: None,
+            });
+        }
+        if point_cloud_buffer.is_some() {
+            // @binding(5) var<storage, read> point_cloud : array<vec4<f32>>
+            entries.push(BindGroupLayoutEntry {
+                binding: 5,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(16),
+                },
+                count: None,
+            });
+        }
         let label = "hanabi:sim_particles_buffer_layout";
         trace!(
             "Creating particle bind group layout '{}' for simulation passes with {} entries.",
@@ -358,9 +428,11 @@ impl EffectBuffer {
             render_device.create_bind_group_layout("hanabi:buffer_layout_render", &entries);
 
         Self {
-            particle_buffer,
+            particle_buffers,
+            particle_buffer_index: 0,
             indirect_buffer,
             properties_buffer,
+            point_cloud_buffer,
             particle_layout,
             property_layout,
             layout_flags,
@@ -370,7 +442,7 @@ impl EffectBuffer {
             used_size: 0,
             free_slices: vec![],
             asset,
-            simulate_bind_group: None,
+            simulate_bind_groups: vec![],
         }
     }
 
@@ -382,6 +454,54 @@ impl EffectBuffer {
         &self.particle_layout
     }
 
+    /// GPU buffer holding all particles for the entire group of effects.
+    ///
+    /// For double-buffered effects, this is the buffer written by the update
+    /// pass this frame, which holds the latest particle state after the update
+    /// and is read by the render pass.
+    pub fn particle_buffer(&self) -> &Buffer {
+        &self.particle_buffers[self.particle_buffer_index]
+    }
+
+    /// GPU buffer holding the particles as they were at the end of the previous
+    /// frame, read by the update pass this frame.
+    ///
+    /// This is `None` unless the effects are double-buffered.
+    #[allow(dead_code)]
+    pub fn prev_particle_buffer(&self) -> Option<&Buffer> {
+        if self.particle_buffers.len() < 2 {
+            return None;
+        }
+        Some(&self.particle_buffers[1 - self.particle_buffer_index])
+    }
+
+    /// Number of particle buffers allocated, either 1, or 2 for double-buffered
+    /// effects.
+    pub fn particle_buffer_count(&self) -> usize {
+        self.particle_buffers.len()
+    }
+
+    /// Index of the particle buffer returned by [`particle_buffer()`].
+    ///
+    /// This is always zero unless the effects are double-buffered.
+    ///
+    /// [`particle_buffer()`]: EffectBuffer::particle_buffer
+    pub fn particle_buffer_index(&self) -> usize {
+        self.particle_buffer_index
+    }
+
+    /// Swap the particle buffers read and written by the update pass.
+    ///
+    /// This must be called once per frame for double-buffered effects, before
+    /// the simulation, so that the update pass reads the buffer it wrote during
+    /// the previous frame. This is a no-op if the effects are not
+    /// double-buffered.
+    pub fn swap_particle_buffers(&mut self) {
+        if self.particle_buffers.len() == 2 {
+            self.particle_buffer_index = 1 - self.particle_buffer_index;
+        }
+    }
+
     pub fn property_layout(&self) -> &PropertyLayout {
         &self.property_layout
     }
@@ -398,22 +518,33 @@ impl EffectBuffer {
         &self.particles_buffer_layout_with_dispatch
     }
 
-    /// Return a binding for the entire particle buffer.
-    pub fn max_binding(&self) -> BindingResource {
+    /// Return a binding for the entire particle buffer at the given index in
+    /// the particle buffers.
+    fn particle_max_binding(&self, index: usize) -> BindingResource<'_> {
         let capacity_bytes = self.capacity as u64 * self.particle_layout.min_binding_size().get();
         BindingResource::Buffer(BufferBinding {
-            buffer: &self.particle_buffer,
+            buffer: &self.particle_buffers[index],
             offset: 0,
             size: Some(NonZeroU64::new(capacity_bytes).unwrap()),
         })
     }
 
+    /// Return a binding for the entire particle buffer, for each particle
+    /// buffer in turn being the one written by the update pass.
+    ///
+    /// This returns a single binding unless the effects are double-buffered.
+    pub fn max_bindings(&self) -> Vec<BindingResource<'_>> {
+        (0..self.particle_buffers.len())
+            .map(|index| self.particle_max_binding(index))
+            .collect()
+    }
+
     /// Return a binding of the buffer for a starting range of a given size (in
     /// bytes).
     #[allow(dead_code)]
     pub fn binding(&self, size: u32) -> BindingResource {
         BindingResource::Buffer(BufferBinding {
-            buffer: &self.particle_buffer,
+            buffer: self.particle_buffer(),
             offset: 0,
             size: Some(NonZeroU64::new(size as u64).unwrap()),
         })
@@ -443,59 +574,86 @@ impl EffectBuffer {
         })
     }
 
-    /// Create the bind group for the init and update passes if needed.
+    /// Create the bind groups for the init and update passes if needed.
     ///
     /// The `buffer_index` must be the index of the current [`EffectBuffer`]
     /// inside the [`EffectCache`]. The `group_binding` is the binding resource
     /// for the particle groups of this buffer.
+    ///
+    /// For double-buffered effects, two bind groups are created, one for each
+    /// particle buffer written by the update pass, which also binds the other
+    /// particle buffer as the one read by the update pass.
     pub fn create_sim_bind_group(
         &mut self,
         buffer_index: u32,
         render_device: &RenderDevice,
         group_binding: BufferBinding,
     ) {
-        if self.simulate_bind_group.is_some() {
+        if !self.simulate_bind_groups.is_empty() {
             return;
         }
 
         let layout = self.particle_layout_bind_group_sim();
-        let label = format!("hanabi:bind_group_sim_batch{}", buffer_index);
-        let mut bindings = vec![
-            BindGroupEntry {
-                binding: 0,
-                resource: self.max_binding(),
-            },
-            BindGroupEntry {
-                binding: 1,
-                resource: self.indirect_max_binding(),
-            },
-            BindGroupEntry {
-                binding: 2,
-                resource: BindingResource::Buffer(group_binding),
-            },
-        ];
-        if let Some(property_binding) = self.properties_max_binding() {
-            bindings.push(BindGroupEntry {
-                binding: 3,
-                resource: property_binding,
-            });
+        let count = self.particle_buffer_count();
+        let mut bind_groups = Vec::with_capacity(count);
+        for index in 0..count {
+            let label = if count > 1 {
+                format!("hanabi:bind_group_sim_batch{}_{}", buffer_index, index)
+            } else {
+                format!("hanabi:bind_group_sim_batch{}", buffer_index)
+            };
+            let mut bindings = vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.particle_max_binding(index),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: self.indirect_max_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Buffer(group_binding.clone()),
+                },
+            ];
+            if let Some(property_binding) = self.properties_max_binding() {
+                bindings.push(BindGroupEntry {
+                    binding: 3,
+                    resource: property_binding,
+                });
+            }
+            if count > 1 {
+                bindings.push(BindGroupEntry {
+                    binding: 4,
+                    resource: self.particle_max_binding(1 - index),
+                });
+            }
+            if let Some(point_cloud_buffer) = &self.point_cloud_buffer {
+                bindings.push(BindGroupEntry {
+                    binding: 5,
+                    resource: point_cloud_buffer.as_entire_binding(),
+                });
+            }
+            trace!(
+                "Create simulate bind group '{}' with {} entries",
+                label,
+                bindings.len()
+            );
+            bind_groups.push(render_device.create_bind_group(Some(&label[..]), layout, &bindings));
         }
-        trace!(
-            "Create simulate bind group '{}' with {} entries",
-            label,
-            bindings.len()
-        );
-        let bind_group = render_device.create_bind_group(Some(&label[..]), layout, &bindings);
-        self.simulate_bind_group = Some(bind_group);
+        self.simulate_bind_groups = bind_groups;
     }
 
     /// Return the cached bind group for the init and update passes.
     ///
     /// This is the per-buffer bind group at binding @1 which binds all
     /// per-buffer resources shared by all effect instances batched in a single
-    /// buffer.
+    /// buffer. For double-buffered effects, this is the bind group writing into
+    /// the current [`particle_buffer()`].
+    ///
+    /// [`particle_buffer()`]: EffectBuffer::particle_buffer
     pub fn sim_bind_group(&self) -> Option<&BindGroup> {
-        self.simulate_bind_group.as_ref()
+        self.simulate_bind_groups.get(self.particle_buffer_index)
     }
 
     /// Try to recycle a free slice to store `size` items.
@@ -747,6 +905,7 @@ impl EffectCache {
         particle_layout: &ParticleLayout,
         property_layout: &PropertyLayout,
         layout_flags: LayoutFlags,
+        point_cloud: &[Vec3],
         dispatch_buffer_indices: DispatchBufferIndices,
         group_order: Vec<u32>,
     ) -> EffectCacheId {
@@ -793,6 +952,7 @@ impl EffectCache {
                     particle_layout.clone(),
                     property_layout.clone(),
                     layout_flags,
+                    point_cloud,
                     &self.device,
                     Some(&format!("hanabi:buffer:effect{buffer_index}_particles")),
                 );
@@ -915,8 +1075,6 @@ impl EffectCache {
 mod gpu_tests {
     use std::borrow::Cow;
 
-    use bevy::math::Vec4;
-
     use super::*;
     use crate::{
         graph::{Value, VectorValue},
@@ -1023,11 +1181,13 @@ mod gpu_tests {
             l64.clone(),
             PropertyLayout::empty(), // not using properties
             LayoutFlags::NONE,
+            &[],
             &render_device,
             Some("my_buffer"),
         );
 
         assert_eq!(buffer.capacity, capacity.max(EffectBuffer::MIN_CAPACITY));
+        assert_eq!(buffer.particle_buffer().size(), buffer.capacity as u64 * 64);
         assert_eq!(64, buffer.particle_layout.size());
         assert_eq!(64, buffer.particle_layout.min_binding_size().get());
         assert_eq!(0, buffer.used_size);
@@ -1076,6 +1236,107 @@ mod gpu_tests {
         assert_eq!(0, buffer.used_size); // collapsed and empty
     }
 
+    #[test]
+    fn effect_buffer_double_buffered() {
+        let renderer = MockRenderer::new();
+        let render_device = renderer.device();
+
+        let l64 = ParticleLayout::new()
+            .append(F4A)
+            .append(F4B)
+            .append(F4C)
+            .append(F4D)
+            .build();
+        let asset = Handle::<EffectAsset>::default();
+
+        // Single buffer by default; swapping is a no-op
+        let mut buffer = EffectBuffer::new(
+            asset.clone(),
+            256,
+            l64.clone(),
+            PropertyLayout::empty(),
+            LayoutFlags::NONE,
+            &[],
+            &render_device,
+            None,
+        );
+        assert_eq!(buffer.particle_buffer_count(), 1);
+        assert!(buffer.prev_particle_buffer().is_none());
+        let id = buffer.particle_buffer().id();
+        buffer.swap_particle_buffers();
+        assert_eq!(buffer.particle_buffer_index(), 0);
+        assert_eq!(buffer.particle_buffer().id(), id);
+
+        // Two buffers of the same size, swapped each frame
+        let mut buffer = EffectBuffer::new(
+            asset,
+            256,
+            l64,
+            PropertyLayout::empty(),
+            LayoutFlags::DOUBLE_BUFFERED,
+            &[],
+            &render_device,
+            None,
+        );
+        assert_eq!(buffer.particle_buffer_count(), 2);
+        assert_eq!(buffer.max_bindings().len(), 2);
+        let front = buffer.particle_buffer().id();
+        let back = buffer.prev_particle_buffer().unwrap().id();
+        assert_ne!(front, back);
+        assert_eq!(buffer.particle_buffer().size(), 256 * 64);
+        assert_eq!(buffer.prev_particle_buffer().unwrap().size(), 256 * 64);
+        for frame in 1..=4 {
+            buffer.swap_particle_buffers();
+            assert_eq!(buffer.particle_buffer_index(), frame % 2);
+            let (written, read) = if frame % 2 == 0 {
+                (front, back)
+            } else {
+                (back, front)
+            };
+            assert_eq!(buffer.particle_buffer().id(), written);
+            assert_eq!(buffer.prev_particle_buffer().unwrap().id(), read);
+        }
+    }
+
+    #[test]
+    fn effect_buffer_point_cloud() {
+        let renderer = MockRenderer::new();
+        let render_device = renderer.device();
+
+        let l64 = ParticleLayout::new()
+            .append(F4A)
+            .append(F4B)
+            .append(F4C)
+            .append(F4D)
+            .build();
+        let asset = Handle::<EffectAsset>::default();
+
+        let buffer = EffectBuffer::new(
+            asset.clone(),
+            256,
+            l64.clone(),
+            PropertyLayout::empty(),
+            LayoutFlags::NONE,
+            &[],
+            &render_device,
+            None,
+        );
+        assert!(buffer.point_cloud_buffer.is_none());
+
+        // Points are padded to vec4
+        let buffer = EffectBuffer::new(
+            asset,
+            256,
+            l64,
+            PropertyLayout::empty(),
+            LayoutFlags::POINT_CLOUD,
+            &[Vec3::X, Vec3::Y, Vec3::Z],
+            &render_device,
+            None,
+        );
+        assert_eq!(buffer.point_cloud_buffer.as_ref().unwrap().size(), 3 * 16);
+    }
+
     #[test]
     fn pop_free_slice() {
         let renderer = MockRenderer::new();
@@ -1098,6 +1359,7 @@ mod gpu_tests {
             l64.clone(),
             PropertyLayout::empty(), // not using properties
             LayoutFlags::NONE,
+            &[],
             &render_device,
             Some("my_buffer"),
         );
@@ -1171,6 +1433,7 @@ mod gpu_tests {
             &l32,
             &empty_property_layout,
             LayoutFlags::NONE,
+            &[],
             DispatchBufferIndices::default(),
             group_order.clone(),
         );
@@ -1189,6 +1452,7 @@ mod gpu_tests {
             &l32,
             &empty_property_layout,
             LayoutFlags::NONE,
+            &[],
             DispatchBufferIndices::default(),
             group_order.clone(),
         );
@@ -1217,6 +1481,7 @@ mod gpu_tests {
             &l32,
             &empty_property_layout,
             LayoutFlags::NONE,
+            &[],
             DispatchBufferIndices::default(),
             group_order,
         );
@@ -0,0 +1,731 @@
+use std::{
+    num::NonZeroU64,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+};
+
+use bevy::{
+    log::trace,
+    prelude::*,
+    render::{
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+    utils::HashMap,
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{
+    aligned_buffer_vec::AlignedBufferVec, readback::StagingBuffer, EffectCache, EffectCacheId,
+    EffectsMeta, ExtractedEffects, GpuCompressedTransform, LayoutFlags,
+};
+use crate::{Attribute, ParticleEffect, ParticleLightSample, ParticleLightSamples, ParticleLights};
+
+/// Brightest particles of an effect instance, read back from GPU.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParticleLightsData {
+    /// Entity of the effect instance in the main world.
+    pub entity: Entity,
+    /// Brightest particles of all groups of the effect, in the local space of
+    /// the effect.
+    pub samples: Vec<ParticleLightSample>,
+}
+
+/// Main world end of the particle lights readback channel.
+#[derive(Resource)]
+pub(crate) struct ParticleLightsReceiver(pub Mutex<Receiver<ParticleLightsData>>);
+
+/// Render world end of the particle lights readback channel.
+#[derive(Resource)]
+pub(crate) struct ParticleLightsSender(pub Sender<ParticleLightsData>);
+
+/// Number of lights emitted by each effect instance with a [`ParticleLights`]
+/// component, extracted from the main world.
+#[derive(Resource, Default)]
+pub(crate) struct ExtractedParticleLights {
+    pub light_counts: HashMap<Entity, u32>,
+}
+
+/// GPU representation of the parameters of the selection of the brightest
+/// particles of a single group.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable, ShaderType)]
+struct GpuParticleLightParams {
+    /// Transform from the simulation space of the particles to the local space
+    /// of the effect.
+    transform: GpuCompressedTransform,
+    /// Size of a particle, in number of `u32`.
+    particle_stride: u32,
+    /// Offset of [`Attribute::POSITION`], in number of `u32`.
+    position_offset: u32,
+    /// Offset of [`Attribute::COLOR`], in number of `u32`, or [`u32::MAX`] if
+    /// the particles don't have that attribute.
+    color_offset: u32,
+    /// Offset of [`Attribute::HDR_COLOR`], in number of `u32`, or
+    /// [`u32::MAX`] if the particles don't have that attribute.
+    hdr_color_offset: u32,
+    /// Index of the first particle of the group in the indirect buffer.
+    base_index: u32,
+    /// Index of the `u32` holding the ping index of the effect in the render
+    /// effect dispatch buffer.
+    render_effect_indirect: u32,
+    /// Index of the first `u32` of the group in the render group dispatch
+    /// buffer.
+    render_group_indirect: u32,
+    /// Number of lights to select.
+    light_count: u32,
+    /// Index of the first sample of the group in the sample buffer.
+    output_index: u32,
+    /// Padding to keep the struct size a multiple of its alignment.
+    padding: [u32; 3],
+}
+
+/// GPU representation of a [`ParticleLightSample`].
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable, ShaderType)]
+struct GpuParticleLightSample {
+    /// Position in the local space of the effect. The `w` component is unused.
+    position: Vec4,
+    /// HDR color, or zero if the sample is unused.
+    color: Vec4,
+}
+
+/// Effect instance whose brightest particles are selected this frame.
+#[derive(Debug, Clone, PartialEq)]
+struct LightEffect {
+    /// Entity of the effect instance in the main world.
+    entity: Entity,
+    /// ID of the effect in the [`EffectCache`].
+    cache_id: EffectCacheId,
+    /// Number of lights to select per group.
+    light_count: u32,
+    /// Transform from the simulation space to the local space of the effect.
+    transform: Mat4,
+}
+
+/// Effect instance whose samples are part of a readback.
+#[derive(Debug, Clone, PartialEq)]
+struct ReadbackLightEffect {
+    /// Entity of the effect instance in the main world.
+    entity: Entity,
+    /// Index of the first sample of the effect in the sample buffer.
+    first_sample: u32,
+    /// Number of consecutive samples of the effect in the sample buffer.
+    sample_count: u32,
+}
+
+/// Selection of the brightest particles of all groups of an effect instance.
+struct LightDispatch {
+    /// Bind group of the particle buffers of the effect.
+    bind_group: BindGroup,
+    /// Offset of the parameters of each group in the parameter buffer.
+    params_offsets: Vec<u32>,
+}
+
+/// Compute pipeline running the `vfx_lights` shader, which selects the
+/// brightest particles of a group.
+#[derive(Resource)]
+pub(crate) struct ParticleLightsPipeline {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl FromWorld for ParticleLightsPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let storage_entry = |binding, read_only, min_size| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: Some(min_size),
+            },
+            count: None,
+        };
+        let u32_size = NonZeroU64::new(4).unwrap();
+        let layout = render_device.create_bind_group_layout(
+            "hanabi:bind_group_layout:particle_lights",
+            &[
+                storage_entry(0, true, u32_size),
+                storage_entry(1, true, u32_size),
+                storage_entry(2, true, u32_size),
+                storage_entry(3, true, u32_size),
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(GpuParticleLightParams::min_size()),
+                    },
+                    count: None,
+                },
+                storage_entry(5, false, GpuParticleLightSample::min_size()),
+            ],
+        );
+
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("hanabi:pipeline_layout:particle_lights"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader_module = render_device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("hanabi:vfx_lights_shader"),
+            source: ShaderSource::Wgsl(include_str!("vfx_lights.wgsl").into()),
+        });
+
+        let pipeline = render_device.create_compute_pipeline(&RawComputePipelineDescriptor {
+            label: Some("hanabi:compute_pipeline:particle_lights"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: "main",
+            compilation_options: default(),
+        });
+
+        Self { layout, pipeline }
+    }
+}
+
+/// State of the selection and readback of the brightest particles of the
+/// effects with a [`ParticleLights`] component.
+///
+/// At the end of the simulation, a compute pass selects the brightest particles
+/// of each group into a small sample buffer, which is then copied into a
+/// staging buffer and mapped without blocking, like the alive counts. The
+/// selection only runs when no readback is in flight, so the lights lag the
+/// particles by a few frames.
+#[derive(Resource)]
+pub(crate) struct ParticleLightsMeta {
+    /// Effects extracted this frame which emit lights.
+    effects: Vec<LightEffect>,
+    /// Parameters of the selection of each group.
+    params_buffer: AlignedBufferVec<GpuParticleLightParams>,
+    /// Samples selected on GPU, [`ParticleLights::MAX_LIGHTS`] per group.
+    sample_buffer: Option<Buffer>,
+    /// Selections to dispatch this frame.
+    dispatches: Vec<LightDispatch>,
+    /// Staging buffer the sample buffer is copied into.
+    staging: StagingBuffer,
+    /// Effects copied this frame, or being mapped.
+    readback_effects: Vec<ReadbackLightEffect>,
+}
+
+impl FromWorld for ParticleLightsMeta {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let item_align = render_device.limits().min_storage_buffer_offset_alignment as u64;
+        Self {
+            effects: vec![],
+            params_buffer: AlignedBufferVec::new(
+                BufferUsages::STORAGE,
+                NonZeroU64::new(item_align),
+                Some("hanabi:buffer:particle_lights_params".to_string()),
+            ),
+            sample_buffer: None,
+            dispatches: vec![],
+            staging: default(),
+            readback_effects: vec![],
+        }
+    }
+}
+
+impl ParticleLightsMeta {
+    /// Record the selection of the brightest particles of all groups, and the
+    /// copy of the selected samples into the staging buffer.
+    ///
+    /// This must run after the update pass.
+    pub fn dispatch(&self, encoder: &mut CommandEncoder, pipeline: &ParticleLightsPipeline) {
+        let Some(sample_buffer) = &self.sample_buffer else {
+            return;
+        };
+        if self.dispatches.is_empty() {
+            return;
+        }
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("hanabi:particle_lights"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&pipeline.pipeline);
+            for dispatch in &self.dispatches {
+                for &offset in &dispatch.params_offsets {
+                    compute_pass.set_bind_group(0, &dispatch.bind_group, &[offset]);
+                    compute_pass.dispatch_workgroups(1, 1, 1);
+                }
+            }
+        }
+
+        trace!(
+            "Copying samples of {} effects for particle lights readback",
+            self.readback_effects.len()
+        );
+        self.staging.copy(encoder, sample_buffer);
+    }
+}
+
+/// Offset of an attribute in a particle, in number of `u32`, or [`u32::MAX`]
+/// if the particles don't have that attribute.
+fn attribute_offset(particle_layout: &crate::ParticleLayout, attribute: Attribute) -> u32 {
+    particle_layout
+        .attributes()
+        .iter()
+        .find(|entry| entry.attribute == attribute)
+        .map(|entry| entry.offset / 4)
+        .unwrap_or(u32::MAX)
+}
+
+/// Decode the samples of the given effects from the content of the sample
+/// buffer.
+///
+/// Unused samples, which don't emit any light, are skipped.
+fn read_particle_lights(data: &[u8], effects: &[ReadbackLightEffect]) -> Vec<ParticleLightsData> {
+    let item_size = std::mem::size_of::<GpuParticleLightSample>();
+    effects
+        .iter()
+        .map(|effect| ParticleLightsData {
+            entity: effect.entity,
+            samples: (effect.first_sample..effect.first_sample + effect.sample_count)
+                .map(|index| {
+                    let offset = index as usize * item_size;
+                    let sample: GpuParticleLightSample =
+                        bytemuck::pod_read_unaligned(&data[offset..offset + item_size]);
+                    ParticleLightSample::new(sample.position.truncate(), sample.color)
+                })
+                .filter(|sample| sample.brightness() > 0.)
+                .collect(),
+        })
+        .collect()
+}
+
+/// Extract the number of lights of all effect instances with a
+/// [`ParticleLights`] component.
+pub(crate) fn extract_particle_lights(
+    query: Extract<Query<(Entity, &ParticleLights), With<ParticleEffect>>>,
+    mut extracted_lights: ResMut<ExtractedParticleLights>,
+) {
+    trace!("extract_particle_lights");
+
+    extracted_lights.light_counts.clear();
+    extracted_lights.light_counts.extend(
+        query
+            .iter()
+            .map(|(entity, particle_lights)| (entity, particle_lights.light_count()))
+            .filter(|&(_, light_count)| light_count > 0),
+    );
+}
+
+/// Collect the effects extracted this frame which emit lights.
+///
+/// This runs before [`prepare_effects()`], like the alive count readback, so
+/// effects added this frame only emit lights from the next frame, once their
+/// particles are allocated.
+///
+/// [`prepare_effects()`]: crate::render::prepare_effects
+pub(crate) fn prepare_particle_lights(
+    effects_meta: Res<EffectsMeta>,
+    extracted_effects: Res<ExtractedEffects>,
+    extracted_lights: Res<ExtractedParticleLights>,
+    mut lights_meta: ResMut<ParticleLightsMeta>,
+) {
+    trace!("prepare_particle_lights");
+
+    lights_meta.effects = extracted_lights
+        .light_counts
+        .iter()
+        .filter_map(|(&entity, &light_count)| {
+            let extracted_effect = extracted_effects.effects.get(&entity)?;
+            let cache_id = effects_meta.entity_map.get(&entity)?.cache_id;
+            // Particles simulated in local space are already relative to the effect
+            let transform = if extracted_effect
+                .layout_flags
+                .contains(LayoutFlags::LOCAL_SPACE_SIMULATION)
+            {
+                Mat4::IDENTITY
+            } else {
+                extracted_effect.inverse_transform
+            };
+            Some(LightEffect {
+                entity,
+                cache_id,
+                light_count,
+                transform,
+            })
+        })
+        .collect();
+}
+
+/// Prepare the selection of the brightest particles of the effects collected
+/// by [`prepare_particle_lights()`], and the readback of the selected samples.
+///
+/// Nothing is selected while a readback is in flight.
+pub(crate) fn prepare_particle_lights_bind_groups(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    effects_meta: Res<EffectsMeta>,
+    effect_cache: Res<EffectCache>,
+    pipeline: Res<ParticleLightsPipeline>,
+    mut lights_meta: ResMut<ParticleLightsMeta>,
+) {
+    trace!("prepare_particle_lights_bind_groups");
+
+    let lights_meta = &mut *lights_meta;
+    lights_meta.dispatches.clear();
+
+    // Keep the effects of the mapping in flight, if any, until it completes.
+    if lights_meta.staging.is_mapping() {
+        return;
+    }
+    lights_meta.staging.cancel();
+    lights_meta.readback_effects.clear();
+    lights_meta.params_buffer.clear();
+
+    let (Some(render_effect_buffer), Some(render_group_buffer)) = (
+        effects_meta.render_effect_dispatch_buffer.buffer(),
+        effects_meta.render_group_dispatch_buffer.buffer(),
+    ) else {
+        return;
+    };
+
+    let max_lights = ParticleLights::MAX_LIGHTS;
+    let params_size = lights_meta.params_buffer.aligned_size() as u32;
+    let mut sample_count = 0;
+    let mut effect_params = vec![];
+    for effect in &lights_meta.effects {
+        // Skip effects removed by prepare_effects() since they were collected
+        match effects_meta.entity_map.get(&effect.entity) {
+            Some(entry) if entry.cache_id == effect.cache_id => {}
+            _ => continue,
+        }
+        let slices = effect_cache.get_slices(effect.cache_id);
+        let particle_layout = &slices.particle_layout;
+        let position_offset = attribute_offset(particle_layout, Attribute::POSITION);
+        if position_offset == u32::MAX {
+            continue;
+        }
+        let dispatch_buffer_indices = effect_cache.get_dispatch_buffer_indices(effect.cache_id);
+        let render_effect_indirect = effects_meta.gpu_limits.render_effect_indirect_offset(
+            dispatch_buffer_indices
+                .render_effect_metadata_buffer_index
+                .0,
+        ) as u32
+            / 4;
+        let first_row = dispatch_buffer_indices
+            .first_render_group_dispatch_buffer_index
+            .0;
+
+        let first_sample = sample_count;
+        let params_offsets: Vec<u32> = slices
+            .slices
+            .windows(2)
+            .enumerate()
+            .map(|(group_index, range)| {
+                let render_group_indirect = effects_meta
+                    .gpu_limits
+                    .render_group_indirect_offset(first_row + group_index as u32)
+                    as u32
+                    / 4;
+                let index = lights_meta.params_buffer.push(GpuParticleLightParams {
+                    transform: effect.transform.into(),
+                    particle_stride: particle_layout.min_binding_size().get() as u32 / 4,
+                    position_offset,
+                    color_offset: attribute_offset(particle_layout, Attribute::COLOR),
+                    hdr_color_offset: attribute_offset(particle_layout, Attribute::HDR_COLOR),
+                    // Same indexing as the update pass
+                    base_index: slices.slices[0] + range[0],
+                    render_effect_indirect,
+                    render_group_indirect,
+                    light_count: effect.light_count,
+                    output_index: sample_count,
+                    padding: [0; 3],
+                });
+                sample_count += max_lights;
+                index as u32 * params_size
+            })
+            .collect();
+
+        lights_meta.readback_effects.push(ReadbackLightEffect {
+            entity: effect.entity,
+            first_sample,
+            sample_count: sample_count - first_sample,
+        });
+        effect_params.push((slices.buffer_index, params_offsets));
+    }
+    if sample_count == 0 {
+        return;
+    }
+
+    lights_meta
+        .params_buffer
+        .write_buffer(&render_device, &render_queue);
+    let Some(params_buffer) = lights_meta.params_buffer.buffer() else {
+        return;
+    };
+
+    let sample_buffer_size =
+        sample_count as u64 * std::mem::size_of::<GpuParticleLightSample>() as u64;
+    let too_small = match &lights_meta.sample_buffer {
+        Some(buffer) => buffer.size() < sample_buffer_size,
+        None => true,
+    };
+    if too_small {
+        lights_meta.sample_buffer = Some(render_device.create_buffer(&BufferDescriptor {
+            label: Some("hanabi:buffer:particle_lights_samples"),
+            size: sample_buffer_size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        }));
+    }
+    let sample_buffer = lights_meta.sample_buffer.as_ref().unwrap();
+
+    for (buffer_index, params_offsets) in effect_params {
+        let Some(Some(effect_buffer)) = effect_cache.buffers().get(buffer_index as usize) else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            "hanabi:bind_group:particle_lights",
+            &pipeline.layout,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: effect_buffer.particle_buffer().as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: effect_buffer.indirect_max_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: render_effect_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: render_group_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: params_buffer,
+                        offset: 0,
+                        size: Some(GpuParticleLightParams::min_size()),
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: sample_buffer.as_entire_binding(),
+                },
+            ],
+        );
+        lights_meta.dispatches.push(LightDispatch {
+            bind_group,
+            params_offsets,
+        });
+    }
+
+    lights_meta.staging.request(
+        &render_device,
+        sample_buffer_size,
+        "hanabi:buffer:particle_lights_readback",
+    );
+}
+
+/// Map the samples copied this frame, and send any readback completed since the
+/// previous frame to the main world.
+pub(crate) fn readback_particle_lights(
+    render_device: Res<RenderDevice>,
+    sender: Res<ParticleLightsSender>,
+    mut lights_meta: ResMut<ParticleLightsMeta>,
+) {
+    trace!("readback_particle_lights");
+
+    let lights_meta = &mut *lights_meta;
+    let effects = &lights_meta.readback_effects;
+    let Some(result) = lights_meta
+        .staging
+        .poll(&render_device, |data| read_particle_lights(data, effects))
+    else {
+        return;
+    };
+    let Ok(particle_lights) = result else {
+        error!("Failed to read back the particle lights.");
+        return;
+    };
+    for data in particle_lights {
+        let _ = sender.0.send(data);
+    }
+}
+
+/// Receive the brightest particles read back from GPU, and store them into the
+/// [`ParticleLightSamples`] of each effect instance.
+///
+/// This runs before [`update_particle_lights()`], which spawns the lights from
+/// those samples.
+///
+/// [`update_particle_lights()`]: crate::update_particle_lights
+pub(crate) fn receive_particle_lights(
+    mut commands: Commands,
+    receiver: Res<ParticleLightsReceiver>,
+    mut query: Query<Option<&mut ParticleLightSamples>, With<ParticleLights>>,
+) {
+    trace!("receive_particle_lights");
+
+    let receiver = receiver.0.lock().unwrap();
+    while let Ok(data) = receiver.try_recv() {
+        let Ok(samples) = query.get_mut(data.entity) else {
+            continue;
+        };
+        match samples {
+            Some(mut samples) => samples.samples = data.samples,
+            None => {
+                commands.entity(data.entity).insert(ParticleLightSamples {
+                    samples: data.samples,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use naga::{front::wgsl::Frontend, proc::Layouter};
+
+    use super::*;
+
+    #[test]
+    fn decode_particle_lights() {
+        let max_lights = ParticleLights::MAX_LIGHTS as usize;
+        let mut samples = vec![GpuParticleLightSample::default(); max_lights * 3];
+        samples[0] = GpuParticleLightSample {
+            position: Vec4::new(1., 2., 3., 1.),
+            color: Vec4::new(4., 2., 0., 1.),
+        };
+        samples[max_lights] = GpuParticleLightSample {
+            position: Vec4::new(-1., 0., 0., 1.),
+            color: Vec4::new(0., 0., 1., 0.5),
+        };
+        samples[2 * max_lights + 1] = GpuParticleLightSample {
+            position: Vec4::ONE,
+            color: Vec4::ONE,
+        };
+        let data = bytemuck::cast_slice(&samples);
+
+        let effects = [
+            ReadbackLightEffect {
+                entity: Entity::from_raw(3),
+                first_sample: 0,
+                sample_count: 2 * max_lights as u32,
+            },
+            ReadbackLightEffect {
+                entity: Entity::from_raw(8),
+                first_sample: 2 * max_lights as u32,
+                sample_count: max_lights as u32,
+            },
+        ];
+        let particle_lights = read_particle_lights(data, &effects);
+        assert_eq!(
+            particle_lights,
+            vec![
+                ParticleLightsData {
+                    entity: Entity::from_raw(3),
+                    samples: vec![
+                        ParticleLightSample::new(Vec3::new(1., 2., 3.), Vec4::new(4., 2., 0., 1.)),
+                        ParticleLightSample::new(Vec3::NEG_X, Vec4::new(0., 0., 1., 0.5)),
+                    ],
+                },
+                ParticleLightsData {
+                    entity: Entity::from_raw(8),
+                    samples: vec![ParticleLightSample::new(Vec3::ONE, Vec4::ONE)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn particle_lights_shader() {
+        let module = Frontend::new()
+            .parse(include_str!("vfx_lights.wgsl"))
+            .unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+
+        // The shader structs match their CPU representation
+        let mut layouter = Layouter::default();
+        layouter.update(module.to_ctx()).unwrap();
+        for (name, size) in [
+            ("LightParams", GpuParticleLightParams::min_size()),
+            ("LightSample", GpuParticleLightSample::min_size()),
+        ] {
+            let (handle, _) = module
+                .types
+                .iter()
+                .find(|(_, ty)| ty.name.as_deref() == Some(name))
+                .unwrap();
+            assert_eq!(layouter[handle].size as u64, size.get(), "{}", name);
+        }
+        let max_lights = module
+            .constants
+            .iter()
+            .find(|(_, constant)| constant.name.as_deref() == Some("MAX_LIGHTS"))
+            .map(|(_, constant)| &module.global_expressions[constant.init]);
+        assert!(matches!(
+            max_lights,
+            Some(naga::Expression::Literal(naga::Literal::U32(max_lights)))
+                if *max_lights == ParticleLights::MAX_LIGHTS
+        ));
+    }
+
+    #[test]
+    fn receive_particle_lights_samples() {
+        let mut world = World::new();
+        let (sender, receiver) = channel();
+        world.insert_resource(ParticleLightsReceiver(Mutex::new(receiver)));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(receive_particle_lights);
+
+        let effect = world.spawn(ParticleLights::new(2)).id();
+        let other = world.spawn_empty().id();
+        let sample = ParticleLightSample::new(Vec3::X, Vec4::ONE);
+        for entity in [effect, other] {
+            sender
+                .send(ParticleLightsData {
+                    entity,
+                    samples: vec![sample],
+                })
+                .unwrap();
+        }
+
+        // The samples are inserted on the entities emitting lights only
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<ParticleLightSamples>(effect).unwrap().samples,
+            vec![sample]
+        );
+        assert!(world.get::<ParticleLightSamples>(other).is_none());
+
+        // Then replaced by the next readback
+        sender
+            .send(ParticleLightsData {
+                entity: effect,
+                samples: vec![],
+            })
+            .unwrap();
+        schedule.run(&mut world);
+        assert!(world
+            .get::<ParticleLightSamples>(effect)
+            .unwrap()
+            .samples
+            .is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
     num::{NonZero, NonZeroU32, NonZeroU64},
+    ops::Range,
 };
 use std::{iter, marker::PhantomData};
 
@@ -40,7 +41,7 @@ use bevy::{
         },
         Extract,
     },
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bitflags::bitflags;
 use bytemuck::{Pod, Zeroable};
@@ -58,20 +59,34 @@ use crate::{
         effect_cache::DispatchBufferIndices,
     },
     spawn::{EffectCloner, EffectInitializer, EffectInitializers, Initializer},
-    AlphaMode, Attribute, CompiledParticleEffect, EffectProperties, EffectShader, EffectSimulation,
-    HanabiPlugin, ParticleLayout, PropertyLayout, RemovedEffectsEvent, SimulationCondition,
-    TextureLayout, ToWgslString,
+    AlphaMode, Attribute, CompiledParticleEffect, EffectGlobals, EffectGravity, EffectProperties,
+    EffectRenderLayer, EffectShader, EffectSimulation, HanabiPlugin, ParticleLayout,
+    PropertyLayout, PropertyStore, RemovedEffectsEvent, SharedProperties, SimulationCondition,
+    SimulationSpace, TextureLayout, ToWgslString, WindZone,
 };
 
 mod aligned_buffer_vec;
 mod batch;
 mod buffer_table;
 mod effect_cache;
+#[cfg(feature = "lights")]
+mod lights;
+mod readback;
 mod shader_cache;
 
 use aligned_buffer_vec::AlignedBufferVec;
 use buffer_table::{BufferTable, BufferTableId};
 pub(crate) use effect_cache::{EffectCache, EffectCacheId};
+#[cfg(feature = "lights")]
+pub(crate) use lights::{
+    extract_particle_lights, prepare_particle_lights, prepare_particle_lights_bind_groups,
+    readback_particle_lights, receive_particle_lights, ExtractedParticleLights, ParticleLightsMeta,
+    ParticleLightsPipeline, ParticleLightsReceiver, ParticleLightsSender,
+};
+pub(crate) use readback::{
+    prepare_alive_count_readback, readback_alive_counts, receive_alive_counts, AliveCountReadback,
+    AliveCountReceiver, AliveCountSender,
+};
 pub use shader_cache::ShaderCache;
 
 use self::batch::EffectBatches;
@@ -100,6 +115,12 @@ pub(crate) struct SimParams {
     real_time: f64,
     /// Real delta time, in seconds, since last effect system update.
     real_delta_time: f32,
+
+    /// Global wind, if any.
+    wind: WindZone,
+
+    /// Global values shared by all effects.
+    globals: EffectGlobals,
 }
 
 /// GPU representation of [`SimParams`], as well as additional per-frame
@@ -131,6 +152,16 @@ struct GpuSimParams {
     ///
     /// This is only used by the `vfx_indirect` compute shader.
     num_groups: u32,
+    /// Normalized direction of the global [`WindZone`].
+    wind_direction: Vec3,
+    /// Strength of the global [`WindZone`].
+    wind_strength: f32,
+    /// Turbulence of the global [`WindZone`].
+    wind_turbulence: f32,
+    /// Global [`EffectGlobals::time_of_day`].
+    time_of_day: f32,
+    /// Global [`EffectGlobals::weather`].
+    weather: f32,
 }
 
 impl Default for GpuSimParams {
@@ -143,6 +174,11 @@ impl Default for GpuSimParams {
             real_delta_time: 0.04,
             real_time: 0.0,
             num_groups: 0,
+            wind_direction: Vec3::X,
+            wind_strength: 0.0,
+            wind_turbulence: 0.0,
+            time_of_day: 0.0,
+            weather: 0.0,
         }
     }
 }
@@ -156,6 +192,11 @@ impl From<SimParams> for GpuSimParams {
             virtual_time: src.virtual_time as f32,
             real_delta_time: src.real_delta_time,
             real_time: src.real_time as f32,
+            wind_direction: src.wind.normalized_direction(),
+            wind_strength: src.wind.strength,
+            wind_turbulence: src.wind.turbulence,
+            time_of_day: src.globals.time_of_day,
+            weather: src.globals.weather,
             ..default()
         }
     }
@@ -276,12 +317,23 @@ pub(crate) struct GpuSpawnerParams {
     count: i32,
     /// Index of the effect in the indirect dispatch and render buffers.
     effect_index: u32,
+    /// Gravity applied to the particles, from the [`EffectGravity`] component
+    /// of the effect, or zero if none.
+    ///
+    /// [`EffectGravity`]: crate::EffectGravity
+    gravity: Vec3,
     /// The time in seconds that the cloned particles live, if this is a cloner.
     ///
     /// If this is a spawner, this value is zero.
     lifetime: f32,
-    /// Padding.
-    pad: [u32; 3],
+    /// Motion of the emitter since the previous frame, to apply to particles
+    /// following it partially. See [`EffectAsset::simulation_space_blend`].
+    emitter_motion: GpuCompressedTransform,
+    /// Number of particles the spawner requested in previous frames, to
+    /// continue sampling point clouds sequentially across frames.
+    spawn_index: u32,
+    /// Padding to keep the struct size a multiple of its alignment.
+    padding: [u32; 3],
 }
 
 // FIXME - min_storage_buffer_offset_alignment
@@ -556,6 +608,8 @@ bitflags! {
         const CLONE = 0x1;
         const ATTRIBUTE_PREV = 0x2;
         const ATTRIBUTE_NEXT = 0x4;
+        const DOUBLE_BUFFERED = 0x8;
+        const POINT_CLOUD = 0x10;
     }
 }
 
@@ -610,11 +664,17 @@ impl SpecializedComputePipeline for ParticlesInitPipeline {
     type Key = ParticleInitPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> ComputePipelineDescriptor {
+        let double_buffered = key
+            .flags
+            .contains(ParticleInitPipelineKeyFlags::DOUBLE_BUFFERED);
         let particles_buffer_layout = create_init_particles_bind_group_layout(
             &self.render_device,
             "hanabi:init_particles_buffer_layout",
             key.particle_layout_min_binding_size,
             key.property_layout_min_binding_size,
+            double_buffered,
+            key.flags
+                .contains(ParticleInitPipelineKeyFlags::POINT_CLOUD),
         );
 
         let mut shader_defs = vec![];
@@ -633,6 +693,9 @@ impl SpecializedComputePipeline for ParticlesInitPipeline {
         {
             shader_defs.push(ShaderDefVal::Bool("ATTRIBUTE_NEXT".to_string(), true));
         }
+        if double_buffered {
+            shader_defs.push(ShaderDefVal::Bool("DOUBLE_BUFFERED".to_string(), true));
+        }
 
         ComputePipelineDescriptor {
             label: Some("hanabi:pipeline_init_compute".into()),
@@ -755,6 +818,10 @@ pub(crate) struct ParticleUpdatePipelineKey {
     /// Property layout.
     property_layout: PropertyLayout,
     is_trail: bool,
+    /// The update pass reads and writes two different particle buffers.
+    double_buffered: bool,
+    /// The effect binds a point cloud storage buffer.
+    point_cloud: bool,
 }
 
 impl SpecializedComputePipeline for ParticlesUpdatePipeline {
@@ -780,6 +847,8 @@ impl SpecializedComputePipeline for ParticlesUpdatePipeline {
             } else {
                 Some(key.property_layout.min_binding_size())
             },
+            key.double_buffered,
+            key.point_cloud,
         );
 
         let mut shader_defs = vec!["REM_MAX_SPAWN_ATOMIC".into()];
@@ -792,6 +861,9 @@ impl SpecializedComputePipeline for ParticlesUpdatePipeline {
         if key.is_trail {
             shader_defs.push("TRAIL".into());
         }
+        if key.double_buffered {
+            shader_defs.push("DOUBLE_BUFFERED".into());
+        }
 
         ComputePipelineDescriptor {
             label: Some("hanabi:pipeline_update_compute".into()),
@@ -934,13 +1006,22 @@ pub(crate) struct ParticleRenderPipelineKey {
     use_alpha_mask: bool,
     /// The effect needs Alpha blend.
     alpha_mode: AlphaMode,
+    /// The effect outputs premultiplied alpha colors, and needs a premultiplied
+    /// alpha blend state.
+    premultiply_alpha: bool,
     /// Key: FLIPBOOK
     /// The effect is rendered with flipbook texture animation based on the
     /// sprite index of each particle.
     flipbook: bool,
+    /// Key: FLIPBOOK_BLEND
+    /// The effect cross-fades between consecutive flipbook sprites.
+    flipbook_blend: bool,
     /// Key: NEEDS_UV
     /// The effect needs UVs.
     needs_uv: bool,
+    /// Key: NEEDS_WORLD_NORMAL
+    /// The effect needs the world-space position and normal of its fragments.
+    needs_world_normal: bool,
     /// Key: RIBBONS
     /// The effect has ribbons.
     ribbons: bool,
@@ -964,8 +1045,11 @@ impl Default for ParticleRenderPipelineKey {
             local_space_simulation: false,
             use_alpha_mask: false,
             alpha_mode: AlphaMode::Blend,
+            premultiply_alpha: false,
             flipbook: false,
+            flipbook_blend: false,
             needs_uv: false,
+            needs_world_normal: false,
             ribbons: false,
             #[cfg(all(feature = "2d", feature = "3d"))]
             pipeline_mode: PipelineMode::Camera3d,
@@ -975,6 +1059,62 @@ impl Default for ParticleRenderPipelineKey {
     }
 }
 
+impl ParticleRenderPipelineKey {
+    /// Is alpha-to-coverage effectively enabled for this pipeline?
+    ///
+    /// Alpha-to-coverage requires a multisampled render target, so this is
+    /// only enabled with [`AlphaMode::AlphaToCoverage`] on views using MSAA.
+    fn alpha_to_coverage(&self) -> bool {
+        self.alpha_mode == AlphaMode::AlphaToCoverage && self.msaa_samples > 1
+    }
+
+    /// Blend state of the color target, or `None` if the target is written
+    /// without blending.
+    fn blend_state(&self) -> Option<BlendState> {
+        if self.alpha_to_coverage() {
+            return None;
+        }
+
+        let blend_state = match self.alpha_mode {
+            AlphaMode::Blend if self.premultiply_alpha => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            AlphaMode::Blend => BlendState::ALPHA_BLENDING,
+            AlphaMode::Premultiply => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            AlphaMode::Add => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+            AlphaMode::Multiply => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            },
+            _ => BlendState::ALPHA_BLENDING,
+        };
+
+        Some(blend_state)
+    }
+
+    /// Multisample state of the pipeline.
+    fn multisample_state(&self) -> MultisampleState {
+        MultisampleState {
+            count: self.msaa_samples,
+            mask: !0,
+            alpha_to_coverage_enabled: self.alpha_to_coverage(),
+        }
+    }
+}
+
 impl SpecializedRenderPipeline for ParticlesRenderPipeline {
     type Key = ParticleRenderPipelineKey;
 
@@ -1111,11 +1251,21 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
             shader_defs.push("FLIPBOOK".into());
         }
 
+        // Key: FLIPBOOK_BLEND
+        if key.flipbook_blend {
+            shader_defs.push("FLIPBOOK_BLEND".into());
+        }
+
         // Key: NEEDS_UV
         if key.needs_uv {
             shader_defs.push("NEEDS_UV".into());
         }
 
+        // Key: NEEDS_WORLD_NORMAL
+        if key.needs_world_normal {
+            shader_defs.push("NEEDS_WORLD_NORMAL".into());
+        }
+
         // Key: RIBBONS
         if key.ribbons {
             shader_defs.push("RIBBONS".into());
@@ -1128,7 +1278,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
             PipelineMode::Camera3d => Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 // Use depth buffer with alpha-masked particles, not with transparent ones
-                depth_write_enabled: key.use_alpha_mask,
+                depth_write_enabled: key.use_alpha_mask || key.alpha_to_coverage(),
                 // Bevy uses reverse-Z, so Greater really means closer
                 depth_compare: CompareFunction::Greater,
                 stencil: StencilState::default(),
@@ -1143,7 +1293,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
         let depth_stencil = Some(DepthStencilState {
             format: TextureFormat::Depth32Float,
             // Use depth buffer with alpha-masked particles, not with transparent ones
-            depth_write_enabled: key.use_alpha_mask,
+            depth_write_enabled: key.use_alpha_mask || key.alpha_to_coverage(),
             // Bevy uses reverse-Z, so Greater really means closer
             depth_compare: CompareFunction::Greater,
             stencil: StencilState::default(),
@@ -1156,31 +1306,8 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
             TextureFormat::bevy_default()
         };
 
-        let blend_state = match key.alpha_mode {
-            AlphaMode::Blend => BlendState::ALPHA_BLENDING,
-            AlphaMode::Premultiply => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
-            AlphaMode::Add => BlendState {
-                color: BlendComponent {
-                    src_factor: BlendFactor::SrcAlpha,
-                    dst_factor: BlendFactor::One,
-                    operation: BlendOperation::Add,
-                },
-                alpha: BlendComponent {
-                    src_factor: BlendFactor::Zero,
-                    dst_factor: BlendFactor::One,
-                    operation: BlendOperation::Add,
-                },
-            },
-            AlphaMode::Multiply => BlendState {
-                color: BlendComponent {
-                    src_factor: BlendFactor::Dst,
-                    dst_factor: BlendFactor::OneMinusSrcAlpha,
-                    operation: BlendOperation::Add,
-                },
-                alpha: BlendComponent::OVER,
-            },
-            _ => BlendState::ALPHA_BLENDING,
-        };
+        let blend_state = key.blend_state();
+        let multisample = key.multisample_state();
 
         RenderPipelineDescriptor {
             vertex: VertexState {
@@ -1195,7 +1322,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format,
-                    blend: Some(blend_state),
+                    blend: blend_state,
                     write_mask: ColorWrites::ALL,
                 })],
             }),
@@ -1210,11 +1337,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
                 strip_index_format: None,
             },
             depth_stencil,
-            multisample: MultisampleState {
-                count: key.msaa_samples,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
+            multisample,
             label: Some("hanabi:pipeline_render".into()),
             push_constant_ranges: Vec::new(),
         }
@@ -1244,6 +1367,13 @@ pub(crate) struct ExtractedEffect {
     ///
     /// [`property_layout`]: crate::render::ExtractedEffect::property_layout
     pub property_data: Option<Vec<u8>>,
+    /// Shared property block the effect reads its properties from, if any.
+    ///
+    /// When set, [`property_data`] is always `None`, and the data is instead
+    /// read from [`ExtractedEffects::shared_properties`].
+    ///
+    /// [`property_data`]: crate::render::ExtractedEffect::property_data
+    pub shared_properties: Option<ExtractedSharedProperties>,
     /// Maps a group number to the runtime initializer for that group.
     ///
     /// Obtained from calling [`EffectSpawner::tick()`] on the source effect
@@ -1257,8 +1387,19 @@ pub(crate) struct ExtractedEffect {
     /// Inverse global transform of the effect origin, extracted from the
     /// [`GlobalTransform`].
     pub inverse_transform: Mat4,
+    /// Gravity applied to the particles, extracted from the [`EffectGravity`],
+    /// or zero if the effect doesn't have any.
+    pub gravity: Vec3,
+    /// Motion of the effect origin since the previous frame, as the transform
+    /// from the previous to the current [`transform`]. This is the identity if
+    /// the particles don't follow that motion, or if there's no previous frame.
+    ///
+    /// [`transform`]: ExtractedEffect::transform
+    pub emitter_motion: Mat4,
     /// Layout flags.
     pub layout_flags: LayoutFlags,
+    /// Layout flags of the secondary render layer, if any.
+    pub secondary_layout_flags: Option<LayoutFlags>,
     /// Texture layout.
     pub texture_layout: TextureLayout,
     /// Textures.
@@ -1293,6 +1434,8 @@ pub struct AddedEffect {
     pub handle: Handle<EffectAsset>,
     /// The order in which we evaluate groups.
     pub group_order: Vec<u32>,
+    /// Points of the point clouds of the effect, if any.
+    pub point_cloud: Vec<Vec3>,
 }
 
 pub struct AddedEffectGroup {
@@ -1300,6 +1443,29 @@ pub struct AddedEffectGroup {
     pub src_group_index_if_trail: Option<u32>,
 }
 
+/// Reference from an [`ExtractedEffect`] to a shared property block.
+#[derive(Debug)]
+pub(crate) struct ExtractedSharedProperties {
+    /// Name of the block in the [`PropertyStore`].
+    pub block: String,
+    /// Force a GPU upload of the block for this effect, even if the block
+    /// didn't change. This is used when the effect starts referencing the
+    /// block.
+    pub force_upload: bool,
+}
+
+/// Serialized data of a shared property block of the [`PropertyStore`].
+pub(crate) struct SharedPropertyData {
+    /// Generation of the block the data was serialized from.
+    pub generation: u64,
+    /// Property layout the data was serialized with.
+    pub layout: PropertyLayout,
+    /// Binary blob of property values, ready for GPU upload.
+    pub data: Vec<u8>,
+    /// Was the data (re-)serialized this frame, and needs a GPU upload?
+    pub dirty: bool,
+}
+
 /// Collection of all extracted effects for this frame, inserted into the
 /// render world as a render resource.
 #[derive(Default, Resource)]
@@ -1315,6 +1481,78 @@ pub(crate) struct ExtractedEffects {
     pub removed_effect_entities: Vec<Entity>,
     /// Newly added effects without a GPU allocation yet.
     pub added_effects: Vec<AddedEffect>,
+    /// Serialized shared property blocks, by name. This is retained from one
+    /// frame to the next to detect changes.
+    pub shared_properties: HashMap<String, SharedPropertyData>,
+}
+
+impl ExtractedEffects {
+    /// Extract a shared property block of the [`PropertyStore`].
+    ///
+    /// The block is serialized at most once per frame, the first time an
+    /// effect referencing it is extracted, and only if it changed since it was
+    /// last serialized. Returns `false` if the block doesn't exist, or if it
+    /// was already serialized this frame with a different property layout.
+    pub(crate) fn extract_shared_properties(
+        &mut self,
+        store: &PropertyStore,
+        block: &str,
+        layout: &PropertyLayout,
+    ) -> bool {
+        let Some(property_block) = store.block(block) else {
+            return false;
+        };
+        if let Some(shared) = self.shared_properties.get_mut(block) {
+            if shared.layout == *layout {
+                if shared.generation != property_block.generation {
+                    shared.generation = property_block.generation;
+                    shared.data = property_block.properties.serialize(layout);
+                    shared.dirty = true;
+                }
+                return true;
+            }
+            if shared.dirty {
+                warn!(
+                    "Shared property block '{}' is referenced by effects with different property layouts. Ignoring effect using layout {:?}.",
+                    block, layout
+                );
+                return false;
+            }
+        }
+        self.shared_properties.insert(
+            block.to_string(),
+            SharedPropertyData {
+                generation: property_block.generation,
+                layout: layout.clone(),
+                data: property_block.properties.serialize(layout),
+                dirty: true,
+            },
+        );
+        true
+    }
+
+    /// Get the shared property data to upload to GPU for an effect.
+    ///
+    /// Returns the data if the shared block needs an upload, either because it
+    /// changed this frame or because `force_upload` is `true`, and if it wasn't
+    /// already uploaded this frame into the property buffer of the effect
+    /// buffer with index `buffer_index`. The `uploaded` set tracks the effect
+    /// buffers already uploaded to for each block this frame.
+    pub(crate) fn shared_property_upload(
+        &self,
+        shared: &ExtractedSharedProperties,
+        buffer_index: u32,
+        uploaded: &mut HashSet<(String, u32)>,
+    ) -> Option<Vec<u8>> {
+        let data = self.shared_properties.get(&shared.block)?;
+        if !data.dirty && !shared.force_upload {
+            return None;
+        }
+        if !uploaded.insert((shared.block.clone(), buffer_index)) {
+            return None;
+        }
+        Some(data.data.clone())
+    }
 }
 
 #[derive(Default, Resource)]
@@ -1353,6 +1591,9 @@ pub(crate) fn extract_effects(
     time: Extract<Res<Time<EffectSimulation>>>,
     effects: Extract<Res<Assets<EffectAsset>>>,
     _images: Extract<Res<Assets<Image>>>,
+    property_store: Extract<Option<Res<PropertyStore>>>,
+    wind: Extract<Option<Res<WindZone>>>,
+    globals: Extract<Option<Res<EffectGlobals>>>,
     mut query: Extract<
         ParamSet<(
             // All existing ParticleEffect components
@@ -1363,6 +1604,8 @@ pub(crate) fn extract_effects(
                 &EffectInitializers,
                 &CompiledParticleEffect,
                 Option<Ref<EffectProperties>>,
+                Option<Ref<SharedProperties>>,
+                Option<&EffectGravity>,
                 &GlobalTransform,
             )>,
             // Newly added ParticleEffect components
@@ -1385,6 +1628,8 @@ pub(crate) fn extract_effects(
     sim_params.virtual_delta_time = virtual_time.delta_seconds();
     sim_params.real_time = real_time.elapsed_seconds_f64();
     sim_params.real_delta_time = real_time.delta_seconds();
+    sim_params.wind = wind.as_deref().copied().unwrap_or_default();
+    sim_params.globals = globals.as_deref().copied().unwrap_or_default();
 
     // Collect removed effects for later GPU data purge
     extracted_effects.removed_effect_entities =
@@ -1443,12 +1688,19 @@ pub(crate) fn extract_effects(
                 group_order,
                 layout_flags: effect.layout_flags,
                 handle,
+                point_cloud: effect.point_cloud.clone(),
             })
         })
         .collect();
 
-    // Loop over all existing effects to update them
-    extracted_effects.effects.clear();
+    // Shared property blocks only need an upload if they change this frame
+    for shared in extracted_effects.shared_properties.values_mut() {
+        shared.dirty = false;
+    }
+
+    // Loop over all existing effects to update them. The effects of the previous
+    // frame are retained to compute the emitter motion since that frame.
+    let prev_effects = std::mem::take(&mut extracted_effects.effects);
     for (
         entity,
         maybe_inherited_visibility,
@@ -1456,6 +1708,8 @@ pub(crate) fn extract_effects(
         initializers,
         effect,
         maybe_properties,
+        maybe_shared_properties,
+        maybe_gravity,
         transform,
     ) in query.p0().iter_mut()
     {
@@ -1484,23 +1738,43 @@ pub(crate) fn extract_effects(
             continue;
         };
 
+        // Skip empty effects entirely, so they don't dispatch nor draw anything
+        if asset.skip_when_empty && initializers.is_idle() {
+            trace!(
+                "Effect '{}' on entity {:?} is idle; skipping.",
+                asset.name,
+                entity
+            );
+            continue;
+        }
+
         #[cfg(feature = "2d")]
         let z_sort_key_2d = effect.z_layer_2d;
 
         let property_layout = asset.property_layout();
         let texture_layout = asset.module().texture_layout();
 
-        let property_data = if let Some(properties) = maybe_properties {
-            // Note: must check that property layout is not empty, because the
-            // EffectProperties component is marked as changed when added but contains an
-            // empty Vec if there's no property, which would later raise an error if we
-            // don't return None here.
-            if properties.is_changed() && !property_layout.is_empty() {
-                trace!("Detected property change, re-serializing...");
-                Some(properties.serialize(&property_layout))
-            } else {
-                None
+        let mut shared_properties = None;
+        let property_data = if let Some(shared) = maybe_shared_properties {
+            // Shared properties override any per-instance property
+            if !property_layout.is_empty() {
+                if let Some(store) = property_store.as_ref() {
+                    if extracted_effects.extract_shared_properties(
+                        store,
+                        &shared.block,
+                        &property_layout,
+                    ) {
+                        shared_properties = Some(ExtractedSharedProperties {
+                            block: shared.block.clone(),
+                            force_upload: shared.is_changed(),
+                        });
+                    }
+                }
             }
+            None
+        } else if let Some(properties) = maybe_properties {
+            // Only re-upload the property block if any property changed
+            EffectProperties::serialize_if_changed(&properties, &property_layout)
         } else {
             None
         };
@@ -1508,6 +1782,17 @@ pub(crate) fn extract_effects(
         let layout_flags = effect.layout_flags;
         let alpha_mode = effect.alpha_mode;
 
+        let transform = transform.compute_matrix();
+        let emitter_motion = if asset.simulation_space == SimulationSpace::Global
+            && asset.simulation_space_blend > 0.
+        {
+            prev_effects
+                .get(&entity)
+                .map_or(Mat4::IDENTITY, |prev| transform * prev.inverse_transform)
+        } else {
+            Mat4::IDENTITY
+        };
+
         trace!(
             "Extracted instance of effect '{}' on entity {:?}: texture_layout_count={} texture_count={} layout_flags={:?}",
             asset.name,
@@ -1524,11 +1809,15 @@ pub(crate) fn extract_effects(
                 particle_layout: asset.particle_layout().clone(),
                 property_layout,
                 property_data,
+                shared_properties,
                 initializers: initializers.0.clone(),
-                transform: transform.compute_matrix(),
+                transform,
                 // TODO - more efficient/correct way than inverse()?
-                inverse_transform: transform.compute_matrix().inverse(),
+                inverse_transform: transform.inverse(),
+                gravity: maybe_gravity.map_or(Vec3::ZERO, |gravity| gravity.0),
+                emitter_motion,
                 layout_flags,
+                secondary_layout_flags: effect.secondary_layout_flags,
                 texture_layout,
                 textures: effect.textures.clone(),
                 alpha_mode,
@@ -1653,6 +1942,110 @@ struct CacheEntry {
     cache_id: EffectCacheId,
 }
 
+/// GPU particle storage of a single simulation-only effect.
+///
+/// See [`SimulationOnlyBuffers`].
+#[derive(Debug, Clone)]
+pub struct SimulationOnlyBuffer {
+    /// GPU buffer storing the particles. This buffer may be shared with other
+    /// effects, so only the [`slices`] of it belong to this effect.
+    ///
+    /// [`slices`]: SimulationOnlyBuffer::slices
+    pub buffer: Buffer,
+    /// Range of particles in [`buffer`] for each particle group of the effect,
+    /// in number of particles.
+    ///
+    /// [`buffer`]: SimulationOnlyBuffer::buffer
+    pub slices: Vec<Range<u32>>,
+    /// Layout of a single particle in the buffer.
+    pub particle_layout: ParticleLayout,
+}
+
+impl SimulationOnlyBuffer {
+    /// Range in bytes of the particles of the given group in [`buffer`].
+    ///
+    /// [`buffer`]: SimulationOnlyBuffer::buffer
+    pub fn byte_range(&self, group_index: usize) -> Option<Range<u64>> {
+        let stride = self.particle_layout.min_binding_size().get();
+        self.slices
+            .get(group_index)
+            .map(|range| range.start as u64 * stride..range.end as u64 * stride)
+    }
+}
+
+/// Render world resource exposing the GPU particle buffers of all
+/// simulation-only effects.
+///
+/// An effect is simulation-only if its asset was created with
+/// [`EffectAsset::with_simulation_only()`]. Such effects are never drawn, and
+/// instead act as GPU data producers. This resource is rebuilt each frame in
+/// [`EffectSystems::PrepareEffectGpuResources`], and allows other render
+/// systems and nodes to bind the particle data, keyed by the main world entity
+/// holding the [`ParticleEffect`] component.
+///
+/// [`EffectAsset::with_simulation_only()`]: crate::EffectAsset::with_simulation_only
+/// [`EffectSystems::PrepareEffectGpuResources`]: crate::EffectSystems::PrepareEffectGpuResources
+/// [`ParticleEffect`]: crate::ParticleEffect
+#[derive(Debug, Default, Resource)]
+pub struct SimulationOnlyBuffers {
+    buffers: HashMap<Entity, SimulationOnlyBuffer>,
+}
+
+impl SimulationOnlyBuffers {
+    /// Get the particle buffer of the simulation-only effect on the given
+    /// entity, if any.
+    pub fn get(&self, entity: Entity) -> Option<&SimulationOnlyBuffer> {
+        self.buffers.get(&entity)
+    }
+
+    /// Iterate over all simulation-only effects and their particle buffer.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &SimulationOnlyBuffer)> {
+        self.buffers
+            .iter()
+            .map(|(entity, buffer)| (*entity, buffer))
+    }
+
+    /// Number of simulation-only effects this frame.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Check if there's no simulation-only effect this frame.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+/// Number of [`EffectDrawBatch`] to spawn for an effect with the given layout
+/// flags and number of groups. Simulation-only effects are never drawn.
+fn draw_batch_count(layout_flags: LayoutFlags, group_count: u32) -> u32 {
+    if layout_flags.contains(LayoutFlags::SIMULATION_ONLY) {
+        0
+    } else {
+        group_count
+    }
+}
+
+/// Group index and render layer of each [`EffectDrawBatch`] to spawn for an
+/// effect with the given layout flags and number of groups.
+///
+/// Each group is drawn once per render layer. The secondary render layer, if
+/// any, is drawn after the primary one, from the same slice of particles.
+fn draw_batches(
+    layout_flags: LayoutFlags,
+    group_count: u32,
+    has_secondary_layer: bool,
+) -> Vec<(u32, EffectRenderLayer)> {
+    let layers: &[EffectRenderLayer] = if has_secondary_layer {
+        &[EffectRenderLayer::Primary, EffectRenderLayer::Secondary]
+    } else {
+        &[EffectRenderLayer::Primary]
+    };
+    (0..draw_batch_count(layout_flags, group_count))
+        .flat_map(|group_index| layers.iter().map(move |&layer| (group_index, layer)))
+        .collect()
+}
+
 /// Global resource containing the GPU data to draw all the particle effects in
 /// all views.
 ///
@@ -1902,6 +2295,7 @@ impl EffectsMeta {
                 &added_effect.particle_layout,
                 &added_effect.property_layout,
                 added_effect.layout_flags,
+                &added_effect.point_cloud,
                 dispatch_buffer_indices,
                 added_effect.group_order,
             );
@@ -1987,6 +2381,18 @@ bitflags! {
         const NEEDS_UV = (1 << 5);
         /// The effect has ribbons.
         const RIBBONS = (1 << 6);
+        /// The effect needs the world-space position and normal of its fragments.
+        const NEEDS_WORLD_NORMAL = (1 << 7);
+        /// The effect cross-fades between consecutive flipbook sprites, based on the [`Attribute::SPRITE_BLEND`] of each particle.
+        const FLIPBOOK_BLEND = (1 << 8);
+        /// The effect is only simulated, and never rendered.
+        const SIMULATION_ONLY = (1 << 9);
+        /// The effect double-buffers its particles, the update pass reading last frame's particle buffer and writing into a second one.
+        const DOUBLE_BUFFERED = (1 << 11);
+        /// The effect premultiplies the color of its fragments by their alpha, and is blended with premultiplied alpha.
+        const PREMULTIPLY_ALPHA = (1 << 10);
+        /// The effect samples the position of its spawned particles from a point cloud storage buffer.
+        const POINT_CLOUD = (1 << 12);
     }
 }
 
@@ -2012,9 +2418,12 @@ pub(crate) fn prepare_effects(
     mut effect_cache: ResMut<EffectCache>,
     mut extracted_effects: ResMut<ExtractedEffects>,
     mut effect_bind_groups: ResMut<EffectBindGroups>,
+    mut simulation_only_buffers: ResMut<SimulationOnlyBuffers>,
 ) {
     trace!("prepare_effects");
 
+    simulation_only_buffers.buffers.clear();
+
     // Allocate spawner buffer if needed
     // if effects_meta.spawner_buffer.is_empty() {
     //    effects_meta.spawner_buffer.push(GpuSpawnerParams::default());
@@ -2063,6 +2472,9 @@ pub(crate) fn prepare_effects(
     // Build batcher inputs from extracted effects
     let effects = std::mem::take(&mut extracted_effects.effects);
 
+    // Shared property blocks are uploaded once per property buffer
+    let mut shared_uploads = HashSet::new();
+
     let effect_entity_list = effects
         .into_iter()
         .map(|(entity, extracted_effect)| {
@@ -2070,6 +2482,15 @@ pub(crate) fn prepare_effects(
             let property_buffer = effect_cache.get_property_buffer(id).cloned(); // clone handle for lifetime
             let effect_slices = effect_cache.get_slices(id);
             let group_order = effect_cache.get_group_order(id);
+            let property_data = if let Some(shared) = &extracted_effect.shared_properties {
+                extracted_effects.shared_property_upload(
+                    shared,
+                    effect_slices.buffer_index,
+                    &mut shared_uploads,
+                )
+            } else {
+                extracted_effect.property_data
+            };
 
             BatchesInput {
                 handle: extracted_effect.handle,
@@ -2078,15 +2499,18 @@ pub(crate) fn prepare_effects(
                 property_layout: extracted_effect.property_layout.clone(),
                 effect_shaders: extracted_effect.effect_shaders.clone(),
                 layout_flags: extracted_effect.layout_flags,
+                secondary_layout_flags: extracted_effect.secondary_layout_flags,
                 texture_layout: extracted_effect.texture_layout.clone(),
                 textures: extracted_effect.textures.clone(),
                 alpha_mode: extracted_effect.alpha_mode,
                 transform: extracted_effect.transform.into(),
                 inverse_transform: extracted_effect.inverse_transform.into(),
+                gravity: extracted_effect.gravity,
+                emitter_motion: extracted_effect.emitter_motion.into(),
                 particle_layout: extracted_effect.particle_layout.clone(),
                 property_buffer,
                 group_order: group_order.to_vec(),
-                property_data: extracted_effect.property_data,
+                property_data,
                 initializers: extracted_effect.initializers,
                 #[cfg(feature = "2d")]
                 z_sort_key_2d: extracted_effect.z_sort_key_2d,
@@ -2108,6 +2532,7 @@ pub(crate) fn prepare_effects(
     effects_meta.spawner_buffer.clear();
     effects_meta.particle_group_buffer.clear();
     let mut total_group_count = 0;
+    let mut swapped_buffers = HashSet::new();
     for (effect_index, input) in effect_entity_list.into_iter().enumerate() {
         let particle_layout_min_binding_size =
             input.effect_slices.particle_layout.min_binding_size();
@@ -2117,6 +2542,20 @@ pub(crate) fn prepare_effects(
             Some(input.property_layout.min_binding_size())
         };
 
+        // Double-buffered effects update from the particle buffer written during the
+        // previous frame into the other one, so swap them once per simulated frame.
+        // Effects not extracted this frame are not simulated, and keep their buffers.
+        let buffer_index = input.effect_slices.buffer_index;
+        if input.layout_flags.contains(LayoutFlags::DOUBLE_BUFFERED)
+            && swapped_buffers.insert(buffer_index)
+        {
+            if let Some(Some(effect_buffer)) =
+                effect_cache.buffers_mut().get_mut(buffer_index as usize)
+            {
+                effect_buffer.swap_particle_buffers();
+            }
+        }
+
         // Create init pipeline key flags.
         let mut init_pipeline_key_flags = ParticleInitPipelineKeyFlags::empty();
         init_pipeline_key_flags.set(
@@ -2127,6 +2566,13 @@ pub(crate) fn prepare_effects(
             ParticleInitPipelineKeyFlags::ATTRIBUTE_NEXT,
             input.particle_layout.contains(Attribute::NEXT),
         );
+        let double_buffered = input.layout_flags.contains(LayoutFlags::DOUBLE_BUFFERED);
+        init_pipeline_key_flags.set(
+            ParticleInitPipelineKeyFlags::DOUBLE_BUFFERED,
+            double_buffered,
+        );
+        let point_cloud = input.layout_flags.contains(LayoutFlags::POINT_CLOUD);
+        init_pipeline_key_flags.set(ParticleInitPipelineKeyFlags::POINT_CLOUD, point_cloud);
 
         // Specialize the init pipeline based on the effect.
         let init_and_update_pipeline_ids = input
@@ -2167,6 +2613,8 @@ pub(crate) fn prepare_effects(
                             input.initializers[group_index],
                             EffectInitializer::Cloner(_)
                         ),
+                        double_buffered,
+                        point_cloud,
                     },
                 );
                 trace!("Update pipeline specialized: id={:?}", update_pipeline_id);
@@ -2231,8 +2679,11 @@ pub(crate) fn prepare_effects(
                         // but the group_index is the index of the particle buffer, which can
                         // in theory (with batching) contain > 1 effect per buffer.
                         effect_index: input.effect_slices.buffer_index,
+                        gravity: input.gravity,
                         lifetime: 0.0,
-                        pad: Default::default(),
+                        emitter_motion: input.emitter_motion,
+                        spawn_index: effect_spawner.spawn_index(),
+                        padding: [0; 3],
                     };
                     trace!("spawner params = {:?}", spawner_params);
                     effects_meta.spawner_buffer.push(spawner_params);
@@ -2250,8 +2701,11 @@ pub(crate) fn prepare_effects(
                         // but the group_index is the index of the particle buffer, which can
                         // in theory (with batching) contain > 1 effect per buffer.
                         effect_index: input.effect_slices.buffer_index,
+                        gravity: input.gravity,
                         lifetime: effect_cloner.cloner.lifetime,
-                        pad: Default::default(),
+                        emitter_motion: input.emitter_motion,
+                        spawn_index: 0,
+                        padding: [0; 3],
                     };
                     trace!("cloner params = {:?}", spawner_params);
                     effects_meta.spawner_buffer.push(spawner_params);
@@ -2302,6 +2756,12 @@ pub(crate) fn prepare_effects(
         #[cfg(feature = "3d")]
         let translation_3d = input.transform.translation();
 
+        let layout_flags = input.layout_flags;
+        let has_secondary_layer = input.secondary_layout_flags.is_some();
+        let simulation_only = layout_flags
+            .contains(LayoutFlags::SIMULATION_ONLY)
+            .then(|| (input.entity, input.effect_slices.clone()));
+
         // Spawn one shared EffectBatches for all groups of this effect. This contains
         // most of the data needed to drive rendering, except the per-group data.
         // However this doesn't drive rendering; this is just storage.
@@ -2315,13 +2775,36 @@ pub(crate) fn prepare_effects(
         );
         let batches_entity = commands.spawn(batches).id();
 
-        // Spawn one EffectDrawBatch per group, to actually drive rendering. Each group
-        // renders with a different indirect call. These are the entities that the
-        // render phase items will receive.
-        for group_index in 0..local_group_count {
+        // Simulation-only effects don't draw anything, but expose their particles to
+        // other render systems instead.
+        if let Some((entity, effect_slices)) = simulation_only {
+            if let Some(Some(effect_buffer)) = effect_cache.buffers().get(buffer_index as usize) {
+                simulation_only_buffers.buffers.insert(
+                    entity,
+                    SimulationOnlyBuffer {
+                        buffer: effect_buffer.particle_buffer().clone(),
+                        slices: effect_slices
+                            .slices
+                            .windows(2)
+                            .map(|range| range[0]..range[1])
+                            .collect(),
+                        particle_layout: effect_slices.particle_layout,
+                    },
+                );
+            }
+        }
+
+        // Spawn one EffectDrawBatch per group and render layer, to actually drive
+        // rendering. Each group renders with a different indirect call, and each render
+        // layer of a group re-draws the same particles with its own render pipeline.
+        // These are the entities that the render phase items will receive.
+        for (group_index, layer) in
+            draw_batches(layout_flags, local_group_count, has_secondary_layer)
+        {
             commands.spawn(EffectDrawBatch {
                 batches_entity,
                 group_index,
+                layer,
                 #[cfg(feature = "2d")]
                 z_sort_key_2d,
                 #[cfg(feature = "3d")]
@@ -2389,7 +2872,13 @@ pub(crate) struct BufferBindGroups {
     /// @binding(3) var<storage, read> spawner : Spawner;
     /// #endif
     /// ```
-    render: BindGroup,
+    ///
+    /// For double-buffered effects, this contains one bind group per particle
+    /// buffer, indexed like [`EffectBuffer::particle_buffer_index()`].
+    render: Vec<BindGroup>,
+    /// Index of the bind group in `render` binding the particle buffer written
+    /// by the update pass this frame.
+    particle_buffer_index: usize,
 }
 
 /// Combination of a texture layout and the bound textures.
@@ -2446,7 +2935,7 @@ impl EffectBindGroups {
     pub fn particle_render(&self, buffer_index: u32) -> Option<&BindGroup> {
         self.particle_buffers
             .get(&buffer_index)
-            .map(|bg| &bg.render)
+            .map(|bg| &bg.render[bg.particle_buffer_index])
     }
 }
 
@@ -2557,13 +3046,17 @@ fn emit_sorted_draw<T, F>(
             // FIXME - We draw the entire batch, but part of it may not be visible in this
             // view! We should re-batch for the current view specifically!
 
-            let local_space_simulation = batches
-                .layout_flags
-                .contains(LayoutFlags::LOCAL_SPACE_SIMULATION);
-            let use_alpha_mask = batches.layout_flags.contains(LayoutFlags::USE_ALPHA_MASK);
-            let flipbook = batches.layout_flags.contains(LayoutFlags::FLIPBOOK);
-            let needs_uv = batches.layout_flags.contains(LayoutFlags::NEEDS_UV);
-            let ribbons = batches.layout_flags.contains(LayoutFlags::RIBBONS);
+            // Each render layer has its own render flags, which only differ by the flags
+            // derived from the render modifiers of that layer.
+            let layout_flags = batches.layer_layout_flags(draw_batch.layer);
+            let local_space_simulation = layout_flags.contains(LayoutFlags::LOCAL_SPACE_SIMULATION);
+            let use_alpha_mask = layout_flags.contains(LayoutFlags::USE_ALPHA_MASK);
+            let flipbook = layout_flags.contains(LayoutFlags::FLIPBOOK);
+            let flipbook_blend = layout_flags.contains(LayoutFlags::FLIPBOOK_BLEND);
+            let premultiply_alpha = layout_flags.contains(LayoutFlags::PREMULTIPLY_ALPHA);
+            let needs_uv = layout_flags.contains(LayoutFlags::NEEDS_UV);
+            let needs_world_normal = layout_flags.contains(LayoutFlags::NEEDS_WORLD_NORMAL);
+            let ribbons = layout_flags.contains(LayoutFlags::RIBBONS);
             let image_count = batches.texture_layout.layout.len() as u8;
 
             // Specialize the render pipeline based on the effect batch
@@ -2578,8 +3071,13 @@ fn emit_sorted_draw<T, F>(
 
             // Add a draw pass for the effect batch
             trace!("Emitting individual draws for batches and groups: group_batches.len()={} batches.render_shaders.len()={}", batches.group_batches.len(), batches.render_shaders.len());
-            let render_shader_source = &batches.render_shaders[draw_batch.group_index as usize];
-            trace!("Emit for group index #{}", draw_batch.group_index);
+            let render_shader_source =
+                batches.layer_render_shader(draw_batch.group_index, draw_batch.layer);
+            trace!(
+                "Emit for group index #{} layer {:?}",
+                draw_batch.group_index,
+                draw_batch.layer
+            );
 
             let alpha_mode = batches.alpha_mode;
 
@@ -2595,8 +3093,11 @@ fn emit_sorted_draw<T, F>(
                     local_space_simulation,
                     use_alpha_mask,
                     alpha_mode,
+                    premultiply_alpha,
                     flipbook,
+                    flipbook_blend,
                     needs_uv,
+                    needs_world_normal,
                     ribbons,
                     #[cfg(all(feature = "2d", feature = "3d"))]
                     pipeline_mode,
@@ -2733,13 +3234,17 @@ fn emit_binned_draw<T, F>(
             // FIXME - We draw the entire batch, but part of it may not be visible in this
             // view! We should re-batch for the current view specifically!
 
-            let local_space_simulation = batches
-                .layout_flags
-                .contains(LayoutFlags::LOCAL_SPACE_SIMULATION);
-            let use_alpha_mask = batches.layout_flags.contains(LayoutFlags::USE_ALPHA_MASK);
-            let flipbook = batches.layout_flags.contains(LayoutFlags::FLIPBOOK);
-            let needs_uv = batches.layout_flags.contains(LayoutFlags::NEEDS_UV);
-            let ribbons = batches.layout_flags.contains(LayoutFlags::RIBBONS);
+            // Each render layer has its own render flags, which only differ by the flags
+            // derived from the render modifiers of that layer.
+            let layout_flags = batches.layer_layout_flags(draw_batch.layer);
+            let local_space_simulation = layout_flags.contains(LayoutFlags::LOCAL_SPACE_SIMULATION);
+            let use_alpha_mask = layout_flags.contains(LayoutFlags::USE_ALPHA_MASK);
+            let flipbook = layout_flags.contains(LayoutFlags::FLIPBOOK);
+            let flipbook_blend = layout_flags.contains(LayoutFlags::FLIPBOOK_BLEND);
+            let premultiply_alpha = layout_flags.contains(LayoutFlags::PREMULTIPLY_ALPHA);
+            let needs_uv = layout_flags.contains(LayoutFlags::NEEDS_UV);
+            let needs_world_normal = layout_flags.contains(LayoutFlags::NEEDS_WORLD_NORMAL);
+            let ribbons = layout_flags.contains(LayoutFlags::RIBBONS);
             let image_count = batches.texture_layout.layout.len() as u8;
 
             // Specialize the render pipeline based on the effect batch
@@ -2754,8 +3259,13 @@ fn emit_binned_draw<T, F>(
 
             // Add a draw pass for the effect batch
             trace!("Emitting individual draws for batches and groups: group_batches.len()={} batches.render_shaders.len()={}", batches.group_batches.len(), batches.render_shaders.len());
-            let render_shader_source = &batches.render_shaders[draw_batch.group_index as usize];
-            trace!("Emit for group index #{}", draw_batch.group_index);
+            let render_shader_source =
+                batches.layer_render_shader(draw_batch.group_index, draw_batch.layer);
+            trace!(
+                "Emit for group index #{} layer {:?}",
+                draw_batch.group_index,
+                draw_batch.layer
+            );
 
             let alpha_mode = batches.alpha_mode;
 
@@ -2771,8 +3281,11 @@ fn emit_binned_draw<T, F>(
                     local_space_simulation,
                     use_alpha_mask,
                     alpha_mode,
+                    premultiply_alpha,
                     flipbook,
+                    flipbook_blend,
                     needs_uv,
+                    needs_world_normal,
                     ribbons,
                     #[cfg(all(feature = "2d", feature = "3d"))]
                     pipeline_mode,
@@ -3216,7 +3729,7 @@ pub(crate) fn prepare_bind_groups(
         // group, since the update phase runs on an entire group/buffer at once,
         // with all the effect instances in it batched together.
         trace!("effect particle buffer_index=#{}", buffer_index);
-        effect_bind_groups
+        let buffer_bind_groups = effect_bind_groups
             .particle_buffers
             .entry(buffer_index as u32)
             .or_insert_with(|| {
@@ -3230,45 +3743,54 @@ pub(crate) fn prepare_bind_groups(
                 let dispatch_indirect_size = GpuDispatchIndirect::aligned_size(render_device
                     .limits()
                     .min_storage_buffer_offset_alignment);
-                let mut entries = vec![
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: buffer.max_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: buffer.indirect_max_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 2,
-                        resource: BindingResource::Buffer(BufferBinding {
-                            buffer: &indirect_buffer,
-                            offset: 0,
-                            size: Some(dispatch_indirect_size),
-                        }),
-                    },
-                ];
-                if buffer.layout_flags().contains(LayoutFlags::LOCAL_SPACE_SIMULATION) {
-                    entries.push(BindGroupEntry {
-                        binding: 3,
-                        resource: BindingResource::Buffer(BufferBinding {
-                            buffer: &spawner_buffer,
-                            offset: 0,
-                            size: Some(GpuSpawnerParams::min_size()),
-                        }),
-                    });
-                }
-                trace!("Creating render bind group with {} entries (layout flags: {:?})", entries.len(), buffer.layout_flags());
-                let render = render_device.create_bind_group(
-                    &format!("hanabi:bind_group_render_vfx{buffer_index}_particles")[..],
-                     buffer.particle_layout_bind_group_with_dispatch(),
-                     &entries,
-                );
+                let render = buffer
+                    .max_bindings()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, particle_binding)| {
+                        let mut entries = vec![
+                            BindGroupEntry {
+                                binding: 0,
+                                resource: particle_binding,
+                            },
+                            BindGroupEntry {
+                                binding: 1,
+                                resource: buffer.indirect_max_binding(),
+                            },
+                            BindGroupEntry {
+                                binding: 2,
+                                resource: BindingResource::Buffer(BufferBinding {
+                                    buffer: &indirect_buffer,
+                                    offset: 0,
+                                    size: Some(dispatch_indirect_size),
+                                }),
+                            },
+                        ];
+                        if buffer.layout_flags().contains(LayoutFlags::LOCAL_SPACE_SIMULATION) {
+                            entries.push(BindGroupEntry {
+                                binding: 3,
+                                resource: BindingResource::Buffer(BufferBinding {
+                                    buffer: &spawner_buffer,
+                                    offset: 0,
+                                    size: Some(GpuSpawnerParams::min_size()),
+                                }),
+                            });
+                        }
+                        trace!("Creating render bind group with {} entries (layout flags: {:?})", entries.len(), buffer.layout_flags());
+                        render_device.create_bind_group(
+                            &format!("hanabi:bind_group_render_vfx{buffer_index}_particles{index}")[..],
+                            buffer.particle_layout_bind_group_with_dispatch(),
+                            &entries,
+                        )
+                    })
+                    .collect();
 
                 BufferBindGroups {
                     render,
+                    particle_buffer_index: 0,
                 }
             });
+        buffer_bind_groups.particle_buffer_index = buffer.particle_buffer_index();
     }
 
     // Create the per-effect bind groups.
@@ -3610,6 +4132,8 @@ fn create_init_particles_bind_group_layout(
     label: &str,
     particle_layout_min_binding_size: NonZero<u64>,
     property_layout_min_binding_size: Option<NonZero<u64>>,
+    double_buffered: bool,
+    point_cloud: bool,
 ) -> BindGroupLayout {
     let mut entries = Vec::with_capacity(3);
     // (1,0) ParticleBuffer
@@ -3660,6 +4184,17 @@ fn create_init_particles_bind_group_layout(
             count: None,
         });
     }
+    if double_buffered {
+        // (1,4) ParticleBuffer read by the update pass, where new particles are written
+        entries.push(double_buffered_layout_entry(
+            particle_layout_min_binding_size,
+        ));
+    }
+
+    if point_cloud {
+        // @binding(5) var<storage, read> point_cloud : array<vec4<f32>>
+        entries.push(point_cloud_layout_entry());
+    }
 
     trace!(
         "Creating particle bind group layout '{}' for init pass with {} entries.",
@@ -3726,6 +4261,8 @@ fn create_update_bind_group_layout(
     label: &str,
     particle_layout_min_binding_size: NonZero<u64>,
     property_layout_min_binding_size: Option<NonZero<u64>>,
+    double_buffered: bool,
+    point_cloud: bool,
 ) -> BindGroupLayout {
     let particle_group_size =
         GpuParticleGroup::aligned_size(render_device.limits().min_storage_buffer_offset_alignment);
@@ -3777,6 +4314,17 @@ fn create_update_bind_group_layout(
             count: None,
         });
     }
+    if double_buffered {
+        // @binding(4) var<storage, read_write> prev_particle_buffer : ParticleBuffer
+        entries.push(double_buffered_layout_entry(
+            particle_layout_min_binding_size,
+        ));
+    }
+
+    if point_cloud {
+        // @binding(5) var<storage, read> point_cloud : array<vec4<f32>>
+        entries.push(point_cloud_layout_entry());
+    }
 
     trace!(
         "Creating particle bind group layout '{}' for update pass with {} entries.",
@@ -3786,6 +4334,38 @@ fn create_update_bind_group_layout(
     render_device.create_bind_group_layout(label, &entries)
 }
 
+/// Layout entry of the particle buffer read by the update pass of
+/// double-buffered effects.
+///
+/// This must match the layout created by [`EffectBuffer::new()`].
+fn double_buffered_layout_entry(
+    particle_layout_min_binding_size: NonZero<u64>,
+) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: 4,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: Some(particle_layout_min_binding_size),
+        },
+        count: None,
+    }
+}
+
+fn point_cloud_layout_entry() -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: 5,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: BufferSize::new(16),
+        },
+        count: None,
+    }
+}
+
 /// Render node to run the simulation sub-graph once per frame.
 ///
 /// This node doesn't simulate anything by itself, but instead schedules the
@@ -3818,8 +4398,22 @@ impl Node for VfxSimulateDriverNode {
 /// Render node to run the simulation of all effects once per frame.
 ///
 /// Runs inside the simulation sub-graph, looping over all extracted effect
-/// batches to simulate them.
-pub(crate) struct VfxSimulateNode {
+/// batches to simulate them with the init and update compute passes. The node
+/// is added by the [`HanabiPlugin`] to the [`HanabiSimulateGraph`] sub-graph,
+/// under the [`HanabiSimulateNode`] label.
+///
+/// The node doesn't have any input or output slot. Once it ran, the GPU
+/// particle buffers of all effects contain the particles simulated this frame.
+/// For simulation-only effects, those buffers are exposed by the
+/// [`SimulationOnlyBuffers`] resource of the render world, which custom nodes
+/// scheduled after this node can read from. See the [`simulate_graph`] module
+/// for an example.
+///
+/// [`HanabiPlugin`]: crate::HanabiPlugin
+/// [`HanabiSimulateGraph`]: crate::simulate_graph::HanabiSimulateGraph
+/// [`HanabiSimulateNode`]: crate::simulate_graph::node::HanabiSimulateNode
+/// [`simulate_graph`]: crate::simulate_graph
+pub struct VfxSimulateNode {
     /// Query to retrieve the batches of effects to simulate and render.
     effect_query: QueryState<(Entity, Read<EffectBatches>)>,
 }
@@ -4330,6 +4924,24 @@ impl Node for VfxSimulateNode {
             }
         }
 
+        // Select the brightest particles of the effects emitting lights, once all
+        // groups were updated
+        #[cfg(feature = "lights")]
+        if let (Some(lights_meta), Some(lights_pipeline)) = (
+            world.get_resource::<ParticleLightsMeta>(),
+            world.get_resource::<ParticleLightsPipeline>(),
+        ) {
+            lights_meta.dispatch(render_context.command_encoder(), lights_pipeline);
+        }
+
+        // Read back the number of particles alive once all groups were updated
+        if let (Some(readback), Some(buffer)) = (
+            world.get_resource::<AliveCountReadback>(),
+            effects_meta.render_group_dispatch_buffer.buffer(),
+        ) {
+            readback.copy(render_context.command_encoder(), buffer);
+        }
+
         Ok(())
     }
 }
@@ -4367,7 +4979,10 @@ where
 
 #[cfg(test)]
 mod tests {
+    use bevy::{ecs::system::RunSystemOnce, render::MainWorld};
+
     use super::*;
+    use crate::{EffectSpawner, Module, SetAttributeModifier, Spawner};
 
     #[test]
     fn layout_flags() {
@@ -4375,6 +4990,274 @@ mod tests {
         assert_eq!(flags, LayoutFlags::NONE);
     }
 
+    #[test]
+    fn sim_params_globals() {
+        let sim_params = SimParams {
+            globals: EffectGlobals::new(0.25, 0.8),
+            ..default()
+        };
+        let gpu_sim_params: GpuSimParams = sim_params.into();
+        assert_eq!(gpu_sim_params.time_of_day, 0.25);
+        assert_eq!(gpu_sim_params.weather, 0.8);
+
+        let gpu_sim_params = GpuSimParams::default();
+        assert_eq!(gpu_sim_params.time_of_day, 0.);
+        assert_eq!(gpu_sim_params.weather, 0.);
+    }
+
+    #[test]
+    fn spawner_params_gravity() {
+        // The layout must match the Spawner struct of vfx_common.wgsl, where the
+        // vec3<f32> gravity is aligned to 16 bytes, followed by the lifetime, the
+        // emitter motion, and the spawn index.
+        assert_eq!(GpuSpawnerParams::min_size().get(), 192);
+        assert_eq!(std::mem::size_of::<GpuSpawnerParams>(), 192);
+
+        let mut params = GpuSpawnerParams {
+            gravity: Vec3::new(0., -9.81, 0.),
+            lifetime: 2.,
+            ..default()
+        };
+        let bytes = bytemuck::bytes_of(&params);
+        assert_eq!(
+            &bytes[112..124],
+            bytemuck::bytes_of(&Vec3::new(0., -9.81, 0.))
+        );
+        assert_eq!(&bytes[124..128], 2_f32.to_ne_bytes());
+
+        let motion: GpuCompressedTransform = Mat4::from_translation(Vec3::X).into();
+        params.emitter_motion = motion;
+        let bytes = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[128..176], bytemuck::bytes_of(&motion));
+
+        params.spawn_index = 42;
+        let bytes = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[176..180], 42_u32.to_ne_bytes());
+
+        // Any new gravity value is uploaded as is
+        params.gravity = Vec3::ZERO;
+        let bytes = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[112..124], &[0; 12]);
+    }
+
+    #[test]
+    fn simulation_only_no_draw() {
+        assert_eq!(draw_batch_count(LayoutFlags::NONE, 3), 3);
+        assert_eq!(draw_batch_count(LayoutFlags::RIBBONS, 1), 1);
+        assert_eq!(draw_batch_count(LayoutFlags::SIMULATION_ONLY, 3), 0);
+        assert_eq!(
+            draw_batch_count(LayoutFlags::SIMULATION_ONLY | LayoutFlags::RIBBONS, 2),
+            0
+        );
+    }
+
+    #[test]
+    fn secondary_render_layer_draws() {
+        assert_eq!(
+            draw_batches(LayoutFlags::NONE, 2, false),
+            vec![
+                (0, EffectRenderLayer::Primary),
+                (1, EffectRenderLayer::Primary)
+            ]
+        );
+
+        // One simulated group with two render sets yields two draws of that same
+        // group, so of the same slice of the same particle buffer.
+        assert_eq!(
+            draw_batches(LayoutFlags::NONE, 1, true),
+            vec![
+                (0, EffectRenderLayer::Primary),
+                (0, EffectRenderLayer::Secondary)
+            ]
+        );
+        assert_eq!(
+            draw_batches(LayoutFlags::NONE, 2, true),
+            vec![
+                (0, EffectRenderLayer::Primary),
+                (0, EffectRenderLayer::Secondary),
+                (1, EffectRenderLayer::Primary),
+                (1, EffectRenderLayer::Secondary)
+            ]
+        );
+        assert!(draw_batches(LayoutFlags::SIMULATION_ONLY, 1, true).is_empty());
+    }
+
+    #[test]
+    fn premultiply_alpha_blend_state() {
+        let key = ParticleRenderPipelineKey {
+            alpha_mode: AlphaMode::Blend,
+            premultiply_alpha: true,
+            ..default()
+        };
+        assert_eq!(
+            key.blend_state(),
+            Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+        );
+        let key = ParticleRenderPipelineKey {
+            alpha_mode: AlphaMode::Blend,
+            premultiply_alpha: false,
+            ..default()
+        };
+        assert_eq!(key.blend_state(), Some(BlendState::ALPHA_BLENDING));
+    }
+
+    #[test]
+    fn alpha_to_coverage_multisampled_only() {
+        for msaa in [Msaa::Off, Msaa::Sample2, Msaa::Sample4, Msaa::Sample8] {
+            let key = ParticleRenderPipelineKey {
+                alpha_mode: AlphaMode::AlphaToCoverage,
+                msaa_samples: msaa.samples(),
+                ..default()
+            };
+            let multisampled = msaa.samples() > 1;
+            let multisample = key.multisample_state();
+            assert_eq!(multisample.count, msaa.samples());
+            assert_eq!(multisample.alpha_to_coverage_enabled, multisampled);
+            if multisampled {
+                assert!(key.blend_state().is_none());
+            } else {
+                // Fall back to regular alpha blending
+                assert_eq!(key.blend_state(), Some(BlendState::ALPHA_BLENDING));
+            }
+
+            // Other alpha modes never use alpha-to-coverage
+            let key = ParticleRenderPipelineKey {
+                alpha_mode: AlphaMode::Blend,
+                msaa_samples: msaa.samples(),
+                ..default()
+            };
+            assert!(!key.multisample_state().alpha_to_coverage_enabled);
+            assert_eq!(key.blend_state(), Some(BlendState::ALPHA_BLENDING));
+        }
+    }
+
+    #[test]
+    fn shared_properties_single_upload() {
+        let mut store = PropertyStore::default();
+        store.set("fountains", "strength", 0.5_f32.into());
+        let layout = PropertyLayout::new(&[crate::Property::new("strength", 0_f32)]);
+
+        let mut extracted_effects = ExtractedEffects::default();
+        let instances = [
+            ExtractedSharedProperties {
+                block: "fountains".to_string(),
+                force_upload: false,
+            },
+            ExtractedSharedProperties {
+                block: "fountains".to_string(),
+                force_upload: false,
+            },
+        ];
+
+        // Two instances in the same effect buffer extract a single block
+        for _ in &instances {
+            assert!(extracted_effects.extract_shared_properties(&store, "fountains", &layout));
+        }
+        assert_eq!(extracted_effects.shared_properties.len(), 1);
+        let shared = &extracted_effects.shared_properties["fountains"];
+        assert!(shared.dirty);
+        assert_eq!(shared.data, 0.5_f32.to_ne_bytes());
+
+        // ...and reference a single upload
+        let mut uploaded = HashSet::new();
+        let uploads = instances
+            .iter()
+            .filter_map(|shared| extracted_effects.shared_property_upload(shared, 0, &mut uploaded))
+            .collect::<Vec<_>>();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0], 0.5_f32.to_ne_bytes());
+
+        // Unknown block
+        assert!(!extracted_effects.extract_shared_properties(&store, "unknown", &layout));
+
+        // Next frame, unchanged: no upload
+        for shared in extracted_effects.shared_properties.values_mut() {
+            shared.dirty = false;
+        }
+        assert!(extracted_effects.extract_shared_properties(&store, "fountains", &layout));
+        let mut uploaded = HashSet::new();
+        assert!(extracted_effects
+            .shared_property_upload(&instances[0], 0, &mut uploaded)
+            .is_none());
+
+        // Changed: single re-upload
+        store.set("fountains", "strength", 0.7_f32.into());
+        for _ in &instances {
+            assert!(extracted_effects.extract_shared_properties(&store, "fountains", &layout));
+        }
+        let uploads = instances
+            .iter()
+            .filter_map(|shared| extracted_effects.shared_property_upload(shared, 0, &mut uploaded))
+            .collect::<Vec<_>>();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0], 0.7_f32.to_ne_bytes());
+    }
+
+    #[test]
+    fn empty_effect_skips_dispatch() {
+        let mut main_world = World::new();
+        main_world.init_resource::<Time<Real>>();
+        main_world.init_resource::<Time<Virtual>>();
+        main_world.init_resource::<Time<EffectSimulation>>();
+        main_world.init_resource::<Assets<EffectAsset>>();
+        main_world.init_resource::<Assets<Image>>();
+        main_world.init_resource::<Events<RemovedEffectsEvent>>();
+
+        let mut module = Module::default();
+        let lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(1.));
+        let asset = EffectAsset::new(32, Spawner::once(32.0.into(), true), module)
+            .init(lifetime)
+            .with_skip_when_empty(true);
+        let handle = main_world.resource_mut::<Assets<EffectAsset>>().add(asset);
+
+        // Finished spawning, and all particles died
+        let mut effect_spawner = EffectSpawner::new(&Spawner::once(32.0.into(), true));
+        effect_spawner.spawn_count = 0;
+        let mut initializers = EffectInitializers(vec![EffectInitializer::Spawner(effect_spawner)]);
+        initializers[0].set_alive_count(0, 0);
+        let entity = main_world
+            .spawn((
+                CompiledParticleEffect {
+                    asset: handle,
+                    effect_shaders: vec![EffectShader::default()],
+                    ..default()
+                },
+                initializers,
+                GlobalTransform::default(),
+            ))
+            .id();
+
+        let mut render_world = World::new();
+        render_world.init_resource::<MainWorld>();
+        std::mem::swap(
+            &mut **render_world.resource_mut::<MainWorld>(),
+            &mut main_world,
+        );
+        render_world.init_resource::<SimParams>();
+        render_world.init_resource::<ExtractedEffects>();
+
+        // Effects which are not extracted are never batched, so never dispatched
+        let is_extracted = |render_world: &mut World, alive_count: u32, spawn_count: u32| {
+            let mut main_world = render_world.resource_mut::<MainWorld>();
+            let mut initializers = main_world.get_mut::<EffectInitializers>(entity).unwrap();
+            initializers[0].set_alive_count(0, alive_count);
+            if let EffectInitializer::Spawner(effect_spawner) = &mut initializers[0] {
+                effect_spawner.spawn_count = spawn_count;
+            }
+            render_world.run_system_once(extract_effects);
+            render_world
+                .resource::<ExtractedEffects>()
+                .effects
+                .contains_key(&entity)
+        };
+        assert!(!is_extracted(&mut render_world, 0, 0));
+
+        // Particles still alive, or particles to spawn, wake the effect up
+        assert!(is_extracted(&mut render_world, 5, 0));
+        assert!(is_extracted(&mut render_world, 0, 10));
+        assert!(!is_extracted(&mut render_world, 0, 0));
+    }
+
     #[cfg(feature = "gpu_tests")]
     #[test]
     fn gpu_limits() {
@@ -0,0 +1,390 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{Receiver, Sender},
+    Arc, Mutex,
+};
+
+use bevy::{
+    log::trace,
+    prelude::*,
+    render::{
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Maintain, MapMode,
+        },
+        renderer::RenderDevice,
+    },
+};
+
+use super::{EffectCache, EffectsMeta, ExtractedEffects, GpuRenderGroupIndirect};
+use crate::EffectInitializers;
+
+/// Number of particles alive in the groups of an effect instance, read back
+/// from GPU.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AliveCountData {
+    /// Entity of the effect instance in the main world.
+    pub entity: Entity,
+    /// Spawn generation and number of particles alive of each group, in group
+    /// index order.
+    pub groups: Vec<(u32, u32)>,
+}
+
+/// Main world end of the alive count readback channel.
+#[derive(Resource)]
+pub(crate) struct AliveCountReceiver(pub Mutex<Receiver<AliveCountData>>);
+
+/// Render world end of the alive count readback channel.
+#[derive(Resource)]
+pub(crate) struct AliveCountSender(pub Sender<AliveCountData>);
+
+/// Effect instance whose alive counts are part of a readback.
+#[derive(Debug, Clone, PartialEq)]
+struct ReadbackEffect {
+    /// Entity of the effect instance in the main world.
+    entity: Entity,
+    /// Row of the first group of the effect in the render group dispatch
+    /// buffer.
+    first_row: u32,
+    /// Spawn generation of each group when the effect was extracted.
+    spawn_generations: Vec<u32>,
+}
+
+/// Staging buffer a GPU buffer is copied into, then mapped without blocking to
+/// read its content back on CPU.
+///
+/// Only one readback is in flight at any time; the next copy happens once the
+/// previous mapping completed, usually a few frames later.
+#[derive(Default)]
+pub(super) struct StagingBuffer {
+    /// Staging buffer the source buffer is copied into.
+    buffer: Option<Buffer>,
+    /// Number of bytes to copy this frame, or zero if no copy is needed.
+    copy_size: u64,
+    /// Whether the copy was recorded this frame.
+    copied: AtomicBool,
+    /// Result of the mapping in flight, set by the map callback, if any.
+    map_result: Option<Arc<Mutex<Option<bool>>>>,
+}
+
+impl StagingBuffer {
+    /// Check if a mapping is in flight.
+    ///
+    /// While mapping, the staging buffer keeps the size of the data copied into
+    /// it, and no new copy can be requested.
+    pub fn is_mapping(&self) -> bool {
+        self.map_result.is_some()
+    }
+
+    /// Request a copy of the given number of bytes this frame, allocating a
+    /// larger staging buffer as needed.
+    ///
+    /// This is a no-op if a mapping is in flight.
+    pub fn request(&mut self, render_device: &RenderDevice, size: u64, label: &'static str) {
+        if self.is_mapping() {
+            return;
+        }
+        let too_small = match &self.buffer {
+            Some(buffer) => buffer.size() < size,
+            None => true,
+        };
+        if too_small {
+            self.buffer = Some(render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        self.copy_size = size;
+    }
+
+    /// Cancel the copy requested this frame, if any.
+    ///
+    /// This is a no-op if a mapping is in flight.
+    pub fn cancel(&mut self) {
+        if !self.is_mapping() {
+            self.copy_size = 0;
+        }
+    }
+
+    /// Record the copy of the source buffer into the staging buffer, if
+    /// requested this frame.
+    pub fn copy(&self, encoder: &mut CommandEncoder, src: &Buffer) {
+        let Some(buffer) = &self.buffer else {
+            return;
+        };
+        if self.copy_size == 0 || self.is_mapping() {
+            return;
+        }
+        encoder.copy_buffer_to_buffer(src, 0, buffer, 0, self.copy_size);
+        self.copied.store(true, Ordering::Release);
+    }
+
+    /// Map the data copied this frame, and read the data of any mapping
+    /// completed since the previous frame.
+    ///
+    /// The mapping doesn't block; the GPU is polled once per frame until the
+    /// mapping completes. This returns `None` while no data is available, or
+    /// `Some(Err(()))` if the mapping failed.
+    pub fn poll<R>(
+        &mut self,
+        render_device: &RenderDevice,
+        read: impl FnOnce(&[u8]) -> R,
+    ) -> Option<Result<R, ()>> {
+        let buffer = self.buffer.as_ref()?;
+
+        if self.map_result.is_none() {
+            if !self.copied.swap(false, Ordering::Acquire) {
+                return None;
+            }
+            let map_result = Arc::new(Mutex::new(None));
+            let callback_result = map_result.clone();
+            render_device.map_buffer(
+                &buffer.slice(..self.copy_size),
+                MapMode::Read,
+                move |result| {
+                    *callback_result.lock().unwrap() = Some(result.is_ok());
+                },
+            );
+            self.map_result = Some(map_result);
+        }
+
+        render_device.poll(Maintain::Poll);
+        let mapped = (*self.map_result.as_ref().unwrap().lock().unwrap())?;
+        self.map_result = None;
+        if !mapped {
+            self.buffer = None;
+            return Some(Err(()));
+        }
+
+        let slice = buffer.slice(..self.copy_size);
+        let data = read(&slice.get_mapped_range());
+        buffer.unmap();
+        Some(Ok(data))
+    }
+}
+
+/// State of the readback of the number of particles alive in each group.
+///
+/// The render group dispatch buffer is copied into a staging buffer at the end
+/// of the simulation, then mapped without blocking.
+#[derive(Resource, Default)]
+pub(crate) struct AliveCountReadback {
+    /// Staging buffer the render group dispatch buffer is copied into.
+    staging: StagingBuffer,
+    /// Size in bytes of a row of the render group dispatch buffer.
+    row_size: u64,
+    /// Effects copied this frame, or being mapped.
+    effects: Vec<ReadbackEffect>,
+}
+
+impl AliveCountReadback {
+    /// Record the copy of the render group dispatch buffer into the staging
+    /// buffer, if requested this frame.
+    pub fn copy(&self, encoder: &mut CommandEncoder, src: &Buffer) {
+        trace!(
+            "Copying {} bytes of alive counts for readback",
+            self.staging.copy_size
+        );
+        self.staging.copy(encoder, src);
+    }
+}
+
+/// Decode the alive counts of the given effects from the content of the render
+/// group dispatch buffer.
+fn read_alive_counts(
+    data: &[u8],
+    row_size: usize,
+    effects: &[ReadbackEffect],
+) -> Vec<AliveCountData> {
+    let item_size = std::mem::size_of::<GpuRenderGroupIndirect>();
+    effects
+        .iter()
+        .map(|effect| AliveCountData {
+            entity: effect.entity,
+            groups: effect
+                .spawn_generations
+                .iter()
+                .enumerate()
+                .map(|(group_index, &spawn_generation)| {
+                    let offset = (effect.first_row as usize + group_index) * row_size;
+                    let row: GpuRenderGroupIndirect =
+                        bytemuck::pod_read_unaligned(&data[offset..offset + item_size]);
+                    (spawn_generation, row.alive_count)
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Prepare the readback of the alive counts of the effects extracted this
+/// frame.
+///
+/// This runs before [`prepare_effects()`], so effects added this frame are
+/// only read back from the next frame, once their rows are allocated.
+///
+/// [`prepare_effects()`]: crate::render::prepare_effects
+pub(crate) fn prepare_alive_count_readback(
+    render_device: Res<RenderDevice>,
+    effects_meta: Res<EffectsMeta>,
+    effect_cache: Res<EffectCache>,
+    extracted_effects: Res<ExtractedEffects>,
+    mut readback: ResMut<AliveCountReadback>,
+) {
+    trace!("prepare_alive_count_readback");
+
+    // Keep the size of the mapping in flight, if any, until it completes.
+    if readback.staging.is_mapping() {
+        return;
+    }
+    readback.staging.cancel();
+
+    readback.effects = extracted_effects
+        .effects
+        .iter()
+        .filter_map(|(&entity, extracted_effect)| {
+            let cache_id = effects_meta.entity_map.get(&entity)?.cache_id;
+            let first_row = effect_cache
+                .get_dispatch_buffer_indices(cache_id)
+                .first_render_group_dispatch_buffer_index
+                .0;
+            Some(ReadbackEffect {
+                entity,
+                first_row,
+                spawn_generations: extracted_effect
+                    .initializers
+                    .iter()
+                    .map(|initializer| initializer.spawn_generation())
+                    .collect(),
+            })
+        })
+        .collect();
+    let Some(row_count) = readback
+        .effects
+        .iter()
+        .map(|effect| effect.first_row + effect.spawn_generations.len() as u32)
+        .max()
+    else {
+        return;
+    };
+
+    readback.row_size = effects_meta.render_group_dispatch_buffer.aligned_size() as u64;
+    let size = row_count as u64 * readback.row_size;
+    readback
+        .staging
+        .request(&render_device, size, "hanabi:buffer:alive_count_readback");
+}
+
+/// Map the alive counts copied this frame, and send any readback completed
+/// since the previous frame to the main world.
+///
+/// The mapping doesn't block; the GPU is polled once per frame until the
+/// mapping completes.
+pub(crate) fn readback_alive_counts(
+    render_device: Res<RenderDevice>,
+    sender: Res<AliveCountSender>,
+    mut readback: ResMut<AliveCountReadback>,
+) {
+    trace!("readback_alive_counts");
+
+    let readback = &mut *readback;
+    let row_size = readback.row_size as usize;
+    let effects = &readback.effects;
+    let Some(result) = readback.staging.poll(&render_device, |data| {
+        read_alive_counts(data, row_size, effects)
+    }) else {
+        return;
+    };
+    let Ok(alive_counts) = result else {
+        error!("Failed to read back the number of particles alive.");
+        return;
+    };
+    for data in alive_counts {
+        let _ = sender.0.send(data);
+    }
+}
+
+/// Receive the alive counts read back from GPU, and store them into the
+/// [`EffectInitializers`] of each effect instance.
+///
+/// This system runs in the [`Last`] schedule, once any system possibly
+/// overriding the spawn count of the current frame ran. It first starts a new
+/// spawn generation for each initializer spawning some particles this frame,
+/// so that the alive counts of any readback simulated before that spawn are
+/// discarded.
+pub(crate) fn receive_alive_counts(
+    receiver: Res<AliveCountReceiver>,
+    mut query: Query<&mut EffectInitializers>,
+) {
+    trace!("receive_alive_counts");
+
+    for mut initializers in query.iter_mut() {
+        if initializers
+            .iter()
+            .any(|initializer| initializer.is_spawning())
+        {
+            for initializer in initializers.iter_mut() {
+                initializer.update_spawn_generation();
+            }
+        }
+    }
+
+    let receiver = receiver.0.lock().unwrap();
+    while let Ok(data) = receiver.try_recv() {
+        let Ok(mut initializers) = query.get_mut(data.entity) else {
+            continue;
+        };
+        for (initializer, (spawn_generation, alive_count)) in
+            initializers.iter_mut().zip(data.groups)
+        {
+            initializer.set_alive_count(spawn_generation, alive_count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_alive_counts() {
+        let row_size = 64;
+        let mut data = vec![0; row_size * 4];
+        for (row, alive_count) in [(1, 7), (2, 0), (3, 42)] {
+            let row_data = GpuRenderGroupIndirect {
+                alive_count,
+                dead_count: 100,
+                ..default()
+            };
+            data[row * row_size..][..std::mem::size_of::<GpuRenderGroupIndirect>()]
+                .copy_from_slice(bytemuck::bytes_of(&row_data));
+        }
+
+        let effects = [
+            ReadbackEffect {
+                entity: Entity::from_raw(3),
+                first_row: 1,
+                spawn_generations: vec![5, 2],
+            },
+            ReadbackEffect {
+                entity: Entity::from_raw(8),
+                first_row: 3,
+                spawn_generations: vec![1],
+            },
+        ];
+        let alive_counts = read_alive_counts(&data, row_size, &effects);
+        assert_eq!(
+            alive_counts,
+            vec![
+                AliveCountData {
+                    entity: Entity::from_raw(3),
+                    groups: vec![(5, 7), (2, 0)],
+                },
+                AliveCountData {
+                    entity: Entity::from_raw(8),
+                    groups: vec![(1, 42)],
+                },
+            ]
+        );
+    }
+}
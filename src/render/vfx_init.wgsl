@@ -1,6 +1,6 @@
 #import bevy_hanabi::vfx_common::{
     IndirectBuffer, ParticleGroup, RenderEffectMetadata, RenderGroupIndirect, SimParams, Spawner,
-    seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
+    seed, tau, pcg_hash, rng_hash, to_float01, frand, frand2, frand3, frand4,
     rand_uniform_f, rand_uniform_vec2, rand_uniform_vec3, rand_uniform_vec4,
     rand_normal_f, rand_normal_vec2, rand_normal_vec3, rand_normal_vec4, proj
 }
@@ -16,7 +16,12 @@ struct ParticleBuffer {
 {{PROPERTIES}}
 
 @group(0) @binding(0) var<uniform> sim_params: SimParams;
+#ifdef DOUBLE_BUFFERED
+// New particles are written into the buffer read by the update pass this frame
+@group(1) @binding(4) var<storage, read_write> particle_buffer: ParticleBuffer;
+#else
 @group(1) @binding(0) var<storage, read_write> particle_buffer: ParticleBuffer;
+#endif
 @group(1) @binding(1) var<storage, read_write> indirect_buffer: IndirectBuffer;
 @group(1) @binding(2) var<storage, read> particle_groups: array<ParticleGroup>;
 {{PROPERTIES_BINDING}}
@@ -69,7 +74,7 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let dest_dead_index = atomicSub(&dest_render_group_indirect.dead_count, 1u) - 1u;
     let dest_index = indirect_buffer.indices[3u * (dest_base_index + dest_dead_index) + 2u];
 
-    seed = pcg_hash(dest_index ^ spawner.seed);
+    seed = rng_hash(dest_index ^ spawner.seed);
 
 #ifdef CLONE
     var particle: Particle = particle_buffer.particles[src_index];
@@ -0,0 +1,164 @@
+// Maximum number of lights per particle group. Must match
+// `ParticleLights::MAX_LIGHTS`.
+const MAX_LIGHTS: u32 = 16u;
+const WORKGROUP_SIZE: u32 = 64u;
+// Offset of an attribute missing from the particle layout.
+const NO_ATTRIBUTE: u32 = 0xffffffffu;
+// Offset of `RenderGroupIndirect::instance_count`, the number of particles
+// alive after the update pass.
+const RGI_OFFSET_INSTANCE_COUNT: u32 = 1u;
+
+/// Parameters of the selection of the brightest particles of a single group.
+struct LightParams {
+    /// Transform from the simulation space of the particles to the local space
+    /// of the effect, as three row vectors. This is the identity for effects
+    /// simulated in local space.
+    x_row: vec4<f32>,
+    y_row: vec4<f32>,
+    z_row: vec4<f32>,
+    /// Size of a particle, in number of u32.
+    particle_stride: u32,
+    /// Offset of the position attribute in a particle, in number of u32.
+    position_offset: u32,
+    /// Offset of the color attribute, in number of u32, or NO_ATTRIBUTE.
+    color_offset: u32,
+    /// Offset of the HDR color attribute, in number of u32, or NO_ATTRIBUTE.
+    hdr_color_offset: u32,
+    /// Index of the first particle of the group in the indirect buffer.
+    base_index: u32,
+    /// Index of the u32 holding the effect ping index.
+    render_effect_indirect: u32,
+    /// Index of the first u32 of the RenderGroupIndirect of the group.
+    render_group_indirect: u32,
+    /// Number of lights to select, at most MAX_LIGHTS.
+    light_count: u32,
+    /// Index of the first output sample of the group.
+    output_index: u32,
+}
+
+struct LightSample {
+    /// Position of the particle, in the local space of the effect. The w
+    /// component is unused.
+    position: vec4<f32>,
+    /// HDR color of the particle, or zero if the sample is unused.
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<storage, read> particle_buffer : array<u32>;
+@group(0) @binding(1) var<storage, read> indirect_buffer : array<u32>;
+@group(0) @binding(2) var<storage, read> render_effect_indirect_buffer : array<u32>;
+@group(0) @binding(3) var<storage, read> render_group_indirect_buffer : array<u32>;
+@group(0) @binding(4) var<storage, read> params : LightParams;
+@group(0) @binding(5) var<storage, read_write> light_samples : array<LightSample>;
+
+// Brightest particles of each thread, merged by the first thread.
+var<workgroup> candidate_brightness : array<f32, 1024>; // WORKGROUP_SIZE * MAX_LIGHTS
+var<workgroup> candidate_index : array<u32, 1024>;
+
+fn read_f32(offset: u32) -> f32 {
+    return bitcast<f32>(particle_buffer[offset]);
+}
+
+fn particle_position(index: u32) -> vec3<f32> {
+    let offset = index * params.particle_stride + params.position_offset;
+    return vec3<f32>(read_f32(offset), read_f32(offset + 1u), read_f32(offset + 2u));
+}
+
+fn particle_color(index: u32) -> vec4<f32> {
+    let base = index * params.particle_stride;
+    if (params.hdr_color_offset != NO_ATTRIBUTE) {
+        let offset = base + params.hdr_color_offset;
+        return vec4<f32>(
+            read_f32(offset),
+            read_f32(offset + 1u),
+            read_f32(offset + 2u),
+            read_f32(offset + 3u),
+        );
+    }
+    if (params.color_offset != NO_ATTRIBUTE) {
+        return unpack4x8unorm(particle_buffer[base + params.color_offset]);
+    }
+    return vec4<f32>(1.0);
+}
+
+/// Brightness of a color, as the largest of its RGB components multiplied by
+/// its alpha. Must match `ParticleLightSample::brightness()`.
+fn brightness(color: vec4<f32>) -> f32 {
+    return max(max(color.r, max(color.g, color.b)), 0.0) * max(color.a, 0.0);
+}
+
+/// Insert a particle into a list of the brightest particles, sorted in
+/// decreasing brightness order, if it's brighter than the last one. Particles
+/// not emitting any light are never inserted.
+fn insert_candidate(
+    best_brightness: ptr<function, array<f32, MAX_LIGHTS>>,
+    best_index: ptr<function, array<u32, MAX_LIGHTS>>,
+    light_count: u32,
+    particle_brightness: f32,
+    index: u32,
+) {
+    if (light_count == 0u || particle_brightness <= (*best_brightness)[light_count - 1u]) {
+        return;
+    }
+    var k = light_count - 1u;
+    while (k > 0u && (*best_brightness)[k - 1u] < particle_brightness) {
+        (*best_brightness)[k] = (*best_brightness)[k - 1u];
+        (*best_index)[k] = (*best_index)[k - 1u];
+        k -= 1u;
+    }
+    (*best_brightness)[k] = particle_brightness;
+    (*best_index)[k] = index;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(local_invocation_index) local_index: u32) {
+    let ping = render_effect_indirect_buffer[params.render_effect_indirect];
+    let alive_count = render_group_indirect_buffer[params.render_group_indirect + RGI_OFFSET_INSTANCE_COUNT];
+    let light_count = min(params.light_count, MAX_LIGHTS);
+
+    // Keep the brightest particles seen by this thread
+    var best_brightness: array<f32, MAX_LIGHTS>;
+    var best_index: array<u32, MAX_LIGHTS>;
+    for (var i = local_index; i < alive_count; i += WORKGROUP_SIZE) {
+        // The update pass wrote the indices of the alive particles into ping
+        let index = indirect_buffer[3u * (params.base_index + i) + ping];
+        let b = brightness(particle_color(index));
+        insert_candidate(&best_brightness, &best_index, light_count, b, index);
+    }
+
+    for (var k = 0u; k < MAX_LIGHTS; k += 1u) {
+        candidate_brightness[local_index * MAX_LIGHTS + k] = best_brightness[k];
+        candidate_index[local_index * MAX_LIGHTS + k] = best_index[k];
+    }
+    workgroupBarrier();
+
+    if (local_index != 0u) {
+        return;
+    }
+
+    // Merge the candidates of all threads. The first ones are already ours.
+    for (var c = MAX_LIGHTS; c < WORKGROUP_SIZE * MAX_LIGHTS; c += 1u) {
+        insert_candidate(
+            &best_brightness,
+            &best_index,
+            light_count,
+            candidate_brightness[c],
+            candidate_index[c],
+        );
+    }
+
+    for (var k = 0u; k < MAX_LIGHTS; k += 1u) {
+        var light_sample = LightSample(vec4<f32>(0.0), vec4<f32>(0.0));
+        if (k < light_count && best_brightness[k] > 0.0) {
+            let position = vec4<f32>(particle_position(best_index[k]), 1.0);
+            light_sample.position = vec4<f32>(
+                dot(params.x_row, position),
+                dot(params.y_row, position),
+                dot(params.z_row, position),
+                1.0,
+            );
+            light_sample.color = particle_color(best_index[k]);
+        }
+        light_samples[params.output_index + k] = light_sample;
+    }
+}
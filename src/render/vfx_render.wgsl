@@ -1,7 +1,7 @@
 #import bevy_render::view::View
 #import bevy_hanabi::vfx_common::{
     DispatchIndirect, IndirectBuffer, SimParams, Spawner,
-    seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
+    seed, tau, pcg_hash, rng_hash, to_float01, frand, frand2, frand3, frand4,
     rand_uniform_f, rand_uniform_vec2, rand_uniform_vec3, rand_uniform_vec4,
     rand_normal_f, rand_normal_vec2, rand_normal_vec3, rand_normal_vec4, proj
 }
@@ -20,6 +20,15 @@ struct VertexOutput {
 #ifdef NEEDS_UV
     @location(1) uv: vec2<f32>,
 #endif
+#ifdef NEEDS_WORLD_NORMAL
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
+#endif
+#ifdef FLIPBOOK_BLEND
+    @location(4) uv_next_offset: vec2<f32>,
+    @location(5) sprite_blend: f32,
+#endif
+{{INTERPOLANTS}}
 }
 
 @group(0) @binding(0) var<uniform> view: View;
@@ -32,6 +41,13 @@ struct VertexOutput {
 #endif
 {{MATERIAL_BINDINGS}}
 
+#ifdef FLIPBOOK_BLEND
+// UV offset from the current flipbook sprite to the next one, and blend factor
+// between them, for the fragment being shaded. Read by sample_material().
+var<private> flipbook_uv_next_offset: vec2<f32>;
+var<private> flipbook_blend: f32;
+#endif
+
 fn get_camera_position_effect_space() -> vec3<f32> {
     let view_pos = view.world_from_view[3].xyz;
 #ifdef LOCAL_SPACE_SIMULATION
@@ -89,6 +105,24 @@ fn transform_position_simulation_to_world(sim_position: vec3<f32>) -> vec4<f32>
 #endif
 }
 
+/// Transform a simulation space normal into a world space normal.
+///
+/// The simulation space depends on the effect's SimulationSpace value, and is either
+/// the effect space (SimulationSpace::Local) or the world space (SimulationSpace::Global).
+fn transform_normal_simulation_to_world(sim_normal: vec3<f32>) -> vec3<f32> {
+#ifdef LOCAL_SPACE_SIMULATION
+    // Normals transform with the inverse transpose of the transform
+    let inverse_transpose = mat3x3(
+        spawner.inverse_transform[0].xyz,
+        spawner.inverse_transform[1].xyz,
+        spawner.inverse_transform[2].xyz,
+    );
+    return normalize(inverse_transpose * sim_normal);
+#else
+    return sim_normal;
+#endif
+}
+
 /// Transform a simulation space position into a clip space position.
 ///
 /// The simulation space depends on the effect's SimulationSpace value, and is either
@@ -99,6 +133,14 @@ fn transform_position_simulation_to_clip(sim_position: vec3<f32>) -> vec4<f32> {
     return view.clip_from_world * transform_position_simulation_to_world(sim_position);
 }
 
+/// Get the view space depth of a simulation space position.
+///
+/// The depth is the distance to the camera along the view direction, and is positive
+/// in front of the camera.
+fn get_view_depth(sim_position: vec3<f32>) -> f32 {
+    return -(view.view_from_world * transform_position_simulation_to_world(sim_position)).z;
+}
+
 {{RENDER_EXTRA}}
 
 @vertex
@@ -121,11 +163,20 @@ fn vertex(
     let row_count = {{FLIPBOOK_ROW_COUNT}};
     let ij = vec2<f32>(f32(particle.sprite_index % row_count), f32(particle.sprite_index / row_count));
     uv = (ij + uv) * {{FLIPBOOK_SCALE}};
+#ifdef FLIPBOOK_BLEND
+    // The last sprite blends into the first one
+    let next_sprite_index = (particle.sprite_index + 1) % {{FLIPBOOK_FRAME_COUNT}};
+    let ij_next = vec2<f32>(f32(next_sprite_index % row_count), f32(next_sprite_index / row_count));
+    out.uv_next_offset = (ij_next - ij) * {{FLIPBOOK_SCALE}};
+    out.sprite_blend = particle.sprite_blend;
+#endif
 #endif
     out.uv = uv;
 #endif  // NEEDS_UV
 
 {{INPUTS}}
+    // Pivot of the particle mesh, in normalized quad space
+    var anchor = vec2<f32>(0.5, 0.5);
 
 {{VERTEX_MODIFIERS}}
 
@@ -149,9 +200,13 @@ fn vertex(
 
     // Expand particle mesh vertex based on particle position ("origin"), and local
     // orientation and size of the particle mesh (currently: only quad).
-    let vpos = vertex_position * vec3<f32>(size.x, size.y, 1.0);
+    let vpos = (vertex_position + vec3<f32>(vec2<f32>(0.5) - anchor, 0.0)) * vec3<f32>(size.x, size.y, 1.0);
     let sim_position = position + axis_x * vpos.x + axis_y * vpos.y;
     out.position = transform_position_simulation_to_clip(sim_position);
+#ifdef NEEDS_WORLD_NORMAL
+    out.world_position = transform_position_simulation_to_world(sim_position).xyz;
+    out.world_normal = transform_normal_simulation_to_world(axis_z);
+#endif
 
     out.color = color;
 
@@ -168,6 +223,11 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
 #ifdef NEEDS_UV
     var uv = in.uv;
 #endif
+#ifdef FLIPBOOK_BLEND
+    flipbook_uv_next_offset = in.uv_next_offset;
+    flipbook_blend = in.sprite_blend;
+#endif
+{{FRAGMENT_INTERPOLANTS}}
 
 {{FRAGMENT_MODIFIERS}}
 
@@ -1,6 +1,6 @@
 #import bevy_hanabi::vfx_common::{
     IndirectBuffer, ParticleGroup, RenderEffectMetadata, RenderGroupIndirect, SimParams, Spawner,
-    seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
+    seed, tau, pcg_hash, rng_hash, to_float01, frand, frand2, frand3, frand4,
     rand_uniform_f, rand_uniform_vec2, rand_uniform_vec3, rand_uniform_vec4,
     rand_normal_f, rand_normal_vec2, rand_normal_vec3, rand_normal_vec4, proj
 }
@@ -13,10 +13,17 @@ struct ParticleBuffer {
     particles: array<Particle>,
 }
 
+// Snapshot of the particle as it was at the end of the previous update
+var<private> prev_particle: Particle;
+
 {{PROPERTIES}}
 
 @group(0) @binding(0) var<uniform> sim_params : SimParams;
 @group(1) @binding(0) var<storage, read_write> particle_buffer : ParticleBuffer;
+#ifdef DOUBLE_BUFFERED
+// Particles as written by the update pass of the previous frame
+@group(1) @binding(4) var<storage, read_write> prev_particle_buffer : ParticleBuffer;
+#endif
 @group(1) @binding(1) var<storage, read_write> indirect_buffer : IndirectBuffer;
 @group(1) @binding(2) var<storage, read> particle_groups : array<ParticleGroup>;
 {{PROPERTIES_BINDING}}
@@ -24,6 +31,89 @@ struct ParticleBuffer {
 @group(3) @binding(0) var<storage, read_write> render_effect_indirect : RenderEffectMetadata;
 @group(3) @binding(1) var<storage, read_write> render_group_indirect : array<RenderGroupIndirect>;
 
+#ifdef SCENE_DEPTH
+struct SceneDepthView {
+    clip_from_world: mat4x4<f32>,
+    world_from_clip: mat4x4<f32>,
+}
+
+@group(4) @binding(0) var<uniform> scene_depth_view : SceneDepthView;
+@group(4) @binding(1) var scene_depth_texture : texture_depth_2d;
+
+fn scene_depth_available() -> bool {
+    return true;
+}
+
+/// Project a world position onto the screen.
+///
+/// Returns the screen UV in xy, the NDC depth in z, and 1 in w if the position
+/// is in front of the camera and on screen, or 0 otherwise.
+fn scene_depth_project(world_position: vec3<f32>) -> vec4<f32> {
+    let clip = scene_depth_view.clip_from_world * vec4<f32>(world_position, 1.0);
+    if (clip.w <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+    let on_screen = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return vec4<f32>(uv, ndc.z, select(0.0, 1.0, on_screen));
+}
+
+/// Sample the scene depth at the given screen UV.
+fn scene_depth_sample(uv: vec2<f32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(scene_depth_texture));
+    let texel = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - vec2<i32>(1));
+    return textureLoad(scene_depth_texture, texel, 0);
+}
+
+/// Reconstruct the world position of the scene surface at the given screen UV
+/// and depth.
+fn scene_depth_unproject(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let world = scene_depth_view.world_from_clip * vec4<f32>(ndc, depth, 1.0);
+    return world.xyz / world.w;
+}
+
+/// Reconstruct the world normal of the scene surface at the given screen UV,
+/// from the neighboring depth samples. The normal faces the camera.
+fn scene_depth_normal(uv: vec2<f32>) -> vec3<f32> {
+    let texel_size = 1.0 / vec2<f32>(textureDimensions(scene_depth_texture));
+    let uv_x = uv + vec2<f32>(texel_size.x, 0.0);
+    let uv_y = uv + vec2<f32>(0.0, texel_size.y);
+    let p = scene_depth_unproject(uv, scene_depth_sample(uv));
+    let px = scene_depth_unproject(uv_x, scene_depth_sample(uv_x));
+    let py = scene_depth_unproject(uv_y, scene_depth_sample(uv_y));
+    return normalize(cross(py - p, px - p));
+}
+#else
+// No scene depth available; collisions with the depth buffer are disabled.
+fn scene_depth_available() -> bool {
+    return false;
+}
+
+fn scene_depth_project(world_position: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(0.0);
+}
+
+fn scene_depth_sample(uv: vec2<f32>) -> f32 {
+    return 0.0;
+}
+
+fn scene_depth_unproject(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    return vec3<f32>(0.0);
+}
+
+fn scene_depth_normal(uv: vec2<f32>) -> vec3<f32> {
+    return vec3<f32>(0.0, 1.0, 0.0);
+}
+#endif
+
+/// Number of particles alive in the current group at the start of the update
+/// pass, before any of them is killed this frame.
+fn group_alive_count() -> u32 {
+    return render_group_indirect[{{GROUP_INDEX}}].max_update;
+}
+
 {{UPDATE_EXTRA}}
 
 @compute @workgroup_size(64)
@@ -43,10 +133,15 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let base_index = effect_particle_offset + particle_groups[{{GROUP_INDEX}}].indirect_index;
     let index = indirect_buffer.indices[3u * (base_index + thread_index) + pong];
 
+#ifdef DOUBLE_BUFFERED
+    var particle: Particle = prev_particle_buffer.particles[index];
+#else
     var particle: Particle = particle_buffer.particles[index];
+#endif
+    prev_particle = particle;
 
     // Update PRNG seed
-    seed = pcg_hash(index ^ spawner.seed);
+    seed = rng_hash(index ^ spawner.seed);
 
     {{AGE_CODE}}
     {{UPDATE_CODE}}
@@ -571,6 +571,23 @@ impl EffectInitializers {
             initializer.set_active(active);
         }
     }
+
+    /// Check if the effect is empty, and none of its initializers spawns any
+    /// particle this frame.
+    ///
+    /// An effect is empty once the GPU reported that all its groups have no
+    /// particle alive after they last spawned, as returned by
+    /// [`EffectInitializer::alive_count()`]. The alive counts are read back
+    /// from GPU with a latency of a few frames, so an effect is not idle until
+    /// the first readback simulated after its last spawn. See
+    /// [`EffectAsset::with_skip_when_empty()`] for details.
+    ///
+    /// [`EffectAsset::with_skip_when_empty()`]: crate::EffectAsset::with_skip_when_empty
+    pub fn is_idle(&self) -> bool {
+        self.0
+            .iter()
+            .all(|initializer| !initializer.is_spawning() && initializer.is_empty())
+    }
 }
 
 /// Holds the runtime state for the initializer of a single particle group on a
@@ -617,10 +634,89 @@ impl EffectInitializer {
             EffectInitializer::Cloner(effect_cloner) => effect_cloner.set_active(active),
         }
     }
+
+    /// Get the number of particles alive in the group, as last read back from
+    /// GPU.
+    ///
+    /// This is `None` until the GPU reports a first value. The value is
+    /// updated during the [`Last`] schedule, a few frames after the GPU
+    /// simulated the particles, so it doesn't account for the particles
+    /// spawned during those last frames.
+    pub fn alive_count(&self) -> Option<u32> {
+        match self {
+            EffectInitializer::Spawner(effect_spawner) => effect_spawner.alive_count,
+            EffectInitializer::Cloner(effect_cloner) => effect_cloner.alive_count,
+        }
+    }
+
+    /// Check if the GPU reported no particle alive in the group after it last
+    /// spawned some.
+    pub(crate) fn is_empty(&self) -> bool {
+        let (alive_count, alive_count_generation) = match self {
+            EffectInitializer::Spawner(effect_spawner) => (
+                effect_spawner.alive_count,
+                effect_spawner.alive_count_generation,
+            ),
+            EffectInitializer::Cloner(effect_cloner) => (
+                effect_cloner.alive_count,
+                effect_cloner.alive_count_generation,
+            ),
+        };
+        alive_count == Some(0) && alive_count_generation == self.spawn_generation()
+    }
+
+    /// Check if the initializer spawns some particles this frame.
+    pub(crate) fn is_spawning(&self) -> bool {
+        match self {
+            EffectInitializer::Spawner(effect_spawner) => effect_spawner.spawn_count > 0,
+            EffectInitializer::Cloner(effect_cloner) => effect_cloner.spawn_this_frame,
+        }
+    }
+
+    /// Get the number of frames the initializer spawned some particles.
+    ///
+    /// This tags the alive counts read back from GPU, to discard the ones
+    /// simulated before the initializer last spawned.
+    pub(crate) fn spawn_generation(&self) -> u32 {
+        match self {
+            EffectInitializer::Spawner(effect_spawner) => effect_spawner.spawn_generation,
+            EffectInitializer::Cloner(effect_cloner) => effect_cloner.spawn_generation,
+        }
+    }
+
+    /// Start a new spawn generation if the initializer spawns some particles
+    /// this frame.
+    pub(crate) fn update_spawn_generation(&mut self) {
+        if !self.is_spawning() {
+            return;
+        }
+        let spawn_generation = match self {
+            EffectInitializer::Spawner(effect_spawner) => &mut effect_spawner.spawn_generation,
+            EffectInitializer::Cloner(effect_cloner) => &mut effect_cloner.spawn_generation,
+        };
+        *spawn_generation = spawn_generation.wrapping_add(1);
+    }
+
+    /// Set the number of particles alive read back from GPU, simulated during
+    /// the given spawn generation.
+    pub(crate) fn set_alive_count(&mut self, spawn_generation: u32, alive_count: u32) {
+        let (count, generation) = match self {
+            EffectInitializer::Spawner(effect_spawner) => (
+                &mut effect_spawner.alive_count,
+                &mut effect_spawner.alive_count_generation,
+            ),
+            EffectInitializer::Cloner(effect_cloner) => (
+                &mut effect_cloner.alive_count,
+                &mut effect_cloner.alive_count_generation,
+            ),
+        };
+        *count = Some(alive_count);
+        *generation = spawn_generation;
+    }
 }
 
 /// Runtime structure maintaining the state of the spawner for a particle group.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
 pub struct EffectSpawner {
     /// The spawner configuration extracted either from the [`EffectAsset`], or
     /// from any overriden value provided by the user on the [`ParticleEffect`].
@@ -656,8 +752,46 @@ pub struct EffectSpawner {
     /// part will be stored in `spawn_count`.
     spawn_remainder: f32,
 
+    /// Number of particles requested in previous frames, wrapping around on
+    /// overflow. This is the index of the first particle spawned this frame.
+    spawn_index: u32,
+
     /// Whether the system is active. Defaults to `true`.
     active: bool,
+
+    /// Number of particles alive in the group, as last read back from GPU.
+    alive_count: Option<u32>,
+
+    /// Spawn generation during which `alive_count` was simulated.
+    alive_count_generation: u32,
+
+    /// Number of frames the spawner spawned some particles.
+    spawn_generation: u32,
+
+    /// Scale applied to the number of particles spawned, in \[0:1\].
+    ///
+    /// This is updated each frame by [`apply_particle_budget()`] to throttle
+    /// the spawner when a [`GlobalParticleBudget`] is exceeded.
+    spawn_rate_scale: f32,
+}
+
+impl Default for EffectSpawner {
+    fn default() -> Self {
+        Self {
+            spawner: default(),
+            time: 0.,
+            curr_spawn_time: 0.,
+            limit: 0.,
+            spawn_count: 0,
+            spawn_remainder: 0.,
+            spawn_index: 0,
+            active: false,
+            alive_count: None,
+            alive_count_generation: 0,
+            spawn_generation: 0,
+            spawn_rate_scale: 1.,
+        }
+    }
 }
 
 impl EffectSpawner {
@@ -674,7 +808,12 @@ impl EffectSpawner {
             limit: 0.,
             spawn_count: 0,
             spawn_remainder: 0.,
+            spawn_index: 0,
             active: spawner.starts_active(),
+            alive_count: None,
+            alive_count_generation: 0,
+            spawn_generation: 0,
+            spawn_rate_scale: 1.,
         }
     }
 
@@ -709,6 +848,27 @@ impl EffectSpawner {
         &self.spawner
     }
 
+    /// Get the scale applied to the number of particles spawned.
+    ///
+    /// This is `1` unless the spawner is throttled by a
+    /// [`GlobalParticleBudget`]. See [`apply_particle_budget()`] for details.
+    pub fn spawn_rate_scale(&self) -> f32 {
+        self.spawn_rate_scale
+    }
+
+    /// Set the scale applied to the number of particles spawned.
+    ///
+    /// The scale multiplies the number of particles sampled from the
+    /// [`Spawner`] on each [`tick()`], and is clamped to \[0:1\]. Note that
+    /// [`apply_particle_budget()`] overwrites this value each frame while a
+    /// [`GlobalParticleBudget`] exists, and resets it to `1` when the budget
+    /// is removed.
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    pub fn set_spawn_rate_scale(&mut self, scale: f32) {
+        self.spawn_rate_scale = scale.clamp(0., 1.);
+    }
+
     /// Reset the spawner state.
     ///
     /// This resets the internal spawner time to zero, and restarts any internal
@@ -723,6 +883,18 @@ impl EffectSpawner {
         self.limit = 0.;
         self.spawn_count = 0;
         self.spawn_remainder = 0.;
+        self.spawn_index = 0;
+    }
+
+    /// Get the number of particles requested by the spawner in previous
+    /// frames, wrapping around on overflow.
+    ///
+    /// This is the index of the first particle spawned this frame among all
+    /// particles spawned since the spawner was created or [`reset()`].
+    ///
+    /// [`reset()`]: crate::EffectSpawner::reset
+    pub(crate) fn spawn_index(&self) -> u32 {
+        self.spawn_index
     }
 
     /// Tick the spawner to calculate the number of particles to spawn this
@@ -740,6 +912,10 @@ impl EffectSpawner {
     /// The integral number of particles to spawn this frame. Any fractional
     /// remainder is saved for the next call.
     pub fn tick(&mut self, mut dt: f32, rng: &mut Pcg32) -> u32 {
+        // Account for the particles spawned last frame, including any count
+        // manually assigned after the previous tick.
+        self.spawn_index = self.spawn_index.wrapping_add(self.spawn_count);
+
         if !self.active {
             self.spawn_count = 0;
             return 0;
@@ -756,13 +932,13 @@ impl EffectSpawner {
             if self.time <= self.curr_spawn_time {
                 // If the spawn time is very small, close to zero, spawn all particles
                 // immediately in one burst over a single frame.
+                let num_particles = self.spawner.num_particles.sample(rng) * self.spawn_rate_scale;
                 self.spawn_remainder += if self.curr_spawn_time < 1e-5f32.max(dt / 100.0) {
-                    self.spawner.num_particles.sample(rng)
+                    num_particles
                 } else {
                     // Spawn an amount of particles equal to the fraction of time the current frame
                     // spans compared to the total burst duration.
-                    self.spawner.num_particles.sample(rng)
-                        * (new_time.min(self.curr_spawn_time) - self.time)
+                    num_particles * (new_time.min(self.curr_spawn_time) - self.time)
                         / self.curr_spawn_time
                 };
             }
@@ -810,6 +986,12 @@ pub struct EffectCloner {
     pub spawn_this_frame: bool,
     /// Whether the cloner is active. Defaults to true.
     pub active: bool,
+    /// Number of particles alive in the group, as last read back from GPU.
+    alive_count: Option<u32>,
+    /// Spawn generation during which `alive_count` was simulated.
+    alive_count_generation: u32,
+    /// Number of frames the cloner cloned some particles.
+    spawn_generation: u32,
 }
 
 impl EffectCloner {
@@ -821,6 +1003,9 @@ impl EffectCloner {
             capacity,
             spawn_this_frame: false,
             active: cloner.starts_active(),
+            alive_count: None,
+            alive_count_generation: 0,
+            spawn_generation: 0,
         }
     }
 
@@ -869,6 +1054,137 @@ impl EffectCloner {
     }
 }
 
+/// Global cap on the number of particles alive across all effects.
+///
+/// Insert this resource to limit the total number of particles, for example on
+/// lower-end hardware. Each frame, [`apply_particle_budget()`] compares the
+/// budget with the particle demand of all effects, and if the demand exceeds
+/// the budget it scales down the spawn rate of all spawners by the ratio of the
+/// budget to the demand. Removing the resource restores the nominal spawn
+/// rates.
+///
+/// The demand of an effect is the number of particles its groups with an
+/// active spawner would have alive at their nominal spawn rate. It's estimated
+/// from the number of particles alive read back from GPU (see
+/// [`EffectInitializer::alive_count()`]) divided by the spawn rate scale
+/// currently applied, so throttling an effect doesn't lower its demand. Groups
+/// whose alive count wasn't read back yet, or which are fully throttled,
+/// conservatively demand their entire capacity. The readback lags a few frames
+/// behind, so the budget can be briefly exceeded when effects start spawning.
+///
+/// # Fairness
+///
+/// The throttling is proportional: all spawners are scaled by the same ratio,
+/// so each effect keeps the same share of the budget it had of the demand.
+/// There's no priority between effects; an important effect is throttled as
+/// much as a background one. To keep an effect at its nominal rate, reset its
+/// scale with [`EffectSpawner::set_spawn_rate_scale()`] in a system running
+/// after [`apply_particle_budget()`] and before [`tick_spawners()`], at the
+/// cost of exceeding the budget.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// # use bevy::prelude::*;
+/// fn setup(mut commands: Commands) {
+///     // Keep at most 100k particles alive across all effects
+///     commands.insert_resource(GlobalParticleBudget::new(100_000));
+/// }
+/// ```
+///
+/// [`EffectSystems::TickSpawners`]: crate::EffectSystems::TickSpawners
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GlobalParticleBudget {
+    /// Maximum number of particles alive across all effects.
+    pub max_particles: u32,
+}
+
+impl GlobalParticleBudget {
+    /// Create a new budget with the given maximum number of particles.
+    pub fn new(max_particles: u32) -> Self {
+        Self { max_particles }
+    }
+
+    /// Calculate the spawn rate scale for a given particle demand.
+    ///
+    /// This is the ratio of the budget to the demand, or `1` if the demand
+    /// doesn't exceed the budget.
+    pub fn spawn_rate_scale(&self, demand: u64) -> f32 {
+        if demand <= self.max_particles as u64 {
+            1.
+        } else {
+            (self.max_particles as f64 / demand as f64) as f32
+        }
+    }
+}
+
+/// Throttle the spawners of all effects to fit a [`GlobalParticleBudget`].
+///
+/// This system runs in the [`PostUpdate`] stage, in the
+/// [`EffectSystems::TickSpawners`] set before [`tick_spawners()`]. It sums the
+/// estimated particle demand of all effect instances with an
+/// [`EffectInitializers`] component, then assigns the same spawn rate scale to
+/// all their spawners with [`EffectSpawner::set_spawn_rate_scale()`]. See
+/// [`GlobalParticleBudget`] for details.
+///
+/// Without any [`GlobalParticleBudget`] resource, the spawn rate scales are
+/// left untouched, except on the frame the resource is removed, where all
+/// spawners are reset to their nominal spawn rate.
+///
+/// [`EffectSystems::TickSpawners`]: crate::EffectSystems::TickSpawners
+pub fn apply_particle_budget(
+    budget: Option<Res<GlobalParticleBudget>>,
+    effects: Res<Assets<EffectAsset>>,
+    mut had_budget: Local<bool>,
+    mut query: Query<(&ParticleEffect, &mut EffectInitializers)>,
+) {
+    trace!("apply_particle_budget");
+
+    let scale = if let Some(budget) = budget {
+        let demand = query
+            .iter()
+            .filter_map(|(effect, initializers)| {
+                let asset = effects.get(&effect.handle)?;
+                Some(
+                    initializers
+                        .iter()
+                        .zip(asset.capacities())
+                        .filter_map(|(initializer, &capacity)| {
+                            let spawner = initializer.get_spawner()?;
+                            if !spawner.is_active() {
+                                return None;
+                            }
+                            let demand = match initializer.alive_count() {
+                                Some(alive_count) if spawner.spawn_rate_scale > 0. => {
+                                    (alive_count as f32 / spawner.spawn_rate_scale).ceil() as u64
+                                }
+                                _ => capacity as u64,
+                            };
+                            Some(demand.min(capacity as u64))
+                        })
+                        .sum::<u64>(),
+                )
+            })
+            .sum();
+        *had_budget = true;
+        budget.spawn_rate_scale(demand)
+    } else if std::mem::take(&mut *had_budget) {
+        1.
+    } else {
+        return;
+    };
+
+    for (_, mut initializers) in query.iter_mut() {
+        for initializer in &mut **initializers {
+            if let EffectInitializer::Spawner(effect_spawner) = initializer {
+                effect_spawner.spawn_rate_scale = scale;
+            }
+        }
+    }
+}
+
 /// Tick all the [`EffectSpawner`] components of the simulated
 /// [`ParticleEffect`] components.
 ///
@@ -1117,6 +1433,29 @@ mod test {
         assert_eq!(count, 0);
     }
 
+    #[test]
+    fn test_spawn_index() {
+        let rng = &mut new_rng();
+        let spawner = Spawner::rate(5.0.into());
+        let mut spawner = make_effect_spawner(spawner);
+        assert_eq!(spawner.spawn_index(), 0);
+        spawner.tick(1.01, rng);
+        assert_eq!(spawner.spawn_index(), 0);
+        spawner.tick(0.4, rng);
+        assert_eq!(spawner.spawn_index(), 5);
+
+        // Manually overridden counts are accounted for on the next tick
+        spawner.spawn_count = 100;
+        spawner.set_active(false);
+        spawner.tick(0.4, rng);
+        assert_eq!(spawner.spawn_index(), 105);
+        spawner.tick(0.4, rng);
+        assert_eq!(spawner.spawn_index(), 105);
+
+        spawner.reset();
+        assert_eq!(spawner.spawn_index(), 0);
+    }
+
     #[test]
     fn test_with_active() {
         let rng = &mut new_rng();
@@ -1134,6 +1473,90 @@ mod test {
         assert_eq!(count, 5);
     }
 
+    #[test]
+    fn test_spawn_rate_scale() {
+        let rng = &mut new_rng();
+        let spawner = Spawner::once(10.0.into(), true);
+        let mut spawner = make_effect_spawner(spawner);
+        assert_eq!(spawner.spawn_rate_scale(), 1.);
+        spawner.set_spawn_rate_scale(0.5);
+        assert_eq!(spawner.spawn_rate_scale(), 0.5);
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 5);
+        spawner.set_spawn_rate_scale(2.); // clamped
+        assert_eq!(spawner.spawn_rate_scale(), 1.);
+        spawner.reset();
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 10);
+
+        let budget = GlobalParticleBudget::new(1000);
+        assert_eq!(budget.spawn_rate_scale(0), 1.);
+        assert_eq!(budget.spawn_rate_scale(1000), 1.);
+        assert_eq!(budget.spawn_rate_scale(4000), 0.25);
+    }
+
+    #[test]
+    fn test_idle() {
+        let rng = &mut new_rng();
+        let spawner = Spawner::once(32.0.into(), true);
+        let mut initializers = EffectInitializers(vec![EffectInitializer::Spawner(
+            make_effect_spawner(spawner),
+        )]);
+        let tick = |initializers: &mut EffectInitializers, dt: f32, rng: &mut Pcg32| {
+            for initializer in &mut initializers.0 {
+                if let EffectInitializer::Spawner(spawner) = initializer {
+                    spawner.tick(dt, rng);
+                }
+            }
+        };
+
+        // Spawning this frame
+        tick(&mut initializers, 0.5, rng);
+        initializers[0].update_spawn_generation();
+        assert_eq!(initializers[0].spawn_generation(), 1);
+        assert_eq!(initializers[0].alive_count(), None);
+        assert!(!initializers.is_idle());
+
+        // Alive counts simulated before the last spawn are outdated
+        tick(&mut initializers, 0.5, rng);
+        initializers[0].set_alive_count(0, 0);
+        assert_eq!(initializers[0].alive_count(), Some(0));
+        assert!(!initializers.is_idle());
+
+        // Finished spawning, but some particles are still alive
+        initializers[0].update_spawn_generation();
+        assert_eq!(initializers[0].spawn_generation(), 1);
+        initializers[0].set_alive_count(1, 32);
+        assert_eq!(initializers[0].alive_count(), Some(32));
+        assert!(!initializers.is_idle());
+
+        // All particles died
+        initializers[0].set_alive_count(1, 0);
+        assert!(initializers.is_idle());
+
+        // Restarting wakes the effect up
+        initializers.reset();
+        tick(&mut initializers, 0.5, rng);
+        assert!(!initializers.is_idle());
+        initializers[0].update_spawn_generation();
+        assert_eq!(initializers[0].spawn_generation(), 2);
+        assert!(!initializers.is_idle());
+        tick(&mut initializers, 0.5, rng);
+        initializers[0].set_alive_count(2, 0);
+        assert!(initializers.is_idle());
+
+        // Cloner groups need to be empty too
+        initializers
+            .0
+            .push(EffectInitializer::Cloner(EffectCloner::new(
+                Cloner::default(),
+                64,
+            )));
+        assert!(!initializers.is_idle());
+        initializers[1].set_alive_count(0, 0);
+        assert!(initializers.is_idle());
+    }
+
     fn make_test_app() -> App {
         IoTaskPool::get_or_init(|| {
             TaskPoolBuilder::default()
@@ -1165,7 +1588,10 @@ mod test {
         app.init_asset::<EffectAsset>();
         app.add_systems(
             PostUpdate,
-            tick_spawners.after(VisibilitySystems::CheckVisibility),
+            (
+                apply_particle_budget.before(tick_spawners),
+                tick_spawners.after(VisibilitySystems::CheckVisibility),
+            ),
         );
 
         app
@@ -1323,4 +1749,104 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_global_particle_budget() {
+        let mut app = make_test_app();
+        app.insert_resource(GlobalParticleBudget::new(500));
+
+        // Two effects exceeding the budget, for a total demand of 1000 particles
+        let entities = {
+            let world = app.world_mut();
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let handles = [(600, 60.), (400, 40.)].map(|(capacity, count)| {
+                let mut asset = EffectAsset::new(
+                    capacity,
+                    Spawner::burst(CpuValue::Single(count), 1.0.into()),
+                    Module::default(),
+                );
+                asset.simulation_condition = SimulationCondition::Always;
+                assets.add(asset)
+            });
+            handles.map(|handle| {
+                world
+                    .spawn(ParticleEffect {
+                        handle,
+                        #[cfg(feature = "2d")]
+                        z_layer_2d: None,
+                    })
+                    .id()
+            })
+        };
+
+        let tick = |app: &mut App| {
+            app.world_mut()
+                .resource_mut::<Time<EffectSimulation>>()
+                .advance_by(Duration::from_secs(1));
+            app.update();
+        };
+        let spawners = |app: &mut App| {
+            entities.map(|entity| {
+                *app.world().get::<EffectInitializers>(entity).unwrap()[0]
+                    .get_spawner()
+                    .unwrap()
+            })
+        };
+
+        // The first frame inserts the EffectInitializers, so isn't throttled yet
+        tick(&mut app);
+        tick(&mut app);
+
+        // Before any readback, both effects demand their capacity, and are throttled
+        // by the same ratio = budget / demand
+        let [s0, s1] = spawners(&mut app);
+        assert_eq!(s0.spawn_rate_scale(), 0.5);
+        assert_eq!(s1.spawn_rate_scale(), 0.5);
+        let throttled = [s0.spawn_count, s1.spawn_count];
+        assert!(throttled[0] > 0);
+        assert_eq!(throttled[0] * 2, throttled[1] * 3);
+
+        // The demand is estimated from the alive counts at the nominal spawn rate
+        let set_alive_counts = |app: &mut App, alive_counts: [u32; 2]| {
+            for (entity, alive_count) in entities.iter().zip(alive_counts) {
+                let mut initializers = app
+                    .world_mut()
+                    .get_mut::<EffectInitializers>(*entity)
+                    .unwrap();
+                initializers[0].set_alive_count(0, alive_count);
+            }
+        };
+        set_alive_counts(&mut app, [150, 100]);
+        tick(&mut app);
+        let [s0, s1] = spawners(&mut app);
+        assert_eq!(s0.spawn_rate_scale(), 1.);
+        assert_eq!(s1.spawn_rate_scale(), 1.);
+        set_alive_counts(&mut app, [400, 600]);
+        tick(&mut app);
+        let [s0, s1] = spawners(&mut app);
+        assert_eq!(s0.spawn_rate_scale(), 0.625); // 500 / (400 + 400)
+        assert_eq!(s1.spawn_rate_scale(), 0.625);
+
+        // Removing the budget restores the nominal spawn rates
+        app.world_mut().remove_resource::<GlobalParticleBudget>();
+        tick(&mut app);
+        let [s0, s1] = spawners(&mut app);
+        assert_eq!(s0.spawn_rate_scale(), 1.);
+        assert_eq!(s1.spawn_rate_scale(), 1.);
+        assert_eq!(s0.spawn_count, throttled[0] * 2);
+        assert_eq!(s1.spawn_count, throttled[1] * 2);
+
+        // Without budget, a manually assigned scale is left untouched
+        if let EffectInitializer::Spawner(effect_spawner) = &mut app
+            .world_mut()
+            .get_mut::<EffectInitializers>(entities[0])
+            .unwrap()[0]
+        {
+            effect_spawner.set_spawn_rate_scale(0.25);
+        }
+        tick(&mut app);
+        let [s0, _] = spawners(&mut app);
+        assert_eq!(s0.spawn_rate_scale(), 0.25);
+        assert_eq!(s0.spawn_count, throttled[0] / 2);
+    }
 }
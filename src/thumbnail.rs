@@ -0,0 +1,467 @@
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        graph::CameraDriverLabel,
+        render_asset::{RenderAssetUsages, RenderAssets},
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer, ImageDataLayout,
+            Maintain, MapMode, TextureDimension, TextureFormat, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::EffectSimulation;
+
+/// Format of the thumbnail images.
+const THUMBNAIL_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Size in bytes of a single pixel of [`THUMBNAIL_FORMAT`].
+const THUMBNAIL_PIXEL_SIZE: u32 = 4;
+
+/// Plugin to render thumbnails of effects with [`EffectThumbnail`].
+///
+/// This plugin requires the [`HanabiPlugin`], and is not added by it, because
+/// the readback of the thumbnails adds some small per-frame overhead to the
+/// render graph.
+///
+/// [`HanabiPlugin`]: crate::HanabiPlugin
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EffectThumbnailPlugin;
+
+/// Render graph label of the node copying the thumbnail render targets into
+/// their readback buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, RenderLabel)]
+pub struct EffectThumbnailCopyLabel;
+
+/// Progress of an [`EffectThumbnail`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum EffectThumbnailState {
+    /// The effect is simulated until the thumbnail duration elapses.
+    #[default]
+    Simulating,
+    /// The thumbnail duration elapsed, and the render target is being read
+    /// back from GPU.
+    Capturing,
+    /// The thumbnail was read back, and an [`EffectThumbnailReady`] event was
+    /// emitted.
+    Done,
+}
+
+/// Request to render a thumbnail of an effect into an offscreen image.
+///
+/// Spawn this component on a [`Camera`] entity rendering the effect(s) to
+/// capture into the [`render_target()`] of the thumbnail. Once the effects have
+/// been simulated for the thumbnail duration, as measured by the
+/// [`Time<EffectSimulation>`] clock, the render target is copied and read back
+/// from GPU into an [`Image`] emitted with an [`EffectThumbnailReady`] event.
+/// The capture entities can then be despawned. This requires the
+/// [`EffectThumbnailPlugin`].
+///
+/// The camera renders its whole view, so use [`RenderLayers`] to avoid
+/// rendering anything else than the effect(s) into the thumbnail.
+///
+/// # Determinism
+///
+/// Thumbnails are not deterministic by default. To render the same thumbnail
+/// for the same effect when called repeatedly:
+/// - The effect simulation must advance by the same time steps. Use for example
+///   [`TimeUpdateStrategy::ManualDuration`] to advance by a fixed time step
+///   each frame, independently of the actual frame rate.
+/// - The CPU random number generator used by the spawners to sample their
+///   [`CpuValue`]s must be seeded with a fixed value, by replacing the
+///   `Random` resource of the [`HanabiPlugin`].
+/// - The GPU random number generator is seeded randomly each time the spawner
+///   of an effect is uploaded to GPU, so any effect using random values in its
+///   expressions, like [`ExprWriter::rand()`], produces a different image each
+///   time.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// # use std::time::Duration;
+/// fn setup(
+///     mut commands: Commands,
+///     mut images: ResMut<Assets<Image>>,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     let thumbnail =
+///         EffectThumbnail::new(UVec2::new(128, 128), Duration::from_secs(1), &mut images);
+///     commands.spawn((
+///         Camera3dBundle {
+///             camera: Camera {
+///                 target: thumbnail.render_target(),
+///                 ..default()
+///             },
+///             transform: Transform::from_xyz(0., 0., 10.),
+///             ..default()
+///         },
+///         thumbnail,
+///     ));
+///     commands.spawn(ParticleEffectBundle::new(asset_server.load("fire.effect")));
+/// }
+///
+/// fn save(mut events: EventReader<EffectThumbnailReady>) {
+///     for event in events.read() {
+///         // Save event.image to disk...
+///     }
+/// }
+/// ```
+///
+/// [`render_target()`]: EffectThumbnail::render_target
+/// [`RenderLayers`]: bevy::render::view::RenderLayers
+/// [`TimeUpdateStrategy::ManualDuration`]: bevy::time::TimeUpdateStrategy::ManualDuration
+/// [`CpuValue`]: crate::CpuValue
+/// [`HanabiPlugin`]: crate::HanabiPlugin
+/// [`ExprWriter::rand()`]: crate::graph::ExprWriter::rand
+#[derive(Debug, Clone, Component, Reflect)]
+pub struct EffectThumbnail {
+    /// Size of the thumbnail, in pixels.
+    size: UVec2,
+    /// Simulated duration before the thumbnail is captured.
+    duration: Duration,
+    /// Offscreen image the thumbnail camera renders into.
+    image: Handle<Image>,
+    /// Simulated duration elapsed so far.
+    elapsed: Duration,
+    /// Progress of the thumbnail.
+    state: EffectThumbnailState,
+}
+
+impl EffectThumbnail {
+    /// Create a new thumbnail request of the given size, captured after the
+    /// given simulated duration.
+    ///
+    /// This allocates the offscreen render target image into `images`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either component of the size is zero.
+    pub fn new(size: UVec2, duration: Duration, images: &mut Assets<Image>) -> Self {
+        assert!(
+            size.x > 0 && size.y > 0,
+            "Invalid zero-sized effect thumbnail."
+        );
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0; THUMBNAIL_PIXEL_SIZE as usize],
+            THUMBNAIL_FORMAT,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC
+            | TextureUsages::RENDER_ATTACHMENT;
+        Self {
+            size,
+            duration,
+            image: images.add(image),
+            elapsed: Duration::ZERO,
+            state: EffectThumbnailState::Simulating,
+        }
+    }
+
+    /// Get the size of the thumbnail, in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Get the simulated duration before the thumbnail is captured.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Get the offscreen image the thumbnail camera renders into.
+    pub fn image(&self) -> &Handle<Image> {
+        &self.image
+    }
+
+    /// Get the render target to assign to the thumbnail camera.
+    pub fn render_target(&self) -> RenderTarget {
+        RenderTarget::Image(self.image.clone())
+    }
+
+    /// Get the progress of the thumbnail.
+    pub fn state(&self) -> EffectThumbnailState {
+        self.state
+    }
+}
+
+/// Event emitted once an [`EffectThumbnail`] was captured.
+#[derive(Debug, Clone, Event)]
+pub struct EffectThumbnailReady {
+    /// The entity holding the [`EffectThumbnail`] component.
+    pub entity: Entity,
+    /// The thumbnail image, of the size requested by the [`EffectThumbnail`].
+    pub image: Image,
+}
+
+/// Raw thumbnail data read back from GPU, without row padding.
+struct ThumbnailData {
+    entity: Entity,
+    size: UVec2,
+    data: Vec<u8>,
+}
+
+/// Main world end of the readback channel.
+#[derive(Resource)]
+struct ThumbnailReceiver(Mutex<Receiver<ThumbnailData>>);
+
+/// Render world end of the readback channel.
+#[derive(Resource)]
+struct ThumbnailSender(Sender<ThumbnailData>);
+
+/// Thumbnail capture of the current frame, in the render world.
+struct ThumbnailCapture {
+    entity: Entity,
+    image: AssetId<Image>,
+    size: UVec2,
+    /// Readback buffer, allocated once the GPU image is available.
+    buffer: Option<Buffer>,
+}
+
+impl ThumbnailCapture {
+    /// Size in bytes of a row of the thumbnail in the readback buffer.
+    ///
+    /// Rows of a texture copy need to be aligned to
+    /// [`COPY_BYTES_PER_ROW_ALIGNMENT`] bytes.
+    ///
+    /// [`COPY_BYTES_PER_ROW_ALIGNMENT`]: bevy::render::render_resource::COPY_BYTES_PER_ROW_ALIGNMENT
+    fn padded_bytes_per_row(&self) -> u32 {
+        RenderDevice::align_copy_bytes_per_row((self.size.x * THUMBNAIL_PIXEL_SIZE) as usize) as u32
+    }
+}
+
+/// Thumbnail captures of the current frame.
+#[derive(Resource, Default)]
+struct ThumbnailCaptures(Vec<ThumbnailCapture>);
+
+impl Plugin for EffectThumbnailPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        app.add_event::<EffectThumbnailReady>()
+            .insert_resource(ThumbnailReceiver(Mutex::new(receiver)))
+            .register_type::<EffectThumbnail>()
+            .add_systems(PostUpdate, (receive_thumbnails, tick_thumbnails).chain());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(ThumbnailSender(sender));
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<ThumbnailCaptures>()
+            .add_systems(ExtractSchedule, extract_thumbnails)
+            .add_systems(
+                Render,
+                (
+                    prepare_thumbnails.in_set(RenderSet::PrepareResources),
+                    readback_thumbnails.in_set(RenderSet::Cleanup),
+                ),
+            );
+
+        // Copy the render targets once all cameras rendered.
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(EffectThumbnailCopyLabel, EffectThumbnailCopyNode);
+        graph.add_node_edge(CameraDriverLabel, EffectThumbnailCopyLabel);
+    }
+}
+
+/// Advance the thumbnails being simulated, and start capturing the ones whose
+/// duration elapsed.
+fn tick_thumbnails(time: Res<Time<EffectSimulation>>, mut query: Query<&mut EffectThumbnail>) {
+    for mut thumbnail in query.iter_mut() {
+        if thumbnail.state != EffectThumbnailState::Simulating {
+            continue;
+        }
+        thumbnail.elapsed += time.delta();
+        if thumbnail.elapsed >= thumbnail.duration {
+            thumbnail.state = EffectThumbnailState::Capturing;
+        }
+    }
+}
+
+/// Receive the thumbnails read back from GPU, and emit the corresponding
+/// events.
+fn receive_thumbnails(
+    receiver: Res<ThumbnailReceiver>,
+    mut query: Query<&mut EffectThumbnail>,
+    mut events: EventWriter<EffectThumbnailReady>,
+) {
+    let receiver = receiver.0.lock().unwrap();
+    while let Ok(thumbnail_data) = receiver.try_recv() {
+        // Captures are requested each frame until one is received, so ignore any later
+        // duplicate.
+        let Ok(mut thumbnail) = query.get_mut(thumbnail_data.entity) else {
+            continue;
+        };
+        if thumbnail.state != EffectThumbnailState::Capturing {
+            continue;
+        }
+        thumbnail.state = EffectThumbnailState::Done;
+
+        let image = Image::new(
+            Extent3d {
+                width: thumbnail_data.size.x,
+                height: thumbnail_data.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            thumbnail_data.data,
+            THUMBNAIL_FORMAT,
+            RenderAssetUsages::default(),
+        );
+        events.send(EffectThumbnailReady {
+            entity: thumbnail_data.entity,
+            image,
+        });
+    }
+}
+
+/// Extract the thumbnails to capture this frame.
+fn extract_thumbnails(
+    mut captures: ResMut<ThumbnailCaptures>,
+    query: Extract<Query<(Entity, &EffectThumbnail)>>,
+) {
+    captures.0.clear();
+    for (entity, thumbnail) in query.iter() {
+        if thumbnail.state == EffectThumbnailState::Capturing {
+            captures.0.push(ThumbnailCapture {
+                entity,
+                image: thumbnail.image.id(),
+                size: thumbnail.size,
+                buffer: None,
+            });
+        }
+    }
+}
+
+/// Allocate the readback buffers of the thumbnails to capture this frame.
+///
+/// Thumbnails whose render target is not yet available on GPU are skipped, and
+/// captured on a later frame.
+fn prepare_thumbnails(
+    render_device: Res<RenderDevice>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    mut captures: ResMut<ThumbnailCaptures>,
+) {
+    captures
+        .0
+        .retain(|capture| gpu_images.get(capture.image).is_some());
+    for capture in captures.0.iter_mut() {
+        let size = capture.padded_bytes_per_row() as u64 * capture.size.y as u64;
+        capture.buffer = Some(render_device.create_buffer(&BufferDescriptor {
+            label: Some("hanabi:buffer:thumbnail_readback"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+}
+
+/// Render graph node copying the thumbnail render targets into their readback
+/// buffers.
+struct EffectThumbnailCopyNode;
+
+impl render_graph::Node for EffectThumbnailCopyNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let captures = world.resource::<ThumbnailCaptures>();
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        for capture in &captures.0 {
+            let (Some(buffer), Some(gpu_image)) = (&capture.buffer, gpu_images.get(capture.image))
+            else {
+                continue;
+            };
+            render_context.command_encoder().copy_texture_to_buffer(
+                gpu_image.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(capture.padded_bytes_per_row()),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: capture.size.x,
+                    height: capture.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Read back the thumbnails copied this frame, and send them to the main world.
+fn readback_thumbnails(
+    render_device: Res<RenderDevice>,
+    sender: Res<ThumbnailSender>,
+    mut captures: ResMut<ThumbnailCaptures>,
+) {
+    for capture in captures.0.drain(..) {
+        let Some(buffer) = &capture.buffer else {
+            continue;
+        };
+        let slice = buffer.slice(..);
+        let (map_sender, map_receiver) = channel();
+        render_device.map_buffer(&slice, MapMode::Read, move |result| {
+            let _ = map_sender.send(result);
+        });
+        // Thumbnails are rare, so block rather than deferring the readback to a
+        // later frame.
+        render_device.poll(Maintain::Wait);
+        if !matches!(map_receiver.recv(), Ok(Ok(()))) {
+            error!(
+                "Failed to read back effect thumbnail of entity {:?}.",
+                capture.entity
+            );
+            continue;
+        }
+
+        // Remove the row padding
+        let row_size = (capture.size.x * THUMBNAIL_PIXEL_SIZE) as usize;
+        let padded_row_size = capture.padded_bytes_per_row() as usize;
+        let data = slice
+            .get_mapped_range()
+            .chunks(padded_row_size)
+            .flat_map(|row| &row[..row_size])
+            .copied()
+            .collect();
+        buffer.unmap();
+
+        let _ = sender.0.send(ThumbnailData {
+            entity: capture.entity,
+            size: capture.size,
+            data,
+        });
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+/// Global wind affecting all effects opted-in with [`AffectedByWind`].
+///
+/// The wind is a global resource uploaded each frame to the GPU alongside the
+/// other simulation parameters. Any change to this resource therefore affects
+/// all opted-in effects on the next frame, without recompiling their shaders.
+///
+/// Each frame, the update pass of an opted-in effect accelerates its particles
+/// along the wind [`direction`] by the wind [`strength`], plus a random
+/// per-particle acceleration of magnitude up to [`turbulence`] in each
+/// direction. The wind has no effect on effects without
+/// [`Attribute::VELOCITY`].
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// # use bevy::prelude::*;
+/// fn gust(mut wind: ResMut<WindZone>, time: Res<Time>) {
+///     wind.strength = 2. + time.elapsed_seconds().sin();
+/// }
+/// ```
+///
+/// [`direction`]: WindZone::direction
+/// [`strength`]: WindZone::strength
+/// [`turbulence`]: WindZone::turbulence
+/// [`Attribute::VELOCITY`]: crate::Attribute::VELOCITY
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct WindZone {
+    /// Direction the wind blows toward, in world space. This is normalized
+    /// before being uploaded to the GPU.
+    pub direction: Vec3,
+    /// Wind acceleration along [`direction`], in units per second squared.
+    ///
+    /// [`direction`]: WindZone::direction
+    pub strength: f32,
+    /// Magnitude of the random per-particle acceleration, in units per second
+    /// squared.
+    pub turbulence: f32,
+}
+
+impl Default for WindZone {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::X,
+            strength: 0.,
+            turbulence: 0.,
+        }
+    }
+}
+
+impl WindZone {
+    /// Create a new wind zone without turbulence.
+    pub fn new(direction: Vec3, strength: f32) -> Self {
+        Self {
+            direction,
+            strength,
+            turbulence: 0.,
+        }
+    }
+
+    /// Set the turbulence of the wind.
+    pub fn with_turbulence(mut self, turbulence: f32) -> Self {
+        self.turbulence = turbulence;
+        self
+    }
+
+    /// Get the normalized wind direction, or zero if the direction is
+    /// degenerate.
+    pub(crate) fn normalized_direction(&self) -> Vec3 {
+        self.direction.normalize_or_zero()
+    }
+}
+
+/// Marker component opting a [`ParticleEffect`] into the global [`WindZone`].
+///
+/// Insert this component on the same entity as the [`ParticleEffect`]. Adding
+/// or removing it triggers a recompiling of the effect's shaders.
+///
+/// [`ParticleEffect`]: crate::ParticleEffect
+#[derive(Debug, Default, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct AffectedByWind;